@@ -0,0 +1,89 @@
+/*
+ * QMX Backend Lib 的 C 语言接口
+ *
+ * 与 src/ffi.rs 保持一致，需要以 `ffi` feature 构建动态库：
+ *     cargo rustc --release --features ffi --crate-type cdylib
+ *
+ * 复杂数据均以 UTF-8 JSON 字符串传递。返回 int32_t 的函数成功时返回 0，
+ * 失败时返回错误码；返回指针的函数失败时返回 NULL。失败原因见 qmx_last_error()。
+ * 本库返回的字符串用 qmx_string_free() 释放。
+ *
+ * tests/ffi_tests.rs 会逐一核对本文件的函数声明和错误码，修改 src/ffi.rs 或
+ * src/error.rs 后需要同步更新。
+ */
+
+#ifndef QMX_BACKEND_H
+#define QMX_BACKEND_H
+
+#include <stdbool.h>
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+/* 管理器句柄 */
+typedef struct QmxManager QmxManager;
+
+/* 错误码 */
+#define QMX_OK 0
+#define QMX_ERR_IO 1001
+#define QMX_ERR_SERDE_JSON 1002
+#define QMX_ERR_CHRONO 1003
+#define QMX_ERR_POISON 1004
+#define QMX_ERR_ENCRYPTION 1005
+#define QMX_ERR_CORRUPTED 1006
+#define QMX_ERR_NOT_FOUND 2001
+#define QMX_ERR_INVALID_INPUT 2002
+#define QMX_ERR_STATE 2003
+#define QMX_ERR_DUPLICATE_UID 2004
+#define QMX_ERR_MEMBERSHIP_INVALID 2005
+#define QMX_ERR_INSTALLMENT_COMPLETE 2006
+#define QMX_ERR_VALIDATION_FAILED 2007
+#define QMX_ERR_VALIDATION 2008
+#define QMX_ERR_READ_ONLY 2009
+#define QMX_ERR_PERMISSION_DENIED 2010
+#define QMX_ERR_CURRENCY_MISMATCH 2011
+#define QMX_ERR_OTHER 9999
+
+/* 创建管理器，数据保存在当前目录的 ./data 下，失败时返回 NULL */
+QmxManager *qmx_manager_new(bool auto_save);
+
+/* 释放管理器 */
+void qmx_manager_free(QmxManager *manager);
+
+/* 保存全部数据 */
+int32_t qmx_manager_save(const QmxManager *manager);
+
+/* 创建学生，如 {"name": "张三", "age": 16, "class": "TenTry"}，UID 写入 out_uid */
+int32_t qmx_student_create(const QmxManager *manager, const char *json, uint64_t *out_uid);
+
+/* 获取学生的 JSON，学生不存在时为 "null" */
+char *qmx_student_get(const QmxManager *manager, uint64_t uid);
+
+/* 删除学生，policy 为关联现金记录的处理方式 */
+#define QMX_DELETE_RESTRICT 0
+#define QMX_DELETE_CASCADE 1
+#define QMX_DELETE_DETACH 2
+int32_t qmx_student_delete(const QmxManager *manager, uint64_t uid, int32_t policy);
+
+/* 搜索学生，如 {"name": "张", "limit": 20}，query 为 NULL 时返回全部学生 */
+char *qmx_students_search(const QmxManager *manager, const char *query);
+
+/* 记录现金，如 {"amount": 1500, "student_id": 1}，金额以分为单位，UID 写入 out_uid */
+int32_t qmx_cash_record(const QmxManager *manager, const char *json, uint64_t *out_uid);
+
+/* 获取仪表板统计的 JSON */
+char *qmx_dashboard_stats(const QmxManager *manager);
+
+/* 当前线程最近一次失败的错误信息，不需要释放 */
+const char *qmx_last_error(void);
+
+/* 释放本库返回的字符串 */
+void qmx_string_free(char *text);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif /* QMX_BACKEND_H */
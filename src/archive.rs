@@ -0,0 +1,179 @@
+//! 不压缩的 ZIP 打包与读取
+//!
+//! xlsx 工作簿和数据包（[`crate::bundle`]）都是 ZIP 文件。各文件以不压缩（stored）方式打包，
+//! 不需要额外的依赖，生成的文件可以用常见的解压软件打开。读取时只支持不压缩的条目。
+
+use crate::error::{Error, Result};
+use std::io::Write;
+
+const LOCAL_HEADER: u32 = 0x0403_4b50;
+const CENTRAL_HEADER: u32 = 0x0201_4b50;
+const END_OF_DIRECTORY: u32 = 0x0605_4b50;
+
+/// ZIP 中的文件时间固定为 1980-01-01 00:00（DOS 日期格式）
+const DOS_DATE: u16 = (1 << 5) | 1;
+
+struct ZipEntry {
+    name: String,
+    crc: u32,
+    size: u32,
+    offset: u32,
+}
+
+pub(crate) struct ZipWriter<W: Write> {
+    writer: W,
+    offset: u32,
+    entries: Vec<ZipEntry>,
+}
+
+impl<W: Write> ZipWriter<W> {
+    pub fn new(writer: W) -> Self {
+        Self {
+            writer,
+            offset: 0,
+            entries: Vec::new(),
+        }
+    }
+
+    pub fn add(&mut self, name: &str, data: &[u8]) -> Result<()> {
+        let size = zip_u32(data.len())?;
+        let crc = crate::compression::crc32(data);
+        let mut header = Vec::with_capacity(30 + name.len());
+        header.extend_from_slice(&LOCAL_HEADER.to_le_bytes());
+        header.extend_from_slice(&20u16.to_le_bytes()); // 解压所需版本
+        header.extend_from_slice(&0u16.to_le_bytes()); // 标志位
+        header.extend_from_slice(&0u16.to_le_bytes()); // 不压缩
+        header.extend_from_slice(&0u16.to_le_bytes()); // 修改时间
+        header.extend_from_slice(&DOS_DATE.to_le_bytes());
+        header.extend_from_slice(&crc.to_le_bytes());
+        header.extend_from_slice(&size.to_le_bytes());
+        header.extend_from_slice(&size.to_le_bytes());
+        header.extend_from_slice(&zip_u16(name.len())?.to_le_bytes());
+        header.extend_from_slice(&0u16.to_le_bytes()); // 扩展字段长度
+        header.extend_from_slice(name.as_bytes());
+
+        self.writer.write_all(&header)?;
+        self.writer.write_all(data)?;
+        self.entries.push(ZipEntry {
+            name: name.to_string(),
+            crc,
+            size,
+            offset: self.offset,
+        });
+        self.offset = self
+            .offset
+            .checked_add(zip_u32(header.len())?)
+            .and_then(|offset| offset.checked_add(size))
+            .ok_or_else(too_large)?;
+        Ok(())
+    }
+
+    /// 写入中央目录，完成打包
+    pub fn finish(mut self) -> Result<()> {
+        let mut directory = Vec::new();
+        for entry in &self.entries {
+            directory.extend_from_slice(&CENTRAL_HEADER.to_le_bytes());
+            directory.extend_from_slice(&20u16.to_le_bytes()); // 创建版本
+            directory.extend_from_slice(&20u16.to_le_bytes()); // 解压所需版本
+            directory.extend_from_slice(&0u16.to_le_bytes()); // 标志位
+            directory.extend_from_slice(&0u16.to_le_bytes()); // 不压缩
+            directory.extend_from_slice(&0u16.to_le_bytes()); // 修改时间
+            directory.extend_from_slice(&DOS_DATE.to_le_bytes());
+            directory.extend_from_slice(&entry.crc.to_le_bytes());
+            directory.extend_from_slice(&entry.size.to_le_bytes());
+            directory.extend_from_slice(&entry.size.to_le_bytes());
+            directory.extend_from_slice(&zip_u16(entry.name.len())?.to_le_bytes());
+            directory.extend_from_slice(&[0; 12]); // 扩展字段、注释、磁盘号、文件属性
+            directory.extend_from_slice(&entry.offset.to_le_bytes());
+            directory.extend_from_slice(entry.name.as_bytes());
+        }
+
+        let count = zip_u16(self.entries.len())?;
+        let mut end = Vec::with_capacity(22);
+        end.extend_from_slice(&END_OF_DIRECTORY.to_le_bytes());
+        end.extend_from_slice(&[0; 4]); // 磁盘号
+        end.extend_from_slice(&count.to_le_bytes());
+        end.extend_from_slice(&count.to_le_bytes());
+        end.extend_from_slice(&zip_u32(directory.len())?.to_le_bytes());
+        end.extend_from_slice(&self.offset.to_le_bytes());
+        end.extend_from_slice(&0u16.to_le_bytes()); // 注释长度
+
+        self.writer.write_all(&directory)?;
+        self.writer.write_all(&end)?;
+        self.writer.flush()?;
+        Ok(())
+    }
+}
+
+/// 读取不压缩的 ZIP 文件，按中央目录的顺序返回 `(文件名, 内容)`
+pub(crate) fn read_zip(bytes: &[u8]) -> Result<Vec<(String, Vec<u8>)>> {
+    let end = (0..=bytes.len().saturating_sub(22))
+        .rev()
+        .find(|&i| bytes[i..].starts_with(&END_OF_DIRECTORY.to_le_bytes()))
+        .ok_or_else(|| corrupt("找不到中央目录"))?;
+    let count = u16_at(bytes, end + 10)?;
+    let mut pos = u32_at(bytes, end + 16)? as usize;
+    let mut files = Vec::with_capacity(count as usize);
+    for _ in 0..count {
+        if u32_at(bytes, pos)? != CENTRAL_HEADER {
+            return Err(corrupt("中央目录条目无效"));
+        }
+        let method = u16_at(bytes, pos + 10)?;
+        let crc = u32_at(bytes, pos + 16)?;
+        let size = u32_at(bytes, pos + 20)? as usize;
+        let name_len = u16_at(bytes, pos + 28)? as usize;
+        let skip = u16_at(bytes, pos + 30)? as usize + u16_at(bytes, pos + 32)? as usize;
+        let offset = u32_at(bytes, pos + 42)? as usize;
+        let name = String::from_utf8(slice(bytes, pos + 46, name_len)?.to_vec())
+            .map_err(|_| corrupt("文件名不是有效的 UTF-8"))?;
+        if method != 0 {
+            return Err(Error::InvalidInput(format!("不支持压缩的 ZIP 条目: {}", name)));
+        }
+
+        if u32_at(bytes, offset)? != LOCAL_HEADER {
+            return Err(corrupt("文件头无效"));
+        }
+        let data_start =
+            offset + 30 + u16_at(bytes, offset + 26)? as usize + u16_at(bytes, offset + 28)? as usize;
+        let data = slice(bytes, data_start, size)?;
+        if crate::compression::crc32(data) != crc {
+            return Err(corrupt(&format!("{} 校验和不匹配", name)));
+        }
+        files.push((name, data.to_vec()));
+        pos += 46 + name_len + skip;
+    }
+    Ok(files)
+}
+
+fn corrupt(reason: &str) -> Error {
+    Error::InvalidInput(format!("ZIP 文件已损坏: {}", reason))
+}
+
+fn slice(bytes: &[u8], start: usize, len: usize) -> Result<&[u8]> {
+    start
+        .checked_add(len)
+        .and_then(|end| bytes.get(start..end))
+        .ok_or_else(|| corrupt("文件被截断"))
+}
+
+fn u16_at(bytes: &[u8], pos: usize) -> Result<u16> {
+    let raw = slice(bytes, pos, 2)?;
+    Ok(u16::from_le_bytes([raw[0], raw[1]]))
+}
+
+fn u32_at(bytes: &[u8], pos: usize) -> Result<u32> {
+    let raw = slice(bytes, pos, 4)?;
+    Ok(u32::from_le_bytes([raw[0], raw[1], raw[2], raw[3]]))
+}
+
+fn too_large() -> Error {
+    Error::Other("文件超过 ZIP 格式 4GB 的大小上限".to_string())
+}
+
+fn zip_u32(len: usize) -> Result<u32> {
+    u32::try_from(len).map_err(|_| too_large())
+}
+
+fn zip_u16(len: usize) -> Result<u16> {
+    u16::try_from(len).map_err(|_| too_large())
+}
@@ -0,0 +1,122 @@
+//! 异步管理器接口
+//!
+//! 需要启用 `tokio` 特性。[`AsyncQmxManager`] 把 [`QmxManager`] 的操作交给 tokio 的
+//! 阻塞线程池（[`tokio::task::spawn_blocking`]）执行，调用方只需 `.await`，
+//! 保存大数据库等耗时操作不会阻塞异步运行时的工作线程。线程池的大小由运行时的
+//! `max_blocking_threads` 限制。
+
+use std::sync::Arc;
+
+use crate::cash::Cash;
+use crate::error::{Error, Result};
+use crate::manager::{
+    CashBuilder, CashQuery, CashUpdater, DeletePolicy, FieldChange, QmxManager, StudentBuilder, StudentQuery,
+    StudentUpdater,
+};
+use crate::student::Student;
+use log::error;
+
+/// 异步版本的 [`QmxManager`]
+///
+/// 每次调用在 tokio 阻塞线程池中执行对应的同步方法，必须在 tokio 运行时中调用。
+/// 通过 [`AsyncQmxManager::manager`] 仍可直接同步调用。
+///
+/// # 示例
+/// ```rust,no_run
+/// use qmx_backend_lib::{AsyncQmxManager, QmxManager, StudentBuilder};
+///
+/// # async fn example() -> qmx_backend_lib::error::Result<()> {
+/// let manager = AsyncQmxManager::new(QmxManager::builder().auto_save(false).build()?);
+/// let uid = manager.create_student(StudentBuilder::new("张三")).await?;
+/// manager.save().await?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone)]
+pub struct AsyncQmxManager {
+    inner: Arc<QmxManager>,
+}
+
+impl AsyncQmxManager {
+    pub fn new(manager: QmxManager) -> Self {
+        Self {
+            inner: Arc::new(manager),
+        }
+    }
+
+    /// 获取内部的同步管理器
+    pub fn manager(&self) -> &Arc<QmxManager> {
+        &self.inner
+    }
+
+    /// 在阻塞线程池中执行任意同步操作
+    ///
+    /// 操作 panic 时返回错误，而不是让等待方一起 panic。
+    pub async fn run<T, F>(&self, f: F) -> Result<T>
+    where
+        T: Send + 'static,
+        F: FnOnce(&QmxManager) -> Result<T> + Send + 'static,
+    {
+        let manager = self.inner.clone();
+        tokio::task::spawn_blocking(move || f(&manager))
+            .await
+            .unwrap_or_else(|e| {
+                error!("后台操作失败: {}", e);
+                Err(Error::Other(format!("后台操作失败: {}", e)))
+            })
+    }
+
+    /// 手动保存所有数据
+    pub async fn save(&self) -> Result<()> {
+        self.run(|m| m.save()).await
+    }
+
+    /// 从最近一次保存的状态重新加载，详见 [`QmxManager::recover`]
+    pub async fn recover(&self) -> Result<()> {
+        self.run(|m| m.recover()).await
+    }
+
+    pub async fn create_student(&self, builder: StudentBuilder) -> Result<u64> {
+        self.run(move |m| m.create_student(builder)).await
+    }
+
+    pub async fn get_student(&self, uid: u64) -> Result<Option<Student>> {
+        self.run(move |m| m.get_student(uid)).await
+    }
+
+    pub async fn update_student(
+        &self,
+        uid: u64,
+        updater: StudentUpdater,
+    ) -> Result<Vec<FieldChange>> {
+        self.run(move |m| m.update_student(uid, updater)).await
+    }
+
+    pub async fn delete_student(&self, uid: u64, policy: DeletePolicy) -> Result<bool> {
+        self.run(move |m| m.delete_student(uid, policy)).await
+    }
+
+    pub async fn search_students(&self, query: StudentQuery) -> Result<Vec<Student>> {
+        self.run(move |m| m.search_students(query)).await
+    }
+
+    pub async fn record_cash(&self, builder: CashBuilder) -> Result<u64> {
+        self.run(move |m| m.record_cash(builder)).await
+    }
+
+    pub async fn get_cash(&self, uid: u64) -> Result<Option<Cash>> {
+        self.run(move |m| m.get_cash(uid)).await
+    }
+
+    pub async fn update_cash(&self, uid: u64, updater: CashUpdater) -> Result<Vec<FieldChange>> {
+        self.run(move |m| m.update_cash(uid, updater)).await
+    }
+
+    pub async fn delete_cash(&self, uid: u64) -> Result<bool> {
+        self.run(move |m| m.delete_cash(uid)).await
+    }
+
+    pub async fn search_cash(&self, query: CashQuery) -> Result<Vec<Cash>> {
+        self.run(move |m| m.search_cash(query)).await
+    }
+}
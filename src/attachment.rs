@@ -0,0 +1,157 @@
+//! 学生附件
+//!
+//! 照片、签字的免责声明、病历等文件按学生分目录保存在 `./data/attachments/<student_uid>/` 下，
+//! 文件的元数据保存在同目录的索引文件中，由 [`AttachmentDatabase`] 管理。
+//! 附件通过 [`crate::QmxManager::attach_file`] 等方法添加和删除。
+
+use crate::common::{Database, HasUid};
+use crate::error::{Error, Result};
+use chrono::{DateTime, Utc};
+use log::debug;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+/// 附件的默认保存目录
+pub const ATTACHMENTS_DIR: &str = "./data/attachments";
+
+/// 附件目录中索引文件的文件名
+pub const ATTACHMENT_INDEX_FILE: &str = "index.json";
+
+/// 一个附件的元数据
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Attachment {
+    /// 附件序号，按添加顺序递增
+    pub uid: u64,
+    pub student_uid: u64,
+    /// 原始文件名
+    pub file_name: String,
+    /// 附件目录中实际保存的文件名，以附件序号开头避免同名文件互相覆盖
+    pub stored_name: String,
+    /// 文件大小（字节）
+    pub size: u64,
+    pub created_at: DateTime<Utc>,
+}
+
+impl HasUid for Attachment {
+    fn uid(&self) -> u64 {
+        self.uid
+    }
+}
+
+impl Attachment {
+    /// 附件文件在 `dir` 下的完整路径
+    pub fn path_in(&self, dir: impl AsRef<Path>) -> PathBuf {
+        dir.as_ref()
+            .join(self.student_uid.to_string())
+            .join(&self.stored_name)
+    }
+}
+
+/// 附件索引数据库
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct AttachmentDatabase {
+    pub attachment_data: BTreeMap<u64, Attachment>,
+}
+
+impl Default for AttachmentDatabase {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Database<Attachment> for AttachmentDatabase {
+    fn data(&self) -> &BTreeMap<u64, Attachment> {
+        &self.attachment_data
+    }
+
+    fn data_mut(&mut self) -> &mut BTreeMap<u64, Attachment> {
+        &mut self.attachment_data
+    }
+
+    fn default_path(&self) -> &'static str {
+        "./data/attachments/index.json"
+    }
+
+    fn type_name(&self) -> &'static str {
+        "附件"
+    }
+
+    fn static_type_name() -> &'static str {
+        "附件"
+    }
+
+    fn data_field() -> &'static str {
+        "attachment_data"
+    }
+
+    fn new() -> Self {
+        Self {
+            attachment_data: BTreeMap::new(),
+        }
+    }
+}
+
+impl AttachmentDatabase {
+    pub fn new() -> Self {
+        <Self as Database<Attachment>>::new()
+    }
+
+    pub fn get(&self, uid: &u64) -> Option<&Attachment> {
+        <Self as Database<Attachment>>::get(self, uid)
+    }
+
+    pub fn insert(&mut self, attachment: Attachment) {
+        <Self as Database<Attachment>>::insert(self, attachment)
+    }
+
+    pub fn remove(&mut self, uid: &u64) -> Option<Attachment> {
+        <Self as Database<Attachment>>::remove(self, uid)
+    }
+
+    pub fn save_to(&self, path: &str) -> Result<()> {
+        <Self as Database<Attachment>>::save_to(self, path)
+    }
+
+    pub fn read_from(path: &str) -> Result<Self> {
+        <Self as Database<Attachment>>::read_from(path)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&u64, &Attachment)> + '_ {
+        <Self as Database<Attachment>>::iter(self)
+    }
+
+    pub fn len(&self) -> usize {
+        <Self as Database<Attachment>>::len(self)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        <Self as Database<Attachment>>::is_empty(self)
+    }
+
+    /// 从指定路径加载附件索引，文件不存在时返回空索引
+    pub fn load_or_new(path: &str) -> Result<Self> {
+        match Self::read_from(path) {
+            Err(Error::Io(e)) if e.kind() == std::io::ErrorKind::NotFound => {
+                debug!("未找到附件索引文件 {}，使用空索引", path);
+                Ok(Self::new())
+            }
+            other => other,
+        }
+    }
+
+    /// 下一个附件序号
+    pub fn next_uid(&self) -> u64 {
+        self.attachment_data
+            .last_key_value()
+            .map_or(1, |(&last, _)| last + 1)
+    }
+
+    /// 获取某个学生的全部附件，按添加顺序排列
+    pub fn for_student(&self, student_uid: u64) -> Vec<&Attachment> {
+        self.attachment_data
+            .values()
+            .filter(|attachment| attachment.student_uid == student_uid)
+            .collect()
+    }
+}
@@ -0,0 +1,219 @@
+//! 操作审计日志
+//!
+//! 通过 [`crate::QmxManager`] 执行的每一次修改操作都会记录一条审计记录：
+//! 操作者、操作类型、时间以及各字段的新旧值。审计记录保存在独立的 [`AuditDatabase`] 中，
+//! 与学生、现金数据分开持久化。
+
+use crate::common::{Database, HasUid};
+use crate::error::{Error, Result};
+use crate::manager::FieldChange;
+use chrono::{DateTime, Utc};
+use log::debug;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// 审计日志的默认保存路径
+pub const AUDIT_LOG_PATH: &str = "./data/audit_log.json";
+
+/// 审计记录中被清除的字段值
+pub const REDACTED: &str = "[已清除]";
+
+/// 审计记录对应的实体类型
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum AuditEntity {
+    Student,
+    Cash,
+}
+
+/// 审计记录的操作类型
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum AuditAction {
+    Create,
+    Update,
+    Delete,
+}
+
+/// 一条审计记录
+///
+/// 创建操作的 `changes` 中旧值均为 `null`，删除操作的新值均为 `null`。
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct AuditEntry {
+    /// 审计记录序号，按记录顺序递增
+    pub uid: u64,
+    pub entity: AuditEntity,
+    /// 被修改的学生或现金记录 UID
+    pub entity_uid: u64,
+    pub action: AuditAction,
+    /// 操作者
+    pub actor: String,
+    pub timestamp: DateTime<Utc>,
+    pub changes: Vec<FieldChange>,
+}
+
+/// 把 `fields` 字段的新旧值替换为 [`REDACTED`]，有值被替换时返回 `true`
+pub fn redact_changes(changes: &mut [FieldChange], fields: &[&str]) -> bool {
+    let redacted = serde_json::Value::String(REDACTED.to_string());
+    let mut changed = false;
+    for change in changes
+        .iter_mut()
+        .filter(|change| fields.contains(&change.field.as_str()))
+    {
+        for value in [&mut change.old, &mut change.new] {
+            if !value.is_null() && *value != redacted {
+                *value = redacted.clone();
+                changed = true;
+            }
+        }
+    }
+    changed
+}
+
+impl HasUid for AuditEntry {
+    fn uid(&self) -> u64 {
+        self.uid
+    }
+}
+
+/// 审计日志数据库
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct AuditDatabase {
+    pub audit_data: BTreeMap<u64, AuditEntry>,
+}
+
+impl Default for AuditDatabase {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Database<AuditEntry> for AuditDatabase {
+    fn data(&self) -> &BTreeMap<u64, AuditEntry> {
+        &self.audit_data
+    }
+
+    fn data_mut(&mut self) -> &mut BTreeMap<u64, AuditEntry> {
+        &mut self.audit_data
+    }
+
+    fn default_path(&self) -> &'static str {
+        AUDIT_LOG_PATH
+    }
+
+    fn type_name(&self) -> &'static str {
+        "审计"
+    }
+
+    fn static_type_name() -> &'static str {
+        "审计"
+    }
+
+    fn data_field() -> &'static str {
+        "audit_data"
+    }
+
+    fn new() -> Self {
+        Self {
+            audit_data: BTreeMap::new(),
+        }
+    }
+}
+
+impl AuditDatabase {
+    pub fn new() -> Self {
+        <Self as Database<AuditEntry>>::new()
+    }
+
+    pub fn get(&self, uid: &u64) -> Option<&AuditEntry> {
+        <Self as Database<AuditEntry>>::get(self, uid)
+    }
+
+    pub fn save_to(&self, path: &str) -> Result<()> {
+        <Self as Database<AuditEntry>>::save_to(self, path)
+    }
+
+    pub fn read_from(path: &str) -> Result<Self> {
+        <Self as Database<AuditEntry>>::read_from(path)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&u64, &AuditEntry)> + '_ {
+        <Self as Database<AuditEntry>>::iter(self)
+    }
+
+    pub fn len(&self) -> usize {
+        <Self as Database<AuditEntry>>::len(self)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        <Self as Database<AuditEntry>>::is_empty(self)
+    }
+
+    /// 从指定路径加载审计日志，文件不存在时返回空日志
+    pub fn load_or_new(path: &str) -> Result<Self> {
+        match Self::read_from(path) {
+            Err(Error::Io(e)) if e.kind() == std::io::ErrorKind::NotFound => {
+                debug!("未找到审计日志文件 {}，使用空日志", path);
+                Ok(Self::new())
+            }
+            other => other,
+        }
+    }
+
+    /// 追加一条审计记录，返回记录序号
+    pub fn record(
+        &mut self,
+        entity: AuditEntity,
+        entity_uid: u64,
+        action: AuditAction,
+        actor: &str,
+        timestamp: DateTime<Utc>,
+        changes: Vec<FieldChange>,
+    ) -> u64 {
+        let uid = self
+            .audit_data
+            .last_key_value()
+            .map_or(1, |(&last, _)| last + 1);
+        debug!(
+            "记录审计: #{} {:?} {:?} {}，操作者: {}",
+            uid, action, entity, entity_uid, actor
+        );
+        self.audit_data.insert(
+            uid,
+            AuditEntry {
+                uid,
+                entity,
+                entity_uid,
+                action,
+                actor: actor.to_string(),
+                timestamp,
+                changes,
+            },
+        );
+        uid
+    }
+
+    /// 把满足 `matches` 的审计记录中 `fields` 字段的新旧值替换为 [`REDACTED`]，返回修改的记录数
+    ///
+    /// 值为 `null` 的不替换，仍能看出字段是在哪次操作中被设置或清除的。
+    pub fn redact(
+        &mut self,
+        matches: impl Fn(&AuditEntry) -> bool,
+        fields: &[&str],
+    ) -> usize {
+        let mut count = 0;
+        for entry in self.audit_data.values_mut().filter(|entry| matches(entry)) {
+            if redact_changes(&mut entry.changes, fields) {
+                count += 1;
+            }
+        }
+        debug!("清除 {} 条审计记录中的个人信息", count);
+        count
+    }
+
+    /// 获取某个学生或现金记录的全部审计记录，按记录顺序排列
+    pub fn entries_for(&self, entity_uid: u64) -> Vec<&AuditEntry> {
+        self.audit_data
+            .values()
+            .filter(|entry| entry.entity_uid == entity_uid)
+            .collect()
+    }
+}
@@ -0,0 +1,118 @@
+//! 防抖的后台自动保存
+//!
+//! [`crate::AutoSave::Debounced`] 模式下，修改操作只通知 [`SaveScheduler`]，
+//! 由后台线程在一段时间内没有新的修改、或累计的修改次数达到上限时统一保存一次，
+//! 批量操作不再触发成百上千次整文件写入。调度器被丢弃时会先保存尚未保存的修改。
+
+use log::debug;
+use std::sync::{Arc, Condvar, Mutex, MutexGuard};
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+
+/// 后台保存调度器
+pub(crate) struct SaveScheduler {
+    shared: Arc<Shared>,
+    handle: Option<JoinHandle<()>>,
+}
+
+struct Shared {
+    state: Mutex<State>,
+    wake: Condvar,
+}
+
+#[derive(Default)]
+struct State {
+    /// 自上次保存以来的修改次数
+    pending: usize,
+    last_change: Option<Instant>,
+    shutdown: bool,
+}
+
+impl Shared {
+    fn lock(&self) -> MutexGuard<'_, State> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+impl SaveScheduler {
+    /// 启动后台线程，`flush` 负责实际保存并自行处理错误；无法创建线程时返回错误
+    pub(crate) fn spawn(
+        quiet_period: Duration,
+        max_pending: usize,
+        flush: impl Fn() + Send + 'static,
+    ) -> std::io::Result<Self> {
+        let shared = Arc::new(Shared {
+            state: Mutex::new(State::default()),
+            wake: Condvar::new(),
+        });
+        let worker = Arc::clone(&shared);
+        let handle = std::thread::Builder::new()
+            .name("qmx-autosave".to_string())
+            .spawn(move || run(&worker, quiet_period, max_pending.max(1), flush))?;
+        debug!(
+            "启动后台自动保存线程，静默期 {:?}，最多累计 {} 次修改",
+            quiet_period, max_pending
+        );
+        Ok(Self {
+            shared,
+            handle: Some(handle),
+        })
+    }
+
+    /// 记录一次修改
+    pub(crate) fn notify(&self) {
+        let mut state = self.shared.lock();
+        state.pending += 1;
+        state.last_change = Some(Instant::now());
+        self.shared.wake.notify_one();
+    }
+
+    /// 尚未保存的修改次数
+    pub(crate) fn pending(&self) -> usize {
+        self.shared.lock().pending
+    }
+
+    /// 手动保存后清空计数
+    pub(crate) fn reset(&self) {
+        self.shared.lock().pending = 0;
+    }
+}
+
+impl Drop for SaveScheduler {
+    fn drop(&mut self) {
+        self.shared.lock().shutdown = true;
+        self.shared.wake.notify_one();
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+        }
+    }
+}
+
+fn run(shared: &Shared, quiet_period: Duration, max_pending: usize, flush: impl Fn()) {
+    let mut state = shared.lock();
+    loop {
+        if state.pending == 0 {
+            if state.shutdown {
+                return;
+            }
+            state = shared.wake.wait(state).unwrap_or_else(|e| e.into_inner());
+            continue;
+        }
+        let elapsed = state
+            .last_change
+            .map_or(quiet_period, |last| last.elapsed());
+        if state.shutdown || state.pending >= max_pending || elapsed >= quiet_period {
+            debug!("后台保存 {} 次修改", state.pending);
+            state.pending = 0;
+            drop(state);
+            flush();
+            state = shared.lock();
+            continue;
+        }
+        state = shared
+            .wake
+            .wait_timeout(state, quiet_period - elapsed)
+            .unwrap_or_else(|e| e.into_inner())
+            .0;
+    }
+}
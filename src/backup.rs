@@ -0,0 +1,195 @@
+//! 数据备份
+//!
+//! 原子保存只能防止文件写坏，防不了误删、错误的批量修改等逻辑错误。
+//! [`crate::QmxManager::backup_now`] 把当前数据写入以时间命名的备份目录，
+//! 并按 [`RetentionPolicy`] 清理旧备份；[`crate::QmxManager::restore_from_backup`]
+//! 从备份目录恢复数据。
+
+use crate::error::{Error, Result};
+use chrono::{DateTime, Datelike, NaiveDateTime, Utc};
+use log::{info, warn};
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+
+/// 备份的默认保存目录
+pub const BACKUP_DIR: &str = "./data/backups";
+
+/// 备份目录名前缀，完整名称形如 `backup-20240101-083000`
+const BACKUP_PREFIX: &str = "backup-";
+
+const TIMESTAMP_FORMAT: &str = "%Y%m%d-%H%M%S";
+
+/// 备份目录中的学生数据文件
+pub const BACKUP_STUDENT_FILE: &str = "student_database.json";
+/// 备份目录中的现金数据文件
+pub const BACKUP_CASH_FILE: &str = "cash_database.json";
+/// 备份目录中的审计日志文件
+pub const BACKUP_AUDIT_FILE: &str = "audit_log.json";
+
+/// 旧备份的保留策略
+///
+/// 三条规则取并集：保留最近 `keep_last` 份备份，最近 `keep_daily` 天中每天最新的一份，
+/// 以及最近 `keep_weekly` 周中每周最新的一份。最新的备份总是保留。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RetentionPolicy {
+    pub keep_last: usize,
+    pub keep_daily: usize,
+    pub keep_weekly: usize,
+}
+
+impl Default for RetentionPolicy {
+    fn default() -> Self {
+        Self {
+            keep_last: 10,
+            keep_daily: 7,
+            keep_weekly: 4,
+        }
+    }
+}
+
+impl RetentionPolicy {
+    /// 只保留最近 `n` 份备份
+    pub fn keep_last(n: usize) -> Self {
+        Self {
+            keep_last: n,
+            keep_daily: 0,
+            keep_weekly: 0,
+        }
+    }
+
+    /// 额外保留最近 `days` 天中每天最新的一份备份
+    pub fn daily(mut self, days: usize) -> Self {
+        self.keep_daily = days;
+        self
+    }
+
+    /// 额外保留最近 `weeks` 周中每周最新的一份备份
+    pub fn weekly(mut self, weeks: usize) -> Self {
+        self.keep_weekly = weeks;
+        self
+    }
+
+    /// 从按时间从新到旧排列的备份中选出应删除的备份
+    fn expired<'a>(&self, backups: &'a [BackupInfo]) -> Vec<&'a BackupInfo> {
+        let mut days = HashSet::new();
+        let mut weeks = HashSet::new();
+        let mut expired = Vec::new();
+        for (i, backup) in backups.iter().enumerate() {
+            let mut keep = i == 0 || i < self.keep_last;
+            let day = backup.created_at.date_naive();
+            if days.len() < self.keep_daily && days.insert(day) {
+                keep = true;
+            }
+            let week = day.iso_week();
+            if weeks.len() < self.keep_weekly && weeks.insert((week.year(), week.week())) {
+                keep = true;
+            }
+            if !keep {
+                expired.push(backup);
+            }
+        }
+        expired
+    }
+}
+
+/// 一份备份
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BackupInfo {
+    pub path: PathBuf,
+    pub created_at: DateTime<Utc>,
+}
+
+/// 列出备份目录中的全部备份，按时间从新到旧排列
+///
+/// 备份目录不存在时返回空列表，名称不符合备份格式的目录会被忽略。
+pub fn list_backups(dir: impl AsRef<Path>) -> Result<Vec<BackupInfo>> {
+    let entries = match std::fs::read_dir(dir.as_ref()) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e.into()),
+    };
+    let mut backups = Vec::new();
+    for entry in entries {
+        let entry = entry?;
+        if !entry.file_type()?.is_dir() {
+            continue;
+        }
+        let name = entry.file_name().to_string_lossy().into_owned();
+        if let Some(created_at) = parse_backup_name(&name) {
+            backups.push(BackupInfo {
+                path: entry.path(),
+                created_at,
+            });
+        }
+    }
+    // 同一秒内的备份按追加的序号排列，序号越大越新
+    backups.sort_by(|a, b| {
+        b.created_at
+            .cmp(&a.created_at)
+            .then_with(|| b.path.as_os_str().len().cmp(&a.path.as_os_str().len()))
+            .then_with(|| b.path.cmp(&a.path))
+    });
+    Ok(backups)
+}
+
+/// 按保留策略删除旧备份，返回被删除的备份目录
+pub fn prune_backups(dir: impl AsRef<Path>, policy: &RetentionPolicy) -> Result<Vec<PathBuf>> {
+    let backups = list_backups(dir)?;
+    let mut removed = Vec::new();
+    for backup in policy.expired(&backups) {
+        std::fs::remove_dir_all(&backup.path)?;
+        removed.push(backup.path.clone());
+    }
+    if !removed.is_empty() {
+        info!("按保留策略删除 {} 份旧备份", removed.len());
+    }
+    Ok(removed)
+}
+
+/// 在备份目录中创建一个以 `now` 命名的新目录
+///
+/// 同一秒内多次备份时在名称后追加序号，避免覆盖已有备份。
+pub(crate) fn create_backup_dir(dir: impl AsRef<Path>, now: DateTime<Utc>) -> Result<PathBuf> {
+    let dir = dir.as_ref();
+    std::fs::create_dir_all(dir)?;
+    let base = format!("{}{}", BACKUP_PREFIX, now.format(TIMESTAMP_FORMAT));
+    for n in 1..=u32::MAX {
+        let name = if n == 1 {
+            base.clone()
+        } else {
+            format!("{}-{}", base, n)
+        };
+        let path = dir.join(name);
+        match std::fs::create_dir(&path) {
+            Ok(()) => return Ok(path),
+            Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => continue,
+            Err(e) => return Err(e.into()),
+        }
+    }
+    Err(Error::Other(format!(
+        "无法在 {} 中创建备份目录",
+        dir.display()
+    )))
+}
+
+/// 检查备份目录是否包含完整的数据文件
+pub(crate) fn check_backup(path: &Path) -> Result<()> {
+    for file in [BACKUP_STUDENT_FILE, BACKUP_CASH_FILE] {
+        if !path.join(file).is_file() {
+            warn!("备份 {} 缺少 {}", path.display(), file);
+            return Err(Error::NotFound(format!(
+                "备份 {} 缺少 {}",
+                path.display(),
+                file
+            )));
+        }
+    }
+    Ok(())
+}
+
+fn parse_backup_name(name: &str) -> Option<DateTime<Utc>> {
+    let timestamp = name.strip_prefix(BACKUP_PREFIX)?.get(..15)?;
+    NaiveDateTime::parse_from_str(timestamp, TIMESTAMP_FORMAT)
+        .ok()
+        .map(|naive| naive.and_utc())
+}
@@ -0,0 +1,248 @@
+//! 完整数据包的导出与导入
+//!
+//! 把整个安装实例迁移到另一台电脑时，[`crate::QmxManager::export_bundle`] 把学生、现金、
+//! 教练、课程数据库，UID 计数器，附件索引和附件文件打包为一个 ZIP 文件；
+//! [`crate::QmxManager::import_bundle`] 在目标电脑上按 [`MergeStrategy`] 导入。
+//!
+//! 数据包中的数据库为未加密、未压缩的 JSON，`manifest.json` 记录数据包格式版本，
+//! 导入时拒绝比当前版本新的数据包。ZIP 中每个文件都有 CRC-32，导入前全部校验，
+//! 文件损坏时不会修改任何数据。审计日志不在数据包中。
+
+use crate::archive::{ZipWriter, read_zip};
+use crate::attachment::AttachmentDatabase;
+use crate::cash::{CASH_UID_COUNTER, CashDatabase, PLAN_ID_COUNTER};
+use crate::coach::CoachDatabase;
+use crate::common::{Database, HasUid};
+use crate::error::{Error, Result};
+use crate::schedule::SessionDatabase;
+use crate::student::{STUDENT_UID_COUNTER, StudentDatabase};
+use chrono::{DateTime, Utc};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::io::BufWriter;
+use std::path::Path;
+use std::sync::atomic::Ordering;
+
+/// 当前的数据包格式版本
+pub const BUNDLE_FORMAT_VERSION: u32 = 1;
+
+const MANIFEST_FILE: &str = "manifest.json";
+const STUDENT_FILE: &str = "student_database.json";
+const CASH_FILE: &str = "cash_database.json";
+const COACH_FILE: &str = "coach_database.json";
+const SESSION_FILE: &str = "session_database.json";
+const ATTACHMENT_INDEX_FILE: &str = "attachments/index.json";
+/// 附件文件在数据包中的目录，其下为 `<学生UID>/<保存的文件名>`
+const ATTACHMENT_FILES_DIR: &str = "attachments/files/";
+
+/// 数据包说明
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct BundleManifest {
+    pub format_version: u32,
+    /// 导出数据包的库版本
+    pub library_version: String,
+    pub created_at: DateTime<Utc>,
+    /// 导出时各计数器的下一个值，导入后计数器不小于这些值
+    pub next_student_uid: u64,
+    pub next_cash_uid: u64,
+    pub next_plan_id: u64,
+    pub students: usize,
+    pub cash: usize,
+    pub coaches: usize,
+    pub sessions: usize,
+    pub attachments: usize,
+}
+
+/// 导入时如何处理已有数据
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MergeStrategy {
+    /// 清空已有数据，完全替换为数据包中的数据（附件文件保留在磁盘上）
+    Replace,
+    /// 只导入 UID 不存在的记录，UID 相同时保留已有记录
+    KeepExisting,
+    /// 导入全部记录，UID 相同时以数据包中的记录为准
+    Overwrite,
+}
+
+/// 一类记录的导入结果
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct MergeCount {
+    /// 新增的记录数
+    pub added: usize,
+    /// 覆盖已有记录的记录数
+    pub overwritten: usize,
+    /// 因 UID 已存在而跳过的记录数
+    pub skipped: usize,
+}
+
+/// 数据包导入结果
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BundleImportReport {
+    pub manifest: BundleManifest,
+    pub students: MergeCount,
+    pub cash: MergeCount,
+    pub coaches: MergeCount,
+    pub sessions: MergeCount,
+    pub attachments: MergeCount,
+    /// 导入前自动备份的目录
+    pub safety_backup: std::path::PathBuf,
+}
+
+/// 数据包中的全部内容
+pub(crate) struct Bundle {
+    pub manifest: BundleManifest,
+    pub student: StudentDatabase,
+    pub cash: CashDatabase,
+    pub coaches: CoachDatabase,
+    pub sessions: SessionDatabase,
+    pub attachments: AttachmentDatabase,
+    /// 附件文件内容，键为附件序号
+    pub files: BTreeMap<u64, Vec<u8>>,
+}
+
+impl Bundle {
+    /// 写入 `path`（原子操作）
+    pub fn write(&self, path: &Path) -> Result<()> {
+        let dir = path
+            .parent()
+            .filter(|dir| !dir.as_os_str().is_empty())
+            .unwrap_or(Path::new("."));
+        std::fs::create_dir_all(dir)?;
+        let mut tmpfile = tempfile::NamedTempFile::new_in(dir)?;
+        {
+            let mut zip = ZipWriter::new(BufWriter::new(tmpfile.as_file_mut()));
+            zip.add(MANIFEST_FILE, &serde_json::to_vec_pretty(&self.manifest)?)?;
+            zip.add(STUDENT_FILE, &serde_json::to_vec(&self.student)?)?;
+            zip.add(CASH_FILE, &serde_json::to_vec(&self.cash)?)?;
+            zip.add(COACH_FILE, &serde_json::to_vec(&self.coaches)?)?;
+            zip.add(SESSION_FILE, &serde_json::to_vec(&self.sessions)?)?;
+            zip.add(ATTACHMENT_INDEX_FILE, &serde_json::to_vec(&self.attachments)?)?;
+            for (uid, data) in &self.files {
+                let attachment = self
+                    .attachments
+                    .get(uid)
+                    .ok_or_else(|| Error::NotFound(format!("附件不存在: {}", uid)))?;
+                zip.add(&attachment_entry(attachment), data)?;
+            }
+            zip.finish()?;
+        }
+        tmpfile.as_file().sync_all()?;
+        tmpfile
+            .persist(path)
+            .map_err(|e| Error::Other(format!("写入数据包失败: {}", e.error)))?;
+        Ok(())
+    }
+
+    /// 读取并校验数据包
+    pub fn read(path: &Path) -> Result<Self> {
+        let mut entries: BTreeMap<String, Vec<u8>> =
+            read_zip(&std::fs::read(path)?)?.into_iter().collect();
+        let mut take = |name: &str| {
+            entries
+                .remove(name)
+                .ok_or_else(|| Error::InvalidInput(format!("数据包缺少 {}", name)))
+        };
+
+        let manifest: BundleManifest = serde_json::from_slice(&take(MANIFEST_FILE)?)?;
+        if manifest.format_version > BUNDLE_FORMAT_VERSION {
+            return Err(Error::InvalidInput(format!(
+                "数据包格式版本 {} 高于当前支持的版本 {}，请先升级",
+                manifest.format_version, BUNDLE_FORMAT_VERSION
+            )));
+        }
+        let student = parse(&take(STUDENT_FILE)?)?;
+        let cash = parse(&take(CASH_FILE)?)?;
+        let coaches = parse(&take(COACH_FILE)?)?;
+        let sessions = parse(&take(SESSION_FILE)?)?;
+        let attachments: AttachmentDatabase = parse(&take(ATTACHMENT_INDEX_FILE)?)?;
+
+        let mut files = BTreeMap::new();
+        for (uid, attachment) in attachments.iter() {
+            files.insert(*uid, take(&attachment_entry(attachment))?);
+        }
+        Ok(Self {
+            manifest,
+            student,
+            cash,
+            coaches,
+            sessions,
+            attachments,
+            files,
+        })
+    }
+
+    /// 把数据包中记录的计数器应用到当前进程，之后分配的 UID 不会与导入的记录重复
+    pub fn advance_counters(&self) {
+        STUDENT_UID_COUNTER.fetch_max(self.manifest.next_student_uid, Ordering::SeqCst);
+        CASH_UID_COUNTER.fetch_max(self.manifest.next_cash_uid, Ordering::SeqCst);
+        PLAN_ID_COUNTER.fetch_max(self.manifest.next_plan_id, Ordering::SeqCst);
+    }
+}
+
+/// 生成数据包说明，计数器取当前进程中的值
+pub(crate) fn manifest(
+    student: &StudentDatabase,
+    cash: &CashDatabase,
+    coaches: &CoachDatabase,
+    sessions: &SessionDatabase,
+    attachments: &AttachmentDatabase,
+    now: DateTime<Utc>,
+) -> BundleManifest {
+    BundleManifest {
+        format_version: BUNDLE_FORMAT_VERSION,
+        library_version: env!("CARGO_PKG_VERSION").to_string(),
+        created_at: now,
+        next_student_uid: STUDENT_UID_COUNTER.load(Ordering::SeqCst),
+        next_cash_uid: CASH_UID_COUNTER.load(Ordering::SeqCst),
+        next_plan_id: PLAN_ID_COUNTER.load(Ordering::SeqCst),
+        students: student.len(),
+        cash: cash.len(),
+        coaches: coaches.len(),
+        sessions: sessions.len(),
+        attachments: attachments.len(),
+    }
+}
+
+/// 按策略把 `incoming` 中的记录合并到 `target`，返回被写入的记录 UID 和统计
+pub(crate) fn merge<D, T>(target: &mut D, incoming: D, strategy: MergeStrategy) -> (Vec<u64>, MergeCount)
+where
+    D: Database<T>,
+    T: Serialize + DeserializeOwned + Clone + HasUid,
+{
+    let mut count = MergeCount::default();
+    if strategy == MergeStrategy::Replace {
+        count.added = incoming.len();
+        let uids = incoming.data().keys().copied().collect();
+        *target = incoming;
+        return (uids, count);
+    }
+
+    let mut written = Vec::new();
+    let mut records = Vec::new();
+    for (&uid, record) in incoming.data() {
+        match (target.get(&uid).is_some(), strategy) {
+            (false, _) => count.added += 1,
+            (true, MergeStrategy::Overwrite) => count.overwritten += 1,
+            (true, _) => {
+                count.skipped += 1;
+                continue;
+            }
+        }
+        written.push(uid);
+        records.push(record.clone());
+    }
+    target.insert_batch(records);
+    (written, count)
+}
+
+fn parse<D: DeserializeOwned>(bytes: &[u8]) -> Result<D> {
+    Ok(serde_json::from_slice(bytes)?)
+}
+
+fn attachment_entry(attachment: &crate::attachment::Attachment) -> String {
+    format!(
+        "{}{}/{}",
+        ATTACHMENT_FILES_DIR, attachment.student_uid, attachment.stored_name
+    )
+}
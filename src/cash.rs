@@ -5,14 +5,27 @@ use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::OnceLock;
 
 use crate::error::{Result, Error};
-use chrono::{DateTime, Utc};
+use chrono::{DateTime, Days, Months, Utc};
 use log::{debug, error, info, warn};
 use serde::{Deserialize, Serialize};
 
-use crate::common::{Database, HasUid};
+use crate::common::{CustomValue, Database, HasUid, SalvageReport, SecondaryIndex};
+use crate::lazy::{PendingDetails, deserialize_detail, read_details, skipping_details};
+use crate::catalog::SaleCredit;
+use crate::money::{Currency, Money};
+use crate::plan::{InstallmentPlan, PlanDatabase};
+use crate::recurring::RecurringOccurrence;
 
 pub static CASH_UID_COUNTER: AtomicU64 = AtomicU64::new(1);
 
+/// 分期计划 ID 计数器，与现金记录 UID 相互独立
+pub static PLAN_ID_COUNTER: AtomicU64 = AtomicU64::new(1);
+
+/// 分配一个新的分期计划 ID
+pub fn allocate_plan_id() -> u64 {
+    PLAN_ID_COUNTER.fetch_add(1, Ordering::SeqCst)
+}
+
 static DATA_DIR: OnceLock<String> = OnceLock::new();
 
 fn get_data_dir() -> &'static str {
@@ -23,80 +36,360 @@ fn get_data_dir() -> &'static str {
 
 /// 独立的 Cash 结构体，包含自己的 UID 和关联的学生 ID
 #[derive(Serialize, Deserialize, Debug, Clone)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct Cash {
     /// Cash 自己的唯一标识符
     pub uid: u64,
     /// 关联的学生 UID
     pub student_id: Option<u64>,
-    /// 金额
-    pub cash: i64,
+    /// 金额，旧版本数据文件中的整数按人民币的分读取
+    pub cash: Money,
     /// 备注信息
+    #[serde(default, deserialize_with = "deserialize_detail")]
     pub note: Option<String>,
     /// 分期付款信息
     pub installment: Option<Installment>,
     /// 创建时间戳
     pub created_at: DateTime<Utc>,
+    /// 退款记录对应的原始现金记录 UID
+    #[serde(default)]
+    pub refund_of: Option<u64>,
+    /// 机构自定义字段
+    #[serde(default)]
+    pub custom_fields: BTreeMap<String, CustomValue>,
+    /// 所属校区，旧版本数据文件中的记录为 `None`
+    #[serde(default)]
+    pub branch_id: Option<String>,
+    /// 支付方式，未记录时为 `None`
+    #[serde(default)]
+    pub payment_method: Option<PaymentMethod>,
+    /// 售卖商品时为学生增加的课时或会员期，见 [`crate::catalog`]
+    #[serde(default)]
+    pub sale: Option<SaleCredit>,
+    /// 对学生储值余额的影响，正数为充值、负数为扣费
+    ///
+    /// 充值记录的 `cash` 为实收金额；从余额扣费不产生实际收支，扣费记录的 `cash` 为 0。
+    #[serde(default)]
+    pub wallet: Option<Money>,
+    /// 收支类别，如房租、工资，未分类时为 `None`
+    #[serde(default)]
+    pub category: Option<String>,
+    /// 由定期支出模板生成时的模板和月份，见 [`crate::recurring`]
+    #[serde(default)]
+    pub recurring: Option<RecurringOccurrence>,
 }
 
-/// 分期付款计划（新增）
+/// 支付方式
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub enum PaymentMethod {
+    /// 现金
+    Cash,
+    WeChat,
+    Alipay,
+    /// 刷卡
+    Card,
+    /// 银行转账
+    BankTransfer,
+    Other,
+}
+
+/// 收支汇总
+///
+/// 仪表盘与财务统计共用同一套计入规则：
+/// - 金额大于 0 计入收入；
+/// - 金额小于 0（支出、退款）按绝对值计入支出；
+/// - 金额为 0 的记录不计入收入也不计入支出，但仍计入记录数。
+///
+/// 同一汇总中的非零金额必须是同一币种，见 [`Currency::common`]。
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CashTotals {
+    pub income: i64,
+    pub expense: i64,
+    pub count: usize,
+    /// 收支的币种，尚未计入非零金额时为 `None`
+    pub currency: Option<Currency>,
+}
+
+impl CashTotals {
+    /// 按统一规则计入一笔金额，累加溢出时取饱和值
+    ///
+    /// 与已计入的金额币种不同时返回 [`Error::CurrencyMismatch`]，汇总保持不变。
+    pub fn record(&mut self, amount: Money) -> Result<&mut Self> {
+        if !amount.is_zero() {
+            let currency = *self.currency.get_or_insert(amount.currency);
+            if currency != amount.currency {
+                return Err(Error::CurrencyMismatch {
+                    expected: currency,
+                    found: amount.currency,
+                });
+            }
+        }
+        if amount.is_positive() {
+            self.income = self.income.saturating_add(amount.amount_minor);
+        } else if amount.is_negative() {
+            self.expense = self
+                .expense
+                .saturating_add(amount.saturating_abs().amount_minor);
+        }
+        self.count += 1;
+        Ok(self)
+    }
+
+    /// 汇总一组现金记录，币种不一致时返回 [`Error::CurrencyMismatch`]
+    pub fn from_records<'a>(records: impl IntoIterator<Item = &'a Cash>) -> Result<Self> {
+        let mut totals = Self::default();
+        for cash in records {
+            totals.record(cash.cash)?;
+        }
+        Ok(totals)
+    }
+
+    /// 净收入
+    pub fn net(&self) -> i64 {
+        self.income.saturating_sub(self.expense)
+    }
+}
+
+/// 分期付款计划中的一期（新增）
+///
+/// 计划条款（总金额、总期数、付款频率等）以 [`CashDatabase::plans`] 中的计划为准。
+/// 每期仍保留一份条款副本，旧版本程序可以继续读取新的数据文件；
+/// 缺少条款的记录（如 [`Installment::new`] 创建的记录）各条款取默认值。
 #[derive(Serialize, Deserialize, Debug, Clone)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct Installment {
     /// 分期计划ID（同一计划的各期共享相同ID）
     pub plan_id: u64,
-    /// 总金额
+    /// 计划总金额的副本，以最小货币单位表示，币种与本期金额相同
+    #[serde(default)]
     pub total_amount: i64,
-    /// 总期数
+    /// 计划总期数的副本
+    #[serde(default)]
     pub total_installments: u32,
     /// 当前期数
     pub current_installment: u32,
-    /// 付款频率
+    /// 计划付款频率的副本
+    #[serde(default)]
     pub frequency: PaymentFrequency,
     /// 到期日期
     pub due_date: DateTime<Utc>,
     /// 付款状态
     pub status: InstallmentStatus,
+    /// 付款时间，未付款时为 `None`
+    #[serde(default)]
+    pub paid_at: Option<DateTime<Utc>>,
+    /// 计划余数分配方式的副本
+    #[serde(default)]
+    pub remainder_strategy: RemainderStrategy,
+    /// 尚未转存到计划数据库的计划，插入 [`CashDatabase`] 时转存
+    #[serde(skip)]
+    pending_plan: Option<Box<InstallmentPlan>>,
+}
+
+impl Installment {
+    /// 计划 `plan_id` 中待付款的第 `current_installment` 期，不带计划条款
+    pub fn new(plan_id: u64, current_installment: u32, due_date: DateTime<Utc>) -> Self {
+        Self {
+            plan_id,
+            total_amount: 0,
+            total_installments: 0,
+            current_installment,
+            frequency: PaymentFrequency::default(),
+            due_date,
+            status: InstallmentStatus::Pending,
+            paid_at: None,
+            remainder_strategy: RemainderStrategy::default(),
+            pending_plan: None,
+        }
+    }
+
+    /// 计划 `plan` 中待付款的第 `current_installment` 期，带计划条款的副本
+    pub fn for_plan(
+        plan: &InstallmentPlan,
+        current_installment: u32,
+        due_date: DateTime<Utc>,
+    ) -> Self {
+        Self {
+            total_amount: plan.total_amount.amount_minor,
+            total_installments: plan.total_installments,
+            frequency: plan.frequency,
+            remainder_strategy: plan.remainder_strategy,
+            ..Self::new(plan.plan_id, current_installment, due_date)
+        }
+    }
+
+    /// 按本期保存的条款副本还原计划，用于转存旧版本数据文件；没有条款时返回 `None`
+    fn stored_plan(&self, student_id: Option<u64>, currency: Currency) -> Option<InstallmentPlan> {
+        if self.total_installments == 0 {
+            return None;
+        }
+        Some(InstallmentPlan {
+            plan_id: self.plan_id,
+            student_id,
+            total_amount: Money::new(self.total_amount, currency),
+            total_installments: self.total_installments,
+            frequency: self.frequency,
+            first_due: self
+                .frequency
+                .rewind(self.due_date, self.current_installment.saturating_sub(1)),
+            remainder_strategy: self.remainder_strategy,
+            grace_days: None,
+        })
+    }
+}
+
+/// 待付分期在 `now` 是否已过到期时间加宽限期
+fn is_overdue(plans: &PlanDatabase, cash: &Cash, now: DateTime<Utc>, grace_days: u32) -> bool {
+    let Some(installment) = &cash.installment else {
+        return false;
+    };
+    if installment.status != InstallmentStatus::Pending {
+        return false;
+    }
+    let grace = plans
+        .get(&installment.plan_id)
+        .map_or(grace_days, |plan| plan.grace_days_or(grace_days));
+    installment
+        .due_date
+        .checked_add_days(Days::new(grace.into()))
+        .is_some_and(|deadline| deadline < now)
+}
+
+/// 分期金额除不尽时余数的分配方式
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub enum RemainderStrategy {
+    /// 余数全部计入最后一期（默认）
+    #[default]
+    LastPays,
+    /// 余数全部计入第一期
+    FirstPays,
+    /// 余数按每期 1 个单位分摊到前几期
+    Spread,
+}
+
+impl RemainderStrategy {
+    /// 计算第 `current_installment` 期（从 1 开始）应付金额
+    ///
+    /// 各期金额之和始终等于 `total_amount`。
+    pub fn amount_for(
+        &self,
+        total_amount: i64,
+        total_installments: u32,
+        current_installment: u32,
+    ) -> i64 {
+        let base_amount = total_amount / total_installments as i64;
+        let remainder = total_amount % total_installments as i64;
+        let extra = match self {
+            Self::LastPays if current_installment == total_installments => remainder,
+            Self::FirstPays if current_installment == 1 => remainder,
+            Self::Spread if (current_installment as i64) <= remainder.abs() => remainder.signum(),
+            _ => 0,
+        };
+        base_amount + extra
+    }
 }
 
 /// 付款频率枚举（新增）
-#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub enum PaymentFrequency {
     Weekly,
+    #[default]
     Monthly,
     Quarterly,
     Custom(u32), // 自定义天数
 }
 
+impl PaymentFrequency {
+    /// 计算从 `from` 开始的下一个到期时间
+    ///
+    /// 按月、按季度时遇到目标月份没有对应日期的情况（如 1 月 31 日）会落到该月最后一天。
+    pub fn next_due(&self, from: DateTime<Utc>) -> DateTime<Utc> {
+        self.nth_due(from, 1)
+    }
+
+    /// 计算以 `anchor` 为第一期到期时间时，往后第 `periods` 期的到期时间
+    ///
+    /// 每期都从 `anchor` 直接推算而不是逐期累加，避免月末日期逐期漂移
+    /// （例如 1 月 31 日起按月付款，3 月仍在 31 日到期）。
+    pub fn nth_due(&self, anchor: DateTime<Utc>, periods: u32) -> DateTime<Utc> {
+        let due = match self {
+            Self::Weekly => anchor.checked_add_days(Days::new(7 * periods as u64)),
+            Self::Monthly => anchor.checked_add_months(Months::new(periods)),
+            Self::Quarterly => periods
+                .checked_mul(3)
+                .and_then(|months| anchor.checked_add_months(Months::new(months))),
+            Self::Custom(days) => anchor.checked_add_days(Days::new(*days as u64 * periods as u64)),
+        };
+        due.unwrap_or_else(|| {
+            warn!(
+                "到期时间计算溢出: {:?}, 起始 {}, 期数 {}",
+                self, anchor, periods
+            );
+            DateTime::<Utc>::MAX_UTC
+        })
+    }
+
+    /// 由第 `periods + 1` 期的到期时间倒推第一期的到期时间，用于迁移旧版本数据
+    fn rewind(&self, due: DateTime<Utc>, periods: u32) -> DateTime<Utc> {
+        let first = match self {
+            Self::Weekly => due.checked_sub_days(Days::new(7 * periods as u64)),
+            Self::Monthly => due.checked_sub_months(Months::new(periods)),
+            Self::Quarterly => periods
+                .checked_mul(3)
+                .and_then(|months| due.checked_sub_months(Months::new(months))),
+            Self::Custom(days) => due.checked_sub_days(Days::new(*days as u64 * periods as u64)),
+        };
+        first.unwrap_or(due)
+    }
+}
+
 /// 分期付款状态枚举（新增）
-#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub enum InstallmentStatus {
+    #[default]
     Pending,
     Paid,
     Overdue,
     Cancelled,
 }
 
-impl Default for InstallmentStatus {
-    fn default() -> Self {
-        Self::Pending
-    }
-}
-
 impl Cash {
     pub fn new(student_id: Option<u64>) -> Self {
         let uid = CASH_UID_COUNTER.fetch_add(1, Ordering::SeqCst);
+        Self::new_with_uid(uid, student_id)
+    }
+
+    /// 使用指定 UID 创建现金记录，不经过全局计数器
+    ///
+    /// 调用方需保证 UID 在数据库中唯一，通常由 [`crate::id::IdNamespace`] 分配。
+    pub fn new_with_uid(uid: u64, student_id: Option<u64>) -> Self {
         let new_cash = Self {
             uid,
             student_id,
-            cash: 0,
+            cash: Money::default(),
             note: None,
             installment: None, // 默认没有分期
             created_at: Utc::now(),
+            refund_of: None,
+            custom_fields: BTreeMap::new(),
+            branch_id: None,
+            payment_method: None,
+            sale: None,
+            wallet: None,
+            category: None,
+            recurring: None,
         };
         info!("创建新的Cash记录，UID为: {}", new_cash.uid);
         new_cash
     }
 
     /// 创建新的分期付款记录
+    ///
+    /// 计划条款随记录一起插入 [`CashDatabase`]，计划数据库中还没有该计划时转存为新计划。
     pub fn new_installment(
         student_id: Option<u64>,
         total_amount: i64,
@@ -107,52 +400,80 @@ impl Cash {
         plan_id: Option<u64>,
     ) -> Self {
         let uid = CASH_UID_COUNTER.fetch_add(1, Ordering::SeqCst);
+        let plan = InstallmentPlan {
+            plan_id: plan_id.unwrap_or_else(allocate_plan_id),
+            student_id,
+            total_amount: Money::cny(total_amount),
+            total_installments,
+            frequency,
+            first_due: frequency.rewind(due_date, current_installment.saturating_sub(1)),
+            remainder_strategy: RemainderStrategy::LastPays,
+            grace_days: None,
+        };
 
         // 分期金额计算：每期基础金额 = 总金额 / 总期数
-        // 最后一期加上余数，确保总金额正确
-        let base_amount = total_amount / total_installments as i64;
-        let remainder = total_amount % total_installments as i64;
-        let cash = base_amount
-            + if current_installment == total_installments {
-                remainder
-            } else {
-                0
-            };
+        // 默认最后一期加上余数，确保总金额正确
+        let cash = plan.amount_for(current_installment);
 
-        let plan_id = plan_id.unwrap_or_else(|| CASH_UID_COUNTER.fetch_add(1, Ordering::SeqCst));
+        // 添加分期创建日志
+        info!(
+            "创建分期付款记录: UID={}, 计划ID={}, 期数={}/{}, 金额={}, 到期时间={}",
+            uid, plan.plan_id, current_installment, total_installments, cash, due_date
+        );
 
-        let cash_record = Cash {
+        let mut installment = Installment::for_plan(&plan, current_installment, due_date);
+        installment.pending_plan = Some(Box::new(plan));
+        Cash {
             uid,
             student_id,
             cash,
             note: None,
-            installment: Some(Installment {
-                plan_id,
-                total_amount,
-                total_installments,
-                current_installment,
-                frequency,
-                due_date,
-                status: InstallmentStatus::Pending,
-            }),
+            installment: Some(installment),
             created_at: Utc::now(),
-        };
-
-        // 添加分期创建日志
-        info!(
-            "创建分期付款记录: UID={}, 计划ID={}, 期数={}/{}, 金额={}, 到期时间={}",
-            uid, plan_id, current_installment, total_installments, cash_record.cash, due_date
-        );
+            refund_of: None,
+            custom_fields: BTreeMap::new(),
+            branch_id: None,
+            payment_method: None,
+            sale: None,
+            wallet: None,
+            category: None,
+            recurring: None,
+        }
+    }
 
-        cash_record
+    /// 按指定的余数分配方式重新计算本期金额
+    ///
+    /// 仅对由 [`Cash::new_installment`] 创建、尚未插入数据库的分期记录生效，
+    /// 同一计划的各期应使用相同的分配方式。
+    pub fn with_remainder_strategy(mut self, strategy: RemainderStrategy) -> Self {
+        if let Some(installment) = &mut self.installment
+            && let Some(plan) = &mut installment.pending_plan
+        {
+            plan.remainder_strategy = strategy;
+            installment.remainder_strategy = strategy;
+            self.cash = plan.amount_for(installment.current_installment);
+            debug!(
+                "分期记录 {} 使用余数分配方式 {:?}，本期金额: {}",
+                self.uid, strategy, self.cash
+            );
+        }
+        self
     }
 
-    pub fn add(&mut self, num: i64) {
-        self.cash += num;
+    /// 在当前币种下增加 `num` 个最小货币单位，溢出时返回 [`Error::InvalidInput`] 且金额不变
+    pub fn add(&mut self, num: i64) -> Result<()> {
+        self.cash = self
+            .cash
+            .checked_add(Money::new(num, self.cash.currency))
+            .ok_or_else(|| {
+                Error::InvalidInput(format!("金额 {} 加上 {} 后溢出", self.cash, num))
+            })?;
+        Ok(())
     }
 
-    pub fn set_cash(&mut self, num: i64) {
-        self.cash = num;
+    /// 设置金额，整数按人民币的分处理
+    pub fn set_cash(&mut self, amount: impl Into<Money>) {
+        self.cash = amount.into();
     }
 
     pub fn set_id(&mut self, id: u64) {
@@ -192,6 +513,32 @@ impl Cash {
     }
 }
 
+fn is_duplicate(
+    cash: &Cash,
+    student_id: Option<u64>,
+    amount: Money,
+    at: DateTime<Utc>,
+    window: chrono::Duration,
+) -> bool {
+    cash.student_id == student_id && cash.cash == amount && (cash.created_at - at).abs() <= window
+}
+
+/// 把一笔储值变动计入余额，币种与余额不同时跳过
+fn add_to_balance(balance: &mut Option<Money>, student_id: u64, delta: Money) {
+    match balance {
+        None => *balance = Some(delta),
+        Some(current) if current.currency == delta.currency => {
+            current.amount_minor = current.amount_minor.saturating_add(delta.amount_minor);
+        }
+        Some(current) => warn!(
+            "学生 {} 的储值记录币种 {} 与余额币种 {} 不同，不计入余额",
+            student_id,
+            delta.currency.code(),
+            current.currency.code()
+        ),
+    }
+}
+
 impl HasUid for Cash {
     fn uid(&self) -> u64 {
         self.uid
@@ -199,9 +546,78 @@ impl HasUid for Cash {
 }
 
 /// Cash 数据库结构，支持持久化存储
-#[derive(Serialize, Deserialize, Debug, Clone)]
+///
+/// 与 [`crate::student::StudentDatabase`] 相同，序列化时写入 `next_uid`，
+/// 加载自己的数据时由 [`CashDatabase::sync_uid_counter`] 推进 [`CASH_UID_COUNTER`]，
+/// 旧版本数据文件仍以 `cash_uid_counter` 文件为准。
+#[derive(Deserialize, Debug, Clone)]
+#[serde(from = "StoredCashDatabase")]
 pub struct CashDatabase {
+    /// 直接修改后需调用 [`CashDatabase::rebuild_indexes`]
     pub cash_data: BTreeMap<u64, Cash>,
+    /// 分期记录所属的计划
+    pub plans: PlanDatabase,
+    by_student: SecondaryIndex<u64>,
+    by_plan: SecondaryIndex<u64>,
+    /// 延迟加载时尚未读取备注的记录
+    pending_details: Option<PendingDetails>,
+    /// 数据文件中记录的下一个 UID
+    next_uid: u64,
+}
+
+/// 延迟加载的现金记录详细字段
+#[derive(Deserialize)]
+struct CashDetails {
+    #[serde(default)]
+    note: Option<String>,
+}
+
+/// 数据文件中的现金数据库
+#[derive(Deserialize)]
+struct StoredCashDatabase {
+    cash_data: BTreeMap<u64, Cash>,
+    #[serde(default)]
+    next_uid: Option<u64>,
+    #[serde(default)]
+    plans: PlanDatabase,
+}
+
+impl From<StoredCashDatabase> for CashDatabase {
+    fn from(stored: StoredCashDatabase) -> Self {
+        let mut db = Self {
+            cash_data: stored.cash_data,
+            plans: stored.plans,
+            by_student: SecondaryIndex::default(),
+            by_plan: SecondaryIndex::default(),
+            pending_details: None,
+            next_uid: stored.next_uid.unwrap_or(1),
+        };
+        db.rebuild_indexes();
+        db
+    }
+}
+
+impl Serialize for CashDatabase {
+    fn serialize<S: serde::Serializer>(
+        &self,
+        serializer: S,
+    ) -> std::result::Result<S::Ok, S::Error> {
+        use serde::ser::{Error as _, SerializeStruct};
+        if let Some(pending) = &self.pending_details {
+            return Err(S::Error::custom(format!(
+                "仍有 {} 条现金记录的备注未加载，保存前需调用 load_all_details",
+                pending.uids.len()
+            )));
+        }
+        let next_uid = CASH_UID_COUNTER
+            .load(Ordering::SeqCst)
+            .max(self.derived_next_uid());
+        let mut state = serializer.serialize_struct("CashDatabase", 3)?;
+        state.serialize_field("cash_data", &self.cash_data)?;
+        state.serialize_field("next_uid", &next_uid)?;
+        state.serialize_field("plans", &self.plans)?;
+        state.end()
+    }
 }
 
 impl Default for CashDatabase {
@@ -232,14 +648,84 @@ impl Database<Cash> for CashDatabase {
         "现金"
     }
 
+    fn data_field() -> &'static str {
+        "cash_data"
+    }
+
     fn new() -> Self {
         Self {
             cash_data: BTreeMap::new(),
+            plans: PlanDatabase::new(),
+            by_student: SecondaryIndex::default(),
+            by_plan: SecondaryIndex::default(),
+            pending_details: None,
+            next_uid: 1,
+        }
+    }
+
+    fn reindex(&mut self, uid: u64) {
+        match self.cash_data.get_mut(&uid) {
+            Some(cash) => {
+                self.by_student.set(uid, cash.student_id);
+                self.by_plan.set(uid, cash.installment_plan_id());
+                // 新建的记录或旧版本数据中没有对应计划的记录，按其带有的条款转存到计划数据库
+                let plan = cash.installment.as_mut().and_then(|installment| {
+                    installment.pending_plan.take().map(|plan| *plan).or_else(|| {
+                        if self.plans.get(&installment.plan_id).is_some() {
+                            None
+                        } else {
+                            installment.stored_plan(cash.student_id, cash.cash.currency)
+                        }
+                    })
+                });
+                if let Some(mut plan) = plan {
+                    plan.student_id = plan.student_id.or(cash.student_id);
+                    let plan_id = plan.plan_id;
+                    if self.plans.insert_if_absent(plan) {
+                        debug!("转存分期计划 {}", plan_id);
+                    }
+                }
+            }
+            None => {
+                self.by_student.remove(uid);
+                self.by_plan.remove(uid);
+                if let Some(pending) = &mut self.pending_details {
+                    pending.uids.remove(&uid);
+                }
+            }
+        }
+    }
+
+    fn rebuild_indexes(&mut self) {
+        self.by_student.clear();
+        self.by_plan.clear();
+        let uids: Vec<u64> = self.cash_data.keys().copied().collect();
+        for uid in uids {
+            self.reindex(uid);
         }
     }
 }
 
 impl CashDatabase {
+    /// 已有最大 UID 之后的第一个 UID，没有记录时为 1
+    fn derived_next_uid(&self) -> u64 {
+        self.cash_data
+            .last_key_value()
+            .map_or(1, |(&uid, _)| uid.saturating_add(1))
+            .max(self.next_uid)
+    }
+
+    /// 把 [`CASH_UID_COUNTER`] 推进到数据文件中的 `next_uid` 和已有最大 UID 之后
+    ///
+    /// 只应在加载自己的数据时调用，见 [`crate::student::StudentDatabase::sync_uid_counter`]。
+    pub fn sync_uid_counter(&self) {
+        let next = self.derived_next_uid();
+        let previous = CASH_UID_COUNTER.fetch_max(next, Ordering::SeqCst);
+        if previous < next {
+            debug!("CASH UID计数器从 {} 调整为 {}", previous, next);
+        }
+    }
+
     /// 从JSON字符串反序列化数据库
     pub fn from_json(json_str: &str) -> Result<Self> {
         serde_json::from_str(json_str).map_err(Error::from)
@@ -273,6 +759,24 @@ impl CashDatabase {
         <Self as Database<Cash>>::get(self, index)
     }
 
+    /// 记录 `uid` 修改后更新索引
+    pub fn reindex(&mut self, uid: u64) {
+        <Self as Database<Cash>>::reindex(self, uid)
+    }
+
+    /// 直接修改 `cash_data` 后重建索引
+    pub fn rebuild_indexes(&mut self) {
+        <Self as Database<Cash>>::rebuild_indexes(self)
+    }
+
+    /// 学生的全部现金记录，按 UID 排列
+    pub fn get_by_student(&self, student_id: u64) -> Vec<&Cash> {
+        self.by_student
+            .get(&student_id)
+            .filter_map(|uid| self.cash_data.get(&uid))
+            .collect()
+    }
+
     pub fn save(&self) -> Result<()> {
         <Self as Database<Cash>>::save(self)
     }
@@ -285,6 +789,62 @@ impl CashDatabase {
         <Self as Database<Cash>>::read_from(path)
     }
 
+    /// 延迟加载：跳过备注，需要时通过 [`CashDatabase::load_details`] 读取，详见 [`crate::lazy`]
+    pub fn read_lazy(path: &str) -> Result<Self> {
+        let mut db = skipping_details(|| Self::read_from(path))?;
+        if !db.is_empty() {
+            db.pending_details = Some(PendingDetails::new(path, db.cash_data.keys().copied()));
+        }
+        Ok(db)
+    }
+
+    /// 是否还有现金记录的备注未加载
+    pub fn has_pending_details(&self) -> bool {
+        self.pending_details.is_some()
+    }
+
+    /// 从原文件加载指定现金记录的备注，已加载的记录跳过，返回本次加载的记录数
+    pub fn load_details(&mut self, uids: &[u64]) -> Result<usize> {
+        let Some(pending) = &self.pending_details else {
+            return Ok(0);
+        };
+        let wanted = pending.among(uids);
+        if wanted.is_empty() {
+            return Ok(0);
+        }
+        let details = read_details::<CashDetails>(&pending.path, &wanted)?;
+        let mut loaded = 0;
+        for (uid, detail) in details {
+            if let Some(cash) = self.cash_data.get_mut(&uid) {
+                cash.note = detail.note;
+                loaded += 1;
+            }
+        }
+        if let Some(pending) = &mut self.pending_details {
+            pending.uids.retain(|uid| !wanted.contains(uid));
+            if pending.uids.is_empty() {
+                self.pending_details = None;
+            }
+        }
+        debug!("加载 {} 条现金记录的备注", loaded);
+        Ok(loaded)
+    }
+
+    /// 加载全部未加载的备注，之后数据库可以正常保存
+    pub fn load_all_details(&mut self) -> Result<usize> {
+        let uids: Vec<u64> = self
+            .pending_details
+            .as_ref()
+            .map(|pending| pending.uids.iter().copied().collect())
+            .unwrap_or_default();
+        self.load_details(&uids)
+    }
+
+    /// 容错加载，详见 [`Database::salvage_from`]
+    pub fn salvage_from(path: &str) -> Result<(Self, SalvageReport)> {
+        <Self as Database<Cash>>::salvage_from(path)
+    }
+
     pub fn iter(&self) -> impl Iterator<Item = (&u64, &Cash)> + '_ {
         <Self as Database<Cash>>::iter(self)
     }
@@ -313,34 +873,179 @@ impl CashDatabase {
             .collect()
     }
 
-    /// 获取指定分期计划的所有记录（新增）
-    pub fn get_installments_by_plan(&self, plan_id: u64) -> Vec<&Cash> {
+    /// 获取指定现金记录的所有退款记录
+    pub fn get_refunds_of(&self, original_uid: u64) -> Vec<&Cash> {
+        self.cash_data
+            .values()
+            .filter(|c| c.refund_of == Some(original_uid))
+            .collect()
+    }
+
+    /// 指定现金记录已退款的总金额（正数）
+    pub fn refunded_amount(&self, original_uid: u64) -> i64 {
+        self.get_refunds_of(original_uid)
+            .iter()
+            .map(|c| c.cash.amount_minor.saturating_neg())
+            .fold(0i64, i64::saturating_add)
+    }
+
+    /// 学生的储值余额，没有充值或扣费记录时为 0
+    ///
+    /// 余额的币种以学生第一条储值记录为准，其他币种的记录不计入。
+    pub fn wallet_balance(&self, student_id: u64) -> Money {
+        let mut balance = None;
+        for cash in self.get_by_student(student_id) {
+            if let Some(delta) = cash.wallet {
+                add_to_balance(&mut balance, student_id, delta);
+            }
+        }
+        balance.unwrap_or_default()
+    }
+
+    /// 所有有储值记录的学生的余额，按学生 UID 排列
+    pub fn wallet_balances(&self) -> BTreeMap<u64, Money> {
+        let mut balances = BTreeMap::new();
+        for cash in self.cash_data.values() {
+            if let (Some(student_id), Some(delta)) = (cash.student_id, cash.wallet) {
+                let balance = balances.entry(student_id).or_insert(None);
+                add_to_balance(balance, student_id, delta);
+            }
+        }
+        balances
+            .into_iter()
+            .filter_map(|(student_id, balance)| balance.map(|balance| (student_id, balance)))
+            .collect()
+    }
+
+    /// 数据库中已使用的最大分期计划 ID，包括计划数据库和各期记录引用的计划
+    pub fn max_plan_id(&self) -> Option<u64> {
         self.cash_data
             .values()
-            .filter(|c| matches!(c.installment_plan_id(), Some(id) if id == plan_id))
+            .filter_map(Cash::installment_plan_id)
+            .chain(self.plans.max_plan_id())
+            .max()
+    }
+
+    /// 获取指定分期计划的所有记录（新增）
+    pub fn get_installments_by_plan(&self, plan_id: u64) -> Vec<&Cash> {
+        self.by_plan
+            .get(&plan_id)
+            .filter_map(|uid| self.cash_data.get(&uid))
             .collect()
     }
 
     /// 获取逾期分期付款（新增）
     pub fn get_overdue_installments(&self) -> Vec<&Cash> {
-        let now = Utc::now();
+        self.get_overdue_installments_at(Utc::now())
+    }
+
+    /// 获取在指定时间已逾期的分期付款
+    pub fn get_overdue_installments_at(&self, now: DateTime<Utc>) -> Vec<&Cash> {
+        self.get_overdue_installments_with_grace(now, 0)
+    }
+
+    /// 获取在指定时间已过宽限期的待付分期
+    ///
+    /// 计划设置了 [`InstallmentPlan::grace_days`] 时以计划为准，否则使用 `grace_days`。
+    pub fn get_overdue_installments_with_grace(
+        &self,
+        now: DateTime<Utc>,
+        grace_days: u32,
+    ) -> Vec<&Cash> {
         self.cash_data
             .values()
-            .filter(|c| {
-                if let Some(installment) = &c.installment {
-                    installment.status == InstallmentStatus::Pending && installment.due_date < now
-                } else {
-                    false
-                }
+            .filter(|c| is_overdue(&self.plans, c, now, grace_days))
+            .collect()
+    }
+
+    /// 将已过到期时间的待付分期标记为逾期
+    ///
+    /// 返回被修改记录的 UID，已付、已取消或已逾期的记录不受影响。
+    pub fn mark_overdue_installments(&mut self, now: DateTime<Utc>) -> Vec<u64> {
+        self.mark_overdue_installments_with_grace(now, 0)
+    }
+
+    /// 将已过宽限期的待付分期标记为逾期，宽限天数的取值同 [`Self::get_overdue_installments_with_grace`]
+    pub fn mark_overdue_installments_with_grace(
+        &mut self,
+        now: DateTime<Utc>,
+        grace_days: u32,
+    ) -> Vec<u64> {
+        let mut marked = Vec::new();
+        for cash in self.cash_data.values_mut() {
+            if is_overdue(&self.plans, cash, now, grace_days) {
+                cash.set_installment_status(InstallmentStatus::Overdue);
+                marked.push(cash.uid);
+            }
+        }
+        if !marked.is_empty() {
+            info!("标记 {} 条分期付款为逾期", marked.len());
+        }
+        marked
+    }
+
+    /// 查找疑似重复的现金记录
+    ///
+    /// 学生、金额相同且创建时间相差不超过 `window` 的记录视为重复（例如前台重复点击）。
+    /// 返回 `(较早记录UID, 较晚记录UID)` 列表，按较早记录的创建时间排序。
+    pub fn find_duplicates(&self, window: chrono::Duration) -> Vec<(u64, u64)> {
+        let mut records: Vec<&Cash> = self.cash_data.values().collect();
+        records.sort_by_key(|c| {
+            (
+                c.student_id,
+                c.cash.currency,
+                c.cash.amount_minor,
+                c.created_at,
+                c.uid,
+            )
+        });
+
+        let mut duplicates: Vec<(&Cash, &Cash)> = records
+            .windows(2)
+            .filter(|pair| {
+                pair[0].student_id == pair[1].student_id
+                    && pair[0].cash == pair[1].cash
+                    && pair[1].created_at - pair[0].created_at <= window
             })
+            .map(|pair| (pair[0], pair[1]))
+            .collect();
+        duplicates.sort_by_key(|(first, _)| (first.created_at, first.uid));
+
+        if !duplicates.is_empty() {
+            warn!("发现 {} 组疑似重复的现金记录", duplicates.len());
+        }
+        duplicates
+            .into_iter()
+            .map(|(first, second)| (first.uid, second.uid))
             .collect()
     }
 
-    /// 获取学生的分期付款记录（新增）
-    pub fn get_student_installments(&self, student_id: u64) -> Vec<&Cash> {
+    /// 查找与给定记录疑似重复的已有记录
+    pub fn find_duplicate_of(&self, cash: &Cash, window: chrono::Duration) -> Option<&Cash> {
+        self.cash_data.values().find(|c| {
+            c.uid != cash.uid
+                && is_duplicate(c, cash.student_id, cash.cash, cash.created_at, window)
+        })
+    }
+
+    /// 查找学生、金额相同且时间相差不超过 `window` 的已有记录，用于写入新记录前的检查
+    pub fn find_duplicate(
+        &self,
+        student_id: Option<u64>,
+        amount: Money,
+        at: DateTime<Utc>,
+        window: chrono::Duration,
+    ) -> Option<&Cash> {
         self.cash_data
             .values()
-            .filter(|c| c.student_id == Some(student_id) && c.installment.is_some())
+            .find(|c| is_duplicate(c, student_id, amount, at, window))
+    }
+
+    /// 获取学生的分期付款记录（新增）
+    pub fn get_student_installments(&self, student_id: u64) -> Vec<&Cash> {
+        self.get_by_student(student_id)
+            .into_iter()
+            .filter(|c| c.installment.is_some())
             .collect()
     }
 
@@ -350,57 +1055,84 @@ impl CashDatabase {
         plan_id: u64,
         due_date: DateTime<Utc>,
     ) -> Result<u64> {
-        let installments = self.get_installments_by_plan(plan_id);
-        if installments.is_empty() {
-            error!("尝试生成下一期分期付款失败: 找不到计划ID {}", plan_id);
-            return Err(Error::NotFound(format!("找不到分期计划 {}", plan_id)));
-        }
-
-        let first = installments.first().expect("已检查 installments 非空");
-        let installment_info = first
+        let new_cash = self.build_next_installment(plan_id, due_date)?;
+        let uid = new_cash.uid;
+        let cash = new_cash.cash;
+        let next_installment = new_cash
             .installment
             .as_ref()
-            .ok_or_else(|| Error::State(format!("计划ID {} 对应的记录不是分期付款记录", plan_id)))?;
+            .map_or(0, |i| i.current_installment);
+        self.insert(new_cash);
+
+        info!(
+            "为计划 {} 生成第 {} 期分期付款: UID={}, 金额={}, 到期时间={}",
+            plan_id, next_installment, uid, cash, due_date
+        );
+
+        Ok(uid)
+    }
+
+    /// 构造下一期分期付款记录，不插入数据库
+    pub(crate) fn build_next_installment(
+        &self,
+        plan_id: u64,
+        due_date: DateTime<Utc>,
+    ) -> Result<Cash> {
+        let plan = self.plan(plan_id)?;
 
         // 计算当前最大期数
-        let max_installment = installments
+        let max_installment = self
+            .get_installments_by_plan(plan_id)
             .iter()
             .filter_map(|c| c.installment.as_ref().map(|i| i.current_installment))
             .max()
-            .ok_or_else(|| Error::State(format!("计划ID {} 没有有效的分期记录", plan_id)))?;
+            .unwrap_or(0);
 
         // 检查是否已完成所有分期
-        if max_installment >= installment_info.total_installments {
+        if max_installment >= plan.total_installments {
             warn!(
                 "尝试生成下一期分期付款失败: 计划 {} 已完成 (当前期数 {}，总期数 {})",
-                plan_id, max_installment, installment_info.total_installments
+                plan_id, max_installment, plan.total_installments
             );
-            return Err(Error::State(format!("分期计划 {} 已完成", plan_id)));
+            return Err(Error::InstallmentComplete(plan_id));
         }
 
         let next_installment = max_installment + 1;
 
         // 创建新分期记录
-        let new_cash = Cash::new_installment(
-            first.student_id,
-            installment_info.total_amount,
-            installment_info.total_installments,
-            installment_info.frequency,
-            due_date,
-            next_installment,
-            Some(plan_id),
-        );
+        let mut new_cash = Cash::new(plan.student_id);
+        new_cash.set_cash(plan.amount_for(next_installment));
+        new_cash.installment = Some(Installment::for_plan(plan, next_installment, due_date));
+        Ok(new_cash)
+    }
 
-        let uid = new_cash.uid;
-        let cash = new_cash.cash;
-        self.insert(new_cash);
+    /// 按付款频率自动计算到期时间并生成下一期分期付款
+    ///
+    /// 到期时间由计划第一期的到期时间按 [`PaymentFrequency::nth_due`] 推算，
+    /// 月末日期和季度跨年都按日历处理，不会逐期漂移。
+    pub fn generate_next_installment_by_frequency(&mut self, plan_id: u64) -> Result<u64> {
+        let due_date = self.next_installment_due(plan_id)?;
+        self.generate_next_installment(plan_id, due_date)
+    }
 
-        info!(
-            "为计划 {} 生成第 {} 期分期付款: UID={}, 金额={}, 到期时间={}",
-            plan_id, next_installment, uid, cash, due_date
-        );
+    /// 按付款频率推算计划下一期的到期时间
+    pub(crate) fn next_installment_due(&self, plan_id: u64) -> Result<DateTime<Utc>> {
+        let plan = self.plan(plan_id)?;
+        let max_installment = self
+            .get_installments_by_plan(plan_id)
+            .iter()
+            .filter_map(|c| c.installment.as_ref().map(|i| i.current_installment))
+            .max()
+            .unwrap_or(0);
+        Ok(plan.due_for(max_installment + 1))
+    }
 
-        Ok(uid)
+    /// 查找分期计划
+    fn plan(&self, plan_id: u64) -> Result<&InstallmentPlan> {
+        self.plans.get(&plan_id).ok_or_else(|| {
+            error!("尝试生成下一期分期付款失败: 找不到计划ID {}", plan_id);
+            Error::NotFound(format!("找不到分期计划 {}", plan_id))
+        })
     }
 
     /// 取消指定分期计划的所有未完成付款
@@ -415,21 +1147,21 @@ impl CashDatabase {
 
         // 查找指定计划的所有分期记录
         for cash in self.cash_data.values_mut() {
-            if let Some(installment) = &mut cash.installment {
-                if installment.plan_id == plan_id {
-                    // 只取消未完成的付款（Pending 或 Overdue 状态）
-                    if installment.status == InstallmentStatus::Pending
-                        || installment.status == InstallmentStatus::Overdue
-                    {
-                        let old_status = installment.status;
-                        installment.status = InstallmentStatus::Cancelled;
-                        cancelled_count += 1;
-
-                        info!(
-                            "取消分期付款: UID={}, 计划ID={}, 期数={}, 状态: {:?} -> Cancelled",
-                            cash.uid, plan_id, installment.current_installment, old_status
-                        );
-                    }
+            if let Some(installment) = &mut cash.installment
+                && installment.plan_id == plan_id
+            {
+                // 只取消未完成的付款（Pending 或 Overdue 状态）
+                if installment.status == InstallmentStatus::Pending
+                    || installment.status == InstallmentStatus::Overdue
+                {
+                    let old_status = installment.status;
+                    installment.status = InstallmentStatus::Cancelled;
+                    cancelled_count += 1;
+
+                    info!(
+                        "取消分期付款: UID={}, 计划ID={}, 期数={}, 状态: {:?} -> Cancelled",
+                        cash.uid, plan_id, installment.current_installment, old_status
+                    );
                 }
             }
         }
@@ -467,7 +1199,7 @@ pub fn load_saved_cash_uid() -> Result<u64> {
         }
         Err(e) => {
             error!("读取CASH UID文件失败: {}", e);
-            Err(e).map_err(Error::from)
+            Err(Error::from(e))
         }
     }
 }
@@ -481,14 +1213,66 @@ pub fn save_uid() -> Result<()> {
     file.write_all(uid.to_string().as_bytes())
         .map_err(Error::from)?;
     file.sync_all().ok();
-    if let Some(dir) = std::path::Path::new(&path).parent() {
-        if let Ok(dirf) = File::open(dir) { let _ = dirf.sync_all(); }
+    if let Some(dir) = std::path::Path::new(&path).parent()
+        && let Ok(dirf) = File::open(dir)
+    {
+        let _ = dirf.sync_all();
     }
 
     debug!("成功保存CASH UID: {} 到文件", uid);
     Ok(())
 }
 
+/// 加载已保存的分期计划 ID 计数器
+pub fn load_saved_plan_id() -> Result<u64> {
+    let path = format!("{}/plan_id_counter", get_data_dir());
+    match std::fs::read_to_string(&path) {
+        Ok(content) => content
+            .trim()
+            .parse::<u64>()
+            .inspect(|&id| {
+                info!("成功加载分期计划ID: {}", id);
+            })
+            .map_err(Error::from),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+            debug!("未找到现有的分期计划ID文件，从默认值1开始");
+            Ok(1)
+        }
+        Err(e) => {
+            error!("读取分期计划ID文件失败: {}", e);
+            Err(Error::from(e))
+        }
+    }
+}
+
+/// 保存分期计划 ID 计数器
+pub fn save_plan_id() -> Result<()> {
+    let id = PLAN_ID_COUNTER.load(Ordering::SeqCst);
+    let path = format!("{}/plan_id_counter", get_data_dir());
+    let mut file = File::create(&path).map_err(Error::from)?;
+
+    file.write_all(id.to_string().as_bytes())
+        .map_err(Error::from)?;
+    file.sync_all().ok();
+
+    debug!("成功保存分期计划ID: {} 到文件", id);
+    Ok(())
+}
+
+/// 确保分期计划 ID 计数器越过数据库中已有的计划 ID
+///
+/// 旧版本的计划 ID 取自现金 UID 计数器，加载旧数据后调用此函数即可完成迁移，
+/// 新分配的计划 ID 不会与已有计划冲突。
+pub fn sync_plan_id_counter(db: &CashDatabase) {
+    if let Some(max) = db.max_plan_id() {
+        let next = max.saturating_add(1);
+        let previous = PLAN_ID_COUNTER.fetch_max(next, Ordering::SeqCst);
+        if previous < next {
+            info!("分期计划ID计数器从 {} 调整为 {}", previous, next);
+        }
+    }
+}
+
 /// Cash 模块初始化函数
 pub fn init() -> Result<()> {
     std::fs::create_dir_all(get_data_dir()).map_err(Error::from)?;
@@ -497,5 +1281,10 @@ pub fn init() -> Result<()> {
     CASH_UID_COUNTER.store(saved_uid, Ordering::SeqCst);
     info!("CASH UID计数器初始化为 {}", saved_uid);
     save_uid()?;
+
+    let saved_plan_id = load_saved_plan_id()?;
+    PLAN_ID_COUNTER.store(saved_plan_id, Ordering::SeqCst);
+    info!("分期计划ID计数器初始化为 {}", saved_plan_id);
+    save_plan_id()?;
     Ok(())
 }
@@ -0,0 +1,295 @@
+//! 价目表
+//!
+//! 可售卖的商品（十次卡、月卡、年卡、器材租赁等）保存在独立的 [`CatalogDatabase`] 中，
+//! 每个商品带有价格和售出后对学生的影响（[`ItemEffect`]）。
+//! 通过 [`crate::QmxManager::sell`] 或带 [`crate::CashBuilder::sale`] 标记的收款售出时，
+//! 收款记录和学生的课时、会员期变化在同一次操作中完成，变化记录在 [`SaleCredit`] 中，
+//! 全额退款时自动撤回。
+//!
+//! 价目表文件不存在时使用 [`CatalogDatabase::with_defaults`] 中的默认商品。
+
+use crate::common::{Database, FileCodec, HasUid};
+use crate::error::{Error, Result};
+use crate::money::Money;
+use crate::student::{Class, ClassPolicyTable, Student};
+use chrono::{DateTime, Months, Utc};
+use log::debug;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// 价目表的默认保存路径
+pub const CATALOG_DATABASE_PATH: &str = "./data/catalog_database.json";
+
+/// 商品售出后对学生的影响
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub enum ItemEffect {
+    /// 增加课时；学生不在 `class` 班级时先切换班级，原有课时不清零
+    Lessons { class: Class, count: u32 },
+    /// 延长会员期并切换到 `class` 班级
+    ///
+    /// 会员未到期时从原结束时间顺延，已到期或没有会员期时从售出时间起算。
+    Membership { class: Class, months: u32 },
+    /// 不影响学生，如器材租赁
+    None,
+}
+
+/// 可售卖的商品
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct CatalogItem {
+    /// 商品序号，按添加顺序递增
+    pub uid: u64,
+    pub name: String,
+    pub price: Money,
+    pub effect: ItemEffect,
+}
+
+impl HasUid for CatalogItem {
+    fn uid(&self) -> u64 {
+        self.uid
+    }
+}
+
+impl CatalogItem {
+    /// 将商品的影响应用到学生，`now` 为售出时间，返回退款时撤回所需的信息
+    ///
+    /// 切换班级时按 `policies` 初始化课时。
+    pub fn apply_to(
+        &self,
+        student: &mut Student,
+        policies: &ClassPolicyTable,
+        now: DateTime<Utc>,
+    ) -> Result<SaleCredit> {
+        let mut credit = SaleCredit {
+            item_id: self.uid,
+            lessons: 0,
+            previous_membership: None,
+            reversed: false,
+        };
+        match &self.effect {
+            ItemEffect::Lessons { class, count } => {
+                if student.class() != class {
+                    student.set_class(class.clone());
+                }
+                student.grant_lessons_with_policy(*count, policies)?;
+                credit.lessons = *count;
+            }
+            ItemEffect::Membership { class, months } => {
+                if student.class() != class {
+                    student.set_class_with_policy(class.clone(), policies);
+                }
+                let previous = MembershipPeriod {
+                    start: student.membership_start_date(),
+                    end: student.membership_end_date(),
+                };
+                let (start, from) = match previous.end {
+                    Some(end) if end > now => (previous.start.unwrap_or(now), end),
+                    _ => (now, now),
+                };
+                let end = from
+                    .checked_add_months(Months::new(*months))
+                    .ok_or_else(|| {
+                        Error::InvalidInput(format!("会员期超出范围: {} 个月", months))
+                    })?;
+                student.set_membership_dates(Some(start), Some(end));
+                credit.previous_membership = Some(previous);
+            }
+            ItemEffect::None => {}
+        }
+        Ok(credit)
+    }
+}
+
+/// 会员期
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct MembershipPeriod {
+    pub start: Option<DateTime<Utc>>,
+    pub end: Option<DateTime<Utc>>,
+}
+
+/// 售卖给学生带来的变化
+///
+/// 保存在收款记录的 [`crate::cash::Cash::sale`] 中，收款全额退款时据此撤回。
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct SaleCredit {
+    /// 售出的商品序号
+    pub item_id: u64,
+    /// 增加的课时数
+    #[serde(default)]
+    pub lessons: u32,
+    /// 会员类商品售出前的会员期
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub previous_membership: Option<MembershipPeriod>,
+    /// 是否已因退款撤回
+    #[serde(default)]
+    pub reversed: bool,
+}
+
+impl SaleCredit {
+    /// 是否修改过学生
+    pub fn affects_student(&self) -> bool {
+        self.lessons > 0 || self.previous_membership.is_some()
+    }
+
+    /// 撤回售卖带来的变化：扣回课时（已消耗的部分扣到 0 为止），恢复售出前的会员期
+    pub fn reverse(&mut self, student: &mut Student) {
+        if self.lessons > 0
+            && let Some(left) = student.lesson_left()
+        {
+            student.set_lesson_left(left.saturating_sub(self.lessons));
+        }
+        if let Some(previous) = self.previous_membership {
+            student.set_membership_dates(previous.start, previous.end);
+        }
+        self.reversed = true;
+    }
+}
+
+/// 价目表
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct CatalogDatabase {
+    pub catalog_data: BTreeMap<u64, CatalogItem>,
+}
+
+impl Default for CatalogDatabase {
+    fn default() -> Self {
+        Self::with_defaults()
+    }
+}
+
+impl Database<CatalogItem> for CatalogDatabase {
+    fn data(&self) -> &BTreeMap<u64, CatalogItem> {
+        &self.catalog_data
+    }
+
+    fn data_mut(&mut self) -> &mut BTreeMap<u64, CatalogItem> {
+        &mut self.catalog_data
+    }
+
+    fn default_path(&self) -> &'static str {
+        CATALOG_DATABASE_PATH
+    }
+
+    fn type_name(&self) -> &'static str {
+        "商品"
+    }
+
+    fn static_type_name() -> &'static str {
+        "商品"
+    }
+
+    fn data_field() -> &'static str {
+        "catalog_data"
+    }
+
+    fn new() -> Self {
+        Self {
+            catalog_data: BTreeMap::new(),
+        }
+    }
+}
+
+impl CatalogDatabase {
+    /// 创建空价目表
+    pub fn new() -> Self {
+        <Self as Database<CatalogItem>>::new()
+    }
+
+    /// 默认价目表：十次卡、月卡、年卡和器材租赁
+    pub fn with_defaults() -> Self {
+        let mut db = Self::new();
+        let defaults = [
+            (
+                "十次卡",
+                100_000,
+                ItemEffect::Lessons {
+                    class: Class::TenTry,
+                    count: 10,
+                },
+            ),
+            (
+                "月卡",
+                80_000,
+                ItemEffect::Membership {
+                    class: Class::Month,
+                    months: 1,
+                },
+            ),
+            (
+                "年卡",
+                680_000,
+                ItemEffect::Membership {
+                    class: Class::Year,
+                    months: 12,
+                },
+            ),
+            ("器材租赁", 5_000, ItemEffect::None),
+        ];
+        for (name, price, effect) in defaults {
+            let uid = db.next_uid();
+            db.insert(CatalogItem {
+                uid,
+                name: name.to_string(),
+                price: Money::cny(price),
+                effect,
+            });
+        }
+        db
+    }
+
+    pub fn get(&self, uid: &u64) -> Option<&CatalogItem> {
+        <Self as Database<CatalogItem>>::get(self, uid)
+    }
+
+    pub fn insert(&mut self, item: CatalogItem) {
+        <Self as Database<CatalogItem>>::insert(self, item)
+    }
+
+    pub fn remove(&mut self, uid: &u64) -> Option<CatalogItem> {
+        <Self as Database<CatalogItem>>::remove(self, uid)
+    }
+
+    pub fn save_to(&self, path: &str) -> Result<()> {
+        <Self as Database<CatalogItem>>::save_to(self, path)
+    }
+
+    pub fn read_from(path: &str) -> Result<Self> {
+        <Self as Database<CatalogItem>>::read_from(path)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&u64, &CatalogItem)> + '_ {
+        <Self as Database<CatalogItem>>::iter(self)
+    }
+
+    pub fn len(&self) -> usize {
+        <Self as Database<CatalogItem>>::len(self)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        <Self as Database<CatalogItem>>::is_empty(self)
+    }
+
+    /// 从指定路径加载价目表，文件不存在时返回默认价目表
+    pub fn load_or_new(path: &str) -> Result<Self> {
+        Self::load_or_new_with(path, &FileCodec::process_default())
+    }
+
+    /// 按指定的编码方式加载价目表，文件不存在时返回默认价目表
+    pub fn load_or_new_with(path: &str, codec: &FileCodec) -> Result<Self> {
+        match <Self as Database<CatalogItem>>::read_from_with(path, codec) {
+            Err(Error::Io(e)) if e.kind() == std::io::ErrorKind::NotFound => {
+                debug!("未找到价目表文件 {}，使用默认价目表", path);
+                Ok(Self::with_defaults())
+            }
+            other => other,
+        }
+    }
+
+    /// 下一个商品序号
+    pub fn next_uid(&self) -> u64 {
+        self.catalog_data
+            .last_key_value()
+            .map_or(1, |(&last, _)| last + 1)
+    }
+}
@@ -0,0 +1,117 @@
+//! 数据文件完整性校验
+//!
+//! 保存数据库文件时，在同目录写入 `<path>.checksum` 校验文件，记录文件内容（压缩、加密后的字节）
+//! 的 CRC-32。[`crate::Database::read_from`] 加载时重新计算并比对，不一致时返回
+//! [`Error::Corrupted`]，调用方可据此从备份恢复，而不是当作文件不存在重新创建。
+//!
+//! 校验文件在数据文件替换之前写入，同时保留上一版本的校验值，
+//! 两次写入之间崩溃时新旧数据文件都能通过校验。没有校验文件（旧版本数据）时跳过校验。
+
+use crate::error::{Error, Result};
+use log::{debug, warn};
+use std::io::{Read, Write};
+use std::path::Path;
+
+/// 校验文件的扩展名
+pub const CHECKSUM_EXT: &str = "checksum";
+
+/// 数据文件对应的校验文件路径
+pub fn checksum_path(path: &str) -> String {
+    format!("{}.{}", path, CHECKSUM_EXT)
+}
+
+/// 计算文件内容的校验值，形如 `crc32:1c291ca3`
+pub fn checksum(bytes: &[u8]) -> String {
+    format_crc(crate::compression::crc32(bytes))
+}
+
+fn format_crc(crc: u32) -> String {
+    format!("crc32:{:08x}", crc)
+}
+
+/// 读取校验文件中记录的校验值，第一个为当前版本，校验文件不存在时返回 `None`
+pub fn read_checksums(path: &str) -> Result<Option<Vec<String>>> {
+    match std::fs::read_to_string(checksum_path(path)) {
+        Ok(text) => Ok(Some(
+            text.lines()
+                .map(str::trim)
+                .filter(|line| !line.is_empty())
+                .map(str::to_string)
+                .collect(),
+        )),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// 在数据文件替换之前写入新内容的校验值，并保留当前版本的校验值
+pub(crate) fn record(path: &str, bytes: &[u8]) -> Result<()> {
+    let mut sums = vec![checksum(bytes)];
+    if let Some(previous) = read_checksums(path)?.and_then(|sums| sums.into_iter().next())
+        && previous != sums[0]
+    {
+        sums.push(previous);
+    }
+    let dir = Path::new(path)
+        .parent()
+        .filter(|dir| !dir.as_os_str().is_empty())
+        .unwrap_or(Path::new("."));
+    let mut tmpfile = tempfile::NamedTempFile::new_in(dir)?;
+    tmpfile.write_all(sums.join("\n").as_bytes())?;
+    tmpfile.write_all(b"\n")?;
+    tmpfile.as_file().sync_all()?;
+    tmpfile
+        .persist(checksum_path(path))
+        .map_err(|e| Error::Other(format!("写入校验文件失败: {}", e.error)))?;
+    Ok(())
+}
+
+/// 比对实际校验值与校验文件中的记录
+pub(crate) fn verify(path: &str, actual: u32) -> Result<()> {
+    let actual = format_crc(actual);
+    let Some(expected) = read_checksums(path)? else {
+        return Ok(());
+    };
+    if expected.is_empty() {
+        warn!("校验文件 {} 为空，跳过校验", checksum_path(path));
+        return Ok(());
+    }
+    if expected.contains(&actual) {
+        debug!("数据文件 {} 校验通过", path);
+        return Ok(());
+    }
+    Err(Error::Corrupted {
+        path: path.to_string(),
+        expected: expected[0].clone(),
+        actual,
+    })
+}
+
+/// 边读边计算 CRC-32 的读取器
+pub(crate) struct HashingReader<R> {
+    inner: R,
+    hasher: crc32fast::Hasher,
+}
+
+impl<R: Read> HashingReader<R> {
+    pub fn new(inner: R) -> Self {
+        Self {
+            inner,
+            hasher: crc32fast::Hasher::new(),
+        }
+    }
+
+    /// 读完剩余内容，返回整个文件的校验值
+    pub fn finish(mut self) -> Result<u32> {
+        std::io::copy(&mut self, &mut std::io::sink())?;
+        Ok(self.hasher.finalize())
+    }
+}
+
+impl<R: Read> Read for HashingReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        self.hasher.update(&buf[..n]);
+        Ok(n)
+    }
+}
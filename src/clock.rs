@@ -0,0 +1,56 @@
+//! 可注入的时钟
+//!
+//! 所有依赖"当前时间"的逻辑（会员有效期、分期逾期、统计周期、记录创建时间）
+//! 都通过 [`Clock`] 获取时间，测试和模拟场景可以冻结或快进时间。
+
+use chrono::{DateTime, Duration, Utc};
+use log::debug;
+use std::sync::RwLock;
+
+/// 时钟 trait，提供当前 UTC 时间
+pub trait Clock: Send + Sync {
+    fn now(&self) -> DateTime<Utc>;
+}
+
+/// 系统时钟，直接返回 `Utc::now()`
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> DateTime<Utc> {
+        Utc::now()
+    }
+}
+
+/// 固定时钟，时间只在显式调用 `set`/`advance` 时变化
+#[derive(Debug)]
+pub struct FixedClock {
+    now: RwLock<DateTime<Utc>>,
+}
+
+impl FixedClock {
+    pub fn new(now: DateTime<Utc>) -> Self {
+        Self {
+            now: RwLock::new(now),
+        }
+    }
+
+    /// 将时间设置为指定时刻
+    pub fn set(&self, now: DateTime<Utc>) {
+        debug!("固定时钟设置为 {}", now);
+        *self.now.write().unwrap_or_else(|e| e.into_inner()) = now;
+    }
+
+    /// 将时间向前（或向后）推移
+    pub fn advance(&self, duration: Duration) {
+        let mut now = self.now.write().unwrap_or_else(|e| e.into_inner());
+        *now += duration;
+        debug!("固定时钟推移到 {}", *now);
+    }
+}
+
+impl Clock for FixedClock {
+    fn now(&self) -> DateTime<Utc> {
+        *self.now.read().unwrap_or_else(|e| e.into_inner())
+    }
+}
@@ -0,0 +1,147 @@
+//! 教练管理
+//!
+//! 教练信息保存在独立的 [`CoachDatabase`] 中，学生通过 [`crate::student::Student::assigned_coach`]
+//! 关联到负责的教练。教练通过 [`crate::QmxManager::create_coach`] 等方法管理，
+//! 某位教练负责的学生可用 [`crate::StudentQuery::coach`] 查询。
+
+use crate::common::{Database, HasUid};
+use crate::error::{Error, Result};
+use crate::student::Subject;
+use log::debug;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// 教练数据库的默认保存路径
+pub const COACH_DATABASE_PATH: &str = "./data/coach_database.json";
+
+/// 教练
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Coach {
+    /// 教练序号，按添加顺序递增
+    pub uid: u64,
+    pub name: String,
+    pub phone: Option<String>,
+    /// 可以执教的科目
+    pub subjects: Vec<Subject>,
+}
+
+impl HasUid for Coach {
+    fn uid(&self) -> u64 {
+        self.uid
+    }
+}
+
+impl Coach {
+    /// 是否可以执教指定科目
+    pub fn teaches(&self, subject: &Subject) -> bool {
+        self.subjects.contains(subject)
+    }
+}
+
+/// 教练数据库
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct CoachDatabase {
+    pub coach_data: BTreeMap<u64, Coach>,
+}
+
+impl Default for CoachDatabase {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Database<Coach> for CoachDatabase {
+    fn data(&self) -> &BTreeMap<u64, Coach> {
+        &self.coach_data
+    }
+
+    fn data_mut(&mut self) -> &mut BTreeMap<u64, Coach> {
+        &mut self.coach_data
+    }
+
+    fn default_path(&self) -> &'static str {
+        COACH_DATABASE_PATH
+    }
+
+    fn type_name(&self) -> &'static str {
+        "教练"
+    }
+
+    fn static_type_name() -> &'static str {
+        "教练"
+    }
+
+    fn data_field() -> &'static str {
+        "coach_data"
+    }
+
+    fn new() -> Self {
+        Self {
+            coach_data: BTreeMap::new(),
+        }
+    }
+}
+
+impl CoachDatabase {
+    pub fn new() -> Self {
+        <Self as Database<Coach>>::new()
+    }
+
+    pub fn get(&self, uid: &u64) -> Option<&Coach> {
+        <Self as Database<Coach>>::get(self, uid)
+    }
+
+    pub fn insert(&mut self, coach: Coach) {
+        <Self as Database<Coach>>::insert(self, coach)
+    }
+
+    pub fn remove(&mut self, uid: &u64) -> Option<Coach> {
+        <Self as Database<Coach>>::remove(self, uid)
+    }
+
+    pub fn save_to(&self, path: &str) -> Result<()> {
+        <Self as Database<Coach>>::save_to(self, path)
+    }
+
+    pub fn read_from(path: &str) -> Result<Self> {
+        <Self as Database<Coach>>::read_from(path)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&u64, &Coach)> + '_ {
+        <Self as Database<Coach>>::iter(self)
+    }
+
+    pub fn len(&self) -> usize {
+        <Self as Database<Coach>>::len(self)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        <Self as Database<Coach>>::is_empty(self)
+    }
+
+    /// 从指定路径加载教练数据库，文件不存在时返回空数据库
+    pub fn load_or_new(path: &str) -> Result<Self> {
+        match Self::read_from(path) {
+            Err(Error::Io(e)) if e.kind() == std::io::ErrorKind::NotFound => {
+                debug!("未找到教练数据库文件 {}，使用空数据库", path);
+                Ok(Self::new())
+            }
+            other => other,
+        }
+    }
+
+    /// 下一个教练序号
+    pub fn next_uid(&self) -> u64 {
+        self.coach_data
+            .last_key_value()
+            .map_or(1, |(&last, _)| last + 1)
+    }
+
+    /// 获取可以执教指定科目的全部教练
+    pub fn for_subject(&self, subject: &Subject) -> Vec<&Coach> {
+        self.coach_data
+            .values()
+            .filter(|coach| coach.teaches(subject))
+            .collect()
+    }
+}
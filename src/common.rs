@@ -1,9 +1,71 @@
 use crate::error::{Result, Error};
-use log::{debug, info};
-use serde::{Serialize, de::DeserializeOwned};
-use std::collections::BTreeMap;
+use chrono::{DateTime, Utc};
+use log::{debug, info, warn};
+use serde::de::{DeserializeOwned, DeserializeSeed};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
 use std::fs::File;
-use std::io::{BufReader, BufWriter, Write};
+use std::io::{BufReader, BufWriter, Cursor, Read, Write};
+use std::marker::PhantomData;
+
+/// 数据文件的编码方式
+///
+/// 包括加密密钥和压缩方式。[`crate::QmxManager`] 持有自己的编码方式，数据文件、
+/// 预写日志和备份都按它读写，不同管理器之间互不影响。[`Database::save_to`] 和
+/// [`Database::read_from`] 使用进程默认值，见 [`FileCodec::process_default`]。
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FileCodec {
+    /// 加密密钥，`None` 表示不加密
+    pub key: Option<crate::encryption::EncryptionKey>,
+    /// 压缩方式
+    pub compression: crate::compression::Compression,
+}
+
+impl FileCodec {
+    /// 不加密、不压缩
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_key(mut self, key: Option<crate::encryption::EncryptionKey>) -> Self {
+        self.key = key;
+        self
+    }
+
+    pub fn with_compression(mut self, compression: crate::compression::Compression) -> Self {
+        self.compression = compression;
+        self
+    }
+
+    /// 进程默认的编码方式，见 [`crate::set_encryption_key`] 和 [`crate::set_compression`]
+    pub fn process_default() -> Self {
+        Self {
+            key: crate::encryption::encryption_key(),
+            compression: crate::compression::compression(),
+        }
+    }
+
+    /// 是否加密
+    pub fn is_encrypted(&self) -> bool {
+        self.key.is_some()
+    }
+
+    /// 编码写入文件的内容：先压缩再加密
+    pub(crate) fn encode(&self, plaintext: Vec<u8>) -> Result<Vec<u8>> {
+        let compressed = crate::compression::compress(plaintext, self.compression)?;
+        crate::encryption::seal(compressed, self.key.as_ref())
+    }
+
+    /// 解密数据，未加密的数据原样返回
+    pub(crate) fn open(&self, data: Vec<u8>) -> Result<Vec<u8>> {
+        crate::encryption::open(data, self.key.as_ref())
+    }
+
+    /// 解码读出的文件内容：先解密再解压
+    pub(crate) fn decode(&self, data: Vec<u8>) -> Result<Vec<u8>> {
+        crate::compression::decompress(self.open(data)?)
+    }
+}
 
 /// 通用数据库trait，定义所有数据库的公共操作
 pub trait Database<T>
@@ -25,6 +87,12 @@ where
     /// 创建新的空数据库
     fn new() -> Self;
 
+    /// 记录 `uid` 插入、修改或删除后更新二级索引，没有索引的数据库无需实现
+    fn reindex(&mut self, _uid: u64) {}
+
+    /// 按全部记录重建二级索引，直接修改 [`Database::data_mut`] 后需要调用
+    fn rebuild_indexes(&mut self) {}
+
     /// 插入记录
     fn insert(&mut self, item: T)
     where
@@ -33,6 +101,7 @@ where
         let uid = item.uid();
         info!("插入{}记录，UID: {}", self.type_name(), uid);
         self.data_mut().insert(uid, item);
+        self.reindex(uid);
     }
 
     /// 批量插入记录
@@ -45,6 +114,7 @@ where
             let uid = item.uid();
             info!("批量插入{}记录，UID: {}", self.type_name(), uid);
             self.data_mut().insert(uid, item);
+            self.reindex(uid);
             inserted_count += 1;
         }
         info!("批量插入 {} 个{}记录", inserted_count, self.type_name());
@@ -58,11 +128,14 @@ where
     {
         let mut updated_count = 0;
         for &uid in uids {
-            if let Some(item) = self.data_mut().get_mut(&uid) {
-                if update_fn(item) {
-                    info!("批量更新{}记录，UID: {}", self.type_name(), uid);
-                    updated_count += 1;
-                }
+            let Some(item) = self.data_mut().get_mut(&uid) else {
+                continue;
+            };
+            let updated = update_fn(item);
+            self.reindex(uid);
+            if updated {
+                info!("批量更新{}记录，UID: {}", self.type_name(), uid);
+                updated_count += 1;
             }
         }
         info!("批量更新 {} 个{}记录", updated_count, self.type_name());
@@ -77,6 +150,7 @@ where
     /// 删除记录
     fn remove(&mut self, uid: &u64) -> Option<T> {
         let removed = self.data_mut().remove(uid);
+        self.reindex(*uid);
         if removed.is_some() {
             info!("成功删除{}记录，UID: {}", self.type_name(), uid);
         } else {
@@ -90,6 +164,7 @@ where
         let mut removed_count = 0;
         for &uid in uids {
             if self.data_mut().remove(&uid).is_some() {
+                self.reindex(uid);
                 removed_count += 1;
             }
         }
@@ -134,12 +209,15 @@ where
     where
         Self: DeserializeOwned,
     {
-        serde_json::from_str(json).map_err(|e| Error::SerdeJson(e))
+        serde_json::from_str(json).map_err(Error::SerdeJson)
     }
 
     /// 获取静态类型名称（用于错误信息）
     fn static_type_name() -> &'static str;
 
+    /// 数据文件中保存记录的字段名，容错加载时据此找到记录表
+    fn data_field() -> &'static str;
+
     /// 保存到默认路径
     fn save(&self) -> Result<()>
     where
@@ -156,71 +234,532 @@ where
         info!("正在简单保存{}数据库到 {}", self.type_name(), path);
 
         // 确保父目录存在
-        if let Some(parent) = std::path::Path::new(path).parent() {
-            if !parent.exists() {
-                std::fs::create_dir_all(parent).map_err(Error::from)?;
-            }
+        if let Some(parent) = std::path::Path::new(path).parent()
+            && !parent.exists()
+        {
+            std::fs::create_dir_all(parent).map_err(Error::from)?;
         }
 
-        let file = File::create(path).map_err(Error::from)?;
-        let writer = BufWriter::new(file);
-
-        serde_json::to_writer(writer, self).map_err(Error::from)?;
+        let bytes = FileCodec::process_default().encode(serde_json::to_vec(self)?)?;
+        let mut writer = BufWriter::new(File::create(path).map_err(Error::from)?);
+        writer.write_all(&bytes).map_err(Error::from)?;
+        writer.flush().map_err(Error::from)?;
+        crate::checksum::record(path, &bytes)?;
 
         debug!("成功简单保存{}数据库到 {}", self.type_name(), path);
         Ok(())
     }
 
     /// 保存到指定路径（原子操作）
+    ///
+    /// 保存成功后同时写入 `<path>.bak` 副本，数据文件损坏时用于恢复，见 [`crate::recovery`]。
     fn save_to(&self, path: &str) -> Result<()>
     where
         Self: Serialize,
     {
-        info!("正在保存{}数据库到 {}", self.type_name(), path);
+        self.save_to_with(path, &FileCodec::process_default())
+    }
 
-        // 确保父目录存在
-        if let Some(parent) = std::path::Path::new(path).parent() {
-            if !parent.exists() {
-                std::fs::create_dir_all(parent).map_err(Error::from)?;
+    /// 按指定的编码方式原子保存到指定路径
+    fn save_to_with(&self, path: &str, codec: &FileCodec) -> Result<()>
+    where
+        Self: Serialize,
+    {
+        save_atomic(self, path, true, codec)
+    }
+
+    /// 从指定路径读取
+    ///
+    /// 文件内容与校验文件记录的不一致时返回 [`Error::Corrupted`]，见 [`crate::checksum`]。
+    fn read_from(path: &str) -> Result<Self>
+    where
+        Self: DeserializeOwned,
+    {
+        Self::read_from_with(path, &FileCodec::process_default())
+    }
+
+    /// 按指定的编码方式从指定路径读取
+    fn read_from_with(path: &str, codec: &FileCodec) -> Result<Self>
+    where
+        Self: DeserializeOwned,
+    {
+        info!("从 {} 加载{}数据库", path, Self::static_type_name());
+        read_json_seed(path, codec, PhantomData::<Self>)
+    }
+
+    /// 按指定的编码方式从指定路径读取，文件不存在时返回空数据库
+    fn load_or_new_with(path: &str, codec: &FileCodec) -> Result<Self>
+    where
+        Self: Sized + DeserializeOwned,
+    {
+        match Self::read_from_with(path, codec) {
+            Err(Error::Io(e)) if e.kind() == std::io::ErrorKind::NotFound => {
+                debug!(
+                    "未找到{}文件 {}，使用空数据库",
+                    Self::static_type_name(),
+                    path
+                );
+                Ok(Self::new())
             }
+            other => other,
         }
+    }
 
-        let mut tmpfile = tempfile::NamedTempFile::new_in(
-            std::path::Path::new(path)
-                .parent()
-                .ok_or_else(|| Error::InvalidInput(format!("无效的保存路径: {}", path)))?,
-        )?;
+    /// 从指定路径容错加载
+    ///
+    /// 文件整体无法解析时逐条解析记录：可读的记录照常加载，
+    /// 无法解析的片段写入 `<path>.corrupt` 隔离文件，并在报告中说明丢失情况。
+    fn salvage_from(path: &str) -> Result<(Self, SalvageReport)>
+    where
+        Self: Sized + DeserializeOwned,
+    {
+        Self::salvage_from_with(path, &FileCodec::process_default())
+    }
 
-        serde_json::to_writer(&mut tmpfile, self).map_err(Error::from)?;
+    /// 按指定的编码方式容错加载，见 [`Database::salvage_from`]
+    fn salvage_from_with(path: &str, codec: &FileCodec) -> Result<(Self, SalvageReport)>
+    where
+        Self: Sized + DeserializeOwned,
+    {
+        info!("容错加载{}数据库: {}", Self::static_type_name(), path);
+        let bytes = codec.decode(std::fs::read(path)?)?;
+        let text = String::from_utf8(bytes)
+            .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))?;
+
+        if let Ok(db) = serde_json::from_str::<Self>(&text) {
+            let report = SalvageReport {
+                recovered: db.len(),
+                ..SalvageReport::default()
+            };
+            return Ok((db, report));
+        }
 
-        tmpfile.flush().map_err(Error::from)?;
-        tmpfile.as_file().sync_all().map_err(Error::from)?;
+        let scanned = scan_data_file(&text, Self::data_field());
+        let mut fragments = scanned.fragments;
+        let mut report = SalvageReport::default();
+        for fragment in &fragments {
+            report.errors.push(format!(
+                "无法切分的片段（{} 字节），文件可能被截断",
+                fragment.len()
+            ));
+        }
 
-        let target_path = std::path::Path::new(path);
-        if let Some(dir) = target_path.parent() {
-            if let Ok(dir_fd) = std::fs::File::open(dir) {
-                let _ = dir_fd.sync_all();
+        // 记录表之外的字段（如现金数据库的 plans、next_uid）按字段名逐个恢复，
+        // 能与已恢复的字段一起解析的才保留
+        let mut known = serde_json::Map::new();
+        known.insert(
+            Self::data_field().to_string(),
+            serde_json::Value::Object(serde_json::Map::new()),
+        );
+        for (name, raw) in scanned.fields {
+            let parsed = serde_json::from_str::<serde_json::Value>(&raw).and_then(|value| {
+                let mut candidate = known.clone();
+                candidate.insert(name.clone(), value);
+                serde_json::from_value::<Self>(serde_json::Value::Object(candidate.clone()))
+                    .map(|_| candidate)
+            });
+            match parsed {
+                Ok(candidate) => known = candidate,
+                Err(e) => {
+                    let reason = format!("字段 {} 解析失败: {}", name, e);
+                    warn!("{}", reason);
+                    report.errors.push(reason);
+                    fragments.push(format!("\"{}\":{}", name, raw));
+                }
+            }
+        }
+        let mut db = serde_json::from_value::<Self>(serde_json::Value::Object(known))
+            .unwrap_or_else(|_| Self::new());
+
+        for (key, raw) in scanned.records {
+            let parsed = key
+                .parse::<u64>()
+                .map_err(|e| format!("无效的UID '{}': {}", key, e))
+                .and_then(|uid| {
+                    serde_json::from_str::<T>(&raw)
+                        .map(|item| (uid, item))
+                        .map_err(|e| format!("记录 {} 解析失败: {}", key, e))
+                });
+            match parsed {
+                Ok((uid, item)) => {
+                    db.data_mut().insert(uid, item);
+                    report.recovered += 1;
+                }
+                Err(reason) => {
+                    warn!("{}", reason);
+                    report.errors.push(reason);
+                    fragments.push(format!("\"{}\":{}", key, raw));
+                }
             }
         }
 
-        tmpfile
-            .persist(path)
-            .map_err(|e| Error::Other(format!("持久化临时文件失败: {}", e.error)))?;
+        db.rebuild_indexes();
+
+        if !fragments.is_empty() {
+            report.lost = fragments.len();
+            let quarantine_path = format!("{}.corrupt", path);
+            std::fs::write(&quarantine_path, fragments.join("\n"))?;
+            warn!(
+                "{}数据库有 {} 个片段无法恢复，已隔离到 {}",
+                Self::static_type_name(),
+                report.lost,
+                quarantine_path
+            );
+            report.quarantine_path = Some(quarantine_path);
+        }
 
-        debug!("成功原子保存{}数据库到 {}", self.type_name(), path);
+        info!(
+            "容错加载{}数据库完成: 恢复 {} 条，丢失 {} 个片段",
+            Self::static_type_name(),
+            report.recovered,
+            report.lost
+        );
+        Ok((db, report))
+    }
+}
 
-        Ok(())
+/// 先写入同目录下的临时文件再重命名，写入中途崩溃不会留下不完整的文件
+pub(crate) fn write_atomic(path: &std::path::Path, bytes: &[u8]) -> Result<()> {
+    let dir = path
+        .parent()
+        .filter(|dir| !dir.as_os_str().is_empty())
+        .unwrap_or(std::path::Path::new("."));
+    std::fs::create_dir_all(dir)?;
+    let mut tmpfile = tempfile::NamedTempFile::new_in(dir)?;
+    tmpfile.write_all(bytes)?;
+    tmpfile.as_file().sync_all()?;
+    tmpfile
+        .persist(path)
+        .map_err(|e| Error::Other(format!("写入 {} 失败: {}", path.display(), e.error)))?;
+    Ok(())
+}
+
+/// 原子保存到指定路径，`keep_bak` 为真时同时写入 `.bak` 副本
+///
+/// 写入备份目录时不需要副本。
+pub(crate) fn save_atomic<D, T>(db: &D, path: &str, keep_bak: bool, codec: &FileCodec) -> Result<()>
+where
+    D: Database<T> + Serialize + ?Sized,
+    T: Serialize + DeserializeOwned + Clone,
+{
+    info!("正在保存{}数据库到 {}", db.type_name(), path);
+
+    // 确保父目录存在
+    if let Some(parent) = std::path::Path::new(path).parent()
+        && !parent.exists()
+    {
+        std::fs::create_dir_all(parent).map_err(Error::from)?;
     }
 
-    /// 从指定路径读取
-    fn read_from(path: &str) -> Result<Self>
-    where
-        Self: DeserializeOwned,
+    let mut tmpfile = tempfile::NamedTempFile::new_in(
+        std::path::Path::new(path)
+            .parent()
+            .ok_or_else(|| Error::InvalidInput(format!("无效的保存路径: {}", path)))?,
+    )?;
+
+    // 按配置先压缩再加密
+    let bytes = codec.encode(serde_json::to_vec(db)?)?;
+    tmpfile.write_all(&bytes).map_err(Error::from)?;
+
+    tmpfile.flush().map_err(Error::from)?;
+    tmpfile.as_file().sync_all().map_err(Error::from)?;
+
+    let target_path = std::path::Path::new(path);
+    if let Some(dir) = target_path.parent()
+        && let Ok(dir_fd) = std::fs::File::open(dir)
     {
-        info!("从 {} 加载{}数据库", path, Self::static_type_name());
-        let file = File::open(path).map_err(Error::from)?;
-        let reader = BufReader::new(file);
-        serde_json::from_reader(reader).map_err(Error::from)
+        let _ = dir_fd.sync_all();
+    }
+
+    // 校验文件先于数据文件替换，两者之间崩溃时新旧文件都能通过校验
+    crate::checksum::record(path, &bytes)?;
+    tmpfile
+        .persist(path)
+        .map_err(|e| Error::Other(format!("持久化临时文件失败: {}", e.error)))?;
+
+    if keep_bak {
+        crate::recovery::write_bak(path, &bytes);
+    }
+
+    debug!("成功原子保存{}数据库到 {}", db.type_name(), path);
+
+    Ok(())
+}
+
+/// 数据文件头的最大长度（加密文件头 8 字节，gzip 文件头 2 字节，zstd 帧头 4 字节）
+const FILE_HEADER_LEN: u64 = 8;
+
+/// 从数据文件反序列化
+///
+/// 未压缩、未加密的文件边读边解析，不把整个文件读入内存；
+/// 压缩或加密的文件需要先完整读入再解压、解密。
+/// 两种情况都会比对校验文件，校验失败优先于解压和解析错误返回。
+pub(crate) fn read_json_seed<S, T>(path: &str, codec: &FileCodec, seed: S) -> Result<T>
+where
+    S: for<'de> DeserializeSeed<'de, Value = T>,
+{
+    let mut file = crate::checksum::HashingReader::new(BufReader::new(File::open(path)?));
+    let mut header = Vec::new();
+    (&mut file).take(FILE_HEADER_LEN).read_to_end(&mut header)?;
+
+    if crate::encryption::is_encrypted(&header) || crate::compression::is_compressed(&header) {
+        let mut bytes = header;
+        file.read_to_end(&mut bytes)?;
+        let actual = file.finish()?;
+        // 加密文件自带认证标签，篡改时由解密先报告
+        let bytes = codec.open(bytes)?;
+        crate::checksum::verify(path, actual)?;
+        let bytes = crate::compression::decompress(bytes)?;
+        let mut deserializer = serde_json::Deserializer::from_slice(&bytes);
+        let value = seed.deserialize(&mut deserializer)?;
+        deserializer.end()?;
+        return Ok(value);
+    }
+
+    let mut reader = Cursor::new(header).chain(&mut file);
+    let mut deserializer = serde_json::Deserializer::from_reader(&mut reader);
+    let parsed = seed
+        .deserialize(&mut deserializer)
+        .and_then(|value| deserializer.end().map(|()| value));
+    crate::checksum::verify(path, file.finish()?)?;
+    Ok(parsed?)
+}
+
+/// 容错加载结果报告
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SalvageReport {
+    /// 成功恢复的记录数
+    pub recovered: usize,
+    /// 无法恢复的片段数
+    pub lost: usize,
+    /// 隔离文件路径（没有丢失时为 None）
+    pub quarantine_path: Option<String>,
+    /// 每个丢失片段的原因
+    pub errors: Vec<String>,
+}
+
+/// 切分后的数据文件
+#[derive(Default)]
+struct ScannedFile {
+    /// 记录表中可切分出的 `(键, 原始JSON)`
+    records: Vec<(String, String)>,
+    /// 记录表之外的顶层字段 `(字段名, 原始JSON)`
+    fields: Vec<(String, String)>,
+    /// 无法切分的剩余片段
+    fragments: Vec<String>,
+}
+
+/// 扫描数据文件的顶层对象
+///
+/// 名为 `data_field` 的记录表按记录逐条切分，其余字段按字段名整体切出，
+/// 不依赖字段顺序。文件被截断时，截断处之后的内容作为无法切分的片段返回。
+fn scan_data_file(text: &str, data_field: &str) -> ScannedFile {
+    let bytes = text.as_bytes();
+    let mut scanned = ScannedFile::default();
+
+    let mut i = skip_ws(bytes, 0);
+    if bytes.get(i) != Some(&b'{') {
+        if !text.trim().is_empty() {
+            scanned.fragments.push(text.to_string());
+        }
+        return scanned;
+    }
+    i += 1;
+
+    loop {
+        i = skip_separators(bytes, i);
+        if i >= bytes.len() || bytes[i] == b'}' {
+            break;
+        }
+        let member_start = i;
+        let member = scan_string(bytes, i).and_then(|key_end| {
+            let key = serde_json::from_str::<String>(&text[i..key_end]).ok()?;
+            let colon = skip_ws(bytes, key_end);
+            (bytes.get(colon) == Some(&b':')).then_some(())?;
+            Some((key, skip_ws(bytes, colon + 1)))
+        });
+        let Some((key, value_start)) = member else {
+            scanned.fragments.push(text[member_start..].to_string());
+            break;
+        };
+
+        if key == data_field && bytes.get(value_start) == Some(&b'{') {
+            match scan_map_entries(text, value_start + 1, &mut scanned) {
+                Some(end) => i = end,
+                None => break,
+            }
+        } else {
+            match scan_value(bytes, value_start) {
+                Some(end) => {
+                    scanned
+                        .fields
+                        .push((key, text[value_start..end].to_string()));
+                    i = end;
+                }
+                None => {
+                    scanned.fragments.push(text[member_start..].to_string());
+                    break;
+                }
+            }
+        }
+    }
+
+    scanned
+}
+
+/// 逐条切分从 `i` 开始的记录表，返回记录表结束之后的位置；记录表被截断时返回 None
+fn scan_map_entries(text: &str, mut i: usize, scanned: &mut ScannedFile) -> Option<usize> {
+    let bytes = text.as_bytes();
+    loop {
+        i = skip_separators(bytes, i);
+        if i >= bytes.len() {
+            return None;
+        }
+        if bytes[i] == b'}' {
+            return Some(i + 1);
+        }
+        let entry_start = i;
+        let parsed = scan_string(bytes, i).and_then(|key_end| {
+            let key = serde_json::from_str::<String>(&text[i..key_end]).ok()?;
+            let colon = skip_ws(bytes, key_end);
+            (bytes.get(colon) == Some(&b':')).then_some(())?;
+            let value_start = skip_ws(bytes, colon + 1);
+            let value_end = scan_value(bytes, value_start)?;
+            Some((key, value_start, value_end))
+        });
+        match parsed {
+            Some((key, value_start, value_end)) => {
+                scanned
+                    .records
+                    .push((key, text[value_start..value_end].to_string()));
+                i = value_end;
+            }
+            None => {
+                scanned.fragments.push(text[entry_start..].to_string());
+                return None;
+            }
+        }
+    }
+}
+
+fn skip_separators(bytes: &[u8], mut i: usize) -> usize {
+    while i < bytes.len() && (bytes[i].is_ascii_whitespace() || bytes[i] == b',') {
+        i += 1;
+    }
+    i
+}
+
+fn skip_ws(bytes: &[u8], mut i: usize) -> usize {
+    while i < bytes.len() && bytes[i].is_ascii_whitespace() {
+        i += 1;
+    }
+    i
+}
+
+/// 扫描从 `i` 开始的字符串字面量，返回结束引号之后的位置
+fn scan_string(bytes: &[u8], i: usize) -> Option<usize> {
+    if bytes.get(i) != Some(&b'"') {
+        return None;
+    }
+    let mut j = i + 1;
+    while j < bytes.len() {
+        match bytes[j] {
+            b'\\' => j += 2,
+            b'"' => return Some(j + 1),
+            _ => j += 1,
+        }
+    }
+    None
+}
+
+/// 扫描从 `i` 开始的一个 JSON 值，返回值结束之后的位置；值被截断时返回 None
+fn scan_value(bytes: &[u8], i: usize) -> Option<usize> {
+    match bytes.get(i)? {
+        b'"' => scan_string(bytes, i),
+        b'{' | b'[' => {
+            let mut depth = 0usize;
+            let mut j = i;
+            while j < bytes.len() {
+                match bytes[j] {
+                    b'"' => {
+                        j = scan_string(bytes, j)?;
+                        continue;
+                    }
+                    b'{' | b'[' => depth += 1,
+                    b'}' | b']' => {
+                        depth -= 1;
+                        if depth == 0 {
+                            return Some(j + 1);
+                        }
+                    }
+                    _ => {}
+                }
+                j += 1;
+            }
+            None
+        }
+        _ => {
+            let mut j = i;
+            while j < bytes.len() && !matches!(bytes[j], b',' | b'}') {
+                j += 1;
+            }
+            (j < bytes.len()).then_some(j)
+        }
+    }
+}
+
+/// 从键到记录 UID 的二级索引
+///
+/// 同时保存每条记录当前的键，记录修改后用 [`SecondaryIndex::set`] 替换即可，无需知道旧值。
+#[derive(Debug, Clone)]
+pub struct SecondaryIndex<K> {
+    entries: BTreeMap<K, BTreeSet<u64>>,
+    keys: BTreeMap<u64, Vec<K>>,
+}
+
+impl<K> Default for SecondaryIndex<K> {
+    fn default() -> Self {
+        Self {
+            entries: BTreeMap::new(),
+            keys: BTreeMap::new(),
+        }
+    }
+}
+
+impl<K: Ord + Clone> SecondaryIndex<K> {
+    /// 把记录 `uid` 的键替换为 `keys`
+    pub fn set(&mut self, uid: u64, keys: impl IntoIterator<Item = K>) {
+        self.remove(uid);
+        let keys: Vec<K> = keys.into_iter().collect();
+        if keys.is_empty() {
+            return;
+        }
+        for key in &keys {
+            self.entries.entry(key.clone()).or_default().insert(uid);
+        }
+        self.keys.insert(uid, keys);
+    }
+
+    /// 从索引中移除记录 `uid`
+    pub fn remove(&mut self, uid: u64) {
+        for key in self.keys.remove(&uid).unwrap_or_default() {
+            if let Some(uids) = self.entries.get_mut(&key) {
+                uids.remove(&uid);
+                if uids.is_empty() {
+                    self.entries.remove(&key);
+                }
+            }
+        }
+    }
+
+    /// 键为 `key` 的记录 UID，按 UID 升序
+    pub fn get(&self, key: &K) -> impl Iterator<Item = u64> + '_ {
+        self.entries.get(key).into_iter().flatten().copied()
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.clear();
+        self.keys.clear();
     }
 }
 
@@ -228,3 +767,55 @@ where
 pub trait HasUid {
     fn uid(&self) -> u64;
 }
+
+/// 学生和现金记录上由机构自行定义的字段值
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub enum CustomValue {
+    Text(String),
+    Number(f64),
+    Bool(bool),
+    Date(DateTime<Utc>),
+}
+
+impl From<&str> for CustomValue {
+    fn from(value: &str) -> Self {
+        Self::Text(value.to_string())
+    }
+}
+
+impl From<String> for CustomValue {
+    fn from(value: String) -> Self {
+        Self::Text(value)
+    }
+}
+
+impl From<f64> for CustomValue {
+    fn from(value: f64) -> Self {
+        Self::Number(value)
+    }
+}
+
+impl From<i32> for CustomValue {
+    fn from(value: i32) -> Self {
+        Self::Number(value.into())
+    }
+}
+
+impl From<i64> for CustomValue {
+    fn from(value: i64) -> Self {
+        Self::Number(value as f64)
+    }
+}
+
+impl From<bool> for CustomValue {
+    fn from(value: bool) -> Self {
+        Self::Bool(value)
+    }
+}
+
+impl From<DateTime<Utc>> for CustomValue {
+    fn from(value: DateTime<Utc>) -> Self {
+        Self::Date(value)
+    }
+}
@@ -0,0 +1,116 @@
+//! 数据文件压缩
+//!
+//! 成绩数组很大时 JSON 文件可达数 MB。启用压缩后数据文件以 gzip（`flate2`）或
+//! zstd（需要启用 `zstd` 特性）格式写入，读取时根据文件头自动识别并解压，
+//! 未压缩的旧文件仍可直接读取。同时启用加密时先压缩再加密。
+//!
+//! 压缩方式属于各个管理器，通过 [`crate::QmxManagerBuilder::compression`] 或
+//! [`crate::QmxManager::with_compression`] 设置，保存在管理器的 [`FileCodec`] 中。
+//! [`set_compression`] 设置的是进程默认值，用于直接调用 [`Database::save_to`]
+//! 以及未指定压缩方式的管理器，默认不压缩。
+//!
+//! [`FileCodec`]: crate::common::FileCodec
+//! [`Database::save_to`]: crate::common::Database::save_to
+
+use crate::error::{Error, Result};
+use log::info;
+use std::io::{Read, Write};
+use std::sync::{OnceLock, RwLock};
+
+/// gzip 文件头的前两个字节
+const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
+
+/// zstd 帧头的前四个字节
+const ZSTD_MAGIC: [u8; 4] = [0x28, 0xb5, 0x2f, 0xfd];
+
+/// 数据文件的压缩方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Compression {
+    /// 不压缩（默认，与旧版本行为一致）
+    #[default]
+    None,
+    /// gzip 压缩
+    Gzip,
+    /// zstd 压缩，压缩和解压都比 gzip 快
+    #[cfg(feature = "zstd")]
+    Zstd,
+}
+
+static COMPRESSION: OnceLock<RwLock<Compression>> = OnceLock::new();
+
+fn compression_lock() -> &'static RwLock<Compression> {
+    COMPRESSION.get_or_init(|| RwLock::new(Compression::default()))
+}
+
+/// 设置进程默认的压缩方式
+///
+/// 只影响直接调用的数据库保存方法和之后创建的、未指定压缩方式的管理器。
+pub fn set_compression(compression: Compression) {
+    *compression_lock()
+        .write()
+        .unwrap_or_else(|e| e.into_inner()) = compression;
+    info!("数据文件默认压缩方式设置为 {:?}", compression);
+}
+
+/// 获取进程默认的压缩方式
+pub fn compression() -> Compression {
+    *compression_lock().read().unwrap_or_else(|e| e.into_inner())
+}
+
+/// 数据是否为压缩格式（gzip 或 zstd）
+pub fn is_compressed(data: &[u8]) -> bool {
+    data.starts_with(&GZIP_MAGIC) || data.starts_with(&ZSTD_MAGIC)
+}
+
+/// 按指定的压缩方式压缩数据
+pub(crate) fn compress(data: Vec<u8>, compression: Compression) -> Result<Vec<u8>> {
+    match compression {
+        Compression::None => Ok(data),
+        Compression::Gzip => {
+            let mut encoder = flate2::write::GzEncoder::new(
+                Vec::with_capacity(data.len() / 4),
+                flate2::Compression::default(),
+            );
+            encoder.write_all(&data)?;
+            Ok(encoder.finish()?)
+        }
+        #[cfg(feature = "zstd")]
+        Compression::Zstd => Ok(zstd::encode_all(data.as_slice(), 0)?),
+    }
+}
+
+/// 解压 gzip 或 zstd 格式的数据，未压缩的数据原样返回
+pub(crate) fn decompress(data: Vec<u8>) -> Result<Vec<u8>> {
+    if data.starts_with(&GZIP_MAGIC) {
+        let mut output = Vec::new();
+        flate2::read::GzDecoder::new(data.as_slice())
+            .read_to_end(&mut output)
+            .map_err(corrupt)?;
+        Ok(output)
+    } else if data.starts_with(&ZSTD_MAGIC) {
+        decompress_zstd(&data)
+    } else {
+        Ok(data)
+    }
+}
+
+#[cfg(feature = "zstd")]
+fn decompress_zstd(data: &[u8]) -> Result<Vec<u8>> {
+    zstd::decode_all(data).map_err(corrupt)
+}
+
+#[cfg(not(feature = "zstd"))]
+fn decompress_zstd(_data: &[u8]) -> Result<Vec<u8>> {
+    Err(Error::InvalidInput(
+        "数据文件为 zstd 压缩格式，需要启用 zstd 特性".to_string(),
+    ))
+}
+
+fn corrupt(e: std::io::Error) -> Error {
+    Error::InvalidInput(format!("压缩数据已损坏: {}", e))
+}
+
+/// CRC-32（IEEE 802.3），校验文件与 ZIP 共用
+pub(crate) fn crc32(data: &[u8]) -> u32 {
+    crc32fast::hash(data)
+}
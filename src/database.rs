@@ -1,9 +1,15 @@
 use super::cash::CashDatabase;
 use super::student::StudentDatabase;
 
+use crate::backup::{BACKUP_CASH_FILE, BACKUP_STUDENT_FILE};
+use crate::common::FileCodec;
 use crate::error::{Result, Error};
+use crate::recovery::{self, RecoveryReport, is_corruption};
+use crate::wal::{WAL_FILE, WriteAheadLog};
 use log::{debug, error, info, warn};
+use serde::de::DeserializeOwned;
 use serde::{Deserialize, Serialize};
+use std::path::Path;
 
 /// 主数据库容器
 ///
@@ -89,8 +95,8 @@ impl Database {
     /// ```
     pub fn save(&self) -> Result<()> {
         info!("开始持久化所有数据库");
-        self.student.save().map_err(Error::from)?;
-        self.cash.save().map_err(Error::from)?;
+        self.student.save()?;
+        self.cash.save()?;
         debug!("所有数据库已成功保存");
         Ok(())
     }
@@ -108,6 +114,8 @@ impl Database {
 /// # 错误
 ///
 /// 当无法创建数据目录、读取数据文件或初始化数据库时返回错误。
+/// 数据文件损坏时先尝试从 `.bak` 副本或备份目录恢复，都无法加载时原样返回错误
+/// （如 [`Error::Corrupted`]），不会当作文件不存在重新创建。
 ///
 /// # 示例
 ///
@@ -131,62 +139,114 @@ impl Database {
 /// ./data/
 /// ├── student_database.json    # 学生数据
 /// ├── cash_database.json       # 现金数据
-/// ├── uid_counter              # 学生UID计数器
-/// └── cash_uid_counter         # 现金UID计数器
+/// ├── *.json.checksum          # 数据文件的校验值
+/// ├── *.json.bak               # 上次成功保存的副本，数据文件损坏时用于恢复
+/// ├── wal.jsonl                # 预写日志，保存成功后清空
+/// ├── uid_counter              # 学生UID计数器（旧版本数据的后备）
+/// └── cash_uid_counter         # 现金UID计数器（旧版本数据的后备）
 /// ```
+///
+/// 数据库文件本身记录了下一个可用的 UID（`next_uid`），加载时计数器取两者中较大的值。
+///
+/// 预写日志中有上次崩溃前未保存的修改时，先重放这些修改并保存，见 [`crate::wal`]。
 pub fn init() -> Result<Database> {
+    init_with_report().map(|(db, _)| db)
+}
+
+/// 初始化数据库系统，并返回损坏数据文件的恢复情况
+///
+/// 与 [`init`] 相同，另外返回自动恢复的数据文件列表（没有文件损坏时为空），
+/// 恢复过程见 [`crate::recovery`]。
+pub fn init_with_report() -> Result<(Database, Vec<RecoveryReport>)> {
+    init_with_codec(&FileCodec::process_default())
+}
+
+/// 按指定的编码方式初始化数据库系统，数据文件和预写日志都使用该编码方式
+pub(crate) fn init_with_codec(codec: &FileCodec) -> Result<(Database, Vec<RecoveryReport>)> {
     info!("正在初始化运行时数据库");
     let data_dir = std::env::var("QMX_DATA_DIR").unwrap_or_else(|_| "./data".to_string());
     std::fs::create_dir_all(&data_dir).map_err(Error::from)?;
+    let backup_dir = Path::new(&data_dir).join("backups");
+    let mut reports = Vec::new();
 
-    let student_db = match StudentDatabase::read_from(&format!("{}/student_database.json", data_dir)) {
-        Ok(db) => {
-            info!("学生数据库加载成功");
-            db
+    let student_path = format!("{}/student_database.json", data_dir);
+    let student_db: StudentDatabase = load_or_create(
+        &student_path,
+        BACKUP_STUDENT_FILE,
+        &backup_dir,
+        codec,
+        &mut reports,
+    )?;
+    info!("学生数据库加载成功");
+
+    let cash_path = format!("{}/cash_database.json", data_dir);
+    let cash_db: CashDatabase = load_or_create(
+        &cash_path,
+        BACKUP_CASH_FILE,
+        &backup_dir,
+        codec,
+        &mut reports,
+    )?;
+    info!("现金数据库加载成功");
+
+    student_db.sync_uid_counter();
+    cash_db.sync_uid_counter();
+    crate::cash::sync_plan_id_counter(&cash_db);
+    let mut db = Database::new(student_db, cash_db);
+
+    // 重放上次崩溃前未保存的修改，写入数据文件后清空日志
+    let wal = WriteAheadLog::with_codec(format!("{}/{}", data_dir, WAL_FILE), codec.clone());
+    let replayed = wal.replay(&mut db)?;
+    if replayed > 0 {
+        crate::common::Database::save_to_with(&db.student, &student_path, codec)?;
+        crate::common::Database::save_to_with(&db.cash, &cash_path, codec)?;
+    }
+    if wal.replay_files()? + replayed > 0 {
+        wal.commit()?;
+    }
+    info!("运行时数据库初始化完成");
+    Ok((db, reports))
+}
+
+/// 加载数据文件：不存在时创建空数据库，损坏时尝试从副本或备份恢复
+fn load_or_create<D, T>(
+    path: &str,
+    backup_file: &str,
+    backup_dir: &Path,
+    codec: &FileCodec,
+    reports: &mut Vec<RecoveryReport>,
+) -> Result<D>
+where
+    D: crate::common::Database<T> + Serialize + DeserializeOwned,
+    T: Serialize + DeserializeOwned + Clone,
+{
+    match D::read_from_with(path, codec) {
+        Ok(db) => Ok(db),
+        Err(Error::Io(e)) if e.kind() == std::io::ErrorKind::NotFound => {
+            warn!("{}数据库文件不存在，正在创建新的数据库...", D::static_type_name());
+            let db = D::new();
+            db.save_to_with(path, codec)?;
+            Ok(db)
         }
-        Err(e) => {
-            if let Error::Io(ref io_err) = e {
-                if io_err.kind() == std::io::ErrorKind::NotFound {
-                    warn!("学生数据库文件不存在，正在创建新的数据库...");
-                    let new_db = StudentDatabase::new();
-                    new_db.save().map_err(Error::from)?;
-                    new_db
-                } else {
-                    error!("加载学生数据库失败: {}", io_err);
-                    return Err(Error::Other(format!("加载学生数据库失败: {}", io_err)));
+        Err(e) if is_corruption(&e) => {
+            error!("{}数据库文件已损坏: {}", D::static_type_name(), e);
+            match recovery::recover::<D, T>(path, backup_dir, backup_file, codec, &e)? {
+                Some((db, report)) => {
+                    reports.push(report);
+                    Ok(db)
                 }
-            } else {
-                error!("加载学生数据库失败: {e:?}");
-                return Err(Error::Other(format!("加载学生数据库失败: {e:?}")));
+                // 没有可用的副本或备份，原样返回错误
+                None => Err(e),
             }
         }
-    };
-
-    let cash_db = match CashDatabase::read_from(&format!("{}/cash_database.json", data_dir)) {
-        Ok(db) => {
-            info!("现金数据库加载成功");
-            db
-        }
         Err(e) => {
-            if let Error::Io(ref io_err) = e {
-                if io_err.kind() == std::io::ErrorKind::NotFound {
-                    warn!("现金数据库文件不存在，正在创建新的数据库...");
-                    let new_db = CashDatabase::new();
-                    new_db.save().map_err(Error::from)?;
-                    new_db
-                } else {
-                    error!("加载现金数据库失败: {}", io_err);
-                    return Err(Error::Other(format!("加载现金数据库失败: {}", io_err)));
-                }
-            } else {
-                error!("加载现金数据库失败: {e:?}");
-                return Err(Error::Other(format!("加载现金数据库失败: {e:?}")));
-            }
+            error!("加载{}数据库失败: {e:?}", D::static_type_name());
+            Err(Error::Other(format!(
+                "加载{}数据库失败: {e:?}",
+                D::static_type_name()
+            )))
         }
-    };
-
-    info!("运行时数据库初始化完成");
-    Ok(Database::new(student_db, cash_db))
+    }
 }
 
 /// 初始化数据库（测试模式，使用简单保存）
@@ -206,12 +266,16 @@ pub fn init_simple() -> Result<Database> {
                 if io_err.kind() == std::io::ErrorKind::NotFound {
                     warn!("学生数据库文件不存在，正在创建新的数据库...");
                     let new_db = StudentDatabase::new();
-                    <StudentDatabase as crate::common::Database<super::student::Student>>::save_to_simple(&new_db, &format!("{}/student_database.json", data_dir)).map_err(Error::from)?;
+                    <StudentDatabase as crate::common::Database<super::student::Student>>::save_to_simple(&new_db, &format!("{}/student_database.json", data_dir))?;
                     new_db
                 } else {
                     error!("加载学生数据库失败: {}", io_err);
                     return Err(Error::Other(format!("加载学生数据库失败: {}", io_err)));
                 }
+            } else if let Error::Corrupted { .. } = e {
+                // 原样返回，调用方据此从备份恢复
+                error!("{}", e);
+                return Err(e);
             } else {
                 error!("加载学生数据库失败: {e:?}");
                 return Err(Error::Other(format!("加载学生数据库失败: {e:?}")));
@@ -232,13 +296,16 @@ pub fn init_simple() -> Result<Database> {
                     <CashDatabase as crate::common::Database<super::cash::Cash>>::save_to_simple(
                         &new_db,
                         &format!("{}/cash_database.json", data_dir),
-                    )
-                    .map_err(Error::from)?;
+                    )?;
                     new_db
                 } else {
                     error!("加载现金数据库失败: {}", io_err);
                     return Err(Error::Other(format!("加载现金数据库失败: {}", io_err)));
                 }
+            } else if let Error::Corrupted { .. } = e {
+                // 原样返回，调用方据此从备份恢复
+                error!("{}", e);
+                return Err(e);
             } else {
                 error!("加载现金数据库失败: {e:?}");
                 return Err(Error::Other(format!("加载现金数据库失败: {e:?}")));
@@ -246,6 +313,7 @@ pub fn init_simple() -> Result<Database> {
         }
     };
 
+    crate::cash::sync_plan_id_counter(&cash_db);
     info!("运行时数据库初始化完成（测试模式）");
     Ok(Database::new(student_db, cash_db))
 }
@@ -0,0 +1,153 @@
+//! 数据文件的静态加密
+//!
+//! 设置密钥后，所有数据库文件（学生、现金、审计日志等）以 AES-256-GCM 加密写入，
+//! 读取时根据文件头自动识别并解密。未加密的旧文件仍可直接读取，下次保存时即被加密。
+//! 加解密使用 `aes-gcm` crate，随机数取自系统随机源。
+//!
+//! 密钥属于各个管理器：[`crate::QmxManagerBuilder::encryption_key`] 设置的密钥保存在
+//! 管理器的 [`FileCodec`] 中，只用于该管理器的数据文件和预写日志。
+//! [`set_encryption_key`] 设置的是进程默认密钥，用于直接调用 [`Database::save_to`]、
+//! [`Database::read_from`] 等方法，以及未指定密钥的管理器。
+//!
+//! [`FileCodec`]: crate::common::FileCodec
+//! [`Database::save_to`]: crate::common::Database::save_to
+//! [`Database::read_from`]: crate::common::Database::read_from
+
+use crate::error::{Error, Result};
+use aes_gcm::aead::AeadInPlace;
+use aes_gcm::{Aes256Gcm, KeyInit, Nonce, Tag};
+use log::{info, warn};
+use std::sync::{OnceLock, RwLock};
+
+/// 加密文件的文件头，同时作为 AES-GCM 的附加认证数据
+const MAGIC: &[u8; 8] = b"QMXENC01";
+
+const NONCE_LEN: usize = 12;
+const TAG_LEN: usize = 16;
+
+/// 密钥长度（字节）
+pub const ENCRYPTION_KEY_LEN: usize = 32;
+
+/// AES-256 密钥
+#[derive(Clone, PartialEq, Eq)]
+pub struct EncryptionKey([u8; ENCRYPTION_KEY_LEN]);
+
+impl EncryptionKey {
+    pub fn new(bytes: [u8; ENCRYPTION_KEY_LEN]) -> Self {
+        Self(bytes)
+    }
+
+    /// 从任意字节切片创建密钥，长度必须为 32 字节
+    pub fn from_slice(bytes: &[u8]) -> Result<Self> {
+        let bytes: [u8; ENCRYPTION_KEY_LEN] = bytes.try_into().map_err(|_| {
+            Error::InvalidInput(format!(
+                "密钥长度必须为 {} 字节，实际为 {} 字节",
+                ENCRYPTION_KEY_LEN,
+                bytes.len()
+            ))
+        })?;
+        Ok(Self(bytes))
+    }
+}
+
+/// 不在日志或调试输出中泄露密钥
+impl std::fmt::Debug for EncryptionKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("EncryptionKey(..)")
+    }
+}
+
+static ENCRYPTION_KEY: OnceLock<RwLock<Option<EncryptionKey>>> = OnceLock::new();
+
+fn key_lock() -> &'static RwLock<Option<EncryptionKey>> {
+    ENCRYPTION_KEY.get_or_init(|| RwLock::new(None))
+}
+
+/// 设置进程默认的加密密钥，`None` 表示不加密（默认）
+///
+/// 只影响直接调用的数据库读写方法和之后创建的、未指定密钥的管理器；
+/// 已创建的管理器和指定了密钥的管理器不受影响。
+pub fn set_encryption_key(key: Option<EncryptionKey>) {
+    let enabled = key.is_some();
+    *key_lock().write().unwrap_or_else(|e| e.into_inner()) = key;
+    if enabled {
+        info!("已启用数据文件加密");
+    } else {
+        info!("已关闭数据文件加密");
+    }
+}
+
+/// 当前是否设置了进程默认的加密密钥
+pub fn encryption_enabled() -> bool {
+    key_lock()
+        .read()
+        .unwrap_or_else(|e| e.into_inner())
+        .is_some()
+}
+
+/// 进程默认的加密密钥
+pub fn encryption_key() -> Option<EncryptionKey> {
+    key_lock().read().unwrap_or_else(|e| e.into_inner()).clone()
+}
+
+/// 数据是否为加密格式
+pub fn is_encrypted(data: &[u8]) -> bool {
+    data.starts_with(MAGIC)
+}
+
+/// 有密钥时加密数据，否则原样返回
+pub(crate) fn seal(plaintext: Vec<u8>, key: Option<&EncryptionKey>) -> Result<Vec<u8>> {
+    let Some(key) = key else {
+        return Ok(plaintext);
+    };
+    let nonce = generate_nonce()?;
+    let mut body = plaintext;
+    let tag = cipher(key)
+        .encrypt_in_place_detached(Nonce::from_slice(&nonce), MAGIC, &mut body)
+        .map_err(|_| Error::Encryption("加密失败".to_string()))?;
+    let mut out = Vec::with_capacity(MAGIC.len() + NONCE_LEN + body.len() + TAG_LEN);
+    out.extend_from_slice(MAGIC);
+    out.extend_from_slice(&nonce);
+    out.extend_from_slice(&body);
+    out.extend_from_slice(&tag);
+    Ok(out)
+}
+
+/// 解密加密格式的数据，未加密的数据原样返回
+pub(crate) fn open(data: Vec<u8>, key: Option<&EncryptionKey>) -> Result<Vec<u8>> {
+    if !is_encrypted(&data) {
+        return Ok(data);
+    }
+    let key =
+        key.ok_or_else(|| Error::Encryption("数据文件已加密，但未设置加密密钥".to_string()))?;
+    if data.len() < MAGIC.len() + NONCE_LEN + TAG_LEN {
+        return Err(Error::Encryption("加密数据文件不完整".to_string()));
+    }
+    let (nonce, rest) = data[MAGIC.len()..].split_at(NONCE_LEN);
+    let (body, tag) = rest.split_at(rest.len() - TAG_LEN);
+    let mut body = body.to_vec();
+    cipher(key)
+        .decrypt_in_place_detached(
+            Nonce::from_slice(nonce),
+            MAGIC,
+            &mut body,
+            Tag::from_slice(tag),
+        )
+        .map_err(|_| {
+            warn!("数据文件解密失败");
+            Error::Encryption("解密失败：密钥错误或文件已被篡改".to_string())
+        })?;
+    Ok(body)
+}
+
+fn cipher(key: &EncryptionKey) -> Aes256Gcm {
+    Aes256Gcm::new(&key.0.into())
+}
+
+/// 从系统随机源生成随机数，随机源不可用时返回错误而不是退化为可预测的值
+fn generate_nonce() -> Result<[u8; NONCE_LEN]> {
+    let mut nonce = [0u8; NONCE_LEN];
+    getrandom::fill(&mut nonce)
+        .map_err(|e| Error::Encryption(format!("无法获取系统随机数: {}", e)))?;
+    Ok(nonce)
+}
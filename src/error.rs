@@ -1,3 +1,5 @@
+use crate::money::Currency;
+use crate::validation::Violation;
 use thiserror::Error;
 
 #[derive(Debug, Error)]
@@ -23,10 +25,104 @@ pub enum Error {
     #[error("状态错误: {0}")]
     State(String),
 
+    #[error("UID重复: {0}")]
+    DuplicateUid(u64),
+
+    #[error("会员信息无效: {0}")]
+    MembershipInvalid(String),
+
+    #[error("分期计划已完成: {0}")]
+    InstallmentComplete(u64),
+
+    #[error("币种不一致: 期望 {}，实际 {}", .expected.code(), .found.code())]
+    CurrencyMismatch { expected: Currency, found: Currency },
+
+    #[error("字段 {field} 校验失败: {reason}")]
+    ValidationFailed { field: String, reason: String },
+
+    #[error("校验未通过: {}", format_violations(.0))]
+    Validation(Vec<Violation>),
+
+    #[error("加密/解密错误: {0}")]
+    Encryption(String),
+
+    #[error("数据文件 {path} 校验失败: 期望 {expected}，实际 {actual}")]
+    Corrupted {
+        path: String,
+        expected: String,
+        actual: String,
+    },
+
+    #[error("只读模式下不能执行: {0}")]
+    ReadOnly(String),
+
+    #[error("没有权限: {0}")]
+    PermissionDenied(String),
+
     #[error("其他错误: {0}")]
     Other(String),
 }
 
+impl Error {
+    /// 稳定的字符串错误码，供上层按错误类型分支处理
+    ///
+    /// 错误码一经发布不会修改，新增变体只会追加新的错误码。
+    pub fn code(&self) -> &'static str {
+        match self {
+            Self::Io(_) => "io",
+            Self::SerdeJson(_) => "serde_json",
+            Self::Chrono(_) => "chrono",
+            Self::Poison(_) => "poison",
+            Self::NotFound(_) => "not_found",
+            Self::InvalidInput(_) => "invalid_input",
+            Self::State(_) => "state",
+            Self::DuplicateUid(_) => "duplicate_uid",
+            Self::MembershipInvalid(_) => "membership_invalid",
+            Self::InstallmentComplete(_) => "installment_complete",
+            Self::CurrencyMismatch { .. } => "currency_mismatch",
+            Self::ValidationFailed { .. } => "validation_failed",
+            Self::Validation(_) => "validation",
+            Self::Encryption(_) => "encryption",
+            Self::Corrupted { .. } => "corrupted",
+            Self::ReadOnly(_) => "read_only",
+            Self::PermissionDenied(_) => "permission_denied",
+            Self::Other(_) => "other",
+        }
+    }
+
+    /// 稳定的数字错误码，与 [`Error::code`] 一一对应
+    pub fn numeric_code(&self) -> u16 {
+        match self {
+            Self::Io(_) => 1001,
+            Self::SerdeJson(_) => 1002,
+            Self::Chrono(_) => 1003,
+            Self::Poison(_) => 1004,
+            Self::Encryption(_) => 1005,
+            Self::Corrupted { .. } => 1006,
+            Self::NotFound(_) => 2001,
+            Self::InvalidInput(_) => 2002,
+            Self::State(_) => 2003,
+            Self::DuplicateUid(_) => 2004,
+            Self::MembershipInvalid(_) => 2005,
+            Self::InstallmentComplete(_) => 2006,
+            Self::ValidationFailed { .. } => 2007,
+            Self::Validation(_) => 2008,
+            Self::ReadOnly(_) => 2009,
+            Self::PermissionDenied(_) => 2010,
+            Self::CurrencyMismatch { .. } => 2011,
+            Self::Other(_) => 9999,
+        }
+    }
+}
+
+fn format_violations(violations: &[Violation]) -> String {
+    violations
+        .iter()
+        .map(|v| v.to_string())
+        .collect::<Vec<_>>()
+        .join("；")
+}
+
 impl From<std::num::ParseIntError> for Error {
     fn from(e: std::num::ParseIntError) -> Self { Self::InvalidInput(e.to_string()) }
 }
@@ -0,0 +1,185 @@
+//! 数据变更事件
+//!
+//! 嵌入方（桌面界面、服务端等）通过 [`crate::QmxManager::subscribe`] 订阅事件，
+//! 在数据变化时得到通知，无需轮询数据库。回调在触发修改的线程上同步执行，
+//! 执行时不持有管理器的任何锁，可以在回调中再次调用管理器。
+//!
+//! 只需知道"哪条记录发生了什么变化"的界面可以改用 [`crate::QmxManager::watch`]，
+//! 从通道中接收 [`ChangeEvent`]，在自己的线程上刷新列表。
+
+use crate::audit::{AuditAction, AuditEntity};
+use crate::manager::FieldChange;
+use log::debug;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::mpsc::{Receiver, Sender, channel};
+use std::sync::{Arc, Mutex, RwLock};
+
+/// 事件类型，用于订阅
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum EventKind {
+    StudentCreated,
+    StudentUpdated,
+    StudentDeleted,
+    CashRecorded,
+    CashUpdated,
+    CashDeleted,
+    InstallmentOverdue,
+    InstallmentPaid,
+}
+
+/// 数据变更事件
+#[derive(Debug, Clone, PartialEq)]
+pub enum Event {
+    StudentCreated {
+        uid: u64,
+    },
+    StudentUpdated {
+        uid: u64,
+        changes: Vec<FieldChange>,
+    },
+    StudentDeleted {
+        uid: u64,
+    },
+    /// 新增现金记录，包括退款和分期计划生成的记录
+    CashRecorded {
+        uid: u64,
+    },
+    CashUpdated {
+        uid: u64,
+        changes: Vec<FieldChange>,
+    },
+    CashDeleted {
+        uid: u64,
+    },
+    /// 分期付款被标记为逾期
+    InstallmentOverdue {
+        uid: u64,
+    },
+    /// 分期付款被标记为已付款
+    InstallmentPaid {
+        uid: u64,
+    },
+}
+
+impl Event {
+    pub fn kind(&self) -> EventKind {
+        match self {
+            Self::StudentCreated { .. } => EventKind::StudentCreated,
+            Self::StudentUpdated { .. } => EventKind::StudentUpdated,
+            Self::StudentDeleted { .. } => EventKind::StudentDeleted,
+            Self::CashRecorded { .. } => EventKind::CashRecorded,
+            Self::CashUpdated { .. } => EventKind::CashUpdated,
+            Self::CashDeleted { .. } => EventKind::CashDeleted,
+            Self::InstallmentOverdue { .. } => EventKind::InstallmentOverdue,
+            Self::InstallmentPaid { .. } => EventKind::InstallmentPaid,
+        }
+    }
+
+    /// 事件涉及的学生或现金记录 UID
+    pub fn uid(&self) -> u64 {
+        match self {
+            Self::StudentCreated { uid }
+            | Self::StudentUpdated { uid, .. }
+            | Self::StudentDeleted { uid }
+            | Self::CashRecorded { uid }
+            | Self::CashUpdated { uid, .. }
+            | Self::CashDeleted { uid }
+            | Self::InstallmentOverdue { uid }
+            | Self::InstallmentPaid { uid } => *uid,
+        }
+    }
+
+    /// 转换为不含字段详情的变更通知，逾期和付款标记视为现金记录的更新
+    pub fn change(&self) -> ChangeEvent {
+        let (entity, action) = match self {
+            Self::StudentCreated { .. } => (AuditEntity::Student, AuditAction::Create),
+            Self::StudentUpdated { .. } => (AuditEntity::Student, AuditAction::Update),
+            Self::StudentDeleted { .. } => (AuditEntity::Student, AuditAction::Delete),
+            Self::CashRecorded { .. } => (AuditEntity::Cash, AuditAction::Create),
+            Self::CashUpdated { .. }
+            | Self::InstallmentOverdue { .. }
+            | Self::InstallmentPaid { .. } => (AuditEntity::Cash, AuditAction::Update),
+            Self::CashDeleted { .. } => (AuditEntity::Cash, AuditAction::Delete),
+        };
+        ChangeEvent {
+            entity,
+            uid: self.uid(),
+            action,
+        }
+    }
+}
+
+/// 变更通知：哪类记录的哪一条发生了什么操作
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ChangeEvent {
+    pub entity: AuditEntity,
+    pub uid: u64,
+    pub action: AuditAction,
+}
+
+/// 订阅句柄，用于取消订阅
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct SubscriptionId(u64);
+
+type Callback = Arc<dyn Fn(&Event) + Send + Sync>;
+
+/// 事件订阅表
+#[derive(Default)]
+pub(crate) struct EventBus {
+    subscribers: RwLock<Vec<(SubscriptionId, EventKind, Callback)>>,
+    next_id: AtomicU64,
+    watchers: Mutex<Vec<Sender<ChangeEvent>>>,
+}
+
+impl EventBus {
+    pub(crate) fn subscribe(&self, kind: EventKind, callback: Callback) -> SubscriptionId {
+        let id = SubscriptionId(self.next_id.fetch_add(1, Ordering::Relaxed) + 1);
+        self.subscribers
+            .write()
+            .unwrap_or_else(|e| e.into_inner())
+            .push((id, kind, callback));
+        debug!("新增 {:?} 事件订阅: {:?}", kind, id);
+        id
+    }
+
+    pub(crate) fn unsubscribe(&self, id: SubscriptionId) -> bool {
+        let mut subscribers = self.subscribers.write().unwrap_or_else(|e| e.into_inner());
+        let before = subscribers.len();
+        subscribers.retain(|(sub_id, _, _)| *sub_id != id);
+        subscribers.len() != before
+    }
+
+    pub(crate) fn watch(&self) -> Receiver<ChangeEvent> {
+        let (sender, receiver) = channel();
+        self.watchers
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .push(sender);
+        debug!("新增变更通知通道");
+        receiver
+    }
+
+    /// 按订阅顺序通知订阅了该类型事件的回调，再向所有通道发送变更通知
+    ///
+    /// 接收端已被丢弃的通道会被移除。
+    pub(crate) fn emit(&self, event: &Event) {
+        let kind = event.kind();
+        let callbacks: Vec<Callback> = self
+            .subscribers
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .iter()
+            .filter(|(_, sub_kind, _)| *sub_kind == kind)
+            .map(|(_, _, callback)| callback.clone())
+            .collect();
+        for callback in callbacks {
+            callback(event);
+        }
+
+        let change = event.change();
+        self.watchers
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .retain(|sender| sender.send(change).is_ok());
+    }
+}
@@ -0,0 +1,126 @@
+//! 数据导出
+//!
+//! 把现金记录导出为会计常用的表格格式，月底对账时无需再自己编写脚本处理 JSON 数据。
+//! 启用 `xlsx` feature 后还可以通过 [`xlsx`] 导出包含多个工作表的 Excel 工作簿。
+//! 课程排期和各类到期日可以通过 [`ical`] 导出为日历订阅文件。
+
+pub mod ical;
+#[cfg(feature = "xlsx")]
+pub mod xlsx;
+
+use crate::cash::{Cash, Installment, InstallmentStatus};
+use crate::error::Result;
+use crate::plan::PlanDatabase;
+use crate::student::StudentDatabase;
+use log::info;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+/// 现金记录 CSV 的表头
+pub const CASH_CSV_HEADER: [&str; 5] = ["日期", "学生", "金额（元）", "备注", "付款计划"];
+
+/// 把现金记录写为 CSV
+///
+/// 输出以 UTF-8 BOM 开头、使用 CRLF 换行，Excel 等表格软件可以直接正确显示中文。
+/// 学生姓名从 `students` 中查找，找不到对应学生时写入学生 UID；分期的总期数从 `plans` 中查找。
+pub fn write_cash_csv<W: Write>(
+    mut writer: W,
+    records: &[Cash],
+    students: &StudentDatabase,
+    plans: &PlanDatabase,
+) -> Result<()> {
+    writer.write_all("\u{feff}".as_bytes())?;
+    write_csv_row(&mut writer, CASH_CSV_HEADER)?;
+    for cash in records {
+        write_csv_row(
+            &mut writer,
+            [
+                cash.created_at.format("%Y-%m-%d").to_string(),
+                student_label(cash, students),
+                cash.cash.to_plain_string(),
+                cash.note.clone().unwrap_or_default(),
+                cash.installment
+                    .as_ref()
+                    .map(|installment| describe_installment(installment, plans))
+                    .unwrap_or_default(),
+            ],
+        )?;
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+/// 把现金记录导出为 CSV 文件，已存在的文件会被覆盖
+pub fn export_cash_csv(
+    path: impl AsRef<Path>,
+    records: &[Cash],
+    students: &StudentDatabase,
+    plans: &PlanDatabase,
+) -> Result<()> {
+    let path = path.as_ref();
+    let writer = BufWriter::new(File::create(path)?);
+    write_cash_csv(writer, records, students, plans)?;
+    info!("导出 {} 条现金记录到 {}", records.len(), path.display());
+    Ok(())
+}
+
+/// 现金记录对应的学生姓名，找不到学生时为 `#UID`，没有关联学生时为空
+fn student_label(cash: &Cash, students: &StudentDatabase) -> String {
+    match cash.student_id {
+        Some(id) => students
+            .get(&id)
+            .map_or_else(|| format!("#{}", id), |s| s.display_name()),
+        None => String::new(),
+    }
+}
+
+/// 付款计划列的内容，如 `计划 #3 第 1/6 期（已付）`，找不到计划时省略总期数
+fn describe_installment(installment: &Installment, plans: &PlanDatabase) -> String {
+    let status = match installment.status {
+        InstallmentStatus::Pending => "待付",
+        InstallmentStatus::Paid => "已付",
+        InstallmentStatus::Overdue => "逾期",
+        InstallmentStatus::Cancelled => "已取消",
+    };
+    format!(
+        "计划 #{} 第 {} 期（{}）",
+        installment.plan_id,
+        period_label(installment, plans),
+        status
+    )
+}
+
+/// 期数，如 `1/6`，找不到计划时只有本期期数
+fn period_label(installment: &Installment, plans: &PlanDatabase) -> String {
+    match plans.get(&installment.plan_id) {
+        Some(plan) => format!(
+            "{}/{}",
+            installment.current_installment, plan.total_installments
+        ),
+        None => installment.current_installment.to_string(),
+    }
+}
+
+fn write_csv_row<W: Write, S: AsRef<str>>(
+    writer: &mut W,
+    fields: impl IntoIterator<Item = S>,
+) -> Result<()> {
+    let line = fields
+        .into_iter()
+        .map(|field| escape_csv(field.as_ref()))
+        .collect::<Vec<_>>()
+        .join(",");
+    writer.write_all(line.as_bytes())?;
+    writer.write_all(b"\r\n")?;
+    Ok(())
+}
+
+/// 含逗号、引号或换行的字段用双引号包裹，内部引号加倍
+fn escape_csv(field: &str) -> String {
+    if field.contains([',', '"', '\r', '\n']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
@@ -0,0 +1,218 @@
+//! iCalendar 导出
+//!
+//! 把即将开始的课程、分期付款到期日和会员到期日写为 `.ics` 日历（RFC 5545），
+//! 工作人员可以在手机日历中订阅该文件。课程按实际上课时间生成事件，
+//! 到期日生成全天事件。
+
+use crate::cash::{CashDatabase, InstallmentStatus};
+use crate::coach::CoachDatabase;
+use crate::error::Result;
+use crate::schedule::Session;
+use crate::student::{StudentDatabase, Subject};
+use chrono::{DateTime, Utc};
+use log::info;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+/// 日历的产品标识
+pub const PRODUCT_ID: &str = "-//QMX//QMX Backend Lib//ZH";
+
+/// 内容行的最大长度（字节），超出部分折行
+const MAX_LINE_OCTETS: usize = 75;
+
+/// 日历导出的数据来源
+pub struct CalendarSource<'a> {
+    pub sessions: &'a [Session],
+    pub students: &'a StudentDatabase,
+    pub cash: &'a CashDatabase,
+    pub coaches: &'a CoachDatabase,
+}
+
+/// 把 `now` 之后的课程、待付分期和会员到期日写为 iCalendar，返回写入的事件数
+///
+/// 已逾期但未付的分期不再写入，逾期提醒见 [`crate::QmxManager::get_overdue_installments`]。
+pub fn write_calendar<W: Write>(
+    mut writer: W,
+    source: &CalendarSource,
+    now: DateTime<Utc>,
+) -> Result<usize> {
+    let stamp = format_datetime(now);
+    let mut events = 0;
+    write_line(&mut writer, "BEGIN:VCALENDAR")?;
+    write_line(&mut writer, "VERSION:2.0")?;
+    write_line(&mut writer, &format!("PRODID:{}", PRODUCT_ID))?;
+    write_line(&mut writer, "CALSCALE:GREGORIAN")?;
+
+    let mut sessions: Vec<&Session> = source
+        .sessions
+        .iter()
+        .filter(|session| session.start >= now)
+        .collect();
+    sessions.sort_by_key(|session| (session.start, session.uid));
+    for session in sessions {
+        let mut description = format!("已报名 {}/{}", session.enrolled.len(), session.capacity);
+        if let Some(coach) = session.coach_id.and_then(|uid| source.coaches.get(&uid)) {
+            description = format!("教练：{}\n{}", coach.name, description);
+        }
+        write_event(
+            &mut writer,
+            &[
+                format!("UID:session-{}@qmx", session.uid),
+                format!("DTSTAMP:{}", stamp),
+                format!("DTSTART:{}", format_datetime(session.start)),
+                format!("DTEND:{}", format_datetime(session.end)),
+                format!(
+                    "SUMMARY:{}",
+                    escape_text(&format!("{}课", subject_label(&session.subject)))
+                ),
+                format!("DESCRIPTION:{}", escape_text(&description)),
+            ],
+        )?;
+        events += 1;
+    }
+
+    for (uid, cash) in source.cash.iter() {
+        let Some(installment) = &cash.installment else {
+            continue;
+        };
+        if installment.status != InstallmentStatus::Pending || installment.due_date < now {
+            continue;
+        }
+        let student = match cash.student_id {
+            Some(id) => source
+                .students
+                .get(&id)
+                .map_or_else(|| format!("#{}", id), |s| s.display_name()),
+            None => "未关联学生".to_string(),
+        };
+        let period = match source.cash.plans.get(&installment.plan_id) {
+            Some(plan) => format!(
+                "{}/{}",
+                installment.current_installment, plan.total_installments
+            ),
+            None => installment.current_installment.to_string(),
+        };
+        let summary = format!(
+            "分期到期：{} 第 {} 期 {}",
+            student, period, cash.cash
+        );
+        write_all_day_event(
+            &mut writer,
+            &format!("installment-{}@qmx", uid),
+            &stamp,
+            installment.due_date,
+            &summary,
+        )?;
+        events += 1;
+    }
+
+    for (uid, student) in source.students.iter() {
+        let Some(end) = student.membership_end_date() else {
+            continue;
+        };
+        if end < now {
+            continue;
+        }
+        let summary = format!("会员到期：{}", student.display_name());
+        write_all_day_event(
+            &mut writer,
+            &format!("membership-{}@qmx", uid),
+            &stamp,
+            end,
+            &summary,
+        )?;
+        events += 1;
+    }
+
+    write_line(&mut writer, "END:VCALENDAR")?;
+    writer.flush()?;
+    Ok(events)
+}
+
+/// 把日历导出为 `.ics` 文件，已存在的文件会被覆盖，返回写入的事件数
+pub fn export_calendar(
+    path: impl AsRef<Path>,
+    source: &CalendarSource,
+    now: DateTime<Utc>,
+) -> Result<usize> {
+    let path = path.as_ref();
+    let writer = BufWriter::new(File::create(path)?);
+    let events = write_calendar(writer, source, now)?;
+    info!("导出 {} 个日历事件到 {}", events, path.display());
+    Ok(events)
+}
+
+fn subject_label(subject: &Subject) -> &'static str {
+    match subject {
+        Subject::Shooting => "射击",
+        Subject::Archery => "射箭",
+        Subject::Others => "其他",
+    }
+}
+
+fn write_all_day_event<W: Write>(
+    writer: &mut W,
+    uid: &str,
+    stamp: &str,
+    date: DateTime<Utc>,
+    summary: &str,
+) -> Result<()> {
+    let day = date.date_naive();
+    let next = day.succ_opt().unwrap_or(day);
+    write_event(
+        writer,
+        &[
+            format!("UID:{}", uid),
+            format!("DTSTAMP:{}", stamp),
+            format!("DTSTART;VALUE=DATE:{}", day.format("%Y%m%d")),
+            format!("DTEND;VALUE=DATE:{}", next.format("%Y%m%d")),
+            format!("SUMMARY:{}", escape_text(summary)),
+        ],
+    )
+}
+
+fn write_event<W: Write>(writer: &mut W, properties: &[String]) -> Result<()> {
+    write_line(writer, "BEGIN:VEVENT")?;
+    for property in properties {
+        write_line(writer, property)?;
+    }
+    write_line(writer, "END:VEVENT")
+}
+
+fn format_datetime(time: DateTime<Utc>) -> String {
+    time.format("%Y%m%dT%H%M%SZ").to_string()
+}
+
+/// 转义文本值中的反斜杠、分号、逗号和换行
+fn escape_text(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '\\' => escaped.push_str("\\\\"),
+            ';' => escaped.push_str("\\;"),
+            ',' => escaped.push_str("\\,"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => {}
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// 写一个内容行，超过 75 字节时在字符边界处折行，续行以空格开头
+fn write_line<W: Write>(writer: &mut W, line: &str) -> Result<()> {
+    let mut width = 0;
+    for c in line.chars() {
+        let len = c.len_utf8();
+        if width + len > MAX_LINE_OCTETS {
+            writer.write_all(b"\r\n ")?;
+            width = 1;
+        }
+        let mut buf = [0; 4];
+        writer.write_all(c.encode_utf8(&mut buf).as_bytes())?;
+        width += len;
+    }
+    writer.write_all(b"\r\n")?;
+    Ok(())
+}
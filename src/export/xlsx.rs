@@ -0,0 +1,336 @@
+//! Excel 工作簿导出
+//!
+//! 生成包含「学生」「现金」「概览」三个工作表的 `.xlsx` 文件。很多管理员无法可靠地处理
+//! JSON 或 CSV 中的中文编码，工作簿可以直接用 Excel、WPS 等软件打开。
+//!
+//! 工作簿按 Office Open XML 格式手工生成：文本使用内联字符串，各部件以不压缩的方式
+//! 打包为 ZIP，因此不需要额外的依赖。
+
+use super::{describe_installment, student_label};
+use crate::archive::ZipWriter;
+use crate::cash::CashDatabase;
+use crate::error::Result;
+use crate::stats::DashboardStats;
+use crate::student::StudentDatabase;
+use log::info;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+/// 单元格内容
+enum Cell {
+    Text(String),
+    Number(f64),
+    Empty,
+}
+
+impl From<String> for Cell {
+    fn from(value: String) -> Self {
+        Self::Text(value)
+    }
+}
+
+impl From<&str> for Cell {
+    fn from(value: &str) -> Self {
+        Self::Text(value.to_string())
+    }
+}
+
+impl<T: Into<Cell>> From<Option<T>> for Cell {
+    fn from(value: Option<T>) -> Self {
+        value.map_or(Self::Empty, Into::into)
+    }
+}
+
+fn number(value: impl Into<f64>) -> Cell {
+    Cell::Number(value.into())
+}
+
+/// 以分为单位的金额转换为以元为单位的数值单元格
+fn yuan(amount: i64) -> Cell {
+    Cell::Number(amount as f64 / 100.0)
+}
+
+struct Sheet {
+    name: &'static str,
+    rows: Vec<Vec<Cell>>,
+}
+
+/// 把学生、现金和统计数据写为 xlsx 工作簿
+pub fn write_workbook<W: Write>(
+    writer: W,
+    students: &StudentDatabase,
+    cash: &CashDatabase,
+    stats: &DashboardStats,
+) -> Result<()> {
+    let sheets = [
+        student_sheet(students),
+        cash_sheet(cash, students),
+        summary_sheet(stats),
+    ];
+
+    let mut zip = ZipWriter::new(writer);
+    zip.add(
+        "[Content_Types].xml",
+        content_types_xml(sheets.len()).as_bytes(),
+    )?;
+    zip.add("_rels/.rels", ROOT_RELS.as_bytes())?;
+    zip.add("xl/workbook.xml", workbook_xml(&sheets).as_bytes())?;
+    zip.add(
+        "xl/_rels/workbook.xml.rels",
+        workbook_rels_xml(sheets.len()).as_bytes(),
+    )?;
+    for (i, sheet) in sheets.iter().enumerate() {
+        zip.add(
+            &format!("xl/worksheets/sheet{}.xml", i + 1),
+            sheet_xml(sheet).as_bytes(),
+        )?;
+    }
+    zip.finish()
+}
+
+/// 导出 xlsx 工作簿到文件，已存在的文件会被覆盖
+pub fn export_workbook(
+    path: impl AsRef<Path>,
+    students: &StudentDatabase,
+    cash: &CashDatabase,
+    stats: &DashboardStats,
+) -> Result<()> {
+    let path = path.as_ref();
+    let writer = BufWriter::new(File::create(path)?);
+    write_workbook(writer, students, cash, stats)?;
+    info!(
+        "导出 {} 名学生、{} 条现金记录到工作簿 {}",
+        students.len(),
+        cash.len(),
+        path.display()
+    );
+    Ok(())
+}
+
+fn student_sheet(students: &StudentDatabase) -> Sheet {
+    let mut rows = vec![header_row(&[
+        "UID",
+        "姓名",
+        "年龄",
+        "电话",
+        "班级",
+        "科目",
+        "剩余课时",
+        "会员开始",
+        "会员结束",
+        "会员等级",
+        "备注",
+    ])];
+    for (_, student) in students.iter() {
+        rows.push(vec![
+            Cell::Number(student.uid() as f64),
+            student.display_name().into(),
+            student.age().map(number).unwrap_or(Cell::Empty),
+            student.phone().into(),
+            format!("{:?}", student.class()).into(),
+            format!("{:?}", student.subject()).into(),
+            student.lesson_left().map(number).unwrap_or(Cell::Empty),
+            student
+                .membership_start_date()
+                .map(|d| d.format("%Y-%m-%d").to_string())
+                .into(),
+            student
+                .membership_end_date()
+                .map(|d| d.format("%Y-%m-%d").to_string())
+                .into(),
+            student.membership_tier().map(|t| t.to_string()).into(),
+            student.note().into(),
+        ]);
+    }
+    Sheet { name: "学生", rows }
+}
+
+fn cash_sheet(cash: &CashDatabase, students: &StudentDatabase) -> Sheet {
+    let mut rows = vec![header_row(&[
+        "UID",
+        "日期",
+        "学生",
+        "金额（元）",
+        "备注",
+        "付款计划",
+    ])];
+    for (_, record) in cash.iter() {
+        rows.push(vec![
+            Cell::Number(record.uid as f64),
+            record.created_at.format("%Y-%m-%d").to_string().into(),
+            student_label(record, students).into(),
+            yuan(record.cash.amount_minor),
+            record.note.clone().into(),
+            record
+                .installment
+                .as_ref()
+                .map(|installment| describe_installment(installment, &cash.plans))
+                .into(),
+        ]);
+    }
+    Sheet { name: "现金", rows }
+}
+
+fn summary_sheet(stats: &DashboardStats) -> Sheet {
+    let mut rows = vec![
+        header_row(&["指标", "数值"]),
+        vec!["学生总数".into(), Cell::Number(stats.total_students as f64)],
+        vec!["总收入（元）".into(), yuan(stats.total_revenue)],
+        vec!["总支出（元）".into(), yuan(stats.total_expense)],
+        vec!["平均成绩".into(), Cell::Number(stats.average_score)],
+        vec!["最高成绩".into(), Cell::Number(stats.max_score)],
+        vec![
+            "活跃课程数".into(),
+            Cell::Number(stats.active_courses as f64),
+        ],
+    ];
+    for (tier, count) in &stats.membership_tiers {
+        rows.push(vec![
+            format!("会员等级：{}", tier).into(),
+            Cell::Number(*count as f64),
+        ]);
+    }
+    Sheet { name: "概览", rows }
+}
+
+fn header_row(titles: &[&str]) -> Vec<Cell> {
+    titles.iter().map(|&title| title.into()).collect()
+}
+
+// ============================================================================
+// Office Open XML 部件
+// ============================================================================
+
+const XML_DECLARATION: &str = r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>"#;
+
+const ROOT_RELS: &str = concat!(
+    r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>"#,
+    r#"<Relationships xmlns="http://schemas.openxmlformats.org/package/2006/relationships">"#,
+    r#"<Relationship Id="rId1" Type="http://schemas.openxmlformats.org/officeDocument/2006/relationships/officeDocument" Target="xl/workbook.xml"/>"#,
+    r#"</Relationships>"#
+);
+
+fn content_types_xml(sheet_count: usize) -> String {
+    let mut xml = format!(
+        concat!(
+            "{}",
+            r#"<Types xmlns="http://schemas.openxmlformats.org/package/2006/content-types">"#,
+            r#"<Default Extension="rels" ContentType="application/vnd.openxmlformats-package.relationships+xml"/>"#,
+            r#"<Default Extension="xml" ContentType="application/xml"/>"#,
+            r#"<Override PartName="/xl/workbook.xml" ContentType="application/vnd.openxmlformats-officedocument.spreadsheetml.sheet.main+xml"/>"#
+        ),
+        XML_DECLARATION
+    );
+    for i in 1..=sheet_count {
+        xml.push_str(&format!(
+            r#"<Override PartName="/xl/worksheets/sheet{}.xml" ContentType="application/vnd.openxmlformats-officedocument.spreadsheetml.worksheet+xml"/>"#,
+            i
+        ));
+    }
+    xml.push_str("</Types>");
+    xml
+}
+
+fn workbook_xml(sheets: &[Sheet]) -> String {
+    let mut xml = format!(
+        concat!(
+            "{}",
+            r#"<workbook xmlns="http://schemas.openxmlformats.org/spreadsheetml/2006/main" "#,
+            r#"xmlns:r="http://schemas.openxmlformats.org/officeDocument/2006/relationships">"#,
+            "<sheets>"
+        ),
+        XML_DECLARATION
+    );
+    for (i, sheet) in sheets.iter().enumerate() {
+        xml.push_str(&format!(
+            r#"<sheet name="{}" sheetId="{}" r:id="rId{}"/>"#,
+            escape_xml(sheet.name),
+            i + 1,
+            i + 1
+        ));
+    }
+    xml.push_str("</sheets></workbook>");
+    xml
+}
+
+fn workbook_rels_xml(sheet_count: usize) -> String {
+    let mut xml = format!(
+        concat!(
+            "{}",
+            r#"<Relationships xmlns="http://schemas.openxmlformats.org/package/2006/relationships">"#
+        ),
+        XML_DECLARATION
+    );
+    for i in 1..=sheet_count {
+        xml.push_str(&format!(
+            r#"<Relationship Id="rId{}" Type="http://schemas.openxmlformats.org/officeDocument/2006/relationships/worksheet" Target="worksheets/sheet{}.xml"/>"#,
+            i, i
+        ));
+    }
+    xml.push_str("</Relationships>");
+    xml
+}
+
+fn sheet_xml(sheet: &Sheet) -> String {
+    let mut xml = format!(
+        concat!(
+            "{}",
+            r#"<worksheet xmlns="http://schemas.openxmlformats.org/spreadsheetml/2006/main">"#,
+            "<sheetData>"
+        ),
+        XML_DECLARATION
+    );
+    for (r, row) in sheet.rows.iter().enumerate() {
+        xml.push_str(&format!(r#"<row r="{}">"#, r + 1));
+        for (c, cell) in row.iter().enumerate() {
+            let reference = format!("{}{}", column_name(c), r + 1);
+            match cell {
+                Cell::Text(text) => xml.push_str(&format!(
+                    r#"<c r="{}" t="inlineStr"><is><t xml:space="preserve">{}</t></is></c>"#,
+                    reference,
+                    escape_xml(text)
+                )),
+                // NaN、无穷大等无法作为数值保存
+                Cell::Number(value) if value.is_finite() => {
+                    xml.push_str(&format!(r#"<c r="{}"><v>{}</v></c>"#, reference, value))
+                }
+                Cell::Number(_) | Cell::Empty => {}
+            }
+        }
+        xml.push_str("</row>");
+    }
+    xml.push_str("</sheetData></worksheet>");
+    xml
+}
+
+/// 从 0 开始的列序号转换为列名，如 0 -> `A`、26 -> `AA`
+fn column_name(index: usize) -> String {
+    let mut name = Vec::new();
+    let mut n = index + 1;
+    while n > 0 {
+        let rem = (n - 1) % 26;
+        name.push(b'A' + rem as u8);
+        n = (n - 1) / 26;
+    }
+    name.reverse();
+    String::from_utf8(name).unwrap_or_default()
+}
+
+/// 转义 XML 特殊字符，并去掉 XML 不允许出现的控制字符
+fn escape_xml(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            '\t' | '\n' | '\r' => escaped.push(c),
+            c if c.is_control() => {}
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
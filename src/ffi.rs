@@ -0,0 +1,331 @@
+//! C 语言绑定
+//!
+//! 启用 `ffi` feature 后提供一组 `extern "C"` 函数，供 C++、Electron 等前端通过动态库调用，
+//! 对应的 C 头文件位于 `include/qmx_backend.h`。构建动态库：
+//!
+//! ```text
+//! cargo rustc --release --features ffi --crate-type cdylib
+//! ```
+//!
+//! 约定：
+//!
+//! - 输入和输出的复杂数据均为 UTF-8 编码的 JSON 字符串，字段与 Rust 类型的序列化结果一致；
+//! - 返回 `int32_t` 的函数成功时返回 0，失败时返回 [`Error::numeric_code`]；
+//!   返回指针的函数失败时返回空指针。失败原因可通过 [`qmx_last_error`] 获取；
+//! - 本库返回的字符串必须用 [`qmx_string_free`] 释放，管理器用 [`qmx_manager_free`] 释放。
+
+use crate::error::{Error, Result};
+use crate::manager::{CashBuilder, DeletePolicy, QmxManager, StudentBuilder, StudentQuery};
+use crate::student::{Class, Subject};
+use serde::Deserialize;
+use std::cell::RefCell;
+use std::ffi::{CStr, CString, c_char};
+use std::ptr;
+
+thread_local! {
+    static LAST_ERROR: RefCell<Option<CString>> = const { RefCell::new(None) };
+}
+
+/// 创建学生的 JSON 参数
+#[derive(Deserialize)]
+struct NewStudent {
+    name: String,
+    age: Option<u8>,
+    phone: Option<String>,
+    class: Option<Class>,
+    subject: Option<Subject>,
+    lesson_left: Option<u32>,
+    note: Option<String>,
+}
+
+/// 搜索学生的 JSON 参数，所有条件均可省略
+#[derive(Deserialize, Default)]
+struct StudentSearch {
+    name: Option<String>,
+    class: Option<Class>,
+    subject: Option<Subject>,
+    limit: Option<usize>,
+    offset: Option<usize>,
+}
+
+/// 记录现金的 JSON 参数
+#[derive(Deserialize)]
+struct NewCash {
+    amount: i64,
+    student_id: Option<u64>,
+    note: Option<String>,
+}
+
+fn set_last_error(e: &Error) {
+    // 错误信息中不会出现 NUL，出现时退化为错误码
+    let message = CString::new(e.to_string())
+        .unwrap_or_else(|_| CString::new(e.code()).expect("错误码不含 NUL"));
+    LAST_ERROR.with(|last| *last.borrow_mut() = Some(message));
+}
+
+/// 执行 `f`，成功返回 0，失败时记录错误信息并返回错误码
+fn status(f: impl FnOnce() -> Result<()>) -> i32 {
+    match f() {
+        Ok(()) => 0,
+        Err(e) => {
+            set_last_error(&e);
+            i32::from(e.numeric_code())
+        }
+    }
+}
+
+/// 执行 `f` 并把结果序列化为 JSON 字符串，失败时记录错误信息并返回空指针
+fn json_result<T: serde::Serialize>(f: impl FnOnce() -> Result<T>) -> *mut c_char {
+    let result = f().and_then(|value| {
+        let json = serde_json::to_string(&value)?;
+        CString::new(json).map_err(|e| Error::Other(e.to_string()))
+    });
+    match result {
+        Ok(json) => json.into_raw(),
+        Err(e) => {
+            set_last_error(&e);
+            ptr::null_mut()
+        }
+    }
+}
+
+/// # Safety
+///
+/// `manager` 为空或由 [`qmx_manager_new`] 返回且尚未释放。
+unsafe fn manager_ref<'a>(manager: *const QmxManager) -> Result<&'a QmxManager> {
+    // SAFETY: 调用方保证指针为空或指向有效的管理器
+    unsafe { manager.as_ref() }.ok_or_else(|| Error::InvalidInput("管理器指针为空".to_string()))
+}
+
+/// # Safety
+///
+/// `text` 为空或指向以 NUL 结尾的字符串。
+unsafe fn str_ref<'a>(text: *const c_char) -> Result<&'a str> {
+    if text.is_null() {
+        return Err(Error::InvalidInput("字符串指针为空".to_string()));
+    }
+    // SAFETY: 调用方保证指针指向以 NUL 结尾的字符串
+    unsafe { CStr::from_ptr(text) }
+        .to_str()
+        .map_err(|e| Error::InvalidInput(format!("字符串不是有效的 UTF-8: {}", e)))
+}
+
+/// 创建管理器，数据保存在当前目录的 `./data` 下，失败时返回空指针
+#[unsafe(no_mangle)]
+pub extern "C" fn qmx_manager_new(auto_save: bool) -> *mut QmxManager {
+    match QmxManager::builder().auto_save(auto_save).build() {
+        Ok(manager) => Box::into_raw(Box::new(manager)),
+        Err(e) => {
+            set_last_error(&e);
+            ptr::null_mut()
+        }
+    }
+}
+
+/// 释放管理器，传入空指针时不做任何操作
+///
+/// # Safety
+///
+/// `manager` 为空或由 [`qmx_manager_new`] 返回且尚未释放。
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn qmx_manager_free(manager: *mut QmxManager) {
+    if !manager.is_null() {
+        // SAFETY: 指针由 Box::into_raw 创建，调用方保证只释放一次
+        drop(unsafe { Box::from_raw(manager) });
+    }
+}
+
+/// 保存全部数据
+///
+/// # Safety
+///
+/// `manager` 为空或由 [`qmx_manager_new`] 返回且尚未释放。
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn qmx_manager_save(manager: *const QmxManager) -> i32 {
+    status(|| unsafe { manager_ref(manager) }?.save())
+}
+
+/// 按 JSON 参数创建学生，成功时把 UID 写入 `out_uid`
+///
+/// JSON 示例：`{"name": "张三", "age": 16, "class": "TenTry", "subject": "Shooting"}`。
+///
+/// # Safety
+///
+/// `manager` 同 [`qmx_manager_free`]；`json` 为以 NUL 结尾的字符串；`out_uid` 为空或可写。
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn qmx_student_create(
+    manager: *const QmxManager,
+    json: *const c_char,
+    out_uid: *mut u64,
+) -> i32 {
+    status(|| {
+        let manager = unsafe { manager_ref(manager) }?;
+        let payload: NewStudent = serde_json::from_str(unsafe { str_ref(json) }?)?;
+        let mut builder = StudentBuilder::new(payload.name);
+        if let Some(age) = payload.age {
+            builder = builder.age(age);
+        }
+        if let Some(phone) = payload.phone {
+            builder = builder.phone(phone);
+        }
+        if let Some(class) = payload.class {
+            builder = builder.class(class);
+        }
+        if let Some(subject) = payload.subject {
+            builder = builder.subject(subject);
+        }
+        if let Some(lessons) = payload.lesson_left {
+            builder = builder.lesson_left(lessons);
+        }
+        if let Some(note) = payload.note {
+            builder = builder.note(note);
+        }
+        let uid = manager.create_student(builder)?;
+        // SAFETY: 调用方保证 out_uid 为空或可写
+        if let Some(out) = unsafe { out_uid.as_mut() } {
+            *out = uid;
+        }
+        Ok(())
+    })
+}
+
+/// 获取学生的 JSON，学生不存在时返回 `null` 字符串
+///
+/// # Safety
+///
+/// `manager` 为空或由 [`qmx_manager_new`] 返回且尚未释放。
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn qmx_student_get(manager: *const QmxManager, uid: u64) -> *mut c_char {
+    json_result(|| unsafe { manager_ref(manager) }?.get_student(uid))
+}
+
+/// 删除学生，学生不存在时返回 [`Error::NotFound`] 的错误码
+///
+/// `policy` 为关联现金记录的处理方式：0 拒绝删除有现金记录的学生，1 一并删除，2 保留并清除学生 UID，
+/// 见 [`DeletePolicy`]。
+///
+/// # Safety
+///
+/// `manager` 为空或由 [`qmx_manager_new`] 返回且尚未释放。
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn qmx_student_delete(manager: *const QmxManager, uid: u64, policy: i32) -> i32 {
+    status(|| {
+        let policy = match policy {
+            0 => DeletePolicy::Restrict,
+            1 => DeletePolicy::Cascade,
+            2 => DeletePolicy::Detach,
+            _ => return Err(Error::InvalidInput(format!("无效的删除策略: {}", policy))),
+        };
+        if unsafe { manager_ref(manager) }?.delete_student(uid, policy)? {
+            Ok(())
+        } else {
+            Err(Error::NotFound(format!("学生不存在: {}", uid)))
+        }
+    })
+}
+
+/// 按 JSON 条件搜索学生，返回学生数组的 JSON
+///
+/// 条件示例：`{"name": "张", "class": "Month", "limit": 20, "offset": 0}`，`query` 为空指针时返回全部学生。
+///
+/// # Safety
+///
+/// `manager` 同 [`qmx_manager_free`]；`query` 为空或以 NUL 结尾的字符串。
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn qmx_students_search(
+    manager: *const QmxManager,
+    query: *const c_char,
+) -> *mut c_char {
+    json_result(|| {
+        let manager = unsafe { manager_ref(manager) }?;
+        let search: StudentSearch = if query.is_null() {
+            StudentSearch::default()
+        } else {
+            serde_json::from_str(unsafe { str_ref(query) }?)?
+        };
+        let mut query = StudentQuery::new();
+        if let Some(name) = search.name {
+            query = query.name_contains(name);
+        }
+        if let Some(class) = search.class {
+            query = query.class(class);
+        }
+        if let Some(subject) = search.subject {
+            query = query.subject(subject);
+        }
+        if let Some(limit) = search.limit {
+            query = query.limit(limit);
+        }
+        if let Some(offset) = search.offset {
+            query = query.offset(offset);
+        }
+        manager.search_students(query)
+    })
+}
+
+/// 按 JSON 参数记录现金，成功时把 UID 写入 `out_uid`
+///
+/// JSON 示例：`{"amount": 1500, "student_id": 1, "note": "月卡费用"}`，金额以分为单位。
+///
+/// # Safety
+///
+/// `manager` 同 [`qmx_manager_free`]；`json` 为以 NUL 结尾的字符串；`out_uid` 为空或可写。
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn qmx_cash_record(
+    manager: *const QmxManager,
+    json: *const c_char,
+    out_uid: *mut u64,
+) -> i32 {
+    status(|| {
+        let manager = unsafe { manager_ref(manager) }?;
+        let payload: NewCash = serde_json::from_str(unsafe { str_ref(json) }?)?;
+        let mut builder = CashBuilder::new(payload.amount);
+        if let Some(student_id) = payload.student_id {
+            builder = builder.student_id(student_id);
+        }
+        if let Some(note) = payload.note {
+            builder = builder.note(note);
+        }
+        let uid = manager.record_cash(builder)?;
+        // SAFETY: 调用方保证 out_uid 为空或可写
+        if let Some(out) = unsafe { out_uid.as_mut() } {
+            *out = uid;
+        }
+        Ok(())
+    })
+}
+
+/// 获取仪表板统计的 JSON
+///
+/// # Safety
+///
+/// `manager` 为空或由 [`qmx_manager_new`] 返回且尚未释放。
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn qmx_dashboard_stats(manager: *const QmxManager) -> *mut c_char {
+    json_result(|| unsafe { manager_ref(manager) }?.get_dashboard_stats())
+}
+
+/// 当前线程最近一次失败的错误信息，没有错误时返回空指针
+///
+/// 返回的字符串归本库所有，不需要释放，在当前线程下一次调用失败前有效。
+#[unsafe(no_mangle)]
+pub extern "C" fn qmx_last_error() -> *const c_char {
+    LAST_ERROR.with(|last| {
+        last.borrow()
+            .as_ref()
+            .map_or(ptr::null(), |message| message.as_ptr())
+    })
+}
+
+/// 释放本库返回的字符串，传入空指针时不做任何操作
+///
+/// # Safety
+///
+/// `text` 为空或由本库返回且尚未释放。
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn qmx_string_free(text: *mut c_char) {
+    if !text.is_null() {
+        // SAFETY: 指针由 CString::into_raw 创建，调用方保证只释放一次
+        drop(unsafe { CString::from_raw(text) });
+    }
+}
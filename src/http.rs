@@ -0,0 +1,622 @@
+//! 内嵌 HTTP API 服务
+//!
+//! 启用 `http-server` feature 后，可以把 [`QmxManager`] 的常用操作以 REST/JSON 接口提供给
+//! Web 界面，不需要再额外编写一层包装。服务基于标准库的 `TcpListener` 实现，
+//! 连接交给固定数量的工作线程处理，响应后关闭连接，适合机构内网中的小规模使用。
+//!
+//! 所有请求都需要带上 `Authorization: Bearer <令牌>` 请求头，令牌在
+//! [`HttpServer::bind`] 时指定，缺少或不匹配时返回 401。每个连接的读写都有超时
+//! （默认 [`DEFAULT_TIMEOUT`]），请求行和请求头合计不超过 [`MAX_HEADER_LEN`] 字节，
+//! 工作线程都在忙且等待队列已满时直接返回 503。
+//!
+//! | 方法 | 路径 | 说明 |
+//! | --- | --- | --- |
+//! | `GET` | `/students` | 学生列表，支持 `name`、`class`、`subject`、`limit`、`offset` 查询参数 |
+//! | `POST` | `/students` | 创建学生，返回 `{"uid": ...}` |
+//! | `GET` | `/students/{uid}` | 获取学生 |
+//! | `PATCH` | `/students/{uid}` | 更新学生，返回变更字段列表 |
+//! | `DELETE` | `/students/{uid}` | 删除学生，`policy` 查询参数为 `Restrict`（默认）、`Cascade` 或 `Detach` |
+//! | `POST` | `/cash` | 记录现金，返回 `{"uid": ...}` |
+//! | `GET` | `/cash/{uid}` | 获取现金记录 |
+//! | `GET` | `/stats/dashboard` | 仪表板统计 |
+//!
+//! 出错时返回 `{"error": 错误码, "message": 错误信息}`，错误码见 [`Error::code`]。
+//! [`handle`] 本身不检查令牌，在其他服务框架中复用时需要自行认证。
+
+use crate::error::{Error, Result};
+use crate::manager::{CashBuilder, DeletePolicy, QmxManager, StudentBuilder, StudentQuery, StudentUpdater};
+use crate::student::{Class, Subject};
+use log::{info, warn};
+use serde::Deserialize;
+use serde::de::DeserializeOwned;
+use serde_json::{Value, json};
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{Shutdown, SocketAddr, TcpListener, TcpStream, ToSocketAddrs};
+use std::sync::mpsc::{Receiver, TrySendError, sync_channel};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// 请求体的最大长度（字节）
+pub const MAX_BODY_LEN: usize = 1024 * 1024;
+
+/// 请求行与请求头合计的最大长度（字节）
+pub const MAX_HEADER_LEN: usize = 8 * 1024;
+
+/// 默认的工作线程数
+pub const DEFAULT_WORKERS: usize = 4;
+
+/// 默认的连接读写超时
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// 解析后的 HTTP 请求
+#[derive(Debug, Clone, PartialEq)]
+pub struct Request {
+    pub method: String,
+    /// 不含查询字符串的路径
+    pub path: String,
+    /// 已解码的查询参数，按出现顺序排列
+    pub query: Vec<(String, String)>,
+    pub body: Vec<u8>,
+}
+
+impl Request {
+    pub fn new(method: impl Into<String>, target: &str, body: impl Into<Vec<u8>>) -> Self {
+        let (path, query) = match target.split_once('?') {
+            Some((path, query)) => (path, parse_query(query)),
+            None => (target, Vec::new()),
+        };
+        Self {
+            method: method.into(),
+            path: path.to_string(),
+            query,
+            body: body.into(),
+        }
+    }
+
+    /// 第一个名为 `name` 的查询参数
+    pub fn param(&self, name: &str) -> Option<&str> {
+        self.query
+            .iter()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.as_str())
+    }
+}
+
+/// HTTP 响应，响应体均为 JSON
+#[derive(Debug, Clone, PartialEq)]
+pub struct Response {
+    pub status: u16,
+    pub body: Value,
+}
+
+impl Response {
+    fn ok(body: Value) -> Self {
+        Self { status: 200, body }
+    }
+
+    fn error(status: u16, code: &str, message: impl Into<String>) -> Self {
+        Self {
+            status,
+            body: json!({ "error": code, "message": message.into() }),
+        }
+    }
+
+    fn from_error(e: &Error) -> Self {
+        let status = match e {
+            Error::NotFound(_) => 404,
+            Error::ReadOnly(_) | Error::PermissionDenied(_) => 403,
+            Error::InvalidInput(_)
+            | Error::SerdeJson(_)
+            | Error::Chrono(_)
+            | Error::ValidationFailed { .. }
+            | Error::Validation(_)
+            | Error::MembershipInvalid(_)
+            | Error::CurrencyMismatch { .. } => 400,
+            Error::State(_) | Error::DuplicateUid(_) | Error::InstallmentComplete(_) => 409,
+            _ => 500,
+        };
+        let mut response = Self::error(status, e.code(), e.to_string());
+        if let Error::Validation(violations) = e {
+            response.body["violations"] = json!(violations);
+        }
+        response
+    }
+}
+
+/// 创建学生的请求体
+#[derive(Deserialize)]
+struct NewStudent {
+    name: String,
+    age: Option<u8>,
+    phone: Option<String>,
+    class: Option<Class>,
+    subject: Option<Subject>,
+    lesson_left: Option<u32>,
+    note: Option<String>,
+}
+
+/// 更新学生的请求体，未出现的字段保持不变
+#[derive(Deserialize)]
+struct StudentPatch {
+    name: Option<String>,
+    age: Option<u8>,
+    phone: Option<String>,
+    class: Option<Class>,
+    subject: Option<Subject>,
+    lesson_left: Option<u32>,
+    note: Option<String>,
+    rings: Option<Vec<f64>>,
+}
+
+/// 记录现金的请求体
+#[derive(Deserialize)]
+struct NewCash {
+    amount: i64,
+    student_id: Option<u64>,
+    note: Option<String>,
+}
+
+/// 处理一个请求
+///
+/// 与网络无关，便于在其他服务框架中复用同一套路由。
+pub fn handle(manager: &QmxManager, request: &Request) -> Response {
+    match route(manager, request) {
+        Ok(response) => response,
+        Err(e) => Response::from_error(&e),
+    }
+}
+
+fn route(manager: &QmxManager, request: &Request) -> Result<Response> {
+    let segments: Vec<&str> = request
+        .path
+        .trim_matches('/')
+        .split('/')
+        .filter(|s| !s.is_empty())
+        .collect();
+    let method = request.method.as_str();
+    match (method, segments.as_slice()) {
+        ("GET", ["students"]) => list_students(manager, request),
+        ("POST", ["students"]) => {
+            let payload: NewStudent = parse_body(request)?;
+            let mut builder = StudentBuilder::new(payload.name);
+            if let Some(age) = payload.age {
+                builder = builder.age(age);
+            }
+            if let Some(phone) = payload.phone {
+                builder = builder.phone(phone);
+            }
+            if let Some(class) = payload.class {
+                builder = builder.class(class);
+            }
+            if let Some(subject) = payload.subject {
+                builder = builder.subject(subject);
+            }
+            if let Some(lessons) = payload.lesson_left {
+                builder = builder.lesson_left(lessons);
+            }
+            if let Some(note) = payload.note {
+                builder = builder.note(note);
+            }
+            let uid = manager.create_student(builder)?;
+            Ok(Response {
+                status: 201,
+                body: json!({ "uid": uid }),
+            })
+        }
+        ("GET", ["students", uid]) => {
+            let uid = parse_uid(uid)?;
+            let student = manager
+                .get_student(uid)?
+                .ok_or_else(|| Error::NotFound(format!("学生不存在: {}", uid)))?;
+            Ok(Response::ok(serde_json::to_value(student)?))
+        }
+        ("PATCH", ["students", uid]) | ("PUT", ["students", uid]) => {
+            let uid = parse_uid(uid)?;
+            let patch: StudentPatch = parse_body(request)?;
+            let mut updater = StudentUpdater::new();
+            if let Some(name) = patch.name {
+                updater = updater.name(name);
+            }
+            if let Some(age) = patch.age {
+                updater = updater.age(Some(age));
+            }
+            if let Some(phone) = patch.phone {
+                updater = updater.phone(phone);
+            }
+            if let Some(class) = patch.class {
+                updater = updater.class(class);
+            }
+            if let Some(subject) = patch.subject {
+                updater = updater.subject(subject);
+            }
+            if let Some(lessons) = patch.lesson_left {
+                updater = updater.lesson_left(Some(lessons));
+            }
+            if let Some(note) = patch.note {
+                updater = updater.note(note);
+            }
+            if let Some(rings) = patch.rings {
+                updater = updater.set_rings(rings);
+            }
+            let changes = manager.update_student(uid, updater)?;
+            Ok(Response::ok(serde_json::to_value(changes)?))
+        }
+        ("DELETE", ["students", uid]) => {
+            let uid = parse_uid(uid)?;
+            let policy = match request.param("policy") {
+                Some(policy) => parse_variant(policy)?,
+                None => DeletePolicy::default(),
+            };
+            if !manager.delete_student(uid, policy)? {
+                return Err(Error::NotFound(format!("学生不存在: {}", uid)));
+            }
+            Ok(Response::ok(json!({ "deleted": uid })))
+        }
+        ("POST", ["cash"]) => {
+            let payload: NewCash = parse_body(request)?;
+            let mut builder = CashBuilder::new(payload.amount);
+            if let Some(student_id) = payload.student_id {
+                builder = builder.student_id(student_id);
+            }
+            if let Some(note) = payload.note {
+                builder = builder.note(note);
+            }
+            let uid = manager.record_cash(builder)?;
+            Ok(Response {
+                status: 201,
+                body: json!({ "uid": uid }),
+            })
+        }
+        ("GET", ["cash", uid]) => {
+            let uid = parse_uid(uid)?;
+            let cash = manager
+                .get_cash(uid)?
+                .ok_or_else(|| Error::NotFound(format!("现金记录不存在: {}", uid)))?;
+            Ok(Response::ok(serde_json::to_value(cash)?))
+        }
+        ("GET", ["stats", "dashboard"]) => Ok(Response::ok(serde_json::to_value(
+            manager.get_dashboard_stats()?,
+        )?)),
+        (_, ["students"] | ["students", _] | ["cash"] | ["cash", _] | ["stats", "dashboard"]) => {
+            Ok(Response::error(
+                405,
+                "method_not_allowed",
+                format!("不支持的方法: {}", method),
+            ))
+        }
+        _ => Ok(Response::error(
+            404,
+            "not_found",
+            format!("未知路径: {}", request.path),
+        )),
+    }
+}
+
+fn list_students(manager: &QmxManager, request: &Request) -> Result<Response> {
+    let mut query = StudentQuery::new();
+    if let Some(name) = request.param("name") {
+        query = query.name_contains(name);
+    }
+    if let Some(class) = request.param("class") {
+        query = query.class(parse_variant(class)?);
+    }
+    if let Some(subject) = request.param("subject") {
+        query = query.subject(parse_variant(subject)?);
+    }
+    if let Some(limit) = request.param("limit") {
+        query = query.limit(limit.parse()?);
+    }
+    if let Some(offset) = request.param("offset") {
+        query = query.offset(offset.parse()?);
+    }
+    let result = manager.search_students_paged(query)?;
+    Ok(Response::ok(json!({
+        "items": result.items,
+        "total": result.total,
+        "offset": result.offset,
+        "limit": result.limit,
+    })))
+}
+
+fn parse_uid(segment: &str) -> Result<u64> {
+    segment
+        .parse()
+        .map_err(|_| Error::InvalidInput(format!("无效的UID: {}", segment)))
+}
+
+fn parse_body<T: DeserializeOwned>(request: &Request) -> Result<T> {
+    Ok(serde_json::from_slice(&request.body)?)
+}
+
+/// 按序列化名称解析枚举值，如 `TenTry`
+fn parse_variant<T: DeserializeOwned>(value: &str) -> Result<T> {
+    serde_json::from_value(Value::String(value.to_string()))
+        .map_err(|_| Error::InvalidInput(format!("无效的取值: {}", value)))
+}
+
+fn parse_query(query: &str) -> Vec<(String, String)> {
+    query
+        .split('&')
+        .filter(|pair| !pair.is_empty())
+        .map(|pair| {
+            let (key, value) = pair.split_once('=').unwrap_or((pair, ""));
+            (percent_decode(key), percent_decode(value))
+        })
+        .collect()
+}
+
+/// 解码 `%XX` 转义和表示空格的 `+`，无效的转义原样保留
+fn percent_decode(text: &str) -> String {
+    let bytes = text.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'%' if i + 2 < bytes.len() => {
+                match std::str::from_utf8(&bytes[i + 1..i + 3])
+                    .ok()
+                    .and_then(|hex| u8::from_str_radix(hex, 16).ok())
+                {
+                    Some(byte) => {
+                        decoded.push(byte);
+                        i += 3;
+                        continue;
+                    }
+                    None => decoded.push(b'%'),
+                }
+            }
+            b'+' => decoded.push(b' '),
+            byte => decoded.push(byte),
+        }
+        i += 1;
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+/// 内嵌 HTTP 服务
+pub struct HttpServer {
+    manager: Arc<QmxManager>,
+    listener: TcpListener,
+    token: Arc<str>,
+    workers: usize,
+    timeout: Duration,
+}
+
+impl HttpServer {
+    /// 绑定监听地址，端口为 0 时由系统分配，实际地址见 [`HttpServer::local_addr`]
+    ///
+    /// 请求需要带上 `Authorization: Bearer <token>`，`token` 为空时返回 [`Error::InvalidInput`]。
+    pub fn bind(
+        manager: Arc<QmxManager>,
+        addr: impl ToSocketAddrs,
+        token: impl Into<String>,
+    ) -> Result<Self> {
+        let token = token.into();
+        if token.is_empty() {
+            return Err(Error::InvalidInput(
+                "HTTP 服务的访问令牌不能为空".to_string(),
+            ));
+        }
+        let listener = TcpListener::bind(addr)?;
+        Ok(Self {
+            manager,
+            listener,
+            token: token.into(),
+            workers: DEFAULT_WORKERS,
+            timeout: DEFAULT_TIMEOUT,
+        })
+    }
+
+    /// 工作线程数，默认为 [`DEFAULT_WORKERS`]
+    pub fn workers(mut self, workers: usize) -> Self {
+        self.workers = workers.max(1);
+        self
+    }
+
+    /// 每个连接的读写超时，默认为 [`DEFAULT_TIMEOUT`]
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    pub fn local_addr(&self) -> Result<SocketAddr> {
+        Ok(self.listener.local_addr()?)
+    }
+
+    /// 持续接受连接，交给工作线程处理
+    ///
+    /// 无法创建工作线程时返回 [`Error::Io`]。
+    pub fn serve(self) -> Result<()> {
+        info!(
+            "HTTP 服务已启动: {}，工作线程 {} 个",
+            self.local_addr()?,
+            self.workers
+        );
+        let (sender, receiver) = sync_channel::<TcpStream>(self.workers * 4);
+        let receiver = Arc::new(Mutex::new(receiver));
+        for index in 0..self.workers {
+            let manager = self.manager.clone();
+            let token = self.token.clone();
+            let receiver = receiver.clone();
+            std::thread::Builder::new()
+                .name(format!("qmx-http-{}", index))
+                .spawn(move || worker(&manager, &token, &receiver))?;
+        }
+        for stream in self.listener.incoming() {
+            let stream = match stream {
+                Ok(stream) => stream,
+                Err(e) => {
+                    warn!("接受连接失败: {}", e);
+                    continue;
+                }
+            };
+            if let Err(e) = stream
+                .set_read_timeout(Some(self.timeout))
+                .and_then(|_| stream.set_write_timeout(Some(self.timeout)))
+            {
+                warn!("设置连接超时失败: {}", e);
+                continue;
+            }
+            match sender.try_send(stream) {
+                Ok(()) => {}
+                Err(TrySendError::Full(stream)) => {
+                    warn!("工作线程繁忙，拒绝连接");
+                    let busy = Response::error(503, "unavailable", "服务繁忙，请稍后重试");
+                    if let Err(e) = write_response(stream, &busy) {
+                        warn!("写入HTTP响应失败: {}", e);
+                    }
+                }
+                Err(TrySendError::Disconnected(_)) => {
+                    return Err(Error::State("HTTP 工作线程已全部退出".to_string()));
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// 工作线程：依次处理队列中的连接，队列关闭后退出
+fn worker(manager: &QmxManager, token: &str, receiver: &Mutex<Receiver<TcpStream>>) {
+    loop {
+        let stream = match receiver.lock().unwrap_or_else(|e| e.into_inner()).recv() {
+            Ok(stream) => stream,
+            Err(_) => return,
+        };
+        if let Err(e) = serve_connection(manager, token, stream) {
+            warn!("处理HTTP连接失败: {}", e);
+        }
+    }
+}
+
+fn serve_connection(manager: &QmxManager, token: &str, stream: TcpStream) -> Result<()> {
+    let mut reader = BufReader::new(stream.try_clone()?);
+    let response = match read_request(&mut reader) {
+        Ok((request, authorization)) => {
+            let response = if authorized(authorization.as_deref(), token) {
+                handle(manager, &request)
+            } else {
+                Response::error(401, "unauthorized", "缺少或无效的访问令牌")
+            };
+            info!("{} {} -> {}", request.method, request.path, response.status);
+            response
+        }
+        Err(e) => {
+            let response = Response::error(400, "bad_request", e.to_string());
+            write_response(&stream, &response)?;
+            // 丢弃未读完的请求再关闭连接，否则客户端可能收到连接重置而读不到响应；
+            // 读取量和时间都受限，超时或出错时直接关闭
+            stream.shutdown(Shutdown::Write)?;
+            let _ = std::io::copy(
+                &mut reader.take(MAX_BODY_LEN as u64),
+                &mut std::io::sink(),
+            );
+            return Ok(());
+        }
+    };
+    write_response(&stream, &response)
+}
+
+/// 检查 `Authorization` 请求头中的令牌，逐字节比较全部内容，耗时与不匹配的位置无关
+fn authorized(authorization: Option<&str>, token: &str) -> bool {
+    let Some(provided) = authorization.and_then(|value| value.strip_prefix("Bearer ")) else {
+        return false;
+    };
+    let (provided, token) = (provided.trim().as_bytes(), token.as_bytes());
+    provided.len() == token.len()
+        && provided
+            .iter()
+            .zip(token)
+            .fold(0, |diff, (a, b)| diff | (a ^ b))
+            == 0
+}
+
+/// 读取一行，累计读取超过 `remaining` 字节时返回错误
+fn read_line_limited<R: BufRead>(
+    reader: &mut R,
+    line: &mut String,
+    remaining: &mut usize,
+) -> Result<usize> {
+    let read = reader.by_ref().take(*remaining as u64).read_line(line)?;
+    *remaining -= read;
+    if read > 0 && !line.ends_with('\n') && *remaining == 0 {
+        return Err(Error::InvalidInput(format!(
+            "请求头过大，超过 {} 字节",
+            MAX_HEADER_LEN
+        )));
+    }
+    Ok(read)
+}
+
+/// 读取请求，同时返回 `Authorization` 请求头
+fn read_request<R: BufRead>(reader: &mut R) -> Result<(Request, Option<String>)> {
+    let mut remaining = MAX_HEADER_LEN;
+    let mut line = String::new();
+    read_line_limited(reader, &mut line, &mut remaining)?;
+    let mut parts = line.split_whitespace();
+    let (Some(method), Some(target)) = (parts.next(), parts.next()) else {
+        return Err(Error::InvalidInput(format!(
+            "无效的请求行: {}",
+            line.trim_end()
+        )));
+    };
+    let (method, target) = (method.to_string(), target.to_string());
+
+    let mut content_length = 0;
+    let mut authorization = None;
+    loop {
+        line.clear();
+        if read_line_limited(reader, &mut line, &mut remaining)? == 0 {
+            break;
+        }
+        let header = line.trim_end();
+        if header.is_empty() {
+            break;
+        }
+        let Some((name, value)) = header.split_once(':') else {
+            continue;
+        };
+        if name.trim().eq_ignore_ascii_case("content-length") {
+            content_length = value.trim().parse()?;
+        } else if name.trim().eq_ignore_ascii_case("authorization") {
+            authorization = Some(value.trim().to_string());
+        }
+    }
+    if content_length > MAX_BODY_LEN {
+        return Err(Error::InvalidInput(format!(
+            "请求体过大: {} 字节",
+            content_length
+        )));
+    }
+    let mut body = vec![0; content_length];
+    reader.read_exact(&mut body)?;
+    Ok((Request::new(method, &target, body), authorization))
+}
+
+fn write_response<W: Write>(mut writer: W, response: &Response) -> Result<()> {
+    let body = serde_json::to_vec(&response.body)?;
+    write!(
+        writer,
+        "HTTP/1.1 {} {}\r\nContent-Type: application/json; charset=utf-8\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+        response.status,
+        reason_phrase(response.status),
+        body.len()
+    )?;
+    writer.write_all(&body)?;
+    writer.flush()?;
+    Ok(())
+}
+
+fn reason_phrase(status: u16) -> &'static str {
+    match status {
+        200 => "OK",
+        201 => "Created",
+        400 => "Bad Request",
+        401 => "Unauthorized",
+        403 => "Forbidden",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        409 => "Conflict",
+        503 => "Service Unavailable",
+        _ => "Internal Server Error",
+    }
+}
@@ -0,0 +1,259 @@
+//! 确定性 ID 生成
+//!
+//! 默认情况下学生和现金记录的 UID 来自全局计数器（`STUDENT_UID_COUNTER`/`CASH_UID_COUNTER`），
+//! 同一进程内的多个测试或多台机器之间会互相影响。
+//! [`IdNamespace`] 为每个命名空间提供独立、可复现的序列：
+//! UID 的高 16 位是命名空间，低 48 位是序号，不同命名空间生成的 UID 不会冲突。
+//!
+//! 多个安装实例的数据需要合并时，也可以使用 [`TimeOrderedIds`]：UID 由毫秒时间戳、
+//! 节点号和毫秒内序号组成，各实例只需配置不同的节点号，无需协调计数器。
+//! 两种方式生成的 UID 都是 `u64`，查询和数据库无需区分。通过
+//! [`crate::QmxManager::with_id_strategy`] 选择 [`IdStrategy`]。
+
+use crate::clock::Clock;
+use chrono::{DateTime, Utc};
+use log::debug;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+
+/// 序号占用的位数
+pub const SEQUENCE_BITS: u32 = 48;
+const SEQUENCE_MASK: u64 = (1 << SEQUENCE_BITS) - 1;
+
+/// 拼接命名空间与序号得到 UID
+pub fn compose_id(namespace: u16, sequence: u64) -> u64 {
+    ((namespace as u64) << SEQUENCE_BITS) | (sequence & SEQUENCE_MASK)
+}
+
+/// 从 UID 中取出命名空间
+pub fn namespace_of(uid: u64) -> u16 {
+    (uid >> SEQUENCE_BITS) as u16
+}
+
+/// 从 UID 中取出序号
+pub fn sequence_of(uid: u64) -> u64 {
+    uid & SEQUENCE_MASK
+}
+
+/// 新建学生和现金记录的 UID 分配策略
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum IdStrategy {
+    /// 全局计数器（默认），见 `STUDENT_UID_COUNTER`/`CASH_UID_COUNTER`
+    #[default]
+    Counter,
+    /// 命名空间前缀加序号，见 [`IdNamespace`]
+    Namespaced(u16),
+    /// 时间有序，节点号取值 `0..1024`，见 [`TimeOrderedIds`]
+    TimeOrdered { node: u16 },
+}
+
+/// UID 分配器，由管理器在新建学生和现金记录时调用
+pub trait IdGenerator: Send + Sync {
+    /// 分配下一个学生 UID
+    fn next_student_uid(&self) -> u64;
+
+    /// 分配下一个现金记录 UID
+    fn next_cash_uid(&self) -> u64;
+
+    /// 根据已有 UID 推进内部状态，确保之后分配的 UID 不与其重复
+    fn observe_existing(
+        &self,
+        student_uids: &mut dyn Iterator<Item = u64>,
+        cash_uids: &mut dyn Iterator<Item = u64>,
+    );
+}
+
+/// 单个命名空间内的确定性 UID 序列
+///
+/// 学生和现金记录各自使用独立的序号，序号从 1 开始。
+#[derive(Debug)]
+pub struct IdNamespace {
+    namespace: u16,
+    next_student: AtomicU64,
+    next_cash: AtomicU64,
+}
+
+impl IdNamespace {
+    pub fn new(namespace: u16) -> Self {
+        Self {
+            namespace,
+            next_student: AtomicU64::new(1),
+            next_cash: AtomicU64::new(1),
+        }
+    }
+
+    pub fn namespace(&self) -> u16 {
+        self.namespace
+    }
+
+    /// 根据已有 UID 推进序列，确保之后分配的 UID 不与其重复
+    ///
+    /// 不属于本命名空间的 UID 会被忽略。
+    pub fn observe_existing(
+        &self,
+        student_uids: impl IntoIterator<Item = u64>,
+        cash_uids: impl IntoIterator<Item = u64>,
+    ) {
+        let advance = |counter: &AtomicU64, uids: &mut dyn Iterator<Item = u64>| {
+            let max = uids
+                .filter(|&uid| namespace_of(uid) == self.namespace)
+                .map(sequence_of)
+                .max();
+            if let Some(max) = max {
+                counter.fetch_max(max + 1, Ordering::SeqCst);
+            }
+        };
+        advance(&self.next_student, &mut student_uids.into_iter());
+        advance(&self.next_cash, &mut cash_uids.into_iter());
+        debug!(
+            "命名空间 {} 的序列推进到: 学生 {}, 现金 {}",
+            self.namespace,
+            self.next_student.load(Ordering::SeqCst),
+            self.next_cash.load(Ordering::SeqCst)
+        );
+    }
+
+    /// 分配下一个学生 UID
+    pub fn next_student_uid(&self) -> u64 {
+        compose_id(
+            self.namespace,
+            self.next_student.fetch_add(1, Ordering::SeqCst),
+        )
+    }
+
+    /// 分配下一个现金记录 UID
+    pub fn next_cash_uid(&self) -> u64 {
+        compose_id(
+            self.namespace,
+            self.next_cash.fetch_add(1, Ordering::SeqCst),
+        )
+    }
+}
+
+impl IdGenerator for IdNamespace {
+    fn next_student_uid(&self) -> u64 {
+        IdNamespace::next_student_uid(self)
+    }
+
+    fn next_cash_uid(&self) -> u64 {
+        IdNamespace::next_cash_uid(self)
+    }
+
+    fn observe_existing(
+        &self,
+        student_uids: &mut dyn Iterator<Item = u64>,
+        cash_uids: &mut dyn Iterator<Item = u64>,
+    ) {
+        IdNamespace::observe_existing(self, student_uids, cash_uids);
+    }
+}
+
+/// 节点号占用的位数
+pub const NODE_BITS: u32 = 10;
+/// 毫秒内序号占用的位数
+pub const TICK_SEQUENCE_BITS: u32 = 12;
+/// 时间有序 UID 的时间起点（2024-01-01T00:00:00Z）的毫秒时间戳
+pub const TIME_ORDERED_EPOCH_MS: i64 = 1_704_067_200_000;
+const NODE_MASK: u64 = (1 << NODE_BITS) - 1;
+const TICK_SEQUENCE_MASK: u64 = (1 << TICK_SEQUENCE_BITS) - 1;
+
+/// 从时间有序 UID 中取出生成时间
+pub fn timestamp_of(uid: u64) -> DateTime<Utc> {
+    let millis = (uid >> (NODE_BITS + TICK_SEQUENCE_BITS)) as i64 + TIME_ORDERED_EPOCH_MS;
+    DateTime::from_timestamp_millis(millis).unwrap_or_default()
+}
+
+/// 从时间有序 UID 中取出节点号
+pub fn node_of(uid: u64) -> u16 {
+    ((uid >> TICK_SEQUENCE_BITS) & NODE_MASK) as u16
+}
+
+/// 时间有序的 UID 序列
+///
+/// 布局与 UUIDv7 类似，按位从高到低为：41 位毫秒时间戳（自 [`TIME_ORDERED_EPOCH_MS`] 起）、
+/// 10 位节点号、12 位毫秒内序号，最高位恒为 0。UID 随生成时间递增，
+/// 节点号不同的实例生成的 UID 不会冲突。学生和现金记录共用同一序列。
+///
+/// 时间取自传入的时钟；时钟回拨或同一毫秒内序号用尽时沿用上一个时间戳继续递增，
+/// 保证同一实例分配的 UID 严格递增。
+pub struct TimeOrderedIds {
+    node: u16,
+    clock: Arc<dyn Clock>,
+    /// 上次分配使用的（相对时间戳，序号）
+    last: Mutex<(u64, u64)>,
+}
+
+impl std::fmt::Debug for TimeOrderedIds {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("TimeOrderedIds")
+            .field("node", &self.node)
+            .finish()
+    }
+}
+
+impl TimeOrderedIds {
+    /// 节点号超过 10 位时返回 `None`
+    pub fn new(node: u16, clock: Arc<dyn Clock>) -> Option<Self> {
+        if u64::from(node) > NODE_MASK {
+            return None;
+        }
+        Some(Self {
+            node,
+            clock,
+            last: Mutex::new((0, 0)),
+        })
+    }
+
+    pub fn node(&self) -> u16 {
+        self.node
+    }
+
+    fn next_uid(&self) -> u64 {
+        let now = (self.clock.now().timestamp_millis() - TIME_ORDERED_EPOCH_MS).max(0) as u64;
+        let mut last = self.last.lock().unwrap_or_else(|e| e.into_inner());
+        let (mut tick, mut sequence) = *last;
+        if now > tick {
+            tick = now;
+            sequence = 0;
+        } else if sequence < TICK_SEQUENCE_MASK {
+            sequence += 1;
+        } else {
+            tick += 1;
+            sequence = 0;
+        }
+        *last = (tick, sequence);
+        (tick << (NODE_BITS + TICK_SEQUENCE_BITS))
+            | (u64::from(self.node) << TICK_SEQUENCE_BITS)
+            | sequence
+    }
+}
+
+impl IdGenerator for TimeOrderedIds {
+    fn next_student_uid(&self) -> u64 {
+        self.next_uid()
+    }
+
+    fn next_cash_uid(&self) -> u64 {
+        self.next_uid()
+    }
+
+    fn observe_existing(
+        &self,
+        student_uids: &mut dyn Iterator<Item = u64>,
+        cash_uids: &mut dyn Iterator<Item = u64>,
+    ) {
+        let max = student_uids
+            .chain(cash_uids)
+            .filter(|&uid| node_of(uid) == self.node)
+            .max();
+        if let Some(max) = max {
+            let observed = (
+                max >> (NODE_BITS + TICK_SEQUENCE_BITS),
+                max & TICK_SEQUENCE_MASK,
+            );
+            let mut last = self.last.lock().unwrap_or_else(|e| e.into_inner());
+            *last = (*last).max(observed);
+            debug!("节点 {} 的时间有序序列推进到: {:?}", self.node, *last);
+        }
+    }
+}
@@ -0,0 +1,152 @@
+//! 数据一致性检查
+//!
+//! [`crate::QmxManager::verify_integrity`] 检查数据之间的引用和计数是否一致，只报告问题，不做修改：
+//!
+//! - 关联的学生已不存在的现金记录
+//! - 分期计划中缺少或重复的期数
+//! - 小于等于已有最大 UID 的计数器（继续分配会产生重复 UID）
+//!
+//! 剩余课时以无符号数保存，负数在加载时就会解析失败，因此不在检查范围内。
+
+use crate::cash::{CASH_UID_COUNTER, PLAN_ID_COUNTER};
+use crate::database::Database;
+use crate::student::STUDENT_UID_COUNTER;
+use std::collections::BTreeMap;
+use std::sync::atomic::Ordering;
+
+/// 一致性检查结果
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct IntegrityReport {
+    /// 关联的学生不存在的现金记录
+    pub orphaned_cash: Vec<OrphanedCash>,
+    /// 期数缺失或重复的分期计划
+    pub installment_plans: Vec<PlanIssue>,
+    /// 落后于已有 UID 的计数器
+    pub counters: Vec<CounterIssue>,
+}
+
+impl IntegrityReport {
+    /// 是否没有发现任何问题
+    pub fn is_ok(&self) -> bool {
+        self.orphaned_cash.is_empty() && self.installment_plans.is_empty() && self.counters.is_empty()
+    }
+
+    /// 发现的问题总数
+    pub fn issue_count(&self) -> usize {
+        self.orphaned_cash.len() + self.installment_plans.len() + self.counters.len()
+    }
+}
+
+/// 关联的学生不存在的现金记录
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OrphanedCash {
+    pub cash_uid: u64,
+    pub student_id: u64,
+}
+
+/// 分期计划的期数问题
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PlanIssue {
+    pub plan_id: u64,
+    /// 1 到总期数之间缺少的期数
+    pub missing: Vec<u32>,
+    /// 出现多次的期数
+    pub duplicated: Vec<u32>,
+}
+
+/// UID 计数器
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Counter {
+    Student,
+    Cash,
+    InstallmentPlan,
+}
+
+/// 计数器的下一个值不大于已有的最大 UID
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CounterIssue {
+    pub counter: Counter,
+    /// 计数器当前值（下一个将分配的 UID）
+    pub next: u64,
+    /// 数据中已有的最大 UID
+    pub max_uid: u64,
+}
+
+/// 检查 `db` 的一致性
+///
+/// `uid_counters` 为 false 时跳过学生和现金 UID 计数器的检查（使用自定义 ID 策略时计数器不参与分配）。
+pub fn check(db: &Database, uid_counters: bool) -> IntegrityReport {
+    let mut report = IntegrityReport::default();
+
+    for cash in db.cash.iter().map(|(_, cash)| cash) {
+        if let Some(student_id) = cash.student_id
+            && db.student.get(&student_id).is_none()
+        {
+            report.orphaned_cash.push(OrphanedCash {
+                cash_uid: cash.uid,
+                student_id,
+            });
+        }
+    }
+
+    // 总期数以计划数据库为准，找不到计划时按已有的最大期数检查
+    let mut plans: BTreeMap<u64, (u32, BTreeMap<u32, usize>)> = BTreeMap::new();
+    for installment in db.cash.iter().filter_map(|(_, cash)| cash.installment.as_ref()) {
+        let (total, periods) = plans.entry(installment.plan_id).or_insert_with(|| {
+            let total = db
+                .cash
+                .plans
+                .get(&installment.plan_id)
+                .map_or(0, |plan| plan.total_installments);
+            (total, BTreeMap::new())
+        });
+        *total = (*total).max(installment.current_installment);
+        *periods.entry(installment.current_installment).or_default() += 1;
+    }
+    for (plan_id, (total, periods)) in plans {
+        let missing: Vec<u32> = (1..=total).filter(|n| !periods.contains_key(n)).collect();
+        let duplicated: Vec<u32> = periods
+            .iter()
+            .filter(|&(_, &count)| count > 1)
+            .map(|(&n, _)| n)
+            .collect();
+        if !missing.is_empty() || !duplicated.is_empty() {
+            report.installment_plans.push(PlanIssue {
+                plan_id,
+                missing,
+                duplicated,
+            });
+        }
+    }
+
+    let mut check_counter = |counter, next: u64, max_uid: Option<u64>| {
+        if let Some(max_uid) = max_uid
+            && next <= max_uid
+        {
+            report.counters.push(CounterIssue {
+                counter,
+                next,
+                max_uid,
+            });
+        }
+    };
+    if uid_counters {
+        check_counter(
+            Counter::Student,
+            STUDENT_UID_COUNTER.load(Ordering::SeqCst),
+            db.student.iter().map(|(&uid, _)| uid).max(),
+        );
+        check_counter(
+            Counter::Cash,
+            CASH_UID_COUNTER.load(Ordering::SeqCst),
+            db.cash.iter().map(|(&uid, _)| uid).max(),
+        );
+    }
+    check_counter(
+        Counter::InstallmentPlan,
+        PLAN_ID_COUNTER.load(Ordering::SeqCst),
+        db.cash.max_plan_id(),
+    );
+
+    report
+}
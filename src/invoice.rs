@@ -0,0 +1,231 @@
+//! 收据生成
+//!
+//! 为任意现金记录生成带顺序编号的收据。收据编号由管理器的 [`ReceiptCounter`] 分配，
+//! 持久化在管理器的数据目录中，每次分配后立即原子写入，保证编号不会重复使用。
+
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+use crate::cash::Cash;
+use crate::common::write_atomic;
+use crate::error::{Error, Result};
+use crate::money::{Currency, Money};
+use crate::student::Student;
+use chrono::{DateTime, Utc};
+use log::{debug, info};
+use serde::{Deserialize, Serialize};
+
+/// 收据编号文件名，与数据文件放在同一目录
+pub const RECEIPT_COUNTER_FILE: &str = "receipt_counter";
+
+/// 收据抬头（机构信息）
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, Default)]
+pub struct InstitutionHeader {
+    pub name: String,
+    pub address: Option<String>,
+    pub phone: Option<String>,
+}
+
+impl InstitutionHeader {
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            address: None,
+            phone: None,
+        }
+    }
+
+    pub fn address(mut self, address: impl Into<String>) -> Self {
+        self.address = Some(address.into());
+        self
+    }
+
+    pub fn phone(mut self, phone: impl Into<String>) -> Self {
+        self.phone = Some(phone.into());
+        self
+    }
+}
+
+/// 收据
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Receipt {
+    /// 顺序收据编号
+    pub number: u64,
+    pub header: InstitutionHeader,
+    /// 对应的现金记录 UID
+    pub cash_uid: u64,
+    pub student_id: Option<u64>,
+    pub student_name: Option<String>,
+    /// 金额
+    pub amount: Money,
+    pub note: Option<String>,
+    /// 现金记录的发生时间
+    pub paid_at: DateTime<Utc>,
+    /// 收据开具时间
+    pub issued_at: DateTime<Utc>,
+}
+
+impl Receipt {
+    /// 为现金记录生成收据，从 `counter` 分配新的收据编号
+    pub fn generate(
+        cash: &Cash,
+        student: Option<&Student>,
+        header: &InstitutionHeader,
+        counter: &ReceiptCounter,
+        issued_at: DateTime<Utc>,
+    ) -> Result<Self> {
+        let number = counter.allocate()?;
+        info!("为现金记录 {} 生成收据，编号: {}", cash.uid, number);
+        Ok(Self {
+            number,
+            header: header.clone(),
+            cash_uid: cash.uid,
+            student_id: cash.student_id,
+            student_name: student.and_then(|s| s.name()).map(str::to_string),
+            amount: cash.cash,
+            note: cash.note.clone(),
+            paid_at: cash.created_at,
+            issued_at,
+        })
+    }
+
+    /// 格式化后的收据编号，如 `No.000042`
+    pub fn formatted_number(&self) -> String {
+        format!("No.{:06}", self.number)
+    }
+
+    /// 以主单位（人民币为元）格式化金额，如 `1234.56`
+    pub fn formatted_amount(&self) -> String {
+        self.amount.to_plain_string()
+    }
+
+    /// 收据中的字段行（标签，内容）
+    fn lines(&self) -> Vec<(&'static str, String)> {
+        let mut lines = Vec::new();
+        if let Some(address) = &self.header.address {
+            lines.push(("地址", address.clone()));
+        }
+        if let Some(phone) = &self.header.phone {
+            lines.push(("电话", phone.clone()));
+        }
+        lines.push(("收据编号", self.formatted_number()));
+        if let Some(name) = &self.student_name {
+            lines.push(("学生", name.clone()));
+        }
+        let unit = match self.amount.currency {
+            Currency::Cny => "元",
+            currency => currency.code(),
+        };
+        lines.push(("金额", format!("{} {}", self.formatted_amount(), unit)));
+        lines.push(("日期", self.paid_at.format("%Y-%m-%d").to_string()));
+        if let Some(note) = &self.note {
+            lines.push(("备注", note.clone()));
+        }
+        lines.push((
+            "开具时间",
+            self.issued_at.format("%Y-%m-%d %H:%M:%S").to_string(),
+        ));
+        lines
+    }
+
+    /// 渲染为纯文本
+    pub fn render_text(&self) -> String {
+        let mut out = format!("{}\n收据\n", self.header.name);
+        for (label, value) in self.lines() {
+            out.push_str(&format!("{}: {}\n", label, value));
+        }
+        out
+    }
+
+    /// 渲染为 HTML 片段，所有内容都会转义
+    pub fn render_html(&self) -> String {
+        let mut out = format!(
+            "<div class=\"receipt\">\n<h1>{}</h1>\n<h2>收据</h2>\n<table>\n",
+            escape_html(&self.header.name)
+        );
+        for (label, value) in self.lines() {
+            out.push_str(&format!(
+                "<tr><th>{}</th><td>{}</td></tr>\n",
+                label,
+                escape_html(&value)
+            ));
+        }
+        out.push_str("</table>\n</div>\n");
+        out
+    }
+}
+
+pub(crate) fn escape_html(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// 收据编号计数器
+///
+/// 每个管理器各有一个，保存在管理器数据目录的 `receipt_counter` 文件中，
+/// 不同数据目录的管理器互不影响。使用存储后端的管理器没有数据目录，编号只保存在内存中。
+#[derive(Debug)]
+pub struct ReceiptCounter {
+    path: Option<PathBuf>,
+    /// 下一个可用的编号，同时串行化编号的分配
+    next: Mutex<u64>,
+}
+
+impl ReceiptCounter {
+    /// 从文件加载计数器，文件不存在时从 1 开始
+    pub fn open(path: impl Into<PathBuf>) -> Result<Self> {
+        let path = path.into();
+        let next = match std::fs::read_to_string(&path) {
+            Ok(content) => content.trim().parse::<u64>().map_err(|e| {
+                Error::InvalidInput(format!("解析收据编号文件 {} 失败: {}", path.display(), e))
+            })?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                debug!("未找到收据编号文件 {}，从默认值1开始", path.display());
+                1
+            }
+            Err(e) => return Err(Error::from(e)),
+        };
+        Ok(Self {
+            path: Some(path),
+            next: Mutex::new(next),
+        })
+    }
+
+    /// 只保存在内存中的计数器，从 1 开始
+    pub fn in_memory() -> Self {
+        Self {
+            path: None,
+            next: Mutex::new(1),
+        }
+    }
+
+    /// 下一个可用的收据编号
+    pub fn peek(&self) -> u64 {
+        *self.next.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// 分配一个新的收据编号并立即持久化，持久化失败时不分配
+    pub fn allocate(&self) -> Result<u64> {
+        let mut next = self.next.lock().unwrap_or_else(|e| e.into_inner());
+        let number = *next;
+        let following = number
+            .checked_add(1)
+            .ok_or_else(|| Error::State("收据编号已用尽".to_string()))?;
+        if let Some(path) = &self.path {
+            write_atomic(path, following.to_string().as_bytes())?;
+            debug!("成功保存收据编号: {} 到 {}", following, path.display());
+        }
+        *next = following;
+        Ok(number)
+    }
+}
@@ -0,0 +1,164 @@
+//! 大型数据文件的延迟加载
+//!
+//! 成绩数组和备注通常占数据文件的大部分。[`crate::student::StudentDatabase::read_lazy`]
+//! 和 [`crate::cash::CashDatabase::read_lazy`] 加载时跳过这些字段（成绩为空、备注为空），
+//! 需要时再通过 `load_details` 从原文件读取指定记录的详细字段。
+//!
+//! 延迟加载适合只做查询、统计收支的场景。详细字段未加载完的数据库不能保存，
+//! 否则会丢失成绩和备注；保存前需调用 `load_all_details`。
+
+use crate::common::{FileCodec, read_json_seed};
+use crate::error::Result;
+use serde::de::{DeserializeSeed, IgnoredAny, MapAccess, Visitor};
+use serde::{Deserialize, Deserializer};
+use std::cell::Cell;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+use std::marker::PhantomData;
+
+thread_local! {
+    static SKIP_DETAILS: Cell<bool> = const { Cell::new(false) };
+}
+
+/// 反序列化详细字段：延迟加载时跳过字段值并返回默认值
+pub(crate) fn deserialize_detail<'de, D, T>(deserializer: D) -> std::result::Result<T, D::Error>
+where
+    D: Deserializer<'de>,
+    T: Deserialize<'de> + Default,
+{
+    if SKIP_DETAILS.with(Cell::get) {
+        IgnoredAny::deserialize(deserializer)?;
+        Ok(T::default())
+    } else {
+        T::deserialize(deserializer)
+    }
+}
+
+/// 在 `f` 执行期间跳过详细字段
+pub(crate) fn skipping_details<R>(f: impl FnOnce() -> R) -> R {
+    struct Reset(bool);
+    impl Drop for Reset {
+        fn drop(&mut self) {
+            SKIP_DETAILS.with(|skip| skip.set(self.0));
+        }
+    }
+    let _reset = Reset(SKIP_DETAILS.with(|skip| skip.replace(true)));
+    f()
+}
+
+/// 尚未加载详细字段的记录
+#[derive(Debug, Clone, Default)]
+pub(crate) struct PendingDetails {
+    /// 详细字段所在的数据文件
+    pub path: String,
+    pub uids: BTreeSet<u64>,
+}
+
+impl PendingDetails {
+    pub fn new(path: &str, uids: impl IntoIterator<Item = u64>) -> Self {
+        Self {
+            path: path.to_string(),
+            uids: uids.into_iter().collect(),
+        }
+    }
+
+    /// `uids` 中仍未加载的记录
+    pub fn among(&self, uids: &[u64]) -> BTreeSet<u64> {
+        uids.iter()
+            .copied()
+            .filter(|uid| self.uids.contains(uid))
+            .collect()
+    }
+}
+
+/// 从数据文件读取 `wanted` 中记录的详细字段，其余记录直接跳过
+pub(crate) fn read_details<D>(path: &str, wanted: &BTreeSet<u64>) -> Result<BTreeMap<u64, D>>
+where
+    D: for<'de> Deserialize<'de>,
+{
+    read_json_seed(
+        path,
+        &FileCodec::process_default(),
+        DetailsFile {
+            wanted,
+            marker: PhantomData,
+        },
+    )
+}
+
+/// 数据文件外层：`{"xxx_data": {...}, "next_uid": ...}`
+struct DetailsFile<'a, D> {
+    wanted: &'a BTreeSet<u64>,
+    marker: PhantomData<D>,
+}
+
+impl<'de, D: Deserialize<'de>> DeserializeSeed<'de> for DetailsFile<'_, D> {
+    type Value = BTreeMap<u64, D>;
+
+    fn deserialize<De: Deserializer<'de>>(
+        self,
+        deserializer: De,
+    ) -> std::result::Result<Self::Value, De::Error> {
+        deserializer.deserialize_map(self)
+    }
+}
+
+impl<'de, D: Deserialize<'de>> Visitor<'de> for DetailsFile<'_, D> {
+    type Value = BTreeMap<u64, D>;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("数据库文件")
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> std::result::Result<Self::Value, A::Error> {
+        let mut details = BTreeMap::new();
+        while let Some(key) = map.next_key::<String>()? {
+            if key.ends_with("_data") {
+                details = map.next_value_seed(DetailsRecords {
+                    wanted: self.wanted,
+                    marker: PhantomData,
+                })?;
+            } else {
+                map.next_value::<IgnoredAny>()?;
+            }
+        }
+        Ok(details)
+    }
+}
+
+/// 记录表：只解析 `wanted` 中的记录
+struct DetailsRecords<'a, D> {
+    wanted: &'a BTreeSet<u64>,
+    marker: PhantomData<D>,
+}
+
+impl<'de, D: Deserialize<'de>> DeserializeSeed<'de> for DetailsRecords<'_, D> {
+    type Value = BTreeMap<u64, D>;
+
+    fn deserialize<De: Deserializer<'de>>(
+        self,
+        deserializer: De,
+    ) -> std::result::Result<Self::Value, De::Error> {
+        deserializer.deserialize_map(self)
+    }
+}
+
+impl<'de, D: Deserialize<'de>> Visitor<'de> for DetailsRecords<'_, D> {
+    type Value = BTreeMap<u64, D>;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("以 UID 为键的记录表")
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> std::result::Result<Self::Value, A::Error> {
+        let mut details = BTreeMap::new();
+        while let Some(uid) = map.next_key::<u64>()? {
+            if self.wanted.contains(&uid) {
+                details.insert(uid, map.next_value::<D>()?);
+            } else {
+                map.next_value::<IgnoredAny>()?;
+            }
+        }
+        Ok(details)
+    }
+}
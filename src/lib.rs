@@ -30,10 +30,10 @@
 //! let mut student = student::Student::new();
 //! student
 //!     .set_name("张三".to_string())
-//!     .set_age(18)
+//!     .set_age(Some(18))
 //!     .set_class(student::Class::TenTry)
 //!     .set_subject(student::Subject::Shooting)
-//!     .add_ring(9.5);
+//!     .add_ring(9.5)?;
 //!
 //! // 设置会员期限
 //! student.set_membership_dates(
@@ -69,10 +69,11 @@
 //!
 //! # fn main() -> qmx_backend_lib::error::Result<()> {
 //! // 初始化管理器
-//! let manager = QmxManager::new(true)?; // auto_save = true
+//! let manager = QmxManager::builder().auto_save(AutoSave::Immediate).build()?;
 //!
 //! // 使用 Builder 模式创建学生
-//! let student_builder = StudentBuilder::new("李四".to_string(), 20)
+//! let student_builder = StudentBuilder::new("李四")
+//!     .age(20)
 //!     .class(student::Class::Month)
 //!     .subject(student::Subject::Archery);
 //!
@@ -108,28 +109,129 @@
 //!
 //! - [`student`] - 学生管理和会员系统
 //! - [`cash`] - 现金流和分期付款管理
+//! - [`plan`] - 分期计划
+//! - [`money`] - 金额与币种
 //! - [`database`] - 数据库初始化和持久化
 //! - [`stats`] - 统计分析功能
 //! - [`manager`] - 现代化统一 API (v2)
 //! - [`common`] - 通用数据库 trait 和工具
+//! - [`clock`] - 可注入的时钟
+//! - [`coach`] - 教练管理
+//! - [`catalog`] - 价目表与售卖
+//! - [`recurring`] - 房租、工资等定期支出
+//! - [`schedule`] - 课程排期
+//! - [`id`] - 确定性、分命名空间的 ID 生成
+//! - [`audit`] - 修改操作的审计日志
+//! - [`events`] - 数据变更事件订阅
+//! - [`attachment`] - 学生附件存储
+//! - [`validation`] - 可插拔的校验规则
+//! - [`permissions`] - 基于角色的权限控制
+//! - [`privacy`] - 个人数据导出与清除
+//! - [`export`] - 导出为表格格式
+//! - [`statement`] - 学生对账单
+//! - `ffi` - C 语言绑定（需启用 `ffi` feature）
+//! - `http` - 内嵌 HTTP API 服务（需启用 `http-server` feature）
+//! - `schema` - 数据类型的 JSON Schema（需启用 `schema` feature）
+//! - `sync` - 多台电脑之间的数据同步（需启用 `sync` feature）
+//! - `async_manager` - 不阻塞调用线程的异步 API（需启用 `tokio` feature）
+//! - [`backup`] - 数据备份与恢复
+//! - [`snapshot`] - 有风险操作前的内存快照
+//! - [`encryption`] - 数据文件的静态加密
+//! - [`compression`] - 数据文件压缩
 
+mod archive;
+#[cfg(feature = "tokio")]
+pub mod async_manager;
+pub mod attachment;
+pub mod audit;
+mod autosave;
+pub mod backup;
+pub mod bundle;
 pub mod cash;
+pub mod catalog;
+pub mod checksum;
+pub mod clock;
+pub mod coach;
 pub mod common;
+pub mod compression;
 pub mod database;
+pub mod encryption;
+pub mod events;
+pub mod export;
+#[cfg(feature = "ffi")]
+pub mod ffi;
+#[cfg(feature = "http-server")]
+pub mod http;
+pub mod id;
 pub mod init;
+pub mod integrity;
+pub mod invoice;
+pub mod lazy;
+pub mod log_policy;
+pub mod merge;
 pub mod manager;
+pub mod money;
+pub mod permissions;
+pub mod plan;
+pub mod privacy;
+pub mod recovery;
+pub mod recurring;
 pub mod save;
+pub mod schedule;
+pub mod snapshot;
+pub mod statement;
+#[cfg(feature = "schema")]
+pub mod schema;
 pub mod stats;
+pub mod storage;
 pub mod student;
+#[cfg(feature = "sync")]
+pub mod sync;
+pub mod validation;
+pub mod wal;
 pub mod error;
 
 // 新的统一API入口
 pub use manager::{
-    CashBuilder, CashQuery, CashUpdater, FinancialStats, MembershipStatus, QmxManager,
-    StudentBuilder, StudentQuery, StudentStats, StudentUpdater, TimePeriod,
+    AutoSave, CashBuilder, CashQuery, CatalogItemBuilder, CoachBuilder, CashSortKey, CashUpdater, DeletePolicy, DuplicateGuard, DuplicatePolicy, FieldChange, CreatedPlan, InstallmentPlanBuilder,
+    ManagerConfig, QmxManagerBuilder, RecurringExpenseBuilder,
+    FinancialStats, Limits, MembershipStatus, QmxManager, SearchResult, SortOrder, StudentBalance, StudentBuilder,
+    ScoreTrend, SessionBuilder, StudentQuery, StudentRanking, StudentSortKey, StudentStats, StudentUpdater, TimePeriod,
 };
+#[cfg(feature = "tokio")]
+pub use async_manager::AsyncQmxManager;
 
 // 原有API（保持向后兼容）
-pub use common::{Database, HasUid};
-pub use stats::{DashboardStats, get_dashboard_stats};
+pub use attachment::Attachment;
+pub use audit::{AuditAction, AuditEntity, AuditEntry};
+pub use backup::{BackupInfo, RetentionPolicy};
+pub use bundle::{BundleImportReport, BundleManifest, MergeStrategy};
+pub use clock::{Clock, FixedClock, SystemClock};
+pub use catalog::{CatalogItem, ItemEffect};
+pub use coach::Coach;
+pub use common::{CustomValue, Database, FileCodec, HasUid, SalvageReport};
+pub use compression::{Compression, set_compression};
+pub use encryption::{EncryptionKey, set_encryption_key};
+pub use events::{ChangeEvent, Event, EventKind, SubscriptionId};
+pub use id::IdStrategy;
+pub use integrity::IntegrityReport;
+pub use invoice::{InstitutionHeader, Receipt, ReceiptCounter};
+pub use recurring::RecurringExpense;
+pub use schedule::Session;
+pub use snapshot::SnapshotInfo;
+pub use statement::StudentStatement;
+pub use merge::{ConflictPolicy, MergeReport};
+pub use money::{Currency, Money};
+pub use log_policy::{LogPolicy, log_policy, set_log_policy};
+pub use permissions::{Capability, Operator, Role};
+pub use plan::InstallmentPlan;
+pub use privacy::StudentDataExport;
+pub use stats::{CashGroup, DashboardStats, GroupBy, GroupKey, get_dashboard_stats};
+pub use storage::{
+    CashLogBackend, JsonFileBackend, KeyValueBackend, KeyValueStore, MemoryBackend, MemoryStore,
+    StorageBackend,
+};
+#[cfg(feature = "sqlite")]
+pub use storage::SqliteBackend;
+pub use validation::{Validator, Violation};
 pub use error::{Error};
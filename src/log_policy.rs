@@ -0,0 +1,111 @@
+//! 日志中个人数据的输出策略
+//!
+//! 学生姓名、电话、备注等个人数据在写入日志前都经过 [`LogPolicy`] 处理，
+//! 共享日志文件时可以切换到脱敏或最小化输出。
+
+use log::info;
+use std::sync::{OnceLock, RwLock};
+
+/// 个人数据的日志输出策略
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum LogPolicy {
+    /// 原样输出（默认，与旧版本行为一致）
+    #[default]
+    Full,
+    /// 脱敏输出：姓名只保留首字，电话只保留后四位，备注只记录长度
+    Redacted,
+    /// 最小化输出：不输出任何个人数据
+    Minimal,
+}
+
+const HIDDEN: &str = "[已隐藏]";
+
+impl LogPolicy {
+    /// 处理姓名
+    pub fn name(&self, name: &str) -> String {
+        match self {
+            Self::Full => name.to_string(),
+            Self::Redacted => mask_tail(name, 1),
+            Self::Minimal => HIDDEN.to_string(),
+        }
+    }
+
+    /// 处理电话号码
+    pub fn phone(&self, phone: &str) -> String {
+        match self {
+            Self::Full => phone.to_string(),
+            Self::Redacted => {
+                let len = phone.chars().count();
+                let keep = 4.min(len);
+                let masked: String = phone.chars().skip(len - keep).collect();
+                format!("{}{}", "*".repeat(len - keep), masked)
+            }
+            Self::Minimal => HIDDEN.to_string(),
+        }
+    }
+
+    /// 处理备注等自由文本
+    pub fn text(&self, text: &str) -> String {
+        match self {
+            Self::Full => text.to_string(),
+            Self::Redacted => format!("<{} 字符>", text.chars().count()),
+            Self::Minimal => HIDDEN.to_string(),
+        }
+    }
+
+    /// 处理字段变更中的值，按字段名判断是否属于个人数据
+    pub fn field_value(&self, field: &str, value: &serde_json::Value) -> String {
+        if field == "guardians"
+            && *self != Self::Full
+            && let Some(guardians) = value.as_array()
+        {
+            return format!("<{} 位监护人>", guardians.len());
+        }
+        if field == "custom_fields"
+            && *self != Self::Full
+            && let Some(fields) = value.as_object()
+        {
+            return format!("<{} 个自定义字段>", fields.len());
+        }
+        let Some(raw) = value.as_str() else {
+            return value.to_string();
+        };
+        match field {
+            "name" => self.name(raw),
+            "phone" => self.phone(raw),
+            "note" => self.text(raw),
+            _ => value.to_string(),
+        }
+    }
+
+    /// 是否允许输出字段变更的具体数值
+    pub fn logs_values(&self) -> bool {
+        !matches!(self, Self::Minimal)
+    }
+}
+
+fn mask_tail(value: &str, keep: usize) -> String {
+    let len = value.chars().count();
+    if len <= keep {
+        return "*".repeat(len);
+    }
+    let head: String = value.chars().take(keep).collect();
+    format!("{}{}", head, "*".repeat(len - keep))
+}
+
+static LOG_POLICY: OnceLock<RwLock<LogPolicy>> = OnceLock::new();
+
+fn policy_lock() -> &'static RwLock<LogPolicy> {
+    LOG_POLICY.get_or_init(|| RwLock::new(LogPolicy::default()))
+}
+
+/// 获取当前全局日志策略
+pub fn log_policy() -> LogPolicy {
+    *policy_lock().read().unwrap_or_else(|e| e.into_inner())
+}
+
+/// 设置全局日志策略
+pub fn set_log_policy(policy: LogPolicy) {
+    info!("日志策略设置为 {:?}", policy);
+    *policy_lock().write().unwrap_or_else(|e| e.into_inner()) = policy;
+}
@@ -1,318 +1,4952 @@
 use crate::error::{Result, Error};
-use chrono::{DateTime, Utc};
-use log::info;
-use std::sync::{Arc, RwLock};
+use chrono::{DateTime, TimeZone, Utc};
+use log::{debug, error, info, warn};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, VecDeque};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, OnceLock, RwLock};
 
-use crate::cash::{Cash, CashDatabase, Installment};
+use crate::autosave::SaveScheduler;
+use crate::attachment::{ATTACHMENT_INDEX_FILE, ATTACHMENTS_DIR, Attachment, AttachmentDatabase};
+use crate::backup::{
+    BACKUP_AUDIT_FILE, BACKUP_CASH_FILE, BACKUP_DIR, BACKUP_STUDENT_FILE, BackupInfo,
+    RetentionPolicy,
+};
+use crate::audit::{
+    AUDIT_LOG_PATH, AuditAction, AuditDatabase, AuditEntity, AuditEntry, redact_changes,
+};
+use crate::bundle::{Bundle, BundleImportReport, BundleManifest, MergeStrategy};
+use crate::cash::{
+    CASH_UID_COUNTER, Cash, CashDatabase, CashTotals, Installment, InstallmentStatus,
+    PaymentFrequency, PaymentMethod, RemainderStrategy, allocate_plan_id,
+};
+use crate::catalog::{CATALOG_DATABASE_PATH, CatalogDatabase, CatalogItem, ItemEffect};
+use crate::clock::{Clock, SystemClock};
+use crate::coach::{COACH_DATABASE_PATH, Coach, CoachDatabase};
+use crate::common::{CustomValue, Database as _, FileCodec};
+use crate::compression::Compression;
 use crate::database::Database as DbContainer;
-use crate::stats::{DashboardStats, get_dashboard_stats};
-use crate::student::{Class, Student, StudentDatabase, Subject};
+use crate::encryption::EncryptionKey;
+use crate::events::{ChangeEvent, Event, EventBus, EventKind, SubscriptionId};
+use crate::integrity::IntegrityReport;
+use crate::id::{IdGenerator, IdNamespace, IdStrategy, TimeOrderedIds};
+use crate::invoice::{InstitutionHeader, RECEIPT_COUNTER_FILE, Receipt, ReceiptCounter};
+use crate::log_policy::log_policy;
+use crate::merge::{ConflictPolicy, MergeReport, MergedRecord};
+use crate::money::{Currency, Money};
+use crate::permissions::{Capability, Operator};
+use crate::plan::InstallmentPlan;
+use crate::privacy::{
+    CASH_PERSONAL_FIELDS, STUDENT_PERSONAL_FIELDS, StudentDataExport, concerns_student,
+    student_cash_uids,
+};
+use crate::recurring::{
+    RECURRING_DATABASE_PATH, RecurringDatabase, RecurringExpense, RecurringOccurrence,
+};
+use crate::snapshot::{Snapshot, SnapshotInfo};
+use crate::statement::StudentStatement;
+use crate::schedule::{SESSION_DATABASE_PATH, Session, SessionDatabase};
+use crate::stats::{
+    BreakdownStats, CashGroup, ConversionFunnel, DashboardStats, GroupBy, MonthlyForecast,
+    OutstandingBalance, aggregate_cash, forecast_revenue, get_branch_dashboard_stats_at,
+    get_breakdown_stats, get_conversion_funnel, get_dashboard_stats_at, get_outstanding_balances,
+};
+use crate::storage::StorageBackend;
+#[cfg(feature = "sync")]
+use crate::sync::{SyncClient, SyncCount, SyncReport};
+use crate::validation::Validator;
+use crate::wal::{WAL_FILE, WAL_PATH, WalRecord, WriteAheadLog};
+use crate::student::{
+    Class, Guardian, MembershipTier, STUDENT_UID_COUNTER, Student, StudentDatabase, Subject,
+    ClassPolicyTable, SCORING_CONFIG_FILE, ScoringConfigs, phone_digits,
+};
+
+/// 未调用 [`QmxManager::with_actor`] 时审计记录中的操作者
+const DEFAULT_ACTOR: &str = "系统";
+
+/// 操作日志最多保留的操作数，超出后丢弃最早的操作
+const UNDO_HISTORY_LIMIT: usize = 100;
+
+/// [`QmxManager::get_students_needing_renewal`] 中剩余课时少于该值的学生需要续费
+pub const RENEWAL_LESSON_THRESHOLD: u32 = 3;
+
+/// [`QmxManager::get_students_needing_renewal`] 中会员在该天数内到期的学生需要续费
+pub const RENEWAL_MEMBERSHIP_DAYS: u32 = 14;
 
 /// QMX管理器 - 统一的API入口点
 ///
 /// 提供线程安全的数据库操作接口，自动处理数据持久化和错误管理
 pub struct QmxManager {
     database: Arc<RwLock<DbContainer>>,
-    auto_save: bool,
+    auto_save: AutoSave,
+    read_only: bool,
+    operator: Option<Operator>,
+    student_path: Option<String>,
+    cash_path: Option<String>,
+    duplicate_guard: Option<DuplicateGuard>,
+    /// 标记分期已付款时是否自动生成下一期
+    auto_next_installment: bool,
+    /// 分期到期后的宽限天数
+    grace_days: u32,
+    limits: Limits,
+    validator: Validator,
+    clock: Arc<dyn Clock>,
+    ids: Option<Arc<dyn IdGenerator>>,
+    backend: Option<Arc<dyn StorageBackend>>,
+    /// 数据文件、预写日志和备份的编码方式（如加密密钥）
+    codec: FileCodec,
+    /// 使用文件持久化时的预写日志，见 [`crate::wal`]
+    wal: Option<Arc<WriteAheadLog>>,
+    audit: Arc<RwLock<AuditDatabase>>,
+    audit_path: Option<String>,
+    actor: String,
+    journal: Arc<Mutex<VecDeque<Vec<JournalEntry>>>>,
+    events: Arc<EventBus>,
+    attachments: Arc<RwLock<AttachmentDatabase>>,
+    attachments_dir: String,
+    coaches: Arc<RwLock<CoachDatabase>>,
+    coach_path: Option<String>,
+    sessions: Arc<RwLock<SessionDatabase>>,
+    session_path: Option<String>,
+    catalog: Arc<RwLock<CatalogDatabase>>,
+    catalog_path: Option<String>,
+    recurring: Arc<RwLock<RecurringDatabase>>,
+    recurring_path: Option<String>,
+    /// 班级课时策略
+    class_policies: ClassPolicyTable,
+    /// 成绩校验与统计使用的计分配置
+    scoring: Arc<RwLock<ScoringConfigs>>,
+    scoring_path: Option<String>,
+    /// 收据编号，保存在数据目录中
+    receipts: Arc<ReceiptCounter>,
+    backup_dir: String,
+    retention: RetentionPolicy,
+    /// 按创建顺序排列的内存快照，见 [`crate::snapshot`]
+    snapshots: Arc<RwLock<Vec<Snapshot>>>,
+    dirty: Arc<DirtyFlags>,
+    /// 串行化前台保存与后台自动保存
+    save_lock: Arc<Mutex<()>>,
+    /// [`AutoSave::Debounced`] 模式下第一次修改时启动
+    scheduler: OnceLock<SaveScheduler>,
+    save_error_handler: Option<SaveErrorHandler>,
+}
+
+/// 后台自动保存失败时的回调
+type SaveErrorHandler = Arc<dyn Fn(&Error) + Send + Sync>;
+
+/// 自上次保存以来被修改过的数据库，保存时跳过未修改的数据库
+#[derive(Debug, Default)]
+struct DirtyFlags {
+    student: AtomicBool,
+    cash: AtomicBool,
+}
+
+impl DirtyFlags {
+    fn mark_all(&self) {
+        self.student.store(true, Ordering::SeqCst);
+        self.cash.store(true, Ordering::SeqCst);
+    }
+}
+
+/// 保存数据所需的共享状态，前台保存和后台自动保存共用
+#[derive(Clone)]
+struct Persistence {
+    database: Arc<RwLock<DbContainer>>,
+    backend: Option<Arc<dyn StorageBackend>>,
+    codec: FileCodec,
+    wal: Option<Arc<WriteAheadLog>>,
     student_path: Option<String>,
     cash_path: Option<String>,
+    audit: Arc<RwLock<AuditDatabase>>,
+    audit_path: Option<String>,
+    dirty: Arc<DirtyFlags>,
+    save_lock: Arc<Mutex<()>>,
+}
+
+impl Persistence {
+    /// 保存修改过的数据库和审计日志
+    fn save(&self) -> Result<()> {
+        let _guard = self.save_lock.lock().unwrap_or_else(|e| e.into_inner());
+        let db = self
+            .database
+            .read()
+            .map_err(|e| Error::Poison(e.to_string()))?;
+
+        // 优先使用存储后端，其次是自定义路径；文件方式只重写修改过的数据库
+        if let Some(backend) = &self.backend {
+            backend.save(&db)?;
+            self.dirty.student.store(false, Ordering::SeqCst);
+            self.dirty.cash.store(false, Ordering::SeqCst);
+        } else {
+            let custom_paths = self.student_path.as_ref().zip(self.cash_path.as_ref());
+            if custom_paths.is_some() {
+                info!("使用自定义路径保存数据库");
+            }
+            if self.dirty.student.load(Ordering::SeqCst) {
+                match custom_paths {
+                    Some((student_path, _)) => {
+                        db.student.save_to_with(student_path, &self.codec)?
+                    }
+                    None => db
+                        .student
+                        .save_to_with(db.student.default_path(), &self.codec)?,
+                }
+                self.dirty.student.store(false, Ordering::SeqCst);
+            } else {
+                debug!("学生数据库未修改，跳过保存");
+            }
+            if self.dirty.cash.load(Ordering::SeqCst) {
+                match custom_paths {
+                    Some((_, cash_path)) => db.cash.save_to_with(cash_path, &self.codec)?,
+                    None => db.cash.save_to_with(db.cash.default_path(), &self.codec)?,
+                }
+                self.dirty.cash.store(false, Ordering::SeqCst);
+            } else {
+                debug!("现金数据库未修改，跳过保存");
+            }
+            if let Some(wal) = &self.wal {
+                // 持有两个读锁期间没有新的修改和审计记录，日志中的记录都已写入数据文件
+                let audit = self
+                    .audit
+                    .read()
+                    .map_err(|e| Error::Poison(e.to_string()))?;
+                if let Some(path) = &self.audit_path {
+                    audit.save_to_with(path, &self.codec)?;
+                }
+                wal.commit()?;
+                return Ok(());
+            }
+        }
+        drop(db);
+
+        self.write_audit()
+    }
+
+    /// 保存审计日志（仅在设置了审计日志路径时）
+    fn save_audit(&self) -> Result<()> {
+        let _guard = self.save_lock.lock().unwrap_or_else(|e| e.into_inner());
+        self.write_audit()
+    }
+
+    fn write_audit(&self) -> Result<()> {
+        let Some(path) = &self.audit_path else {
+            return Ok(());
+        };
+        self.audit
+            .read()
+            .map_err(|e| Error::Poison(e.to_string()))?
+            .save_to_with(path, &self.codec)
+    }
+}
+
+/// 操作日志中一条记录修改前的状态，`None` 表示该记录原本不存在
+#[derive(Clone)]
+enum JournalEntry {
+    Student(u64, Option<Student>),
+    Cash(u64, Option<Cash>),
+    Plan(u64, Option<InstallmentPlan>),
+}
+
+/// 售卖收款对学生的修改
+struct SaleCredited {
+    /// 修改前的学生
+    before: Student,
+    changes: Vec<FieldChange>,
+}
+
+/// 字段长度与数量限制
+///
+/// 由构建器和更新器在写入前检查，防止异常客户端写入超大字段撑爆数据文件。
+/// 长度按字符数计算。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Limits {
+    /// 姓名最大长度
+    pub max_name_len: usize,
+    /// 备注最大长度（学生和现金记录）
+    pub max_note_len: usize,
+    /// 每个学生最多保存的成绩数
+    pub max_rings: usize,
+    /// 单个附件最大字节数
+    pub max_attachment_size: u64,
+}
+
+impl Default for Limits {
+    fn default() -> Self {
+        Self {
+            max_name_len: 100,
+            max_note_len: 5000,
+            max_rings: 10_000,
+            max_attachment_size: 10 * 1024 * 1024,
+        }
+    }
+}
+
+impl Limits {
+    /// 不做任何限制
+    pub fn unlimited() -> Self {
+        Self {
+            max_name_len: usize::MAX,
+            max_note_len: usize::MAX,
+            max_rings: usize::MAX,
+            max_attachment_size: u64::MAX,
+        }
+    }
+
+    fn check_len(field: &str, value: &str, max: usize) -> Result<()> {
+        let len = value.chars().count();
+        if len > max {
+            return Err(Error::ValidationFailed {
+                field: field.to_string(),
+                reason: format!("长度 {} 超过上限 {}", len, max),
+            });
+        }
+        Ok(())
+    }
+
+    /// 自定义字段名按姓名长度限制，文本值按备注长度限制
+    fn check_custom_field(&self, key: &str, value: &CustomValue) -> Result<()> {
+        if key.is_empty() {
+            return Err(Error::InvalidInput("自定义字段名不能为空".to_string()));
+        }
+        Self::check_len("custom_fields", key, self.max_name_len)?;
+        if let CustomValue::Text(text) = value {
+            Self::check_len(key, text, self.max_note_len)?;
+        }
+        Ok(())
+    }
+
+    fn check_rings(&self, count: usize) -> Result<()> {
+        if count > self.max_rings {
+            return Err(Error::ValidationFailed {
+                field: "rings".to_string(),
+                reason: format!("成绩数量 {} 超过上限 {}", count, self.max_rings),
+            });
+        }
+        Ok(())
+    }
+
+    /// 检查附件大小是否超过上限
+    pub fn check_attachment_size(&self, size: u64) -> Result<()> {
+        if size > self.max_attachment_size {
+            return Err(Error::ValidationFailed {
+                field: "attachment".to_string(),
+                reason: format!(
+                    "附件大小 {} 字节超过上限 {}",
+                    size, self.max_attachment_size
+                ),
+            });
+        }
+        Ok(())
+    }
+}
+
+/// 重复现金记录的处理方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DuplicatePolicy {
+    /// 仅记录警告，照常写入
+    Warn,
+    /// 拒绝写入，除非 `CashBuilder::force()` 强制记录
+    Reject,
+}
+
+/// 删除学生时关联现金记录的处理方式
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum DeletePolicy {
+    /// 学生还有现金记录时拒绝删除（默认）
+    #[default]
+    Restrict,
+    /// 同时删除学生的全部现金记录
+    Cascade,
+    /// 保留现金记录，清除其中的学生 UID
+    Detach,
+}
+
+/// `record_cash` 的重复记录防护配置
+#[derive(Debug, Clone, Copy)]
+pub struct DuplicateGuard {
+    /// 学生、金额相同时视为重复的时间窗口
+    pub window: chrono::Duration,
+    pub policy: DuplicatePolicy,
+}
+
+/// 自动保存策略
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum AutoSave {
+    /// 每次修改后立即保存（默认）
+    #[default]
+    Immediate,
+    /// 不自动保存，需要手动调用 [`QmxManager::save`]
+    Off,
+    /// 合并修改，由后台线程统一保存
+    ///
+    /// 距最后一次修改超过 `quiet_period`，或累计 `max_pending` 次修改后保存一次，
+    /// 适合批量导入等连续修改的场景。调用 [`QmxManager::flush`] 可立即保存，
+    /// 管理器被丢弃时会保存尚未保存的修改。后台保存失败时调用
+    /// [`QmxManager::with_save_error_handler`] 设置的回调。
+    Debounced {
+        quiet_period: std::time::Duration,
+        max_pending: usize,
+    },
+}
+
+impl From<bool> for AutoSave {
+    fn from(enabled: bool) -> Self {
+        if enabled { Self::Immediate } else { Self::Off }
+    }
+}
+
+/// 管理器配置，由 [`QmxManagerBuilder`] 使用
+///
+/// 所有字段都有默认值：数据保存在 `QMX_DATA_DIR` 环境变量指定的目录或 `./data`，
+/// 每次修改后立即保存，使用默认的字段限制，不启用加密、自定义校验和重复记录防护。
+#[derive(Debug, Clone, Default)]
+pub struct ManagerConfig {
+    /// 数据目录，学生、现金、审计日志、附件、教练、课程排期和备份都保存在该目录下
+    pub data_dir: Option<PathBuf>,
+    /// 自动保存策略
+    pub auto_save: AutoSave,
+    /// 备份目录，默认为数据目录下的 `backups`
+    pub backup_dir: Option<PathBuf>,
+    /// 旧备份的保留策略
+    pub retention: RetentionPolicy,
+    /// 数据文件的加密密钥，只用于这个管理器；未设置时使用进程默认密钥
+    pub encryption_key: Option<EncryptionKey>,
+    /// 数据文件的压缩方式，只用于这个管理器；未设置时使用进程默认压缩方式
+    pub compression: Option<Compression>,
+    /// 字段长度与数量限制，传入 [`Limits::unlimited`] 可关闭限制
+    pub limits: Limits,
+    /// 创建学生和记录现金时执行的校验规则
+    pub validator: Validator,
+    /// `record_cash` 的重复记录防护，`None` 表示关闭
+    pub duplicate_guard: Option<DuplicateGuard>,
+    /// 标记分期已付款时自动生成下一期，见 [`QmxManager::mark_installment_paid`]
+    pub auto_next_installment: bool,
+    /// 分期到期后的宽限天数，见 [`QmxManager::with_grace_days`]
+    pub grace_days: u32,
+    /// 只读模式，见 [`QmxManager::open_read_only`]
+    pub read_only: bool,
+    /// 执行操作的人，见 [`QmxManager::with_operator`]
+    pub operator: Option<Operator>,
+    /// 计分配置，`None` 时从数据目录的 [`SCORING_CONFIG_FILE`] 加载，见 [`QmxManager::set_scoring_configs`]
+    pub scoring: Option<ScoringConfigs>,
+    /// 班级课时策略，决定切换班级时初始化多少课时，见 [`ClassPolicyTable`]
+    pub class_policies: ClassPolicyTable,
+}
+
+/// [`QmxManager`] 的构建器
+///
+/// ```rust
+/// use qmx_backend_lib::{AutoSave, Limits, QmxManager};
+///
+/// # fn main() -> qmx_backend_lib::error::Result<()> {
+/// # let dir = tempfile::TempDir::new()?;
+/// let manager = QmxManager::builder()
+///     .data_dir(dir.path())
+///     .auto_save(AutoSave::Off)
+///     .limits(Limits::default())
+///     .build()?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Default)]
+pub struct QmxManagerBuilder {
+    config: ManagerConfig,
+    database_files: Option<(String, String)>,
+    storage: Option<Arc<dyn StorageBackend>>,
+    save_error_handler: Option<SaveErrorHandler>,
+}
+
+impl QmxManagerBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 从已有配置开始构建
+    pub fn from_config(config: ManagerConfig) -> Self {
+        Self {
+            config,
+            ..Self::default()
+        }
+    }
+
+    /// 数据目录，不存在时自动创建，缺少的数据库文件会创建为空数据库
+    pub fn data_dir(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.config.data_dir = Some(dir.into());
+        self
+    }
+
+    /// 从指定的学生和现金数据库文件加载，文件必须已存在
+    ///
+    /// 审计日志、附件、教练、课程排期和备份放在学生数据库文件所在的目录。优先于 [`Self::data_dir`]。
+    pub fn database_files(
+        mut self,
+        student_path: impl AsRef<Path>,
+        cash_path: impl AsRef<Path>,
+    ) -> Self {
+        let path = |p: &Path| p.to_string_lossy().into_owned();
+        self.database_files = Some((path(student_path.as_ref()), path(cash_path.as_ref())));
+        self
+    }
+
+    /// 使用存储后端，不访问文件系统。优先于其他数据位置设置
+    ///
+    /// 数据只从 `backend` 加载和保存，审计日志、教练和课程排期只保存在内存中，
+    /// 评分配置使用默认值。适用于编译到 `wasm32-unknown-unknown` 等没有文件系统的环境，
+    /// 此时可用 [`crate::storage::KeyValueBackend`] 包装 IndexedDB 或 localStorage，
+    /// 修改后调用 [`crate::storage::KeyValueBackend::flush`] 写回。
+    ///
+    /// 在浏览器中运行时，需要按本库的 `.cargo/config.toml` 为 `getrandom` 选择 `wasm_js` 后端；
+    /// [`AutoSave::Debounced`] 依赖后台线程，在浏览器中不可用。
+    pub fn storage(mut self, backend: Arc<dyn StorageBackend>) -> Self {
+        self.storage = Some(backend);
+        self
+    }
+
+    /// 自动保存策略，也可以传入 `bool`
+    pub fn auto_save(mut self, policy: impl Into<AutoSave>) -> Self {
+        self.config.auto_save = policy.into();
+        self
+    }
+
+    /// 后台自动保存失败时的回调，见 [`QmxManager::with_save_error_handler`]
+    pub fn on_save_error(mut self, handler: impl Fn(&Error) + Send + Sync + 'static) -> Self {
+        self.save_error_handler = Some(Arc::new(handler));
+        self
+    }
+
+    pub fn backup_dir(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.config.backup_dir = Some(dir.into());
+        self
+    }
+
+    pub fn retention_policy(mut self, policy: RetentionPolicy) -> Self {
+        self.config.retention = policy;
+        self
+    }
+
+    /// 数据文件和预写日志的加密密钥，见 [`crate::encryption`]
+    ///
+    /// 密钥只属于这个管理器，不影响其他管理器和进程默认密钥。使用 [`Self::storage`]
+    /// 时由后端自己的编码方式决定是否加密，见 [`crate::JsonFileBackend::with_codec`]。
+    pub fn encryption_key(mut self, key: EncryptionKey) -> Self {
+        self.config.encryption_key = Some(key);
+        self
+    }
+
+    /// 数据文件的压缩方式，见 [`QmxManager::with_compression`]
+    pub fn compression(mut self, compression: Compression) -> Self {
+        self.config.compression = Some(compression);
+        self
+    }
+
+    pub fn limits(mut self, limits: Limits) -> Self {
+        self.config.limits = limits;
+        self
+    }
+
+    pub fn validator(mut self, validator: Validator) -> Self {
+        self.config.validator = validator;
+        self
+    }
+
+    pub fn duplicate_guard(mut self, guard: DuplicateGuard) -> Self {
+        self.config.duplicate_guard = Some(guard);
+        self
+    }
+
+    /// 标记分期已付款时自动生成下一期，见 [`QmxManager::mark_installment_paid`]
+    pub fn auto_next_installment(mut self, enabled: bool) -> Self {
+        self.config.auto_next_installment = enabled;
+        self
+    }
+
+    /// 分期到期后的宽限天数，见 [`QmxManager::with_grace_days`]
+    pub fn grace_days(mut self, days: u32) -> Self {
+        self.config.grace_days = days;
+        self
+    }
+
+    /// 执行操作的人，见 [`QmxManager::with_operator`]
+    pub fn operator(mut self, operator: Operator) -> Self {
+        self.config.operator = Some(operator);
+        self
+    }
+
+    /// 班级课时策略，见 [`QmxManager::with_class_policies`]
+    pub fn class_policies(mut self, policies: ClassPolicyTable) -> Self {
+        self.config.class_policies = policies;
+        self
+    }
+
+    /// 计分配置，优先于数据目录中的 [`SCORING_CONFIG_FILE`]
+    pub fn scoring_configs(mut self, configs: ScoringConfigs) -> Self {
+        self.config.scoring = Some(configs);
+        self
+    }
+
+    /// 只读模式：只加载已存在的数据文件，拒绝所有修改操作，不写入任何文件
+    pub fn read_only(mut self, read_only: bool) -> Self {
+        self.config.read_only = read_only;
+        self
+    }
+
+    /// 当前配置
+    pub fn config(&self) -> &ManagerConfig {
+        &self.config
+    }
+
+    /// 按配置加载数据并创建管理器
+    pub fn build(self) -> Result<QmxManager> {
+        let config = self.config;
+        // 未指定的项使用进程默认值，指定的密钥和压缩方式只属于这个管理器
+        let mut codec = FileCodec::process_default();
+        if let Some(key) = config.encryption_key {
+            codec.key = Some(key);
+        }
+        if let Some(compression) = config.compression {
+            codec.compression = compression;
+        }
+        let auto_save = config.auto_save != AutoSave::Off && !config.read_only;
+        let mut manager = if let Some(backend) = self.storage {
+            QmxManager::open_storage(backend, codec, auto_save)?
+        } else if let Some((student_path, cash_path)) = &self.database_files {
+            QmxManager::open_paths(student_path, cash_path, codec, auto_save, config.read_only)?
+        } else if config.read_only {
+            // 只读模式不创建目录和空数据库，数据文件必须已存在
+            let dir = match &config.data_dir {
+                Some(dir) => dir.clone(),
+                None => PathBuf::from(
+                    std::env::var("QMX_DATA_DIR").unwrap_or_else(|_| "./data".to_string()),
+                ),
+            };
+            let path = |name: &str| dir.join(name).to_string_lossy().into_owned();
+            QmxManager::open_paths(
+                &path("student_database.json"),
+                &path("cash_database.json"),
+                codec,
+                false,
+                true,
+            )?
+        } else if let Some(dir) = &config.data_dir {
+            QmxManager::open_dir(dir, codec, auto_save)?
+        } else {
+            QmxManager::open_default(codec, auto_save)?
+        };
+        if config.read_only {
+            info!("管理器以只读模式打开");
+            manager.read_only = true;
+        } else {
+            manager.auto_save = config.auto_save;
+        }
+        manager.save_error_handler = self.save_error_handler;
+        manager.limits = config.limits;
+        manager.validator = config.validator;
+        manager.duplicate_guard = config.duplicate_guard;
+        manager.auto_next_installment = config.auto_next_installment;
+        manager.grace_days = config.grace_days;
+        manager.retention = config.retention;
+        manager.class_policies = config.class_policies;
+        if config.compression.is_some() {
+            // 与 with_compression 相同，下次保存时按指定的压缩方式重写所有数据文件
+            manager.dirty.mark_all();
+        }
+        if let Some(scoring) = config.scoring {
+            manager.scoring = Arc::new(RwLock::new(scoring));
+        }
+        if let Some(operator) = config.operator {
+            manager = manager.with_operator(operator);
+        }
+        if let Some(dir) = config.backup_dir {
+            manager.backup_dir = dir.to_string_lossy().into_owned();
+        }
+        Ok(manager)
+    }
 }
 
 impl QmxManager {
+    /// 创建管理器构建器，见 [`QmxManagerBuilder`]
+    ///
+    /// # 示例
+    /// ```rust
+    /// use qmx_backend_lib::QmxManager;
+    ///
+    /// # fn main() -> qmx_backend_lib::error::Result<()> {
+    /// let manager = QmxManager::builder().auto_save(true).build()?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn builder() -> QmxManagerBuilder {
+        QmxManagerBuilder::new()
+    }
+
     /// 创建新的QMX管理器实例
     ///
     /// # 参数
     /// * `auto_save` - 是否在每次操作后自动保存数据
+    #[deprecated(
+        since = "2.6.0",
+        note = "请使用 `QmxManager::builder().auto_save(..).build()`"
+    )]
+    pub fn new(auto_save: bool) -> Result<Self> {
+        Self::open_default(FileCodec::process_default(), auto_save)
+    }
+
+    /// 从指定路径加载数据库
+    #[deprecated(
+        since = "2.6.0",
+        note = "请使用 `QmxManager::builder().database_files(..).build()`"
+    )]
+    pub fn from_path(student_path: &str, cash_path: &str, auto_save: bool) -> Result<Self> {
+        Self::open_paths(
+            student_path,
+            cash_path,
+            FileCodec::process_default(),
+            auto_save,
+            false,
+        )
+    }
+
+    /// 使用存储后端创建管理器，不访问文件系统
+    #[deprecated(
+        since = "2.6.0",
+        note = "请使用 `QmxManager::builder().storage(..).build()`"
+    )]
+    pub fn with_storage(backend: Arc<dyn StorageBackend>, auto_save: bool) -> Result<Self> {
+        Self::open_storage(backend, FileCodec::process_default(), auto_save)
+    }
+
+    /// 以只读模式加载指定的学生和现金数据库文件
+    ///
+    /// 所有修改数据的方法（包括 [`Self::save`] 和 [`Self::backup_now`]）都返回
+    /// [`Error::ReadOnly`]，查询、统计和导出不受影响。适用于报表工具，
+    /// 以及在不影响备份文件的前提下检查备份内容。其他选项可用
+    /// [`QmxManagerBuilder::read_only`] 设置。
     ///
     /// # 示例
     /// ```rust
-    /// use qmx_backend_lib::QmxManager;
+    /// use qmx_backend_lib::{QmxManager, StudentBuilder};
     ///
     /// # fn main() -> qmx_backend_lib::error::Result<()> {
-    /// let manager = QmxManager::new(true)?;
+    /// # let dir = tempfile::TempDir::new()?;
+    /// # QmxManager::builder().data_dir(dir.path()).build()?;
+    /// # let student_path = dir.path().join("student_database.json");
+    /// # let cash_path = dir.path().join("cash_database.json");
+    /// let manager = QmxManager::open_read_only(&student_path, &cash_path)?;
+    /// assert!(manager.is_read_only());
+    /// assert!(manager.create_student(StudentBuilder::new("张三")).is_err());
     /// # Ok(())
     /// # }
     /// ```
-    pub fn new(auto_save: bool) -> Result<Self> {
+    pub fn open_read_only(
+        student_path: impl AsRef<Path>,
+        cash_path: impl AsRef<Path>,
+    ) -> Result<Self> {
+        Self::builder()
+            .database_files(student_path, cash_path)
+            .read_only(true)
+            .build()
+    }
+
+    /// 是否以只读模式打开
+    pub fn is_read_only(&self) -> bool {
+        self.read_only
+    }
+
+    /// 检查当前操作者是否拥有权限，未指定操作者时总是通过
+    fn authorize(&self, capability: Capability) -> Result<()> {
+        match &self.operator {
+            Some(operator) => operator.authorize(capability).inspect_err(|e| {
+                warn!("{}", e);
+            }),
+            None => Ok(()),
+        }
+    }
+
+    /// 只读模式下拒绝修改操作
+    fn ensure_writable(&self, operation: &str) -> Result<()> {
+        if self.read_only {
+            warn!("只读模式下拒绝操作: {}", operation);
+            return Err(Error::ReadOnly(operation.to_string()));
+        }
+        Ok(())
+    }
+
+    /// 分配新学生 UID，配置了 ID 生成器时由生成器分配，否则使用全局计数器
+    fn next_student_uid(&self) -> u64 {
+        match &self.ids {
+            Some(ids) => ids.next_student_uid(),
+            None => STUDENT_UID_COUNTER.fetch_add(1, Ordering::SeqCst),
+        }
+    }
+
+    /// 分配新现金记录 UID，规则同 [`Self::next_student_uid`]
+    fn next_cash_uid(&self) -> u64 {
+        match &self.ids {
+            Some(ids) => ids.next_cash_uid(),
+            None => CASH_UID_COUNTER.fetch_add(1, Ordering::SeqCst),
+        }
+    }
+
+    /// 使用默认数据目录创建管理器
+    fn open_default(codec: FileCodec, auto_save: bool) -> Result<Self> {
         info!("正在初始化QMX管理器");
-        let database = crate::database::init()?;
+        let (database, _) = crate::database::init_with_codec(&codec)?;
+        let data_dir =
+            PathBuf::from(std::env::var("QMX_DATA_DIR").unwrap_or_else(|_| "./data".to_string()));
+        let scoring_path = data_dir
+            .join(SCORING_CONFIG_FILE)
+            .to_string_lossy()
+            .into_owned();
+        let scoring = ScoringConfigs::load_or_default(&scoring_path)?;
+        let receipts = ReceiptCounter::open(data_dir.join(RECEIPT_COUNTER_FILE))?;
+        let audit_path = AUDIT_LOG_PATH.to_string();
+        let audit = AuditDatabase::load_or_new_with(&audit_path, &codec)?;
+        let attachments_dir = ATTACHMENTS_DIR.to_string();
+        let attachments = load_attachment_index(&attachments_dir, &codec)?;
+        let coach_path = COACH_DATABASE_PATH.to_string();
+        let coaches = CoachDatabase::load_or_new_with(&coach_path, &codec)?;
+        let session_path = SESSION_DATABASE_PATH.to_string();
+        let sessions = SessionDatabase::load_or_new_with(&session_path, &codec)?;
+        let catalog_path = CATALOG_DATABASE_PATH.to_string();
+        let catalog = CatalogDatabase::load_or_new_with(&catalog_path, &codec)?;
+        let recurring_path = RECURRING_DATABASE_PATH.to_string();
+        let recurring = RecurringDatabase::load_or_new_with(&recurring_path, &codec)?;
+        let backup_dir = BACKUP_DIR.to_string();
 
         Ok(Self {
             database: Arc::new(RwLock::new(database)),
-            auto_save,
+            auto_save: AutoSave::from(auto_save),
+            read_only: false,
+            operator: None,
             student_path: None,
             cash_path: None,
+            duplicate_guard: None,
+            auto_next_installment: false,
+            grace_days: 0,
+            limits: Limits::default(),
+            validator: Validator::default(),
+            clock: Arc::new(SystemClock),
+            ids: None,
+            backend: None,
+            codec: codec.clone(),
+            // database::init 已重放并清空日志
+            wal: Some(Arc::new(WriteAheadLog::with_codec(WAL_PATH, codec.clone()))),
+            audit: Arc::new(RwLock::new(audit)),
+            audit_path: Some(audit_path),
+            actor: DEFAULT_ACTOR.to_string(),
+            journal: Arc::new(Mutex::new(VecDeque::new())),
+            events: Arc::new(EventBus::default()),
+            attachments: Arc::new(RwLock::new(attachments)),
+            attachments_dir,
+            coaches: Arc::new(RwLock::new(coaches)),
+            coach_path: Some(coach_path),
+            sessions: Arc::new(RwLock::new(sessions)),
+            session_path: Some(session_path),
+            catalog: Arc::new(RwLock::new(catalog)),
+            catalog_path: Some(catalog_path),
+            recurring: Arc::new(RwLock::new(recurring)),
+            recurring_path: Some(recurring_path),
+            class_policies: ClassPolicyTable::default(),
+            scoring: Arc::new(RwLock::new(scoring)),
+            scoring_path: Some(scoring_path),
+            receipts: Arc::new(receipts),
+            backup_dir,
+            retention: RetentionPolicy::default(),
+            snapshots: Arc::new(RwLock::new(Vec::new())),
+            dirty: Arc::new(DirtyFlags::default()),
+            save_lock: Arc::new(Mutex::new(())),
+            scheduler: OnceLock::new(),
+            save_error_handler: None,
         })
     }
 
+    /// 在数据目录中加载数据库，目录或数据库文件不存在时创建
+    fn open_dir(dir: &Path, codec: FileCodec, auto_save: bool) -> Result<Self> {
+        std::fs::create_dir_all(dir)?;
+        let student_path = dir
+            .join("student_database.json")
+            .to_string_lossy()
+            .into_owned();
+        let cash_path = dir
+            .join("cash_database.json")
+            .to_string_lossy()
+            .into_owned();
+        if !Path::new(&student_path).exists() {
+            StudentDatabase::new().save_to_with(&student_path, &codec)?;
+        }
+        if !Path::new(&cash_path).exists() {
+            CashDatabase::new().save_to_with(&cash_path, &codec)?;
+        }
+        Self::open_paths(&student_path, &cash_path, codec, auto_save, false)
+    }
+
     /// 从指定路径加载数据库
-    pub fn from_path(student_path: &str, cash_path: &str, auto_save: bool) -> Result<Self> {
+    ///
+    /// 只读模式下不写入任何文件，预写日志中学生和现金之外的记录不会重放。
+    fn open_paths(
+        student_path: &str,
+        cash_path: &str,
+        codec: FileCodec,
+        auto_save: bool,
+        read_only: bool,
+    ) -> Result<Self> {
         info!(
             "从指定路径加载数据库: student={}, cash={}",
             student_path, cash_path
         );
 
-        let student_db = StudentDatabase::read_from(student_path)?;
-        let cash_db = CashDatabase::read_from(cash_path)?;
+        let student_db = StudentDatabase::read_from_with(student_path, &codec)?;
+        let cash_db = CashDatabase::read_from_with(cash_path, &codec)?;
+
+        let mut database = DbContainer::new(student_db, cash_db);
+        sync_uid_counters(&database);
+
+        // 重放上次崩溃前未保存的修改，下次保存时写入数据文件
+        let wal = WriteAheadLog::with_codec(
+            Path::new(student_path)
+                .with_file_name(WAL_FILE)
+                .to_string_lossy()
+                .into_owned(),
+            codec.clone(),
+        );
+        let dirty = DirtyFlags::default();
+        let replayed = wal.replay(&mut database)?;
+        if replayed > 0 {
+            dirty.mark_all();
+        }
+        // 其他数据库的记录直接写入各自的文件，之后再加载
+        if !read_only && wal.replay_files()? > 0 && replayed == 0 {
+            wal.commit()?;
+        }
 
-        let database = DbContainer::new(student_db, cash_db);
+        // 审计日志与学生数据库放在同一目录
+        let audit_path = std::path::Path::new(student_path)
+            .with_file_name("audit_log.json")
+            .to_string_lossy()
+            .into_owned();
+        let audit = AuditDatabase::load_or_new_with(&audit_path, &codec)?;
+        let attachments_dir = std::path::Path::new(student_path)
+            .with_file_name("attachments")
+            .to_string_lossy()
+            .into_owned();
+        let attachments = load_attachment_index(&attachments_dir, &codec)?;
+        let coach_path = std::path::Path::new(student_path)
+            .with_file_name("coach_database.json")
+            .to_string_lossy()
+            .into_owned();
+        let coaches = CoachDatabase::load_or_new_with(&coach_path, &codec)?;
+        let session_path = std::path::Path::new(student_path)
+            .with_file_name("session_database.json")
+            .to_string_lossy()
+            .into_owned();
+        let sessions = SessionDatabase::load_or_new_with(&session_path, &codec)?;
+        let catalog_path = std::path::Path::new(student_path)
+            .with_file_name("catalog_database.json")
+            .to_string_lossy()
+            .into_owned();
+        let catalog = CatalogDatabase::load_or_new_with(&catalog_path, &codec)?;
+        let recurring_path = std::path::Path::new(student_path)
+            .with_file_name("recurring_database.json")
+            .to_string_lossy()
+            .into_owned();
+        let recurring = RecurringDatabase::load_or_new_with(&recurring_path, &codec)?;
+        let scoring_path = std::path::Path::new(student_path)
+            .with_file_name(SCORING_CONFIG_FILE)
+            .to_string_lossy()
+            .into_owned();
+        let scoring = ScoringConfigs::load_or_default(&scoring_path)?;
+        let receipts =
+            ReceiptCounter::open(Path::new(student_path).with_file_name(RECEIPT_COUNTER_FILE))?;
+        let backup_dir = std::path::Path::new(student_path)
+            .with_file_name("backups")
+            .to_string_lossy()
+            .into_owned();
 
         Ok(Self {
             database: Arc::new(RwLock::new(database)),
-            auto_save,
+            auto_save: AutoSave::from(auto_save),
+            read_only,
+            operator: None,
             student_path: Some(student_path.to_string()),
             cash_path: Some(cash_path.to_string()),
+            duplicate_guard: None,
+            auto_next_installment: false,
+            grace_days: 0,
+            limits: Limits::default(),
+            validator: Validator::default(),
+            clock: Arc::new(SystemClock),
+            ids: None,
+            backend: None,
+            codec: codec.clone(),
+            wal: Some(Arc::new(wal)),
+            audit: Arc::new(RwLock::new(audit)),
+            audit_path: Some(audit_path),
+            actor: DEFAULT_ACTOR.to_string(),
+            journal: Arc::new(Mutex::new(VecDeque::new())),
+            events: Arc::new(EventBus::default()),
+            attachments: Arc::new(RwLock::new(attachments)),
+            attachments_dir,
+            coaches: Arc::new(RwLock::new(coaches)),
+            coach_path: Some(coach_path),
+            sessions: Arc::new(RwLock::new(sessions)),
+            session_path: Some(session_path),
+            catalog: Arc::new(RwLock::new(catalog)),
+            catalog_path: Some(catalog_path),
+            recurring: Arc::new(RwLock::new(recurring)),
+            recurring_path: Some(recurring_path),
+            class_policies: ClassPolicyTable::default(),
+            scoring: Arc::new(RwLock::new(scoring)),
+            scoring_path: Some(scoring_path),
+            receipts: Arc::new(receipts),
+            backup_dir,
+            retention: RetentionPolicy::default(),
+            snapshots: Arc::new(RwLock::new(Vec::new())),
+            dirty: Arc::new(dirty),
+            save_lock: Arc::new(Mutex::new(())),
+            scheduler: OnceLock::new(),
+            save_error_handler: None,
         })
     }
 
-    /// 手动保存所有数据
-    pub fn save(&self) -> Result<()> {
-        let db = self
-            .database
-            .read()
-            .map_err(|e| Error::Poison(e.to_string()))?;
+    /// 使用存储后端创建管理器，不访问文件系统
+    fn open_storage(
+        backend: Arc<dyn StorageBackend>,
+        codec: FileCodec,
+        auto_save: bool,
+    ) -> Result<Self> {
+        let database = backend.load()?;
+        sync_uid_counters(&database);
+        info!("使用存储后端初始化QMX管理器");
 
-        // 如果有自定义路径，使用自定义路径保存
-        if let (Some(student_path), Some(cash_path)) = (&self.student_path, &self.cash_path) {
-            info!("使用自定义路径保存数据库");
-            db.student
-                .save_to(student_path)
-                .map_err(Error::from)?;
-            db.cash
-                .save_to(cash_path)
-                .map_err(Error::from)?;
-        } else {
-            // 使用默认路径保存
-            db.save().map_err(Error::from)?;
-        }
+        Ok(Self {
+            database: Arc::new(RwLock::new(database)),
+            auto_save: AutoSave::from(auto_save),
+            read_only: false,
+            operator: None,
+            student_path: None,
+            cash_path: None,
+            duplicate_guard: None,
+            auto_next_installment: false,
+            grace_days: 0,
+            limits: Limits::default(),
+            validator: Validator::default(),
+            clock: Arc::new(SystemClock),
+            ids: None,
+            backend: Some(backend),
+            codec,
+            wal: None,
+            audit: Arc::new(RwLock::new(AuditDatabase::new())),
+            audit_path: None,
+            actor: DEFAULT_ACTOR.to_string(),
+            journal: Arc::new(Mutex::new(VecDeque::new())),
+            events: Arc::new(EventBus::default()),
+            attachments: Arc::new(RwLock::new(AttachmentDatabase::new())),
+            attachments_dir: ATTACHMENTS_DIR.to_string(),
+            coaches: Arc::new(RwLock::new(CoachDatabase::new())),
+            coach_path: None,
+            sessions: Arc::new(RwLock::new(SessionDatabase::new())),
+            session_path: None,
+            catalog: Arc::new(RwLock::new(CatalogDatabase::with_defaults())),
+            catalog_path: None,
+            recurring: Arc::new(RwLock::new(RecurringDatabase::new())),
+            recurring_path: None,
+            class_policies: ClassPolicyTable::default(),
+            scoring: Arc::new(RwLock::new(ScoringConfigs::default())),
+            scoring_path: None,
+            receipts: Arc::new(ReceiptCounter::in_memory()),
+            backup_dir: BACKUP_DIR.to_string(),
+            retention: RetentionPolicy::default(),
+            snapshots: Arc::new(RwLock::new(Vec::new())),
+            dirty: Arc::new(DirtyFlags::default()),
+            save_lock: Arc::new(Mutex::new(())),
+            scheduler: OnceLock::new(),
+            save_error_handler: None,
+        })
+    }
+
+    /// 启用 `record_cash` 的重复记录防护
+    pub fn with_duplicate_guard(mut self, guard: DuplicateGuard) -> Self {
+        self.duplicate_guard = Some(guard);
+        self
+    }
+
+    /// 标记分期已付款时自动生成下一期，见 [`QmxManager::mark_installment_paid`]
+    pub fn with_auto_next_installment(mut self, enabled: bool) -> Self {
+        self.auto_next_installment = enabled;
+        self
+    }
+
+    /// 设置分期到期后的宽限天数，默认为 0
+    ///
+    /// 待付分期在到期时间加宽限天数之后才算逾期，见 [`QmxManager::get_overdue_installments`]。
+    /// 计划通过 [`InstallmentPlanBuilder::grace_days`] 设置了宽限天数时以计划为准。
+    pub fn with_grace_days(mut self, days: u32) -> Self {
+        self.grace_days = days;
+        self
+    }
+
+    /// 设置字段长度与数量限制
+    pub fn with_limits(mut self, limits: Limits) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    /// 设置创建学生和记录现金时执行的校验规则，见 [`crate::validation`]
+    pub fn with_validator(mut self, validator: Validator) -> Self {
+        self.validator = validator;
+        self
+    }
+
+    /// 设置班级课时策略，默认只有 `TenTry` 记录课时
+    ///
+    /// 创建学生、切换班级、设置和增加课时以及售卖课时商品都按该策略表处理。
+    pub fn with_class_policies(mut self, policies: ClassPolicyTable) -> Self {
+        self.class_policies = policies;
+        self
+    }
+
+    /// 当前的计分配置
+    pub fn scoring_configs(&self) -> Result<ScoringConfigs> {
+        Ok(self
+            .scoring
+            .read()
+            .map_err(|e| Error::Poison(e.to_string()))?
+            .clone())
+    }
+
+    /// 替换计分配置，之后的成绩校验与统计按新配置进行，已有成绩不受影响
+    ///
+    /// 使用文件持久化时同时保存到数据目录的 [`SCORING_CONFIG_FILE`]。
+    pub fn set_scoring_configs(&self, configs: ScoringConfigs) -> Result<()> {
+        self.ensure_writable("set_scoring_configs")?;
+        self.authorize(Capability::ManageSettings)?;
+        let mut scoring = self
+            .scoring
+            .write()
+            .map_err(|e| Error::Poison(e.to_string()))?;
+        if let Some(path) = &self.scoring_path {
+            configs.save_to(path)?;
+        }
+        *scoring = configs;
+        info!("计分配置已更新");
+        Ok(())
+    }
+
+    /// 使用指定的时钟代替系统时间
+    ///
+    /// 会员状态、分期逾期、统计周期和新记录的创建时间都以该时钟为准。
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// 使用指定的存储后端
+    ///
+    /// 立即从后端加载数据替换当前内存中的数据，之后的保存（包括自动保存）都写入该后端。
+    /// 支持增量写入的后端在单条记录变更时只写入该记录。
+    ///
+    /// 审计日志不属于后端管理的数据，切换后只保存在内存中，
+    /// 需要持久化时再调用 [`QmxManager::with_audit_path`]。
+    pub fn with_backend(mut self, backend: Arc<dyn StorageBackend>) -> Result<Self> {
+        let loaded = backend.load()?;
+        {
+            let mut db = self
+                .database
+                .write()
+                .map_err(|e| Error::Poison(e.to_string()))?;
+            *db = loaded;
+        }
+        info!("切换到自定义存储后端");
+        self.backend = Some(backend);
+        self.wal = None;
+        self.audit_path = None;
+        self.journal
+            .lock()
+            .map_err(|e| Error::Poison(e.to_string()))?
+            .clear();
+        Ok(self)
+    }
+
+    /// 设置审计记录中的操作者，默认为 `系统`
+    pub fn with_actor(mut self, actor: impl Into<String>) -> Self {
+        self.actor = actor.into();
+        self
+    }
+
+    /// 以指定操作者的身份执行之后的所有操作
+    ///
+    /// 修改操作按操作者角色检查权限，没有权限时返回 [`Error::PermissionDenied`]，
+    /// 权限矩阵见 [`crate::permissions`]。操作者标识同时作为审计记录中的操作者。
+    pub fn with_operator(mut self, operator: Operator) -> Self {
+        self.actor = operator.id.clone();
+        self.operator = Some(operator);
+        self
+    }
+
+    /// 当前操作者，未指定时不检查权限
+    pub fn operator(&self) -> Option<&Operator> {
+        self.operator.as_ref()
+    }
+
+    /// 从指定路径加载审计日志，之后的审计记录都保存到该路径
+    pub fn with_audit_path(mut self, path: impl Into<String>) -> Result<Self> {
+        let path = path.into();
+        let audit = AuditDatabase::load_or_new_with(&path, &self.codec)?;
+        *self
+            .audit
+            .write()
+            .map_err(|e| Error::Poison(e.to_string()))? = audit;
+        info!("审计日志路径设置为 {}", path);
+        self.audit_path = Some(path);
+        Ok(self)
+    }
+
+    /// 使用指定的附件目录，并从该目录加载附件索引
+    pub fn with_attachments_dir(mut self, dir: impl Into<String>) -> Result<Self> {
+        let dir = dir.into();
+        let attachments = load_attachment_index(&dir, &self.codec)?;
+        *self
+            .attachments
+            .write()
+            .map_err(|e| Error::Poison(e.to_string()))? = attachments;
+        info!("附件目录设置为 {}", dir);
+        self.attachments_dir = dir;
+        Ok(self)
+    }
+
+    /// 从指定路径加载教练数据库，之后的教练修改都保存到该路径
+    pub fn with_coach_path(mut self, path: impl Into<String>) -> Result<Self> {
+        let path = path.into();
+        let coaches = CoachDatabase::load_or_new_with(&path, &self.codec)?;
+        *self
+            .coaches
+            .write()
+            .map_err(|e| Error::Poison(e.to_string()))? = coaches;
+        info!("教练数据库路径设置为 {}", path);
+        self.coach_path = Some(path);
+        Ok(self)
+    }
+
+    /// 从指定路径加载课程排期，之后的排期修改都保存到该路径
+    pub fn with_session_path(mut self, path: impl Into<String>) -> Result<Self> {
+        let path = path.into();
+        let sessions = SessionDatabase::load_or_new_with(&path, &self.codec)?;
+        *self
+            .sessions
+            .write()
+            .map_err(|e| Error::Poison(e.to_string()))? = sessions;
+        info!("课程排期路径设置为 {}", path);
+        self.session_path = Some(path);
+        Ok(self)
+    }
+
+    /// 从指定路径加载价目表，之后的价目表修改都保存到该路径
+    pub fn with_catalog_path(mut self, path: impl Into<String>) -> Result<Self> {
+        let path = path.into();
+        let catalog = CatalogDatabase::load_or_new_with(&path, &self.codec)?;
+        *self
+            .catalog
+            .write()
+            .map_err(|e| Error::Poison(e.to_string()))? = catalog;
+        info!("价目表路径设置为 {}", path);
+        self.catalog_path = Some(path);
+        Ok(self)
+    }
+
+    /// 从指定路径加载定期支出模板，之后的模板修改都保存到该路径
+    pub fn with_recurring_path(mut self, path: impl Into<String>) -> Result<Self> {
+        let path = path.into();
+        let recurring = RecurringDatabase::load_or_new_with(&path, &self.codec)?;
+        *self
+            .recurring
+            .write()
+            .map_err(|e| Error::Poison(e.to_string()))? = recurring;
+        info!("定期支出路径设置为 {}", path);
+        self.recurring_path = Some(path);
+        Ok(self)
+    }
+
+    /// 设置备份目录
+    pub fn with_backup_dir(mut self, dir: impl Into<String>) -> Self {
+        self.backup_dir = dir.into();
+        self
+    }
+
+    /// 设置旧备份的保留策略，每次备份后按该策略清理
+    pub fn with_retention_policy(mut self, policy: RetentionPolicy) -> Self {
+        self.retention = policy;
+        self
+    }
+
+    /// 设置保存数据文件时使用的压缩方式
+    ///
+    /// 只影响这个管理器的数据文件和备份，其他管理器和进程默认值
+    /// （见 [`crate::compression::set_compression`]）不受影响。读取时自动识别是否压缩，
+    /// 不受此设置影响。下次保存时所有数据文件都会按新的压缩方式重写。
+    /// 使用存储后端时由后端自己的编码方式决定，见 [`crate::JsonFileBackend::with_codec`]。
+    pub fn with_compression(mut self, compression: Compression) -> Self {
+        self.codec.compression = compression;
+        self.dirty.mark_all();
+        self
+    }
+
+    /// 使用确定性的命名空间 ID 序列代替全局 UID 计数器
+    ///
+    /// 新建的学生和现金记录 UID 由 [`IdNamespace`] 分配，序列会跳过数据库中
+    /// 该命名空间已存在的 UID。不同命名空间（如不同测试、不同分店）生成的 UID 互不冲突，
+    /// 相同的操作序列总是得到相同的 UID。
+    pub fn with_id_namespace(self, namespace: u16) -> Result<Self> {
+        self.with_id_strategy(IdStrategy::Namespaced(namespace))
+    }
+
+    /// 设置新建学生和现金记录的 UID 分配策略
+    ///
+    /// 需要合并多个安装实例的数据时，为每个实例配置不同的命名空间或节点号。
+    /// [`IdStrategy::TimeOrdered`] 使用管理器当前的时钟，应在 [`Self::with_clock`] 之后调用；
+    /// 节点号超出范围时返回 [`Error::ValidationFailed`]。
+    pub fn with_id_strategy(mut self, strategy: IdStrategy) -> Result<Self> {
+        let ids: Arc<dyn IdGenerator> = match strategy {
+            IdStrategy::Counter => {
+                info!("使用全局计数器分配ID");
+                self.ids = None;
+                return Ok(self);
+            }
+            IdStrategy::Namespaced(namespace) => Arc::new(IdNamespace::new(namespace)),
+            IdStrategy::TimeOrdered { node } => Arc::new(
+                TimeOrderedIds::new(node, self.clock.clone()).ok_or_else(|| {
+                    Error::ValidationFailed {
+                        field: "node".to_string(),
+                        reason: format!("节点号 {} 超出范围 0-1023", node),
+                    }
+                })?,
+            ),
+        };
+        {
+            let db = self
+                .database
+                .read()
+                .map_err(|e| Error::Poison(e.to_string()))?;
+            ids.observe_existing(
+                &mut db.student.iter().map(|(&uid, _)| uid),
+                &mut db.cash.iter().map(|(&uid, _)| uid),
+            );
+        }
+        info!("UID 分配策略: {:?}", strategy);
+        self.ids = Some(ids);
+        Ok(self)
+    }
+
+    /// 获取管理器时钟的当前时间
+    pub fn now(&self) -> DateTime<Utc> {
+        self.clock.now()
+    }
+
+    /// 手动保存数据
+    ///
+    /// 只重写自上次保存以来修改过的数据库文件，审计日志总是保存。
+    pub fn save(&self) -> Result<()> {
+        self.ensure_writable("save")?;
+        if let Some(scheduler) = self.scheduler.get() {
+            scheduler.reset();
+        }
+        self.persistence().save()
+    }
+
+    /// 立即保存尚未保存的修改，没有修改时不写文件
+    ///
+    /// [`AutoSave::Debounced`] 模式下用于在关键操作后确保数据已落盘，
+    /// 不必等待后台线程；其他模式下等同于有修改时调用 [`Self::save`]。
+    pub fn flush(&self) -> Result<()> {
+        let pending = self.scheduler.get().is_some_and(|s| s.pending() > 0);
+        if pending || self.has_unsaved_changes() {
+            self.save()
+        } else {
+            Ok(())
+        }
+    }
+
+    /// 是否有尚未保存到磁盘的修改
+    pub fn has_unsaved_changes(&self) -> bool {
+        self.dirty.student.load(Ordering::SeqCst) || self.dirty.cash.load(Ordering::SeqCst)
+    }
+
+    /// 设置后台自动保存失败时的回调
+    ///
+    /// 只在 [`AutoSave::Debounced`] 模式下使用：后台保存的错误无法返回给调用方，
+    /// 除记录错误日志外还会传给该回调，调用方可据此提示用户或重试 [`Self::flush`]。
+    pub fn with_save_error_handler(
+        mut self,
+        handler: impl Fn(&Error) + Send + Sync + 'static,
+    ) -> Self {
+        self.save_error_handler = Some(Arc::new(handler));
+        self
+    }
+
+    /// 保存审计日志（仅在设置了审计日志路径时）
+    fn save_audit(&self) -> Result<()> {
+        self.persistence().save_audit()
+    }
+
+    /// 保存所需的共享状态，可交给后台线程使用
+    fn persistence(&self) -> Persistence {
+        Persistence {
+            database: Arc::clone(&self.database),
+            backend: self.backend.clone(),
+            codec: self.codec.clone(),
+            wal: self.wal.clone(),
+            student_path: self.student_path.clone(),
+            cash_path: self.cash_path.clone(),
+            audit: Arc::clone(&self.audit),
+            audit_path: self.audit_path.clone(),
+            dirty: Arc::clone(&self.dirty),
+            save_lock: Arc::clone(&self.save_lock),
+        }
+    }
+
+    /// [`AutoSave::Debounced`] 模式下通知后台线程有新的修改，其他模式下不做任何事
+    ///
+    /// 第一次调用时启动后台线程，无法创建线程时返回 [`Error::Io`]。
+    fn schedule_save(&self) -> Result<()> {
+        let AutoSave::Debounced {
+            quiet_period,
+            max_pending,
+        } = self.auto_save
+        else {
+            return Ok(());
+        };
+        if self.scheduler.get().is_none() {
+            let persistence = self.persistence();
+            let on_error = self.save_error_handler.clone();
+            let scheduler = SaveScheduler::spawn(quiet_period, max_pending, move || {
+                if let Err(e) = persistence.save() {
+                    error!("后台自动保存失败: {}", e);
+                    if let Some(handler) = &on_error {
+                        handler(&e);
+                    }
+                }
+            })?;
+            // 并发启动时只保留先设置的调度器，多出的调度器在丢弃时退出
+            let _ = self.scheduler.set(scheduler);
+        }
+        if let Some(scheduler) = self.scheduler.get() {
+            scheduler.notify();
+        }
+        Ok(())
+    }
+
+    /// 记录一次修改操作，启用自动保存时同时保存审计日志
+    fn record_audit(
+        &self,
+        entity: AuditEntity,
+        entity_uid: u64,
+        action: AuditAction,
+        changes: Vec<FieldChange>,
+    ) -> Result<()> {
+        let mut audit = self
+            .audit
+            .write()
+            .map_err(|e| Error::Poison(e.to_string()))?;
+        let uid = audit.record(
+            entity,
+            entity_uid,
+            action,
+            &self.actor,
+            self.clock.now(),
+            changes,
+        );
+        if self.auto_save == AutoSave::Immediate {
+            drop(audit);
+            return self.save_audit();
+        }
+        // 审计日志随数据文件一起保存，在此之前由预写日志保留
+        if let (Some(path), Some(entry)) = (&self.audit_path, audit.get(&uid)) {
+            self.append_wal(WalRecord::Audit {
+                path: path.clone(),
+                entry: entry.clone(),
+            })?;
+        }
+        Ok(())
+    }
+
+    /// 追加一条学生和现金之外的记录（仅在使用预写日志时）
+    fn append_wal(&self, record: WalRecord) -> Result<()> {
+        match &self.wal {
+            Some(wal) => wal.append(&[record]),
+            None => Ok(()),
+        }
+    }
+
+    /// 把记录修改后的内容写入预写日志，需在持有数据库写锁、修改内存之后调用
+    ///
+    /// `before` 为涉及记录修改前的状态。写入失败时按 `before` 撤回内存中的修改并返回错误，
+    /// 因此释放写锁之后可见的修改都已写入日志，见 [`crate::wal`]。
+    fn write_wal(&self, db: &mut DbContainer, before: &[JournalEntry]) -> Result<()> {
+        let Some(wal) = &self.wal else {
+            return Ok(());
+        };
+        let records: Vec<WalRecord> = before
+            .iter()
+            .filter_map(|entry| match entry {
+                JournalEntry::Plan(plan_id, _) => Some(WalRecord::plan(db, *plan_id)),
+                _ => None,
+            })
+            .chain(before.iter().filter_map(|entry| match entry {
+                JournalEntry::Student(uid, _) => Some(WalRecord::student(db, *uid)),
+                _ => None,
+            }))
+            .chain(before.iter().filter_map(|entry| match entry {
+                JournalEntry::Cash(uid, _) => Some(WalRecord::cash(db, *uid)),
+                _ => None,
+            }))
+            .collect();
+        if let Err(e) = wal.append(&records) {
+            error!("写入预写日志失败，撤回本次修改: {}", e);
+            for entry in before.iter().rev().cloned() {
+                restore_entry(db, entry);
+            }
+            return Err(e);
+        }
+        Ok(())
+    }
+
+    /// 把一次操作修改前的记录状态写入操作日志
+    ///
+    /// 需在持有数据库写锁时调用，保证日志顺序与实际修改顺序一致。
+    /// 同时把涉及的数据库标记为已修改，并把修改后的内容写入预写日志，
+    /// 写入失败时撤回本次修改，见 [`QmxManager::write_wal`]。
+    fn push_journal(&self, db: &mut DbContainer, entries: Vec<JournalEntry>) -> Result<()> {
+        if entries.is_empty() {
+            return Ok(());
+        }
+        self.write_wal(db, &entries)?;
+        for entry in &entries {
+            match entry {
+                JournalEntry::Student(..) => self.dirty.student.store(true, Ordering::SeqCst),
+                // 分期计划保存在现金数据库中
+                JournalEntry::Cash(..) | JournalEntry::Plan(..) => {
+                    self.dirty.cash.store(true, Ordering::SeqCst)
+                }
+            }
+        }
+        let mut journal = self
+            .journal
+            .lock()
+            .map_err(|e| Error::Poison(e.to_string()))?;
+        if journal.len() == UNDO_HISTORY_LIMIT {
+            journal.pop_front();
+        }
+        journal.push_back(entries);
+        Ok(())
+    }
+
+    /// 单个学生变更后自动保存（如果启用）
+    fn auto_save_student(&self, uid: u64) -> Result<()> {
+        if self.auto_save != AutoSave::Immediate {
+            return self.schedule_save();
+        }
+        match &self.backend {
+            Some(backend) => {
+                let db = self
+                    .database
+                    .read()
+                    .map_err(|e| Error::Poison(e.to_string()))?;
+                backend.save_student(&db, uid)
+            }
+            None => self.save(),
+        }
+    }
+
+    /// 单条现金记录变更后自动保存（如果启用）
+    fn auto_save_cash(&self, uid: u64) -> Result<()> {
+        if self.auto_save != AutoSave::Immediate {
+            return self.schedule_save();
+        }
+        match &self.backend {
+            Some(backend) => {
+                let db = self
+                    .database
+                    .read()
+                    .map_err(|e| Error::Poison(e.to_string()))?;
+                backend.save_cash(&db, uid)
+            }
+            None => self.save(),
+        }
+    }
+
+    /// 多个学生变更后自动保存（如果启用），默认持久化方式只整体保存一次
+    fn auto_save_student_batch(&self, uids: &[u64]) -> Result<()> {
+        if uids.is_empty() {
+            return Ok(());
+        }
+        if self.auto_save != AutoSave::Immediate {
+            return self.schedule_save();
+        }
+        match &self.backend {
+            Some(backend) => {
+                let db = self
+                    .database
+                    .read()
+                    .map_err(|e| Error::Poison(e.to_string()))?;
+                backend.save_student_batch(&db, uids)
+            }
+            None => self.save(),
+        }
+    }
+
+    /// 多条现金记录变更后自动保存（如果启用），默认持久化方式只整体保存一次
+    fn auto_save_cash_batch(&self, uids: &[u64]) -> Result<()> {
+        if uids.is_empty() {
+            return Ok(());
+        }
+        if self.auto_save != AutoSave::Immediate {
+            return self.schedule_save();
+        }
+        match &self.backend {
+            Some(backend) => {
+                let db = self
+                    .database
+                    .read()
+                    .map_err(|e| Error::Poison(e.to_string()))?;
+                backend.save_cash_batch(&db, uids)
+            }
+            None => self.save(),
+        }
+    }
+
+    /// 数据库锁是否因其他线程在持锁期间 panic 而中毒
+    ///
+    /// 中毒后所有读写操作都会返回 [`Error::Poison`]，需调用 [`QmxManager::recover`] 恢复。
+    pub fn is_poisoned(&self) -> bool {
+        self.database.is_poisoned()
+    }
+
+    /// 从锁中毒状态恢复
+    ///
+    /// 丢弃内存中可能处于不一致状态的数据，从最近一次保存的文件重新加载，
+    /// 然后清除锁的中毒标记。未中毒时调用等同于重新加载已保存的数据。
+    /// 预写日志中尚未保存的修改同样被丢弃。
+    pub fn recover(&self) -> Result<()> {
+        let was_poisoned = self.is_poisoned();
+        if let Some(wal) = &self.wal {
+            wal.commit()?;
+        }
+        let reloaded = self.load_saved()?;
+        sync_uid_counters(&reloaded);
+        if let Some(ids) = &self.ids {
+            ids.observe_existing(
+                &mut reloaded.student.iter().map(|(&uid, _)| uid),
+                &mut reloaded.cash.iter().map(|(&uid, _)| uid),
+            );
+        }
+
+        let mut db = self.database.write().unwrap_or_else(|e| e.into_inner());
+        *db = reloaded;
+        self.dirty.student.store(false, Ordering::SeqCst);
+        self.dirty.cash.store(false, Ordering::SeqCst);
+        drop(db);
+        self.database.clear_poison();
+        self.journal
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .clear();
+
+        if was_poisoned {
+            warn!("数据库锁已中毒，已丢弃内存数据并从最近保存的状态恢复");
+        } else {
+            info!("已从最近保存的状态重新加载数据库");
+        }
+        Ok(())
+    }
+
+    /// 从管理器使用的路径加载最近保存的数据
+    fn load_saved(&self) -> Result<DbContainer> {
+        if let Some(backend) = &self.backend {
+            backend.load()
+        } else if let (Some(student_path), Some(cash_path)) = (&self.student_path, &self.cash_path)
+        {
+            Ok(DbContainer::new(
+                StudentDatabase::read_from_with(student_path, &self.codec)?,
+                CashDatabase::read_from_with(cash_path, &self.codec)?,
+            ))
+        } else {
+            crate::database::init_with_codec(&self.codec).map(|(db, _)| db)
+        }
+    }
+}
+
+// ============================================================================
+// 学生管理API
+// ============================================================================
+
+impl QmxManager {
+    /// 创建新学生
+    ///
+    /// # 参数
+    /// * `builder` - 学生构建器，使用链式调用设置属性
+    ///
+    /// # 示例
+    /// ```rust
+    /// use qmx_backend_lib::{QmxManager, StudentBuilder};
+    /// use qmx_backend_lib::student::{Class, Subject};
+    ///
+    /// # fn main() -> qmx_backend_lib::error::Result<()> {
+    /// # let manager = QmxManager::builder().auto_save(true).build()?;
+    /// let student_id = manager.create_student(
+    ///     StudentBuilder::new("张三")
+    ///         .age(16)
+    ///         .phone("13800138000")
+    ///         .class(Class::TenTry)
+    ///         .subject(Subject::Shooting)
+    ///         .note("优秀学生")
+    /// )?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn create_student(&self, builder: StudentBuilder) -> Result<u64> {
+        self.ensure_writable("create_student")?;
+        self.authorize(Capability::ManageStudents)?;
+        let mut db = self
+            .database
+            .write()
+            .map_err(|e| Error::Poison(e.to_string()))?;
+        let mut student = builder.build(&self.limits, &self.class_policies)?;
+        self.validator.validate_student(&student)?;
+        // 校验通过后才分配 UID，被拒绝的学生不占用编号
+        let uid = self.next_student_uid();
+        let now = self.clock.now();
+        student
+            .set_uid(uid)
+            .set_created_at(Some(now))
+            .set_updated_at(Some(now));
+        let changes = snapshot_fields(&student, AuditAction::Create)?;
+        db.student.insert(student);
+        self.push_journal(&mut db, vec![JournalEntry::Student(uid, None)])?;
+        drop(db);
+
+        self.record_audit(AuditEntity::Student, uid, AuditAction::Create, changes)?;
+        self.auto_save_student(uid)?;
+        self.events.emit(&Event::StudentCreated { uid });
+        info!("创建学生成功，UID: {}", uid);
+        Ok(uid)
+    }
+
+    /// 批量创建学生，只触发一次自动保存
+    ///
+    /// 任一构建器校验失败时整批都不写入，返回的 UID 与输入顺序一致。整批在撤销时算作一次操作。
+    pub fn create_students(&self, builders: Vec<StudentBuilder>) -> Result<Vec<u64>> {
+        self.ensure_writable("create_students")?;
+        self.authorize(Capability::ManageStudents)?;
+        let mut db = self
+            .database
+            .write()
+            .map_err(|e| Error::Poison(e.to_string()))?;
+        let now = self.clock.now();
+        let mut students = builders
+            .into_iter()
+            .map(|builder| {
+                let student = builder.build(&self.limits, &self.class_policies)?;
+                self.validator.validate_student(&student)?;
+                Ok(student)
+            })
+            .collect::<Result<Vec<_>>>()?;
+        for student in &mut students {
+            let uid = self.next_student_uid();
+            student
+                .set_uid(uid)
+                .set_created_at(Some(now))
+                .set_updated_at(Some(now));
+        }
+        let uids: Vec<u64> = students.iter().map(|s| s.uid()).collect();
+        let snapshots = students
+            .iter()
+            .map(|s| snapshot_fields(s, AuditAction::Create))
+            .collect::<Result<Vec<_>>>()?;
+        db.student.insert_batch(students);
+        self.push_journal(&mut db, 
+            uids.iter()
+                .map(|&uid| JournalEntry::Student(uid, None))
+                .collect(),
+        )?;
+        drop(db);
+
+        for (&uid, changes) in uids.iter().zip(snapshots) {
+            self.record_audit(AuditEntity::Student, uid, AuditAction::Create, changes)?;
+        }
+        self.auto_save_student_batch(&uids)?;
+        for &uid in &uids {
+            self.events.emit(&Event::StudentCreated { uid });
+        }
+        info!("批量创建学生成功，共 {} 个", uids.len());
+        Ok(uids)
+    }
+
+    /// 批量更新学生，只触发一次自动保存
+    ///
+    /// 按顺序执行每个更新器，返回每个更新实际发生变化的字段。任一更新失败时
+    /// 已执行的更新全部回滚。整批在撤销时算作一次操作。
+    pub fn update_students(
+        &self,
+        updates: Vec<(u64, StudentUpdater)>,
+    ) -> Result<Vec<Vec<FieldChange>>> {
+        self.ensure_writable("update_students")?;
+        self.authorize(Capability::ManageStudents)?;
+        let scoring = self.scoring_configs()?;
+        let mut db = self
+            .database
+            .write()
+            .map_err(|e| Error::Poison(e.to_string()))?;
+        let mut journal = Vec::new();
+        let mut results = Vec::with_capacity(updates.len());
+        for (uid, updater) in updates {
+            let before = db.student.get(&uid).cloned();
+            match updater.apply(
+                &mut db.student,
+                uid,
+                &self.limits,
+                &self.class_policies,
+                &scoring,
+                self.clock.now(),
+            ) {
+                Ok(changes) => {
+                    if !changes.is_empty() {
+                        journal.push(JournalEntry::Student(uid, before));
+                    }
+                    results.push((uid, changes));
+                }
+                Err(e) => {
+                    // 失败的更新不修改学生，逆序恢复已执行的更新，同一学生被多次更新时最终回到最初的状态
+                    for entry in journal.into_iter().rev() {
+                        if let JournalEntry::Student(_, Some(student)) = entry {
+                            db.student.insert(student);
+                        }
+                    }
+                    return Err(e);
+                }
+            }
+        }
+        self.push_journal(&mut db, journal)?;
+        drop(db);
+
+        let mut changed = Vec::new();
+        for (uid, changes) in &results {
+            if !changes.is_empty() {
+                self.record_audit(
+                    AuditEntity::Student,
+                    *uid,
+                    AuditAction::Update,
+                    changes.clone(),
+                )?;
+                changed.push(*uid);
+            }
+        }
+        self.auto_save_student_batch(&changed)?;
+        for (uid, changes) in &results {
+            if !changes.is_empty() {
+                self.events.emit(&Event::StudentUpdated {
+                    uid: *uid,
+                    changes: changes.clone(),
+                });
+            }
+        }
+        info!("批量更新学生成功，共 {} 个发生变化", changed.len());
+        Ok(results.into_iter().map(|(_, changes)| changes).collect())
+    }
+
+    /// 获取学生信息
+    pub fn get_student(&self, uid: u64) -> Result<Option<Student>> {
+        let db = self
+            .database
+            .read()
+            .map_err(|e| Error::Poison(e.to_string()))?;
+        Ok(db.student.get(&uid).cloned())
+    }
+
+    /// 更新学生信息
+    ///
+    /// 返回本次更新实际发生变化的字段列表。
+    pub fn update_student(&self, uid: u64, updater: StudentUpdater) -> Result<Vec<FieldChange>> {
+        self.ensure_writable("update_student")?;
+        self.authorize(Capability::ManageStudents)?;
+        // 先取出计分配置的副本，不在持有数据库锁时等待计分配置锁
+        let scoring = self.scoring_configs()?;
+        let mut db = self
+            .database
+            .write()
+            .map_err(|e| Error::Poison(e.to_string()))?;
+        let before = db.student.get(&uid).cloned();
+        let changes = updater.apply(
+            &mut db.student,
+            uid,
+            &self.limits,
+            &self.class_policies,
+            &scoring,
+            self.clock.now(),
+        )?;
+        if !changes.is_empty() {
+            self.push_journal(&mut db, vec![JournalEntry::Student(uid, before)])?;
+        }
+        drop(db);
+
+        if !changes.is_empty() {
+            self.record_audit(
+                AuditEntity::Student,
+                uid,
+                AuditAction::Update,
+                changes.clone(),
+            )?;
+        }
+        self.auto_save_student(uid)?;
+        if !changes.is_empty() {
+            self.events.emit(&Event::StudentUpdated {
+                uid,
+                changes: changes.clone(),
+            });
+        }
+        info!(
+            "更新学生信息成功，UID: {}，变更 {} 个字段",
+            uid,
+            changes.len()
+        );
+        Ok(changes)
+    }
+
+    /// 删除学生，关联的现金记录按 `policy` 处理
+    ///
+    /// 学生和现金记录在同一次操作中修改，[`DeletePolicy::Restrict`] 拒绝删除时不做任何修改。
+    /// [`DeletePolicy::Cascade`] 还需要删除现金记录的权限，[`DeletePolicy::Detach`] 还需要修改现金记录的权限。
+    pub fn delete_student(&self, uid: u64, policy: DeletePolicy) -> Result<bool> {
+        self.ensure_writable("delete_student")?;
+        self.authorize(Capability::DeleteStudents)?;
+        match policy {
+            DeletePolicy::Restrict => {}
+            DeletePolicy::Cascade => self.authorize(Capability::DeleteCash)?,
+            DeletePolicy::Detach => self.authorize(Capability::EditCash)?,
+        }
+        let mut db = self
+            .database
+            .write()
+            .map_err(|e| Error::Poison(e.to_string()))?;
+        if db.student.get(&uid).is_none() {
+            return Ok(false);
+        }
+        let linked: Vec<Cash> = db.cash.get_by_student(uid).into_iter().cloned().collect();
+        if policy == DeletePolicy::Restrict && !linked.is_empty() {
+            return Err(Error::State(format!(
+                "学生 {} 还有 {} 条现金记录，不能删除",
+                uid,
+                linked.len()
+            )));
+        }
+        let removed = db.student.remove(&uid);
+        let mut entries = vec![JournalEntry::Student(uid, removed.clone())];
+        let mut cash_changes = Vec::new();
+        for cash in &linked {
+            let changes = match policy {
+                DeletePolicy::Cascade => {
+                    db.cash.remove(&cash.uid);
+                    snapshot_fields(cash, AuditAction::Delete)?
+                }
+                _ => {
+                    let mut detached = cash.clone();
+                    detached.student_id = None;
+                    let changes = diff_fields(cash, &detached)?;
+                    db.cash.insert(detached);
+                    changes
+                }
+            };
+            entries.push(JournalEntry::Cash(cash.uid, Some(cash.clone())));
+            cash_changes.push((cash.uid, changes));
+        }
+        self.push_journal(&mut db, entries)?;
+        drop(db);
+
+        let cash_uids: Vec<u64> = linked.iter().map(|cash| cash.uid).collect();
+        if let Some(student) = &removed {
+            let changes = snapshot_fields(student, AuditAction::Delete)?;
+            self.record_audit(AuditEntity::Student, uid, AuditAction::Delete, changes)?;
+        }
+        let action = match policy {
+            DeletePolicy::Cascade => AuditAction::Delete,
+            _ => AuditAction::Update,
+        };
+        for (cash_uid, changes) in &cash_changes {
+            self.record_audit(AuditEntity::Cash, *cash_uid, action, changes.clone())?;
+        }
+        self.auto_save_student(uid)?;
+        self.auto_save_cash_batch(&cash_uids)?;
+        for (cash_uid, changes) in cash_changes {
+            self.events.emit(&match policy {
+                DeletePolicy::Cascade => Event::CashDeleted { uid: cash_uid },
+                _ => Event::CashUpdated {
+                    uid: cash_uid,
+                    changes,
+                },
+            });
+        }
+        self.events.emit(&Event::StudentDeleted { uid });
+        info!(
+            "删除学生成功，UID: {}，按 {:?} 处理 {} 条关联现金记录",
+            uid,
+            policy,
+            cash_uids.len()
+        );
+        Ok(true)
+    }
+
+    /// 搜索学生
+    pub fn search_students(&self, query: StudentQuery) -> Result<Vec<Student>> {
+        Ok(self.search_students_paged(query)?.items)
+    }
+
+    /// 分页搜索学生，同时返回匹配总数
+    pub fn search_students_paged(&self, query: StudentQuery) -> Result<SearchResult<Student>> {
+        let db = self
+            .database
+            .read()
+            .map_err(|e| Error::Poison(e.to_string()))?;
+        Ok(query.execute(&db.student))
+    }
+
+    /// 统计匹配查询条件的学生数，忽略查询的分页设置
+    pub fn count_students(&self, query: StudentQuery) -> Result<usize> {
+        let db = self
+            .database
+            .read()
+            .map_err(|e| Error::Poison(e.to_string()))?;
+        Ok(query.count(&db.student))
+    }
+
+    /// 是否存在匹配查询条件的学生，找到第一个匹配的学生即返回
+    pub fn student_exists(&self, query: StudentQuery) -> Result<bool> {
+        let db = self
+            .database
+            .read()
+            .map_err(|e| Error::Poison(e.to_string()))?;
+        Ok(query.exists(&db.student))
+    }
+
+    /// 在读锁下依次访问匹配查询条件的学生，返回访问的学生数
+    ///
+    /// 与 [`QmxManager::search_students`] 的排序和分页规则相同，但不克隆学生记录。
+    /// 回调执行期间持有数据库读锁，不能在回调中调用本管理器的修改方法，否则会死锁。
+    pub fn for_each_student<F>(&self, query: StudentQuery, mut f: F) -> Result<usize>
+    where
+        F: FnMut(&Student),
+    {
+        let db = self
+            .database
+            .read()
+            .map_err(|e| Error::Poison(e.to_string()))?;
+        let mut visited = 0;
+        for student in query.page.window(query.matched(&db.student)) {
+            f(student);
+            visited += 1;
+        }
+        Ok(visited)
+    }
+
+    /// 获取所有学生
+    pub fn list_students(&self) -> Result<Vec<Student>> {
+        let db = self
+            .database
+            .read()
+            .map_err(|e| Error::Poison(e.to_string()))?;
+        Ok(db.student.iter().map(|(_, s)| s).cloned().collect())
+    }
+
+    /// 获取会员将在 `within_days` 天内到期的学生，按到期时间升序排列
+    ///
+    /// 以管理器时钟为准，已经过期的会员不包含在内。
+    pub fn get_expiring_memberships(&self, within_days: u32) -> Result<Vec<Student>> {
+        let now = self.clock.now();
+        let deadline = now + chrono::Duration::days(i64::from(within_days));
+        let db = self
+            .database
+            .read()
+            .map_err(|e| Error::Poison(e.to_string()))?;
+        let mut expiring: Vec<Student> = db
+            .student
+            .iter()
+            .map(|(_, s)| s)
+            .filter(|s| {
+                s.membership_end_date()
+                    .is_some_and(|end| end >= now && end <= deadline)
+            })
+            .cloned()
+            .collect();
+        expiring.sort_by_key(|s| (s.membership_end_date(), s.uid()));
+        Ok(expiring)
+    }
+
+    /// 获取最近 `days` 天内既没有现金记录也没有上课记录的学生，按 UID 排列
+    ///
+    /// 上课记录指报名了开始时间在该时间段内的课程。以管理器时钟为准。
+    pub fn get_inactive_students(&self, days: u32) -> Result<Vec<Student>> {
+        let now = self.clock.now();
+        let since = now - chrono::Duration::days(i64::from(days));
+        let mut active: std::collections::HashSet<u64> = self
+            .sessions
+            .read()
+            .map_err(|e| Error::Poison(e.to_string()))?
+            .iter()
+            .filter(|(_, session)| session.start >= since && session.start <= now)
+            .flat_map(|(_, session)| session.enrolled.iter().copied())
+            .collect();
+        let db = self
+            .database
+            .read()
+            .map_err(|e| Error::Poison(e.to_string()))?;
+        active.extend(
+            db.cash
+                .iter()
+                .filter(|(_, c)| c.created_at >= since && c.created_at <= now)
+                .filter_map(|(_, c)| c.student_id),
+        );
+        Ok(db
+            .student
+            .iter()
+            .filter(|(uid, _)| !active.contains(uid))
+            .map(|(_, s)| s.clone())
+            .collect())
+    }
+
+    /// 获取需要续费的学生，按 UID 排列
+    ///
+    /// 剩余课时少于 [`RENEWAL_LESSON_THRESHOLD`] 或会员将在 [`RENEWAL_MEMBERSHIP_DAYS`]
+    /// 天内到期的学生都包含在内，便于前台提前联系。不记录课时的学生只按会员到期时间判断。
+    pub fn get_students_needing_renewal(&self) -> Result<Vec<Student>> {
+        let now = self.clock.now();
+        let deadline = now + chrono::Duration::days(i64::from(RENEWAL_MEMBERSHIP_DAYS));
+        let db = self
+            .database
+            .read()
+            .map_err(|e| Error::Poison(e.to_string()))?;
+        Ok(db
+            .student
+            .iter()
+            .map(|(_, s)| s)
+            .filter(|s| {
+                s.lesson_left()
+                    .is_some_and(|left| left < RENEWAL_LESSON_THRESHOLD)
+                    || s.membership_end_date()
+                        .is_some_and(|end| end >= now && end <= deadline)
+            })
+            .cloned()
+            .collect())
+    }
+}
+
+// ============================================================================
+// 现金管理API
+// ============================================================================
+
+impl QmxManager {
+    /// 记录现金流
+    ///
+    /// 关联的学生不存在时返回 [`Error::NotFound`]，除非使用了 [`CashBuilder::allow_dangling`]。
+    pub fn record_cash(&self, builder: CashBuilder) -> Result<u64> {
+        self.ensure_writable("record_cash")?;
+        self.authorize(Capability::RecordCash)?;
+        let sale_item = builder.sale_item;
+        if sale_item.is_some() {
+            self.authorize(Capability::ManageStudents)?;
+        }
+        let mut db = self
+            .database
+            .write()
+            .map_err(|e| Error::Poison(e.to_string()))?;
+        let mut cash = self.prepare_cash(&db.student, &db.cash, builder)?;
+        let credited = match sale_item {
+            Some(item_id) => self.credit_sale(&mut db.student, &mut cash, item_id)?,
+            None => None,
+        };
+        let uid = self.next_cash_uid();
+        cash.uid = uid;
+        let changes = snapshot_fields(&cash, AuditAction::Create)?;
+        db.cash.insert(cash);
+        let mut entries = vec![JournalEntry::Cash(uid, None)];
+        if let Some(credit) = &credited {
+            entries.push(JournalEntry::Student(
+                credit.before.uid(),
+                Some(credit.before.clone()),
+            ));
+        }
+        self.push_journal(&mut db, entries)?;
+        drop(db);
+
+        self.record_audit(AuditEntity::Cash, uid, AuditAction::Create, changes)?;
+        if let Some(credit) = &credited {
+            self.record_audit(
+                AuditEntity::Student,
+                credit.before.uid(),
+                AuditAction::Update,
+                credit.changes.clone(),
+            )?;
+            self.auto_save_student(credit.before.uid())?;
+        }
+        self.auto_save_cash(uid)?;
+        self.events.emit(&Event::CashRecorded { uid });
+        if let Some(credit) = credited {
+            self.events.emit(&Event::StudentUpdated {
+                uid: credit.before.uid(),
+                changes: credit.changes,
+            });
+        }
+        info!("记录现金流成功，UID: {}", uid);
+        Ok(uid)
+    }
+
+    /// 批量记录现金流，只触发一次自动保存
+    ///
+    /// 任一记录校验失败（包括重复防护拒绝）时整批都不写入。批次内的记录之间同样做重复检测。
+    /// 整批在撤销时算作一次操作。
+    pub fn record_cash_batch(&self, builders: Vec<CashBuilder>) -> Result<Vec<u64>> {
+        self.ensure_writable("record_cash_batch")?;
+        self.authorize(Capability::RecordCash)?;
+        let has_sale = builders.iter().any(|b| b.sale_item.is_some());
+        if has_sale {
+            self.authorize(Capability::ManageStudents)?;
+        }
+        let mut db = self
+            .database
+            .write()
+            .map_err(|e| Error::Poison(e.to_string()))?;
+        let mut staged = db.cash.clone();
+        let mut students = has_sale.then(|| db.student.clone());
+        let mut records = Vec::with_capacity(builders.len());
+        let mut credits = Vec::new();
+        for builder in builders {
+            let sale_item = builder.sale_item;
+            let mut cash =
+                self.prepare_cash(students.as_ref().unwrap_or(&db.student), &staged, builder)?;
+            if let (Some(item_id), Some(students)) = (sale_item, students.as_mut())
+                && let Some(credit) = self.credit_sale(students, &mut cash, item_id)?
+            {
+                credits.push(credit);
+            }
+            cash.uid = self.next_cash_uid();
+            staged.insert(cash.clone());
+            records.push(cash);
+        }
+        let uids: Vec<u64> = records.iter().map(|c| c.uid).collect();
+        let snapshots = records
+            .iter()
+            .map(|c| snapshot_fields(c, AuditAction::Create))
+            .collect::<Result<Vec<_>>>()?;
+        db.cash = staged;
+        if let Some(students) = students {
+            db.student = students;
+        }
+        let mut entries: Vec<JournalEntry> = uids
+            .iter()
+            .map(|&uid| JournalEntry::Cash(uid, None))
+            .collect();
+        // 同一学生多次售卖时只记录第一次修改前的状态
+        let mut credited_students = Vec::new();
+        for credit in &credits {
+            if !credited_students.contains(&credit.before.uid()) {
+                credited_students.push(credit.before.uid());
+                entries.push(JournalEntry::Student(
+                    credit.before.uid(),
+                    Some(credit.before.clone()),
+                ));
+            }
+        }
+        self.push_journal(&mut db, entries)?;
+        drop(db);
+
+        for (&uid, changes) in uids.iter().zip(snapshots) {
+            self.record_audit(AuditEntity::Cash, uid, AuditAction::Create, changes)?;
+        }
+        for credit in &credits {
+            self.record_audit(
+                AuditEntity::Student,
+                credit.before.uid(),
+                AuditAction::Update,
+                credit.changes.clone(),
+            )?;
+        }
+        if !credited_students.is_empty() {
+            self.auto_save_student_batch(&credited_students)?;
+        }
+        self.auto_save_cash_batch(&uids)?;
+        for &uid in &uids {
+            self.events.emit(&Event::CashRecorded { uid });
+        }
+        for credit in credits {
+            self.events.emit(&Event::StudentUpdated {
+                uid: credit.before.uid(),
+                changes: credit.changes,
+            });
+        }
+        info!("批量记录现金流成功，共 {} 条", uids.len());
+        Ok(uids)
+    }
+
+    /// 构建待写入的现金记录并执行重复防护检查
+    ///
+    /// 重复防护在分配 UID 之前检查，被拒绝的重复记录不占用 UID。
+    /// 校验收款构建器并生成草稿，草稿尚未分配 UID
+    fn prepare_cash(
+        &self,
+        students: &StudentDatabase,
+        existing: &CashDatabase,
+        builder: CashBuilder,
+    ) -> Result<Cash> {
+        let force = builder.force;
+        let created_at = builder.created_at.unwrap_or_else(|| self.clock.now());
+        if !builder.allow_dangling {
+            check_student_exists(students, builder.student_id)?;
+        }
+        let duplicate = self.duplicate_guard.and_then(|guard| {
+            existing
+                .find_duplicate(builder.student_id, builder.amount, created_at, guard.window)
+                .map(|duplicate| (guard.policy, duplicate.uid))
+        });
+        if let Some((DuplicatePolicy::Reject, duplicate_uid)) = duplicate
+            && !force
+        {
+            return Err(Error::ValidationFailed {
+                field: "cash".to_string(),
+                reason: format!(
+                    "与现金记录 {} 疑似重复（学生 {:?}，金额 {}）",
+                    duplicate_uid, builder.student_id, builder.amount
+                ),
+            });
+        }
+        let mut cash = builder.build(&self.limits)?;
+        check_wallet_currency(existing, &cash)?;
+        check_recurring_unique(existing, &cash)?;
+        inherit_branch(&mut cash, students);
+        self.validator.validate_cash(&cash)?;
+        cash.created_at = created_at;
+        if let Some((_, duplicate_uid)) = duplicate {
+            warn!(
+                "新现金记录与已有记录 {} 疑似重复（学生 {:?}，金额 {}）",
+                duplicate_uid, cash.student_id, cash.cash
+            );
+        }
+        Ok(cash)
+    }
+
+    /// 按售卖标记为关联学生应用商品效果，撤回信息写入 `cash.sale`
+    ///
+    /// 修改后的学生写入 `students`；商品不影响学生时返回 `None`。
+    fn credit_sale(
+        &self,
+        students: &mut StudentDatabase,
+        cash: &mut Cash,
+        item_id: u64,
+    ) -> Result<Option<SaleCredited>> {
+        let item = self
+            .get_catalog_item(item_id)?
+            .ok_or_else(|| Error::NotFound(format!("商品不存在: {}", item_id)))?;
+        let student_id = cash.student_id.ok_or_else(|| {
+            Error::InvalidInput(format!("售卖商品 {} 的收款必须关联学生", item.name))
+        })?;
+        let before = students
+            .get(&student_id)
+            .cloned()
+            .ok_or_else(|| Error::NotFound(format!("学生不存在: {}", student_id)))?;
+        let mut student = before.clone();
+        let credit = item.apply_to(&mut student, &self.class_policies, cash.created_at)?;
+        let affects_student = credit.affects_student();
+        cash.sale = Some(credit);
+        if !affects_student {
+            return Ok(None);
+        }
+        student.set_updated_at(Some(cash.created_at));
+        let changes = diff_fields(&before, &student)?;
+        students.insert(student);
+        debug!("售卖商品 {} 已应用到学生 {}", item.name, student_id);
+        Ok(Some(SaleCredited { before, changes }))
+    }
+
+    /// 为已有的收入记录登记退款
+    ///
+    /// `amount` 为退款金额（正数），退款记录以负数金额保存并关联原记录，
+    /// 统计时计入支出。累计退款不能超过原记录金额。
+    ///
+    /// 原记录是售卖收款（见 [`CashBuilder::sale`]）且本次退款后全额退清时，
+    /// 同时撤回售卖为学生增加的课时和会员期；部分退款不影响学生。
+    /// 原记录是储值充值时，退款金额同时从学生余额中扣除。
+    pub fn refund_cash(&self, original_uid: u64, amount: i64, note: Option<String>) -> Result<u64> {
+        self.ensure_writable("refund_cash")?;
+        self.authorize(Capability::EditCash)?;
+        if amount <= 0 {
+            return Err(Error::InvalidInput(format!(
+                "退款金额必须为正数: {}",
+                amount
+            )));
+        }
+        if let Some(note) = &note {
+            Limits::check_len("note", note, self.limits.max_note_len)?;
+        }
+
+        let mut db = self
+            .database
+            .write()
+            .map_err(|e| Error::Poison(e.to_string()))?;
+        let original = db
+            .cash
+            .get(&original_uid)
+            .ok_or_else(|| Error::NotFound(format!("现金记录不存在: {}", original_uid)))?;
+        if original.refund_of.is_some() || !original.cash.is_positive() {
+            return Err(Error::State(format!(
+                "现金记录 {} 不是收入记录，无法退款",
+                original_uid
+            )));
+        }
+        let refundable = original.cash.amount_minor - db.cash.refunded_amount(original_uid);
+        let currency = original.cash.currency;
+        if amount > refundable {
+            return Err(Error::ValidationFailed {
+                field: "cash".to_string(),
+                reason: format!(
+                    "退款金额 {} 超过记录 {} 的可退金额 {}",
+                    amount, original_uid, refundable
+                ),
+            });
+        }
+
+        let mut refund = match self.ids.as_deref() {
+            Some(ids) => Cash::new_with_uid(ids.next_cash_uid(), original.student_id),
+            None => Cash::new(original.student_id),
+        };
+        refund.set_cash(Money::new(-amount, currency));
+        refund.set_note(note);
+        refund.refund_of = Some(original_uid);
+        refund.branch_id = original.branch_id.clone();
+        if original.wallet.is_some() {
+            // 充值退款同时扣减余额
+            refund.wallet = Some(refund.cash);
+        }
+        refund.created_at = self.clock.now();
+        let uid = refund.uid;
+        let changes = snapshot_fields(&refund, AuditAction::Create)?;
+        let mut entries = vec![JournalEntry::Cash(uid, None)];
+        let mut original_changes = None;
+        let mut student_changes = None;
+        if amount == refundable
+            && let Some(before) = db.cash.get(&original_uid).cloned()
+            && let Some(mut credit) = before.sale.clone().filter(|credit| !credit.reversed)
+        {
+            let student_before = before
+                .student_id
+                .filter(|_| credit.affects_student())
+                .and_then(|student_id| db.student.get(&student_id).cloned());
+            let reversed_student = match &student_before {
+                Some(student_before) => {
+                    let mut student = student_before.clone();
+                    credit.reverse(&mut student);
+                    student.set_updated_at(Some(refund.created_at));
+                    let changes = diff_fields(student_before, &student)?;
+                    Some((student, changes))
+                }
+                None => {
+                    credit.reversed = true;
+                    None
+                }
+            };
+            let mut after = before.clone();
+            after.sale = Some(credit);
+            original_changes = Some(diff_fields(&before, &after)?);
+            db.cash.insert(after);
+            entries.push(JournalEntry::Cash(original_uid, Some(before)));
+            if let (Some(student_before), Some((student, changes))) =
+                (student_before, reversed_student)
+            {
+                student_changes = Some((student.uid(), changes));
+                db.student.insert(student);
+                entries.push(JournalEntry::Student(
+                    student_before.uid(),
+                    Some(student_before),
+                ));
+            }
+        }
+        db.cash.insert(refund);
+        self.push_journal(&mut db, entries)?;
+        drop(db);
+
+        self.record_audit(AuditEntity::Cash, uid, AuditAction::Create, changes)?;
+        if let Some(changes) = &original_changes {
+            self.record_audit(
+                AuditEntity::Cash,
+                original_uid,
+                AuditAction::Update,
+                changes.clone(),
+            )?;
+            self.auto_save_cash_batch(&[uid, original_uid])?;
+        } else {
+            self.auto_save_cash(uid)?;
+        }
+        if let Some((student_id, changes)) = &student_changes {
+            self.record_audit(
+                AuditEntity::Student,
+                *student_id,
+                AuditAction::Update,
+                changes.clone(),
+            )?;
+            self.auto_save_student(*student_id)?;
+        }
+        self.events.emit(&Event::CashRecorded { uid });
+        if let Some(changes) = original_changes {
+            self.events.emit(&Event::CashUpdated {
+                uid: original_uid,
+                changes,
+            });
+        }
+        if let Some((uid, changes)) = student_changes {
+            self.events.emit(&Event::StudentUpdated { uid, changes });
+        }
+        info!(
+            "登记退款成功，UID: {}，原记录: {}，金额: {}",
+            uid, original_uid, amount
+        );
+        Ok(uid)
+    }
+
+    /// 创建分期计划，一次性生成所有分期记录
+    ///
+    /// 计划条款保存到计划数据库，所有分期记录状态为 `Pending`，
+    /// 返回计划 ID 和按期数排列的现金记录 UID。关联的学生不存在时返回 [`Error::NotFound`]。
+    pub fn create_installment_plan(
+        &self,
+        builder: InstallmentPlanBuilder,
+    ) -> Result<CreatedPlan> {
+        self.ensure_writable("create_installment_plan")?;
+        self.authorize(Capability::RecordCash)?;
+        let mut db = self
+            .database
+            .write()
+            .map_err(|e| Error::Poison(e.to_string()))?;
+        check_student_exists(&db.student, builder.student_id)?;
+        let (plan, mut records) =
+            builder.build(&self.limits, self.ids.as_deref(), self.clock.now())?;
+        let plan_id = plan.plan_id;
+        for cash in &mut records {
+            inherit_branch(cash, &db.student);
+        }
+        let cash_uids: Vec<u64> = records.iter().map(|c| c.uid).collect();
+        let snapshots = records
+            .iter()
+            .map(|c| snapshot_fields(c, AuditAction::Create))
+            .collect::<Result<Vec<_>>>()?;
+        db.cash.plans.insert(plan);
+        db.cash.insert_batch(records);
+        self.push_journal(
+            &mut db,
+            std::iter::once(JournalEntry::Plan(plan_id, None))
+                .chain(cash_uids.iter().map(|&uid| JournalEntry::Cash(uid, None)))
+                .collect(),
+        )?;
+        drop(db);
+
+        for (&uid, changes) in cash_uids.iter().zip(snapshots) {
+            self.record_audit(AuditEntity::Cash, uid, AuditAction::Create, changes)?;
+        }
+        self.auto_save_cash_batch(&cash_uids)?;
+        for &uid in &cash_uids {
+            self.events.emit(&Event::CashRecorded { uid });
+        }
+        info!(
+            "创建分期计划成功，计划ID: {}，共 {} 期",
+            plan_id,
+            cash_uids.len()
+        );
+        Ok(CreatedPlan { plan_id, cash_uids })
+    }
+
+    /// 获取分期计划的条款
+    pub fn get_installment_plan(&self, plan_id: u64) -> Result<Option<InstallmentPlan>> {
+        let db = self
+            .database
+            .read()
+            .map_err(|e| Error::Poison(e.to_string()))?;
+        Ok(db.cash.plans.get(&plan_id).cloned())
+    }
+
+    /// 为现金记录生成带顺序编号的收据
+    pub fn generate_receipt(&self, cash_uid: u64, header: &InstitutionHeader) -> Result<Receipt> {
+        let db = self
+            .database
+            .read()
+            .map_err(|e| Error::Poison(e.to_string()))?;
+        let cash = db
+            .cash
+            .get(&cash_uid)
+            .ok_or_else(|| Error::NotFound(format!("现金记录不存在: {}", cash_uid)))?;
+        let student = cash.student_id.and_then(|id| db.student.get(&id));
+        Receipt::generate(cash, student, header, &self.receipts, self.clock.now())
+    }
+
+    /// 下一张收据的编号
+    pub fn next_receipt_number(&self) -> u64 {
+        self.receipts.peek()
+    }
+
+    /// 获取现金记录
+    pub fn get_cash(&self, uid: u64) -> Result<Option<Cash>> {
+        let db = self
+            .database
+            .read()
+            .map_err(|e| Error::Poison(e.to_string()))?;
+        Ok(db.cash.get(&uid).cloned())
+    }
+
+    /// 更新现金记录
+    ///
+    /// 返回本次更新实际发生变化的字段列表。新关联的学生不存在时返回 [`Error::NotFound`]，
+    /// 除非使用了 [`CashUpdater::allow_dangling`]。
+    pub fn update_cash(&self, uid: u64, updater: CashUpdater) -> Result<Vec<FieldChange>> {
+        self.ensure_writable("update_cash")?;
+        self.authorize(Capability::EditCash)?;
+        let mut db = self
+            .database
+            .write()
+            .map_err(|e| Error::Poison(e.to_string()))?;
+        let before = db.cash.get(&uid).cloned();
+        updater.check_students(&db.student)?;
+        let changes = updater.apply(&mut db.cash, uid, &self.limits)?;
+        if !changes.is_empty() {
+            self.push_journal(&mut db, vec![JournalEntry::Cash(uid, before)])?;
+        }
+        drop(db);
+
+        if !changes.is_empty() {
+            self.record_audit(AuditEntity::Cash, uid, AuditAction::Update, changes.clone())?;
+        }
+        self.auto_save_cash(uid)?;
+        if !changes.is_empty() {
+            self.events.emit(&Event::CashUpdated {
+                uid,
+                changes: changes.clone(),
+            });
+        }
+        info!(
+            "更新现金记录成功，UID: {}，变更 {} 个字段",
+            uid,
+            changes.len()
+        );
+        Ok(changes)
+    }
+
+    /// 删除现金记录
+    pub fn delete_cash(&self, uid: u64) -> Result<bool> {
+        self.ensure_writable("delete_cash")?;
+        self.authorize(Capability::DeleteCash)?;
+        let mut db = self
+            .database
+            .write()
+            .map_err(|e| Error::Poison(e.to_string()))?;
+        let removed = db.cash.remove(&uid);
+        if removed.is_some() {
+            self.push_journal(&mut db, vec![JournalEntry::Cash(uid, removed.clone())])?;
+        }
+        drop(db);
+
+        if let Some(cash) = removed {
+            let changes = snapshot_fields(&cash, AuditAction::Delete)?;
+            self.record_audit(AuditEntity::Cash, uid, AuditAction::Delete, changes)?;
+            self.auto_save_cash(uid)?;
+            self.events.emit(&Event::CashDeleted { uid });
+            info!("删除现金记录成功，UID: {}", uid);
+            return Ok(true);
+        }
+        Ok(false)
+    }
+
+    /// 搜索现金记录
+    pub fn search_cash(&self, query: CashQuery) -> Result<Vec<Cash>> {
+        Ok(self.search_cash_paged(query)?.items)
+    }
+
+    /// 分页搜索现金记录，同时返回匹配总数
+    pub fn search_cash_paged(&self, query: CashQuery) -> Result<SearchResult<Cash>> {
+        let db = self
+            .database
+            .read()
+            .map_err(|e| Error::Poison(e.to_string()))?;
+        Ok(query.execute(&db.cash, &db.student))
+    }
+
+    /// 统计匹配查询条件的现金记录数，忽略查询的分页设置
+    pub fn count_cash(&self, query: CashQuery) -> Result<usize> {
+        let db = self
+            .database
+            .read()
+            .map_err(|e| Error::Poison(e.to_string()))?;
+        Ok(query.count(&db.cash, &db.student))
+    }
+
+    /// 是否存在匹配查询条件的现金记录，找到第一条匹配的记录即返回
+    pub fn cash_exists(&self, query: CashQuery) -> Result<bool> {
+        let db = self
+            .database
+            .read()
+            .map_err(|e| Error::Poison(e.to_string()))?;
+        Ok(query.exists(&db.cash, &db.student))
+    }
+
+    /// 对匹配查询条件的现金记录分组汇总，详见 [`aggregate_cash`]
+    ///
+    /// 查询的排序和分页设置同样生效，只汇总当前页的记录。
+    pub fn aggregate_cash(&self, query: CashQuery, group_by: GroupBy) -> Result<Vec<CashGroup>> {
+        let db = self
+            .database
+            .read()
+            .map_err(|e| Error::Poison(e.to_string()))?;
+        let matched = query.matched(&db.cash, &db.student);
+        aggregate_cash(query.page.window(matched), group_by)
+    }
+
+    /// 在读锁下依次访问匹配查询条件的现金记录，返回访问的记录数
+    ///
+    /// 与 [`QmxManager::search_cash`] 的排序和分页规则相同，但不克隆现金记录。
+    /// 回调执行期间持有数据库读锁，不能在回调中调用本管理器的修改方法，否则会死锁。
+    pub fn for_each_cash<F>(&self, query: CashQuery, mut f: F) -> Result<usize>
+    where
+        F: FnMut(&Cash),
+    {
+        let db = self
+            .database
+            .read()
+            .map_err(|e| Error::Poison(e.to_string()))?;
+        let mut visited = 0;
+        for cash in query.page.window(query.matched(&db.cash, &db.student)) {
+            f(cash);
+            visited += 1;
+        }
+        Ok(visited)
+    }
+
+    /// 把匹配查询条件的现金记录导出为 CSV 文件，返回导出的记录数
+    ///
+    /// 列依次为日期、学生姓名、金额（元）、备注和付款计划，格式详见
+    /// [`crate::export::write_cash_csv`]。
+    pub fn export_cash_csv(
+        &self,
+        query: CashQuery,
+        path: impl AsRef<std::path::Path>,
+    ) -> Result<usize> {
+        let db = self
+            .database
+            .read()
+            .map_err(|e| Error::Poison(e.to_string()))?;
+        let records = query.execute(&db.cash, &db.student).items;
+        crate::export::export_cash_csv(path, &records, &db.student, &db.cash.plans)?;
+        Ok(records.len())
+    }
+
+    /// 把全部学生、现金记录和仪表板统计导出为 xlsx 工作簿
+    #[cfg(feature = "xlsx")]
+    pub fn export_xlsx(&self, path: impl AsRef<std::path::Path>) -> Result<()> {
+        let db = self
+            .database
+            .read()
+            .map_err(|e| Error::Poison(e.to_string()))?;
+        let stats = get_dashboard_stats_at(&db.student, &db.cash, self.clock.now())?;
+        crate::export::xlsx::export_workbook(path, &db.student, &db.cash, &stats)
+    }
+
+    /// 把即将开始的课程、分期付款到期日和会员到期日导出为 iCalendar 文件，返回事件数
+    ///
+    /// 以管理器时钟为准，只导出尚未到来的日程，详见 [`crate::export::ical::write_calendar`]。
+    pub fn export_ical(&self, path: impl AsRef<std::path::Path>) -> Result<usize> {
+        let db = self
+            .database
+            .read()
+            .map_err(|e| Error::Poison(e.to_string()))?;
+        let coaches = self
+            .coaches
+            .read()
+            .map_err(|e| Error::Poison(e.to_string()))?;
+        let sessions: Vec<Session> = self
+            .sessions
+            .read()
+            .map_err(|e| Error::Poison(e.to_string()))?
+            .iter()
+            .map(|(_, session)| session.clone())
+            .collect();
+        let source = crate::export::ical::CalendarSource {
+            sessions: &sessions,
+            students: &db.student,
+            cash: &db.cash,
+            coaches: &coaches,
+        };
+        crate::export::ical::export_calendar(path, &source, self.clock.now())
+    }
+
+    /// 检测疑似重复的现金记录
+    ///
+    /// 返回 `(较早记录UID, 较晚记录UID)` 列表，详见 [`CashDatabase::find_duplicates`]。
+    pub fn detect_duplicate_cash(&self, window: chrono::Duration) -> Result<Vec<(u64, u64)>> {
+        let db = self
+            .database
+            .read()
+            .map_err(|e| Error::Poison(e.to_string()))?;
+        Ok(db.cash.find_duplicates(window))
+    }
+
+    /// 获取按管理器时钟计算已过宽限期的待付分期，宽限期见 [`QmxManager::with_grace_days`]
+    pub fn get_overdue_installments(&self) -> Result<Vec<Cash>> {
+        let db = self
+            .database
+            .read()
+            .map_err(|e| Error::Poison(e.to_string()))?;
+        Ok(db
+            .cash
+            .get_overdue_installments_with_grace(self.clock.now(), self.grace_days)
+            .into_iter()
+            .cloned()
+            .collect())
+    }
+
+    /// 将已过宽限期的待付分期标记为 `Overdue`，返回被修改的记录
+    pub fn mark_overdue_installments(&self) -> Result<Vec<Cash>> {
+        self.ensure_writable("mark_overdue_installments")?;
+        self.authorize(Capability::EditCash)?;
+        let mut db = self
+            .database
+            .write()
+            .map_err(|e| Error::Poison(e.to_string()))?;
+        let now = self.clock.now();
+        let before: Vec<Cash> = db
+            .cash
+            .get_overdue_installments_with_grace(now, self.grace_days)
+            .into_iter()
+            .cloned()
+            .collect();
+        let marked = db
+            .cash
+            .mark_overdue_installments_with_grace(now, self.grace_days);
+        let records: Vec<Cash> = marked
+            .iter()
+            .filter_map(|uid| db.cash.get(uid).cloned())
+            .collect();
+        self.push_journal(&mut db, 
+            before
+                .iter()
+                .map(|c| JournalEntry::Cash(c.uid, Some(c.clone())))
+                .collect(),
+        )?;
+        drop(db);
+
+        for (old, new) in before.iter().zip(&records) {
+            let changes = diff_fields(old, new)?;
+            self.record_audit(AuditEntity::Cash, new.uid, AuditAction::Update, changes)?;
+        }
+        self.auto_save_cash_batch(&marked)?;
+        for &uid in &marked {
+            self.events.emit(&Event::InstallmentOverdue { uid });
+        }
+        Ok(records)
+    }
+
+    /// 将分期付款标记为已付款，记录付款时间和付款方式
+    ///
+    /// 启用 [`QmxManager::with_auto_next_installment`] 且该期是计划中已生成的最后一期时，
+    /// 按计划的付款频率生成下一期并返回其 UID，计划的各期都已生成时返回 `None`。
+    /// 记录不是分期付款、已付款或已取消时返回 [`Error::State`]。
+    pub fn mark_installment_paid(
+        &self,
+        cash_uid: u64,
+        paid_at: DateTime<Utc>,
+        payment_method: PaymentMethod,
+    ) -> Result<Option<u64>> {
+        self.ensure_writable("mark_installment_paid")?;
+        self.authorize(Capability::EditCash)?;
+        let mut db = self
+            .database
+            .write()
+            .map_err(|e| Error::Poison(e.to_string()))?;
+        let before = db
+            .cash
+            .get(&cash_uid)
+            .cloned()
+            .ok_or_else(|| Error::NotFound(format!("现金记录不存在: {}", cash_uid)))?;
+        let installment = before
+            .installment
+            .as_ref()
+            .ok_or_else(|| Error::State(format!("现金记录 {} 不是分期付款", cash_uid)))?;
+        match installment.status {
+            InstallmentStatus::Paid => {
+                return Err(Error::State(format!("分期付款 {} 已付款", cash_uid)));
+            }
+            InstallmentStatus::Cancelled => {
+                return Err(Error::State(format!("分期付款 {} 已取消", cash_uid)));
+            }
+            InstallmentStatus::Pending | InstallmentStatus::Overdue => {}
+        }
+        let plan_id = installment.plan_id;
+        let generate_next = self.auto_next_installment
+            && db.cash.plans.get(&plan_id).is_some_and(|plan| {
+                installment.current_installment < plan.total_installments
+            })
+            && db
+                .cash
+                .get_installments_by_plan(plan_id)
+                .iter()
+                .filter_map(|c| c.installment.as_ref())
+                .all(|i| i.current_installment <= installment.current_installment);
+
+        let mut paid = before.clone();
+        paid.set_installment_status(InstallmentStatus::Paid);
+        if let Some(installment) = &mut paid.installment {
+            installment.paid_at = Some(paid_at);
+        }
+        paid.payment_method = Some(payment_method);
+        let changes = diff_fields(&before, &paid)?;
+
+        let next = if generate_next {
+            let due_date = db.cash.next_installment_due(plan_id)?;
+            let mut next = db.cash.build_next_installment(plan_id, due_date)?;
+            if let Some(ids) = &self.ids {
+                next.uid = ids.next_cash_uid();
+            }
+            next.created_at = self.clock.now();
+            next.branch_id = before.branch_id.clone();
+            Some(next)
+        } else {
+            None
+        };
+        let next_uid = next.as_ref().map(|c| c.uid);
+        let next_snapshot = next
+            .as_ref()
+            .map(|c| snapshot_fields(c, AuditAction::Create))
+            .transpose()?;
+
+        db.cash.insert(paid);
+        let mut journal = vec![JournalEntry::Cash(cash_uid, Some(before))];
+        if let Some(next) = next {
+            journal.push(JournalEntry::Cash(next.uid, None));
+            db.cash.insert(next);
+        }
+        self.push_journal(&mut db, journal)?;
+        drop(db);
+
+        self.record_audit(AuditEntity::Cash, cash_uid, AuditAction::Update, changes)?;
+        if let (Some(uid), Some(snapshot)) = (next_uid, next_snapshot) {
+            self.record_audit(AuditEntity::Cash, uid, AuditAction::Create, snapshot)?;
+        }
+        let saved: Vec<u64> = std::iter::once(cash_uid).chain(next_uid).collect();
+        self.auto_save_cash_batch(&saved)?;
+        self.events.emit(&Event::InstallmentPaid { uid: cash_uid });
+        if let Some(uid) = next_uid {
+            self.events.emit(&Event::CashRecorded { uid });
+        }
+        info!(
+            "分期付款已付款，UID: {}，计划ID: {}，下一期: {:?}",
+            cash_uid, plan_id, next_uid
+        );
+        Ok(next_uid)
+    }
+
+    /// 获取学生的所有现金记录
+    pub fn get_student_cash(&self, student_id: u64) -> Result<Vec<Cash>> {
+        let db = self
+            .database
+            .read()
+            .map_err(|e| Error::Poison(e.to_string()))?;
+        Ok(db
+            .cash
+            .get_by_student(student_id)
+            .into_iter()
+            .cloned()
+            .collect())
+    }
+}
+
+// ============================================================================
+// 储值余额API
+// ============================================================================
+
+impl QmxManager {
+    /// 为学生充值储值余额，返回充值记录的 UID
+    ///
+    /// 充值记录是一笔普通收入，同时计入学生余额。等同于
+    /// `record_cash(CashBuilder::new(amount).student_id(uid).top_up())`。
+    pub fn top_up_balance(
+        &self,
+        uid: u64,
+        amount: impl Into<Money>,
+        method: PaymentMethod,
+    ) -> Result<u64> {
+        self.record_cash(
+            CashBuilder::new(amount)
+                .student_id(uid)
+                .note("储值充值")
+                .payment_method(method)
+                .top_up(),
+        )
+    }
+
+    /// 从学生余额扣费，返回扣费记录的 UID
+    ///
+    /// 用于上课扣费等场景。扣费记录的金额为 0，不计入收入和支出，只减少余额。
+    /// 余额不足时允许扣成负数，欠费学生见 [`QmxManager::get_negative_balances`]。
+    pub fn deduct_balance(
+        &self,
+        uid: u64,
+        amount: impl Into<Money>,
+        note: impl Into<String>,
+    ) -> Result<u64> {
+        self.debit_balance(uid, amount.into(), note.into(), None)
+    }
+
+    /// 用学生余额购买价目表中的商品，返回扣费记录的 UID
+    ///
+    /// 按商品价格扣费，课时和会员期的变化与 [`QmxManager::sell`] 相同。
+    pub fn sell_from_balance(&self, uid: u64, item_id: u64) -> Result<u64> {
+        let item = self
+            .get_catalog_item(item_id)?
+            .ok_or_else(|| Error::NotFound(format!("商品不存在: {}", item_id)))?;
+        self.debit_balance(uid, item.price, item.name, Some(item_id))
+    }
+
+    fn debit_balance(
+        &self,
+        uid: u64,
+        amount: Money,
+        note: String,
+        sale_item: Option<u64>,
+    ) -> Result<u64> {
+        self.ensure_writable("deduct_balance")?;
+        self.authorize(Capability::RecordCash)?;
+        if sale_item.is_some() {
+            self.authorize(Capability::ManageStudents)?;
+        }
+        if !amount.is_positive() {
+            return Err(Error::InvalidInput(format!("扣费金额必须为正数: {}", amount)));
+        }
+        Limits::check_len("note", &note, self.limits.max_note_len)?;
+
+        let mut db = self
+            .database
+            .write()
+            .map_err(|e| Error::Poison(e.to_string()))?;
+        check_student_exists(&db.student, Some(uid))?;
+        let mut cash = match self.ids.as_deref() {
+            Some(ids) => Cash::new_with_uid(ids.next_cash_uid(), Some(uid)),
+            None => Cash::new(Some(uid)),
+        };
+        cash.set_cash(Money::zero(amount.currency));
+        cash.set_note(Some(note));
+        cash.wallet = Some(-amount);
+        cash.created_at = self.clock.now();
+        inherit_branch(&mut cash, &db.student);
+        check_wallet_currency(&db.cash, &cash)?;
+        let credited = match sale_item {
+            Some(item_id) => self.credit_sale(&mut db.student, &mut cash, item_id)?,
+            None => None,
+        };
+        let cash_uid = cash.uid;
+        let changes = snapshot_fields(&cash, AuditAction::Create)?;
+        db.cash.insert(cash);
+        let balance = db.cash.wallet_balance(uid);
+        let mut entries = vec![JournalEntry::Cash(cash_uid, None)];
+        if let Some(credit) = &credited {
+            entries.push(JournalEntry::Student(uid, Some(credit.before.clone())));
+        }
+        self.push_journal(&mut db, entries)?;
+        drop(db);
+
+        self.record_audit(AuditEntity::Cash, cash_uid, AuditAction::Create, changes)?;
+        if let Some(credit) = &credited {
+            self.record_audit(
+                AuditEntity::Student,
+                uid,
+                AuditAction::Update,
+                credit.changes.clone(),
+            )?;
+            self.auto_save_student(uid)?;
+        }
+        self.auto_save_cash(cash_uid)?;
+        self.events.emit(&Event::CashRecorded { uid: cash_uid });
+        if let Some(credit) = credited {
+            self.events.emit(&Event::StudentUpdated {
+                uid,
+                changes: credit.changes,
+            });
+        }
+        if balance.is_negative() {
+            warn!("学生 {} 扣费 {} 后余额为 {}", uid, amount, balance);
+        } else {
+            info!("学生 {} 扣费 {}，余额 {}", uid, amount, balance);
+        }
+        Ok(cash_uid)
+    }
+
+    /// 学生的储值余额
+    pub fn get_student_balance(&self, uid: u64) -> Result<Money> {
+        let db = self
+            .database
+            .read()
+            .map_err(|e| Error::Poison(e.to_string()))?;
+        check_student_exists(&db.student, Some(uid))?;
+        Ok(db.cash.wallet_balance(uid))
+    }
+
+    /// 余额为负（欠费）的学生，按欠费金额从多到少排列
+    pub fn get_negative_balances(&self) -> Result<Vec<StudentBalance>> {
+        let db = self
+            .database
+            .read()
+            .map_err(|e| Error::Poison(e.to_string()))?;
+        let mut debtors: Vec<StudentBalance> = db
+            .cash
+            .wallet_balances()
+            .into_iter()
+            .filter(|(_, balance)| balance.is_negative())
+            .filter_map(|(uid, balance)| {
+                db.student.get(&uid).map(|student| StudentBalance {
+                    uid,
+                    name: student.name().unwrap_or_default().to_string(),
+                    balance,
+                })
+            })
+            .collect();
+        debtors.sort_by_key(|debtor| {
+            (
+                debtor.balance.currency,
+                debtor.balance.amount_minor,
+                debtor.uid,
+            )
+        });
+        Ok(debtors)
+    }
+}
+
+// ============================================================================
+// 统计分析API
+// ============================================================================
+
+impl QmxManager {
+    /// 获取仪表板统计信息
+    pub fn get_dashboard_stats(&self) -> Result<DashboardStats> {
+        let db = self
+            .database
+            .read()
+            .map_err(|e| Error::Poison(e.to_string()))?;
+        get_dashboard_stats_at(&db.student, &db.cash, self.clock.now())
+    }
+
+    /// 获取一个校区的仪表板统计数据，详见 [`get_branch_dashboard_stats_at`]
+    pub fn get_branch_dashboard_stats(&self, branch_id: &str) -> Result<DashboardStats> {
+        let db = self
+            .database
+            .read()
+            .map_err(|e| Error::Poison(e.to_string()))?;
+        get_branch_dashboard_stats_at(&db.student, &db.cash, branch_id, self.clock.now())
+    }
+
+    /// 获取按科目和按班级分组的统计信息，详见 [`get_breakdown_stats`]
+    pub fn get_breakdown_stats(&self) -> Result<BreakdownStats> {
+        let db = self
+            .database
+            .read()
+            .map_err(|e| Error::Poison(e.to_string()))?;
+        get_breakdown_stats(&db.student, &db.cash)
+    }
+
+    /// 获取试课学生转为月卡、年卡的转化漏斗，详见 [`get_conversion_funnel`]
+    pub fn get_conversion_funnel(&self) -> Result<ConversionFunnel> {
+        let audit = self
+            .audit
+            .read()
+            .map_err(|e| Error::Poison(e.to_string()))?;
+        get_conversion_funnel(&audit)
+    }
+
+    /// 预测从本月起 `months_ahead` 个月的收入，详见 [`forecast_revenue`]
+    pub fn forecast_revenue(&self, months_ahead: u32) -> Result<Vec<MonthlyForecast>> {
+        let db = self
+            .database
+            .read()
+            .map_err(|e| Error::Poison(e.to_string()))?;
+        forecast_revenue(&db.cash, months_ahead, self.clock.now())
+    }
+
+    /// 获取每名学生的欠款（未付分期和储值欠费），按欠款从多到少排列，详见 [`get_outstanding_balances`]
+    pub fn get_outstanding_balances(&self) -> Result<Vec<OutstandingBalance>> {
+        let db = self
+            .database
+            .read()
+            .map_err(|e| Error::Poison(e.to_string()))?;
+        get_outstanding_balances(&db.cash)
+    }
+
+    /// 获取学生统计信息
+    pub fn get_student_stats(&self, uid: u64) -> Result<StudentStats> {
+        let scoring = self.scoring_configs()?;
+        let db = self
+            .database
+            .read()
+            .map_err(|e| Error::Poison(e.to_string()))?;
+        StudentStats::calculate(&db.student, &db.cash, uid, &scoring, self.clock.now())
+    }
+
+    /// 获取学生最近 `last_n` 次成绩的趋势，详见 [`ScoreTrend::calculate`]
+    ///
+    /// 学生没有成绩时返回 `None`。
+    pub fn get_score_trend(
+        &self,
+        uid: u64,
+        last_n: usize,
+        window: usize,
+    ) -> Result<Option<ScoreTrend>> {
+        let db = self
+            .database
+            .read()
+            .map_err(|e| Error::Poison(e.to_string()))?;
+        let student = db
+            .student
+            .get(&uid)
+            .ok_or_else(|| Error::NotFound(format!("学生不存在: {}", uid)))?;
+        ScoreTrend::calculate(student.rings(), last_n, window)
+    }
+
+    /// 获取某科目学生按平均成绩从高到低的排名
+    ///
+    /// 没有成绩的学生不参与排名。平均成绩相同时按 UID 排列。
+    pub fn get_student_ranking(&self, subject: Subject) -> Result<Vec<StudentRanking>> {
+        let db = self
+            .database
+            .read()
+            .map_err(|e| Error::Poison(e.to_string()))?;
+        let mut scored: Vec<(u64, String, f64)> = db
+            .student
+            .iter()
+            .filter(|(_, student)| *student.subject() == subject && !student.rings().is_empty())
+            .map(|(&uid, student)| {
+                let rings = student.rings();
+                let average = rings.iter().sum::<f64>() / rings.len() as f64;
+                (uid, student.name().unwrap_or_default().to_string(), average)
+            })
+            .collect();
+        drop(db);
+        scored.sort_by(|a, b| b.2.total_cmp(&a.2).then(a.0.cmp(&b.0)));
+
+        let mut rankings: Vec<StudentRanking> = Vec::with_capacity(scored.len());
+        for (i, (uid, name, average_score)) in scored.into_iter().enumerate() {
+            let rank = match rankings.last() {
+                Some(previous) if previous.average_score == average_score => previous.rank,
+                _ => i + 1,
+            };
+            rankings.push(StudentRanking {
+                uid,
+                name,
+                average_score,
+                rank,
+                percentile: 100.0,
+            });
+        }
+
+        let total = rankings.len();
+        if total > 1 {
+            for i in 0..total {
+                let average = rankings[i].average_score;
+                let below = total - rankings.partition_point(|r| r.average_score >= average);
+                rankings[i].percentile = below as f64 * 100.0 / (total - 1) as f64;
+            }
+        }
+        Ok(rankings)
+    }
+
+    /// 获取财务统计信息
+    pub fn get_financial_stats(&self, period: TimePeriod) -> Result<FinancialStats> {
+        let db = self
+            .database
+            .read()
+            .map_err(|e| Error::Poison(e.to_string()))?;
+        FinancialStats::calculate(&db.cash, period, self.clock.now())
+    }
+}
+
+// ============================================================================
+// 对账单API
+// ============================================================================
+
+impl QmxManager {
+    /// 生成学生在指定期间的对账单
+    ///
+    /// 包含期间内的收付款、储值扣费、课时和会员期变化，并附上每条记录之后的储值余额，
+    /// 可用 [`StudentStatement::render_text`] 或 [`StudentStatement::render_html`] 渲染。
+    pub fn get_student_statement(&self, uid: u64, period: TimePeriod) -> Result<StudentStatement> {
+        let (start, end) = period.bounds(self.clock.now(), &Utc)?;
+        let db = self
+            .database
+            .read()
+            .map_err(|e| Error::Poison(e.to_string()))?;
+        let student = db
+            .student
+            .get(&uid)
+            .ok_or_else(|| Error::NotFound(format!("学生不存在: {}", uid)))?;
+        let audit = self
+            .audit
+            .read()
+            .map_err(|e| Error::Poison(e.to_string()))?;
+        Ok(StudentStatement::build(
+            student,
+            &db.cash,
+            audit.entries_for(uid),
+            start,
+            end,
+        ))
+    }
+}
+
+// ============================================================================
+// 审计日志API
+// ============================================================================
+
+impl QmxManager {
+    /// 获取学生或现金记录的全部修改记录，按发生顺序排列
+    pub fn get_audit_log(&self, entity_uid: u64) -> Result<Vec<AuditEntry>> {
+        let audit = self
+            .audit
+            .read()
+            .map_err(|e| Error::Poison(e.to_string()))?;
+        Ok(audit.entries_for(entity_uid).into_iter().cloned().collect())
+    }
+}
+
+// ============================================================================
+// 附件API
+// ============================================================================
+
+impl QmxManager {
+    /// 把 `source` 文件复制到学生的附件目录，返回附件序号
+    ///
+    /// 附件文件和索引在调用时立即写入磁盘，不受自动保存设置影响。
+    /// 删除学生不会删除其附件，附件也不参与撤销。
+    pub fn attach_file(
+        &self,
+        student_uid: u64,
+        source: impl AsRef<std::path::Path>,
+    ) -> Result<u64> {
+        self.ensure_writable("attach_file")?;
+        self.authorize(Capability::ManageAttachments)?;
+        let source = source.as_ref();
+        if self.get_student(student_uid)?.is_none() {
+            return Err(Error::NotFound(format!("学生不存在: {}", student_uid)));
+        }
+        let metadata = std::fs::metadata(source)?;
+        if !metadata.is_file() {
+            return Err(Error::InvalidInput(format!(
+                "附件不是文件: {}",
+                source.display()
+            )));
+        }
+        self.limits.check_attachment_size(metadata.len())?;
+        let file_name = source
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .ok_or_else(|| Error::InvalidInput(format!("无效的附件路径: {}", source.display())))?;
+        Limits::check_len("attachment.file_name", &file_name, self.limits.max_name_len)?;
+
+        let mut attachments = self
+            .attachments
+            .write()
+            .map_err(|e| Error::Poison(e.to_string()))?;
+        let uid = attachments.next_uid();
+        let attachment = Attachment {
+            uid,
+            student_uid,
+            stored_name: format!("{}_{}", uid, file_name),
+            file_name,
+            size: metadata.len(),
+            created_at: self.clock.now(),
+        };
+        let target = attachment.path_in(&self.attachments_dir);
+        if let Some(parent) = target.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::copy(source, &target)?;
+
+        attachments.insert(attachment);
+        if let Err(e) = attachments.save_to_with(&self.attachment_index_path(), &self.codec) {
+            // 索引写入失败时撤回本次添加，避免留下索引中没有的文件
+            attachments.remove(&uid);
+            let _ = std::fs::remove_file(&target);
+            return Err(e);
+        }
+        info!("学生 {} 添加附件 #{}", student_uid, uid);
+        Ok(uid)
+    }
+
+    /// 获取学生的全部附件，按添加顺序排列
+    pub fn list_attachments(&self, student_uid: u64) -> Result<Vec<Attachment>> {
+        let attachments = self
+            .attachments
+            .read()
+            .map_err(|e| Error::Poison(e.to_string()))?;
+        Ok(attachments
+            .for_student(student_uid)
+            .into_iter()
+            .cloned()
+            .collect())
+    }
+
+    /// 获取附件文件的完整路径
+    pub fn attachment_path(&self, uid: u64) -> Result<Option<std::path::PathBuf>> {
+        let attachments = self
+            .attachments
+            .read()
+            .map_err(|e| Error::Poison(e.to_string()))?;
+        Ok(attachments
+            .get(&uid)
+            .map(|attachment| attachment.path_in(&self.attachments_dir)))
+    }
+
+    /// 删除附件及其文件，附件不存在时返回 `false`
+    pub fn remove_attachment(&self, uid: u64) -> Result<bool> {
+        self.ensure_writable("remove_attachment")?;
+        self.authorize(Capability::ManageAttachments)?;
+        let mut attachments = self
+            .attachments
+            .write()
+            .map_err(|e| Error::Poison(e.to_string()))?;
+        let removed = self.remove_attachment_file(&mut attachments, uid)?;
+        if removed {
+            attachments.save_to_with(&self.attachment_index_path(), &self.codec)?;
+        }
+        Ok(removed)
+    }
+
+    /// 从索引中移除附件并删除文件，文件删除失败时附件保留在索引中
+    fn remove_attachment_file(&self, attachments: &mut AttachmentDatabase, uid: u64) -> Result<bool> {
+        let Some(attachment) = attachments.remove(&uid) else {
+            return Ok(false);
+        };
+        match std::fs::remove_file(attachment.path_in(&self.attachments_dir)) {
+            Ok(()) => {}
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                warn!("附件 #{} 的文件已不存在", uid);
+            }
+            Err(e) => {
+                attachments.insert(attachment);
+                return Err(e.into());
+            }
+        }
+        info!("删除学生 {} 的附件 #{}", attachment.student_uid, uid);
+        Ok(true)
+    }
+
+    fn attachment_index_path(&self) -> String {
+        std::path::Path::new(&self.attachments_dir)
+            .join(ATTACHMENT_INDEX_FILE)
+            .to_string_lossy()
+            .into_owned()
+    }
+}
+
+// ============================================================================
+// 个人数据API
+// ============================================================================
+
+impl QmxManager {
+    /// 导出一个学生的全部个人数据，包括档案、现金记录、报名的课程、附件元数据和审计记录
+    ///
+    /// 学生不存在时返回 [`Error::NotFound`]，使用 [`StudentDataExport::to_json`] 得到 JSON。
+    pub fn export_student_data(&self, uid: u64) -> Result<StudentDataExport> {
+        let db = self
+            .database
+            .read()
+            .map_err(|e| Error::Poison(e.to_string()))?;
+        let student = db
+            .student
+            .get(&uid)
+            .cloned()
+            .ok_or_else(|| Error::NotFound(format!("学生不存在: {}", uid)))?;
+        let cash: Vec<Cash> = db
+            .cash
+            .get_by_student(uid)
+            .into_iter()
+            .cloned()
+            .collect();
+        let audit = self
+            .audit
+            .read()
+            .map_err(|e| Error::Poison(e.to_string()))?;
+        let cash_uids = student_cash_uids(&db.cash, &audit, uid);
+        let audit: Vec<AuditEntry> = audit
+            .iter()
+            .filter(|(_, entry)| concerns_student(entry, uid, &cash_uids))
+            .map(|(_, entry)| entry.clone())
+            .collect();
+        drop(db);
+
+        let sessions = self
+            .sessions
+            .read()
+            .map_err(|e| Error::Poison(e.to_string()))?
+            .iter()
+            .filter(|(_, session)| session.is_enrolled(uid))
+            .map(|(_, session)| session.clone())
+            .collect();
+        let attachments = self.list_attachments(uid)?;
+        info!("导出学生 {} 的个人数据", uid);
+        Ok(StudentDataExport {
+            exported_at: self.clock.now(),
+            student,
+            cash,
+            sessions,
+            attachments,
+            audit,
+        })
+    }
+
+    /// 清除一个学生的个人信息，学生不存在时返回 `false`
+    ///
+    /// 学生和现金记录本身保留，只清除 [`STUDENT_PERSONAL_FIELDS`] 和现金记录中的
+    /// [`CASH_PERSONAL_FIELDS`]，收支汇总和统计结果不变。审计记录中这些字段的历史值
+    /// 替换为 [`crate::audit::REDACTED`]，学生的附件连同文件一起删除。
+    /// 为避免通过撤销恢复个人信息，清除后撤销历史会被清空。已有的备份文件不受影响。
+    pub fn erase_student_data(&self, uid: u64) -> Result<bool> {
+        self.ensure_writable("erase_student_data")?;
+        self.authorize(Capability::DeleteStudents)?;
+        let mut db = self
+            .database
+            .write()
+            .map_err(|e| Error::Poison(e.to_string()))?;
+        let Some(before) = db.student.get(&uid).cloned() else {
+            return Ok(false);
+        };
+        let mut student = before.clone();
+        student.anonymize();
+        student.set_updated_at(Some(self.clock.now()));
+        let student_changes = diff_fields(&before, &student)?;
+        db.student.insert(student);
+
+        let mut befores = vec![JournalEntry::Student(uid, Some(before))];
+        let mut cash_changes = Vec::new();
+        for cash in db
+            .cash
+            .cash_data
+            .values_mut()
+            .filter(|c| c.student_id == Some(uid))
+        {
+            let before = cash.clone();
+            cash.note = None;
+            cash.custom_fields.clear();
+            let changes = diff_fields(&before, &*cash)?;
+            if !changes.is_empty() {
+                cash_changes.push((cash.uid, changes));
+                befores.push(JournalEntry::Cash(cash.uid, Some(before)));
+            }
+        }
+        self.write_wal(&mut db, &befores)?;
+        self.journal
+            .lock()
+            .map_err(|e| Error::Poison(e.to_string()))?
+            .clear();
+        self.dirty.student.store(true, Ordering::SeqCst);
+        if !cash_changes.is_empty() {
+            self.dirty.cash.store(true, Ordering::SeqCst);
+        }
+
+        {
+            let mut audit = self
+                .audit
+                .write()
+                .map_err(|e| Error::Poison(e.to_string()))?;
+            let cash_uids = student_cash_uids(&db.cash, &audit, uid);
+            audit.redact(
+                |entry| entry.entity == AuditEntity::Student && entry.entity_uid == uid,
+                STUDENT_PERSONAL_FIELDS,
+            );
+            audit.redact(
+                |entry| {
+                    entry.entity == AuditEntity::Cash && concerns_student(entry, uid, &cash_uids)
+                },
+                CASH_PERSONAL_FIELDS,
+            );
+            // 日志中不再保留清除前的内容
+            if let Some(wal) = &self.wal {
+                wal.rewrite(&db, &audit)?;
+            }
+        }
+        drop(db);
+
+        let mut student_changes = student_changes;
+        redact_changes(&mut student_changes, STUDENT_PERSONAL_FIELDS);
+        self.record_audit(
+            AuditEntity::Student,
+            uid,
+            AuditAction::Update,
+            student_changes.clone(),
+        )?;
+        for (cash_uid, changes) in &mut cash_changes {
+            redact_changes(changes, CASH_PERSONAL_FIELDS);
+            self.record_audit(AuditEntity::Cash, *cash_uid, AuditAction::Update, changes.clone())?;
+        }
+
+        let removed_attachments = {
+            let mut attachments = self
+                .attachments
+                .write()
+                .map_err(|e| Error::Poison(e.to_string()))?;
+            let uids: Vec<u64> = attachments
+                .for_student(uid)
+                .into_iter()
+                .map(|attachment| attachment.uid)
+                .collect();
+            for attachment_uid in &uids {
+                self.remove_attachment_file(&mut attachments, *attachment_uid)?;
+            }
+            if !uids.is_empty() {
+                attachments.save_to_with(&self.attachment_index_path(), &self.codec)?;
+            }
+            uids.len()
+        };
+
+        let cash_uids: Vec<u64> = cash_changes.iter().map(|(cash_uid, _)| *cash_uid).collect();
+        if self.auto_save == AutoSave::Immediate && self.backend.is_none() {
+            self.save()?;
+        } else {
+            self.auto_save_student(uid)?;
+            self.auto_save_cash_batch(&cash_uids)?;
+        }
+        self.events.emit(&Event::StudentUpdated {
+            uid,
+            changes: student_changes,
+        });
+        for (cash_uid, changes) in cash_changes {
+            self.events.emit(&Event::CashUpdated {
+                uid: cash_uid,
+                changes,
+            });
+        }
+        info!(
+            "清除学生 {} 的个人信息，涉及 {} 条现金记录、{} 个附件",
+            uid,
+            cash_uids.len(),
+            removed_attachments
+        );
+        Ok(true)
+    }
+}
+
+/// 把一条记录恢复为操作日志中保存的状态
+fn restore_entry(db: &mut DbContainer, entry: JournalEntry) {
+    match entry {
+        JournalEntry::Student(uid, before) => match before {
+            Some(student) => db.student.insert(student),
+            None => {
+                db.student.remove(&uid);
+            }
+        },
+        JournalEntry::Cash(uid, before) => match before {
+            Some(cash) => db.cash.insert(cash),
+            None => {
+                db.cash.remove(&uid);
+            }
+        },
+        JournalEntry::Plan(plan_id, before) => match before {
+            Some(plan) => db.cash.plans.insert(plan),
+            None => {
+                db.cash.plans.remove(&plan_id);
+            }
+        },
+    }
+}
+
+/// 把全局 UID 计数器推进到管理器自己的数据之后，避免新记录与已加载的记录冲突
+///
+/// 反序列化不修改计数器，管理器加载、恢复或合并自己的数据时调用。
+fn sync_uid_counters(db: &DbContainer) {
+    db.student.sync_uid_counter();
+    db.cash.sync_uid_counter();
+    crate::cash::sync_plan_id_counter(&db.cash);
+}
+
+/// 加载附件目录中的索引，目录或索引不存在时返回空索引
+fn load_attachment_index(dir: &str, codec: &FileCodec) -> Result<AttachmentDatabase> {
+    let path = std::path::Path::new(dir).join(ATTACHMENT_INDEX_FILE);
+    AttachmentDatabase::load_or_new_with(&path.to_string_lossy(), codec)
+}
+
+// ============================================================================
+// 校区API
+// ============================================================================
+
+impl QmxManager {
+    /// 列出所有校区，按标识排序
+    ///
+    /// 校区不需要单独登记，凡是有学生或现金记录使用过的校区标识都会列出。
+    pub fn list_branches(&self) -> Result<Vec<String>> {
+        let db = self
+            .database
+            .read()
+            .map_err(|e| Error::Poison(e.to_string()))?;
+        let branches: std::collections::BTreeSet<&str> = db
+            .student
+            .iter()
+            .filter_map(|(_, s)| s.branch_id())
+            .chain(db.cash.iter().filter_map(|(_, c)| c.branch_id.as_deref()))
+            .collect();
+        Ok(branches.into_iter().map(str::to_string).collect())
+    }
+
+    /// 把学生转到另一个校区，返回发生变化的字段
+    ///
+    /// 只修改学生本身，已有的现金记录仍属于原校区，之后记录的现金默认属于新校区。
+    pub fn move_student_to_branch(
+        &self,
+        uid: u64,
+        branch_id: impl Into<String>,
+    ) -> Result<Vec<FieldChange>> {
+        let branch_id = branch_id.into();
+        info!("学生 {} 转到校区 {}", uid, branch_id);
+        self.update_student(uid, StudentUpdater::new().branch(Some(branch_id)))
+    }
+}
+
+// ============================================================================
+// 教练API
+// ============================================================================
+
+impl QmxManager {
+    /// 添加教练，返回教练序号
+    ///
+    /// 教练数据库在调用时立即写入磁盘，不受自动保存设置影响。
+    pub fn create_coach(&self, builder: CoachBuilder) -> Result<u64> {
+        self.ensure_writable("create_coach")?;
+        self.authorize(Capability::ManageCoaches)?;
+        builder.validate(&self.limits)?;
+        let mut coaches = self
+            .coaches
+            .write()
+            .map_err(|e| Error::Poison(e.to_string()))?;
+        let uid = coaches.next_uid();
+        coaches.insert(builder.build(uid));
+        if let Err(e) = self.save_coaches(&coaches, &[uid]) {
+            coaches.remove(&uid);
+            return Err(e);
+        }
+        info!("添加教练成功，序号: {}", uid);
+        Ok(uid)
+    }
+
+    pub fn get_coach(&self, uid: u64) -> Result<Option<Coach>> {
+        let coaches = self
+            .coaches
+            .read()
+            .map_err(|e| Error::Poison(e.to_string()))?;
+        Ok(coaches.get(&uid).cloned())
+    }
+
+    /// 获取全部教练，按序号排列
+    pub fn list_coaches(&self) -> Result<Vec<Coach>> {
+        let coaches = self
+            .coaches
+            .read()
+            .map_err(|e| Error::Poison(e.to_string()))?;
+        Ok(coaches.iter().map(|(_, coach)| coach.clone()).collect())
+    }
+
+    /// 用构建器中的信息替换教练的姓名、电话和执教科目
+    pub fn update_coach(&self, uid: u64, builder: CoachBuilder) -> Result<()> {
+        self.ensure_writable("update_coach")?;
+        self.authorize(Capability::ManageCoaches)?;
+        builder.validate(&self.limits)?;
+        let mut coaches = self
+            .coaches
+            .write()
+            .map_err(|e| Error::Poison(e.to_string()))?;
+        let Some(before) = coaches.get(&uid).cloned() else {
+            return Err(Error::NotFound(format!("教练不存在: {}", uid)));
+        };
+        coaches.insert(builder.build(uid));
+        if let Err(e) = self.save_coaches(&coaches, &[uid]) {
+            coaches.insert(before);
+            return Err(e);
+        }
+        info!("更新教练成功，序号: {}", uid);
+        Ok(())
+    }
+
+    /// 删除教练，教练不存在时返回 `false`
+    ///
+    /// 仍有学生分配给该教练时拒绝删除，需要先通过 [`QmxManager::assign_coach`] 重新分配。
+    pub fn delete_coach(&self, uid: u64) -> Result<bool> {
+        self.ensure_writable("delete_coach")?;
+        self.authorize(Capability::ManageCoaches)?;
+        let assigned = self.search_students(StudentQuery::new().coach(uid))?.len();
+        if assigned > 0 {
+            return Err(Error::State(format!(
+                "教练 {} 仍有 {} 名学生，无法删除",
+                uid, assigned
+            )));
+        }
+        let mut coaches = self
+            .coaches
+            .write()
+            .map_err(|e| Error::Poison(e.to_string()))?;
+        let Some(coach) = coaches.remove(&uid) else {
+            return Ok(false);
+        };
+        if let Err(e) = self.save_coaches(&coaches, &[uid]) {
+            coaches.insert(coach);
+            return Err(e);
+        }
+        info!("删除教练成功，序号: {}", uid);
+        Ok(true)
+    }
+
+    /// 保存教练数据库（仅在设置了保存路径时）
+    ///
+    /// 保存前先把 `uids` 对应记录的当前内容写入预写日志。
+    fn save_coaches(&self, coaches: &CoachDatabase, uids: &[u64]) -> Result<()> {
+        let Some(path) = &self.coach_path else {
+            return Ok(());
+        };
+        for &uid in uids {
+            self.append_wal(WalRecord::Coach {
+                path: path.clone(),
+                uid,
+                record: coaches.get(&uid).cloned(),
+            })?;
+        }
+        coaches.save_to_with(path, &self.codec)
+    }
+
+    /// 为学生分配教练，`None` 表示取消分配
+    ///
+    /// 教练不存在时返回 [`Error::NotFound`]，其余行为与 [`QmxManager::update_student`] 相同。
+    pub fn assign_coach(&self, student_uid: u64, coach: Option<u64>) -> Result<Vec<FieldChange>> {
+        if let Some(coach_uid) = coach {
+            let coaches = self
+                .coaches
+                .read()
+                .map_err(|e| Error::Poison(e.to_string()))?;
+            if coaches.get(&coach_uid).is_none() {
+                return Err(Error::NotFound(format!("教练不存在: {}", coach_uid)));
+            }
+        }
+        self.update_student(student_uid, StudentUpdater::new().assigned_coach(coach))
+    }
+}
+
+// ============================================================================
+// 课程排期API
+// ============================================================================
+
+impl QmxManager {
+    /// 创建课程，返回课程序号
+    ///
+    /// 指定的教练必须存在。课程排期在调用时立即写入磁盘，不受自动保存设置影响。
+    pub fn create_session(&self, builder: SessionBuilder) -> Result<u64> {
+        self.ensure_writable("create_session")?;
+        self.authorize(Capability::ManageSessions)?;
+        builder.validate()?;
+        if let Some(coach_uid) = builder.coach_id
+            && self.get_coach(coach_uid)?.is_none()
+        {
+            return Err(Error::NotFound(format!("教练不存在: {}", coach_uid)));
+        }
+        let mut sessions = self
+            .sessions
+            .write()
+            .map_err(|e| Error::Poison(e.to_string()))?;
+        let uid = sessions.next_uid();
+        sessions.insert(builder.build(uid));
+        if let Err(e) = self.save_sessions(&sessions, &[uid]) {
+            sessions.remove(&uid);
+            return Err(e);
+        }
+        info!("创建课程成功，序号: {}", uid);
+        Ok(uid)
+    }
+
+    pub fn get_session(&self, uid: u64) -> Result<Option<Session>> {
+        let sessions = self
+            .sessions
+            .read()
+            .map_err(|e| Error::Poison(e.to_string()))?;
+        Ok(sessions.get(&uid).cloned())
+    }
+
+    /// 删除课程，课程不存在时返回 `false`
+    pub fn delete_session(&self, uid: u64) -> Result<bool> {
+        self.ensure_writable("delete_session")?;
+        self.authorize(Capability::ManageSessions)?;
+        let mut sessions = self
+            .sessions
+            .write()
+            .map_err(|e| Error::Poison(e.to_string()))?;
+        let Some(session) = sessions.remove(&uid) else {
+            return Ok(false);
+        };
+        if let Err(e) = self.save_sessions(&sessions, &[uid]) {
+            sessions.insert(session);
+            return Err(e);
+        }
+        info!("删除课程成功，序号: {}", uid);
+        Ok(true)
+    }
+
+    /// 保存课程排期（仅在设置了保存路径时）
+    ///
+    /// 保存前先把 `uids` 对应记录的当前内容写入预写日志。
+    fn save_sessions(&self, sessions: &SessionDatabase, uids: &[u64]) -> Result<()> {
+        let Some(path) = &self.session_path else {
+            return Ok(());
+        };
+        for &uid in uids {
+            self.append_wal(WalRecord::Session {
+                path: path.clone(),
+                uid,
+                record: sessions.get(&uid).cloned(),
+            })?;
+        }
+        sessions.save_to_with(path, &self.codec)
+    }
+
+    /// 学生报名课程
+    ///
+    /// 课程或学生不存在时返回 [`Error::NotFound`]，已报名或课程已满时返回 [`Error::State`]。
+    pub fn enroll(&self, session_uid: u64, student_uid: u64) -> Result<()> {
+        self.ensure_writable("enroll")?;
+        self.authorize(Capability::ManageSessions)?;
+        if self.get_student(student_uid)?.is_none() {
+            return Err(Error::NotFound(format!("学生不存在: {}", student_uid)));
+        }
+        self.modify_session(session_uid, |session| {
+            if session.is_enrolled(student_uid) {
+                return Err(Error::State(format!(
+                    "学生 {} 已报名课程 {}",
+                    student_uid, session_uid
+                )));
+            }
+            if session.is_full() {
+                return Err(Error::State(format!(
+                    "课程 {} 已满（容量 {}）",
+                    session_uid, session.capacity
+                )));
+            }
+            session.enrolled.push(student_uid);
+            Ok(())
+        })?;
+        info!("学生 {} 报名课程 {}", student_uid, session_uid);
+        Ok(())
+    }
+
+    /// 取消报名，学生未报名该课程时返回 `false`
+    pub fn unenroll(&self, session_uid: u64, student_uid: u64) -> Result<bool> {
+        self.ensure_writable("unenroll")?;
+        self.authorize(Capability::ManageSessions)?;
+        let removed = self.modify_session(session_uid, |session| {
+            let before = session.enrolled.len();
+            session.enrolled.retain(|&uid| uid != student_uid);
+            Ok(session.enrolled.len() != before)
+        })?;
+        if removed {
+            info!("学生 {} 取消报名课程 {}", student_uid, session_uid);
+        }
+        Ok(removed)
+    }
+
+    /// 今天（按注入的时钟，UTC 日期）开始的课程，按开始时间排列
+    pub fn todays_sessions(&self) -> Result<Vec<Session>> {
+        let sessions = self
+            .sessions
+            .read()
+            .map_err(|e| Error::Poison(e.to_string()))?;
+        Ok(sessions
+            .on_date(self.clock.now().date_naive())
+            .into_iter()
+            .cloned()
+            .collect())
+    }
+
+    /// 学生已报名且尚未开始的课程，按开始时间排列
+    pub fn upcoming_sessions(&self, student_uid: u64) -> Result<Vec<Session>> {
+        let sessions = self
+            .sessions
+            .read()
+            .map_err(|e| Error::Poison(e.to_string()))?;
+        Ok(sessions
+            .upcoming_for_student(student_uid, self.clock.now())
+            .into_iter()
+            .cloned()
+            .collect())
+    }
+
+    /// 修改一节课程并保存，`f` 返回错误或保存失败时课程保持原样
+    fn modify_session<T>(&self, uid: u64, f: impl FnOnce(&mut Session) -> Result<T>) -> Result<T> {
+        let mut sessions = self
+            .sessions
+            .write()
+            .map_err(|e| Error::Poison(e.to_string()))?;
+        let Some(before) = sessions.get(&uid).cloned() else {
+            return Err(Error::NotFound(format!("课程不存在: {}", uid)));
+        };
+        let mut session = before.clone();
+        let result = f(&mut session)?;
+        if session != before {
+            sessions.insert(session);
+            if let Err(e) = self.save_sessions(&sessions, &[uid]) {
+                sessions.insert(before);
+                return Err(e);
+            }
+        }
+        Ok(result)
+    }
+}
+
+// ============================================================================
+// 价目表API
+// ============================================================================
+
+impl QmxManager {
+    /// 获取全部商品，按序号排列
+    pub fn list_catalog(&self) -> Result<Vec<CatalogItem>> {
+        let catalog = self
+            .catalog
+            .read()
+            .map_err(|e| Error::Poison(e.to_string()))?;
+        Ok(catalog.iter().map(|(_, item)| item.clone()).collect())
+    }
+
+    pub fn get_catalog_item(&self, uid: u64) -> Result<Option<CatalogItem>> {
+        let catalog = self
+            .catalog
+            .read()
+            .map_err(|e| Error::Poison(e.to_string()))?;
+        Ok(catalog.get(&uid).cloned())
+    }
+
+    /// 添加商品，返回商品序号
+    ///
+    /// 价目表在调用时立即写入磁盘，不受自动保存设置影响。
+    pub fn add_catalog_item(&self, builder: CatalogItemBuilder) -> Result<u64> {
+        self.ensure_writable("add_catalog_item")?;
+        self.authorize(Capability::ManageCatalog)?;
+        builder.validate(&self.limits)?;
+        let mut catalog = self
+            .catalog
+            .write()
+            .map_err(|e| Error::Poison(e.to_string()))?;
+        let uid = catalog.next_uid();
+        catalog.insert(builder.build(uid));
+        if let Err(e) = self.save_catalog(&catalog, &[uid]) {
+            catalog.remove(&uid);
+            return Err(e);
+        }
+        info!("添加商品成功，序号: {}", uid);
+        Ok(uid)
+    }
+
+    /// 用构建器中的信息替换商品的名称、价格和效果，已售出的记录不受影响
+    pub fn update_catalog_item(&self, uid: u64, builder: CatalogItemBuilder) -> Result<()> {
+        self.ensure_writable("update_catalog_item")?;
+        self.authorize(Capability::ManageCatalog)?;
+        builder.validate(&self.limits)?;
+        let mut catalog = self
+            .catalog
+            .write()
+            .map_err(|e| Error::Poison(e.to_string()))?;
+        let Some(before) = catalog.get(&uid).cloned() else {
+            return Err(Error::NotFound(format!("商品不存在: {}", uid)));
+        };
+        catalog.insert(builder.build(uid));
+        if let Err(e) = self.save_catalog(&catalog, &[uid]) {
+            catalog.insert(before);
+            return Err(e);
+        }
+        info!("更新商品成功，序号: {}", uid);
+        Ok(())
+    }
+
+    /// 删除商品，商品不存在时返回 `false`
+    pub fn delete_catalog_item(&self, uid: u64) -> Result<bool> {
+        self.ensure_writable("delete_catalog_item")?;
+        self.authorize(Capability::ManageCatalog)?;
+        let mut catalog = self
+            .catalog
+            .write()
+            .map_err(|e| Error::Poison(e.to_string()))?;
+        let Some(item) = catalog.remove(&uid) else {
+            return Ok(false);
+        };
+        if let Err(e) = self.save_catalog(&catalog, &[uid]) {
+            catalog.insert(item);
+            return Err(e);
+        }
+        info!("删除商品成功，序号: {}", uid);
+        Ok(true)
+    }
+
+    /// 保存价目表（仅在设置了保存路径时）
+    ///
+    /// 保存前先把 `uids` 对应记录的当前内容写入预写日志。
+    fn save_catalog(&self, catalog: &CatalogDatabase, uids: &[u64]) -> Result<()> {
+        let Some(path) = &self.catalog_path else {
+            return Ok(());
+        };
+        for &uid in uids {
+            self.append_wal(WalRecord::CatalogItem {
+                path: path.clone(),
+                uid,
+                record: catalog.get(&uid).cloned(),
+            })?;
+        }
+        catalog.save_to_with(path, &self.codec)
+    }
+
+    /// 向学生售出商品，返回收款记录的 UID
+    ///
+    /// 按商品价格记录一笔带售卖标记的收款（备注为商品名称），效果与
+    /// [`CashBuilder::sale`] 相同：同时为学生增加课时或延长会员期，撤销时一起恢复，
+    /// 全额退款时自动撤回。需要记录现金和修改学生两项权限。
+    pub fn sell(&self, student_id: u64, item_id: u64) -> Result<u64> {
+        let item = self
+            .get_catalog_item(item_id)?
+            .ok_or_else(|| Error::NotFound(format!("商品不存在: {}", item_id)))?;
+        let uid = self.record_cash(
+            CashBuilder::new(item.price)
+                .student_id(student_id)
+                .note(item.name.clone())
+                .sale(item_id),
+        )?;
+        info!(
+            "向学生 {} 售出商品 {}，收款记录 UID: {}",
+            student_id, item.name, uid
+        );
+        Ok(uid)
+    }
+}
+
+// ============================================================================
+// 定期支出API
+// ============================================================================
+
+impl QmxManager {
+    /// 获取全部定期支出模板，按序号排列
+    pub fn list_recurring_expenses(&self) -> Result<Vec<RecurringExpense>> {
+        let recurring = self
+            .recurring
+            .read()
+            .map_err(|e| Error::Poison(e.to_string()))?;
+        Ok(recurring
+            .iter()
+            .map(|(_, expense)| expense.clone())
+            .collect())
+    }
+
+    /// 添加定期支出模板，返回模板序号
+    ///
+    /// 未指定生效时间时从当前时间生效。模板在调用时立即写入磁盘，不受自动保存设置影响。
+    pub fn add_recurring_expense(&self, builder: RecurringExpenseBuilder) -> Result<u64> {
+        self.ensure_writable("add_recurring_expense")?;
+        self.authorize(Capability::ManageRecurring)?;
+        builder.validate(&self.limits)?;
+        let mut recurring = self
+            .recurring
+            .write()
+            .map_err(|e| Error::Poison(e.to_string()))?;
+        let uid = recurring.next_uid();
+        let start = builder.start.unwrap_or_else(|| self.clock.now());
+        recurring.insert(builder.build(uid, start));
+        if let Err(e) = self.save_recurring(&recurring, &[uid]) {
+            recurring.remove(&uid);
+            return Err(e);
+        }
+        info!("添加定期支出成功，序号: {}", uid);
+        Ok(uid)
+    }
+
+    /// 用构建器中的信息替换模板，未指定生效时间时保留原生效时间；已生成的记录不受影响
+    pub fn update_recurring_expense(
+        &self,
+        uid: u64,
+        builder: RecurringExpenseBuilder,
+    ) -> Result<()> {
+        self.ensure_writable("update_recurring_expense")?;
+        self.authorize(Capability::ManageRecurring)?;
+        builder.validate(&self.limits)?;
+        let mut recurring = self
+            .recurring
+            .write()
+            .map_err(|e| Error::Poison(e.to_string()))?;
+        let Some(before) = recurring.get(&uid).cloned() else {
+            return Err(Error::NotFound(format!("定期支出不存在: {}", uid)));
+        };
+        let start = builder.start.unwrap_or(before.start);
+        recurring.insert(builder.build(uid, start));
+        if let Err(e) = self.save_recurring(&recurring, &[uid]) {
+            recurring.insert(before);
+            return Err(e);
+        }
+        info!("更新定期支出成功，序号: {}", uid);
+        Ok(())
+    }
+
+    /// 删除定期支出模板，之后不再生成记录，已生成的记录保留；模板不存在时返回 `false`
+    pub fn delete_recurring_expense(&self, uid: u64) -> Result<bool> {
+        self.ensure_writable("delete_recurring_expense")?;
+        self.authorize(Capability::ManageRecurring)?;
+        let mut recurring = self
+            .recurring
+            .write()
+            .map_err(|e| Error::Poison(e.to_string()))?;
+        let Some(expense) = recurring.remove(&uid) else {
+            return Ok(false);
+        };
+        if let Err(e) = self.save_recurring(&recurring, &[uid]) {
+            recurring.insert(expense);
+            return Err(e);
+        }
+        info!("删除定期支出成功，序号: {}", uid);
+        Ok(true)
+    }
+
+    /// 保存定期支出模板（仅在设置了保存路径时）
+    ///
+    /// 保存前先把 `uids` 对应记录的当前内容写入预写日志。
+    fn save_recurring(&self, recurring: &RecurringDatabase, uids: &[u64]) -> Result<()> {
+        let Some(path) = &self.recurring_path else {
+            return Ok(());
+        };
+        for &uid in uids {
+            self.append_wal(WalRecord::Recurring {
+                path: path.clone(),
+                uid,
+                record: recurring.get(&uid).cloned(),
+            })?;
+        }
+        recurring.save_to_with(path, &self.codec)
+    }
+
+    /// 为截至 `now` 已到付款日的每个模板月份生成一条支出记录，返回新记录的 UID
+    ///
+    /// 记录金额为模板金额的相反数，备注为模板名称，创建时间为付款日。之前漏处理的月份会一并补上，
+    /// 已生成过的月份跳过，因此可以反复调用（如每次启动时）。新记录作为一次操作写入，可整体撤销；
+    /// 撤销或删除生成的记录后再次调用会重新生成。
+    pub fn process_recurring_expenses(&self, now: DateTime<Utc>) -> Result<Vec<u64>> {
+        self.ensure_writable("process_recurring_expenses")?;
+        self.authorize(Capability::RecordCash)?;
+        let templates = self.list_recurring_expenses()?;
+        let generated: std::collections::HashSet<RecurringOccurrence> = {
+            let db = self
+                .database
+                .read()
+                .map_err(|e| Error::Poison(e.to_string()))?;
+            db.cash
+                .iter()
+                .filter_map(|(_, cash)| cash.recurring)
+                .collect()
+        };
+        let mut builders = Vec::new();
+        for template in &templates {
+            for (period, due) in template.due_periods(now) {
+                let occurrence = RecurringOccurrence {
+                    template_id: template.uid,
+                    period,
+                };
+                if generated.contains(&occurrence) {
+                    continue;
+                }
+                let mut builder = CashBuilder::new(-template.amount)
+                    .note(template.name.clone())
+                    .category(template.category.clone())
+                    .force();
+                builder.recurring = Some(occurrence);
+                builder.created_at = Some(due);
+                builders.push(builder);
+            }
+        }
+        if builders.is_empty() {
+            debug!("没有到期的定期支出");
+            return Ok(Vec::new());
+        }
+        // 并发调用时由 check_recurring_unique 保证不会重复生成
+        let uids = self.record_cash_batch(builders)?;
+        info!("生成定期支出记录 {} 条", uids.len());
+        Ok(uids)
+    }
+}
+
+// ============================================================================
+// 备份API
+// ============================================================================
+
+impl QmxManager {
+    /// 检查数据一致性，只报告问题，不做修改，详见 [`crate::integrity`]
+    ///
+    /// 使用自定义 ID 策略时学生和现金 UID 不由全局计数器分配，不检查这两个计数器。
+    pub fn verify_integrity(&self) -> Result<IntegrityReport> {
+        let db = self
+            .database
+            .read()
+            .map_err(|e| Error::Poison(e.to_string()))?;
+        let report = crate::integrity::check(&db, self.ids.is_none());
+        if report.is_ok() {
+            info!("数据一致性检查通过");
+        } else {
+            warn!("数据一致性检查发现 {} 个问题", report.issue_count());
+        }
+        Ok(report)
+    }
+
+    /// 立即备份当前数据，返回新备份的目录
+    ///
+    /// 备份的是内存中的数据（包括尚未保存的修改）和审计日志，完成后按保留策略清理旧备份。
+    pub fn backup_now(&self) -> Result<std::path::PathBuf> {
+        self.ensure_writable("backup_now")?;
+        self.authorize(Capability::Backup)?;
+        let path = crate::backup::create_backup_dir(&self.backup_dir, self.clock.now())?;
+        if let Err(e) = self.write_backup(&path) {
+            // 不留下不完整的备份
+            let _ = std::fs::remove_dir_all(&path);
+            return Err(e);
+        }
+        info!("数据已备份到 {}", path.display());
+        crate::backup::prune_backups(&self.backup_dir, &self.retention)?;
+        Ok(path)
+    }
+
+    fn write_backup(&self, path: &std::path::Path) -> Result<()> {
+        let file = |name: &str| path.join(name).to_string_lossy().into_owned();
+        {
+            let db = self
+                .database
+                .read()
+                .map_err(|e| Error::Poison(e.to_string()))?;
+            crate::common::save_atomic(
+                &db.student,
+                &file(BACKUP_STUDENT_FILE),
+                false,
+                &self.codec,
+            )?;
+            crate::common::save_atomic(&db.cash, &file(BACKUP_CASH_FILE), false, &self.codec)?;
+        }
+        self.audit
+            .read()
+            .map_err(|e| Error::Poison(e.to_string()))?
+            .save_to_with(&file(BACKUP_AUDIT_FILE), &self.codec)
+    }
 
-        Ok(())
+    /// 列出备份目录中的全部备份，按时间从新到旧排列
+    pub fn list_backups(&self) -> Result<Vec<BackupInfo>> {
+        crate::backup::list_backups(&self.backup_dir)
     }
 
-    /// 自动保存（如果启用）
-    fn auto_save_if_enabled(&self) -> Result<()> {
-        if self.auto_save {
-            self.save()?;
+    /// 从备份目录恢复学生和现金数据，并立即保存
+    ///
+    /// 恢复前会先备份当前数据，恢复错了可以再从那份备份恢复回来。
+    /// 审计日志不会被恢复，恢复前的修改记录仍然保留。
+    pub fn restore_from_backup(&self, path: impl AsRef<std::path::Path>) -> Result<()> {
+        self.ensure_writable("restore_from_backup")?;
+        self.authorize(Capability::Restore)?;
+        let path = path.as_ref();
+        crate::backup::check_backup(path)?;
+        let file = |name: &str| path.join(name).to_string_lossy().into_owned();
+        let restored = DbContainer::new(
+            StudentDatabase::read_from_with(&file(BACKUP_STUDENT_FILE), &self.codec)?,
+            CashDatabase::read_from_with(&file(BACKUP_CASH_FILE), &self.codec)?,
+        );
+
+        let safety = self.backup_now()?;
+        sync_uid_counters(&restored);
+        if let Some(ids) = &self.ids {
+            ids.observe_existing(
+                &mut restored.student.iter().map(|(&uid, _)| uid),
+                &mut restored.cash.iter().map(|(&uid, _)| uid),
+            );
+        }
+        {
+            let mut db = self
+                .database
+                .write()
+                .map_err(|e| Error::Poison(e.to_string()))?;
+            *db = restored;
+            self.dirty.mark_all();
         }
+        self.journal
+            .lock()
+            .map_err(|e| Error::Poison(e.to_string()))?
+            .clear();
+        self.save()?;
+        info!(
+            "已从备份 {} 恢复数据，恢复前的数据已备份到 {}",
+            path.display(),
+            safety.display()
+        );
         Ok(())
     }
-}
-
-// ============================================================================
-// 学生管理API
-// ============================================================================
 
-impl QmxManager {
-    /// 创建新学生
-    ///
-    /// # 参数
-    /// * `builder` - 学生构建器，使用链式调用设置属性
-    ///
-    /// # 示例
-    /// ```rust
-    /// use qmx_backend_lib::{QmxManager, StudentBuilder};
-    /// use qmx_backend_lib::student::{Class, Subject};
+    /// 以 `label` 为名记下学生和现金数据库的当前状态，详见 [`crate::snapshot`]
     ///
-    /// # fn main() -> qmx_backend_lib::error::Result<()> {
-    /// # let manager = QmxManager::new(true)?;
-    /// let student_id = manager.create_student(
-    ///     StudentBuilder::new("张三", 16)
-    ///         .phone("13800138000")
-    ///         .class(Class::TenTry)
-    ///         .subject(Subject::Shooting)
-    ///         .note("优秀学生")
-    /// )?;
-    /// # Ok(())
-    /// # }
-    /// ```
-    pub fn create_student(&self, builder: StudentBuilder) -> Result<u64> {
-        let mut db = self
-            .database
+    /// 名称不能为空，也不能与已有快照重复。
+    pub fn snapshot(&self, label: impl Into<String>) -> Result<SnapshotInfo> {
+        self.authorize(Capability::Backup)?;
+        let label = label.into();
+        if label.trim().is_empty() {
+            return Err(Error::ValidationFailed {
+                field: "label".to_string(),
+                reason: "快照名称不能为空".to_string(),
+            });
+        }
+        let mut snapshots = self
+            .snapshots
             .write()
             .map_err(|e| Error::Poison(e.to_string()))?;
-        let student = builder.build();
-        let uid = student.uid();
-        db.student.insert(student);
-        drop(db);
-
-        self.auto_save_if_enabled()?;
-        info!("创建学生成功，UID: {}", uid);
-        Ok(uid)
+        if snapshots.iter().any(|snapshot| snapshot.info.label == label) {
+            return Err(Error::InvalidInput(format!("快照已存在: {}", label)));
+        }
+        let data = self
+            .database
+            .read()
+            .map_err(|e| Error::Poison(e.to_string()))?
+            .clone();
+        let snapshot = Snapshot::new(label, self.clock.now(), data);
+        let info = snapshot.info.clone();
+        snapshots.push(snapshot);
+        info!(
+            "已创建快照 {}：{} 名学生、{} 条现金记录",
+            info.label, info.students, info.cash
+        );
+        Ok(info)
     }
 
-    /// 获取学生信息
-    pub fn get_student(&self, uid: u64) -> Result<Option<Student>> {
-        let db = self
-            .database
+    /// 列出全部快照，按创建时间从旧到新排列
+    pub fn list_snapshots(&self) -> Result<Vec<SnapshotInfo>> {
+        Ok(self
+            .snapshots
             .read()
-            .map_err(|e| Error::Poison(e.to_string()))?;
-        Ok(db.student.get(&uid).cloned())
+            .map_err(|e| Error::Poison(e.to_string()))?
+            .iter()
+            .map(|snapshot| snapshot.info.clone())
+            .collect())
     }
 
-    /// 更新学生信息
-    pub fn update_student(&self, uid: u64, updater: StudentUpdater) -> Result<()> {
+    /// 把学生和现金数据恢复为快照 `label` 中的状态，返回被恢复的记录数
+    ///
+    /// 快照之后新增的记录被删除，修改和删除的记录恢复为快照中的内容。
+    /// 恢复算作一次修改操作，可以用 [`QmxManager::undo_last`] 撤销；快照本身保留，可以再次恢复。
+    pub fn restore_snapshot(&self, label: &str) -> Result<usize> {
+        self.ensure_writable("restore_snapshot")?;
+        self.authorize(Capability::Restore)?;
+        let snapshots = self
+            .snapshots
+            .read()
+            .map_err(|e| Error::Poison(e.to_string()))?;
+        let snapshot = snapshots
+            .iter()
+            .find(|snapshot| snapshot.info.label == label)
+            .ok_or_else(|| Error::NotFound(format!("快照不存在: {}", label)))?;
         let mut db = self
             .database
             .write()
             .map_err(|e| Error::Poison(e.to_string()))?;
-        updater.apply(&mut db.student, uid)?;
+        let written = snapshot.restore_into(&mut db);
+        drop(snapshots);
+
+        let mut student_uids = Vec::new();
+        let mut cash_uids = Vec::new();
+        let entries = written
+            .into_iter()
+            .map(|record| match record {
+                MergedRecord::Student(uid, previous) => {
+                    student_uids.push(uid);
+                    JournalEntry::Student(uid, previous)
+                }
+                MergedRecord::Cash(uid, previous) => {
+                    cash_uids.push(uid);
+                    JournalEntry::Cash(uid, previous)
+                }
+            })
+            .collect();
+        self.push_journal(&mut db, entries)?;
         drop(db);
 
-        self.auto_save_if_enabled()?;
-        info!("更新学生信息成功，UID: {}", uid);
-        Ok(())
+        self.auto_save_student_batch(&student_uids)?;
+        self.auto_save_cash_batch(&cash_uids)?;
+        let restored = student_uids.len() + cash_uids.len();
+        info!("已恢复快照 {}，共 {} 条记录", label, restored);
+        Ok(restored)
     }
 
-    /// 删除学生
-    pub fn delete_student(&self, uid: u64) -> Result<bool> {
-        let mut db = self
-            .database
+    /// 删除快照 `label`，释放其占用的内存，返回快照是否存在
+    pub fn delete_snapshot(&self, label: &str) -> Result<bool> {
+        self.authorize(Capability::Backup)?;
+        let mut snapshots = self
+            .snapshots
             .write()
             .map_err(|e| Error::Poison(e.to_string()))?;
-        let removed = db.student.remove(&uid).is_some();
-        drop(db);
-
-        if removed {
-            self.auto_save_if_enabled()?;
-            info!("删除学生成功，UID: {}", uid);
-        }
-        Ok(removed)
+        let before = snapshots.len();
+        snapshots.retain(|snapshot| snapshot.info.label != label);
+        Ok(snapshots.len() < before)
     }
 
-    /// 搜索学生
-    pub fn search_students(&self, query: StudentQuery) -> Result<Vec<Student>> {
-        let db = self
-            .database
-            .read()
-            .map_err(|e| Error::Poison(e.to_string()))?;
-        Ok(query.execute(&db.student))
+    /// 把全部数据导出为一个数据包，用于迁移到另一台电脑，详见 [`crate::bundle`]
+    ///
+    /// 数据包包括学生、现金、教练、课程数据库，UID 计数器和全部附件，内存中尚未保存的修改也会导出。
+    pub fn export_bundle(&self, path: impl AsRef<Path>) -> Result<BundleManifest> {
+        self.authorize(Capability::Backup)?;
+        let path = path.as_ref();
+        let bundle = {
+            let db = self
+                .database
+                .read()
+                .map_err(|e| Error::Poison(e.to_string()))?;
+            let coaches = self
+                .coaches
+                .read()
+                .map_err(|e| Error::Poison(e.to_string()))?;
+            let sessions = self
+                .sessions
+                .read()
+                .map_err(|e| Error::Poison(e.to_string()))?;
+            let attachments = self
+                .attachments
+                .read()
+                .map_err(|e| Error::Poison(e.to_string()))?;
+            let files = attachments
+                .iter()
+                .map(|(&uid, attachment)| {
+                    Ok((uid, std::fs::read(attachment.path_in(&self.attachments_dir))?))
+                })
+                .collect::<Result<_>>()?;
+            Bundle {
+                manifest: crate::bundle::manifest(
+                    &db.student,
+                    &db.cash,
+                    &coaches,
+                    &sessions,
+                    &attachments,
+                    self.clock.now(),
+                ),
+                student: db.student.clone(),
+                cash: db.cash.clone(),
+                coaches: coaches.clone(),
+                sessions: sessions.clone(),
+                attachments: attachments.clone(),
+                files,
+            }
+        };
+        bundle.write(path)?;
+        info!(
+            "已导出数据包 {}：{} 名学生，{} 条现金记录，{} 个附件",
+            path.display(),
+            bundle.manifest.students,
+            bundle.manifest.cash,
+            bundle.manifest.attachments
+        );
+        Ok(bundle.manifest)
     }
 
-    /// 获取所有学生
-    pub fn list_students(&self) -> Result<Vec<Student>> {
-        let db = self
-            .database
-            .read()
-            .map_err(|e| Error::Poison(e.to_string()))?;
-        Ok(db.student.iter().map(|(_, s)| s).cloned().collect())
-    }
-}
+    /// 从数据包导入数据并立即保存
+    ///
+    /// 数据包在修改任何数据之前完整读取并校验，导入前会先备份当前的学生和现金数据。
+    /// 导入后计数器不小于数据包中记录的值，操作日志被清空，导入不能撤销。
+    pub fn import_bundle(
+        &self,
+        path: impl AsRef<Path>,
+        strategy: MergeStrategy,
+    ) -> Result<BundleImportReport> {
+        self.ensure_writable("import_bundle")?;
+        self.authorize(Capability::Restore)?;
+        let path = path.as_ref();
+        let bundle = Bundle::read(path)?;
+        let safety_backup = self.backup_now()?;
+        bundle.advance_counters();
+        if let Some(ids) = &self.ids {
+            ids.observe_existing(
+                &mut bundle.student.iter().map(|(&uid, _)| uid),
+                &mut bundle.cash.iter().map(|(&uid, _)| uid),
+            );
+        }
+        let Bundle {
+            manifest,
+            student,
+            cash,
+            coaches,
+            sessions,
+            attachments,
+            mut files,
+        } = bundle;
 
-// ============================================================================
-// 现金管理API
-// ============================================================================
+        let (students, cash) = {
+            let mut db = self
+                .database
+                .write()
+                .map_err(|e| Error::Poison(e.to_string()))?;
+            let (_, students) = crate::bundle::merge(&mut db.student, student, strategy);
+            let (_, cash) = crate::bundle::merge(&mut db.cash, cash, strategy);
+            crate::cash::sync_plan_id_counter(&db.cash);
+            self.dirty.mark_all();
+            (students, cash)
+        };
+        self.journal
+            .lock()
+            .map_err(|e| Error::Poison(e.to_string()))?
+            .clear();
+        self.save()?;
 
-impl QmxManager {
-    /// 记录现金流
-    pub fn record_cash(&self, builder: CashBuilder) -> Result<u64> {
+        let coaches = {
+            let mut current = self
+                .coaches
+                .write()
+                .map_err(|e| Error::Poison(e.to_string()))?;
+            let (written, count) = crate::bundle::merge(&mut *current, coaches, strategy);
+            self.save_coaches(&current, &written)?;
+            count
+        };
+        let sessions = {
+            let mut current = self
+                .sessions
+                .write()
+                .map_err(|e| Error::Poison(e.to_string()))?;
+            let (written, count) = crate::bundle::merge(&mut *current, sessions, strategy);
+            self.save_sessions(&current, &written)?;
+            count
+        };
+        let attachments = {
+            let mut current = self
+                .attachments
+                .write()
+                .map_err(|e| Error::Poison(e.to_string()))?;
+            let (written, count) = crate::bundle::merge(&mut *current, attachments, strategy);
+            // 先写附件文件，再保存索引
+            for uid in written {
+                if let (Some(attachment), Some(data)) = (current.get(&uid), files.remove(&uid)) {
+                    let target = attachment.path_in(&self.attachments_dir);
+                    if let Some(dir) = target.parent() {
+                        std::fs::create_dir_all(dir)?;
+                    }
+                    std::fs::write(target, data)?;
+                }
+            }
+            current.save_to_with(&self.attachment_index_path(), &self.codec)?;
+            count
+        };
+
+        info!(
+            "已从数据包 {} 导入数据（{:?}），导入前的数据已备份到 {}",
+            path.display(),
+            strategy,
+            safety_backup.display()
+        );
+        Ok(BundleImportReport {
+            manifest,
+            students,
+            cash,
+            coaches,
+            sessions,
+            attachments,
+            safety_backup,
+        })
+    }
+
+    /// 把另一个数据库中的学生和现金记录合并进来，详见 [`crate::merge`]
+    ///
+    /// UID 冲突的记录分配新的 UID，返回的报告中列出 UID 的对应关系。
+    /// 合并算作一次操作，可以通过 [`QmxManager::undo_last`] 撤销。
+    pub fn merge_from(&self, other: DbContainer, policy: ConflictPolicy) -> Result<MergeReport> {
+        self.ensure_writable("merge_from")?;
+        self.authorize(Capability::Restore)?;
         let mut db = self
             .database
             .write()
             .map_err(|e| Error::Poison(e.to_string()))?;
-        let cash = builder.build()?;
-        let uid = cash.uid;
-        db.cash.insert(cash);
+        // 计数器推进到两边已有的 UID 之后，重新分配的 UID 不会与任何一边重复
+        sync_uid_counters(&db);
+        sync_uid_counters(&other);
+        if let Some(ids) = &self.ids {
+            ids.observe_existing(
+                &mut db.student.iter().chain(other.student.iter()).map(|(&uid, _)| uid),
+                &mut db.cash.iter().chain(other.cash.iter()).map(|(&uid, _)| uid),
+            );
+        }
+        let mut next_student_uid = || self.next_student_uid();
+        let mut next_cash_uid = || self.next_cash_uid();
+        let (report, written) = crate::merge::merge_into(
+            &mut db,
+            other,
+            policy,
+            &mut next_student_uid,
+            &mut next_cash_uid,
+        );
+
+        let mut student_uids = Vec::new();
+        let mut cash_uids = Vec::new();
+        let entries = written
+            .into_iter()
+            .map(|record| match record {
+                MergedRecord::Student(uid, previous) => {
+                    student_uids.push(uid);
+                    JournalEntry::Student(uid, previous)
+                }
+                MergedRecord::Cash(uid, previous) => {
+                    cash_uids.push(uid);
+                    JournalEntry::Cash(uid, previous)
+                }
+            })
+            .collect();
+        self.push_journal(&mut db, entries)?;
         drop(db);
 
-        self.auto_save_if_enabled()?;
-        info!("记录现金流成功，UID: {}", uid);
-        Ok(uid)
+        self.auto_save_student_batch(&student_uids)?;
+        self.auto_save_cash_batch(&cash_uids)?;
+        info!(
+            "合并完成：新增 {} 名学生、{} 条现金记录，{} 处冲突",
+            report.students.added,
+            report.cash.added,
+            report.conflicts.len()
+        );
+        Ok(report)
     }
 
-    /// 获取现金记录
-    pub fn get_cash(&self, uid: u64) -> Result<Option<Cash>> {
-        let db = self
-            .database
-            .read()
-            .map_err(|e| Error::Poison(e.to_string()))?;
-        Ok(db.cash.get(&uid).cloned())
-    }
+    /// 与远端同步学生和现金记录，详见 [`crate::sync`]
+    ///
+    /// 从远端应用到本地的修改和 [`QmxManager::merge_from`] 一样可以撤销，
+    /// 撤销后的状态在下次同步时作为本地修改写回远端。
+    #[cfg(feature = "sync")]
+    pub fn sync(&self, client: &SyncClient) -> Result<SyncReport> {
+        self.ensure_writable("sync")?;
+        self.authorize(Capability::Restore)?;
+        let (students, cash, plans) = {
+            let db = self
+                .database
+                .read()
+                .map_err(|e| Error::Poison(e.to_string()))?;
+            (
+                db.student.student_data.clone(),
+                db.cash.cash_data.clone(),
+                db.cash.plans.plan_data.clone(),
+            )
+        };
+        // 网络读写期间不持有锁，应用远端修改前再确认本地记录没有被修改
+        let plan = client.prepare(
+            &students,
+            &cash,
+            &plans,
+            self.clock.now(),
+            self.codec.key.as_ref(),
+        )?;
+        drop((students, cash, plans));
+        client.upload(&plan)?;
 
-    /// 更新现金记录
-    pub fn update_cash(&self, uid: u64, updater: CashUpdater) -> Result<()> {
         let mut db = self
             .database
             .write()
             .map_err(|e| Error::Poison(e.to_string()))?;
-        updater.apply(&mut db.cash, uid)?;
+        let (written, deferred) = plan.apply(&mut db)?;
+        sync_uid_counters(&db);
+        if let Some(ids) = &self.ids {
+            ids.observe_existing(
+                &mut db.student.iter().map(|(&uid, _)| uid),
+                &mut db.cash.iter().map(|(&uid, _)| uid),
+            );
+        }
+        let mut student_uids = Vec::new();
+        let mut cash_uids = Vec::new();
+        let entries = written
+            .into_iter()
+            .map(|record| match record {
+                MergedRecord::Student(uid, previous) => {
+                    student_uids.push(uid);
+                    JournalEntry::Student(uid, previous)
+                }
+                MergedRecord::Cash(uid, previous) => {
+                    cash_uids.push(uid);
+                    JournalEntry::Cash(uid, previous)
+                }
+            })
+            .collect();
+        self.push_journal(&mut db, entries)?;
         drop(db);
 
-        self.auto_save_if_enabled()?;
-        info!("更新现金记录成功，UID: {}", uid);
-        Ok(())
+        self.auto_save_student_batch(&student_uids)?;
+        self.auto_save_cash_batch(&cash_uids)?;
+        client.finish(&plan)?;
+        let report = SyncReport {
+            pulled: SyncCount {
+                students: student_uids.len(),
+                cash: cash_uids.len(),
+            },
+            deferred,
+            ..plan.report
+        };
+        info!(
+            "同步完成：拉取 {} 名学生、{} 条现金记录，推送 {} 名学生、{} 条现金记录",
+            report.pulled.students, report.pulled.cash, report.pushed.students, report.pushed.cash
+        );
+        Ok(report)
     }
+}
 
-    /// 删除现金记录
-    pub fn delete_cash(&self, uid: u64) -> Result<bool> {
+// ============================================================================
+// 撤销API
+// ============================================================================
+
+impl QmxManager {
+    /// 撤销最近的 `n` 次修改操作，返回实际撤销的操作数
+    ///
+    /// 每次调用创建、更新、删除类方法算作一次操作（一次生成的全部分期、
+    /// 一次标记的全部逾期记录也算一次），按从新到旧的顺序恢复到操作前的状态。
+    /// 操作日志只保存在内存中，最多保留最近 100 次操作，
+    /// 调用 [`QmxManager::recover`] 后清空。撤销本身会写入审计日志，但不能再被撤销。
+    pub fn undo_last(&self, n: usize) -> Result<usize> {
+        self.ensure_writable("undo_last")?;
+        self.authorize(Capability::Restore)?;
         let mut db = self
             .database
             .write()
             .map_err(|e| Error::Poison(e.to_string()))?;
-        let removed = db.cash.remove(&uid).is_some();
-        drop(db);
+        let operations: Vec<Vec<JournalEntry>> = {
+            let mut journal = self
+                .journal
+                .lock()
+                .map_err(|e| Error::Poison(e.to_string()))?;
+            let keep = journal.len().saturating_sub(n);
+            journal.drain(keep..).rev().collect()
+        };
+        let undone = operations.len();
 
-        if removed {
-            self.auto_save_if_enabled()?;
-            info!("删除现金记录成功，UID: {}", uid);
+        let mut audits = Vec::new();
+        let mut student_uids = Vec::new();
+        let mut cash_uids = Vec::new();
+        let mut plan_ids = Vec::new();
+        // 撤销前的状态，写入预写日志失败时据此撤回
+        let mut redo = Vec::new();
+        for entry in operations.iter().flat_map(|op| op.iter().rev()).cloned() {
+            match &entry {
+                JournalEntry::Student(uid, before) => {
+                    let current = db.student.get(uid);
+                    if let Some((action, changes)) = restore_fields(current, before.as_ref())? {
+                        audits.push((AuditEntity::Student, *uid, action, changes));
+                    }
+                    redo.push(JournalEntry::Student(*uid, current.cloned()));
+                    student_uids.push(*uid);
+                }
+                JournalEntry::Cash(uid, before) => {
+                    let current = db.cash.get(uid);
+                    if let Some((action, changes)) = restore_fields(current, before.as_ref())? {
+                        audits.push((AuditEntity::Cash, *uid, action, changes));
+                    }
+                    redo.push(JournalEntry::Cash(*uid, current.cloned()));
+                    cash_uids.push(*uid);
+                }
+                JournalEntry::Plan(plan_id, _) => {
+                    let current = db.cash.plans.get(plan_id).cloned();
+                    redo.push(JournalEntry::Plan(*plan_id, current));
+                    plan_ids.push(*plan_id);
+                }
+            }
+            restore_entry(&mut db, entry);
         }
-        Ok(removed)
-    }
-
-    /// 搜索现金记录
-    pub fn search_cash(&self, query: CashQuery) -> Result<Vec<Cash>> {
-        let db = self
-            .database
-            .read()
-            .map_err(|e| Error::Poison(e.to_string()))?;
-        Ok(query.execute(&db.cash))
-    }
+        if let Err(e) = self.write_wal(&mut db, &redo) {
+            let mut journal = self
+                .journal
+                .lock()
+                .map_err(|e| Error::Poison(e.to_string()))?;
+            journal.extend(operations.into_iter().rev());
+            return Err(e);
+        }
+        if !student_uids.is_empty() {
+            self.dirty.student.store(true, Ordering::SeqCst);
+        }
+        if !cash_uids.is_empty() || !plan_ids.is_empty() {
+            self.dirty.cash.store(true, Ordering::SeqCst);
+        }
+        drop(db);
 
-    /// 获取学生的所有现金记录
-    pub fn get_student_cash(&self, student_id: u64) -> Result<Vec<Cash>> {
-        let db = self
-            .database
-            .read()
-            .map_err(|e| Error::Poison(e.to_string()))?;
-        Ok(db
-            .cash
-            .iter()
-            .filter(|(_, c)| c.student_id == Some(student_id))
-            .map(|(_, c)| c)
-            .cloned()
-            .collect())
+        let mut events = Vec::with_capacity(audits.len());
+        for (entity, uid, action, changes) in audits {
+            events.push(match (entity, action) {
+                (AuditEntity::Student, AuditAction::Create) => Event::StudentCreated { uid },
+                (AuditEntity::Student, AuditAction::Update) => Event::StudentUpdated {
+                    uid,
+                    changes: changes.clone(),
+                },
+                (AuditEntity::Student, AuditAction::Delete) => Event::StudentDeleted { uid },
+                (AuditEntity::Cash, AuditAction::Create) => Event::CashRecorded { uid },
+                (AuditEntity::Cash, AuditAction::Update) => Event::CashUpdated {
+                    uid,
+                    changes: changes.clone(),
+                },
+                (AuditEntity::Cash, AuditAction::Delete) => Event::CashDeleted { uid },
+            });
+            self.record_audit(entity, uid, action, changes)?;
+        }
+        if self.auto_save == AutoSave::Immediate && self.backend.is_none() {
+            if !student_uids.is_empty() || !cash_uids.is_empty() {
+                self.save()?;
+            }
+        } else {
+            self.auto_save_student_batch(&student_uids)?;
+            self.auto_save_cash_batch(&cash_uids)?;
+        }
+        for event in &events {
+            self.events.emit(event);
+        }
+        info!("撤销 {} 次操作", undone);
+        Ok(undone)
     }
 }
 
 // ============================================================================
-// 统计分析API
+// 事件订阅API
 // ============================================================================
 
 impl QmxManager {
-    /// 获取仪表板统计信息
-    pub fn get_dashboard_stats(&self) -> Result<DashboardStats> {
-        let db = self
-            .database
-            .read()
-            .map_err(|e| Error::Poison(e.to_string()))?;
-        get_dashboard_stats(&db.student, &db.cash)
+    /// 订阅某一类数据变更事件
+    ///
+    /// 回调在修改成功（包括自动保存）之后、在触发修改的线程上同步执行，
+    /// 执行时不持有管理器的锁，可以在回调中再次调用管理器。撤销操作同样会触发对应事件。
+    ///
+    /// # 示例
+    /// ```rust
+    /// use qmx_backend_lib::{EventKind, QmxManager};
+    ///
+    /// # fn main() -> qmx_backend_lib::error::Result<()> {
+    /// # let manager = QmxManager::builder().auto_save(false).build()?;
+    /// let id = manager.subscribe(EventKind::CashRecorded, |event| {
+    ///     println!("新增现金记录: {}", event.uid());
+    /// });
+    /// manager.unsubscribe(id);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn subscribe<F>(&self, kind: EventKind, callback: F) -> SubscriptionId
+    where
+        F: Fn(&Event) + Send + Sync + 'static,
+    {
+        self.events.subscribe(kind, Arc::new(callback))
     }
 
-    /// 获取学生统计信息
-    pub fn get_student_stats(&self, uid: u64) -> Result<StudentStats> {
-        let db = self
-            .database
-            .read()
-            .map_err(|e| Error::Poison(e.to_string()))?;
-        StudentStats::calculate(&db.student, &db.cash, uid)
+    /// 取消订阅，订阅不存在时返回 `false`
+    pub fn unsubscribe(&self, id: SubscriptionId) -> bool {
+        self.events.unsubscribe(id)
     }
 
-    /// 获取财务统计信息
-    pub fn get_financial_stats(&self, period: TimePeriod) -> Result<FinancialStats> {
-        let db = self
-            .database
-            .read()
-            .map_err(|e| Error::Poison(e.to_string()))?;
-        FinancialStats::calculate(&db.cash, period)
+    /// 获取接收全部变更通知的通道
+    ///
+    /// 每次成功的创建、更新、删除（包括撤销）都会向通道发送一条 [`ChangeEvent`]。
+    /// 通道没有容量上限，接收端应及时取走通知；丢弃接收端即停止通知。
+    ///
+    /// ```rust
+    /// use qmx_backend_lib::*;
+    ///
+    /// # fn main() -> qmx_backend_lib::error::Result<()> {
+    /// # let manager = QmxManager::builder().auto_save(false).build()?;
+    /// let changes = manager.watch();
+    /// std::thread::spawn(move || {
+    ///     for change in changes {
+    ///         println!("{:?} #{} {:?}", change.entity, change.uid, change.action);
+    ///     }
+    /// });
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn watch(&self) -> std::sync::mpsc::Receiver<ChangeEvent> {
+        self.events.watch()
     }
 }
 
@@ -331,9 +4965,16 @@ pub struct StudentBuilder {
     note: Option<String>,
     membership_start: Option<DateTime<Utc>>,
     membership_end: Option<DateTime<Utc>>,
+    membership_tier: Option<MembershipTier>,
+    guardians: Vec<Guardian>,
+    custom_fields: BTreeMap<String, CustomValue>,
+    branch_id: Option<String>,
 }
 
 impl StudentBuilder {
+    /// 创建学生构建器，仅姓名为必填项
+    ///
+    /// 年龄等其余属性通过链式方法设置，例如 `StudentBuilder::new("张三").age(16)`。
     pub fn new(name: impl Into<String>) -> Self {
         Self {
             name: name.into(),
@@ -345,9 +4986,19 @@ impl StudentBuilder {
             note: None,
             membership_start: None,
             membership_end: None,
+            membership_tier: None,
+            guardians: Vec::new(),
+            custom_fields: BTreeMap::new(),
+            branch_id: None,
         }
     }
 
+    /// 旧版双参数构造函数，等价于 `StudentBuilder::new(name).age(age)`
+    #[deprecated(since = "2.5.0", note = "请使用 `StudentBuilder::new(name).age(age)`")]
+    pub fn new_with_age(name: impl Into<String>, age: u8) -> Self {
+        Self::new(name).age(age)
+    }
+
     pub fn phone(mut self, phone: impl Into<String>) -> Self {
         self.phone = Some(phone.into());
         self
@@ -368,24 +5019,52 @@ impl StudentBuilder {
         self
     }
 
-    pub fn age(mut self, age: u8) -> Self {
-        self.age = Some(age);
+    pub fn age(mut self, age: u8) -> Self {
+        self.age = Some(age);
+        self
+    }
+
+    pub fn note(mut self, note: impl Into<String>) -> Self {
+        self.note = Some(note.into());
+        self
+    }
+
+    pub fn membership(mut self, start: DateTime<Utc>, end: DateTime<Utc>) -> Self {
+        self.membership_start = Some(start);
+        self.membership_end = Some(end);
+        self
+    }
+
+    pub fn membership_tier(mut self, tier: MembershipTier) -> Self {
+        self.membership_tier = Some(tier);
+        self
+    }
+
+    /// 添加一位监护人，可多次调用
+    pub fn guardian(mut self, guardian: Guardian) -> Self {
+        self.guardians.push(guardian);
         self
     }
 
-    pub fn note(mut self, note: impl Into<String>) -> Self {
-        self.note = Some(note.into());
+    /// 设置自定义字段，可多次调用
+    pub fn custom_field(mut self, key: impl Into<String>, value: impl Into<CustomValue>) -> Self {
+        self.custom_fields.insert(key.into(), value.into());
         self
     }
 
-    pub fn membership(mut self, start: DateTime<Utc>, end: DateTime<Utc>) -> Self {
-        self.membership_start = Some(start);
-        self.membership_end = Some(end);
+    /// 所属校区
+    pub fn branch(mut self, branch_id: impl Into<String>) -> Self {
+        self.branch_id = Some(branch_id.into());
         self
     }
 
-    fn build(self) -> Student {
-        let mut s = Student::new();
+    /// 构建尚未分配 UID 的草稿，UID 由管理器在校验通过后分配
+    fn build(self, limits: &Limits, policies: &ClassPolicyTable) -> Result<Student> {
+        Limits::check_len("name", &self.name, limits.max_name_len)?;
+        if let Some(note) = &self.note {
+            Limits::check_len("note", note, limits.max_note_len)?;
+        }
+        let mut s = Student::new_with_uid(0);
         s.set_name(self.name);
         if let Some(age) = self.age {
             s.set_age(Some(age));
@@ -394,13 +5073,13 @@ impl StudentBuilder {
             s.set_phone(phone);
         }
         if let Some(class) = self.class {
-            s.set_class_with_lesson_init(class);
+            s.set_class_with_policy(class, policies);
         }
         if let Some(subject) = self.subject {
             s.set_subject(subject);
         }
         if let Some(lesson) = self.lesson_left {
-            s.set_lesson_left(lesson);
+            s.set_lesson_left_with_policy(lesson, policies);
         }
         if let Some(note) = self.note {
             s.set_note(note);
@@ -408,28 +5087,79 @@ impl StudentBuilder {
         if self.membership_start.is_some() || self.membership_end.is_some() {
             s.set_membership_dates(self.membership_start, self.membership_end);
         }
-        s
+        if self.membership_tier.is_some() {
+            s.set_membership_tier(self.membership_tier);
+        }
+        for guardian in &self.guardians {
+            Limits::check_len("guardian.name", &guardian.name, limits.max_name_len)?;
+        }
+        if !self.guardians.is_empty() {
+            s.set_guardians(self.guardians);
+        }
+        for (key, value) in self.custom_fields {
+            limits.check_custom_field(&key, &value)?;
+            s.set_custom_field(key, value);
+        }
+        if let Some(branch_id) = self.branch_id {
+            check_branch_id(&branch_id, limits)?;
+            s.set_branch_id(Some(branch_id));
+        }
+        Ok(s)
     }
 }
 
 /// 现金构建器
 pub struct CashBuilder {
     student_id: Option<u64>,
-    amount: i64,
+    amount: Money,
     note: Option<String>,
     installment: Option<Installment>,
+    custom_fields: BTreeMap<String, CustomValue>,
+    branch_id: Option<String>,
+    payment_method: Option<PaymentMethod>,
+    sale_item: Option<u64>,
+    top_up: bool,
+    category: Option<String>,
+    recurring: Option<RecurringOccurrence>,
+    /// 定期支出按付款日记录，其他记录使用当前时间
+    created_at: Option<DateTime<Utc>>,
+    force: bool,
+    allow_dangling: bool,
 }
 
 impl CashBuilder {
-    pub fn new(amount: i64) -> Self {
+    /// 金额不能为 0，整数按人民币的分处理
+    pub fn new(amount: impl Into<Money>) -> Self {
         Self {
             student_id: None,
-            amount,
+            amount: amount.into(),
             note: None,
             installment: None,
+            custom_fields: BTreeMap::new(),
+            branch_id: None,
+            payment_method: None,
+            sale_item: None,
+            top_up: false,
+            category: None,
+            recurring: None,
+            created_at: None,
+            force: false,
+            allow_dangling: false,
         }
     }
 
+    /// 跳过重复记录防护，强制写入
+    pub fn force(mut self) -> Self {
+        self.force = true;
+        self
+    }
+
+    /// 不检查关联的学生是否存在，用于导入学生已被删除的历史记录
+    pub fn allow_dangling(mut self) -> Self {
+        self.allow_dangling = true;
+        self
+    }
+
     pub fn student_id(mut self, student_id: u64) -> Self {
         self.student_id = Some(student_id);
         self
@@ -445,11 +5175,60 @@ impl CashBuilder {
         self
     }
 
-    fn build(self) -> Result<Cash> {
-        let mut c = Cash::new(self.student_id);
-        if self.amount == 0 {
+    /// 设置自定义字段，可多次调用
+    pub fn custom_field(mut self, key: impl Into<String>, value: impl Into<CustomValue>) -> Self {
+        self.custom_fields.insert(key.into(), value.into());
+        self
+    }
+
+    /// 所属校区，未设置时使用关联学生的校区
+    pub fn branch(mut self, branch_id: impl Into<String>) -> Self {
+        self.branch_id = Some(branch_id.into());
+        self
+    }
+
+    pub fn payment_method(mut self, method: PaymentMethod) -> Self {
+        self.payment_method = Some(method);
+        self
+    }
+
+    /// 收支类别，如房租、工资
+    pub fn category(mut self, category: impl Into<String>) -> Self {
+        self.category = Some(category.into());
+        self
+    }
+
+    /// 标记为售卖价目表中的商品，记录时自动为关联学生增加课时或延长会员期
+    ///
+    /// 金额仍以构建器为准，可与商品价格不同（如折扣）。见 [`QmxManager::record_cash`]。
+    pub fn sale(mut self, item_id: u64) -> Self {
+        self.sale_item = Some(item_id);
+        self
+    }
+
+    /// 标记为储值充值，金额计入关联学生的余额，见 [`QmxManager::top_up_balance`]
+    pub fn top_up(mut self) -> Self {
+        self.top_up = true;
+        self
+    }
+
+    /// 构建尚未分配 UID 的草稿，UID 由管理器在校验通过后分配
+    fn build(self, limits: &Limits) -> Result<Cash> {
+        if self.amount.is_zero() {
             return Err(Error::InvalidInput("amount cannot be zero".to_string()));
         }
+        if self.top_up && (!self.amount.is_positive() || self.student_id.is_none()) {
+            return Err(Error::InvalidInput(
+                "储值充值的金额必须为正数且必须关联学生".to_string(),
+            ));
+        }
+        if let Some(note) = &self.note {
+            Limits::check_len("note", note, limits.max_note_len)?;
+        }
+        if let Some(category) = &self.category {
+            Limits::check_len("category", category, limits.max_name_len)?;
+        }
+        let mut c = Cash::new_with_uid(0, self.student_id);
         c.set_cash(self.amount);
         if let Some(n) = self.note {
             c.set_note(Some(n));
@@ -457,14 +5236,477 @@ impl CashBuilder {
         if let Some(inst) = self.installment {
             c.installment = Some(inst);
         }
+        for (key, value) in &self.custom_fields {
+            limits.check_custom_field(key, value)?;
+        }
+        c.custom_fields = self.custom_fields;
+        if let Some(branch_id) = &self.branch_id {
+            check_branch_id(branch_id, limits)?;
+        }
+        c.branch_id = self.branch_id;
+        c.payment_method = self.payment_method;
+        if self.top_up {
+            c.wallet = Some(self.amount);
+        }
+        c.category = self.category;
+        c.recurring = self.recurring;
         Ok(c)
     }
 }
 
+/// 分期计划构建器
+///
+/// 一次性生成整个计划的所有分期记录，各期到期时间由 [`PaymentFrequency`] 从首期到期时间推算。
+pub struct InstallmentPlanBuilder {
+    student_id: Option<u64>,
+    total_amount: Money,
+    total_installments: u32,
+    frequency: PaymentFrequency,
+    first_due: DateTime<Utc>,
+    remainder_strategy: RemainderStrategy,
+    grace_days: Option<u32>,
+    note: Option<String>,
+}
+
+/// 已创建的分期计划
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CreatedPlan {
+    pub plan_id: u64,
+    /// 按期数顺序排列的现金记录 UID
+    pub cash_uids: Vec<u64>,
+}
+
+impl InstallmentPlanBuilder {
+    /// 总金额为整数时按人民币的分处理，各期金额使用总金额的币种
+    pub fn new(
+        total_amount: impl Into<Money>,
+        total_installments: u32,
+        frequency: PaymentFrequency,
+        first_due: DateTime<Utc>,
+    ) -> Self {
+        Self {
+            student_id: None,
+            total_amount: total_amount.into(),
+            total_installments,
+            frequency,
+            first_due,
+            remainder_strategy: RemainderStrategy::default(),
+            grace_days: None,
+            note: None,
+        }
+    }
+
+    pub fn student_id(mut self, student_id: u64) -> Self {
+        self.student_id = Some(student_id);
+        self
+    }
+
+    pub fn remainder_strategy(mut self, strategy: RemainderStrategy) -> Self {
+        self.remainder_strategy = strategy;
+        self
+    }
+
+    /// 本计划的宽限天数，覆盖 [`QmxManager::with_grace_days`] 的全局设置
+    pub fn grace_days(mut self, days: u32) -> Self {
+        self.grace_days = Some(days);
+        self
+    }
+
+    pub fn note(mut self, note: impl Into<String>) -> Self {
+        self.note = Some(note.into());
+        self
+    }
+
+    fn build(
+        self,
+        limits: &Limits,
+        ids: Option<&dyn IdGenerator>,
+        now: DateTime<Utc>,
+    ) -> Result<(InstallmentPlan, Vec<Cash>)> {
+        if !self.total_amount.is_positive() {
+            return Err(Error::InvalidInput(format!(
+                "分期总金额必须为正数: {}",
+                self.total_amount
+            )));
+        }
+        if self.total_installments == 0 {
+            return Err(Error::InvalidInput("分期期数不能为 0".to_string()));
+        }
+        if let Some(note) = &self.note {
+            Limits::check_len("note", note, limits.max_note_len)?;
+        }
+
+        let plan = InstallmentPlan {
+            plan_id: allocate_plan_id(),
+            student_id: self.student_id,
+            total_amount: self.total_amount,
+            total_installments: self.total_installments,
+            frequency: self.frequency,
+            first_due: self.first_due,
+            remainder_strategy: self.remainder_strategy,
+            grace_days: self.grace_days,
+        };
+        let records = (1..=self.total_installments)
+            .map(|current| {
+                let mut cash = match ids {
+                    Some(ids) => Cash::new_with_uid(ids.next_cash_uid(), self.student_id),
+                    None => Cash::new(self.student_id),
+                };
+                cash.set_cash(plan.amount_for(current));
+                cash.set_note(self.note.clone());
+                cash.installment = Some(Installment::for_plan(
+                    &plan,
+                    current,
+                    plan.due_for(current),
+                ));
+                cash.created_at = now;
+                cash
+            })
+            .collect();
+        Ok((plan, records))
+    }
+}
+
+/// 教练构建器
+pub struct CoachBuilder {
+    name: String,
+    phone: Option<String>,
+    subjects: Vec<Subject>,
+}
+
+impl CoachBuilder {
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            phone: None,
+            subjects: Vec::new(),
+        }
+    }
+
+    pub fn phone(mut self, phone: impl Into<String>) -> Self {
+        self.phone = Some(phone.into());
+        self
+    }
+
+    /// 添加一个执教科目，重复添加的科目只保留一个
+    pub fn subject(mut self, subject: Subject) -> Self {
+        if !self.subjects.contains(&subject) {
+            self.subjects.push(subject);
+        }
+        self
+    }
+
+    fn validate(&self, limits: &Limits) -> Result<()> {
+        Limits::check_len("name", &self.name, limits.max_name_len)
+    }
+
+    fn build(self, uid: u64) -> Coach {
+        Coach {
+            uid,
+            name: self.name,
+            phone: self.phone,
+            subjects: self.subjects,
+        }
+    }
+}
+
+/// 商品构建器
+pub struct CatalogItemBuilder {
+    name: String,
+    price: Money,
+    effect: ItemEffect,
+}
+
+impl CatalogItemBuilder {
+    pub fn new(name: impl Into<String>, price: impl Into<Money>, effect: ItemEffect) -> Self {
+        Self {
+            name: name.into(),
+            price: price.into(),
+            effect,
+        }
+    }
+
+    fn validate(&self, limits: &Limits) -> Result<()> {
+        Limits::check_len("name", &self.name, limits.max_name_len)
+    }
+
+    fn build(self, uid: u64) -> CatalogItem {
+        CatalogItem {
+            uid,
+            name: self.name,
+            price: self.price,
+            effect: self.effect,
+        }
+    }
+}
+
+/// 定期支出模板构建器
+pub struct RecurringExpenseBuilder {
+    name: String,
+    amount: Money,
+    category: String,
+    day_of_month: u32,
+    start: Option<DateTime<Utc>>,
+}
+
+impl RecurringExpenseBuilder {
+    /// `amount` 为每期支出金额（正数），`day_of_month` 为每月付款日（1-31）
+    pub fn new(
+        name: impl Into<String>,
+        amount: impl Into<Money>,
+        category: impl Into<String>,
+        day_of_month: u32,
+    ) -> Self {
+        Self {
+            name: name.into(),
+            amount: amount.into(),
+            category: category.into(),
+            day_of_month,
+            start: None,
+        }
+    }
+
+    /// 生效时间，早于该时间的付款日不生成记录
+    pub fn start(mut self, start: DateTime<Utc>) -> Self {
+        self.start = Some(start);
+        self
+    }
+
+    fn validate(&self, limits: &Limits) -> Result<()> {
+        Limits::check_len("name", &self.name, limits.max_name_len)?;
+        Limits::check_len("category", &self.category, limits.max_name_len)?;
+        if !self.amount.is_positive() {
+            return Err(Error::InvalidInput(format!(
+                "定期支出金额必须为正数: {}",
+                self.amount
+            )));
+        }
+        if !(1..=31).contains(&self.day_of_month) {
+            return Err(Error::InvalidInput(format!(
+                "付款日必须在 1 到 31 之间: {}",
+                self.day_of_month
+            )));
+        }
+        Ok(())
+    }
+
+    fn build(self, uid: u64, start: DateTime<Utc>) -> RecurringExpense {
+        RecurringExpense {
+            uid,
+            name: self.name,
+            amount: self.amount,
+            category: self.category,
+            day_of_month: self.day_of_month,
+            start,
+        }
+    }
+}
+
+/// 课程构建器
+pub struct SessionBuilder {
+    start: DateTime<Utc>,
+    end: DateTime<Utc>,
+    subject: Subject,
+    coach_id: Option<u64>,
+    capacity: u32,
+}
+
+impl SessionBuilder {
+    /// 默认课程容量
+    pub const DEFAULT_CAPACITY: u32 = 10;
+
+    /// 创建课程构建器，容量默认为 [`SessionBuilder::DEFAULT_CAPACITY`]
+    pub fn new(start: DateTime<Utc>, end: DateTime<Utc>, subject: Subject) -> Self {
+        Self {
+            start,
+            end,
+            subject,
+            coach_id: None,
+            capacity: Self::DEFAULT_CAPACITY,
+        }
+    }
+
+    pub fn coach(mut self, coach_id: u64) -> Self {
+        self.coach_id = Some(coach_id);
+        self
+    }
+
+    pub fn capacity(mut self, capacity: u32) -> Self {
+        self.capacity = capacity;
+        self
+    }
+
+    fn validate(&self) -> Result<()> {
+        if self.start >= self.end {
+            return Err(Error::InvalidInput(format!(
+                "课程开始时间 {} 不早于结束时间 {}",
+                self.start, self.end
+            )));
+        }
+        if self.capacity == 0 {
+            return Err(Error::ValidationFailed {
+                field: "capacity".to_string(),
+                reason: "容量必须大于 0".to_string(),
+            });
+        }
+        Ok(())
+    }
+
+    fn build(self, uid: u64) -> Session {
+        Session {
+            uid,
+            start: self.start,
+            end: self.end,
+            subject: self.subject,
+            coach_id: self.coach_id,
+            capacity: self.capacity,
+            enrolled: Vec::new(),
+        }
+    }
+}
+
 // ============================================================================
 // 更新器模式
 // ============================================================================
 
+/// 单个字段的变更记录
+///
+/// 字段名与序列化后的字段名一致，新旧值以 JSON 值表示，便于直接展示或记录。
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FieldChange {
+    pub field: String,
+    pub old: serde_json::Value,
+    pub new: serde_json::Value,
+}
+
+/// 现金记录关联的学生必须存在
+fn check_student_exists(students: &StudentDatabase, student_id: Option<u64>) -> Result<()> {
+    match student_id {
+        Some(uid) if students.get(&uid).is_none() => {
+            Err(Error::NotFound(format!("学生不存在: {}", uid)))
+        }
+        _ => Ok(()),
+    }
+}
+
+/// 储值记录的币种必须与学生现有余额的币种一致
+fn check_wallet_currency(existing: &CashDatabase, cash: &Cash) -> Result<()> {
+    if let (Some(student_id), Some(delta)) = (cash.student_id, cash.wallet) {
+        let balance = existing.wallet_balance(student_id);
+        if !balance.is_zero() && balance.currency != delta.currency {
+            return Err(Error::InvalidInput(format!(
+                "学生 {} 的余额币种为 {}，不能记入 {}",
+                student_id,
+                balance.currency.code(),
+                delta.currency.code()
+            )));
+        }
+    }
+    Ok(())
+}
+
+/// 同一定期支出模板的同一月份只能有一条记录
+fn check_recurring_unique(existing: &CashDatabase, cash: &Cash) -> Result<()> {
+    if let Some(occurrence) = cash.recurring
+        && let Some((&uid, _)) = existing
+            .iter()
+            .find(|(_, other)| other.recurring == Some(occurrence))
+    {
+        return Err(Error::InvalidInput(format!(
+            "定期支出 {} 在 {} 已生成现金记录 {}",
+            occurrence.template_id,
+            occurrence.period.format("%Y-%m"),
+            uid
+        )));
+    }
+    Ok(())
+}
+
+/// 创建或删除操作的审计字段：创建时旧值为 `null`，删除时新值为 `null`
+fn snapshot_fields<T: Serialize>(record: &T, action: AuditAction) -> Result<Vec<FieldChange>> {
+    let value = serde_json::to_value(record)?;
+    let Some(fields) = value.as_object() else {
+        return Ok(Vec::new());
+    };
+    Ok(fields
+        .iter()
+        .map(|(field, value)| {
+            let (old, new) = match action {
+                AuditAction::Delete => (value.clone(), serde_json::Value::Null),
+                _ => (serde_json::Value::Null, value.clone()),
+            };
+            FieldChange {
+                field: field.clone(),
+                old,
+                new,
+            }
+        })
+        .collect())
+}
+
+/// 撤销时把记录从 `current` 恢复为 `restored` 的审计操作与字段变更，两者都不存在时返回 `None`
+fn restore_fields<T: Serialize>(
+    current: Option<&T>,
+    restored: Option<&T>,
+) -> Result<Option<(AuditAction, Vec<FieldChange>)>> {
+    Ok(match (current, restored) {
+        (Some(current), Some(restored)) => {
+            Some((AuditAction::Update, diff_fields(current, restored)?))
+        }
+        (None, Some(restored)) => Some((
+            AuditAction::Create,
+            snapshot_fields(restored, AuditAction::Create)?,
+        )),
+        (Some(current), None) => Some((
+            AuditAction::Delete,
+            snapshot_fields(current, AuditAction::Delete)?,
+        )),
+        (None, None) => None,
+    })
+}
+
+/// 比较更新前后的记录，返回发生变化的字段
+fn diff_fields<T: Serialize>(before: &T, after: &T) -> Result<Vec<FieldChange>> {
+    let before = serde_json::to_value(before)?;
+    let after = serde_json::to_value(after)?;
+    let (Some(before), Some(after)) = (before.as_object(), after.as_object()) else {
+        return Ok(Vec::new());
+    };
+    Ok(after
+        .iter()
+        .filter_map(|(field, new)| {
+            let old = before
+                .get(field)
+                .cloned()
+                .unwrap_or(serde_json::Value::Null);
+            (old != *new).then(|| FieldChange {
+                field: field.clone(),
+                old,
+                new: new.clone(),
+            })
+        })
+        .collect())
+}
+
+/// 按全局日志策略记录字段变更
+fn log_field_changes(kind: &str, uid: u64, changes: &[FieldChange]) {
+    let policy = log_policy();
+    for change in changes {
+        if policy.logs_values() {
+            info!(
+                "{} {} 字段 {} 从 {} 改为 {}",
+                kind,
+                uid,
+                change.field,
+                policy.field_value(&change.field, &change.old),
+                policy.field_value(&change.field, &change.new)
+            );
+        } else {
+            info!("{} {} 字段 {} 已变更", kind, uid, change.field);
+        }
+    }
+}
+
 /// 学生更新器 - 用于更新现有学生信息
 pub struct StudentUpdater {
     updates: Vec<StudentUpdate>,
@@ -477,12 +5719,22 @@ enum StudentUpdate {
     Class(Class),
     Subject(Subject),
     LessonLeft(Option<u32>),
+    ConsumeLesson,
+    GrantLessons(u32),
     Note(String),
     AddRing(f64),
     SetRings(Vec<f64>),
     Membership(Option<DateTime<Utc>>, Option<DateTime<Utc>>),
+    MembershipTier(Option<MembershipTier>),
     UpdateRingAt(usize, f64),
     RemoveRingAt(usize),
+    AddGuardian(Guardian),
+    SetGuardians(Vec<Guardian>),
+    RemoveGuardianAt(usize),
+    SetCustomField(String, CustomValue),
+    RemoveCustomField(String),
+    AssignedCoach(Option<u64>),
+    Branch(Option<String>),
 }
 
 impl Default for StudentUpdater {
@@ -528,6 +5780,18 @@ impl StudentUpdater {
         self
     }
 
+    /// 消耗一节课时，剩余课时不足时更新失败
+    pub fn consume_lesson(mut self) -> Self {
+        self.updates.push(StudentUpdate::ConsumeLesson);
+        self
+    }
+
+    /// 增加指定数量的课时
+    pub fn grant_lessons(mut self, lessons: u32) -> Self {
+        self.updates.push(StudentUpdate::GrantLessons(lessons));
+        self
+    }
+
     pub fn note(mut self, note: impl Into<String>) -> Self {
         self.updates.push(StudentUpdate::Note(note.into()));
         self
@@ -558,15 +5822,90 @@ impl StudentUpdater {
         self
     }
 
-    fn apply(self, db: &mut StudentDatabase, uid: u64) -> Result<()> {
-        let student = db
-            .student_data
-            .get_mut(&uid)
+    pub fn membership_tier(mut self, tier: Option<MembershipTier>) -> Self {
+        self.updates.push(StudentUpdate::MembershipTier(tier));
+        self
+    }
+
+    pub fn add_guardian(mut self, guardian: Guardian) -> Self {
+        self.updates.push(StudentUpdate::AddGuardian(guardian));
+        self
+    }
+
+    pub fn set_guardians(mut self, guardians: Vec<Guardian>) -> Self {
+        self.updates.push(StudentUpdate::SetGuardians(guardians));
+        self
+    }
+
+    pub fn remove_guardian_at(mut self, index: usize) -> Self {
+        self.updates.push(StudentUpdate::RemoveGuardianAt(index));
+        self
+    }
+
+    pub fn set_custom_field(
+        mut self,
+        key: impl Into<String>,
+        value: impl Into<CustomValue>,
+    ) -> Self {
+        self.updates
+            .push(StudentUpdate::SetCustomField(key.into(), value.into()));
+        self
+    }
+
+    pub fn remove_custom_field(mut self, key: impl Into<String>) -> Self {
+        self.updates
+            .push(StudentUpdate::RemoveCustomField(key.into()));
+        self
+    }
+
+    /// 设置负责的教练，不检查教练是否存在，需要校验时使用 [`QmxManager::assign_coach`]
+    pub fn assigned_coach(mut self, coach: Option<u64>) -> Self {
+        self.updates.push(StudentUpdate::AssignedCoach(coach));
+        self
+    }
+
+    /// 设置所属校区，`None` 表示不属于任何校区
+    pub fn branch(mut self, branch_id: Option<String>) -> Self {
+        self.updates.push(StudentUpdate::Branch(branch_id));
+        self
+    }
+
+    /// 在副本上执行全部修改，全部成功后才写回数据库；任一修改失败时学生保持不变
+    fn apply(
+        self,
+        db: &mut StudentDatabase,
+        uid: u64,
+        limits: &Limits,
+        policies: &ClassPolicyTable,
+        scoring: &ScoringConfigs,
+        now: DateTime<Utc>,
+    ) -> Result<Vec<FieldChange>> {
+        let mut student = db
+            .get(&uid)
+            .cloned()
             .ok_or_else(|| Error::NotFound(format!("学生不存在: {}", uid)))?;
+        let changes = self.apply_updates(&mut student, uid, limits, policies, scoring, now)?;
+        if !changes.is_empty() {
+            db.insert(student);
+        }
+        Ok(changes)
+    }
+
+    fn apply_updates(
+        self,
+        student: &mut Student,
+        uid: u64,
+        limits: &Limits,
+        policies: &ClassPolicyTable,
+        scoring: &ScoringConfigs,
+        now: DateTime<Utc>,
+    ) -> Result<Vec<FieldChange>> {
+        let before = student.clone();
 
         for update in self.updates {
             match update {
                 StudentUpdate::Name(name) => {
+                    Limits::check_len("name", &name, limits.max_name_len)?;
                     student.set_name(name);
                 }
                 StudentUpdate::Age(age) => {
@@ -576,55 +5915,117 @@ impl StudentUpdater {
                     student.set_phone(phone);
                 }
                 StudentUpdate::Class(class) => {
-                    student.set_class_with_lesson_init(class);
+                    student.set_class_with_policy(class, policies);
                 }
                 StudentUpdate::Subject(subject) => {
                     student.set_subject(subject);
                 }
                 StudentUpdate::LessonLeft(lessons) => {
                     match lessons {
-                        Some(v) => student.set_lesson_left(v),
+                        Some(v) => student.set_lesson_left_with_policy(v, policies),
                         None => {
                             student.clear_lesson_left();
                             &mut *student
                         }
                     };
                 }
+                StudentUpdate::ConsumeLesson => {
+                    student.consume_lesson()?;
+                }
+                StudentUpdate::GrantLessons(lessons) => {
+                    student.grant_lessons_with_policy(lessons, policies)?;
+                }
                 StudentUpdate::Note(note) => {
+                    Limits::check_len("note", &note, limits.max_note_len)?;
                     student.set_note(note);
                 }
                 StudentUpdate::AddRing(score) => {
-                    student.add_ring(score);
+                    limits.check_rings(student.rings().len() + 1)?;
+                    student.add_ring_with(score, scoring)?;
                 }
                 StudentUpdate::SetRings(rings) => {
-                    student.set_rings(rings);
+                    limits.check_rings(rings.len())?;
+                    student.set_rings_with(rings, scoring)?;
                 }
                 StudentUpdate::UpdateRingAt(index, value) => {
-                    student.update_ring_at(index, value)?;
+                    student.update_ring_at_with(index, value, scoring)?;
                 }
                 StudentUpdate::RemoveRingAt(index) => {
                     student.remove_ring_at(index)?;
                 }
                 StudentUpdate::Membership(start, end) => {
+                    if let (Some(start), Some(end)) = (start, end)
+                        && start > end
+                    {
+                        return Err(Error::MembershipInvalid(format!(
+                            "开始时间 {} 晚于结束时间 {}",
+                            start.format("%Y-%m-%d"),
+                            end.format("%Y-%m-%d")
+                        )));
+                    }
                     student.set_membership_dates(start, end);
                 }
+                StudentUpdate::MembershipTier(tier) => {
+                    student.set_membership_tier(tier);
+                }
+                StudentUpdate::AddGuardian(guardian) => {
+                    Limits::check_len("guardian.name", &guardian.name, limits.max_name_len)?;
+                    student.add_guardian(guardian);
+                }
+                StudentUpdate::SetGuardians(guardians) => {
+                    for guardian in &guardians {
+                        Limits::check_len("guardian.name", &guardian.name, limits.max_name_len)?;
+                    }
+                    student.set_guardians(guardians);
+                }
+                StudentUpdate::RemoveGuardianAt(index) => {
+                    student.remove_guardian_at(index)?;
+                }
+                StudentUpdate::SetCustomField(key, value) => {
+                    limits.check_custom_field(&key, &value)?;
+                    student.set_custom_field(key, value);
+                }
+                StudentUpdate::RemoveCustomField(key) => {
+                    student.remove_custom_field(&key);
+                }
+                StudentUpdate::AssignedCoach(coach) => {
+                    student.set_assigned_coach(coach);
+                }
+                StudentUpdate::Branch(branch_id) => {
+                    if let Some(branch_id) = &branch_id {
+                        check_branch_id(branch_id, limits)?;
+                    }
+                    student.set_branch_id(branch_id);
+                }
             }
         }
 
-        Ok(())
+        // 修改时间不计入变更字段，仅在确有变化时更新
+        student.set_updated_at(before.updated_at());
+        let changes = diff_fields(&before, &*student)?;
+        if !changes.is_empty() {
+            student.set_updated_at(Some(now));
+        }
+        log_field_changes("学生", uid, &changes);
+        Ok(changes)
     }
 }
 
 /// 现金更新器
 pub struct CashUpdater {
     updates: Vec<CashUpdate>,
+    allow_dangling: bool,
 }
 
 enum CashUpdate {
     StudentId(Option<u64>),
-    Amount(i64),
+    Amount(Money),
     Note(Option<String>),
     Installment(Option<Installment>),
+    SetCustomField(String, CustomValue),
+    RemoveCustomField(String),
+    Branch(Option<String>),
+    PaymentMethod(Option<PaymentMethod>),
 }
 
 impl Default for CashUpdater {
@@ -637,7 +6038,27 @@ impl CashUpdater {
     pub fn new() -> Self {
         Self {
             updates: Vec::new(),
+            allow_dangling: false,
+        }
+    }
+
+    /// 不检查新关联的学生是否存在，见 [`CashBuilder::allow_dangling`]
+    pub fn allow_dangling(mut self) -> Self {
+        self.allow_dangling = true;
+        self
+    }
+
+    /// 检查要关联的学生是否存在
+    fn check_students(&self, students: &StudentDatabase) -> Result<()> {
+        if self.allow_dangling {
+            return Ok(());
+        }
+        for update in &self.updates {
+            if let CashUpdate::StudentId(student_id) = update {
+                check_student_exists(students, *student_id)?;
+            }
         }
+        Ok(())
     }
 
     pub fn student_id(mut self, student_id: Option<u64>) -> Self {
@@ -645,8 +6066,9 @@ impl CashUpdater {
         self
     }
 
-    pub fn amount(mut self, amount: i64) -> Self {
-        self.updates.push(CashUpdate::Amount(amount));
+    /// 修改金额，整数按人民币的分处理
+    pub fn amount(mut self, amount: impl Into<Money>) -> Self {
+        self.updates.push(CashUpdate::Amount(amount.into()));
         self
     }
 
@@ -660,27 +6082,87 @@ impl CashUpdater {
         self
     }
 
-    fn apply(self, db: &mut CashDatabase, uid: u64) -> Result<()> {
-        let cash = db
-            .cash_data
-            .get_mut(&uid)
+    pub fn set_custom_field(
+        mut self,
+        key: impl Into<String>,
+        value: impl Into<CustomValue>,
+    ) -> Self {
+        self.updates
+            .push(CashUpdate::SetCustomField(key.into(), value.into()));
+        self
+    }
+
+    pub fn remove_custom_field(mut self, key: impl Into<String>) -> Self {
+        self.updates.push(CashUpdate::RemoveCustomField(key.into()));
+        self
+    }
+
+    /// 设置所属校区，`None` 表示不属于任何校区
+    pub fn branch(mut self, branch_id: Option<String>) -> Self {
+        self.updates.push(CashUpdate::Branch(branch_id));
+        self
+    }
+
+    /// 设置支付方式，`None` 表示未记录
+    pub fn payment_method(mut self, method: Option<PaymentMethod>) -> Self {
+        self.updates.push(CashUpdate::PaymentMethod(method));
+        self
+    }
+
+    /// 在副本上执行全部修改，全部成功后才写回数据库；任一修改失败时记录保持不变
+    fn apply(self, db: &mut CashDatabase, uid: u64, limits: &Limits) -> Result<Vec<FieldChange>> {
+        let mut cash = db
+            .get(&uid)
+            .cloned()
             .ok_or_else(|| Error::NotFound(format!("现金记录不存在: {}", uid)))?;
+        let changes = self.apply_updates(&mut cash, uid, limits)?;
+        if !changes.is_empty() {
+            db.insert(cash);
+        }
+        Ok(changes)
+    }
+
+    fn apply_updates(self, cash: &mut Cash, uid: u64, limits: &Limits) -> Result<Vec<FieldChange>> {
+        let before = cash.clone();
 
         for update in self.updates {
             match update {
                 CashUpdate::StudentId(student_id) => cash.student_id = student_id,
                 CashUpdate::Amount(amount) => {
-                    if amount == 0 {
+                    if amount.is_zero() {
                         return Err(Error::InvalidInput("amount cannot be zero".to_string()));
                     }
                     cash.cash = amount;
                 }
-                CashUpdate::Note(note) => cash.note = note,
+                CashUpdate::Note(note) => {
+                    if let Some(note) = &note {
+                        Limits::check_len("note", note, limits.max_note_len)?;
+                    }
+                    cash.note = note;
+                }
                 CashUpdate::Installment(installment) => cash.installment = installment,
+                CashUpdate::SetCustomField(key, value) => {
+                    limits.check_custom_field(&key, &value)?;
+                    cash.custom_fields.insert(key, value);
+                }
+                CashUpdate::RemoveCustomField(key) => {
+                    cash.custom_fields.remove(&key);
+                }
+                CashUpdate::Branch(branch_id) => {
+                    if let Some(branch_id) = &branch_id {
+                        check_branch_id(branch_id, limits)?;
+                    }
+                    cash.branch_id = branch_id;
+                }
+                CashUpdate::PaymentMethod(method) => {
+                    cash.payment_method = method;
+                }
             }
         }
 
-        Ok(())
+        let changes = diff_fields(&before, &*cash)?;
+        log_field_changes("现金记录", uid, &changes);
+        Ok(changes)
     }
 }
 
@@ -688,9 +6170,132 @@ impl CashUpdater {
 // 查询构建器
 // ============================================================================
 
+/// 分页参数
+#[derive(Debug, Clone, Copy, Default)]
+struct Page {
+    offset: usize,
+    limit: Option<usize>,
+}
+
+impl Page {
+    /// 对匹配结果分页，只克隆当前页的记录
+    fn apply<T: Clone>(self, matched: Vec<&T>) -> SearchResult<T> {
+        let total = matched.len();
+        let items = self.window(matched).cloned().collect();
+        SearchResult {
+            items,
+            total,
+            offset: self.offset,
+            limit: self.limit,
+        }
+    }
+
+    /// 当前页的记录引用
+    fn window<T>(self, matched: Vec<&T>) -> impl Iterator<Item = &T> {
+        matched
+            .into_iter()
+            .skip(self.offset)
+            .take(self.limit.unwrap_or(usize::MAX))
+    }
+}
+
+/// 排序方向
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SortOrder {
+    #[default]
+    Ascending,
+    Descending,
+}
+
+impl SortOrder {
+    fn apply(self, ordering: std::cmp::Ordering) -> std::cmp::Ordering {
+        match self {
+            Self::Ascending => ordering,
+            Self::Descending => ordering.reverse(),
+        }
+    }
+}
+
+/// 学生排序字段
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StudentSortKey {
+    Name,
+    Age,
+    Uid,
+    MembershipEnd,
+}
+
+/// 现金记录排序字段
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CashSortKey {
+    /// 先按币种、再按同一币种内的金额排序
+    Amount,
+    CreatedAt,
+}
+
+/// 校区标识不能为空，长度限制与姓名相同
+fn check_branch_id(branch_id: &str, limits: &Limits) -> Result<()> {
+    if branch_id.trim().is_empty() {
+        return Err(Error::ValidationFailed {
+            field: "branch_id".to_string(),
+            reason: "校区标识不能为空".to_string(),
+        });
+    }
+    Limits::check_len("branch_id", branch_id, limits.max_name_len)
+}
+
+/// 未指定校区的现金记录使用关联学生的校区
+fn inherit_branch(cash: &mut Cash, students: &StudentDatabase) {
+    if cash.branch_id.is_none()
+        && let Some(student) = cash.student_id.and_then(|id| students.get(&id))
+    {
+        cash.branch_id = student.branch_id().map(str::to_string);
+    }
+}
+
+/// 自定义字段过滤：未指定期望值时只要求字段存在
+fn custom_field_matches(actual: Option<&CustomValue>, expected: Option<&CustomValue>) -> bool {
+    match (actual, expected) {
+        (Some(actual), Some(expected)) => actual == expected,
+        (Some(_), None) => true,
+        (None, _) => false,
+    }
+}
+
+/// 比较可能为空的字段，空值无论升序降序都排在最后
+fn compare_optional<T: Ord>(a: Option<T>, b: Option<T>, order: SortOrder) -> std::cmp::Ordering {
+    use std::cmp::Ordering;
+    match (a, b) {
+        (Some(a), Some(b)) => order.apply(a.cmp(&b)),
+        (Some(_), None) => Ordering::Less,
+        (None, Some(_)) => Ordering::Greater,
+        (None, None) => Ordering::Equal,
+    }
+}
+
+/// 分页查询结果
+#[derive(Debug, Clone)]
+pub struct SearchResult<T> {
+    /// 当前页的记录
+    pub items: Vec<T>,
+    /// 分页前的匹配总数
+    pub total: usize,
+    pub offset: usize,
+    pub limit: Option<usize>,
+}
+
+impl<T> SearchResult<T> {
+    /// 当前页之后是否还有更多记录
+    pub fn has_more(&self) -> bool {
+        self.offset.saturating_add(self.items.len()) < self.total
+    }
+}
+
 /// 学生查询构建器
 pub struct StudentQuery {
     filters: Vec<StudentFilter>,
+    order: Option<(StudentSortKey, SortOrder)>,
+    page: Page,
 }
 
 enum StudentFilter {
@@ -700,7 +6305,77 @@ enum StudentFilter {
     Subject(Subject),
     HasMembership(bool),
     MembershipActive(DateTime<Utc>),
+    MembershipTier(MembershipTier),
+    GuardianPhone(String),
+    PhoneEquals(String),
+    PhoneContains(String),
+    CustomField(String, Option<CustomValue>),
     ScoreRange(f64, f64),
+    CreatedBetween(DateTime<Utc>, DateTime<Utc>),
+    Coach(u64),
+    Branch(String),
+    LessonLeftBelow(u32),
+    /// 任意一组条件全部满足
+    Any(Vec<Vec<StudentFilter>>),
+    /// 这组条件不全部满足
+    Not(Vec<StudentFilter>),
+}
+
+impl StudentFilter {
+    fn matches(&self, student: &Student) -> bool {
+        match self {
+            Self::Name(name) => student.name().is_some_and(|n| n.contains(name)),
+            Self::AgeRange(min, max) => {
+                if let Some(age) = student.age() {
+                    age >= *min && age <= *max
+                } else {
+                    false // 如果年龄为空，则不匹配任何范围
+                }
+            }
+            Self::Class(class) => student.class() == class,
+            Self::Subject(subject) => student.subject() == subject,
+            Self::HasMembership(has) => student.membership_start_date().is_some() == *has,
+            Self::MembershipActive(date) => {
+                if let (Some(start), Some(end)) = (
+                    student.membership_start_date(),
+                    student.membership_end_date(),
+                ) {
+                    *date >= start && *date <= end
+                } else {
+                    false
+                }
+            }
+            Self::MembershipTier(tier) => student.membership_tier() == Some(tier),
+            Self::GuardianPhone(phone) => student.guardians().iter().any(|g| g.phone_matches(phone)),
+            Self::PhoneEquals(phone) => student.phone().is_some_and(|p| {
+                let wanted = phone_digits(phone);
+                !wanted.is_empty() && phone_digits(p) == wanted
+            }),
+            Self::PhoneContains(phone) => student.phone().is_some_and(|p| {
+                let wanted = phone_digits(phone);
+                !wanted.is_empty() && phone_digits(p).contains(&wanted)
+            }),
+            Self::CustomField(key, expected) => {
+                custom_field_matches(student.custom_field(key), expected.as_ref())
+            }
+            Self::ScoreRange(min, max) => {
+                // Check if any of the student's scores (rings) fall within the range
+                student.rings().iter().any(|&score| score >= *min && score <= *max)
+            }
+            Self::CreatedBetween(start, end) => student
+                .created_at()
+                .is_some_and(|created| created >= *start && created <= *end),
+            Self::Coach(coach) => student.assigned_coach() == Some(*coach),
+            Self::Branch(branch) => student.branch_id() == Some(branch.as_str()),
+            Self::LessonLeftBelow(lessons) => {
+                student.lesson_left().is_some_and(|left| left < *lessons)
+            }
+            Self::Any(groups) => groups
+                .iter()
+                .any(|filters| filters.iter().all(|f| f.matches(student))),
+            Self::Not(filters) => !filters.iter().all(|f| f.matches(student)),
+        }
+    }
 }
 
 impl Default for StudentQuery {
@@ -713,9 +6388,29 @@ impl StudentQuery {
     pub fn new() -> Self {
         Self {
             filters: Vec::new(),
+            order: None,
+            page: Page::default(),
         }
     }
 
+    /// 按指定字段排序，未指定时按 UID 升序返回
+    pub fn order_by(mut self, key: StudentSortKey, order: SortOrder) -> Self {
+        self.order = Some((key, order));
+        self
+    }
+
+    /// 最多返回 `limit` 条记录
+    pub fn limit(mut self, limit: usize) -> Self {
+        self.page.limit = Some(limit);
+        self
+    }
+
+    /// 跳过前 `offset` 条匹配记录
+    pub fn offset(mut self, offset: usize) -> Self {
+        self.page.offset = offset;
+        self
+    }
+
     pub fn name_contains(mut self, name: impl Into<String>) -> Self {
         self.filters.push(StudentFilter::Name(name.into()));
         self
@@ -746,60 +6441,189 @@ impl StudentQuery {
         self
     }
 
+    pub fn membership_tier(mut self, tier: MembershipTier) -> Self {
+        self.filters.push(StudentFilter::MembershipTier(tier));
+        self
+    }
+
+    /// 按监护人电话查找学生，忽略空格、连字符等非数字字符
+    pub fn guardian_phone(mut self, phone: impl Into<String>) -> Self {
+        self.filters
+            .push(StudentFilter::GuardianPhone(phone.into()));
+        self
+    }
+
+    /// 按学生本人电话精确查找，忽略空格、连字符等非数字字符
+    pub fn phone_equals(mut self, phone: impl Into<String>) -> Self {
+        self.filters.push(StudentFilter::PhoneEquals(phone.into()));
+        self
+    }
+
+    /// 按学生本人电话的一部分（如尾号）查找，忽略空格、连字符等非数字字符
+    pub fn phone_contains(mut self, phone: impl Into<String>) -> Self {
+        self.filters.push(StudentFilter::PhoneContains(phone.into()));
+        self
+    }
+
+    /// 自定义字段等于指定值
+    pub fn custom_field(mut self, key: impl Into<String>, value: impl Into<CustomValue>) -> Self {
+        self.filters
+            .push(StudentFilter::CustomField(key.into(), Some(value.into())));
+        self
+    }
+
+    /// 设置了指定自定义字段，不限取值
+    pub fn has_custom_field(mut self, key: impl Into<String>) -> Self {
+        self.filters
+            .push(StudentFilter::CustomField(key.into(), None));
+        self
+    }
+
     pub fn score_range(mut self, min: f64, max: f64) -> Self {
         self.filters.push(StudentFilter::ScoreRange(min, max));
         self
     }
 
-    fn execute(self, db: &StudentDatabase) -> Vec<Student> {
+    /// 创建时间在 `[start, end]` 区间内，未记录创建时间的学生不匹配
+    pub fn created_between(mut self, start: DateTime<Utc>, end: DateTime<Utc>) -> Self {
+        self.filters.push(StudentFilter::CreatedBetween(start, end));
+        self
+    }
+
+    /// 分配给指定教练的学生
+    pub fn coach(mut self, coach: u64) -> Self {
+        self.filters.push(StudentFilter::Coach(coach));
+        self
+    }
+
+    /// 属于指定校区的学生
+    pub fn branch(mut self, branch_id: impl Into<String>) -> Self {
+        self.filters.push(StudentFilter::Branch(branch_id.into()));
+        self
+    }
+
+    /// 剩余课时少于 `lessons` 的学生，不记录课时的学生不匹配
+    pub fn lesson_left_below(mut self, lessons: u32) -> Self {
+        self.filters.push(StudentFilter::LessonLeftBelow(lessons));
+        self
+    }
+
+    /// 满足当前全部条件或满足 `other` 全部条件的学生
+    ///
+    /// 排序和分页沿用当前查询，`other` 的排序和分页被忽略。
+    /// 之后添加的条件与组合后的结果取交集。
+    pub fn or(self, other: StudentQuery) -> Self {
+        Self {
+            filters: vec![StudentFilter::Any(vec![self.filters, other.filters])],
+            order: self.order,
+            page: self.page,
+        }
+    }
+
+    /// 排除满足 `other` 全部条件的学生，`other` 的排序和分页被忽略
+    pub fn not(mut self, other: StudentQuery) -> Self {
+        self.filters.push(StudentFilter::Not(other.filters));
+        self
+    }
+
+    fn execute(self, db: &StudentDatabase) -> SearchResult<Student> {
+        let matched = self.matched(db);
+        self.page.apply(matched)
+    }
+
+    fn count(&self, db: &StudentDatabase) -> usize {
         db.iter()
-            .filter(|(_, student)| {
-                self.filters.iter().all(|filter| match filter {
-                    StudentFilter::Name(name) => student.name().contains(name),
-                    StudentFilter::AgeRange(min, max) => {
-                        if let Some(age) = student.age() {
-                            age >= *min && age <= *max
-                        } else {
-                            false // 如果年龄为空，则不匹配任何范围
-                        }
-                    }
-                    StudentFilter::Class(class) => student.class() == class,
-                    StudentFilter::Subject(subject) => student.subject() == subject,
-                    StudentFilter::HasMembership(has) => {
-                        student.membership_start_date().is_some() == *has
-                    }
-                    StudentFilter::MembershipActive(date) => {
-                        if let (Some(start), Some(end)) = (
-                            student.membership_start_date(),
-                            student.membership_end_date(),
-                        ) {
-                            *date >= start && *date <= end
-                        } else {
-                            false
-                        }
-                    }
-                    StudentFilter::ScoreRange(min, max) => {
-                        // Check if any of the student's scores (rings) fall within the range
-                        student.rings().iter().any(|&score| score >= *min && score <= *max)
-                    }
-                })
-            })
+            .filter(|(_, student)| self.filters.iter().all(|f| f.matches(student)))
+            .count()
+    }
+
+    fn exists(&self, db: &StudentDatabase) -> bool {
+        db.iter()
+            .any(|(_, student)| self.filters.iter().all(|f| f.matches(student)))
+    }
+
+    /// 按条件筛选并排序，不克隆记录
+    fn matched<'a>(&self, db: &'a StudentDatabase) -> Vec<&'a Student> {
+        let mut matched = db
+            .iter()
+            .filter(|(_, student)| self.filters.iter().all(|f| f.matches(student)))
             .map(|(_, s)| s)
-            .cloned()
-            .collect()
+            .collect::<Vec<_>>();
+        if let Some((key, order)) = self.order {
+            matched.sort_by(|a, b| match key {
+                StudentSortKey::Name => compare_optional(a.name(), b.name(), order),
+                StudentSortKey::Age => compare_optional(a.age(), b.age(), order),
+                StudentSortKey::Uid => order.apply(a.uid().cmp(&b.uid())),
+                StudentSortKey::MembershipEnd => {
+                    compare_optional(a.membership_end_date(), b.membership_end_date(), order)
+                }
+            });
+        }
+        matched
     }
 }
 
 /// 现金查询构建器
 pub struct CashQuery {
     filters: Vec<CashFilter>,
+    order: Option<(CashSortKey, SortOrder)>,
+    page: Page,
 }
 
 enum CashFilter {
     StudentId(u64),
-    AmountRange(i64, i64),
+    AmountRange(Money, Money),
     HasInstallment(bool),
     DateRange(DateTime<Utc>, DateTime<Utc>),
+    CustomField(String, Option<CustomValue>),
+    Branch(String),
+    NoteContains(String),
+    StudentNameContains(String),
+    InstallmentStatus(InstallmentStatus),
+    PlanId(u64),
+    /// 任意一组条件全部满足
+    Any(Vec<Vec<CashFilter>>),
+    /// 这组条件不全部满足
+    Not(Vec<CashFilter>),
+}
+
+impl CashFilter {
+    fn matches(&self, cash: &Cash, students: &StudentDatabase) -> bool {
+        match self {
+            Self::StudentId(id) => cash.student_id == Some(*id),
+            Self::AmountRange(min, max) => {
+                cash.cash.currency == min.currency
+                    && cash.cash.currency == max.currency
+                    && (min.amount_minor..=max.amount_minor).contains(&cash.cash.amount_minor)
+            }
+            Self::HasInstallment(has) => cash.installment.is_some() == *has,
+            Self::DateRange(start, end) => cash.created_at >= *start && cash.created_at <= *end,
+            Self::CustomField(key, expected) => {
+                custom_field_matches(cash.custom_fields.get(key), expected.as_ref())
+            }
+            Self::Branch(branch) => cash.branch_id.as_deref() == Some(branch),
+            Self::NoteContains(text) => {
+                cash.note.as_deref().is_some_and(|note| note.contains(text.as_str()))
+            }
+            Self::StudentNameContains(name) => cash
+                .student_id
+                .and_then(|id| students.get(&id))
+                .and_then(|student| student.name())
+                .is_some_and(|n| n.contains(name.as_str())),
+            Self::InstallmentStatus(status) => cash
+                .installment
+                .as_ref()
+                .is_some_and(|i| i.status == *status),
+            Self::PlanId(plan_id) => cash
+                .installment
+                .as_ref()
+                .is_some_and(|i| i.plan_id == *plan_id),
+            Self::Any(groups) => groups
+                .iter()
+                .any(|filters| filters.iter().all(|f| f.matches(cash, students))),
+            Self::Not(filters) => !filters.iter().all(|f| f.matches(cash, students)),
+        }
+    }
 }
 
 impl Default for CashQuery {
@@ -812,16 +6636,39 @@ impl CashQuery {
     pub fn new() -> Self {
         Self {
             filters: Vec::new(),
+            order: None,
+            page: Page::default(),
         }
     }
 
+    /// 按指定字段排序，未指定时按 UID 升序返回
+    pub fn order_by(mut self, key: CashSortKey, order: SortOrder) -> Self {
+        self.order = Some((key, order));
+        self
+    }
+
+    /// 最多返回 `limit` 条记录
+    pub fn limit(mut self, limit: usize) -> Self {
+        self.page.limit = Some(limit);
+        self
+    }
+
+    /// 跳过前 `offset` 条匹配记录
+    pub fn offset(mut self, offset: usize) -> Self {
+        self.page.offset = offset;
+        self
+    }
+
     pub fn student_id(mut self, student_id: u64) -> Self {
         self.filters.push(CashFilter::StudentId(student_id));
         self
     }
 
-    pub fn amount_range(mut self, min: i64, max: i64) -> Self {
-        self.filters.push(CashFilter::AmountRange(min, max));
+    /// 金额在 `min` 与 `max` 之间（含两端）且币种与 `min` 相同的记录，整数按人民币的分处理
+    ///
+    /// `min` 与 `max` 币种不同时没有记录匹配。
+    pub fn amount_range(mut self, min: impl Into<Money>, max: impl Into<Money>) -> Self {
+        self.filters.push(CashFilter::AmountRange(min.into(), max.into()));
         self
     }
 
@@ -835,20 +6682,101 @@ impl CashQuery {
         self
     }
 
-    fn execute(self, db: &CashDatabase) -> Vec<Cash> {
+    /// 自定义字段等于指定值
+    pub fn custom_field(mut self, key: impl Into<String>, value: impl Into<CustomValue>) -> Self {
+        self.filters
+            .push(CashFilter::CustomField(key.into(), Some(value.into())));
+        self
+    }
+
+    /// 设置了指定自定义字段，不限取值
+    pub fn has_custom_field(mut self, key: impl Into<String>) -> Self {
+        self.filters.push(CashFilter::CustomField(key.into(), None));
+        self
+    }
+
+    /// 属于指定校区的现金记录
+    pub fn branch(mut self, branch_id: impl Into<String>) -> Self {
+        self.filters.push(CashFilter::Branch(branch_id.into()));
+        self
+    }
+
+    /// 备注包含 `text`，没有备注的记录不匹配
+    pub fn note_contains(mut self, text: impl Into<String>) -> Self {
+        self.filters.push(CashFilter::NoteContains(text.into()));
+        self
+    }
+
+    /// 关联学生的姓名包含 `name`，未关联学生或学生已删除的记录不匹配
+    pub fn student_name_contains(mut self, name: impl Into<String>) -> Self {
+        self.filters
+            .push(CashFilter::StudentNameContains(name.into()));
+        self
+    }
+
+    /// 分期状态为 `status` 的记录，不是分期付款的记录不匹配
+    pub fn installment_status(mut self, status: InstallmentStatus) -> Self {
+        self.filters.push(CashFilter::InstallmentStatus(status));
+        self
+    }
+
+    /// 属于指定分期计划的记录
+    pub fn plan_id(mut self, plan_id: u64) -> Self {
+        self.filters.push(CashFilter::PlanId(plan_id));
+        self
+    }
+
+    /// 满足当前全部条件或满足 `other` 全部条件的记录
+    ///
+    /// 排序和分页沿用当前查询，`other` 的排序和分页被忽略。
+    /// 之后添加的条件与组合后的结果取交集。
+    pub fn or(self, other: CashQuery) -> Self {
+        Self {
+            filters: vec![CashFilter::Any(vec![self.filters, other.filters])],
+            order: self.order,
+            page: self.page,
+        }
+    }
+
+    /// 排除满足 `other` 全部条件的记录，`other` 的排序和分页被忽略
+    pub fn not(mut self, other: CashQuery) -> Self {
+        self.filters.push(CashFilter::Not(other.filters));
+        self
+    }
+
+    fn execute(self, db: &CashDatabase, students: &StudentDatabase) -> SearchResult<Cash> {
+        let matched = self.matched(db, students);
+        self.page.apply(matched)
+    }
+
+    fn count(&self, db: &CashDatabase, students: &StudentDatabase) -> usize {
         db.iter()
-            .filter(|(_, cash)| {
-                self.filters.iter().all(|filter| match filter {
-                    CashFilter::StudentId(id) => cash.student_id == Some(*id),
-                    CashFilter::AmountRange(min, max) => cash.cash >= *min && cash.cash <= *max,
-                    CashFilter::HasInstallment(has) => cash.installment.is_some() == *has,
-                    CashFilter::DateRange(start, end) => {
-                        cash.created_at >= *start && cash.created_at <= *end
-                    }
-                })
-            })
-            .map(|(_, c)| c.clone())
-            .collect()
+            .filter(|(_, cash)| self.filters.iter().all(|f| f.matches(cash, students)))
+            .count()
+    }
+
+    fn exists(&self, db: &CashDatabase, students: &StudentDatabase) -> bool {
+        db.iter()
+            .any(|(_, cash)| self.filters.iter().all(|f| f.matches(cash, students)))
+    }
+
+    /// 按条件筛选并排序，不克隆记录
+    fn matched<'a>(&self, db: &'a CashDatabase, students: &StudentDatabase) -> Vec<&'a Cash> {
+        let mut matched = db
+            .iter()
+            .filter(|(_, cash)| self.filters.iter().all(|f| f.matches(cash, students)))
+            .map(|(_, c)| c)
+            .collect::<Vec<_>>();
+        if let Some((key, order)) = self.order {
+            matched.sort_by(|a, b| match key {
+                CashSortKey::Amount => order.apply(
+                    (a.cash.currency, a.cash.amount_minor)
+                        .cmp(&(b.cash.currency, b.cash.amount_minor)),
+                ),
+                CashSortKey::CreatedAt => order.apply(a.created_at.cmp(&b.created_at)),
+            });
+        }
+        matched
     }
 }
 
@@ -859,13 +6787,111 @@ impl CashQuery {
 /// 学生统计信息
 #[derive(Debug, Clone)]
 pub struct StudentStats {
+    /// 学生全部现金记录的金额合计，以最小货币单位表示
     pub total_payments: i64,
     pub payment_count: usize,
     pub average_score: Option<f64>,
     pub score_count: usize,
+    /// 按学生科目计分配置统计的整环成绩分布
+    pub score_histogram: Vec<usize>,
+    /// 按学生科目计分配置分组的每组总成绩
+    pub series_totals: Vec<f64>,
     pub membership_status: MembershipStatus,
 }
 
+/// 学生最近若干次成绩的趋势
+///
+/// 成绩按录入顺序视为时间顺序。
+#[derive(Debug, Clone, PartialEq)]
+pub struct ScoreTrend {
+    /// 参与计算的成绩数量，成绩不足 N 次时为全部成绩数量
+    pub count: usize,
+    /// 按时间顺序排列的移动平均，成绩数量少于窗口大小时只有一项（全部成绩的平均）
+    pub moving_average: Vec<f64>,
+    pub best: f64,
+    pub worst: f64,
+    pub mean: f64,
+    /// 总体标准差
+    pub std_dev: f64,
+    /// 最小二乘拟合的斜率，即平均每次成绩的变化量，正数表示在进步；只有一次成绩时为 0
+    pub slope: f64,
+}
+
+impl ScoreTrend {
+    /// 计算 `rings` 中最近 `last_n` 次成绩的趋势，移动平均的窗口为 `window` 次
+    ///
+    /// 没有成绩时返回 `None`；`last_n` 或 `window` 为 0 时返回错误。
+    pub fn calculate(rings: &[f64], last_n: usize, window: usize) -> Result<Option<Self>> {
+        if last_n == 0 || window == 0 {
+            return Err(Error::InvalidInput(
+                "成绩数量和移动平均窗口必须大于 0".to_string(),
+            ));
+        }
+        if rings.is_empty() {
+            return Ok(None);
+        }
+        let recent = &rings[rings.len().saturating_sub(last_n)..];
+        let count = recent.len();
+        let n = count as f64;
+
+        let mean = recent.iter().sum::<f64>() / n;
+        let variance = recent.iter().map(|r| (r - mean).powi(2)).sum::<f64>() / n;
+        let best = recent.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+        let worst = recent.iter().copied().fold(f64::INFINITY, f64::min);
+
+        // x 取 0..count，x 的均值为 (count - 1) / 2
+        let x_mean = (n - 1.0) / 2.0;
+        let (covariance, x_variance) =
+            recent
+                .iter()
+                .enumerate()
+                .fold((0.0, 0.0), |(cov, var), (i, r)| {
+                    let dx = i as f64 - x_mean;
+                    (cov + dx * (r - mean), var + dx * dx)
+                });
+        let slope = if x_variance > 0.0 {
+            covariance / x_variance
+        } else {
+            0.0
+        };
+
+        let moving_average = recent
+            .windows(window.min(count))
+            .map(|w| w.iter().sum::<f64>() / w.len() as f64)
+            .collect();
+
+        Ok(Some(Self {
+            count,
+            moving_average,
+            best,
+            worst,
+            mean,
+            std_dev: variance.sqrt(),
+            slope,
+        }))
+    }
+}
+
+/// 学生的储值余额
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StudentBalance {
+    pub uid: u64,
+    pub name: String,
+    pub balance: Money,
+}
+
+/// 学生在同一科目内按平均成绩的排名
+#[derive(Debug, Clone, PartialEq)]
+pub struct StudentRanking {
+    pub uid: u64,
+    pub name: String,
+    pub average_score: f64,
+    /// 名次，从 1 开始；平均成绩相同的学生名次相同
+    pub rank: usize,
+    /// 百分位（0–100）：同科目其他学生中平均成绩低于该学生的比例，只有一名学生时为 100
+    pub percentile: f64,
+}
+
 /// 会员状态
 #[derive(Debug, Clone)]
 pub enum MembershipStatus {
@@ -875,20 +6901,26 @@ pub enum MembershipStatus {
 }
 
 impl StudentStats {
-    fn calculate(student_db: &StudentDatabase, cash_db: &CashDatabase, uid: u64) -> Result<Self> {
+    fn calculate(
+        student_db: &StudentDatabase,
+        cash_db: &CashDatabase,
+        uid: u64,
+        scoring: &ScoringConfigs,
+        now: DateTime<Utc>,
+    ) -> Result<Self> {
         let student = student_db
             .get(&uid)
             .ok_or_else(|| Error::NotFound(format!("学生不存在: {}", uid)))?;
 
-        let cash_records: Vec<_> = cash_db
+        let cash_records = cash_db.get_by_student(uid);
+        Currency::common(cash_records.iter().map(|c| c.cash))?;
+        let total_payments = cash_records
             .iter()
-            .filter(|(_, c)| c.student_id == Some(uid))
-            .map(|(_, c)| c)
-            .collect();
-        let total_payments: i64 = cash_records.iter().map(|c| c.cash).sum();
+            .fold(0i64, |sum, c| sum.saturating_add(c.cash.amount_minor));
         let payment_count = cash_records.len();
 
         let rings = student.rings();
+        let scoring = scoring.get(student.subject());
         let average_score = if rings.is_empty() {
             None
         } else {
@@ -900,7 +6932,6 @@ impl StudentStats {
             student.membership_end_date(),
         ) {
             (Some(_start), Some(end)) => {
-                let now = Utc::now();
                 if now <= end {
                     MembershipStatus::Active { expires_at: end }
                 } else {
@@ -915,12 +6946,16 @@ impl StudentStats {
             payment_count,
             average_score,
             score_count: rings.len(),
+            score_histogram: scoring.histogram(rings),
+            series_totals: scoring.series_totals(rings),
             membership_status,
         })
     }
 }
 
 /// 财务统计信息
+///
+/// 收入与支出的计入规则与仪表盘统计一致，见 [`CashTotals`]。
 #[derive(Debug, Clone)]
 pub struct FinancialStats {
     pub total_income: i64,
@@ -943,65 +6978,96 @@ pub enum TimePeriod {
     },
 }
 
-impl FinancialStats {
-    fn calculate(cash_db: &CashDatabase, period: TimePeriod) -> Result<Self> {
-        use chrono::{Duration, Datelike};
-        
-        let (start_time, end_time) = match period {
-            TimePeriod::Today => {
-                let now = Utc::now();
-                let start = now.date_naive().and_hms_opt(0, 0, 0).unwrap().and_utc();
-                let end = now.date_naive().and_hms_opt(23, 59, 59).unwrap().and_utc();
-                (start, end)
-            }
+impl TimePeriod {
+    /// 计算时间周期在指定时区下的起止时间（闭区间，UTC 表示）
+    ///
+    /// `Today` 覆盖当天 00:00:00 至 23:59:59；`ThisWeek`/`ThisMonth`/`ThisYear`
+    /// 从周一、月初、年初的 00:00:00 开始，截止到 `now`。
+    /// 日期计算全部使用可失败的 API，遇到时区中不存在的本地时间时返回错误而不是 panic。
+    ///
+    /// # 示例
+    /// ```rust
+    /// use chrono::{TimeZone, Utc};
+    /// use qmx_backend_lib::TimePeriod;
+    ///
+    /// # fn main() -> qmx_backend_lib::error::Result<()> {
+    /// let now = Utc.with_ymd_and_hms(2024, 3, 15, 10, 30, 0).unwrap();
+    /// let (start, end) = TimePeriod::ThisMonth.bounds(now, &Utc)?;
+    /// assert_eq!(start, Utc.with_ymd_and_hms(2024, 3, 1, 0, 0, 0).unwrap());
+    /// assert_eq!(end, now);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn bounds<Tz: TimeZone>(
+        &self,
+        now: DateTime<Utc>,
+        tz: &Tz,
+    ) -> Result<(DateTime<Utc>, DateTime<Utc>)> {
+        use chrono::{Datelike, Days, NaiveDate, NaiveTime};
+
+        let today = now.with_timezone(tz).date_naive();
+        let start_date = match self {
+            TimePeriod::Custom { start, end } => return Ok((*start, *end)),
+            TimePeriod::Today => today,
             TimePeriod::ThisWeek => {
-                let now = Utc::now();
-                let days_from_monday = now.weekday().num_days_from_monday();
-                let start = (now - Duration::days(days_from_monday as i64)).date_naive().and_hms_opt(0, 0, 0).unwrap().and_utc();
-                let end = now;
-                (start, end)
-            }
-            TimePeriod::ThisMonth => {
-                let now = Utc::now();
-                let start = now.date_naive().with_day(1).unwrap().and_hms_opt(0, 0, 0).unwrap().and_utc();
-                let end = now;
-                (start, end)
-            }
-            TimePeriod::ThisYear => {
-                let now = Utc::now();
-                let start = now.date_naive().with_month(1).unwrap().with_day(1).unwrap().and_hms_opt(0, 0, 0).unwrap().and_utc();
-                let end = now;
-                (start, end)
-            }
-            TimePeriod::Custom { start, end } => (start, end),
-        };
-
-        let mut total_income: i64 = 0;
-        let mut total_expense: i64 = 0;
-        let mut transaction_count = 0;
-        let mut installment_count = 0;
-
-        for (_, cash) in cash_db.iter() {
-            if cash.created_at >= start_time && cash.created_at <= end_time {
-                transaction_count += 1;
-                if cash.cash > 0 {
-                    total_income += cash.cash;
-                } else {
-                    total_expense += cash.cash.abs();
-                }
-                if cash.installment.is_some() {
-                    installment_count += 1;
-                }
+                let days_from_monday = today.weekday().num_days_from_monday();
+                today
+                    .checked_sub_days(Days::new(days_from_monday as u64))
+                    .ok_or_else(|| Error::State(format!("无法计算 {} 所在周的起始日期", today)))?
             }
-        }
+            TimePeriod::ThisMonth => today
+                .with_day(1)
+                .ok_or_else(|| Error::State(format!("无法计算 {} 所在月的起始日期", today)))?,
+            TimePeriod::ThisYear => NaiveDate::from_ymd_opt(today.year(), 1, 1)
+                .ok_or_else(|| Error::State(format!("无法计算 {} 所在年的起始日期", today)))?,
+        };
+
+        let start = tz
+            .from_local_datetime(&start_date.and_time(NaiveTime::MIN))
+            .earliest()
+            .ok_or_else(|| {
+                Error::State(format!("本地时间 {} 00:00:00 在该时区不存在", start_date))
+            })?
+            .with_timezone(&Utc);
+
+        let end = match self {
+            TimePeriod::Today => {
+                let end_of_day = NaiveTime::from_hms_opt(23, 59, 59)
+                    .ok_or_else(|| Error::State("无法构造当天结束时间".to_string()))?;
+                tz.from_local_datetime(&today.and_time(end_of_day))
+                    .latest()
+                    .ok_or_else(|| {
+                        Error::State(format!("本地时间 {} 23:59:59 在该时区不存在", today))
+                    })?
+                    .with_timezone(&Utc)
+            }
+            _ => now,
+        };
 
-        let net_income = total_income - total_expense;
+        Ok((start, end))
+    }
+}
+
+impl FinancialStats {
+    fn calculate(cash_db: &CashDatabase, period: TimePeriod, now: DateTime<Utc>) -> Result<Self> {
+        let (start_time, end_time) = period.bounds(now, &Utc)?;
+
+        let in_period: Vec<&Cash> = cash_db
+            .iter()
+            .map(|(_, cash)| cash)
+            .filter(|cash| cash.created_at >= start_time && cash.created_at <= end_time)
+            .collect();
+        let totals = CashTotals::from_records(in_period.iter().copied())?;
+        let installment_count = in_period
+            .iter()
+            .filter(|cash| cash.installment.is_some())
+            .count();
 
         Ok(Self {
-            total_income,
-            total_expense,
-            net_income,
-            transaction_count,
+            total_income: totals.income,
+            total_expense: totals.expense,
+            net_income: totals.net(),
+            transaction_count: totals.count,
             installment_count,
         })
     }
@@ -0,0 +1,284 @@
+//! 合并两个数据库
+//!
+//! 各校区离线运行一段时间后，通过 [`crate::QmxManager::merge_from`] 把另一个数据库中的学生和
+//! 现金记录合并进来。合并按以下规则识别同一条记录：
+//!
+//! - 学生：UID 相同且姓名相同，或姓名和电话（只比较数字）都相同；
+//! - 现金：UID 相同或 UID 不同，但关联学生、金额和创建时间都相同。
+//!
+//! 识别为同一条记录且内容不同时按 [`ConflictPolicy`] 取舍；其余记录作为新记录加入，
+//! UID 已被占用时分配新的 UID，并同步改写现金记录中的学生 UID、退款原记录 UID 和分期计划 ID。
+
+use crate::audit::AuditEntity;
+use crate::cash::Cash;
+use crate::database::Database;
+use crate::money::Money;
+use crate::student::{Student, phone_digits};
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::collections::hash_map::Entry;
+use std::collections::{BTreeMap, HashMap};
+
+/// 同一条记录两边内容不同时的取舍
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConflictPolicy {
+    /// 保留已有的版本
+    KeepExisting,
+    /// 以合并进来的版本为准
+    PreferIncoming,
+    /// 保留最后修改时间较晚的学生；现金记录没有修改时间，保留已有的版本
+    PreferNewer,
+}
+
+/// 一类记录的合并统计
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct MergeStats {
+    /// 作为新记录加入的记录数（包括分配了新 UID 的）
+    pub added: usize,
+    /// 因 UID 已被占用而分配了新 UID 的记录数
+    pub remapped: usize,
+    /// 识别为已有记录的记录数
+    pub matched: usize,
+    /// 识别为已有记录并以合并进来的版本覆盖的记录数
+    pub updated: usize,
+}
+
+/// 内容不同的同一条记录
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MergeConflict {
+    pub entity: AuditEntity,
+    /// 已有记录的 UID
+    pub uid: u64,
+    /// 合并进来的记录原来的 UID
+    pub incoming_uid: u64,
+    /// 是否采用了合并进来的版本
+    pub took_incoming: bool,
+}
+
+/// 合并结果
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MergeReport {
+    pub students: MergeStats,
+    pub cash: MergeStats,
+    /// UID 发生变化的学生：原 UID 到合并后 UID
+    pub student_uids: BTreeMap<u64, u64>,
+    /// UID 发生变化的现金记录：原 UID 到合并后 UID
+    pub cash_uids: BTreeMap<u64, u64>,
+    /// 发生变化的分期计划 ID：原 ID 到合并后 ID
+    pub plan_ids: BTreeMap<u64, u64>,
+    pub conflicts: Vec<MergeConflict>,
+}
+
+/// 合并或同步时被写入的记录及其之前的内容，`None` 表示之前不存在
+pub(crate) enum MergedRecord {
+    Student(u64, Option<Student>),
+    Cash(u64, Option<Cash>),
+}
+
+/// 把 `other` 合并到 `target`
+///
+/// 调用方需保证 `next_student_uid`、`next_cash_uid` 和分期计划 ID 计数器分配的值不与两边已有的值重复。
+pub(crate) fn merge_into(
+    target: &mut Database,
+    other: Database,
+    policy: ConflictPolicy,
+    next_student_uid: &mut dyn FnMut() -> u64,
+    next_cash_uid: &mut dyn FnMut() -> u64,
+) -> (MergeReport, Vec<MergedRecord>) {
+    let mut report = MergeReport::default();
+    let mut written = Vec::new();
+
+    // 学生
+    let identities: HashMap<(String, String), u64> = target
+        .student
+        .iter()
+        .filter_map(|(&uid, student)| student_identity(student).map(|key| (key, uid)))
+        .collect();
+    let mut student_map = HashMap::new();
+    for (&incoming_uid, student) in other.student.iter() {
+        let matched = target
+            .student
+            .get(&incoming_uid)
+            .filter(|existing| existing.name() == student.name())
+            .map(|_| incoming_uid)
+            .or_else(|| student_identity(student).and_then(|key| identities.get(&key).copied()));
+        let uid = match matched {
+            Some(uid) => uid,
+            None if target.student.get(&incoming_uid).is_some() => {
+                report.students.remapped += 1;
+                next_student_uid()
+            }
+            None => incoming_uid,
+        };
+        student_map.insert(incoming_uid, uid);
+        if uid != incoming_uid {
+            report.student_uids.insert(incoming_uid, uid);
+        }
+
+        let mut student = student.clone();
+        if uid != incoming_uid {
+            // SAFETY: 新 UID 由调用方保证唯一，或为识别出的已有记录的 UID（随后整体替换该记录）
+            unsafe { student.set_id(uid) };
+        }
+        match target.student.get(&uid) {
+            Some(existing) if matched.is_some() => {
+                report.students.matched += 1;
+                if same_content(existing, &student) {
+                    continue;
+                }
+                let take = match policy {
+                    ConflictPolicy::KeepExisting => false,
+                    ConflictPolicy::PreferIncoming => true,
+                    ConflictPolicy::PreferNewer => student.updated_at() > existing.updated_at(),
+                };
+                report.conflicts.push(MergeConflict {
+                    entity: AuditEntity::Student,
+                    uid,
+                    incoming_uid,
+                    took_incoming: take,
+                });
+                if take {
+                    report.students.updated += 1;
+                    written.push(MergedRecord::Student(uid, Some(existing.clone())));
+                    target.student.insert(student);
+                }
+            }
+            _ => {
+                report.students.added += 1;
+                written.push(MergedRecord::Student(uid, None));
+                target.student.insert(student);
+            }
+        }
+    }
+
+    // 现金：先确定每条记录对应的 UID，再改写外键
+    let map_student = |id: Option<u64>| id.map(|id| student_map.get(&id).copied().unwrap_or(id));
+    let identities: HashMap<CashIdentity, u64> = target
+        .cash
+        .iter()
+        .map(|(&uid, cash)| (cash_identity(cash, cash.student_id), uid))
+        .collect();
+    let mut cash_map = HashMap::new();
+    let mut matched_cash = BTreeMap::new();
+    for (&incoming_uid, cash) in other.cash.iter() {
+        let identity = cash_identity(cash, map_student(cash.student_id));
+        let matched = target
+            .cash
+            .get(&incoming_uid)
+            .filter(|existing| cash_identity(existing, existing.student_id) == identity)
+            .map(|_| incoming_uid)
+            .or_else(|| identities.get(&identity).copied());
+        let uid = match matched {
+            Some(uid) => {
+                matched_cash.insert(incoming_uid, uid);
+                uid
+            }
+            None if target.cash.get(&incoming_uid).is_some() => {
+                report.cash.remapped += 1;
+                next_cash_uid()
+            }
+            None => incoming_uid,
+        };
+        cash_map.insert(incoming_uid, uid);
+        if uid != incoming_uid {
+            report.cash_uids.insert(incoming_uid, uid);
+        }
+    }
+
+    // 分期计划：与已有记录同属一个计划的沿用已有计划 ID，其余计划 ID 被占用时重新分配
+    let mut plan_map = HashMap::new();
+    for (incoming_uid, uid) in &matched_cash {
+        let incoming_plan = other.cash.get(incoming_uid).and_then(Cash::installment_plan_id);
+        let existing_plan = target.cash.get(uid).and_then(Cash::installment_plan_id);
+        if let (Some(incoming_plan), Some(existing_plan)) = (incoming_plan, existing_plan) {
+            plan_map.entry(incoming_plan).or_insert(existing_plan);
+            if incoming_plan != existing_plan {
+                report.plan_ids.entry(incoming_plan).or_insert(existing_plan);
+            }
+        }
+    }
+    for plan_id in other.cash.iter().filter_map(|(_, cash)| cash.installment_plan_id()) {
+        if let Entry::Vacant(entry) = plan_map.entry(plan_id) {
+            let mapped = if target.cash.get_installments_by_plan(plan_id).is_empty()
+                && target.cash.plans.get(&plan_id).is_none()
+            {
+                plan_id
+            } else {
+                crate::cash::allocate_plan_id()
+            };
+            entry.insert(mapped);
+            if mapped != plan_id {
+                report.plan_ids.insert(plan_id, mapped);
+            }
+        }
+    }
+    for (&incoming_plan, &plan_id) in &plan_map {
+        if let Some(plan) = other.cash.plans.get(&incoming_plan) {
+            let mut plan = plan.clone();
+            plan.plan_id = plan_id;
+            plan.student_id = map_student(plan.student_id);
+            target.cash.plans.insert_if_absent(plan);
+        }
+    }
+
+    for (&incoming_uid, cash) in other.cash.iter() {
+        let uid = cash_map[&incoming_uid];
+        let mut cash = cash.clone();
+        cash.uid = uid;
+        cash.student_id = map_student(cash.student_id);
+        cash.refund_of = cash
+            .refund_of
+            .map(|id| cash_map.get(&id).copied().unwrap_or(id));
+        if let Some(installment) = &mut cash.installment {
+            installment.plan_id = plan_map[&installment.plan_id];
+        }
+
+        match target.cash.get(&uid) {
+            Some(existing) if matched_cash.contains_key(&incoming_uid) => {
+                report.cash.matched += 1;
+                if same_content(existing, &cash) {
+                    continue;
+                }
+                let take = policy == ConflictPolicy::PreferIncoming;
+                report.conflicts.push(MergeConflict {
+                    entity: AuditEntity::Cash,
+                    uid,
+                    incoming_uid,
+                    took_incoming: take,
+                });
+                if take {
+                    report.cash.updated += 1;
+                    written.push(MergedRecord::Cash(uid, Some(existing.clone())));
+                    target.cash.insert(cash);
+                }
+            }
+            _ => {
+                report.cash.added += 1;
+                written.push(MergedRecord::Cash(uid, None));
+                target.cash.insert(cash);
+            }
+        }
+    }
+
+    (report, written)
+}
+
+/// 姓名和电话（只保留数字），没有姓名或电话时无法按身份识别
+fn student_identity(student: &Student) -> Option<(String, String)> {
+    let name = student.name()?;
+    let phone = phone_digits(student.phone()?);
+    (!phone.is_empty()).then(|| (name.to_string(), phone))
+}
+
+type CashIdentity = (Option<u64>, Money, DateTime<Utc>);
+
+fn cash_identity(cash: &Cash, student_id: Option<u64>) -> CashIdentity {
+    (student_id, cash.cash, cash.created_at)
+}
+
+pub(crate) fn same_content<T: Serialize>(a: &T, b: &T) -> bool {
+    match (serde_json::to_value(a), serde_json::to_value(b)) {
+        (Ok(a), Ok(b)) => a == b,
+        _ => false,
+    }
+}
@@ -0,0 +1,252 @@
+//! 金额与币种
+//!
+//! 金额统一以最小货币单位（人民币为分）的整数保存在 [`Money::amount_minor`] 中，
+//! 不再用裸 `i64` 表示，避免“元”和“分”混用。
+//!
+//! 人民币金额序列化为整数，与旧版本数据文件和 HTTP 接口保持兼容；其他币种序列化为
+//! `{ "amount_minor": 1234, "currency": "USD" }`。两种形式都可以读取，整数按人民币的分处理。
+//!
+//! 不同币种的金额不做汇率换算，也不能相加或比较；统计和筛选遇到多种币种时返回
+//! [`Error::CurrencyMismatch`]。金额为 0 时不区分币种。
+
+use crate::error::Error;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::fmt;
+use std::ops::Neg;
+
+/// 币种
+#[derive(
+    Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default,
+)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(rename_all = "UPPERCASE")]
+pub enum Currency {
+    /// 人民币（默认）
+    #[default]
+    Cny,
+    Usd,
+    Eur,
+    Hkd,
+}
+
+impl Currency {
+    /// ISO 4217 代码，如 `CNY`
+    pub fn code(&self) -> &'static str {
+        match self {
+            Self::Cny => "CNY",
+            Self::Usd => "USD",
+            Self::Eur => "EUR",
+            Self::Hkd => "HKD",
+        }
+    }
+
+    /// 显示用的货币符号
+    pub fn symbol(&self) -> &'static str {
+        match self {
+            Self::Cny => "¥",
+            Self::Usd => "$",
+            Self::Eur => "€",
+            Self::Hkd => "HK$",
+        }
+    }
+
+    /// 一个主单位包含的最小单位数，如 1 元 = 100 分
+    pub fn minor_per_major(&self) -> i64 {
+        100
+    }
+
+    /// 一组金额共同的币种，没有非零金额时为人民币
+    ///
+    /// 金额为 0 的不参与比较；出现多种币种时返回 [`Error::CurrencyMismatch`]。
+    pub fn common(amounts: impl IntoIterator<Item = Money>) -> crate::error::Result<Self> {
+        let mut common = None;
+        for amount in amounts.into_iter().filter(|amount| !amount.is_zero()) {
+            match common {
+                None => common = Some(amount.currency),
+                Some(currency) if currency != amount.currency => {
+                    return Err(Error::CurrencyMismatch {
+                        expected: currency,
+                        found: amount.currency,
+                    });
+                }
+                Some(_) => {}
+            }
+        }
+        Ok(common.unwrap_or_default())
+    }
+}
+
+/// 金额，以最小货币单位保存
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct Money {
+    /// 最小货币单位的数量，人民币为分；正数为收入，负数为支出
+    pub amount_minor: i64,
+    pub currency: Currency,
+}
+
+impl Money {
+    pub const fn new(amount_minor: i64, currency: Currency) -> Self {
+        Self {
+            amount_minor,
+            currency,
+        }
+    }
+
+    /// 以分为单位的人民币金额
+    pub const fn cny(amount_minor: i64) -> Self {
+        Self::new(amount_minor, Currency::Cny)
+    }
+
+    pub const fn zero(currency: Currency) -> Self {
+        Self::new(0, currency)
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.amount_minor == 0
+    }
+
+    pub fn is_positive(&self) -> bool {
+        self.amount_minor > 0
+    }
+
+    pub fn is_negative(&self) -> bool {
+        self.amount_minor < 0
+    }
+
+    /// 相加，币种不同或溢出时返回 `None`
+    pub fn checked_add(self, rhs: Self) -> Option<Self> {
+        if self.currency != rhs.currency {
+            return None;
+        }
+        self.amount_minor
+            .checked_add(rhs.amount_minor)
+            .map(|amount| Self::new(amount, self.currency))
+    }
+
+    /// 相减，币种不同或溢出时返回 `None`
+    pub fn checked_sub(self, rhs: Self) -> Option<Self> {
+        self.checked_add(rhs.saturating_neg())
+    }
+
+    /// 取反，`i64::MIN` 取反时饱和
+    pub fn saturating_neg(self) -> Self {
+        Self::new(self.amount_minor.saturating_neg(), self.currency)
+    }
+
+    /// 绝对值，`i64::MIN` 时饱和
+    pub fn saturating_abs(self) -> Self {
+        Self::new(self.amount_minor.saturating_abs(), self.currency)
+    }
+
+    /// 不带货币符号和千位分隔符的主单位金额，如 `-12.05`，用于表格导出
+    pub fn to_plain_string(&self) -> String {
+        let (major, minor) = self.split();
+        format!("{}{}.{:02}", self.sign(), major, minor)
+    }
+
+    fn sign(&self) -> &'static str {
+        if self.is_negative() { "-" } else { "" }
+    }
+
+    /// 绝对值的主单位和最小单位部分
+    fn split(&self) -> (u64, u64) {
+        let per_major = self.currency.minor_per_major().unsigned_abs();
+        let minor = self.amount_minor.unsigned_abs();
+        (minor / per_major, minor % per_major)
+    }
+}
+
+impl From<i64> for Money {
+    /// 整数按人民币的分处理
+    fn from(amount_minor: i64) -> Self {
+        Self::cny(amount_minor)
+    }
+}
+
+impl Neg for Money {
+    type Output = Self;
+
+    fn neg(self) -> Self {
+        self.saturating_neg()
+    }
+}
+
+impl fmt::Display for Money {
+    /// 带货币符号和千位分隔符，如 `¥1,234.50`、`-$3.00`
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (major, minor) = self.split();
+        let digits = major.to_string();
+        let mut grouped = String::with_capacity(digits.len() + digits.len() / 3);
+        for (i, c) in digits.chars().enumerate() {
+            if i > 0 && (digits.len() - i) % 3 == 0 {
+                grouped.push(',');
+            }
+            grouped.push(c);
+        }
+        write!(
+            f,
+            "{}{}{}.{:02}",
+            self.sign(),
+            self.currency.symbol(),
+            grouped,
+            minor
+        )
+    }
+}
+
+/// 非人民币金额的序列化形式
+#[derive(Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+struct StoredMoney {
+    amount_minor: i64,
+    #[serde(default)]
+    currency: Currency,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum MoneyRepr {
+    /// 旧版本数据文件中的整数金额，单位为分
+    Minor(i64),
+    Full(StoredMoney),
+}
+
+impl Serialize for Money {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self.currency {
+            Currency::Cny => serializer.serialize_i64(self.amount_minor),
+            currency => StoredMoney {
+                amount_minor: self.amount_minor,
+                currency,
+            }
+            .serialize(serializer),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for Money {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        Ok(match MoneyRepr::deserialize(deserializer)? {
+            MoneyRepr::Minor(amount_minor) => Self::cny(amount_minor),
+            MoneyRepr::Full(stored) => Self::new(stored.amount_minor, stored.currency),
+        })
+    }
+}
+
+/// 与 serde 表示一致：人民币为整数，其他币种为带币种的对象
+#[cfg(feature = "schema")]
+impl schemars::JsonSchema for Money {
+    fn schema_name() -> std::borrow::Cow<'static, str> {
+        "Money".into()
+    }
+
+    fn json_schema(generator: &mut schemars::SchemaGenerator) -> schemars::Schema {
+        schemars::json_schema!({
+            "description": "金额，人民币为以分为单位的整数，其他币种为带币种的对象",
+            "oneOf": [
+                generator.subschema_for::<i64>(),
+                StoredMoney::json_schema(generator),
+            ]
+        })
+    }
+}
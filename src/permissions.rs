@@ -0,0 +1,176 @@
+//! 基于角色的权限控制
+//!
+//! 通过 [`crate::QmxManager::with_operator`] 为管理器指定操作者后，每个修改操作执行前
+//! 都会按操作者的 [`Role`] 检查所需的 [`Capability`]，没有权限时返回
+//! [`Error::PermissionDenied`]。未指定操作者时不做任何检查，与之前的行为一致。
+//! 查询、统计和导出不受限制。
+//!
+//! | 权限 | 管理员 | 前台 | 教练 | 访客 |
+//! |------|:---:|:---:|:---:|:---:|
+//! | 创建、修改学生 | ✓ | ✓ | ✓ | |
+//! | 删除学生、清除个人信息 | ✓ | | | |
+//! | 记录现金、创建分期计划 | ✓ | ✓ | | |
+//! | 修改现金记录、退款 | ✓ | | | |
+//! | 删除现金记录 | ✓ | | | |
+//! | 管理附件 | ✓ | ✓ | | |
+//! | 管理教练 | ✓ | | | |
+//! | 管理价目表 | ✓ | | | |
+//! | 管理定期支出 | ✓ | | | |
+//! | 修改计分等设置 | ✓ | | | |
+//! | 管理课程排期 | ✓ | ✓ | ✓ | |
+//! | 备份 | ✓ | ✓ | | |
+//! | 从备份恢复、撤销 | ✓ | | | |
+//!
+//! ```rust
+//! use qmx_backend_lib::permissions::{Capability, Operator, Role};
+//!
+//! let operator = Operator::new("frontdesk-01", Role::FrontDesk);
+//! assert!(operator.role.can(Capability::RecordCash));
+//! assert!(!operator.role.can(Capability::DeleteCash));
+//! ```
+
+use crate::error::{Error, Result};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// 操作者角色
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Role {
+    /// 管理员，拥有全部权限
+    Admin,
+    /// 前台，负责学生登记和收款
+    FrontDesk,
+    /// 教练，可以修改学生信息（如记录成绩）和安排课程
+    Coach,
+    /// 访客，只能查询
+    Viewer,
+}
+
+/// 需要权限的操作类别
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Capability {
+    /// 创建、修改学生
+    ManageStudents,
+    /// 删除学生、清除学生个人信息
+    DeleteStudents,
+    /// 记录现金、创建分期计划
+    RecordCash,
+    /// 修改现金记录、退款、标记逾期分期
+    EditCash,
+    /// 删除现金记录
+    DeleteCash,
+    /// 添加、删除附件
+    ManageAttachments,
+    /// 添加、修改、删除教练
+    ManageCoaches,
+    /// 添加、修改、删除价目表中的商品
+    ManageCatalog,
+    /// 添加、修改、删除定期支出模板
+    ManageRecurring,
+    /// 修改计分配置等管理器设置
+    ManageSettings,
+    /// 创建、删除课程，报名和取消报名
+    ManageSessions,
+    /// 立即备份
+    Backup,
+    /// 从备份恢复、撤销操作
+    Restore,
+}
+
+impl Role {
+    /// 角色拥有的全部权限
+    pub fn capabilities(self) -> &'static [Capability] {
+        use Capability::*;
+        match self {
+            Self::Admin => &[
+                ManageStudents,
+                DeleteStudents,
+                RecordCash,
+                EditCash,
+                DeleteCash,
+                ManageAttachments,
+                ManageCoaches,
+                ManageCatalog,
+                ManageRecurring,
+                ManageSettings,
+                ManageSessions,
+                Backup,
+                Restore,
+            ],
+            Self::FrontDesk => &[
+                ManageStudents,
+                RecordCash,
+                ManageAttachments,
+                ManageSessions,
+                Backup,
+            ],
+            Self::Coach => &[ManageStudents, ManageSessions],
+            Self::Viewer => &[],
+        }
+    }
+
+    /// 角色是否拥有权限
+    pub fn can(self, capability: Capability) -> bool {
+        self.capabilities().contains(&capability)
+    }
+}
+
+impl fmt::Display for Role {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Admin => "管理员",
+            Self::FrontDesk => "前台",
+            Self::Coach => "教练",
+            Self::Viewer => "访客",
+        })
+    }
+}
+
+impl fmt::Display for Capability {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::ManageStudents => "创建、修改学生",
+            Self::DeleteStudents => "删除学生",
+            Self::RecordCash => "记录现金",
+            Self::EditCash => "修改现金记录",
+            Self::DeleteCash => "删除现金记录",
+            Self::ManageAttachments => "管理附件",
+            Self::ManageCoaches => "管理教练",
+            Self::ManageCatalog => "管理价目表",
+            Self::ManageRecurring => "管理定期支出",
+            Self::ManageSettings => "修改设置",
+            Self::ManageSessions => "管理课程排期",
+            Self::Backup => "备份",
+            Self::Restore => "恢复、撤销",
+        })
+    }
+}
+
+/// 执行操作的人
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Operator {
+    /// 操作者标识，同时作为审计记录中的操作者
+    pub id: String,
+    pub role: Role,
+}
+
+impl Operator {
+    pub fn new(id: impl Into<String>, role: Role) -> Self {
+        Self {
+            id: id.into(),
+            role,
+        }
+    }
+
+    /// 检查权限，没有权限时返回 [`Error::PermissionDenied`]
+    pub fn authorize(&self, capability: Capability) -> Result<()> {
+        if self.role.can(capability) {
+            Ok(())
+        } else {
+            Err(Error::PermissionDenied(format!(
+                "{}（{}）不能{}",
+                self.id, self.role, capability
+            )))
+        }
+    }
+}
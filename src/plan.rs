@@ -0,0 +1,154 @@
+//! 分期计划
+//!
+//! 计划的条款（学生、总金额、期数、付款频率、余数分配方式）以 [`PlanDatabase`] 中的计划为准，
+//! 各期现金记录通过 [`crate::cash::Installment::plan_id`] 引用所属计划。
+//! 计划数据库随现金数据库一起保存，见 [`crate::cash::CashDatabase::plans`]。
+//!
+//! 各期记录仍带有条款的副本，旧版本程序可以继续读取新的数据文件。
+//! 旧版本数据文件中没有计划数据库，加载时按各期带有的条款自动转存。
+
+use crate::cash::{PaymentFrequency, RemainderStrategy};
+use crate::common::{Database, HasUid};
+use crate::money::Money;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// 计划数据库单独保存时的默认路径，通常随现金数据库保存
+pub const PLAN_DATABASE_PATH: &str = "./data/plan_database.json";
+
+/// 分期计划
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct InstallmentPlan {
+    pub plan_id: u64,
+    /// 关联的学生 UID
+    pub student_id: Option<u64>,
+    /// 总金额，各期金额使用同一币种
+    pub total_amount: Money,
+    /// 总期数
+    pub total_installments: u32,
+    /// 付款频率
+    pub frequency: PaymentFrequency,
+    /// 第一期的到期时间，其余各期由付款频率推算
+    pub first_due: DateTime<Utc>,
+    /// 余数分配方式
+    #[serde(default)]
+    pub remainder_strategy: RemainderStrategy,
+    /// 到期后的宽限天数，`None` 时使用管理器的全局设置
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub grace_days: Option<u32>,
+}
+
+impl HasUid for InstallmentPlan {
+    fn uid(&self) -> u64 {
+        self.plan_id
+    }
+}
+
+impl InstallmentPlan {
+    /// 第 `current_installment` 期（从 1 开始）应付金额
+    pub fn amount_for(&self, current_installment: u32) -> Money {
+        Money::new(
+            self.remainder_strategy.amount_for(
+                self.total_amount.amount_minor,
+                self.total_installments,
+                current_installment,
+            ),
+            self.total_amount.currency,
+        )
+    }
+
+    /// 第 `current_installment` 期（从 1 开始）的到期时间
+    pub fn due_for(&self, current_installment: u32) -> DateTime<Utc> {
+        self.frequency
+            .nth_due(self.first_due, current_installment.saturating_sub(1))
+    }
+
+    /// 实际生效的宽限天数，计划未设置时使用 `default_days`
+    pub fn grace_days_or(&self, default_days: u32) -> u32 {
+        self.grace_days.unwrap_or(default_days)
+    }
+}
+
+/// 分期计划数据库
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct PlanDatabase {
+    pub plan_data: BTreeMap<u64, InstallmentPlan>,
+}
+
+impl Database<InstallmentPlan> for PlanDatabase {
+    fn data(&self) -> &BTreeMap<u64, InstallmentPlan> {
+        &self.plan_data
+    }
+
+    fn data_mut(&mut self) -> &mut BTreeMap<u64, InstallmentPlan> {
+        &mut self.plan_data
+    }
+
+    fn default_path(&self) -> &'static str {
+        PLAN_DATABASE_PATH
+    }
+
+    fn type_name(&self) -> &'static str {
+        "分期计划"
+    }
+
+    fn static_type_name() -> &'static str {
+        "分期计划"
+    }
+
+    fn data_field() -> &'static str {
+        "plan_data"
+    }
+
+    fn new() -> Self {
+        Self {
+            plan_data: BTreeMap::new(),
+        }
+    }
+}
+
+impl PlanDatabase {
+    pub fn new() -> Self {
+        <Self as Database<InstallmentPlan>>::new()
+    }
+
+    pub fn get(&self, plan_id: &u64) -> Option<&InstallmentPlan> {
+        <Self as Database<InstallmentPlan>>::get(self, plan_id)
+    }
+
+    pub fn insert(&mut self, plan: InstallmentPlan) {
+        <Self as Database<InstallmentPlan>>::insert(self, plan)
+    }
+
+    pub fn remove(&mut self, plan_id: &u64) -> Option<InstallmentPlan> {
+        <Self as Database<InstallmentPlan>>::remove(self, plan_id)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&u64, &InstallmentPlan)> + '_ {
+        <Self as Database<InstallmentPlan>>::iter(self)
+    }
+
+    pub fn len(&self) -> usize {
+        <Self as Database<InstallmentPlan>>::len(self)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        <Self as Database<InstallmentPlan>>::is_empty(self)
+    }
+
+    /// 插入尚不存在的计划，已存在时保留原计划，返回是否插入
+    pub fn insert_if_absent(&mut self, plan: InstallmentPlan) -> bool {
+        if self.plan_data.contains_key(&plan.plan_id) {
+            return false;
+        }
+        self.plan_data.insert(plan.plan_id, plan);
+        true
+    }
+
+    /// 数据库中最大的计划 ID
+    pub fn max_plan_id(&self) -> Option<u64> {
+        self.plan_data.last_key_value().map(|(&plan_id, _)| plan_id)
+    }
+}
@@ -0,0 +1,69 @@
+//! 个人数据导出与清除
+//!
+//! 学生或家长要求查阅或删除个人数据时，通过 [`crate::QmxManager::export_student_data`]
+//! 导出与该学生有关的全部数据，通过 [`crate::QmxManager::erase_student_data`]
+//! 清除其中可识别个人身份的信息。清除后学生和现金记录本身保留，收支汇总和各类统计不受影响。
+
+use crate::attachment::Attachment;
+use crate::audit::{AuditDatabase, AuditEntity, AuditEntry};
+use crate::cash::{Cash, CashDatabase};
+use crate::error::Result;
+use crate::schedule::Session;
+use crate::student::Student;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+
+/// 学生记录中属于个人信息的字段，清除时同时清除审计记录中这些字段的值
+pub const STUDENT_PERSONAL_FIELDS: &[&str] =
+    &["name", "age", "phone", "note", "guardians", "custom_fields"];
+
+/// 现金记录中可能包含个人信息的字段
+pub const CASH_PERSONAL_FIELDS: &[&str] = &["note", "custom_fields"];
+
+/// 一个学生的全部个人数据
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct StudentDataExport {
+    pub exported_at: DateTime<Utc>,
+    pub student: Student,
+    /// 学生的现金记录，按 UID 排列
+    pub cash: Vec<Cash>,
+    /// 学生报名的课程（出勤），按课程 UID 排列
+    pub sessions: Vec<Session>,
+    /// 附件元数据，不包含文件内容
+    pub attachments: Vec<Attachment>,
+    /// 学生及其现金记录（包括已删除的现金记录）的审计记录，按记录顺序排列
+    pub audit: Vec<AuditEntry>,
+}
+
+impl StudentDataExport {
+    /// 格式化为便于阅读的 JSON
+    pub fn to_json(&self) -> Result<String> {
+        Ok(serde_json::to_string_pretty(self)?)
+    }
+}
+
+/// 学生的全部现金记录 UID，包括审计日志中关联到该学生的已删除记录
+pub fn student_cash_uids(cash: &CashDatabase, audit: &AuditDatabase, uid: u64) -> BTreeSet<u64> {
+    let student_id = serde_json::Value::from(uid);
+    let current = cash.get_by_student(uid).into_iter().map(|c| c.uid);
+    let historical = audit
+        .iter()
+        .filter(|(_, entry)| {
+            entry.entity == AuditEntity::Cash
+                && entry.changes.iter().any(|change| {
+                    change.field == "student_id"
+                        && (change.old == student_id || change.new == student_id)
+                })
+        })
+        .map(|(_, entry)| entry.entity_uid);
+    current.chain(historical).collect()
+}
+
+/// 审计记录是否属于该学生或其现金记录
+pub fn concerns_student(entry: &AuditEntry, uid: u64, cash_uids: &BTreeSet<u64>) -> bool {
+    match entry.entity {
+        AuditEntity::Student => entry.entity_uid == uid,
+        AuditEntity::Cash => cash_uids.contains(&entry.entity_uid),
+    }
+}
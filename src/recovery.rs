@@ -0,0 +1,144 @@
+//! 数据文件损坏时的自动恢复
+//!
+//! 每次原子保存成功后，数据文件的内容同时写入 `<path>.bak`。[`crate::database::init`]
+//! 加载时遇到损坏的数据文件（无法解析、校验失败、解密失败），依次尝试 `.bak` 副本和
+//! 备份目录中由新到旧的备份，找到可加载的版本后把损坏的文件隔离为
+//! `<path>.corrupt-<时间>`，用恢复的数据覆盖原文件，并通过 [`RecoveryReport`] 说明恢复情况。
+//!
+//! 所有来源都无法加载时不做任何改动，返回原来的错误。
+
+use crate::backup::list_backups;
+use crate::common::{Database, FileCodec};
+use crate::error::{Error, Result};
+use chrono::Utc;
+use log::{info, warn};
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+/// 数据文件副本的扩展名
+pub const BAK_EXT: &str = "bak";
+
+/// 数据文件对应的副本路径
+pub fn bak_path(path: &str) -> String {
+    format!("{}.{}", path, BAK_EXT)
+}
+
+/// 恢复数据的来源
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RecoverySource {
+    /// 上次成功保存时写入的 `.bak` 副本
+    Bak(PathBuf),
+    /// 备份目录中的一份备份
+    Backup(PathBuf),
+}
+
+/// 一个数据文件的恢复结果
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RecoveryReport {
+    /// 损坏的数据文件
+    pub path: String,
+    /// 加载失败的原因
+    pub error: String,
+    /// 损坏文件被移到的位置
+    pub quarantine_path: String,
+    pub source: RecoverySource,
+    /// 恢复的记录数
+    pub recovered: usize,
+}
+
+/// 错误是否说明文件内容已损坏（而不是文件不存在、没有权限等）
+pub fn is_corruption(error: &Error) -> bool {
+    match error {
+        Error::Corrupted { .. }
+        | Error::SerdeJson(_)
+        | Error::InvalidInput(_)
+        | Error::Encryption(_) => true,
+        Error::Io(e) => matches!(
+            e.kind(),
+            std::io::ErrorKind::InvalidData | std::io::ErrorKind::UnexpectedEof
+        ),
+        _ => false,
+    }
+}
+
+/// 原子写入数据文件的副本，失败只记录警告，不影响已经完成的保存
+pub(crate) fn write_bak(path: &str, bytes: &[u8]) {
+    let bak = bak_path(path);
+    let write = || -> Result<()> {
+        let dir = Path::new(path)
+            .parent()
+            .filter(|dir| !dir.as_os_str().is_empty())
+            .unwrap_or(Path::new("."));
+        let mut tmpfile = tempfile::NamedTempFile::new_in(dir)?;
+        tmpfile.write_all(bytes)?;
+        tmpfile.as_file().sync_all()?;
+        crate::checksum::record(&bak, bytes)?;
+        tmpfile
+            .persist(&bak)
+            .map_err(|e| Error::Other(format!("写入副本失败: {}", e.error)))?;
+        Ok(())
+    };
+    if let Err(e) = write() {
+        warn!("写入数据文件副本 {} 失败: {}", bak, e);
+    }
+}
+
+/// 从 `.bak` 副本或 `backup_dir` 中的备份恢复损坏的数据文件
+///
+/// `file_name` 为数据文件在备份目录中的文件名。
+pub(crate) fn recover<D, T>(
+    path: &str,
+    backup_dir: &Path,
+    file_name: &str,
+    codec: &FileCodec,
+    error: &Error,
+) -> Result<Option<(D, RecoveryReport)>>
+where
+    D: Database<T> + Serialize + DeserializeOwned,
+    T: Serialize + DeserializeOwned + Clone,
+{
+    let mut candidates = vec![RecoverySource::Bak(PathBuf::from(bak_path(path)))];
+    candidates.extend(
+        list_backups(backup_dir)?
+            .into_iter()
+            .map(|backup| RecoverySource::Backup(backup.path.join(file_name))),
+    );
+
+    for source in candidates {
+        let (RecoverySource::Bak(candidate) | RecoverySource::Backup(candidate)) = &source;
+        if !candidate.is_file() {
+            continue;
+        }
+        let db = match D::read_from_with(&candidate.to_string_lossy(), codec) {
+            Ok(db) => db,
+            Err(e) => {
+                warn!("无法从 {} 恢复: {}", candidate.display(), e);
+                continue;
+            }
+        };
+
+        let quarantine_path = format!("{}.corrupt-{}", path, Utc::now().format("%Y%m%d-%H%M%S"));
+        std::fs::rename(path, &quarantine_path)?;
+        db.save_to_with(path, codec)?;
+        warn!(
+            "数据文件 {} 已损坏（{}），已隔离到 {}，并从 {} 恢复 {} 条记录",
+            path,
+            error,
+            quarantine_path,
+            candidate.display(),
+            db.len()
+        );
+        let report = RecoveryReport {
+            path: path.to_string(),
+            error: error.to_string(),
+            quarantine_path,
+            recovered: db.len(),
+            source,
+        };
+        return Ok(Some((db, report)));
+    }
+    info!("没有找到可用于恢复 {} 的副本或备份", path);
+    Ok(None)
+}
@@ -0,0 +1,176 @@
+//! 定期支出
+//!
+//! 房租、工资等每月固定发生的支出保存为 [`RecurringExpense`] 模板，模板保存在独立的
+//! [`RecurringDatabase`] 中。[`crate::QmxManager::process_recurring_expenses`] 为每个已到付款日的
+//! 月份生成一条支出记录，记录带有 [`RecurringOccurrence`] 标记，同一模板的同一月份只生成一次。
+
+use crate::common::{Database, HasUid};
+use crate::error::{Error, Result};
+use crate::money::Money;
+use chrono::{DateTime, Datelike, Months, NaiveDate, Utc};
+use log::debug;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// 定期支出模板的默认保存路径
+pub const RECURRING_DATABASE_PATH: &str = "./data/recurring_database.json";
+
+/// 定期支出模板
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct RecurringExpense {
+    /// 模板序号，按添加顺序递增
+    pub uid: u64,
+    /// 名称，作为生成记录的备注
+    pub name: String,
+    /// 每期支出金额，为正数；生成的现金记录金额为其相反数
+    pub amount: Money,
+    /// 支出类别，如房租、工资
+    pub category: String,
+    /// 每月付款日（1-31），当月没有这一天时取当月最后一天
+    pub day_of_month: u32,
+    /// 生效时间，早于该时间的付款日不生成记录
+    pub start: DateTime<Utc>,
+}
+
+impl HasUid for RecurringExpense {
+    fn uid(&self) -> u64 {
+        self.uid
+    }
+}
+
+impl RecurringExpense {
+    /// `period` 所在月份的付款时间（UTC 零点）
+    pub fn due_in(&self, period: NaiveDate) -> Option<DateTime<Utc>> {
+        let first = period.with_day(1)?;
+        let last_day = first.checked_add_months(Months::new(1))?.pred_opt()?.day();
+        first
+            .with_day(self.day_of_month.clamp(1, last_day))?
+            .and_hms_opt(0, 0, 0)
+            .map(|due| due.and_utc())
+    }
+
+    /// 从生效时间到 `now` 为止已到付款日的各期，元素为（月份第一天，付款时间），按时间排列
+    pub fn due_periods(&self, now: DateTime<Utc>) -> Vec<(NaiveDate, DateTime<Utc>)> {
+        let mut periods = Vec::new();
+        let Some(mut period) = self.start.date_naive().with_day(1) else {
+            return periods;
+        };
+        while let Some(due) = self.due_in(period) {
+            if due > now {
+                break;
+            }
+            if due >= self.start {
+                periods.push((period, due));
+            }
+            let Some(next) = period.checked_add_months(Months::new(1)) else {
+                break;
+            };
+            period = next;
+        }
+        periods
+    }
+}
+
+/// 由定期支出模板生成的现金记录的标记，保存在 [`crate::cash::Cash::recurring`] 中
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct RecurringOccurrence {
+    /// 模板序号
+    pub template_id: u64,
+    /// 所属月份的第一天
+    pub period: NaiveDate,
+}
+
+/// 定期支出模板数据库
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct RecurringDatabase {
+    pub recurring_data: BTreeMap<u64, RecurringExpense>,
+}
+
+impl Database<RecurringExpense> for RecurringDatabase {
+    fn data(&self) -> &BTreeMap<u64, RecurringExpense> {
+        &self.recurring_data
+    }
+
+    fn data_mut(&mut self) -> &mut BTreeMap<u64, RecurringExpense> {
+        &mut self.recurring_data
+    }
+
+    fn default_path(&self) -> &'static str {
+        RECURRING_DATABASE_PATH
+    }
+
+    fn type_name(&self) -> &'static str {
+        "定期支出"
+    }
+
+    fn static_type_name() -> &'static str {
+        "定期支出"
+    }
+
+    fn data_field() -> &'static str {
+        "recurring_data"
+    }
+
+    fn new() -> Self {
+        Self {
+            recurring_data: BTreeMap::new(),
+        }
+    }
+}
+
+impl RecurringDatabase {
+    pub fn new() -> Self {
+        <Self as Database<RecurringExpense>>::new()
+    }
+
+    pub fn get(&self, uid: &u64) -> Option<&RecurringExpense> {
+        <Self as Database<RecurringExpense>>::get(self, uid)
+    }
+
+    pub fn insert(&mut self, expense: RecurringExpense) {
+        <Self as Database<RecurringExpense>>::insert(self, expense)
+    }
+
+    pub fn remove(&mut self, uid: &u64) -> Option<RecurringExpense> {
+        <Self as Database<RecurringExpense>>::remove(self, uid)
+    }
+
+    pub fn save_to(&self, path: &str) -> Result<()> {
+        <Self as Database<RecurringExpense>>::save_to(self, path)
+    }
+
+    pub fn read_from(path: &str) -> Result<Self> {
+        <Self as Database<RecurringExpense>>::read_from(path)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&u64, &RecurringExpense)> + '_ {
+        <Self as Database<RecurringExpense>>::iter(self)
+    }
+
+    pub fn len(&self) -> usize {
+        <Self as Database<RecurringExpense>>::len(self)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        <Self as Database<RecurringExpense>>::is_empty(self)
+    }
+
+    /// 从指定路径加载模板，文件不存在时返回空数据库
+    pub fn load_or_new(path: &str) -> Result<Self> {
+        match Self::read_from(path) {
+            Err(Error::Io(e)) if e.kind() == std::io::ErrorKind::NotFound => {
+                debug!("未找到定期支出文件 {}，使用空数据库", path);
+                Ok(Self::new())
+            }
+            other => other,
+        }
+    }
+
+    /// 下一个模板序号
+    pub fn next_uid(&self) -> u64 {
+        self.recurring_data
+            .last_key_value()
+            .map_or(1, |(&last, _)| last + 1)
+    }
+}
@@ -6,7 +6,7 @@ use log::{debug, info};
 /// 保存数据库并更新 UID 计数器
 ///
 /// 该函数负责：
-/// 1. 保存学生和现金模块的 UID 计数器到文件
+/// 1. 保存学生和现金模块的 UID 计数器及分期计划 ID 计数器到文件
 /// 2. 保存数据库到磁盘
 ///
 /// 成功时返回 Ok(())，失败时返回错误信息
@@ -17,6 +17,7 @@ pub fn save(database: Database) -> Result<()> {
     student::save_uid()?;
 
     cash::save_uid()?;
+    cash::save_plan_id()?;
 
     // 保存数据库内容
     database.save()?;
@@ -0,0 +1,177 @@
+//! 课程排期
+//!
+//! 每节课程是一个 [`Session`]，记录上课时间、科目、执教教练、容量和已报名的学生，
+//! 保存在独立的 [`SessionDatabase`] 中。课程通过 [`crate::QmxManager::create_session`]
+//! 创建，学生通过 [`crate::QmxManager::enroll`] 报名。
+
+use crate::common::{Database, HasUid};
+use crate::error::{Error, Result};
+use crate::student::Subject;
+use chrono::{DateTime, NaiveDate, Utc};
+use log::debug;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// 课程排期数据库的默认保存路径
+pub const SESSION_DATABASE_PATH: &str = "./data/session_database.json";
+
+/// 一节课程
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Session {
+    /// 课程序号，按创建顺序递增
+    pub uid: u64,
+    pub start: DateTime<Utc>,
+    pub end: DateTime<Utc>,
+    pub subject: Subject,
+    /// 执教教练 UID，见 [`crate::coach::Coach`]
+    pub coach_id: Option<u64>,
+    /// 最多可报名的学生数
+    pub capacity: u32,
+    /// 已报名学生的 UID，按报名顺序排列
+    pub enrolled: Vec<u64>,
+}
+
+impl HasUid for Session {
+    fn uid(&self) -> u64 {
+        self.uid
+    }
+}
+
+impl Session {
+    pub fn is_enrolled(&self, student_uid: u64) -> bool {
+        self.enrolled.contains(&student_uid)
+    }
+
+    /// 剩余名额
+    pub fn remaining_seats(&self) -> u32 {
+        self.capacity.saturating_sub(self.enrolled.len() as u32)
+    }
+
+    pub fn is_full(&self) -> bool {
+        self.remaining_seats() == 0
+    }
+}
+
+/// 课程排期数据库
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct SessionDatabase {
+    pub session_data: BTreeMap<u64, Session>,
+}
+
+impl Default for SessionDatabase {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Database<Session> for SessionDatabase {
+    fn data(&self) -> &BTreeMap<u64, Session> {
+        &self.session_data
+    }
+
+    fn data_mut(&mut self) -> &mut BTreeMap<u64, Session> {
+        &mut self.session_data
+    }
+
+    fn default_path(&self) -> &'static str {
+        SESSION_DATABASE_PATH
+    }
+
+    fn type_name(&self) -> &'static str {
+        "课程"
+    }
+
+    fn static_type_name() -> &'static str {
+        "课程"
+    }
+
+    fn data_field() -> &'static str {
+        "session_data"
+    }
+
+    fn new() -> Self {
+        Self {
+            session_data: BTreeMap::new(),
+        }
+    }
+}
+
+impl SessionDatabase {
+    pub fn new() -> Self {
+        <Self as Database<Session>>::new()
+    }
+
+    pub fn get(&self, uid: &u64) -> Option<&Session> {
+        <Self as Database<Session>>::get(self, uid)
+    }
+
+    pub fn insert(&mut self, session: Session) {
+        <Self as Database<Session>>::insert(self, session)
+    }
+
+    pub fn remove(&mut self, uid: &u64) -> Option<Session> {
+        <Self as Database<Session>>::remove(self, uid)
+    }
+
+    pub fn save_to(&self, path: &str) -> Result<()> {
+        <Self as Database<Session>>::save_to(self, path)
+    }
+
+    pub fn read_from(path: &str) -> Result<Self> {
+        <Self as Database<Session>>::read_from(path)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&u64, &Session)> + '_ {
+        <Self as Database<Session>>::iter(self)
+    }
+
+    pub fn len(&self) -> usize {
+        <Self as Database<Session>>::len(self)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        <Self as Database<Session>>::is_empty(self)
+    }
+
+    /// 从指定路径加载课程排期，文件不存在时返回空数据库
+    pub fn load_or_new(path: &str) -> Result<Self> {
+        match Self::read_from(path) {
+            Err(Error::Io(e)) if e.kind() == std::io::ErrorKind::NotFound => {
+                debug!("未找到课程排期文件 {}，使用空数据库", path);
+                Ok(Self::new())
+            }
+            other => other,
+        }
+    }
+
+    /// 下一个课程序号
+    pub fn next_uid(&self) -> u64 {
+        self.session_data
+            .last_key_value()
+            .map_or(1, |(&last, _)| last + 1)
+    }
+
+    /// 在指定日期（UTC）开始的课程，按开始时间排列
+    pub fn on_date(&self, date: NaiveDate) -> Vec<&Session> {
+        sorted_by_start(
+            self.session_data
+                .values()
+                .filter(|session| session.start.date_naive() == date),
+        )
+    }
+
+    /// 学生已报名且在 `now` 之后开始的课程，按开始时间排列
+    pub fn upcoming_for_student(&self, student_uid: u64, now: DateTime<Utc>) -> Vec<&Session> {
+        sorted_by_start(
+            self.session_data
+                .values()
+                .filter(|session| session.start >= now && session.is_enrolled(student_uid)),
+        )
+    }
+}
+
+fn sorted_by_start<'a>(sessions: impl Iterator<Item = &'a Session>) -> Vec<&'a Session> {
+    let mut sessions: Vec<&Session> = sessions.collect();
+    sessions.sort_by_key(|session| (session.start, session.uid));
+    sessions
+}
@@ -0,0 +1,69 @@
+//! JSON Schema 导出
+//!
+//! 启用 `schema` feature 后对外序列化的数据类型派生 [`schemars::JsonSchema`]，
+//! 导出 JSON Schema（draft 2020-12），前端可以用 `json-schema-to-typescript` 等工具
+//! 据此生成类型定义，而不必手写。
+//!
+//! Schema 由 `schemars` 根据类型定义和 serde 属性生成，与序列化结果保持一致：
+//!
+//! - `required` 只列出反序列化时必须提供的字段，`Option` 字段和带默认值的字段可以省略；
+//! - 时间为 RFC 3339 字符串，人民币金额为以分为单位的整数；
+//! - 嵌套类型放在 `$defs` 中，通过 `$ref` 引用。
+//!
+//! ```no_run
+//! # fn main() -> qmx_backend_lib::error::Result<()> {
+//! let count = qmx_backend_lib::schema::write_schemas("./schemas")?;
+//! println!("导出 {} 个 Schema", count);
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::cash::{Cash, Installment};
+use crate::error::Result;
+use crate::plan::InstallmentPlan;
+use crate::stats::DashboardStats;
+use crate::student::Student;
+use log::info;
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::path::Path;
+
+pub use schemars::JsonSchema;
+
+/// Schema 方言
+pub const SCHEMA_DIALECT: &str = "https://json-schema.org/draft/2020-12/schema";
+
+/// 类型 `T` 的根 Schema，包含其引用的全部 `$defs`，`title` 为类型名称
+pub fn schema_for<T: JsonSchema>() -> Value {
+    schemars::schema_for!(T).into()
+}
+
+/// 导出全部公开数据类型的 JSON Schema，键为 Schema 名称
+pub fn export_schemas() -> BTreeMap<String, Value> {
+    fn entry<T: JsonSchema>() -> (String, Value) {
+        (T::schema_name().into_owned(), schema_for::<T>())
+    }
+
+    BTreeMap::from([
+        entry::<Student>(),
+        entry::<Cash>(),
+        entry::<Installment>(),
+        entry::<InstallmentPlan>(),
+        entry::<DashboardStats>(),
+    ])
+}
+
+/// 把全部 Schema 写入目录，每个类型一个 `<名称>.schema.json` 文件，返回写入的文件数
+///
+/// 目录不存在时会自动创建，已存在的同名文件会被覆盖。
+pub fn write_schemas(dir: impl AsRef<Path>) -> Result<usize> {
+    let dir = dir.as_ref();
+    std::fs::create_dir_all(dir)?;
+    let schemas = export_schemas();
+    for (name, schema) in &schemas {
+        let path = dir.join(format!("{}.schema.json", name));
+        std::fs::write(&path, serde_json::to_string_pretty(schema)?)?;
+    }
+    info!("导出 {} 个 JSON Schema 到 {}", schemas.len(), dir.display());
+    Ok(schemas.len())
+}
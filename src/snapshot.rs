@@ -0,0 +1,90 @@
+//! 内存快照
+//!
+//! 批量修改、导入等有风险的操作之前，用 [`crate::QmxManager::snapshot`] 记下学生和现金数据库的
+//! 当前状态，出错时用 [`crate::QmxManager::restore_snapshot`] 回到该状态。
+//!
+//! 快照只保存在内存中，不写文件、不清理旧备份，比 [`crate::QmxManager::backup_now`] 轻量得多，
+//! 但程序退出后即丢失，需要长期保存的数据请使用备份。快照创建后不可修改，
+//! 恢复快照算作一次修改操作，可以用 [`crate::QmxManager::undo_last`] 撤销。
+
+use crate::database::Database;
+use crate::merge::{MergedRecord, same_content};
+use chrono::{DateTime, Utc};
+
+/// 快照说明
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SnapshotInfo {
+    pub label: String,
+    pub created_at: DateTime<Utc>,
+    /// 快照中的学生数
+    pub students: usize,
+    /// 快照中的现金记录数
+    pub cash: usize,
+}
+
+/// 一份快照
+pub(crate) struct Snapshot {
+    pub info: SnapshotInfo,
+    pub data: Database,
+}
+
+impl Snapshot {
+    pub fn new(label: String, created_at: DateTime<Utc>, data: Database) -> Self {
+        Self {
+            info: SnapshotInfo {
+                label,
+                created_at,
+                students: data.student.len(),
+                cash: data.cash.len(),
+            },
+            data,
+        }
+    }
+
+    /// 把 `target` 恢复为快照中的状态，返回被写入或删除的记录及其之前的内容
+    pub fn restore_into(&self, target: &mut Database) -> Vec<MergedRecord> {
+        let mut written = Vec::new();
+
+        let removed: Vec<u64> = target
+            .student
+            .iter()
+            .map(|(&uid, _)| uid)
+            .filter(|uid| self.data.student.get(uid).is_none())
+            .collect();
+        for uid in removed {
+            written.push(MergedRecord::Student(uid, target.student.remove(&uid)));
+        }
+        for (&uid, student) in self.data.student.iter() {
+            let previous = target.student.get(&uid).cloned();
+            if previous.as_ref().is_some_and(|current| same_content(current, student)) {
+                continue;
+            }
+            target.student.insert(student.clone());
+            written.push(MergedRecord::Student(uid, previous));
+        }
+
+        let removed: Vec<u64> = target
+            .cash
+            .iter()
+            .map(|(&uid, _)| uid)
+            .filter(|uid| self.data.cash.get(uid).is_none())
+            .collect();
+        for uid in removed {
+            written.push(MergedRecord::Cash(uid, target.cash.remove(&uid)));
+        }
+        for (&uid, cash) in self.data.cash.iter() {
+            let previous = target.cash.get(&uid).cloned();
+            if previous.as_ref().is_some_and(|current| same_content(current, cash)) {
+                continue;
+            }
+            target.cash.insert(cash.clone());
+            written.push(MergedRecord::Cash(uid, previous));
+        }
+        // 计划只增不删，补回目标中缺少的计划即可
+        for (_, plan) in self.data.cash.plans.iter() {
+            target.cash.plans.insert_if_absent(plan.clone());
+        }
+
+        written
+    }
+}
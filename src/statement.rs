@@ -0,0 +1,303 @@
+//! 学生对账单
+//!
+//! 汇总学生在一段时间内的收付款、储值扣费、课时变化和会员期变化，按时间排列并附上每一步之后的
+//! 储值余额，可渲染为纯文本或 HTML 发给家长。收付款来自现金记录，课时和会员期的变化来自审计日志，
+//! 未记录审计日志的修改不会出现在对账单中。通过 [`crate::QmxManager::get_student_statement`] 生成。
+
+use crate::audit::{AuditAction, AuditEntity, AuditEntry};
+use crate::cash::{Cash, CashDatabase};
+use crate::invoice::escape_html;
+use crate::money::Money;
+use crate::student::Student;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+/// 对账单中一条记录的内容
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub enum StatementItem {
+    /// 收款、退款、储值充值或扣费
+    Transaction {
+        cash_uid: u64,
+        /// 实际收支金额，储值扣费为 0
+        amount: Money,
+        /// 对储值余额的影响
+        wallet: Option<Money>,
+    },
+    /// 消耗课时
+    LessonsConsumed {
+        count: u32,
+        lesson_left: Option<u32>,
+    },
+    /// 增加课时
+    LessonsAdded {
+        count: u32,
+        lesson_left: Option<u32>,
+    },
+    /// 会员到期时间变更，`end` 为变更后的到期时间
+    MembershipChanged { end: Option<DateTime<Utc>> },
+}
+
+/// 对账单中的一条记录
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct StatementEntry {
+    pub at: DateTime<Utc>,
+    pub description: String,
+    pub item: StatementItem,
+    /// 本条记录之后的储值余额
+    pub balance: Money,
+}
+
+impl StatementEntry {
+    /// 金额或变化的说明，如 `金额 ¥500.00`、`消耗 1 节，剩余 9 节`
+    pub fn detail(&self) -> String {
+        match &self.item {
+            StatementItem::Transaction { amount, wallet, .. } => {
+                let mut parts = Vec::new();
+                if !amount.is_zero() {
+                    parts.push(format!("金额 {}", amount));
+                }
+                if let Some(wallet) = wallet {
+                    parts.push(format!("储值 {}", wallet));
+                }
+                parts.join("，")
+            }
+            StatementItem::LessonsConsumed { count, lesson_left } => {
+                format!("消耗 {} 节，剩余 {}", count, format_lessons(*lesson_left))
+            }
+            StatementItem::LessonsAdded { count, lesson_left } => {
+                format!("增加 {} 节，剩余 {}", count, format_lessons(*lesson_left))
+            }
+            StatementItem::MembershipChanged { end } => {
+                format!("会员到期 {}", format_date(*end))
+            }
+        }
+    }
+}
+
+/// 学生对账单
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct StudentStatement {
+    pub student_id: u64,
+    pub student_name: String,
+    /// 对账期间（闭区间）
+    pub start: DateTime<Utc>,
+    pub end: DateTime<Utc>,
+    /// 期初储值余额
+    pub opening_balance: Money,
+    /// 期末储值余额
+    pub closing_balance: Money,
+    /// 按时间排列的记录
+    pub entries: Vec<StatementEntry>,
+}
+
+impl StudentStatement {
+    /// 用学生的现金记录和审计日志生成 `start` 至 `end` 的对账单
+    ///
+    /// `audit` 中与该学生无关的记录会被忽略。
+    pub fn build<'a>(
+        student: &Student,
+        cash: &CashDatabase,
+        audit: impl IntoIterator<Item = &'a AuditEntry>,
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+    ) -> Self {
+        let student_id = student.uid();
+        let records = cash.get_by_student(student_id);
+        let opening_balance = records
+            .iter()
+            .filter(|c| c.created_at < start)
+            .filter_map(|c| c.wallet)
+            .fold(
+                Money::zero(cash.wallet_balance(student_id).currency),
+                add_wallet,
+            );
+
+        let mut entries: Vec<(DateTime<Utc>, String, StatementItem)> = records
+            .iter()
+            .filter(|c| c.created_at >= start && c.created_at <= end)
+            .map(|c| {
+                (
+                    c.created_at,
+                    describe_cash(c),
+                    StatementItem::Transaction {
+                        cash_uid: c.uid,
+                        amount: c.cash,
+                        wallet: c.wallet,
+                    },
+                )
+            })
+            .collect();
+        for entry in audit.into_iter().filter(|entry| {
+            entry.entity == AuditEntity::Student
+                && entry.entity_uid == student_id
+                && entry.action != AuditAction::Delete
+                && entry.timestamp >= start
+                && entry.timestamp <= end
+        }) {
+            entries.extend(
+                student_items(entry)
+                    .into_iter()
+                    .map(|(description, item)| (entry.timestamp, description, item)),
+            );
+        }
+        // 同一时刻的收付款排在课时和会员期变化之前
+        entries.sort_by_key(|(at, _, _)| *at);
+
+        let mut balance = opening_balance;
+        let entries = entries
+            .into_iter()
+            .map(|(at, description, item)| {
+                if let StatementItem::Transaction {
+                    wallet: Some(delta),
+                    ..
+                } = &item
+                {
+                    balance = add_wallet(balance, *delta);
+                }
+                StatementEntry {
+                    at,
+                    description,
+                    item,
+                    balance,
+                }
+            })
+            .collect();
+
+        Self {
+            student_id,
+            student_name: student.name().unwrap_or_default().to_string(),
+            start,
+            end,
+            opening_balance,
+            closing_balance: balance,
+            entries,
+        }
+    }
+
+    fn period(&self) -> String {
+        format!(
+            "{} 至 {}",
+            self.start.format("%Y-%m-%d"),
+            self.end.format("%Y-%m-%d")
+        )
+    }
+
+    /// 渲染为纯文本
+    pub fn render_text(&self) -> String {
+        let mut out = format!(
+            "学生对账单\n学生: {}\n期间: {}\n期初余额: {}\n",
+            self.student_name,
+            self.period(),
+            self.opening_balance
+        );
+        for entry in &self.entries {
+            out.push_str(&format!(
+                "{}  {}  {}  余额 {}\n",
+                entry.at.format("%Y-%m-%d"),
+                entry.description,
+                entry.detail(),
+                entry.balance
+            ));
+        }
+        out.push_str(&format!("期末余额: {}\n", self.closing_balance));
+        out
+    }
+
+    /// 渲染为 HTML 片段，所有内容都会转义
+    pub fn render_html(&self) -> String {
+        let mut out = format!(
+            "<div class=\"statement\">\n<h1>学生对账单</h1>\n<p>学生: {}</p>\n<p>期间: {}</p>\n\
+             <p>期初余额: {}</p>\n<table>\n\
+             <tr><th>日期</th><th>项目</th><th>明细</th><th>余额</th></tr>\n",
+            escape_html(&self.student_name),
+            self.period(),
+            escape_html(&self.opening_balance.to_string())
+        );
+        for entry in &self.entries {
+            out.push_str(&format!(
+                "<tr><td>{}</td><td>{}</td><td>{}</td><td>{}</td></tr>\n",
+                entry.at.format("%Y-%m-%d"),
+                escape_html(&entry.description),
+                escape_html(&entry.detail()),
+                escape_html(&entry.balance.to_string())
+            ));
+        }
+        out.push_str(&format!(
+            "</table>\n<p>期末余额: {}</p>\n</div>\n",
+            escape_html(&self.closing_balance.to_string())
+        ));
+        out
+    }
+}
+
+/// 现金记录在对账单中的说明，有备注时使用备注
+fn describe_cash(cash: &Cash) -> String {
+    if let Some(note) = &cash.note {
+        return note.clone();
+    }
+    let label = if cash.refund_of.is_some() {
+        "退款"
+    } else if cash.wallet.is_some_and(|w| w.is_negative()) {
+        "余额扣费"
+    } else if cash.wallet.is_some() {
+        "储值充值"
+    } else if cash.cash.is_negative() {
+        "支出"
+    } else {
+        "收款"
+    };
+    label.to_string()
+}
+
+/// 学生审计记录中的课时和会员期变化
+fn student_items(entry: &AuditEntry) -> Vec<(String, StatementItem)> {
+    let mut items = Vec::new();
+    let field = |name: &str| entry.changes.iter().find(|change| change.field == name);
+    // 切换到不记录课时的班级时新值为空，不算作消耗
+    if let Some(change) = field("lesson_left")
+        && let Some(new) = change.new.as_u64()
+    {
+        let old = change.old.as_u64().unwrap_or(0);
+        let count = u32::try_from(old.abs_diff(new)).unwrap_or(u32::MAX);
+        let lesson_left = u32::try_from(new).ok();
+        if new < old {
+            items.push((
+                "上课".to_string(),
+                StatementItem::LessonsConsumed { count, lesson_left },
+            ));
+        } else if new > old {
+            items.push((
+                "增加课时".to_string(),
+                StatementItem::LessonsAdded { count, lesson_left },
+            ));
+        }
+    }
+    if let Some(change) = field("membership_end_date")
+        && change.old != change.new
+    {
+        items.push((
+            "会员".to_string(),
+            StatementItem::MembershipChanged {
+                end: parse_date(&change.new),
+            },
+        ));
+    }
+    items
+}
+
+fn parse_date(value: &Value) -> Option<DateTime<Utc>> {
+    serde_json::from_value(value.clone()).ok()
+}
+
+fn add_wallet(balance: Money, delta: Money) -> Money {
+    balance.checked_add(delta).unwrap_or(balance)
+}
+
+fn format_lessons(lesson_left: Option<u32>) -> String {
+    lesson_left.map_or_else(|| "-".to_string(), |left| format!("{} 节", left))
+}
+
+fn format_date(date: Option<DateTime<Utc>>) -> String {
+    date.map_or_else(|| "-".to_string(), |d| d.format("%Y-%m-%d").to_string())
+}
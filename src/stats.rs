@@ -1,7 +1,12 @@
-use crate::cash::CashDatabase;
-use crate::student::StudentDatabase;
+use crate::audit::{AuditDatabase, AuditEntity};
+use crate::cash::{Cash, CashDatabase, CashTotals, InstallmentStatus, PaymentMethod};
+use crate::student::{Class, StudentDatabase, Subject};
 use crate::error::Result;
+use crate::manager::TimePeriod;
+use crate::money::Currency;
+use chrono::{DateTime, Months, NaiveDate, Utc};
 use log::info;
+use std::collections::{BTreeMap, HashMap};
 
 /// 仪表板统计数据结构
 ///
@@ -10,11 +15,16 @@ use log::info;
 /// # 字段说明
 ///
 /// - `total_students`: 系统中的学生总数
-/// - `total_revenue`: 总收入金额（单位：分）
-/// - `total_expense`: 总支出金额（单位：分）
+/// - `total_revenue`: 总收入金额（单位：分），计入规则见 [`CashTotals`]
+/// - `total_expense`: 总支出金额（单位：分），计入规则见 [`CashTotals`]
 /// - `average_score`: 所有学生的平均成绩
 /// - `max_score`: 系统中的最高成绩
 /// - `active_courses`: 活跃课程类型数量
+/// - `membership_tiers`: 各会员等级的学生数量（未设置等级的学生不计入）
+/// - `new_students_this_month`: 本月新建的学生数量（没有创建时间的旧数据不计入）
+/// - `expired_memberships_this_month`: 本月内已到期的会员数量
+/// - `active_members`: 当前会员有效的学生数量
+/// - `total_outstanding`: 应收欠款合计（单位：分），见 [`get_outstanding_balances`]
 ///
 /// # 示例
 ///
@@ -32,6 +42,7 @@ use log::info;
 /// # }
 /// ```
 #[derive(serde::Serialize, Debug)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct DashboardStats {
     pub total_students: usize,
     pub total_revenue: i64,
@@ -39,6 +50,12 @@ pub struct DashboardStats {
     pub average_score: f64,
     pub max_score: f64,
     pub active_courses: usize,
+    /// 各会员等级的学生数量，键为等级名称
+    pub membership_tiers: BTreeMap<String, usize>,
+    pub new_students_this_month: usize,
+    pub expired_memberships_this_month: usize,
+    pub active_members: usize,
+    pub total_outstanding: i64,
 }
 
 /// 计算仪表板统计数据
@@ -64,19 +81,20 @@ pub struct DashboardStats {
 /// use qmx_backend_lib::*;
 ///
 /// # fn main() -> qmx_backend_lib::error::Result<()> {
-/// let mut db = database::init()?;
+/// let mut students = student::StudentDatabase::new();
+/// let mut cash_db = cash::CashDatabase::new();
 ///
 /// // 添加一些测试数据
 /// let mut student = student::Student::new();
-/// student.set_name("测试学生".to_string()).add_ring(9.5);
-/// db.student.insert(student);
+/// student.set_name("测试学生".to_string()).add_ring(9.5)?;
+/// students.insert(student);
 ///
 /// let mut cash = cash::Cash::new(None);
 /// cash.set_cash(1000);
-/// db.cash.insert(cash);
+/// cash_db.insert(cash);
 ///
 /// // 计算统计数据
-/// let stats = get_dashboard_stats(&db.student, &db.cash)?;
+/// let stats = get_dashboard_stats(&students, &cash_db)?;
 /// assert_eq!(stats.total_students, 1);
 /// assert_eq!(stats.total_revenue, 1000);
 /// # Ok(())
@@ -90,19 +108,46 @@ pub struct DashboardStats {
 pub fn get_dashboard_stats(
     student_db: &StudentDatabase,
     cash_db: &CashDatabase,
+) -> Result<DashboardStats> {
+    get_dashboard_stats_at(student_db, cash_db, Utc::now())
+}
+
+/// 以指定时间为"现在"计算仪表板统计数据
+///
+/// "本月"指 `now` 所在的 UTC 自然月，从月初到 `now` 为止。
+pub fn get_dashboard_stats_at(
+    student_db: &StudentDatabase,
+    cash_db: &CashDatabase,
+    now: DateTime<Utc>,
 ) -> Result<DashboardStats> {
     info!("开始计算仪表盘统计数据");
-    let mut total_revenue = 0;
-    let mut total_expense = 0;
+    let (month_start, month_end) = TimePeriod::ThisMonth.bounds(now, &Utc)?;
+    let this_month = |time: DateTime<Utc>| time >= month_start && time <= month_end;
+    let mut new_students_this_month = 0;
+    let mut expired_memberships_this_month = 0;
+    let mut active_members = 0;
     let mut max_score = 0.0;
     let mut total_score_sum = 0.0;
     let mut total_score_count = 0;
 
     let total_students = student_db.len();
     let mut class_types = std::collections::HashSet::new();
+    let mut membership_tiers = BTreeMap::new();
 
     for (_, student) in student_db.iter() {
         class_types.insert(format!("{:?}", student.class()));
+        if let Some(tier) = student.membership_tier() {
+            *membership_tiers.entry(tier.to_string()).or_insert(0) += 1;
+        }
+        if student.created_at().is_some_and(this_month) {
+            new_students_this_month += 1;
+        }
+        if student.membership_end_date().is_some_and(this_month) {
+            expired_memberships_this_month += 1;
+        }
+        if student.is_membership_active_at(now) {
+            active_members += 1;
+        }
         for &score in student.rings() {
             total_score_sum += score;
             total_score_count += 1;
@@ -117,13 +162,12 @@ pub fn get_dashboard_stats(
         .filter(|class| class.as_str() != "Others")
         .count();
 
-    for (_, transaction) in cash_db.iter() {
-        if transaction.cash >= 0 {
-            total_revenue += transaction.cash;
-        } else {
-            total_expense += transaction.cash.abs();
-        }
-    }
+    let totals = CashTotals::from_records(cash_db.iter().map(|(_, transaction)| transaction))?;
+    let total_revenue = totals.income;
+    let total_expense = totals.expense;
+    let total_outstanding = get_outstanding_balances(cash_db)?
+        .iter()
+        .fold(0i64, |sum, balance| sum.saturating_add(balance.total));
 
     let average_score = if total_score_count == 0 {
         0.0
@@ -138,6 +182,11 @@ pub fn get_dashboard_stats(
         average_score,
         max_score,
         active_courses,
+        membership_tiers,
+        new_students_this_month,
+        expired_memberships_this_month,
+        active_members,
+        total_outstanding,
     };
     info!(
         "仪表盘统计计算完成: students={}, revenue={}, expense={}, avg={}, max={}, active_courses={}",
@@ -145,3 +194,537 @@ pub fn get_dashboard_stats(
     );
     Ok(stats)
 }
+
+/// 只统计一个校区的仪表板数据
+///
+/// 学生按 [`crate::student::Student::branch_id`]、现金记录按 [`crate::cash::Cash::branch_id`] 筛选，
+/// 因此学生转到其他校区后，之前在原校区的收支仍计入原校区。
+pub fn get_branch_dashboard_stats_at(
+    student_db: &StudentDatabase,
+    cash_db: &CashDatabase,
+    branch: &str,
+    now: DateTime<Utc>,
+) -> Result<DashboardStats> {
+    info!("计算校区 {} 的仪表盘统计数据", branch);
+    let mut students = StudentDatabase::new();
+    for (_, student) in student_db.iter() {
+        if student.branch_id() == Some(branch) {
+            students.insert(student.clone());
+        }
+    }
+    let mut cash_records = CashDatabase::new();
+    for (_, cash) in cash_db.iter() {
+        if cash.branch_id.as_deref() == Some(branch) {
+            cash_records.insert(cash.clone());
+        }
+    }
+    get_dashboard_stats_at(&students, &cash_records, now)
+}
+
+/// 一组学生（同一科目或同一班级）的统计数据
+#[derive(serde::Serialize, Debug, Clone, Default, PartialEq)]
+pub struct GroupStats {
+    pub student_count: usize,
+    /// 该组学生关联的现金记录的收入（单位：分），计入规则见 [`CashTotals`]
+    pub revenue: i64,
+    /// 该组学生关联的现金记录的支出（单位：分）
+    pub expense: i64,
+    /// 该组全部成绩的平均值，没有成绩时为 0
+    pub average_score: f64,
+    /// 参与平均的成绩数量
+    pub score_count: usize,
+}
+
+/// 按科目和按班级分组的统计数据
+///
+/// 只包含至少有一名学生的分组。未关联学生或关联的学生已不存在的现金记录不计入任何分组。
+#[derive(serde::Serialize, Debug, Clone, Default)]
+pub struct BreakdownStats {
+    pub by_subject: HashMap<Subject, GroupStats>,
+    pub by_class: HashMap<Class, GroupStats>,
+}
+
+/// 按科目和按班级分组计算学生数、收支和平均成绩
+///
+/// # 示例
+///
+/// ```rust
+/// use qmx_backend_lib::*;
+/// use qmx_backend_lib::student::Subject;
+///
+/// # fn main() -> qmx_backend_lib::error::Result<()> {
+/// let mut db = database::init()?;
+///
+/// let mut student = student::Student::new();
+/// student.set_subject(Subject::Archery).add_ring(9.0)?;
+/// let student_id = student.uid();
+/// db.student.insert(student);
+///
+/// let mut cash = cash::Cash::new(Some(student_id));
+/// cash.set_cash(1000);
+/// db.cash.insert(cash);
+///
+/// let stats = stats::get_breakdown_stats(&db.student, &db.cash)?;
+/// let archery = &stats.by_subject[&Subject::Archery];
+/// assert!(archery.revenue >= 1000);
+/// # Ok(())
+/// # }
+/// ```
+pub fn get_breakdown_stats(
+    student_db: &StudentDatabase,
+    cash_db: &CashDatabase,
+) -> Result<BreakdownStats> {
+    info!("开始计算分组统计数据");
+    // 各组的收支由多名学生的记录相加，所有关联学生的记录必须是同一币种
+    Currency::common(
+        cash_db
+            .iter()
+            .filter(|(_, cash)| cash.student_id.is_some())
+            .map(|(_, cash)| cash.cash),
+    )?;
+    let mut totals_by_student: HashMap<u64, CashTotals> = HashMap::new();
+    for (_, cash) in cash_db.iter() {
+        if let Some(student_id) = cash.student_id {
+            totals_by_student
+                .entry(student_id)
+                .or_default()
+                .record(cash.cash)?;
+        }
+    }
+
+    let mut subject_sums: HashMap<Subject, (GroupStats, f64)> = HashMap::new();
+    let mut class_sums: HashMap<Class, (GroupStats, f64)> = HashMap::new();
+    for (uid, student) in student_db.iter() {
+        let totals = totals_by_student.get(uid).copied().unwrap_or_default();
+        let score_sum: f64 = student.rings().iter().sum();
+        for (group, sum) in [
+            subject_sums.entry(student.subject().clone()).or_default(),
+            class_sums.entry(student.class().clone()).or_default(),
+        ] {
+            group.student_count += 1;
+            group.revenue = group.revenue.saturating_add(totals.income);
+            group.expense = group.expense.saturating_add(totals.expense);
+            group.score_count += student.rings().len();
+            *sum += score_sum;
+        }
+    }
+
+    let finish = |(mut group, sum): (GroupStats, f64)| {
+        if group.score_count > 0 {
+            group.average_score = sum / group.score_count as f64;
+        }
+        group
+    };
+    let stats = BreakdownStats {
+        by_subject: subject_sums
+            .into_iter()
+            .map(|(subject, sums)| (subject, finish(sums)))
+            .collect(),
+        by_class: class_sums
+            .into_iter()
+            .map(|(class, sums)| (class, finish(sums)))
+            .collect(),
+    };
+    info!(
+        "分组统计计算完成: subjects={}, classes={}",
+        stats.by_subject.len(),
+        stats.by_class.len()
+    );
+    Ok(stats)
+}
+
+/// 某个月开始试课的学生的转化情况
+#[derive(serde::Serialize, Debug, Clone, PartialEq)]
+pub struct ConversionPeriod {
+    /// 月份的第一天（UTC）
+    pub period_start: NaiveDate,
+    /// 该月开始试课（班级为 [`Class::TenTry`]）的学生数
+    pub trials: usize,
+    /// 其中之后转为月卡或年卡的学生数
+    pub converted: usize,
+    /// 转化率（0–1）
+    pub conversion_rate: f64,
+}
+
+/// 试课到会员的转化漏斗
+#[derive(serde::Serialize, Debug, Clone, PartialEq)]
+pub struct ConversionFunnel {
+    pub trials: usize,
+    pub converted: usize,
+    /// 总转化率（0–1），没有试课学生时为 0
+    pub conversion_rate: f64,
+    /// 从开始试课到转化的平均天数，没有转化时为 `None`
+    pub average_days_to_convert: Option<f64>,
+    /// 按开始试课的月份分组，按时间顺序排列
+    pub periods: Vec<ConversionPeriod>,
+}
+
+/// 根据审计日志中的班级变更统计试课学生转为月卡、年卡的情况
+///
+/// 学生第一次以 [`Class::TenTry`] 创建或被改为 [`Class::TenTry`] 的时间视为开始试课，
+/// 此后第一次改为 [`Class::Month`] 或 [`Class::Year`] 视为转化。
+/// 统计基于审计日志，已删除的学生同样计入；审计日志之外的修改（如 v1 API）无法统计。
+pub fn get_conversion_funnel(audit: &AuditDatabase) -> Result<ConversionFunnel> {
+    info!("开始计算试课转化漏斗");
+    let trial = serde_json::to_value(Class::TenTry)?;
+    let members = [
+        serde_json::to_value(Class::Month)?,
+        serde_json::to_value(Class::Year)?,
+    ];
+
+    // 学生 UID -> (开始试课时间, 转化时间)
+    let mut students: HashMap<u64, (DateTime<Utc>, Option<DateTime<Utc>>)> = HashMap::new();
+    for (_, entry) in audit.iter() {
+        if entry.entity != AuditEntity::Student {
+            continue;
+        }
+        let Some(change) = entry.changes.iter().find(|c| c.field == "class") else {
+            continue;
+        };
+        match students.get_mut(&entry.entity_uid) {
+            None if change.new == trial => {
+                students.insert(entry.entity_uid, (entry.timestamp, None));
+            }
+            Some((_, converted @ None)) if members.contains(&change.new) => {
+                *converted = Some(entry.timestamp);
+            }
+            _ => {}
+        }
+    }
+
+    let mut periods: BTreeMap<NaiveDate, (usize, usize)> = BTreeMap::new();
+    let mut total_days = 0.0;
+    let mut converted = 0;
+    for (started, converted_at) in students.values() {
+        let month = month_of(*started);
+        let period = periods.entry(month).or_default();
+        period.0 += 1;
+        if let Some(converted_at) = converted_at {
+            period.1 += 1;
+            converted += 1;
+            total_days += (*converted_at - *started).num_seconds() as f64 / 86_400.0;
+        }
+    }
+
+    let rate = |converted: usize, trials: usize| {
+        if trials == 0 {
+            0.0
+        } else {
+            converted as f64 / trials as f64
+        }
+    };
+    let trials = students.len();
+    let funnel = ConversionFunnel {
+        trials,
+        converted,
+        conversion_rate: rate(converted, trials),
+        average_days_to_convert: (converted > 0).then(|| total_days / converted as f64),
+        periods: periods
+            .into_iter()
+            .map(|(period_start, (trials, converted))| ConversionPeriod {
+                period_start,
+                trials,
+                converted,
+                conversion_rate: rate(converted, trials),
+            })
+            .collect(),
+    };
+    info!(
+        "试课转化漏斗计算完成: trials={}, converted={}",
+        funnel.trials, funnel.converted
+    );
+    Ok(funnel)
+}
+
+/// 现金记录的分组方式，见 [`aggregate_cash`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GroupBy {
+    /// 按创建时间所在月份（UTC）
+    Month,
+    /// 按关联学生
+    Student,
+    /// 按支付方式
+    PaymentMethod,
+}
+
+/// 一个分组的键
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum GroupKey {
+    /// 月份的第一天（UTC）
+    Month(NaiveDate),
+    /// 关联的学生 UID，`None` 为未关联学生的记录
+    Student(Option<u64>),
+    /// `None` 为未记录支付方式的记录
+    PaymentMethod(Option<PaymentMethod>),
+}
+
+/// 一个分组的收支汇总
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CashGroup {
+    pub key: GroupKey,
+    pub totals: CashTotals,
+}
+
+/// 按 `group_by` 对现金记录分组汇总，结果按分组键升序排列
+///
+/// 每组的收入、支出和记录数按 [`CashTotals`] 的规则计算，没有记录的分组不出现在结果中。
+/// 不同分组可以使用不同币种，同一分组中出现多种币种时返回 [`crate::Error::CurrencyMismatch`]。
+///
+/// # 示例
+///
+/// ```rust
+/// use qmx_backend_lib::cash::Cash;
+/// use qmx_backend_lib::stats::{GroupBy, GroupKey, aggregate_cash};
+///
+/// let mut first = Cash::new(Some(1));
+/// first.set_cash(1000);
+/// let mut second = Cash::new(Some(1));
+/// second.set_cash(-200);
+/// let groups = aggregate_cash(&[first, second], GroupBy::Student)?;
+/// assert_eq!(groups.len(), 1);
+/// assert_eq!(groups[0].key, GroupKey::Student(Some(1)));
+/// assert_eq!(groups[0].totals.net(), 800);
+/// # Ok::<(), qmx_backend_lib::Error>(())
+/// ```
+pub fn aggregate_cash<'a>(
+    records: impl IntoIterator<Item = &'a Cash>,
+    group_by: GroupBy,
+) -> Result<Vec<CashGroup>> {
+    let mut groups: BTreeMap<GroupKey, CashTotals> = BTreeMap::new();
+    for cash in records {
+        let key = match group_by {
+            GroupBy::Month => GroupKey::Month(month_of(cash.created_at)),
+            GroupBy::Student => GroupKey::Student(cash.student_id),
+            GroupBy::PaymentMethod => GroupKey::PaymentMethod(cash.payment_method),
+        };
+        groups.entry(key).or_default().record(cash.cash)?;
+    }
+    Ok(groups
+        .into_iter()
+        .map(|(key, totals)| CashGroup { key, totals })
+        .collect())
+}
+
+/// 参与收入趋势估计的历史月数
+const FORECAST_HISTORY_MONTHS: u32 = 6;
+
+/// 某个月的预计收入（单位：分）
+#[derive(serde::Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct MonthlyForecast {
+    /// 月份的第一天（UTC）
+    pub month: NaiveDate,
+    /// 该月到期、尚未付款的分期金额
+    pub installment_income: i64,
+    /// 按历史趋势估计的非分期收入
+    pub trend_income: i64,
+    pub total: i64,
+}
+
+/// 预测从本月起 `months_ahead` 个月的收入
+///
+/// - 分期收入：状态为待付且到期日在对应月份的分期金额；已逾期的分期不计入；
+/// - 趋势收入：对本月之前 6 个完整月的非分期收入（金额大于 0、不属于分期计划的记录）
+///   做最小二乘线性拟合后外推，结果不小于 0。
+///
+/// 现金记录中有多种币种时返回 [`crate::Error::CurrencyMismatch`]。
+///
+/// # 示例
+///
+/// ```rust
+/// use chrono::Utc;
+/// use qmx_backend_lib::cash::CashDatabase;
+/// use qmx_backend_lib::stats::forecast_revenue;
+///
+/// # fn main() -> qmx_backend_lib::error::Result<()> {
+/// let forecast = forecast_revenue(&CashDatabase::new(), 3, Utc::now())?;
+/// assert_eq!(forecast.len(), 3);
+/// assert!(forecast.iter().all(|month| month.total == 0));
+/// # Ok(())
+/// # }
+/// ```
+pub fn forecast_revenue(
+    cash_db: &CashDatabase,
+    months_ahead: u32,
+    now: DateTime<Utc>,
+) -> Result<Vec<MonthlyForecast>> {
+    info!("开始预测未来 {} 个月的收入", months_ahead);
+    Currency::common(cash_db.iter().map(|(_, cash)| cash.cash))?;
+    let current_month = month_of(now);
+    let history_start = current_month
+        .checked_sub_months(Months::new(FORECAST_HISTORY_MONTHS))
+        .unwrap_or(NaiveDate::MIN);
+
+    let mut installments: BTreeMap<NaiveDate, i64> = BTreeMap::new();
+    let mut history: BTreeMap<NaiveDate, i64> = BTreeMap::new();
+    for (_, cash) in cash_db.iter() {
+        match &cash.installment {
+            Some(installment)
+                if installment.status == InstallmentStatus::Pending
+                    && installment.due_date >= now =>
+            {
+                let month = month_of(installment.due_date);
+                let total = installments.entry(month).or_default();
+                *total = total.saturating_add(cash.cash.amount_minor);
+            }
+            Some(_) => {}
+            None if cash.cash.is_positive() => {
+                let month = month_of(cash.created_at);
+                if month >= history_start && month < current_month {
+                    let total = history.entry(month).or_default();
+                    *total = total.saturating_add(cash.cash.amount_minor);
+                }
+            }
+            None => {}
+        }
+    }
+
+    // x 为距历史起点的月数，本月为 FORECAST_HISTORY_MONTHS
+    let n = f64::from(FORECAST_HISTORY_MONTHS);
+    let points: Vec<(f64, f64)> = (0..FORECAST_HISTORY_MONTHS)
+        .map(|i| {
+            let month = history_start
+                .checked_add_months(Months::new(i))
+                .unwrap_or(history_start);
+            (
+                f64::from(i),
+                history.get(&month).copied().unwrap_or(0) as f64,
+            )
+        })
+        .collect();
+    let x_mean = (n - 1.0) / 2.0;
+    let y_mean = points.iter().map(|(_, y)| y).sum::<f64>() / n;
+    let (covariance, x_variance) = points.iter().fold((0.0, 0.0), |(cov, var), (x, y)| {
+        (
+            cov + (x - x_mean) * (y - y_mean),
+            var + (x - x_mean).powi(2),
+        )
+    });
+    let slope = covariance / x_variance;
+
+    let forecast = (0..months_ahead)
+        .map(|i| {
+            let month = current_month
+                .checked_add_months(Months::new(i))
+                .unwrap_or(NaiveDate::MAX);
+            let x = n + f64::from(i);
+            let trend_income = (y_mean + slope * (x - x_mean)).max(0.0).round() as i64;
+            let installment_income = installments.get(&month).copied().unwrap_or(0);
+            MonthlyForecast {
+                month,
+                installment_income,
+                trend_income,
+                total: installment_income.saturating_add(trend_income),
+            }
+        })
+        .collect();
+    Ok(forecast)
+}
+
+/// 一名学生的欠款（单位：分）
+#[derive(serde::Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct OutstandingBalance {
+    pub student_id: u64,
+    /// 待付和已逾期分期的金额合计
+    pub unpaid_installments: i64,
+    /// 待付和已逾期分期的期数
+    pub installment_count: usize,
+    /// 储值余额为负时欠的金额，余额不为负时为 0
+    pub wallet_debt: i64,
+    /// 欠款合计
+    pub total: i64,
+}
+
+/// 汇总每名学生的欠款，按欠款合计从多到少排列，合计相同时按学生 UID 升序
+///
+/// 欠款包括状态为待付或已逾期的分期金额，以及为负的储值余额。
+/// 未关联学生的分期和没有欠款的学生不出现在结果中。
+/// 计入的分期金额和储值余额中有多种币种时返回 [`crate::Error::CurrencyMismatch`]。
+///
+/// # 示例
+///
+/// ```rust
+/// use qmx_backend_lib::cash::{Cash, CashDatabase};
+/// use qmx_backend_lib::stats::get_outstanding_balances;
+///
+/// # fn main() -> qmx_backend_lib::error::Result<()> {
+/// let mut cash_db = CashDatabase::new();
+/// let mut charge = Cash::new(Some(1));
+/// charge.wallet = Some((-500).into());
+/// cash_db.insert(charge);
+///
+/// let debtors = get_outstanding_balances(&cash_db)?;
+/// assert_eq!(debtors[0].wallet_debt, 500);
+/// assert_eq!(debtors[0].total, 500);
+/// # Ok(())
+/// # }
+/// ```
+pub fn get_outstanding_balances(cash_db: &CashDatabase) -> Result<Vec<OutstandingBalance>> {
+    info!("开始汇总学生欠款");
+    let mut balances: BTreeMap<u64, OutstandingBalance> = BTreeMap::new();
+    let mut counted = Vec::new();
+    for (_, cash) in cash_db.iter() {
+        if let (Some(student_id), Some(installment)) = (cash.student_id, &cash.installment)
+            && matches!(
+                installment.status,
+                InstallmentStatus::Pending | InstallmentStatus::Overdue
+            )
+        {
+            let balance = outstanding_entry(&mut balances, student_id);
+            balance.unpaid_installments = balance
+                .unpaid_installments
+                .saturating_add(cash.cash.amount_minor);
+            balance.installment_count += 1;
+            counted.push(cash.cash);
+        }
+    }
+    for (student_id, wallet) in cash_db.wallet_balances() {
+        if wallet.is_negative() {
+            outstanding_entry(&mut balances, student_id).wallet_debt =
+                wallet.saturating_abs().amount_minor;
+            counted.push(wallet);
+        }
+    }
+    Currency::common(counted)?;
+
+    let mut debtors: Vec<OutstandingBalance> = balances
+        .into_values()
+        .map(|mut balance| {
+            balance.total = balance
+                .unpaid_installments
+                .saturating_add(balance.wallet_debt);
+            balance
+        })
+        .filter(|balance| balance.total > 0)
+        .collect();
+    debtors.sort_by(|a, b| {
+        b.total
+            .cmp(&a.total)
+            .then_with(|| a.student_id.cmp(&b.student_id))
+    });
+    info!("学生欠款汇总完成: debtors={}", debtors.len());
+    Ok(debtors)
+}
+
+fn outstanding_entry(
+    balances: &mut BTreeMap<u64, OutstandingBalance>,
+    student_id: u64,
+) -> &mut OutstandingBalance {
+    balances
+        .entry(student_id)
+        .or_insert_with(|| OutstandingBalance {
+            student_id,
+            unpaid_installments: 0,
+            installment_count: 0,
+            wallet_debt: 0,
+            total: 0,
+        })
+}
+
+/// `time` 所在 UTC 自然月的第一天，与 [`TimePeriod::ThisMonth`] 的起点一致
+///
+/// UTC 下月初总是存在，`bounds` 不会失败；保险起见失败时退回当天日期。
+fn month_of(time: DateTime<Utc>) -> NaiveDate {
+    TimePeriod::ThisMonth
+        .bounds(time, &Utc)
+        .map_or(time.date_naive(), |(start, _)| start.date_naive())
+}
@@ -0,0 +1,602 @@
+//! 可插拔的存储后端
+//!
+//! 默认情况下 [`crate::QmxManager`] 把学生和现金数据库整体写入 JSON 文件。
+//! 实现 [`StorageBackend`] 后可以通过 [`crate::QmxManager::with_backend`] 替换持久化方式，
+//! 支持增量写入的后端只需覆盖 `save_student`/`save_cash`，每次修改只写入受影响的记录，
+//! 例如 [`CashLogBackend`] 把现金记录的变更追加到日志文件末尾，启用 `sqlite` 特性后
+//! `SqliteBackend` 把每条记录保存为 SQLite 表中的一行。
+//!
+//! 没有文件系统的环境（如编译到 `wasm32-unknown-unknown` 的浏览器管理后台）可以实现
+//! 异步的 [`KeyValueStore`]，用 [`KeyValueBackend`] 包装后交给 [`crate::QmxManagerBuilder::storage`]。
+
+use crate::cash::{CASH_UID_COUNTER, Cash, CashDatabase};
+use crate::common::{Database as _, FileCodec};
+use crate::database::Database;
+use crate::error::{Error, Result};
+use crate::plan::InstallmentPlan;
+use crate::student::StudentDatabase;
+use log::{debug, info, warn};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeSet, HashMap};
+use std::fs::{File, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::Ordering;
+
+#[cfg(feature = "sqlite")]
+mod sqlite;
+#[cfg(feature = "sqlite")]
+pub use sqlite::SqliteBackend;
+
+/// 键值存储中学生数据库的键
+pub const STUDENT_KEY: &str = "student_database";
+
+/// 键值存储中现金数据库的键
+pub const CASH_KEY: &str = "cash_database";
+
+/// 存储后端
+pub trait StorageBackend: Send + Sync {
+    /// 加载全部数据
+    fn load(&self) -> Result<Database>;
+
+    /// 整体保存全部数据
+    fn save(&self, db: &Database) -> Result<()>;
+
+    /// 保存单个学生的变更，记录已不在 `db` 中表示被删除
+    ///
+    /// 默认实现退化为整体保存。
+    fn save_student(&self, db: &Database, uid: u64) -> Result<()> {
+        debug!("后端不支持增量写入，学生 {} 的变更触发整体保存", uid);
+        self.save(db)
+    }
+
+    /// 保存单条现金记录的变更，记录已不在 `db` 中表示被删除
+    ///
+    /// 默认实现退化为整体保存。
+    fn save_cash(&self, db: &Database, uid: u64) -> Result<()> {
+        debug!("后端不支持增量写入，现金记录 {} 的变更触发整体保存", uid);
+        self.save(db)
+    }
+
+    /// 保存一批学生的变更
+    ///
+    /// 默认实现只整体保存一次，支持增量写入的后端可以逐条写入。
+    fn save_student_batch(&self, db: &Database, uids: &[u64]) -> Result<()> {
+        debug!("批量保存 {} 个学生的变更，触发整体保存", uids.len());
+        self.save(db)
+    }
+
+    /// 保存一批现金记录的变更
+    ///
+    /// 默认实现只整体保存一次，支持增量写入的后端可以逐条写入。
+    fn save_cash_batch(&self, db: &Database, uids: &[u64]) -> Result<()> {
+        debug!("批量保存 {} 条现金记录的变更，触发整体保存", uids.len());
+        self.save(db)
+    }
+}
+
+/// JSON 文件后端，与默认的持久化格式完全一致
+///
+/// 默认使用创建时的进程默认编码方式，可以用 [`JsonFileBackend::with_codec`] 指定。
+#[derive(Debug, Clone)]
+pub struct JsonFileBackend {
+    student_path: String,
+    cash_path: String,
+    codec: FileCodec,
+}
+
+impl JsonFileBackend {
+    pub fn new(student_path: impl Into<String>, cash_path: impl Into<String>) -> Self {
+        Self {
+            student_path: student_path.into(),
+            cash_path: cash_path.into(),
+            codec: FileCodec::process_default(),
+        }
+    }
+
+    /// 数据文件的编码方式（如加密密钥）
+    pub fn with_codec(mut self, codec: FileCodec) -> Self {
+        self.codec = codec;
+        self
+    }
+}
+
+impl StorageBackend for JsonFileBackend {
+    fn load(&self) -> Result<Database> {
+        let student = match StudentDatabase::read_from_with(&self.student_path, &self.codec) {
+            Err(Error::Io(e)) if e.kind() == std::io::ErrorKind::NotFound => StudentDatabase::new(),
+            other => other?,
+        };
+        let cash = match CashDatabase::read_from_with(&self.cash_path, &self.codec) {
+            Err(Error::Io(e)) if e.kind() == std::io::ErrorKind::NotFound => CashDatabase::new(),
+            other => other?,
+        };
+        crate::cash::sync_plan_id_counter(&cash);
+        Ok(Database::new(student, cash))
+    }
+
+    fn save(&self, db: &Database) -> Result<()> {
+        db.student.save_to_with(&self.student_path, &self.codec)?;
+        db.cash.save_to_with(&self.cash_path, &self.codec)?;
+        Ok(())
+    }
+
+    fn save_student(&self, db: &Database, _uid: u64) -> Result<()> {
+        db.student.save_to_with(&self.student_path, &self.codec)
+    }
+
+    fn save_cash(&self, db: &Database, _uid: u64) -> Result<()> {
+        db.cash.save_to_with(&self.cash_path, &self.codec)
+    }
+
+    fn save_student_batch(&self, db: &Database, _uids: &[u64]) -> Result<()> {
+        db.student.save_to_with(&self.student_path, &self.codec)
+    }
+
+    fn save_cash_batch(&self, db: &Database, _uids: &[u64]) -> Result<()> {
+        db.cash.save_to_with(&self.cash_path, &self.codec)
+    }
+}
+
+/// [`CashLogBackend`] 默认在日志达到该条数后压缩
+pub const DEFAULT_COMPACT_AFTER: usize = 1000;
+
+/// 现金日志中的一行：记录变更后的完整内容，`None` 表示被删除
+///
+/// 分期记录同时带上所属计划。
+#[derive(Serialize, Deserialize)]
+struct CashLogEntry {
+    uid: u64,
+    record: Option<Cash>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    plan: Option<InstallmentPlan>,
+}
+
+/// 日志中已有的记录
+#[derive(Debug, Default)]
+struct CashLogState {
+    entries: usize,
+    uids: BTreeSet<u64>,
+}
+
+/// 现金记录追加日志后端
+///
+/// 学生数据库与 [`JsonFileBackend`] 相同，整体写入 JSON 文件。现金数据库由快照文件
+/// （格式与 `cash_database.json` 相同）和 JSON Lines 格式的追加日志（`<快照路径>.log`）
+/// 组成：单条现金记录变更时只在日志末尾追加一行，日志达到 `compact_after` 条后
+/// 重写快照并清空日志。加载时读取快照再按顺序重放日志。
+///
+/// 整体保存（包括 [`crate::AutoSave::Debounced`] 的延迟保存）总是压缩日志，
+/// 逐条追加只发生在 [`crate::AutoSave::Immediate`] 模式下。编码方式设置了加密密钥时
+/// 日志无法加密，每次变更都直接压缩。
+#[derive(Debug)]
+pub struct CashLogBackend {
+    student_path: String,
+    cash_path: String,
+    log_path: String,
+    codec: FileCodec,
+    compact_after: usize,
+    state: Mutex<CashLogState>,
+}
+
+impl CashLogBackend {
+    pub fn new(student_path: impl Into<String>, cash_path: impl Into<String>) -> Self {
+        let cash_path = cash_path.into();
+        Self {
+            student_path: student_path.into(),
+            log_path: format!("{}.log", cash_path),
+            cash_path,
+            codec: FileCodec::process_default(),
+            compact_after: DEFAULT_COMPACT_AFTER,
+            state: Mutex::new(CashLogState::default()),
+        }
+    }
+
+    /// 数据文件的编码方式，见 [`JsonFileBackend::with_codec`]
+    pub fn with_codec(mut self, codec: FileCodec) -> Self {
+        self.codec = codec;
+        self
+    }
+
+    /// 日志达到 `entries` 条后压缩，默认为 [`DEFAULT_COMPACT_AFTER`]
+    pub fn compact_after(mut self, entries: usize) -> Self {
+        self.compact_after = entries.max(1);
+        self
+    }
+
+    /// 追加日志的路径
+    pub fn log_path(&self) -> &str {
+        &self.log_path
+    }
+
+    /// 当前日志中的条数
+    pub fn log_len(&self) -> usize {
+        self.state.lock().unwrap_or_else(|e| e.into_inner()).entries
+    }
+
+    /// 重写快照并清空日志
+    pub fn compact(&self, db: &Database) -> Result<()> {
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        self.compact_locked(&mut state, db)
+    }
+
+    fn compact_locked(&self, state: &mut CashLogState, db: &Database) -> Result<()> {
+        // 先把日志中出现过的记录按当前内容再追加一遍：若在写入快照后、清空日志前崩溃，
+        // 重放日志得到的仍是当前内容，不会用旧版本覆盖快照
+        if !state.uids.is_empty() {
+            let uids: Vec<u64> = state.uids.iter().copied().collect();
+            self.append_locked(state, db, &uids)?;
+        }
+        db.cash.save_to_with(&self.cash_path, &self.codec)?;
+        File::create(&self.log_path)?.sync_all()?;
+        debug!("压缩现金日志: 清除 {} 条", state.entries);
+        *state = CashLogState::default();
+        Ok(())
+    }
+
+    fn append_locked(&self, state: &mut CashLogState, db: &Database, uids: &[u64]) -> Result<()> {
+        let mut lines = Vec::new();
+        for &uid in uids {
+            let record = db.cash.get(&uid).cloned();
+            let plan = record
+                .as_ref()
+                .and_then(Cash::installment_plan_id)
+                .and_then(|plan_id| db.cash.plans.get(&plan_id).cloned());
+            let entry = CashLogEntry { uid, record, plan };
+            serde_json::to_writer(&mut lines, &entry)?;
+            lines.push(b'\n');
+        }
+        let mut log = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.log_path)?;
+        log.write_all(&lines)?;
+        log.sync_data()?;
+        state.entries += uids.len();
+        state.uids.extend(uids);
+        Ok(())
+    }
+
+    /// 追加现金记录的变更，日志过长或启用了加密时改为压缩
+    fn append(&self, db: &Database, uids: &[u64]) -> Result<()> {
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        if self.codec.is_encrypted() {
+            return self.compact_locked(&mut state, db);
+        }
+        self.append_locked(&mut state, db, uids)?;
+        if state.entries >= self.compact_after {
+            self.compact_locked(&mut state, db)?;
+        }
+        Ok(())
+    }
+
+    /// 按顺序重放日志
+    ///
+    /// 最后一行不完整（追加时崩溃）时忽略该行，其他行损坏时返回错误。
+    fn replay(&self, cash: &mut CashDatabase) -> Result<CashLogState> {
+        let mut state = CashLogState::default();
+        let file = match File::open(&self.log_path) {
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(state),
+            other => other?,
+        };
+        let mut lines = BufReader::new(file).lines().enumerate().peekable();
+        while let Some((index, line)) = lines.next() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            let entry: CashLogEntry = match serde_json::from_str(&line) {
+                Ok(entry) => entry,
+                Err(e) if lines.peek().is_none() => {
+                    warn!("现金日志最后一行不完整，已忽略: {}", e);
+                    break;
+                }
+                Err(e) => {
+                    return Err(Error::InvalidInput(format!(
+                        "现金日志第 {} 行已损坏: {}",
+                        index + 1,
+                        e
+                    )));
+                }
+            };
+            if let Some(plan) = entry.plan {
+                cash.plans.insert_if_absent(plan);
+            }
+            match entry.record {
+                Some(record) => {
+                    CASH_UID_COUNTER.fetch_max(entry.uid.saturating_add(1), Ordering::SeqCst);
+                    cash.insert(record);
+                }
+                None => {
+                    cash.remove(&entry.uid);
+                }
+            }
+            state.entries += 1;
+            state.uids.insert(entry.uid);
+        }
+        info!("重放现金日志 {} 条", state.entries);
+        Ok(state)
+    }
+}
+
+impl StorageBackend for CashLogBackend {
+    fn load(&self) -> Result<Database> {
+        let student = match StudentDatabase::read_from_with(&self.student_path, &self.codec) {
+            Err(Error::Io(e)) if e.kind() == std::io::ErrorKind::NotFound => StudentDatabase::new(),
+            other => other?,
+        };
+        let mut cash = match CashDatabase::read_from_with(&self.cash_path, &self.codec) {
+            Err(Error::Io(e)) if e.kind() == std::io::ErrorKind::NotFound => CashDatabase::new(),
+            other => other?,
+        };
+        let state = self.replay(&mut cash)?;
+        *self.state.lock().unwrap_or_else(|e| e.into_inner()) = state;
+        crate::cash::sync_plan_id_counter(&cash);
+        Ok(Database::new(student, cash))
+    }
+
+    fn save(&self, db: &Database) -> Result<()> {
+        db.student.save_to_with(&self.student_path, &self.codec)?;
+        self.compact(db)
+    }
+
+    fn save_student(&self, db: &Database, _uid: u64) -> Result<()> {
+        db.student.save_to_with(&self.student_path, &self.codec)
+    }
+
+    fn save_cash(&self, db: &Database, uid: u64) -> Result<()> {
+        self.append(db, &[uid])
+    }
+
+    fn save_student_batch(&self, db: &Database, _uids: &[u64]) -> Result<()> {
+        db.student.save_to_with(&self.student_path, &self.codec)
+    }
+
+    fn save_cash_batch(&self, db: &Database, uids: &[u64]) -> Result<()> {
+        self.append(db, uids)
+    }
+}
+
+/// 内存后端，不落盘，适用于测试和临时会话
+#[derive(Debug, Default)]
+pub struct MemoryBackend {
+    saved: Mutex<Option<Database>>,
+}
+
+impl MemoryBackend {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl StorageBackend for MemoryBackend {
+    fn load(&self) -> Result<Database> {
+        let saved = self.saved.lock().unwrap_or_else(|e| e.into_inner());
+        Ok(saved
+            .clone()
+            .unwrap_or_else(|| Database::new(StudentDatabase::new(), CashDatabase::new())))
+    }
+
+    fn save(&self, db: &Database) -> Result<()> {
+        *self.saved.lock().unwrap_or_else(|e| e.into_inner()) = Some(db.clone());
+        Ok(())
+    }
+
+    fn save_student(&self, db: &Database, uid: u64) -> Result<()> {
+        let mut saved = self.saved.lock().unwrap_or_else(|e| e.into_inner());
+        let target =
+            saved.get_or_insert_with(|| Database::new(StudentDatabase::new(), CashDatabase::new()));
+        match db.student.get(&uid) {
+            Some(student) => {
+                target.student.insert(student.clone());
+            }
+            None => {
+                target.student.remove(&uid);
+            }
+        }
+        Ok(())
+    }
+
+    fn save_cash(&self, db: &Database, uid: u64) -> Result<()> {
+        let mut saved = self.saved.lock().unwrap_or_else(|e| e.into_inner());
+        let target =
+            saved.get_or_insert_with(|| Database::new(StudentDatabase::new(), CashDatabase::new()));
+        match db.cash.get(&uid) {
+            Some(cash) => {
+                if let Some(plan) = cash
+                    .installment_plan_id()
+                    .and_then(|plan_id| db.cash.plans.get(&plan_id))
+                {
+                    target.cash.plans.insert_if_absent(plan.clone());
+                }
+                target.cash.insert(cash.clone());
+            }
+            None => {
+                target.cash.remove(&uid);
+            }
+        }
+        Ok(())
+    }
+
+    fn save_student_batch(&self, db: &Database, uids: &[u64]) -> Result<()> {
+        for &uid in uids {
+            self.save_student(db, uid)?;
+        }
+        Ok(())
+    }
+
+    fn save_cash_batch(&self, db: &Database, uids: &[u64]) -> Result<()> {
+        for &uid in uids {
+            self.save_cash(db, uid)?;
+        }
+        Ok(())
+    }
+}
+
+/// 键值存储
+///
+/// 只需按键异步读写整块数据，浏览器中可以用 IndexedDB 或 localStorage 实现。
+/// 返回的 future 不要求 `Send`，可以直接等待 JavaScript 的 Promise。
+pub trait KeyValueStore {
+    /// 读取键对应的数据，键不存在时返回 `None`
+    fn get(&self, key: &str) -> impl Future<Output = Result<Option<Vec<u8>>>>;
+
+    /// 写入键对应的数据，已存在时覆盖
+    fn set(&self, key: &str, value: Vec<u8>) -> impl Future<Output = Result<()>>;
+}
+
+/// 内存键值存储，适用于测试
+///
+/// 克隆得到的存储共享同一份数据。
+#[derive(Debug, Clone, Default)]
+pub struct MemoryStore {
+    entries: Arc<Mutex<HashMap<String, Vec<u8>>>>,
+}
+
+impl MemoryStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl KeyValueStore for MemoryStore {
+    async fn get(&self, key: &str) -> Result<Option<Vec<u8>>> {
+        let entries = self.entries.lock().unwrap_or_else(|e| e.into_inner());
+        Ok(entries.get(key).cloned())
+    }
+
+    async fn set(&self, key: &str, value: Vec<u8>) -> Result<()> {
+        let mut entries = self.entries.lock().unwrap_or_else(|e| e.into_inner());
+        entries.insert(key.to_string(), value);
+        Ok(())
+    }
+}
+
+#[derive(Debug, Default)]
+struct KeyValueState {
+    entries: HashMap<&'static str, Vec<u8>>,
+    /// 尚未写回存储的键
+    dirty: BTreeSet<&'static str>,
+}
+
+/// 键值存储后端
+///
+/// 学生和现金数据库分别以 JSON 保存在 [`STUDENT_KEY`] 和 [`CASH_KEY`] 下。
+/// [`StorageBackend`] 是同步接口，而键值存储是异步的，因此数据在
+/// [`KeyValueBackend::open`] 时整体读入内存，保存只更新内存中的副本，
+/// 由调用方在合适的时机（如每次操作之后）调用 [`KeyValueBackend::flush`] 写回存储。
+#[derive(Debug)]
+pub struct KeyValueBackend<S> {
+    store: S,
+    state: Mutex<KeyValueState>,
+}
+
+impl<S: KeyValueStore> KeyValueBackend<S> {
+    /// 从存储中读取已有数据
+    pub async fn open(store: S) -> Result<Self> {
+        let mut entries = HashMap::new();
+        for key in [STUDENT_KEY, CASH_KEY] {
+            if let Some(bytes) = store.get(key).await? {
+                entries.insert(key, bytes);
+            }
+        }
+        Ok(Self {
+            store,
+            state: Mutex::new(KeyValueState {
+                entries,
+                dirty: BTreeSet::new(),
+            }),
+        })
+    }
+
+    pub fn store(&self) -> &S {
+        &self.store
+    }
+
+    /// 是否有尚未写回存储的修改
+    pub fn is_dirty(&self) -> bool {
+        !self.lock().dirty.is_empty()
+    }
+
+    /// 把尚未写回的数据写入存储
+    ///
+    /// 写入失败时未写入的键保持待写入状态，可以再次调用重试。
+    pub async fn flush(&self) -> Result<()> {
+        let pending: Vec<(&'static str, Vec<u8>)> = {
+            let mut state = self.lock();
+            let dirty = std::mem::take(&mut state.dirty);
+            dirty
+                .into_iter()
+                .filter_map(|key| state.entries.get(key).map(|bytes| (key, bytes.clone())))
+                .collect()
+        };
+        for (i, (key, bytes)) in pending.iter().enumerate() {
+            if let Err(e) = self.store.set(key, bytes.clone()).await {
+                warn!("写入键值存储 {} 失败: {}", key, e);
+                self.lock()
+                    .dirty
+                    .extend(pending[i..].iter().map(|(key, _)| *key));
+                return Err(e);
+            }
+        }
+        debug!("写回 {} 个键", pending.len());
+        Ok(())
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, KeyValueState> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn stage(&self, key: &'static str, bytes: Vec<u8>) {
+        let mut state = self.lock();
+        state.entries.insert(key, bytes);
+        state.dirty.insert(key);
+    }
+
+    fn save_students(&self, db: &Database) -> Result<()> {
+        self.stage(STUDENT_KEY, serde_json::to_vec(&db.student)?);
+        Ok(())
+    }
+
+    fn save_cash_records(&self, db: &Database) -> Result<()> {
+        self.stage(CASH_KEY, serde_json::to_vec(&db.cash)?);
+        Ok(())
+    }
+}
+
+impl<S: KeyValueStore + Send + Sync> StorageBackend for KeyValueBackend<S> {
+    fn load(&self) -> Result<Database> {
+        let state = self.lock();
+        let student = match state.entries.get(STUDENT_KEY) {
+            Some(bytes) => serde_json::from_slice(bytes)?,
+            None => StudentDatabase::new(),
+        };
+        let cash = match state.entries.get(CASH_KEY) {
+            Some(bytes) => serde_json::from_slice(bytes)?,
+            None => CashDatabase::new(),
+        };
+        crate::cash::sync_plan_id_counter(&cash);
+        Ok(Database::new(student, cash))
+    }
+
+    fn save(&self, db: &Database) -> Result<()> {
+        self.save_students(db)?;
+        self.save_cash_records(db)
+    }
+
+    fn save_student(&self, db: &Database, _uid: u64) -> Result<()> {
+        self.save_students(db)
+    }
+
+    fn save_cash(&self, db: &Database, _uid: u64) -> Result<()> {
+        self.save_cash_records(db)
+    }
+
+    fn save_student_batch(&self, db: &Database, _uids: &[u64]) -> Result<()> {
+        self.save_students(db)
+    }
+
+    fn save_cash_batch(&self, db: &Database, _uids: &[u64]) -> Result<()> {
+        self.save_cash_records(db)
+    }
+}
@@ -0,0 +1,242 @@
+//! SQLite 存储后端（需要启用 `sqlite` 特性）
+
+use super::StorageBackend;
+use crate::cash::{CASH_UID_COUNTER, Cash, CashDatabase};
+use crate::common::{Database as _, FileCodec};
+use crate::database::Database;
+use crate::error::{Error, Result};
+use crate::plan::InstallmentPlan;
+use crate::student::{STUDENT_UID_COUNTER, Student, StudentDatabase};
+use log::{debug, info};
+use rusqlite::{Connection, OptionalExtension, Transaction, params};
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+use std::path::Path;
+use std::sync::Mutex;
+use std::sync::atomic::Ordering;
+
+const SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS students (uid INTEGER PRIMARY KEY, data BLOB NOT NULL);
+    CREATE TABLE IF NOT EXISTS cash (uid INTEGER PRIMARY KEY, data BLOB NOT NULL);
+    CREATE TABLE IF NOT EXISTS plans (plan_id INTEGER PRIMARY KEY, data BLOB NOT NULL);
+    CREATE TABLE IF NOT EXISTS counters (name TEXT PRIMARY KEY, next_uid INTEGER NOT NULL);
+";
+
+impl From<rusqlite::Error> for Error {
+    fn from(e: rusqlite::Error) -> Self {
+        Self::Other(format!("SQLite错误: {}", e))
+    }
+}
+
+/// SQLite 后端
+///
+/// 学生、现金记录和分期计划各占一张表，每行保存一条记录的 JSON。单条记录变更时只写入
+/// 这一行（分期记录同时写入所属计划），批量变更在同一个事务中写入，整体保存时在一个事务中
+/// 重写全部表。下一个 UID 保存在 `counters` 表中，删除最新的记录后重新打开也不会复用其 UID。
+///
+/// 默认使用创建时的进程默认编码方式，设置了加密密钥时每行单独加密。
+#[derive(Debug)]
+pub struct SqliteBackend {
+    connection: Mutex<Connection>,
+    codec: FileCodec,
+}
+
+impl SqliteBackend {
+    /// 打开数据库文件，不存在时创建
+    pub fn open(path: impl AsRef<Path>) -> Result<Self> {
+        Self::with_connection(Connection::open(path)?)
+    }
+
+    /// 创建内存数据库，适用于测试
+    pub fn open_in_memory() -> Result<Self> {
+        Self::with_connection(Connection::open_in_memory()?)
+    }
+
+    fn with_connection(connection: Connection) -> Result<Self> {
+        connection.execute_batch(SCHEMA)?;
+        Ok(Self {
+            connection: Mutex::new(connection),
+            codec: FileCodec::process_default(),
+        })
+    }
+
+    /// 记录的编码方式，见 [`super::JsonFileBackend::with_codec`]
+    pub fn with_codec(mut self, codec: FileCodec) -> Self {
+        self.codec = codec;
+        self
+    }
+
+    /// 在一个事务中执行 `f` 并提交
+    fn transaction(&self, f: impl FnOnce(&Transaction) -> Result<()>) -> Result<()> {
+        let mut connection = self.connection.lock().unwrap_or_else(|e| e.into_inner());
+        let tx = connection.transaction()?;
+        f(&tx)?;
+        tx.commit()?;
+        Ok(())
+    }
+
+    fn encode<T: Serialize>(&self, record: &T) -> Result<Vec<u8>> {
+        self.codec.encode(serde_json::to_vec(record)?)
+    }
+
+    fn decode<T: DeserializeOwned>(&self, data: Vec<u8>) -> Result<T> {
+        Ok(serde_json::from_slice(&self.codec.decode(data)?)?)
+    }
+
+    fn load_table<T: DeserializeOwned>(
+        &self,
+        connection: &Connection,
+        table: &str,
+    ) -> Result<Vec<T>> {
+        let mut statement = connection.prepare(&format!("SELECT data FROM {}", table))?;
+        let rows = statement.query_map([], |row| row.get::<_, Vec<u8>>(0))?;
+        let mut records = Vec::new();
+        for data in rows {
+            records.push(self.decode(data?)?);
+        }
+        Ok(records)
+    }
+
+    fn write_student(&self, tx: &Transaction, db: &Database, uid: u64) -> Result<()> {
+        match db.student.get(&uid) {
+            Some(student) => {
+                tx.execute(
+                    "INSERT OR REPLACE INTO students (uid, data) VALUES (?1, ?2)",
+                    params![uid as i64, self.encode(student)?],
+                )?;
+            }
+            None => {
+                tx.execute("DELETE FROM students WHERE uid = ?1", params![uid as i64])?;
+            }
+        }
+        Ok(())
+    }
+
+    fn write_cash(&self, tx: &Transaction, db: &Database, uid: u64) -> Result<()> {
+        match db.cash.get(&uid) {
+            Some(cash) => {
+                if let Some(plan) = cash
+                    .installment_plan_id()
+                    .and_then(|plan_id| db.cash.plans.get(&plan_id))
+                {
+                    self.write_plan(tx, plan)?;
+                }
+                tx.execute(
+                    "INSERT OR REPLACE INTO cash (uid, data) VALUES (?1, ?2)",
+                    params![uid as i64, self.encode(cash)?],
+                )?;
+            }
+            None => {
+                tx.execute("DELETE FROM cash WHERE uid = ?1", params![uid as i64])?;
+            }
+        }
+        Ok(())
+    }
+
+    fn write_plan(&self, tx: &Transaction, plan: &InstallmentPlan) -> Result<()> {
+        tx.execute(
+            "INSERT OR REPLACE INTO plans (plan_id, data) VALUES (?1, ?2)",
+            params![plan.plan_id as i64, self.encode(plan)?],
+        )?;
+        Ok(())
+    }
+
+    /// 记录当前的 UID 计数器，只会增大
+    fn write_counters(tx: &Transaction) -> Result<()> {
+        for (name, counter) in [
+            ("student", &STUDENT_UID_COUNTER),
+            ("cash", &CASH_UID_COUNTER),
+        ] {
+            tx.execute(
+                "INSERT INTO counters (name, next_uid) VALUES (?1, ?2)
+                 ON CONFLICT(name) DO UPDATE SET next_uid = max(next_uid, excluded.next_uid)",
+                params![name, counter.load(Ordering::SeqCst) as i64],
+            )?;
+        }
+        Ok(())
+    }
+
+    fn read_counter(connection: &Connection, name: &str) -> Result<u64> {
+        let next_uid: Option<i64> = connection
+            .query_row(
+                "SELECT next_uid FROM counters WHERE name = ?1",
+                params![name],
+                |row| row.get(0),
+            )
+            .optional()?;
+        Ok(next_uid.map_or(1, |next| next as u64))
+    }
+}
+
+impl StorageBackend for SqliteBackend {
+    fn load(&self) -> Result<Database> {
+        let connection = self.connection.lock().unwrap_or_else(|e| e.into_inner());
+        let mut student = StudentDatabase::new();
+        for record in self.load_table::<Student>(&connection, "students")? {
+            student.insert(record);
+        }
+        let mut cash = CashDatabase::new();
+        for plan in self.load_table::<InstallmentPlan>(&connection, "plans")? {
+            cash.plans.insert(plan);
+        }
+        for record in self.load_table::<Cash>(&connection, "cash")? {
+            cash.insert(record);
+        }
+        STUDENT_UID_COUNTER.fetch_max(
+            Self::read_counter(&connection, "student")?,
+            Ordering::SeqCst,
+        );
+        CASH_UID_COUNTER.fetch_max(Self::read_counter(&connection, "cash")?, Ordering::SeqCst);
+        crate::cash::sync_plan_id_counter(&cash);
+        info!(
+            "从 SQLite 加载 {} 个学生、{} 条现金记录",
+            student.len(),
+            cash.len()
+        );
+        Ok(Database::new(student, cash))
+    }
+
+    fn save(&self, db: &Database) -> Result<()> {
+        self.transaction(|tx| {
+            tx.execute_batch("DELETE FROM students; DELETE FROM cash; DELETE FROM plans;")?;
+            for &uid in db.student.data().keys() {
+                self.write_student(tx, db, uid)?;
+            }
+            for (_, plan) in db.cash.plans.iter() {
+                self.write_plan(tx, plan)?;
+            }
+            for &uid in db.cash.data().keys() {
+                self.write_cash(tx, db, uid)?;
+            }
+            Self::write_counters(tx)
+        })
+    }
+
+    fn save_student(&self, db: &Database, uid: u64) -> Result<()> {
+        self.save_student_batch(db, &[uid])
+    }
+
+    fn save_cash(&self, db: &Database, uid: u64) -> Result<()> {
+        self.save_cash_batch(db, &[uid])
+    }
+
+    fn save_student_batch(&self, db: &Database, uids: &[u64]) -> Result<()> {
+        debug!("写入 {} 个学生", uids.len());
+        self.transaction(|tx| {
+            for &uid in uids {
+                self.write_student(tx, db, uid)?;
+            }
+            Self::write_counters(tx)
+        })
+    }
+
+    fn save_cash_batch(&self, db: &Database, uids: &[u64]) -> Result<()> {
+        debug!("写入 {} 条现金记录", uids.len());
+        self.transaction(|tx| {
+            for &uid in uids {
+                self.write_cash(tx, db, uid)?;
+            }
+            Self::write_counters(tx)
+        })
+    }
+}
@@ -1,15 +1,17 @@
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, HashMap};
 use std::fs::File;
 use std::io::Write;
 use std::sync::atomic::{AtomicU64, Ordering};
-use std::sync::OnceLock;
+use std::sync::{OnceLock, RwLock};
 
 use crate::error::{Result, Error};
 use chrono::{DateTime, Utc};
 use log::{debug, error, info, warn};
 use serde::{Deserialize, Serialize};
 
-use crate::common::{Database, HasUid};
+use crate::common::{CustomValue, Database, HasUid, SalvageReport, SecondaryIndex};
+use crate::lazy::{PendingDetails, deserialize_detail, read_details, skipping_details};
+use crate::log_policy::log_policy;
 
 pub static STUDENT_UID_COUNTER: AtomicU64 = AtomicU64::new(1);
 
@@ -21,23 +23,156 @@ fn get_data_dir() -> &'static str {
     })
 }
 
+/// 旧版本数据文件中表示"未填写"的占位字符串
+const LEGACY_PLACEHOLDER: &str = "未填写";
+
+/// 兼容旧数据：占位字符串和空字符串读取为 `None`
+fn deserialize_optional_text<'de, D>(
+    deserializer: D,
+) -> std::result::Result<Option<String>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    let value = Option::<String>::deserialize(deserializer)?;
+    Ok(value.filter(|v| !v.is_empty() && v != LEGACY_PLACEHOLDER))
+}
+
+/// 缺失字段在展示层使用的默认文本
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DisplayDefaults {
+    pub name: String,
+    pub phone: String,
+}
+
+impl Default for DisplayDefaults {
+    fn default() -> Self {
+        Self {
+            name: LEGACY_PLACEHOLDER.to_string(),
+            phone: LEGACY_PLACEHOLDER.to_string(),
+        }
+    }
+}
+
+static DISPLAY_DEFAULTS: OnceLock<RwLock<DisplayDefaults>> = OnceLock::new();
+
+fn display_defaults_lock() -> &'static RwLock<DisplayDefaults> {
+    DISPLAY_DEFAULTS.get_or_init(|| RwLock::new(DisplayDefaults::default()))
+}
+
+/// 获取当前全局生效的展示默认值
+pub fn display_defaults() -> DisplayDefaults {
+    display_defaults_lock()
+        .read()
+        .unwrap_or_else(|e| e.into_inner())
+        .clone()
+}
+
+/// 替换全局展示默认值
+pub fn set_display_defaults(defaults: DisplayDefaults) {
+    info!("更新展示默认值");
+    *display_defaults_lock()
+        .write()
+        .unwrap_or_else(|e| e.into_inner()) = defaults;
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct Student {
     uid: u64,
     age: Option<u8>,
-    name: String,
-    phone: String,
+    #[serde(default, deserialize_with = "deserialize_optional_text")]
+    name: Option<String>,
+    #[serde(default, deserialize_with = "deserialize_optional_text")]
+    phone: Option<String>,
     lesson_left: Option<u32>,
     class: Class,
     subject: Subject,
+    #[serde(deserialize_with = "deserialize_detail")]
     rings: Vec<f64>,
+    #[serde(deserialize_with = "deserialize_detail")]
     note: String,
     // 会员相关字段
     membership_start_date: Option<DateTime<Utc>>,
     membership_end_date: Option<DateTime<Utc>>,
+    #[serde(default)]
+    membership_tier: Option<MembershipTier>,
+    #[serde(default)]
+    guardians: Vec<Guardian>,
+    /// 机构自定义字段
+    #[serde(default)]
+    custom_fields: BTreeMap<String, CustomValue>,
+    /// 负责该学生的教练 UID，见 [`crate::coach::Coach`]
+    #[serde(default)]
+    assigned_coach: Option<u64>,
+    /// 所属校区，未分配校区的学生（包括旧版本数据文件中的学生）为 `None`
+    #[serde(default)]
+    branch_id: Option<String>,
+    /// 创建时间，旧版本数据文件中的学生为 `None`
+    #[serde(default)]
+    created_at: Option<DateTime<Utc>>,
+    /// 最后修改时间，旧版本数据文件中的学生为 `None`
+    #[serde(default)]
+    updated_at: Option<DateTime<Utc>>,
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+/// 监护人 / 紧急联系人
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct Guardian {
+    pub name: String,
+    pub phone: String,
+    /// 与学生的关系，如“母亲”
+    pub relation: String,
+}
+
+impl Guardian {
+    pub fn new(
+        name: impl Into<String>,
+        phone: impl Into<String>,
+        relation: impl Into<String>,
+    ) -> Self {
+        Self {
+            name: name.into(),
+            phone: phone.into(),
+            relation: relation.into(),
+        }
+    }
+
+    /// 电话号码是否匹配，忽略空格、连字符等非数字字符
+    pub fn phone_matches(&self, phone: &str) -> bool {
+        let wanted = phone_digits(phone);
+        !wanted.is_empty() && phone_digits(&self.phone) == wanted
+    }
+}
+
+/// 电话号码中的数字部分，用于比较格式不同的号码
+pub fn phone_digits(phone: &str) -> String {
+    phone.chars().filter(char::is_ascii_digit).collect()
+}
+
+/// 会员等级
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub enum MembershipTier {
+    Basic,
+    Silver,
+    Gold,
+    Custom(String),
+}
+
+impl std::fmt::Display for MembershipTier {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Basic => write!(f, "Basic"),
+            Self::Silver => write!(f, "Silver"),
+            Self::Gold => write!(f, "Gold"),
+            Self::Custom(name) => write!(f, "{}", name),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub enum Class {
     TenTry,
     Month,
@@ -45,21 +180,343 @@ pub enum Class {
     Others,
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+/// 班级的课时策略
+///
+/// 决定某类班级是否按课时计费，以及切换到该班级时初始化多少课时。
+/// 例如带次数限制的月卡可配置为 `ClassPolicy::lessons(8)`。
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ClassPolicy {
+    /// 是否记录剩余课时（为 false 时 `lesson_left` 保持为空）
+    pub tracks_lessons: bool,
+    /// 切换到该班级时初始化的课时数
+    pub initial_lessons: Option<u32>,
+}
+
+impl ClassPolicy {
+    /// 不记录课时的策略（默认的月卡、年卡）
+    pub const fn untracked() -> Self {
+        Self {
+            tracks_lessons: false,
+            initial_lessons: None,
+        }
+    }
+
+    /// 记录课时并初始化为指定数量的策略
+    pub const fn lessons(initial: u32) -> Self {
+        Self {
+            tracks_lessons: true,
+            initial_lessons: Some(initial),
+        }
+    }
+}
+
+/// 班级到课时策略的映射表
+///
+/// 默认只有 `TenTry` 记录课时（初始 10 次），其余班级不记录课时。
+/// 每个 [`crate::QmxManager`] 持有自己的策略表，见 [`crate::QmxManagerBuilder::class_policies`]；
+/// 不经过管理器的 [`Student`] 方法使用进程级默认策略表，见 [`class_policies`]。
+#[derive(Debug, Clone, PartialEq)]
+pub struct ClassPolicyTable {
+    policies: HashMap<Class, ClassPolicy>,
+}
+
+impl Default for ClassPolicyTable {
+    fn default() -> Self {
+        let mut policies = HashMap::new();
+        policies.insert(Class::TenTry, ClassPolicy::lessons(10));
+        Self { policies }
+    }
+}
+
+impl ClassPolicyTable {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 设置某个班级的课时策略
+    pub fn set(&mut self, class: Class, policy: ClassPolicy) -> &mut Self {
+        debug!("班级 {:?} 的课时策略设置为 {:?}", class, policy);
+        self.policies.insert(class, policy);
+        self
+    }
+
+    /// 获取某个班级的课时策略，未配置的班级不记录课时
+    pub fn get(&self, class: &Class) -> ClassPolicy {
+        self.policies
+            .get(class)
+            .copied()
+            .unwrap_or(ClassPolicy::untracked())
+    }
+}
+
+static CLASS_POLICIES: OnceLock<RwLock<ClassPolicyTable>> = OnceLock::new();
+
+fn class_policy_lock() -> &'static RwLock<ClassPolicyTable> {
+    CLASS_POLICIES.get_or_init(|| RwLock::new(ClassPolicyTable::default()))
+}
+
+/// 借用进程级默认班级课时策略表，避免每次查询都复制
+fn default_class_policies() -> std::sync::RwLockReadGuard<'static, ClassPolicyTable> {
+    class_policy_lock()
+        .read()
+        .unwrap_or_else(|e| e.into_inner())
+}
+
+/// 获取进程级默认班级课时策略表
+///
+/// 只用于不经过管理器直接调用的 [`Student::set_class_with_lesson_init`] 等方法，
+/// 管理器使用自己的策略表。
+pub fn class_policies() -> ClassPolicyTable {
+    default_class_policies().clone()
+}
+
+/// 替换进程级默认班级课时策略表
+pub fn set_class_policies(table: ClassPolicyTable) {
+    info!("更新班级课时策略表");
+    *class_policy_lock()
+        .write()
+        .unwrap_or_else(|e| e.into_inner()) = table;
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub enum Subject {
     Shooting,
     Archery,
     Others,
 }
 
+/// 单个成绩允许的取值范围（闭区间，且必须是有限数）
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub struct RingRange {
+    pub min: f64,
+    pub max: f64,
+}
+
+impl RingRange {
+    /// 射击：0.0 ~ 10.9 环
+    pub const SHOOTING: Self = Self::new(0.0, 10.9);
+    /// 射箭：0 ~ 10 环
+    pub const ARCHERY: Self = Self::new(0.0, 10.0);
+    /// 百分制：0 ~ 100 分
+    pub const PERCENTAGE: Self = Self::new(0.0, 100.0);
+
+    pub const fn new(min: f64, max: f64) -> Self {
+        Self { min, max }
+    }
+
+    /// 检查成绩是否在范围内，NaN 和无穷值始终无效
+    pub fn contains(&self, ring: f64) -> bool {
+        ring.is_finite() && ring >= self.min && ring <= self.max
+    }
+}
+
+/// 单个科目的计分配置
+///
+/// 成绩范围为 `min_ring` ~ `max_ring`；`decimals` 为允许的小数位数，`None` 表示不限制；
+/// `series_size` 为每组成绩包含的发数（射击常见 10 发一组，射箭 6 箭一组）。
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub struct ScoringConfig {
+    /// 旧版本配置文件没有该字段，按 0 处理
+    #[serde(default)]
+    pub min_ring: f64,
+    pub max_ring: f64,
+    pub decimals: Option<u8>,
+    pub series_size: usize,
+}
+
+impl ScoringConfig {
+    /// 射击：最高 10.9 环，保留一位小数，10 发一组
+    pub const SHOOTING: Self = Self::new(10.9, Some(1), 10);
+    /// 射箭：最高 10 环，只计整数，6 箭一组
+    pub const ARCHERY: Self = Self::new(10.0, Some(0), 6);
+    /// 百分制：最高 100 分，小数位不限，不分组
+    pub const PERCENTAGE: Self = Self::new(100.0, None, 1);
+
+    pub const fn new(max_ring: f64, decimals: Option<u8>, series_size: usize) -> Self {
+        Self {
+            min_ring: 0.0,
+            max_ring,
+            decimals,
+            series_size,
+        }
+    }
+
+    /// 替换成绩范围，其他设置不变
+    pub const fn with_range(self, range: RingRange) -> Self {
+        Self {
+            min_ring: range.min,
+            max_ring: range.max,
+            ..self
+        }
+    }
+
+    /// 对应的成绩范围
+    pub fn range(&self) -> RingRange {
+        RingRange::new(self.min_ring, self.max_ring)
+    }
+
+    /// 检查成绩的小数位数是否符合配置
+    pub fn allows_precision(&self, ring: f64) -> bool {
+        match self.decimals {
+            None => true,
+            Some(decimals) => {
+                let scale = 10f64.powi(i32::from(decimals));
+                let scaled = ring * scale;
+                (scaled - scaled.round()).abs() < 1e-6
+            }
+        }
+    }
+
+    /// 按整环统计成绩分布，第 `i` 个桶表示 `[i, i + 1)` 环，最高桶包含满环
+    pub fn histogram(&self, rings: &[f64]) -> Vec<usize> {
+        let buckets = self.max_ring.max(0.0).floor() as usize + 1;
+        let mut histogram = vec![0; buckets];
+        for &ring in rings.iter().filter(|r| self.range().contains(**r)) {
+            let index = (ring.floor() as usize).min(buckets - 1);
+            histogram[index] += 1;
+        }
+        histogram
+    }
+
+    /// 按组计算成绩总和，最后一组可能不满
+    pub fn series_totals(&self, rings: &[f64]) -> Vec<f64> {
+        rings
+            .chunks(self.series_size.max(1))
+            .map(|series| series.iter().sum())
+            .collect()
+    }
+}
+
+/// 计分配置文件名，位于管理器的数据目录中
+pub const SCORING_CONFIG_FILE: &str = "scoring_config.json";
+
+/// 各科目的计分配置，保存在管理器数据目录的 [`SCORING_CONFIG_FILE`] 中
+///
+/// 成绩范围、小数位数和分组都来自这一份配置，成绩校验与统计使用同一套规则。
+/// 每个 [`crate::QmxManager`] 持有自己的配置，见 [`crate::QmxManager::set_scoring_configs`]；
+/// 不经过管理器的 [`Student`] 方法使用默认配置。
+///
+/// 默认配置只限制成绩范围（射击 0.0 ~ 10.9，射箭 0 ~ 10，其他科目百分制），
+/// 不限制小数位数、不分组；[`ScoringConfigs::standard`] 另外按项目规则限制小数位数并分组。
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct ScoringConfigs {
+    configs: HashMap<Subject, ScoringConfig>,
+    fallback: ScoringConfig,
+}
+
+impl Default for ScoringConfigs {
+    fn default() -> Self {
+        let mut configs = Self::percentage();
+        configs
+            .set_range(Subject::Shooting, RingRange::SHOOTING)
+            .set_range(Subject::Archery, RingRange::ARCHERY);
+        configs
+    }
+}
+
+impl ScoringConfigs {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 所有科目都按百分制计分
+    pub fn percentage() -> Self {
+        Self {
+            configs: HashMap::new(),
+            fallback: ScoringConfig::PERCENTAGE,
+        }
+    }
+
+    /// 射击、射箭按环数计分，其他科目百分制
+    pub fn standard() -> Self {
+        let mut configs = Self::percentage();
+        configs
+            .set(Subject::Shooting, ScoringConfig::SHOOTING)
+            .set(Subject::Archery, ScoringConfig::ARCHERY);
+        configs
+    }
+
+    /// 设置某个科目的计分配置
+    pub fn set(&mut self, subject: Subject, config: ScoringConfig) -> &mut Self {
+        debug!("科目 {:?} 的计分配置设置为 {:?}", subject, config);
+        self.configs.insert(subject, config);
+        self
+    }
+
+    /// 获取某个科目的计分配置，未配置的科目使用百分制
+    pub fn get(&self, subject: &Subject) -> ScoringConfig {
+        self.configs.get(subject).copied().unwrap_or(self.fallback)
+    }
+
+    /// 替换某个科目的成绩范围，小数位数和分组设置保持不变
+    pub fn set_range(&mut self, subject: Subject, range: RingRange) -> &mut Self {
+        let config = self.get(&subject).with_range(range);
+        self.set(subject, config)
+    }
+
+    /// 获取某个科目的成绩范围
+    pub fn range(&self, subject: &Subject) -> RingRange {
+        self.get(subject).range()
+    }
+
+    /// 从文件加载计分配置
+    pub fn read_from(path: &str) -> Result<Self> {
+        let content = std::fs::read_to_string(path)?;
+        Ok(serde_json::from_str(&content)?)
+    }
+
+    /// 从文件加载计分配置，文件不存在时返回默认配置
+    pub fn load_or_default(path: &str) -> Result<Self> {
+        match Self::read_from(path) {
+            Err(Error::Io(e)) if e.kind() == std::io::ErrorKind::NotFound => {
+                debug!("未找到计分配置文件 {}，使用默认配置", path);
+                Ok(Self::default())
+            }
+            other => other,
+        }
+    }
+
+    /// 将计分配置原子地保存到文件：先写入同目录下的临时文件再重命名
+    pub fn save_to(&self, path: &str) -> Result<()> {
+        let content = serde_json::to_string_pretty(self)?;
+        let dir = std::path::Path::new(path)
+            .parent()
+            .filter(|dir| !dir.as_os_str().is_empty())
+            .unwrap_or(std::path::Path::new("."));
+        let mut tmpfile = tempfile::NamedTempFile::new_in(dir)?;
+        tmpfile.write_all(content.as_bytes())?;
+        tmpfile.as_file().sync_all()?;
+        tmpfile
+            .persist(path)
+            .map_err(|e| Error::Other(format!("保存计分配置失败: {}", e.error)))?;
+        debug!("计分配置已保存到 {}", path);
+        Ok(())
+    }
+}
+
+/// 不经过管理器的 [`Student`] 方法使用的默认计分配置
+fn default_scoring_configs() -> &'static ScoringConfigs {
+    static DEFAULT: OnceLock<ScoringConfigs> = OnceLock::new();
+    DEFAULT.get_or_init(ScoringConfigs::default)
+}
+
 impl Student {
     pub fn new() -> Self {
         let uid = STUDENT_UID_COUNTER.fetch_add(1, Ordering::SeqCst);
+        Self::new_with_uid(uid)
+    }
+
+    /// 使用指定 UID 创建学生，不经过全局计数器
+    ///
+    /// 调用方需保证 UID 在数据库中唯一，通常由 [`crate::id::IdNamespace`] 分配。
+    pub fn new_with_uid(uid: u64) -> Self {
+        let now = Utc::now();
         let new_student = Self {
             uid,
             age: None,
-            name: "未填写".to_string(),
-            phone: "未填写".to_string(),
+            name: None,
+            phone: None,
             lesson_left: None,
             class: Class::Others,
             subject: Subject::Others,
@@ -67,6 +524,13 @@ impl Student {
             note: String::new(),
             membership_start_date: None,
             membership_end_date: None,
+            membership_tier: None,
+            guardians: Vec::new(),
+            custom_fields: BTreeMap::new(),
+            assigned_coach: None,
+            branch_id: None,
+            created_at: Some(now),
+            updated_at: Some(now),
         };
         info!("创建新用户，UID: {}", new_student.uid);
         new_student
@@ -76,78 +540,269 @@ impl Student {
         let old_age = self.age;
         self.age = age;
         match (&old_age, &self.age) {
-            (Some(old), Some(new)) => debug!("年龄从 {} 更新到 {}，对象: {}", old, new, self.name),
-            (Some(old), None) => debug!("年龄从 {} 清除，对象: {}", old, self.name),
-            (None, Some(new)) => debug!("年龄设置为 {}，对象: {}", new, self.name),
-            (None, None) => debug!("年龄保持为空，对象: {}", self.name),
+            (Some(old), Some(new)) => debug!(
+                "年龄从 {} 更新到 {}，对象: {}",
+                old,
+                new,
+                log_policy().name(&self.display_name())
+            ),
+            (Some(old), None) => debug!(
+                "年龄从 {} 清除，对象: {}",
+                old,
+                log_policy().name(&self.display_name())
+            ),
+            (None, Some(new)) => debug!(
+                "年龄设置为 {}，对象: {}",
+                new,
+                log_policy().name(&self.display_name())
+            ),
+            (None, None) => debug!(
+                "年龄保持为空，对象: {}",
+                log_policy().name(&self.display_name())
+            ),
         }
+        self.touch();
         self
     }
 
     pub fn set_name(&mut self, name: String) -> &mut Self {
-        info!("名称从 '{}' 改为 '{}'", self.name, name);
-        self.name = name;
+        let policy = log_policy();
+        info!(
+            "名称从 '{}' 改为 '{}'",
+            policy.name(&self.display_name()),
+            policy.name(&name)
+        );
+        self.name = Some(name);
+        self.touch();
         self
     }
 
     pub fn set_class(&mut self, class: Class) -> &mut Self {
         debug!("班级从 {:?} 改为 {:?}", self.class, class);
         self.class = class;
+        self.touch();
         self
     }
 
+    /// 切换班级并按进程级默认课时策略表初始化剩余课时
     pub fn set_class_with_lesson_init(&mut self, class: Class) -> &mut Self {
+        self.set_class_with_policy(class, &default_class_policies())
+    }
+
+    /// 切换班级并按指定的课时策略表初始化剩余课时
+    pub fn set_class_with_policy(&mut self, class: Class, policies: &ClassPolicyTable) -> &mut Self {
         debug!("班级从 {:?} 改为 {:?}", self.class, class);
-        self.lesson_left = match class {
-            Class::TenTry => Some(10),
-            _ => None,
+        let policy = policies.get(&class);
+        self.lesson_left = if policy.tracks_lessons {
+            policy.initial_lessons
+        } else {
+            None
         };
         self.class = class;
+        self.touch();
         self
     }
 
+    /// 按进程级默认课时策略表设置剩余课时，见 [`Student::set_lesson_left_with_policy`]
     pub fn set_lesson_left(&mut self, lesson: u32) -> &mut Self {
-        if self.lesson_left.is_none() {
-            warn!("尝试为非TenTry班级设置剩余课时: {}", self.name);
+        self.set_lesson_left_with_policy(lesson, &default_class_policies())
+    }
+
+    /// 设置剩余课时，班级按策略表不记录课时且当前没有课时时忽略并记录警告
+    pub fn set_lesson_left_with_policy(
+        &mut self,
+        lesson: u32,
+        policies: &ClassPolicyTable,
+    ) -> &mut Self {
+        if self.lesson_left.is_none() && !policies.get(&self.class).tracks_lessons {
+            warn!(
+                "尝试为不记录课时的班级设置剩余课时: {}",
+                log_policy().name(&self.display_name())
+            );
             return self;
         }
         let old_value = self.lesson_left.unwrap_or(0);
         self.lesson_left = Some(lesson);
         info!(
             "剩余课时从 {} 改为 {}，对象: {}",
-            old_value, lesson, self.name
+            old_value,
+            lesson,
+            log_policy().name(&self.display_name())
         );
+        self.touch();
         self
     }
 
+    /// 消耗一节课时
+    ///
+    /// 剩余课时为 0 或未记录课时时返回错误，不会修改数据。
+    pub fn consume_lesson(&mut self) -> Result<&mut Self> {
+        match self.lesson_left {
+            Some(0) => Err(Error::State(format!(
+                "{} 的剩余课时已用完",
+                self.display_name()
+            ))),
+            Some(left) => {
+                self.lesson_left = Some(left - 1);
+                info!(
+                    "{} 消耗一节课时，剩余课时从 {} 改为 {}",
+                    log_policy().name(&self.display_name()),
+                    left,
+                    left - 1
+                );
+                self.touch();
+                Ok(self)
+            }
+            None => Err(Error::State(format!(
+                "{} 的班级不记录课时",
+                self.display_name()
+            ))),
+        }
+    }
+
+    /// 按进程级默认课时策略表增加课时，见 [`Student::grant_lessons_with_policy`]
+    pub fn grant_lessons(&mut self, lessons: u32) -> Result<&mut Self> {
+        self.grant_lessons_with_policy(lessons, &default_class_policies())
+    }
+
+    /// 增加指定数量的课时
+    ///
+    /// 仅适用于已有课时或按策略表记录课时的班级；结果溢出时返回错误。
+    pub fn grant_lessons_with_policy(
+        &mut self,
+        lessons: u32,
+        policies: &ClassPolicyTable,
+    ) -> Result<&mut Self> {
+        let current = match self.lesson_left {
+            Some(left) => left,
+            None if policies.get(&self.class).tracks_lessons => 0,
+            None => {
+                return Err(Error::State(format!(
+                    "{} 的班级不记录课时",
+                    self.display_name()
+                )));
+            }
+        };
+        let updated = current.checked_add(lessons).ok_or_else(|| {
+            Error::InvalidInput(format!("课时数溢出: {} + {}", current, lessons))
+        })?;
+        self.lesson_left = Some(updated);
+        info!(
+            "为 {} 增加 {} 节课时，剩余课时从 {} 改为 {}",
+            log_policy().name(&self.display_name()),
+            lessons,
+            current,
+            updated
+        );
+        self.touch();
+        Ok(self)
+    }
+
     pub fn clear_lesson_left(&mut self) -> &mut Self {
         self.lesson_left = None;
-        info!("清除{}的剩余课时", self.name);
+        info!("清除{}的剩余课时", log_policy().name(&self.display_name()));
+        self.touch();
         self
     }
 
-    pub fn add_ring(&mut self, ring: f64) -> &mut Self {
-        info!("为 {} 添加新的环形数据", self.name);
+    /// 按默认计分配置检查成绩是否有效，见 [`Student::validate_ring_with`]
+    pub fn validate_ring(&self, ring: f64) -> Result<()> {
+        self.validate_ring_with(ring, default_scoring_configs())
+    }
+
+    /// 按指定的计分配置检查成绩的范围和小数位数
+    pub fn validate_ring_with(&self, ring: f64, configs: &ScoringConfigs) -> Result<()> {
+        let config = configs.get(&self.subject);
+        let range = config.range();
+        if !range.contains(ring) {
+            return Err(Error::ValidationFailed {
+                field: "rings".to_string(),
+                reason: format!(
+                    "成绩 {} 超出科目 {:?} 的有效范围 {} ~ {}",
+                    ring, self.subject, range.min, range.max
+                ),
+            });
+        }
+        if !config.allows_precision(ring) {
+            return Err(Error::ValidationFailed {
+                field: "rings".to_string(),
+                reason: format!(
+                    "成绩 {} 的小数位数超过科目 {:?} 允许的 {} 位",
+                    ring,
+                    self.subject,
+                    config.decimals.unwrap_or_default()
+                ),
+            });
+        }
+        Ok(())
+    }
+
+    /// 按默认计分配置添加成绩
+    ///
+    /// 成绩超出科目的有效范围时返回 [`Error::ValidationFailed`]，不会修改数据。
+    pub fn add_ring(&mut self, ring: f64) -> Result<&mut Self> {
+        self.add_ring_with(ring, default_scoring_configs())
+    }
+
+    /// 按指定的计分配置添加成绩
+    pub fn add_ring_with(&mut self, ring: f64, configs: &ScoringConfigs) -> Result<&mut Self> {
+        self.validate_ring_with(ring, configs)?;
+        info!(
+            "为 {} 添加新的环形数据",
+            log_policy().name(&self.display_name())
+        );
         self.rings.push(ring);
-        self
+        self.touch();
+        Ok(self)
     }
 
-    pub fn set_rings(&mut self, rings: Vec<f64>) -> &mut Self {
-        info!("为 {} 设置成绩列表，共 {} 个成绩", self.name, rings.len());
+    /// 按默认计分配置替换成绩列表
+    ///
+    /// 列表中存在无效成绩时返回 [`Error::ValidationFailed`]，原列表保持不变。
+    pub fn set_rings(&mut self, rings: Vec<f64>) -> Result<&mut Self> {
+        self.set_rings_with(rings, default_scoring_configs())
+    }
+
+    /// 按指定的计分配置替换成绩列表
+    pub fn set_rings_with(&mut self, rings: Vec<f64>, configs: &ScoringConfigs) -> Result<&mut Self> {
+        for &ring in &rings {
+            self.validate_ring_with(ring, configs)?;
+        }
+        info!(
+            "为 {} 设置成绩列表，共 {} 个成绩",
+            log_policy().name(&self.display_name()),
+            rings.len()
+        );
         self.rings = rings;
-        self
+        self.touch();
+        Ok(self)
     }
 
     pub fn update_ring_at(&mut self, index: usize, value: f64) -> Result<&mut Self> {
+        self.update_ring_at_with(index, value, default_scoring_configs())
+    }
+
+    /// 按指定的计分配置修改第 `index` 条成绩
+    pub fn update_ring_at_with(
+        &mut self,
+        index: usize,
+        value: f64,
+        configs: &ScoringConfigs,
+    ) -> Result<&mut Self> {
         if index >= self.rings.len() {
             return Err(Error::InvalidInput(format!("分数索引越界: {}，当前长度: {}", index, self.rings.len())));
         }
+        self.validate_ring_with(value, configs)?;
         let old = self.rings[index];
         self.rings[index] = value;
         info!(
             "更新 {} 的第 {} 条成绩: {} -> {}",
-            self.name, index, old, value
+            log_policy().name(&self.display_name()),
+            index,
+            old,
+            value
         );
+        self.touch();
         Ok(self)
     }
 
@@ -156,14 +811,25 @@ impl Student {
             return Err(Error::InvalidInput(format!("分数索引越界: {}，当前长度: {}", index, self.rings.len())));
         }
         let removed = self.rings.remove(index);
-        info!("删除 {} 的第 {} 条成绩: {}", self.name, index, removed);
+        info!(
+            "删除 {} 的第 {} 条成绩: {}",
+            log_policy().name(&self.display_name()),
+            index,
+            removed
+        );
+        self.touch();
         Ok(self)
     }
 
     pub fn set_note(&mut self, note: String) -> &mut Self {
         let old_note = self.note.clone();
         self.note = note;
-        debug!("备注已更新: {}. 旧长度: {} 字符", self.name, old_note.len());
+        debug!(
+            "备注已更新: {}. 旧长度: {} 字符",
+            log_policy().name(&self.display_name()),
+            old_note.len()
+        );
+        self.touch();
         self
     }
 
@@ -177,9 +843,22 @@ impl Student {
     }
 
     pub fn set_phone(&mut self, phone: String) -> &mut Self {
-        let old_phone = self.phone.clone();
-        self.phone = phone;
-        info!("电话号码从 '{}' 改为 '{}'", old_phone, self.phone);
+        let policy = log_policy();
+        info!(
+            "电话号码从 '{}' 改为 '{}'",
+            policy.phone(&self.display_phone()),
+            policy.phone(&phone)
+        );
+        self.phone = Some(phone);
+        self.touch();
+        self
+    }
+
+    /// 清除电话号码
+    pub fn clear_phone(&mut self) -> &mut Self {
+        self.phone = None;
+        info!("清除{}的电话号码", log_policy().name(&self.display_name()));
+        self.touch();
         self
     }
 
@@ -188,8 +867,11 @@ impl Student {
         self.subject = subject;
         debug!(
             "Subject changed from {:?} to {:?} for {}",
-            old_subject, self.subject, self.name
+            old_subject,
+            self.subject,
+            log_policy().name(&self.display_name())
         );
+        self.touch();
         self
     }
 
@@ -205,7 +887,7 @@ impl Student {
             (Some(start), Some(end)) => {
                 info!(
                     "设置{}的会员期限: {} 到 {}",
-                    self.name,
+                    log_policy().name(&self.display_name()),
                     start.format("%Y-%m-%d"),
                     end.format("%Y-%m-%d")
                 );
@@ -213,21 +895,22 @@ impl Student {
             (Some(start), None) => {
                 info!(
                     "设置{}的会员开始时间: {}",
-                    self.name,
+                    log_policy().name(&self.display_name()),
                     start.format("%Y-%m-%d")
                 );
             }
             (None, Some(end)) => {
                 info!(
                     "设置{}的会员结束时间: {}",
-                    self.name,
+                    log_policy().name(&self.display_name()),
                     end.format("%Y-%m-%d")
                 );
             }
             (None, None) => {
-                info!("清除{}的会员时间", self.name);
+                info!("清除{}的会员时间", log_policy().name(&self.display_name()));
             }
         }
+        self.touch();
         self
     }
 
@@ -235,9 +918,10 @@ impl Student {
         self.membership_start_date = Some(start_date);
         info!(
             "设置{}的会员开始时间: {}",
-            self.name,
+            log_policy().name(&self.display_name()),
             start_date.format("%Y-%m-%d")
         );
+        self.touch();
         self
     }
 
@@ -245,22 +929,181 @@ impl Student {
         self.membership_end_date = Some(end_date);
         info!(
             "设置{}的会员结束时间: {}",
-            self.name,
+            log_policy().name(&self.display_name()),
             end_date.format("%Y-%m-%d")
         );
+        self.touch();
+        self
+    }
+
+    /// 设置会员等级，`None` 表示不分等级
+    pub fn set_membership_tier(&mut self, tier: Option<MembershipTier>) -> &mut Self {
+        info!(
+            "{}的会员等级从 {:?} 改为 {:?}",
+            log_policy().name(&self.display_name()),
+            self.membership_tier,
+            tier
+        );
+        self.membership_tier = tier;
+        self.touch();
         self
     }
 
+    pub fn add_guardian(&mut self, guardian: Guardian) -> &mut Self {
+        info!(
+            "为{}添加监护人: {}（{}）",
+            log_policy().name(&self.display_name()),
+            log_policy().name(&guardian.name),
+            guardian.relation
+        );
+        self.guardians.push(guardian);
+        self.touch();
+        self
+    }
+
+    pub fn set_guardians(&mut self, guardians: Vec<Guardian>) -> &mut Self {
+        info!(
+            "{}的监护人设置为 {} 位",
+            log_policy().name(&self.display_name()),
+            guardians.len()
+        );
+        self.guardians = guardians;
+        self.touch();
+        self
+    }
+
+    pub fn remove_guardian_at(&mut self, index: usize) -> Result<&mut Self> {
+        if index >= self.guardians.len() {
+            return Err(Error::InvalidInput(format!(
+                "监护人索引越界: {}，当前数量: {}",
+                index,
+                self.guardians.len()
+            )));
+        }
+        let removed = self.guardians.remove(index);
+        info!(
+            "删除{}的监护人: {}",
+            log_policy().name(&self.display_name()),
+            log_policy().name(&removed.name)
+        );
+        self.touch();
+        Ok(self)
+    }
+
+    /// 设置自定义字段，已存在时覆盖
+    pub fn set_custom_field(&mut self, key: impl Into<String>, value: CustomValue) -> &mut Self {
+        let key = key.into();
+        debug!(
+            "设置{}的自定义字段 {}",
+            log_policy().name(&self.display_name()),
+            key
+        );
+        self.custom_fields.insert(key, value);
+        self.touch();
+        self
+    }
+
+    /// 删除自定义字段，返回原来的值
+    pub fn remove_custom_field(&mut self, key: &str) -> Option<CustomValue> {
+        debug!(
+            "删除{}的自定义字段 {}",
+            log_policy().name(&self.display_name()),
+            key
+        );
+        let removed = self.custom_fields.remove(key);
+        if removed.is_some() {
+            self.touch();
+        }
+        removed
+    }
+
     pub fn clear_membership(&mut self) -> &mut Self {
         self.membership_start_date = None;
         self.membership_end_date = None;
-        info!("清除{}的会员信息", self.name);
+        info!("清除{}的会员信息", log_policy().name(&self.display_name()));
+        self.touch();
+        self
+    }
+
+    /// 清除可识别个人身份的信息
+    ///
+    /// 清除姓名、年龄、电话、备注、监护人和自定义字段，保留班级、科目、成绩、课时和会员信息，
+    /// 按班级或科目的统计结果不受影响。
+    pub fn anonymize(&mut self) -> &mut Self {
+        info!("清除{}的个人信息", log_policy().name(&self.display_name()));
+        self.name = None;
+        self.age = None;
+        self.phone = None;
+        self.note = String::new();
+        self.guardians.clear();
+        self.custom_fields.clear();
+        self.touch();
+        self
+    }
+
+    /// 设置负责的教练，`None` 表示取消分配
+    ///
+    /// 不检查教练是否存在，需要校验时使用 [`crate::QmxManager::assign_coach`]。
+    pub fn set_assigned_coach(&mut self, coach: Option<u64>) -> &mut Self {
+        debug!(
+            "设置{}的教练为 {:?}",
+            log_policy().name(&self.display_name()),
+            coach
+        );
+        self.assigned_coach = coach;
+        self.touch();
+        self
+    }
+
+    /// 设置所属校区，`None` 表示不属于任何校区
+    ///
+    /// 只修改学生本身，已有的现金记录仍属于原校区，需要时使用 [`crate::QmxManager::move_student_to_branch`]。
+    pub fn set_branch_id(&mut self, branch: Option<String>) -> &mut Self {
+        debug!(
+            "设置{}的校区为 {:?}",
+            log_policy().name(&self.display_name()),
+            branch
+        );
+        self.branch_id = branch;
+        self.touch();
         self
     }
 
+    /// 创建时间，旧版本数据文件中的学生为 `None`
+    pub fn created_at(&self) -> Option<DateTime<Utc>> {
+        self.created_at
+    }
+
+    /// 设置创建时间，用于导入数据或按注入的时钟记录时间
+    pub fn set_created_at(&mut self, created_at: Option<DateTime<Utc>>) -> &mut Self {
+        self.created_at = created_at;
+        self
+    }
+
+    /// 最后修改时间，旧版本数据文件中的学生为 `None`
+    ///
+    /// 各 setter 修改数据时自动更新为当前时间。
+    pub fn updated_at(&self) -> Option<DateTime<Utc>> {
+        self.updated_at
+    }
+
+    /// 设置最后修改时间，用于导入数据或按注入的时钟记录时间
+    pub fn set_updated_at(&mut self, updated_at: Option<DateTime<Utc>>) -> &mut Self {
+        self.updated_at = updated_at;
+        self
+    }
+
+    fn touch(&mut self) {
+        self.updated_at = Some(Utc::now());
+    }
+
     /// 检查会员是否有效（当前时间在会员期内）
     pub fn is_membership_active(&self) -> bool {
-        let now = Utc::now();
+        self.is_membership_active_at(Utc::now())
+    }
+
+    /// 检查会员在指定时间是否有效
+    pub fn is_membership_active_at(&self, now: DateTime<Utc>) -> bool {
         match (&self.membership_start_date, &self.membership_end_date) {
             (Some(start), Some(end)) => now >= *start && now <= *end,
             (Some(start), None) => now >= *start, // 只有开始时间，认为永久有效
@@ -271,8 +1114,12 @@ impl Student {
 
     /// 获取会员剩余天数
     pub fn membership_days_remaining(&self) -> Option<i64> {
+        self.membership_days_remaining_at(Utc::now())
+    }
+
+    /// 获取以指定时间计算的会员剩余天数
+    pub fn membership_days_remaining_at(&self, now: DateTime<Utc>) -> Option<i64> {
         if let Some(end_date) = self.membership_end_date {
-            let now = Utc::now();
             if now <= end_date {
                 Some((end_date - now).num_days())
             } else {
@@ -286,11 +1133,22 @@ impl Student {
     pub fn uid(&self) -> u64 {
         self.uid
     }
+
+    /// 为校验通过的草稿分配正式 UID
+    pub(crate) fn set_uid(&mut self, uid: u64) -> &mut Self {
+        self.uid = uid;
+        self
+    }
     pub fn age(&self) -> Option<u8> {
         self.age
     }
-    pub fn name(&self) -> &str {
-        self.name.as_str()
+    /// 姓名，未填写时为 `None`
+    pub fn name(&self) -> Option<&str> {
+        self.name.as_deref()
+    }
+    /// 用于展示的姓名，未填写时使用 [`display_defaults`] 中的默认文本
+    pub fn display_name(&self) -> String {
+        self.name.clone().unwrap_or_else(|| display_defaults().name)
     }
     pub fn lesson_left(&self) -> Option<u32> {
         self.lesson_left
@@ -304,8 +1162,15 @@ impl Student {
     pub fn note(&self) -> &str {
         &self.note
     }
-    pub fn phone(&self) -> &str {
-        self.phone.as_str()
+    /// 电话号码，未填写时为 `None`
+    pub fn phone(&self) -> Option<&str> {
+        self.phone.as_deref()
+    }
+    /// 用于展示的电话号码，未填写时使用 [`display_defaults`] 中的默认文本
+    pub fn display_phone(&self) -> String {
+        self.phone
+            .clone()
+            .unwrap_or_else(|| display_defaults().phone)
     }
     pub fn subject(&self) -> &Subject {
         &self.subject
@@ -318,6 +1183,24 @@ impl Student {
     pub fn membership_end_date(&self) -> Option<DateTime<Utc>> {
         self.membership_end_date
     }
+    pub fn membership_tier(&self) -> Option<&MembershipTier> {
+        self.membership_tier.as_ref()
+    }
+    pub fn guardians(&self) -> &[Guardian] {
+        &self.guardians
+    }
+    pub fn custom_fields(&self) -> &BTreeMap<String, CustomValue> {
+        &self.custom_fields
+    }
+    pub fn custom_field(&self, key: &str) -> Option<&CustomValue> {
+        self.custom_fields.get(key)
+    }
+    pub fn assigned_coach(&self) -> Option<u64> {
+        self.assigned_coach
+    }
+    pub fn branch_id(&self) -> Option<&str> {
+        self.branch_id.as_deref()
+    }
 }
 
 impl Default for Student {
@@ -357,7 +1240,7 @@ pub fn load_saved_uid() -> Result<u64> {
         }
         Err(e) => {
             error!("读取UID文件失败: {}", e);
-            Err(e).map_err(Error::from)
+            Err(Error::from(e))
         }
     }
 }
@@ -369,8 +1252,10 @@ pub fn save_uid() -> Result<()> {
     file.write_all(uid.to_string().as_bytes())
         .map_err(Error::from)?;
     file.sync_all().ok();
-    if let Some(dir) = std::path::Path::new(&path).parent() {
-        if let Ok(dirf) = File::open(dir) { let _ = dirf.sync_all(); }
+    if let Some(dir) = std::path::Path::new(&path).parent()
+        && let Ok(dirf) = File::open(dir)
+    {
+        let _ = dirf.sync_all();
     }
     debug!("成功将UID: {} 保存到文件", uid);
     Ok(())
@@ -385,9 +1270,75 @@ pub fn init() -> Result<()> {
     Ok(())
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
+/// 学生数据库
+///
+/// 序列化时在学生数据之后写入 `next_uid`（下一个可用的学生 UID），
+/// 只复制数据文件到其他机器也不会与已有学生冲突。反序列化只记录该值，
+/// 不修改全局计数器；加载自己数据的一方（管理器、[`crate::database::init`]）
+/// 调用 [`StudentDatabase::sync_uid_counter`] 推进 [`STUDENT_UID_COUNTER`]。
+/// 旧版本数据文件没有该字段，仍以 `uid_counter` 文件为准。
+#[derive(Deserialize, Debug, Clone)]
+#[serde(from = "StoredStudentDatabase")]
 pub struct StudentDatabase {
+    /// 直接修改后需调用 [`StudentDatabase::rebuild_indexes`]
     pub student_data: BTreeMap<u64, Student>,
+    by_name: SecondaryIndex<String>,
+    by_phone: SecondaryIndex<String>,
+    /// 延迟加载时尚未读取成绩和备注的学生
+    pending_details: Option<PendingDetails>,
+    /// 数据文件中记录的下一个 UID
+    next_uid: u64,
+}
+
+/// 延迟加载的学生详细字段
+#[derive(Deserialize)]
+struct StudentDetails {
+    rings: Vec<f64>,
+    note: String,
+}
+
+/// 数据文件中的学生数据库
+#[derive(Deserialize)]
+struct StoredStudentDatabase {
+    student_data: BTreeMap<u64, Student>,
+    #[serde(default)]
+    next_uid: Option<u64>,
+}
+
+impl From<StoredStudentDatabase> for StudentDatabase {
+    fn from(stored: StoredStudentDatabase) -> Self {
+        let mut db = Self {
+            student_data: stored.student_data,
+            by_name: SecondaryIndex::default(),
+            by_phone: SecondaryIndex::default(),
+            pending_details: None,
+            next_uid: stored.next_uid.unwrap_or(1),
+        };
+        db.rebuild_indexes();
+        db
+    }
+}
+
+impl Serialize for StudentDatabase {
+    fn serialize<S: serde::Serializer>(
+        &self,
+        serializer: S,
+    ) -> std::result::Result<S::Ok, S::Error> {
+        use serde::ser::{Error as _, SerializeStruct};
+        if let Some(pending) = &self.pending_details {
+            return Err(S::Error::custom(format!(
+                "仍有 {} 个学生的成绩和备注未加载，保存前需调用 load_all_details",
+                pending.uids.len()
+            )));
+        }
+        let next_uid = STUDENT_UID_COUNTER
+            .load(Ordering::SeqCst)
+            .max(self.derived_next_uid());
+        let mut state = serializer.serialize_struct("StudentDatabase", 2)?;
+        state.serialize_field("student_data", &self.student_data)?;
+        state.serialize_field("next_uid", &next_uid)?;
+        state.end()
+    }
 }
 
 impl Default for StudentDatabase {
@@ -418,14 +1369,72 @@ impl Database<Student> for StudentDatabase {
         "学生"
     }
 
+    fn data_field() -> &'static str {
+        "student_data"
+    }
+
     fn new() -> Self {
         Self {
             student_data: BTreeMap::new(),
+            by_name: SecondaryIndex::default(),
+            by_phone: SecondaryIndex::default(),
+            pending_details: None,
+            next_uid: 1,
+        }
+    }
+
+    fn reindex(&mut self, uid: u64) {
+        match self.student_data.get(&uid) {
+            Some(student) => {
+                self.by_name.set(uid, student.name().map(str::to_string));
+                self.by_phone.set(
+                    uid,
+                    student
+                        .phone()
+                        .map(phone_digits)
+                        .filter(|digits| !digits.is_empty()),
+                );
+            }
+            None => {
+                self.by_name.remove(uid);
+                self.by_phone.remove(uid);
+                if let Some(pending) = &mut self.pending_details {
+                    pending.uids.remove(&uid);
+                }
+            }
+        }
+    }
+
+    fn rebuild_indexes(&mut self) {
+        self.by_name.clear();
+        self.by_phone.clear();
+        let uids: Vec<u64> = self.student_data.keys().copied().collect();
+        for uid in uids {
+            self.reindex(uid);
         }
     }
 }
 
 impl StudentDatabase {
+    /// 已有最大 UID 之后的第一个 UID，没有学生时为 1
+    fn derived_next_uid(&self) -> u64 {
+        self.student_data
+            .last_key_value()
+            .map_or(1, |(&uid, _)| uid.saturating_add(1))
+            .max(self.next_uid)
+    }
+
+    /// 把 [`STUDENT_UID_COUNTER`] 推进到数据文件中的 `next_uid` 和已有最大 UID 之后
+    ///
+    /// 只应在加载自己的数据时调用；解析备份、导入或同步得到的数据库不会影响计数器。
+    pub fn sync_uid_counter(&self) {
+        let next = self.derived_next_uid();
+        let previous = STUDENT_UID_COUNTER.fetch_max(next, Ordering::SeqCst);
+        if previous < next {
+            debug!("学生UID计数器从 {} 调整为 {}", previous, next);
+        }
+    }
+
     pub fn from_json(json: &str) -> Result<Self> {
         let deserialized =
             serde_json::from_str(json).map_err(Error::from)?;
@@ -461,6 +1470,32 @@ impl StudentDatabase {
         <Self as Database<Student>>::get(self, index)
     }
 
+    /// 记录 `uid` 修改后更新索引
+    pub fn reindex(&mut self, uid: u64) {
+        <Self as Database<Student>>::reindex(self, uid)
+    }
+
+    /// 直接修改 `student_data` 后重建索引
+    pub fn rebuild_indexes(&mut self) {
+        <Self as Database<Student>>::rebuild_indexes(self)
+    }
+
+    /// 姓名完全相同的学生，按 UID 排列
+    pub fn find_by_name(&self, name: &str) -> Vec<&Student> {
+        self.by_name
+            .get(&name.to_string())
+            .filter_map(|uid| self.student_data.get(&uid))
+            .collect()
+    }
+
+    /// 电话号码相同的学生，忽略空格、连字符等非数字字符，按 UID 排列
+    pub fn find_by_phone(&self, phone: &str) -> Vec<&Student> {
+        self.by_phone
+            .get(&phone_digits(phone))
+            .filter_map(|uid| self.student_data.get(&uid))
+            .collect()
+    }
+
     pub fn save(&self) -> Result<()> {
         <Self as Database<Student>>::save(self)
     }
@@ -473,6 +1508,64 @@ impl StudentDatabase {
         <Self as Database<Student>>::read_from(path)
     }
 
+    /// 延迟加载：跳过成绩和备注，需要时通过 [`StudentDatabase::load_details`] 读取，
+    /// 详见 [`crate::lazy`]
+    pub fn read_lazy(path: &str) -> Result<Self> {
+        let mut db = skipping_details(|| Self::read_from(path))?;
+        if !db.is_empty() {
+            db.pending_details = Some(PendingDetails::new(path, db.student_data.keys().copied()));
+        }
+        Ok(db)
+    }
+
+    /// 是否还有学生的成绩和备注未加载
+    pub fn has_pending_details(&self) -> bool {
+        self.pending_details.is_some()
+    }
+
+    /// 从原文件加载指定学生的成绩和备注，已加载的学生跳过，返回本次加载的学生数
+    pub fn load_details(&mut self, uids: &[u64]) -> Result<usize> {
+        let Some(pending) = &self.pending_details else {
+            return Ok(0);
+        };
+        let wanted = pending.among(uids);
+        if wanted.is_empty() {
+            return Ok(0);
+        }
+        let details = read_details::<StudentDetails>(&pending.path, &wanted)?;
+        let mut loaded = 0;
+        for (uid, detail) in details {
+            if let Some(student) = self.student_data.get_mut(&uid) {
+                student.rings = detail.rings;
+                student.note = detail.note;
+                loaded += 1;
+            }
+        }
+        if let Some(pending) = &mut self.pending_details {
+            pending.uids.retain(|uid| !wanted.contains(uid));
+            if pending.uids.is_empty() {
+                self.pending_details = None;
+            }
+        }
+        debug!("加载 {} 个学生的成绩和备注", loaded);
+        Ok(loaded)
+    }
+
+    /// 加载全部未加载的成绩和备注，之后数据库可以正常保存
+    pub fn load_all_details(&mut self) -> Result<usize> {
+        let uids: Vec<u64> = self
+            .pending_details
+            .as_ref()
+            .map(|pending| pending.uids.iter().copied().collect())
+            .unwrap_or_default();
+        self.load_details(&uids)
+    }
+
+    /// 容错加载，详见 [`Database::salvage_from`]
+    pub fn salvage_from(path: &str) -> Result<(Self, SalvageReport)> {
+        <Self as Database<Student>>::salvage_from(path)
+    }
+
     pub fn iter(&self) -> impl Iterator<Item = (&u64, &Student)> + '_ {
         <Self as Database<Student>>::iter(self)
     }
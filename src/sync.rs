@@ -0,0 +1,785 @@
+//! 多台电脑之间的数据同步
+//!
+//! 启用 `sync` feature 后，[`crate::QmxManager::sync`] 通过一份保存在远端的同步文档，
+//! 让前台电脑、负责人的笔记本等多个安装实例的学生和现金记录保持一致，不需要手动复制数据文件。
+//! 远端可以是支持 `GET`/`PUT` 的 HTTP 接口或 WebDAV 共享（[`HttpTransport`]），
+//! 也可以是共享文件夹中的文件（[`FileTransport`]）。
+//!
+//! 同步文档为每条记录保存最后修改时间，删除的记录保存为没有内容的墓碑。每次同步时：
+//!
+//! 1. 与本机状态文件中记录的上次同步结果比较，找出本地新增、修改和删除的记录；
+//! 2. 与远端文档逐条比较修改时间，较新的一方胜出，时间相同时以远端为准；
+//! 3. 把合并后的文档写回远端，写入带条件，远端同时被其他电脑修改时返回 [`Error::State`]，重试即可；
+//! 4. 把远端较新的修改应用到本地，并更新状态文件。
+//!
+//! 学生以 `updated_at` 为修改时间；现金记录没有修改时间，以发现修改的同步时间为准。
+//! 记录按 UID 对应，参与同步的电脑应使用 [`crate::IdStrategy::TimeOrdered`] 并配置不同的节点号，
+//! 避免各自新建的记录分配到相同的 UID。分期计划创建后不再修改，按计划 ID 取并集。
+//! 教练、课程和附件不参与同步。
+//!
+//! 管理器设置了加密密钥（[`crate::QmxManagerBuilder::encryption_key`]）时，同步文档用该密钥
+//! 加密后再写入远端，参与同步的电脑需要使用相同的密钥。[`HttpTransport`] 只支持明文 HTTP，
+//! 通过它同步时必须设置密钥，远端的未加密文档也会被拒绝，见 [`SyncTransport::is_secure`]。
+
+use crate::cash::Cash;
+use crate::database::Database;
+use crate::encryption::EncryptionKey;
+use crate::error::{Error, Result};
+use crate::merge::MergedRecord;
+use crate::plan::InstallmentPlan;
+use crate::student::Student;
+use chrono::{DateTime, Utc};
+use log::{debug, info};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::collections::btree_map::Entry;
+use std::fs::OpenOptions;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::path::PathBuf;
+use std::time::{Duration, SystemTime};
+
+/// 当前的同步文档格式版本
+pub const SYNC_FORMAT_VERSION: u32 = 1;
+
+/// 同步文档中的一条记录
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct SyncRecord<T> {
+    pub updated_at: DateTime<Utc>,
+    /// 记录内容，`None` 表示已删除
+    pub record: Option<T>,
+}
+
+/// 保存在远端的同步文档
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct SyncDocument {
+    pub format_version: u32,
+    #[serde(default)]
+    pub students: BTreeMap<u64, SyncRecord<Student>>,
+    #[serde(default)]
+    pub cash: BTreeMap<u64, SyncRecord<Cash>>,
+    /// 分期计划
+    #[serde(default)]
+    pub plans: BTreeMap<u64, InstallmentPlan>,
+}
+
+impl Default for SyncDocument {
+    fn default() -> Self {
+        Self {
+            format_version: SYNC_FORMAT_VERSION,
+            students: BTreeMap::new(),
+            cash: BTreeMap::new(),
+            plans: BTreeMap::new(),
+        }
+    }
+}
+
+/// 从远端读取的同步文档
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RemoteDocument {
+    pub bytes: Vec<u8>,
+    /// 远端的版本标识（如 HTTP 的 `ETag`），写回时用于发现并发修改
+    pub version: Option<String>,
+}
+
+/// 同步文档的读写方式
+pub trait SyncTransport: Send + Sync {
+    /// 读取远端同步文档，尚不存在时返回 `None`
+    fn fetch(&self) -> Result<Option<RemoteDocument>>;
+
+    /// 写入远端同步文档
+    ///
+    /// `expected` 为写入前读取到的文档（`None` 表示当时远端不存在），
+    /// 远端在此之后被修改过时应返回 [`Error::State`]，不覆盖远端。
+    fn store(&self, bytes: &[u8], expected: Option<&RemoteDocument>) -> Result<()>;
+
+    /// 传输过程能否防止第三方读取和篡改同步文档，默认为 `true`
+    ///
+    /// 返回 `false` 时同步文档必须加密：未设置密钥时拒绝同步，读到未加密的远端文档时返回错误。
+    fn is_secure(&self) -> bool {
+        true
+    }
+}
+
+fn remote_changed() -> Error {
+    Error::State("远端同步文档已被其他电脑修改，请重新同步".to_string())
+}
+
+/// HTTP 响应正文的最大长度，防止对端或中间人用超大的长度耗尽内存
+pub const MAX_RESPONSE_LEN: usize = 256 * 1024 * 1024;
+
+/// 锁文件存在超过该时长时视为持有者已崩溃，可以清除
+const STALE_LOCK_AFTER: Duration = Duration::from_secs(60);
+
+/// 保存在文件中的同步文档，适用于共享文件夹或已挂载到本地的 WebDAV 目录
+///
+/// 写入时在同一目录创建 `<文件名>.lock` 锁文件，持有锁期间比较版本并用临时文件整体替换，
+/// 其他电脑同时写入时返回 [`Error::State`]。
+#[derive(Debug, Clone)]
+pub struct FileTransport {
+    path: PathBuf,
+}
+
+impl FileTransport {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into() }
+    }
+
+    fn lock_path(&self) -> PathBuf {
+        let mut name = self.path.file_name().unwrap_or_default().to_os_string();
+        name.push(".lock");
+        self.path.with_file_name(name)
+    }
+}
+
+/// 同步文档的写入锁，释放时删除锁文件
+struct FileLock {
+    path: PathBuf,
+}
+
+impl FileLock {
+    fn acquire(path: PathBuf) -> Result<Self> {
+        if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+            std::fs::create_dir_all(dir)?;
+        }
+        for _ in 0..2 {
+            match OpenOptions::new().write(true).create_new(true).open(&path) {
+                Ok(_) => return Ok(Self { path }),
+                Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => {
+                    let stale = std::fs::metadata(&path)
+                        .and_then(|metadata| metadata.modified())
+                        .ok()
+                        .and_then(|modified| SystemTime::now().duration_since(modified).ok())
+                        .is_some_and(|age| age > STALE_LOCK_AFTER);
+                    if !stale {
+                        return Err(Error::State(
+                            "其他电脑正在写入远端同步文档，请稍后重新同步".to_string(),
+                        ));
+                    }
+                    debug!("清除过期的同步锁文件 {}", path.display());
+                    let _ = std::fs::remove_file(&path);
+                }
+                Err(e) => return Err(e.into()),
+            }
+        }
+        Err(Error::State(
+            "其他电脑正在写入远端同步文档，请稍后重新同步".to_string(),
+        ))
+    }
+}
+
+impl Drop for FileLock {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.path);
+    }
+}
+
+impl SyncTransport for FileTransport {
+    fn fetch(&self) -> Result<Option<RemoteDocument>> {
+        match std::fs::read(&self.path) {
+            Ok(bytes) => Ok(Some(RemoteDocument {
+                version: Some(crate::checksum::checksum(&bytes)),
+                bytes,
+            })),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    fn store(&self, bytes: &[u8], expected: Option<&RemoteDocument>) -> Result<()> {
+        let _lock = FileLock::acquire(self.lock_path())?;
+        let current = self.fetch()?.and_then(|document| document.version);
+        if current != expected.and_then(|document| document.version.clone()) {
+            return Err(remote_changed());
+        }
+        crate::common::write_atomic(&self.path, bytes)
+    }
+}
+
+/// 通过 HTTP `GET`/`PUT` 读写同步文档，适用于 WebDAV 共享或自建的同步接口
+///
+/// 基于标准库的 `TcpStream` 实现，只支持 `http://`，因此同步文档必须用管理器的密钥加密
+/// （见 [`SyncTransport::is_secure`]）。Basic 认证的密码仍以明文传输，需要 HTTPS 时可在本机
+/// 通过反向代理转发，或把 WebDAV 挂载为本地目录后使用 [`FileTransport`]。写入时根据 `ETag` 发送
+/// `If-Match`/`If-None-Match` 条件，服务器返回 412 时视为远端已被修改。
+#[derive(Debug, Clone)]
+pub struct HttpTransport {
+    host: String,
+    port: u16,
+    path: String,
+    authorization: Option<String>,
+    timeout: Duration,
+}
+
+/// HTTP 响应中同步需要的部分
+struct HttpResponse {
+    status: u16,
+    etag: Option<String>,
+    body: Vec<u8>,
+}
+
+impl HttpTransport {
+    /// `url` 为同步文档的地址，如 `http://192.168.1.10:8080/dav/qmx-sync.json`
+    pub fn new(url: &str) -> Result<Self> {
+        let invalid = || Error::InvalidInput(format!("无效的同步地址: {}", url));
+        let rest = match url.split_once("://") {
+            Some((scheme, rest)) if scheme.eq_ignore_ascii_case("http") => rest,
+            Some((scheme, _)) => {
+                return Err(Error::InvalidInput(format!(
+                    "不支持的协议 {}，只支持 http://",
+                    scheme
+                )));
+            }
+            None => return Err(invalid()),
+        };
+        let (authority, path) = match rest.find('/') {
+            Some(index) => rest.split_at(index),
+            None => (rest, "/"),
+        };
+        let (host, port) = match authority.rsplit_once(':') {
+            Some((host, port)) => (host, port.parse().map_err(|_| invalid())?),
+            None => (authority, 80),
+        };
+        if host.is_empty() {
+            return Err(invalid());
+        }
+        Ok(Self {
+            host: host.to_string(),
+            port,
+            path: path.to_string(),
+            authorization: None,
+            timeout: Duration::from_secs(30),
+        })
+    }
+
+    /// 使用 HTTP Basic 认证
+    pub fn basic_auth(mut self, user: &str, password: &str) -> Self {
+        self.authorization = Some(format!(
+            "Basic {}",
+            base64(format!("{}:{}", user, password).as_bytes())
+        ));
+        self
+    }
+
+    /// 连接和读写的超时时间，默认 30 秒
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    fn send(&self, method: &str, headers: &[(&str, &str)], body: &[u8]) -> Result<HttpResponse> {
+        let mut last_error = None;
+        let mut stream = None;
+        for addr in (self.host.as_str(), self.port).to_socket_addrs()? {
+            match TcpStream::connect_timeout(&addr, self.timeout) {
+                Ok(connected) => {
+                    stream = Some(connected);
+                    break;
+                }
+                Err(e) => last_error = Some(e),
+            }
+        }
+        let stream = match (stream, last_error) {
+            (Some(stream), _) => stream,
+            (None, Some(e)) => return Err(e.into()),
+            (None, None) => {
+                return Err(Error::NotFound(format!("无法解析主机: {}", self.host)));
+            }
+        };
+        stream.set_read_timeout(Some(self.timeout))?;
+        stream.set_write_timeout(Some(self.timeout))?;
+
+        let host = if self.port == 80 {
+            self.host.clone()
+        } else {
+            format!("{}:{}", self.host, self.port)
+        };
+        let mut request = format!(
+            "{} {} HTTP/1.1\r\nHost: {}\r\nConnection: close\r\nContent-Length: {}\r\n",
+            method,
+            self.path,
+            host,
+            body.len()
+        );
+        if let Some(authorization) = &self.authorization {
+            request.push_str(&format!("Authorization: {}\r\n", authorization));
+        }
+        for (name, value) in headers {
+            request.push_str(&format!("{}: {}\r\n", name, value));
+        }
+        request.push_str("\r\n");
+
+        let mut writer = &stream;
+        writer.write_all(request.as_bytes())?;
+        writer.write_all(body)?;
+        writer.flush()?;
+        let response = read_response(&mut BufReader::new(&stream))?;
+        debug!("{} {} -> {}", method, self.path, response.status);
+        Ok(response)
+    }
+}
+
+impl SyncTransport for HttpTransport {
+    fn fetch(&self) -> Result<Option<RemoteDocument>> {
+        let response = self.send("GET", &[], &[])?;
+        match response.status {
+            200 => Ok(Some(RemoteDocument {
+                bytes: response.body,
+                version: response.etag,
+            })),
+            404 => Ok(None),
+            status => Err(status_error("读取", status)),
+        }
+    }
+
+    fn store(&self, bytes: &[u8], expected: Option<&RemoteDocument>) -> Result<()> {
+        let mut headers = vec![("Content-Type", "application/json; charset=utf-8")];
+        match expected {
+            Some(RemoteDocument {
+                version: Some(version),
+                ..
+            }) => headers.push(("If-Match", version)),
+            Some(_) => {}
+            None => headers.push(("If-None-Match", "*")),
+        }
+        let response = self.send("PUT", &headers, bytes)?;
+        match response.status {
+            200 | 201 | 204 => Ok(()),
+            412 => Err(remote_changed()),
+            status => Err(status_error("写入", status)),
+        }
+    }
+
+    fn is_secure(&self) -> bool {
+        false
+    }
+}
+
+fn status_error(action: &str, status: u16) -> Error {
+    let message = format!("{}远端同步文档失败: HTTP {}", action, status);
+    match status {
+        401 | 403 => Error::PermissionDenied(message),
+        _ => Error::Other(message),
+    }
+}
+
+fn read_response<R: BufRead>(reader: &mut R) -> Result<HttpResponse> {
+    let mut line = String::new();
+    reader.read_line(&mut line)?;
+    let status = line
+        .split_whitespace()
+        .nth(1)
+        .and_then(|status| status.parse().ok())
+        .ok_or_else(|| Error::InvalidInput(format!("无效的响应行: {}", line.trim_end())))?;
+
+    let mut content_length = None;
+    let mut chunked = false;
+    let mut etag = None;
+    loop {
+        line.clear();
+        if reader.read_line(&mut line)? == 0 {
+            break;
+        }
+        let header = line.trim_end();
+        if header.is_empty() {
+            break;
+        }
+        let Some((name, value)) = header.split_once(':') else {
+            continue;
+        };
+        let (name, value) = (name.trim(), value.trim());
+        if name.eq_ignore_ascii_case("content-length") {
+            content_length = Some(value.parse::<usize>()?);
+        } else if name.eq_ignore_ascii_case("transfer-encoding") {
+            chunked = value.eq_ignore_ascii_case("chunked");
+        } else if name.eq_ignore_ascii_case("etag") {
+            etag = Some(value.to_string());
+        }
+    }
+
+    let mut body = Vec::new();
+    if chunked {
+        loop {
+            line.clear();
+            reader.read_line(&mut line)?;
+            let size = line.trim_end().split(';').next().unwrap_or_default();
+            let size = usize::from_str_radix(size.trim(), 16)
+                .map_err(|_| Error::InvalidInput(format!("无效的分块长度: {}", size)))?;
+            if size == 0 {
+                break;
+            }
+            let start = body.len();
+            let total = start.saturating_add(size);
+            if total > MAX_RESPONSE_LEN {
+                return Err(response_too_large(total));
+            }
+            body.resize(total, 0);
+            reader.read_exact(&mut body[start..])?;
+            line.clear();
+            reader.read_line(&mut line)?;
+        }
+    } else if let Some(length) = content_length {
+        if length > MAX_RESPONSE_LEN {
+            return Err(response_too_large(length));
+        }
+        body.resize(length, 0);
+        reader.read_exact(&mut body)?;
+    } else {
+        reader
+            .take(MAX_RESPONSE_LEN as u64 + 1)
+            .read_to_end(&mut body)?;
+        if body.len() > MAX_RESPONSE_LEN {
+            return Err(response_too_large(body.len()));
+        }
+    }
+    Ok(HttpResponse { status, etag, body })
+}
+
+fn response_too_large(length: usize) -> Error {
+    Error::InvalidInput(format!(
+        "同步响应过大: {} 字节，上限为 {} 字节",
+        length, MAX_RESPONSE_LEN
+    ))
+}
+
+fn base64(bytes: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut encoded = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for chunk in bytes.chunks(3) {
+        let byte = |i: usize| u32::from(chunk.get(i).copied().unwrap_or(0));
+        let n = (byte(0) << 16) | (byte(1) << 8) | byte(2);
+        for i in 0..4 {
+            if i <= chunk.len() {
+                encoded.push(ALPHABET[((n >> (18 - 6 * i)) & 63) as usize] as char);
+            } else {
+                encoded.push('=');
+            }
+        }
+    }
+    encoded
+}
+
+/// 一次同步的结果
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SyncReport {
+    /// 从远端应用到本地的记录数（包括删除）
+    pub pulled: SyncCount,
+    /// 写入远端的本地修改数（包括删除）
+    pub pushed: SyncCount,
+    /// 同步过程中本地又被修改、留待下次同步的记录数
+    pub deferred: usize,
+}
+
+/// 按记录类型统计的数量
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SyncCount {
+    pub students: usize,
+    pub cash: usize,
+}
+
+/// 上次同步后一条记录的状态
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+struct SyncedVersion {
+    updated_at: DateTime<Utc>,
+    /// 记录内容的校验值，已删除的记录为 `None`
+    checksum: Option<String>,
+}
+
+/// 本机上次同步的结果
+#[derive(Serialize, Deserialize, Debug, Default)]
+struct SyncState {
+    #[serde(default)]
+    students: BTreeMap<u64, SyncedVersion>,
+    #[serde(default)]
+    cash: BTreeMap<u64, SyncedVersion>,
+}
+
+/// 同步客户端
+pub struct SyncClient {
+    transport: Box<dyn SyncTransport>,
+    state_path: PathBuf,
+}
+
+impl SyncClient {
+    /// `state_path` 保存本机上次同步的结果，每台电脑各自一份，不能放在共享位置
+    pub fn new(transport: impl SyncTransport + 'static, state_path: impl Into<PathBuf>) -> Self {
+        Self {
+            transport: Box::new(transport),
+            state_path: state_path.into(),
+        }
+    }
+
+    fn load_state(&self) -> Result<SyncState> {
+        match std::fs::read(&self.state_path) {
+            Ok(bytes) => Ok(serde_json::from_slice(&bytes)?),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(SyncState::default()),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// 读取远端文档并与本地记录合并，`key` 为管理器的加密密钥
+    pub(crate) fn prepare(
+        &self,
+        students: &BTreeMap<u64, Student>,
+        cash: &BTreeMap<u64, Cash>,
+        plans: &BTreeMap<u64, InstallmentPlan>,
+        now: DateTime<Utc>,
+        key: Option<&EncryptionKey>,
+    ) -> Result<SyncPlan> {
+        let secure = self.transport.is_secure();
+        if !secure && key.is_none() {
+            return Err(Error::Encryption(
+                "通过不安全的通道同步时需要设置加密密钥".to_string(),
+            ));
+        }
+        let state = self.load_state()?;
+        let remote = self.transport.fetch()?;
+        let document: SyncDocument = match &remote {
+            Some(remote) => {
+                if !secure && !crate::encryption::is_encrypted(&remote.bytes) {
+                    return Err(Error::Encryption(
+                        "远端同步文档未加密，可能已被篡改".to_string(),
+                    ));
+                }
+                serde_json::from_slice(&crate::encryption::open(remote.bytes.clone(), key)?)?
+            }
+            None => SyncDocument::default(),
+        };
+        if document.format_version > SYNC_FORMAT_VERSION {
+            return Err(Error::InvalidInput(format!(
+                "远端同步文档格式版本 {} 高于当前支持的版本 {}，请先升级",
+                document.format_version, SYNC_FORMAT_VERSION
+            )));
+        }
+
+        let students = reconcile(students, Student::updated_at, &state.students, document.students, now)?;
+        let cash = reconcile(cash, |_| None, &state.cash, document.cash, now)?;
+        let pulled_plans: Vec<InstallmentPlan> = document
+            .plans
+            .values()
+            .filter(|plan| !plans.contains_key(&plan.plan_id))
+            .cloned()
+            .collect();
+        let mut merged_plans = document.plans;
+        let mut pushed_plans = false;
+        for (&plan_id, plan) in plans {
+            if let Entry::Vacant(entry) = merged_plans.entry(plan_id) {
+                entry.insert(plan.clone());
+                pushed_plans = true;
+            }
+        }
+        let report = SyncReport {
+            pulled: SyncCount {
+                students: students.pulls.len(),
+                cash: cash.pulls.len(),
+            },
+            pushed: SyncCount {
+                students: students.pushed,
+                cash: cash.pushed,
+            },
+            deferred: 0,
+        };
+        Ok(SyncPlan {
+            upload: remote.is_none() || students.pushed > 0 || cash.pushed > 0 || pushed_plans,
+            key: key.cloned(),
+            remote,
+            document: SyncDocument {
+                format_version: SYNC_FORMAT_VERSION,
+                students: students.merged,
+                cash: cash.merged,
+                plans: merged_plans,
+            },
+            students: students.pulls,
+            cash: cash.pulls,
+            plans: pulled_plans,
+            report,
+        })
+    }
+
+    /// 把合并后的文档写回远端
+    pub(crate) fn upload(&self, plan: &SyncPlan) -> Result<()> {
+        if !plan.upload {
+            return Ok(());
+        }
+        let bytes =
+            crate::encryption::seal(serde_json::to_vec(&plan.document)?, plan.key.as_ref())?;
+        self.transport.store(&bytes, plan.remote.as_ref())?;
+        info!(
+            "已写入远端同步文档：{} 名学生、{} 条现金记录有本地修改",
+            plan.report.pushed.students, plan.report.pushed.cash
+        );
+        Ok(())
+    }
+
+    /// 记录本次同步的结果
+    pub(crate) fn finish(&self, plan: &SyncPlan) -> Result<()> {
+        let state = SyncState {
+            students: versions(&plan.document.students)?,
+            cash: versions(&plan.document.cash)?,
+        };
+        crate::common::write_atomic(&self.state_path, &serde_json::to_vec(&state)?)
+    }
+}
+
+/// 远端较新、需要应用到本地的一条记录
+struct Pull<T> {
+    uid: u64,
+    /// 合并时本地记录内容的校验值，应用前据此确认本地没有再被修改
+    seen: Option<String>,
+    incoming: Option<T>,
+}
+
+/// 合并后待执行的同步
+pub(crate) struct SyncPlan {
+    remote: Option<RemoteDocument>,
+    document: SyncDocument,
+    upload: bool,
+    /// 写回远端时加密同步文档的密钥
+    key: Option<EncryptionKey>,
+    students: Vec<Pull<Student>>,
+    cash: Vec<Pull<Cash>>,
+    /// 本地没有的分期计划
+    plans: Vec<InstallmentPlan>,
+    pub report: SyncReport,
+}
+
+impl SyncPlan {
+    /// 把远端较新的记录应用到 `db`，返回被写入的记录和因本地又被修改而跳过的记录数
+    pub fn apply(&self, db: &mut Database) -> Result<(Vec<MergedRecord>, usize)> {
+        let mut written = Vec::new();
+        let mut deferred = 0;
+        for pull in &self.students {
+            let previous = db.student.get(&pull.uid).cloned();
+            if record_checksum(previous.as_ref())? != pull.seen {
+                deferred += 1;
+                continue;
+            }
+            match &pull.incoming {
+                Some(student) => db.student.insert(student.clone()),
+                None => {
+                    db.student.remove(&pull.uid);
+                }
+            }
+            written.push(MergedRecord::Student(pull.uid, previous));
+        }
+        for plan in &self.plans {
+            db.cash.plans.insert_if_absent(plan.clone());
+        }
+        for pull in &self.cash {
+            let previous = db.cash.get(&pull.uid).cloned();
+            if record_checksum(previous.as_ref())? != pull.seen {
+                deferred += 1;
+                continue;
+            }
+            match &pull.incoming {
+                Some(cash) => db.cash.insert(cash.clone()),
+                None => {
+                    db.cash.remove(&pull.uid);
+                }
+            }
+            written.push(MergedRecord::Cash(pull.uid, previous));
+        }
+        Ok((written, deferred))
+    }
+}
+
+/// 一类记录的合并结果
+struct Reconciled<T> {
+    merged: BTreeMap<u64, SyncRecord<T>>,
+    pulls: Vec<Pull<T>>,
+    pushed: usize,
+}
+
+/// 按修改时间合并本地和远端的一类记录
+fn reconcile<T: Serialize + Clone>(
+    local: &BTreeMap<u64, T>,
+    modified_at: impl Fn(&T) -> Option<DateTime<Utc>>,
+    state: &BTreeMap<u64, SyncedVersion>,
+    mut remote: BTreeMap<u64, SyncRecord<T>>,
+    now: DateTime<Utc>,
+) -> Result<Reconciled<T>> {
+    // 本地每条记录（包括上次同步后删除的）的当前版本
+    let mut versions: BTreeMap<u64, (SyncRecord<T>, Option<String>)> = BTreeMap::new();
+    for (&uid, record) in local {
+        let checksum = record_checksum(Some(record))?;
+        let updated_at = match state.get(&uid) {
+            Some(synced) if synced.checksum == checksum => synced.updated_at,
+            Some(synced) => modified_at(record)
+                .filter(|&at| at > synced.updated_at)
+                .unwrap_or(now),
+            None => modified_at(record).unwrap_or(now),
+        };
+        let record = Some(record.clone());
+        versions.insert(uid, (SyncRecord { updated_at, record }, checksum));
+    }
+    for (&uid, synced) in state {
+        if !local.contains_key(&uid) {
+            let updated_at = if synced.checksum.is_some() { now } else { synced.updated_at };
+            versions.insert(uid, (SyncRecord { updated_at, record: None }, None));
+        }
+    }
+
+    let mut merged = BTreeMap::new();
+    let mut pulls = Vec::new();
+    let mut pushed = 0;
+    for (uid, (mine, checksum)) in versions {
+        let Some(theirs) = remote.remove(&uid) else {
+            pushed += 1;
+            merged.insert(uid, mine);
+            continue;
+        };
+        if record_checksum(theirs.record.as_ref())? == checksum {
+            merged.insert(uid, theirs);
+        } else if mine.updated_at > theirs.updated_at {
+            pushed += 1;
+            merged.insert(uid, mine);
+        } else {
+            let seen = if local.contains_key(&uid) { checksum } else { None };
+            pulls.push(Pull {
+                uid,
+                seen,
+                incoming: theirs.record.clone(),
+            });
+            merged.insert(uid, theirs);
+        }
+    }
+    for (uid, theirs) in remote {
+        if let Some(record) = &theirs.record {
+            pulls.push(Pull {
+                uid,
+                seen: None,
+                incoming: Some(record.clone()),
+            });
+        }
+        merged.insert(uid, theirs);
+    }
+    Ok(Reconciled {
+        merged,
+        pulls,
+        pushed,
+    })
+}
+
+fn record_checksum<T: Serialize>(record: Option<&T>) -> Result<Option<String>> {
+    record
+        .map(|record| Ok(crate::checksum::checksum(&serde_json::to_vec(record)?)))
+        .transpose()
+}
+
+fn versions<T: Serialize>(
+    records: &BTreeMap<u64, SyncRecord<T>>,
+) -> Result<BTreeMap<u64, SyncedVersion>> {
+    records
+        .iter()
+        .map(|(&uid, record)| {
+            Ok((
+                uid,
+                SyncedVersion {
+                    updated_at: record.updated_at,
+                    checksum: record_checksum(record.record.as_ref())?,
+                },
+            ))
+        })
+        .collect()
+}
+
@@ -0,0 +1,243 @@
+//! 可插拔的校验规则
+//!
+//! 调用方把规则注册到 [`Validator`]，再通过 [`crate::QmxManager::with_validator`] 交给管理器，
+//! `create_student`、`record_cash` 及其批量版本会在写入前执行全部规则，
+//! 有任何规则不通过时返回 [`Error::Validation`]，其中包含所有违反的规则，而不只是第一条。
+//!
+//! ```rust
+//! use qmx_backend_lib::validation::{Rule, Validator};
+//!
+//! let validator = Validator::new()
+//!     .student_rule(Rule::phone_pattern(&["1##########"]))
+//!     .student_rule(Rule::age_between(6, 80))
+//!     .student_rule(Rule::student_note_max_len(200))
+//!     .cash_rule(Rule::non_zero_amount());
+//! ```
+//!
+//! 内置规则不够用时可以用 [`Rule::new`] 编写任意检查，例如借助正则表达式库校验手机号。
+
+use crate::cash::Cash;
+use crate::error::{Error, Result};
+use crate::student::Student;
+use serde::Serialize;
+use std::fmt;
+use std::sync::Arc;
+
+/// 一条未通过的规则
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Violation {
+    /// 出错的字段
+    pub field: String,
+    /// 错误说明
+    pub message: String,
+}
+
+impl Violation {
+    pub fn new(field: impl Into<String>, message: impl Into<String>) -> Self {
+        Self {
+            field: field.into(),
+            message: message.into(),
+        }
+    }
+}
+
+impl fmt::Display for Violation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.field, self.message)
+    }
+}
+
+type Check<T> = dyn Fn(&T) -> std::result::Result<(), String> + Send + Sync;
+
+/// 作用于 `T`（[`Student`] 或 [`Cash`]）的一条校验规则
+pub struct Rule<T> {
+    field: String,
+    check: Arc<Check<T>>,
+}
+
+impl<T> Clone for Rule<T> {
+    fn clone(&self) -> Self {
+        Self {
+            field: self.field.clone(),
+            check: Arc::clone(&self.check),
+        }
+    }
+}
+
+impl<T> fmt::Debug for Rule<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Rule").field("field", &self.field).finish()
+    }
+}
+
+impl<T> Rule<T> {
+    /// 自定义规则，`check` 返回 `Err(说明)` 表示不通过
+    pub fn new<F>(field: impl Into<String>, check: F) -> Self
+    where
+        F: Fn(&T) -> std::result::Result<(), String> + Send + Sync + 'static,
+    {
+        Self {
+            field: field.into(),
+            check: Arc::new(check),
+        }
+    }
+
+    /// 规则检查的字段
+    pub fn field(&self) -> &str {
+        &self.field
+    }
+
+    fn evaluate(&self, value: &T) -> Option<Violation> {
+        (self.check)(value)
+            .err()
+            .map(|message| Violation::new(self.field.clone(), message))
+    }
+}
+
+impl Rule<Student> {
+    /// 手机号须匹配任一格式，未填写手机号时不检查
+    ///
+    /// 格式中 `#` 匹配一位数字，`?` 匹配任意一个字符，其他字符按原样匹配，
+    /// 如 `"1##########"` 匹配 11 位以 1 开头的手机号。
+    pub fn phone_pattern(patterns: &[&str]) -> Self {
+        let patterns: Vec<String> = patterns.iter().map(|p| p.to_string()).collect();
+        Self::new("phone", move |student: &Student| match student.phone() {
+            Some(phone) if !patterns.iter().any(|p| matches_pattern(p, phone)) => {
+                Err(format!("手机号 {} 格式不正确", phone))
+            }
+            _ => Ok(()),
+        })
+    }
+
+    /// 年龄须在闭区间 `[min, max]` 内，未填写年龄时不检查
+    pub fn age_between(min: u8, max: u8) -> Self {
+        Self::new("age", move |student: &Student| match student.age() {
+            Some(age) if age < min || age > max => {
+                Err(format!("年龄 {} 不在 {}-{} 之间", age, min, max))
+            }
+            _ => Ok(()),
+        })
+    }
+
+    /// 备注长度（字符数）不超过 `max`
+    pub fn student_note_max_len(max: usize) -> Self {
+        Self::new("note", move |student: &Student| {
+            check_len(student.note(), max)
+        })
+    }
+}
+
+impl Rule<Cash> {
+    /// 金额不能为 0
+    pub fn non_zero_amount() -> Self {
+        Self::new("cash", |cash: &Cash| {
+            if cash.cash.is_zero() {
+                Err("金额不能为 0".to_string())
+            } else {
+                Ok(())
+            }
+        })
+    }
+
+    /// 备注长度（字符数）不超过 `max`，没有备注时不检查
+    pub fn cash_note_max_len(max: usize) -> Self {
+        Self::new("note", move |cash: &Cash| {
+            cash.note
+                .as_deref()
+                .map_or(Ok(()), |note| check_len(note, max))
+        })
+    }
+}
+
+/// 已注册的校验规则集合，默认不包含任何规则
+#[derive(Debug, Clone, Default)]
+pub struct Validator {
+    student_rules: Vec<Rule<Student>>,
+    cash_rules: Vec<Rule<Cash>>,
+}
+
+impl Validator {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 注册学生规则
+    pub fn student_rule(mut self, rule: Rule<Student>) -> Self {
+        self.student_rules.push(rule);
+        self
+    }
+
+    /// 注册现金记录规则
+    pub fn cash_rule(mut self, rule: Rule<Cash>) -> Self {
+        self.cash_rules.push(rule);
+        self
+    }
+
+    /// 是否没有注册任何规则
+    pub fn is_empty(&self) -> bool {
+        self.student_rules.is_empty() && self.cash_rules.is_empty()
+    }
+
+    /// 按注册顺序执行全部学生规则，返回所有违反的规则
+    pub fn check_student(&self, student: &Student) -> Vec<Violation> {
+        evaluate(&self.student_rules, student)
+    }
+
+    /// 按注册顺序执行全部现金记录规则，返回所有违反的规则
+    pub fn check_cash(&self, cash: &Cash) -> Vec<Violation> {
+        evaluate(&self.cash_rules, cash)
+    }
+
+    /// 执行学生规则，有违反时返回 [`Error::Validation`]
+    pub fn validate_student(&self, student: &Student) -> Result<()> {
+        into_result(self.check_student(student))
+    }
+
+    /// 执行现金记录规则，有违反时返回 [`Error::Validation`]
+    pub fn validate_cash(&self, cash: &Cash) -> Result<()> {
+        into_result(self.check_cash(cash))
+    }
+}
+
+fn evaluate<T>(rules: &[Rule<T>], value: &T) -> Vec<Violation> {
+    rules
+        .iter()
+        .filter_map(|rule| rule.evaluate(value))
+        .collect()
+}
+
+fn into_result(violations: Vec<Violation>) -> Result<()> {
+    if violations.is_empty() {
+        Ok(())
+    } else {
+        Err(Error::Validation(violations))
+    }
+}
+
+fn check_len(value: &str, max: usize) -> std::result::Result<(), String> {
+    let len = value.chars().count();
+    if len > max {
+        Err(format!("长度 {} 超过上限 {}", len, max))
+    } else {
+        Ok(())
+    }
+}
+
+/// 按字符匹配格式：`#` 为数字，`?` 为任意字符，其他字符须相同
+fn matches_pattern(pattern: &str, value: &str) -> bool {
+    let mut value = value.chars();
+    for expected in pattern.chars() {
+        let Some(c) = value.next() else {
+            return false;
+        };
+        let ok = match expected {
+            '#' => c.is_ascii_digit(),
+            '?' => true,
+            expected => c == expected,
+        };
+        if !ok {
+            return false;
+        }
+    }
+    value.next().is_none()
+}
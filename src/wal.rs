@@ -0,0 +1,462 @@
+//! 预写日志（WAL）与崩溃恢复
+//!
+//! 使用文件持久化时，[`crate::QmxManager`] 的每次修改在写入数据文件之前先追加到数据目录中的
+//! `wal.jsonl`（每行一条记录修改后的完整内容），数据文件保存成功后清空日志。
+//! 未启用自动保存或保存前进程崩溃时，[`crate::database::init`] 和管理器加载数据时
+//! 会按顺序重放日志中尚未保存的修改，而不是静默丢失上次保存之后的操作。
+//!
+//! # 保证
+//!
+//! 学生、现金和分期计划在持有数据库写锁时先修改内存，再追加日志，最后才释放写锁。
+//! 追加失败时管理器按修改前的状态撤回内存中的修改并返回错误，因此其他线程能看到的修改、
+//! 以及之后写入数据文件的修改，都已经同步写入日志。
+//!
+//! 日志同样覆盖审计日志、教练、课程排期、价目表和定期支出：审计记录在写入内存后追加，
+//! 随数据文件一起保存；其余几个数据库在每次修改时立即保存，日志在保存文件之前追加，
+//! 两者之间崩溃时由重放补上。这些记录带有数据文件的路径，重放时直接写入对应文件。
+//!
+//! 最后一行不完整（追加时崩溃）时忽略该行，其他行损坏时返回错误，避免在不知情的情况下丢失数据。
+//! 日志使用所属管理器的编码方式（见 [`crate::common::FileCodec`]），
+//! 设置了加密密钥时每行单独加密后以十六进制写入。
+
+use crate::audit::{AuditDatabase, AuditEntry};
+use crate::cash::{CASH_UID_COUNTER, Cash};
+use crate::catalog::{CatalogDatabase, CatalogItem};
+use crate::coach::{Coach, CoachDatabase};
+use crate::common::{Database as _, FileCodec, HasUid};
+use crate::database::Database;
+use crate::error::{Error, Result};
+use crate::plan::InstallmentPlan;
+use crate::recurring::{RecurringDatabase, RecurringExpense};
+use crate::schedule::{Session, SessionDatabase};
+use crate::student::{STUDENT_UID_COUNTER, Student};
+use log::{debug, info, warn};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::collections::btree_map::Entry;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs::{File, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::path::Path;
+use std::sync::Mutex;
+use std::sync::atomic::Ordering;
+
+/// 数据目录中预写日志的文件名
+pub const WAL_FILE: &str = "wal.jsonl";
+
+/// 预写日志的默认路径
+pub const WAL_PATH: &str = "./data/wal.jsonl";
+
+/// 预写日志中的一条记录：修改后的完整内容，`None` 表示被删除
+///
+/// 分期记录同时带上所属计划，重放时补回尚未保存的计划。
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub enum WalRecord {
+    Student {
+        uid: u64,
+        record: Option<Student>,
+    },
+    Cash {
+        uid: u64,
+        record: Option<Cash>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        plan: Option<InstallmentPlan>,
+    },
+    Plan {
+        plan_id: u64,
+        record: Option<InstallmentPlan>,
+    },
+    /// 保存在 `path` 中的审计日志新增的一条记录
+    Audit {
+        path: String,
+        entry: AuditEntry,
+    },
+    Coach {
+        path: String,
+        uid: u64,
+        record: Option<Coach>,
+    },
+    Session {
+        path: String,
+        uid: u64,
+        record: Option<Session>,
+    },
+    CatalogItem {
+        path: String,
+        uid: u64,
+        record: Option<CatalogItem>,
+    },
+    Recurring {
+        path: String,
+        uid: u64,
+        record: Option<RecurringExpense>,
+    },
+}
+
+impl WalRecord {
+    /// 按 `db` 中的当前内容生成学生记录
+    pub fn student(db: &Database, uid: u64) -> Self {
+        Self::Student {
+            uid,
+            record: db.student.get(&uid).cloned(),
+        }
+    }
+
+    /// 按 `db` 中的当前内容生成现金记录
+    pub fn cash(db: &Database, uid: u64) -> Self {
+        let record = db.cash.get(&uid).cloned();
+        let plan = record
+            .as_ref()
+            .and_then(Cash::installment_plan_id)
+            .and_then(|plan_id| db.cash.plans.get(&plan_id).cloned());
+        Self::Cash { uid, record, plan }
+    }
+
+    /// 按 `db` 中的当前内容生成分期计划记录
+    pub fn plan(db: &Database, plan_id: u64) -> Self {
+        Self::Plan {
+            plan_id,
+            record: db.cash.plans.get(&plan_id).cloned(),
+        }
+    }
+
+    /// 是否属于学生和现金数据库（包括分期计划）
+    fn is_core(&self) -> bool {
+        matches!(
+            self,
+            Self::Student { .. } | Self::Cash { .. } | Self::Plan { .. }
+        )
+    }
+
+    fn apply(self, db: &mut Database) {
+        match self {
+            Self::Student { uid, record } => match record {
+                Some(student) => {
+                    STUDENT_UID_COUNTER.fetch_max(uid.saturating_add(1), Ordering::SeqCst);
+                    db.student.insert(student);
+                }
+                None => {
+                    db.student.remove(&uid);
+                }
+            },
+            Self::Cash { uid, record, plan } => {
+                if let Some(plan) = plan {
+                    db.cash.plans.insert_if_absent(plan);
+                }
+                match record {
+                    Some(cash) => {
+                        CASH_UID_COUNTER.fetch_max(uid.saturating_add(1), Ordering::SeqCst);
+                        db.cash.insert(cash);
+                    }
+                    None => {
+                        db.cash.remove(&uid);
+                    }
+                }
+            }
+            Self::Plan { plan_id, record } => match record {
+                Some(plan) => db.cash.plans.insert(plan),
+                None => {
+                    db.cash.plans.remove(&plan_id);
+                }
+            },
+            _ => {}
+        }
+    }
+}
+
+/// 预写日志文件
+#[derive(Debug)]
+pub struct WriteAheadLog {
+    path: String,
+    /// 加密每一行所用的编码方式
+    codec: FileCodec,
+    /// 串行化追加与清空
+    lock: Mutex<()>,
+}
+
+impl WriteAheadLog {
+    /// 使用进程默认的编码方式
+    pub fn new(path: impl Into<String>) -> Self {
+        Self::with_codec(path, FileCodec::process_default())
+    }
+
+    pub fn with_codec(path: impl Into<String>, codec: FileCodec) -> Self {
+        Self {
+            path: path.into(),
+            codec,
+            lock: Mutex::new(()),
+        }
+    }
+
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    /// 追加记录并同步到磁盘
+    pub fn append(&self, records: &[WalRecord]) -> Result<()> {
+        if records.is_empty() {
+            return Ok(());
+        }
+        let mut lines = Vec::new();
+        for record in records {
+            encode_line(&mut lines, record, &self.codec)?;
+        }
+        let _guard = self.lock.lock().unwrap_or_else(|e| e.into_inner());
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?;
+        file.write_all(&lines)?;
+        file.sync_data()?;
+        debug!("预写日志追加 {} 条记录", records.len());
+        Ok(())
+    }
+
+    /// 数据文件已保存，清空日志
+    pub fn commit(&self) -> Result<()> {
+        let _guard = self.lock.lock().unwrap_or_else(|e| e.into_inner());
+        if !Path::new(&self.path).exists() {
+            return Ok(());
+        }
+        File::create(&self.path)?.sync_all()?;
+        debug!("预写日志已清空");
+        Ok(())
+    }
+
+    /// 按 `db` 和 `audit` 中的当前内容重写日志，日志中不再保留记录的旧版本
+    ///
+    /// 清除个人信息后调用，避免被清除的内容仍留在日志中。
+    pub fn rewrite(&self, db: &Database, audit: &AuditDatabase) -> Result<()> {
+        let _guard = self.lock.lock().unwrap_or_else(|e| e.into_inner());
+        let mut students = BTreeSet::new();
+        let mut cash = BTreeSet::new();
+        let mut plans = BTreeSet::new();
+        let mut others = Vec::new();
+        for record in read_records(&self.path, &self.codec)? {
+            match record {
+                WalRecord::Student { uid, .. } => {
+                    students.insert(uid);
+                }
+                WalRecord::Cash { uid, .. } => {
+                    cash.insert(uid);
+                }
+                WalRecord::Plan { plan_id, .. } => {
+                    plans.insert(plan_id);
+                }
+                // 审计记录按脱敏后的内容重写
+                WalRecord::Audit { path, entry } => {
+                    if let Some(entry) = audit.get(&entry.uid) {
+                        others.push(WalRecord::Audit {
+                            path,
+                            entry: entry.clone(),
+                        });
+                    }
+                }
+                other => others.push(other),
+            }
+        }
+        if students.is_empty() && cash.is_empty() && plans.is_empty() && others.is_empty() {
+            return Ok(());
+        }
+        let mut lines = Vec::new();
+        let records = plans
+            .into_iter()
+            .map(|plan_id| WalRecord::plan(db, plan_id))
+            .chain(students.into_iter().map(|uid| WalRecord::student(db, uid)))
+            .chain(cash.into_iter().map(|uid| WalRecord::cash(db, uid)))
+            .chain(others);
+        for record in records {
+            encode_line(&mut lines, &record, &self.codec)?;
+        }
+        let dir = Path::new(&self.path)
+            .parent()
+            .filter(|dir| !dir.as_os_str().is_empty())
+            .unwrap_or(Path::new("."));
+        let mut tmpfile = tempfile::NamedTempFile::new_in(dir)?;
+        tmpfile.write_all(&lines)?;
+        tmpfile.as_file().sync_all()?;
+        tmpfile
+            .persist(&self.path)
+            .map_err(|e| Error::Other(format!("重写预写日志失败: {}", e.error)))?;
+        debug!("预写日志已按当前内容重写");
+        Ok(())
+    }
+
+    /// 读取日志中尚未保存的记录，日志不存在时返回空列表
+    pub fn read(&self) -> Result<Vec<WalRecord>> {
+        read_records(&self.path, &self.codec)
+    }
+
+    /// 把日志中学生、现金和分期计划尚未保存的修改应用到 `db`，返回重放的记录数
+    pub fn replay(&self, db: &mut Database) -> Result<usize> {
+        let records: Vec<WalRecord> = self
+            .read()?
+            .into_iter()
+            .filter(WalRecord::is_core)
+            .collect();
+        let count = records.len();
+        for record in records {
+            record.apply(db);
+        }
+        if count > 0 {
+            crate::cash::sync_plan_id_counter(&db.cash);
+            info!("从预写日志 {} 恢复 {} 条未保存的修改", self.path, count);
+        }
+        Ok(count)
+    }
+
+    /// 把日志中审计日志、教练、课程排期、价目表和定期支出的记录写入记录中的数据文件，
+    /// 返回重放的记录数
+    ///
+    /// 同一文件只读写一次。需在加载这些数据库之前调用。
+    pub fn replay_files(&self) -> Result<usize> {
+        let records: Vec<WalRecord> = self
+            .read()?
+            .into_iter()
+            .filter(|record| !record.is_core())
+            .collect();
+        let count = records.len();
+        let mut audits: BTreeMap<String, AuditDatabase> = BTreeMap::new();
+        let mut coaches: BTreeMap<String, CoachDatabase> = BTreeMap::new();
+        let mut sessions: BTreeMap<String, SessionDatabase> = BTreeMap::new();
+        let mut catalogs: BTreeMap<String, CatalogDatabase> = BTreeMap::new();
+        let mut recurring: BTreeMap<String, RecurringDatabase> = BTreeMap::new();
+        for record in records {
+            match record {
+                WalRecord::Audit { path, entry } => {
+                    load_file(&mut audits, path, &self.codec)?.insert(entry);
+                }
+                WalRecord::Coach { path, uid, record } => {
+                    apply_record(load_file(&mut coaches, path, &self.codec)?, uid, record);
+                }
+                WalRecord::Session { path, uid, record } => {
+                    apply_record(load_file(&mut sessions, path, &self.codec)?, uid, record);
+                }
+                WalRecord::CatalogItem { path, uid, record } => {
+                    let catalog = match catalogs.entry(path) {
+                        Entry::Occupied(entry) => entry.into_mut(),
+                        Entry::Vacant(entry) => {
+                            let loaded =
+                                CatalogDatabase::load_or_new_with(entry.key(), &self.codec)?;
+                            entry.insert(loaded)
+                        }
+                    };
+                    apply_record(catalog, uid, record);
+                }
+                WalRecord::Recurring { path, uid, record } => {
+                    apply_record(load_file(&mut recurring, path, &self.codec)?, uid, record);
+                }
+                _ => {}
+            }
+        }
+        save_files(audits, &self.codec)?;
+        save_files(coaches, &self.codec)?;
+        save_files(sessions, &self.codec)?;
+        save_files(catalogs, &self.codec)?;
+        save_files(recurring, &self.codec)?;
+        Ok(count)
+    }
+}
+
+/// 取出 `path` 对应的数据库，第一次用到时从文件加载
+fn load_file<'a, D, T>(
+    files: &'a mut BTreeMap<String, D>,
+    path: String,
+    codec: &FileCodec,
+) -> Result<&'a mut D>
+where
+    D: crate::common::Database<T> + DeserializeOwned,
+    T: Serialize + DeserializeOwned + Clone,
+{
+    Ok(match files.entry(path) {
+        Entry::Occupied(entry) => entry.into_mut(),
+        Entry::Vacant(entry) => {
+            let loaded = D::load_or_new_with(entry.key(), codec)?;
+            entry.insert(loaded)
+        }
+    })
+}
+
+fn apply_record<D, T>(db: &mut D, uid: u64, record: Option<T>)
+where
+    D: crate::common::Database<T>,
+    T: Serialize + DeserializeOwned + Clone + HasUid,
+{
+    match record {
+        Some(record) => db.insert(record),
+        None => {
+            db.remove(&uid);
+        }
+    }
+}
+
+fn save_files<D, T>(files: BTreeMap<String, D>, codec: &FileCodec) -> Result<()>
+where
+    D: crate::common::Database<T> + Serialize,
+    T: Serialize + DeserializeOwned + Clone,
+{
+    for (path, db) in files {
+        db.save_to_with(&path, codec)?;
+        info!("从预写日志恢复{}数据库 {}", db.type_name(), path);
+    }
+    Ok(())
+}
+
+fn read_records(path: &str, codec: &FileCodec) -> Result<Vec<WalRecord>> {
+    let file = match File::open(path) {
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        other => other?,
+    };
+    let mut records = Vec::new();
+    let mut lines = BufReader::new(file).lines().enumerate().peekable();
+    while let Some((index, line)) = lines.next() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        match decode_line(&line, codec) {
+            Ok(record) => records.push(record),
+            Err(e) if lines.peek().is_none() => {
+                warn!("预写日志最后一行不完整，已忽略: {}", e);
+            }
+            Err(e) => {
+                return Err(Error::InvalidInput(format!(
+                    "预写日志第 {} 行已损坏: {}",
+                    index + 1,
+                    e
+                )));
+            }
+        }
+    }
+    Ok(records)
+}
+
+/// 把一条记录编码为一行，设置了加密密钥时加密后以十六进制写入
+fn encode_line(out: &mut Vec<u8>, record: &WalRecord, codec: &FileCodec) -> Result<()> {
+    let json = serde_json::to_vec(record)?;
+    if let Some(key) = &codec.key {
+        for byte in crate::encryption::seal(json, Some(key))? {
+            out.extend_from_slice(format!("{:02x}", byte).as_bytes());
+        }
+    } else {
+        out.extend_from_slice(&json);
+    }
+    out.push(b'\n');
+    Ok(())
+}
+
+fn decode_line(line: &str, codec: &FileCodec) -> Result<WalRecord> {
+    let line = line.trim();
+    if line.starts_with('{') {
+        return Ok(serde_json::from_str(line)?);
+    }
+    let bytes = (0..line.len())
+        .step_by(2)
+        .map(|i| {
+            line.get(i..i + 2)
+                .and_then(|pair| u8::from_str_radix(pair, 16).ok())
+                .ok_or_else(|| Error::InvalidInput("预写日志行不是有效的十六进制".to_string()))
+        })
+        .collect::<Result<Vec<u8>>>()?;
+    Ok(serde_json::from_slice(&codec.open(bytes)?)?)
+}
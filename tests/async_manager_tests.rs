@@ -0,0 +1,93 @@
+// 测试异步管理器接口，需要启用 tokio feature
+#![cfg(feature = "tokio")]
+
+use qmx_backend_lib::{AsyncQmxManager, CashBuilder, QmxManager, StudentBuilder, StudentQuery};
+use tempfile::TempDir;
+
+fn setup() -> TempDir {
+    let temp_dir = TempDir::new().unwrap();
+    std::env::set_current_dir(temp_dir.path()).unwrap();
+    let _ = std::fs::create_dir_all("data");
+    temp_dir
+}
+
+mod async_manager_tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_async_operations_and_save() {
+        let _temp_dir = setup();
+        let manager = AsyncQmxManager::new(QmxManager::builder().auto_save(false).build().unwrap());
+
+        let uid = manager
+            .create_student(StudentBuilder::new("异步学生").age(14))
+            .await
+            .unwrap();
+        manager
+            .record_cash(CashBuilder::new(600).student_id(uid))
+            .await
+            .unwrap();
+        manager.save().await.unwrap();
+
+        let found = manager
+            .search_students(StudentQuery::new().name_contains("异步"))
+            .await
+            .unwrap();
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].uid(), uid);
+
+        let cash = manager.run(move |m| m.get_student_cash(uid)).await.unwrap();
+        assert_eq!(cash.len(), 1);
+
+        // 保存结果可以被同步接口重新加载
+        let reloaded = QmxManager::builder().auto_save(false).build().unwrap();
+        assert_eq!(reloaded.list_students().unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_background_panic_returns_error() {
+        let _temp_dir = setup();
+        let manager = AsyncQmxManager::new(QmxManager::builder().auto_save(false).build().unwrap());
+
+        let result: qmx_backend_lib::error::Result<()> =
+            manager.run(|_| panic!("后台操作失败")).await;
+        assert!(result.is_err());
+
+        // 同步接口仍然可用
+        assert!(manager.manager().list_students().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_calls_share_bounded_pool() {
+        let _temp_dir = setup();
+        let runtime = tokio::runtime::Builder::new_multi_thread()
+            .max_blocking_threads(2)
+            .build()
+            .unwrap();
+        let manager = AsyncQmxManager::new(QmxManager::builder().auto_save(false).build().unwrap());
+
+        // 并发调用数超过线程池大小时排队执行，不会为每次调用创建新线程
+        let threads = runtime.block_on(async {
+            let calls = (0..16).map(|i| {
+                let manager = manager.clone();
+                tokio::spawn(async move {
+                    manager
+                        .create_student(StudentBuilder::new(format!("学生{}", i)))
+                        .await
+                        .unwrap();
+                    manager
+                        .run(|_| Ok(std::thread::current().id()))
+                        .await
+                        .unwrap()
+                })
+            });
+            let mut threads = std::collections::HashSet::new();
+            for call in calls.collect::<Vec<_>>() {
+                threads.insert(call.await.unwrap());
+            }
+            threads
+        });
+        assert!(threads.len() <= 2);
+        assert_eq!(manager.manager().list_students().unwrap().len(), 16);
+    }
+}
@@ -24,12 +24,14 @@ fn test_improved_atomic_write() {
         .unwrap();
 
     // 使用改进的原子写入
-    let manager = QmxManager::from_path(
-        student_db_path.to_str().unwrap(),
-        cash_db_path.to_str().unwrap(),
-        true,
-    )
-    .unwrap();
+    let manager = QmxManager::builder()
+        .database_files(
+            student_db_path.to_str().unwrap(),
+            cash_db_path.to_str().unwrap(),
+        )
+        .auto_save(true)
+        .build()
+        .unwrap();
 
     // 创建一些数据
     let student_id = manager
@@ -69,12 +71,14 @@ fn test_database_operations_with_improved_save() {
             .save_to_simple(cash_db_path.to_str().unwrap())
             .unwrap();
 
-        let manager = QmxManager::from_path(
-            student_db_path.to_str().unwrap(),
-            cash_db_path.to_str().unwrap(),
-            true,
-        )
-        .unwrap();
+        let manager = QmxManager::builder()
+            .database_files(
+                student_db_path.to_str().unwrap(),
+                cash_db_path.to_str().unwrap(),
+            )
+            .auto_save(true)
+            .build()
+            .unwrap();
 
         let student_id = manager
             .create_student(StudentBuilder::new("持久化测试").age(25))
@@ -95,19 +99,21 @@ fn test_database_operations_with_improved_save() {
 
     // 第二阶段：重新加载数据验证持久化
     {
-        let manager = QmxManager::from_path(
-            student_db_path.to_str().unwrap(),
-            cash_db_path.to_str().unwrap(),
-            false,
-        )
-        .unwrap();
+        let manager = QmxManager::builder()
+            .database_files(
+                student_db_path.to_str().unwrap(),
+                cash_db_path.to_str().unwrap(),
+            )
+            .auto_save(false)
+            .build()
+            .unwrap();
 
         let students = manager.list_students().unwrap();
         let cash_records = manager.search_cash(CashQuery::new()).unwrap();
 
         assert_eq!(students.len(), 1);
         assert_eq!(cash_records.len(), 1);
-        assert_eq!(students[0].name(), "持久化测试");
-        assert_eq!(cash_records[0].cash, 2500);
+        assert_eq!(students[0].name(), Some("持久化测试"));
+        assert_eq!(cash_records[0].cash.amount_minor, 2500);
     }
 }
@@ -0,0 +1,102 @@
+// 测试学生附件存储
+use qmx_backend_lib::{Limits, QmxManager, StudentBuilder};
+use std::path::Path;
+use tempfile::TempDir;
+
+fn setup() -> TempDir {
+    let temp_dir = TempDir::new().unwrap();
+    std::env::set_current_dir(temp_dir.path()).unwrap();
+    let _ = std::fs::create_dir_all("data");
+    temp_dir
+}
+
+mod attachment_tests {
+    use super::*;
+
+    #[test]
+    fn test_attach_list_and_remove() {
+        let temp_dir = setup();
+        let manager = QmxManager::builder().auto_save(false).build().unwrap();
+        let uid = manager
+            .create_student(StudentBuilder::new("附件学生"))
+            .unwrap();
+
+        let source = temp_dir.path().join("免责声明.pdf");
+        std::fs::write(&source, b"signed waiver").unwrap();
+        let first = manager.attach_file(uid, &source).unwrap();
+        // 同名文件不会互相覆盖
+        std::fs::write(&source, b"second copy").unwrap();
+        let second = manager.attach_file(uid, &source).unwrap();
+
+        let list = manager.list_attachments(uid).unwrap();
+        assert_eq!(list.len(), 2);
+        assert_eq!(list[0].uid, first);
+        assert_eq!(list[0].file_name, "免责声明.pdf");
+        assert_eq!(list[0].size, 13);
+
+        let stored = manager.attachment_path(first).unwrap().unwrap();
+        assert!(stored.starts_with(Path::new("./data/attachments").join(uid.to_string())));
+        assert_eq!(std::fs::read(&stored).unwrap(), b"signed waiver");
+
+        // 重新加载后附件索引仍然存在
+        let reloaded = QmxManager::builder().auto_save(false).build().unwrap();
+        assert_eq!(reloaded.list_attachments(uid).unwrap(), list);
+
+        assert!(manager.remove_attachment(first).unwrap());
+        assert!(!manager.remove_attachment(first).unwrap());
+        assert!(!stored.exists());
+        let list = manager.list_attachments(uid).unwrap();
+        assert_eq!(list.len(), 1);
+        assert_eq!(list[0].uid, second);
+    }
+
+    #[test]
+    fn test_attach_rejects_invalid_input() {
+        let temp_dir = setup();
+        let manager = QmxManager::builder()
+            .auto_save(false)
+            .build()
+            .unwrap()
+            .with_limits(Limits {
+                max_attachment_size: 4,
+                ..Limits::default()
+            });
+        let uid = manager
+            .create_student(StudentBuilder::new("附件学生"))
+            .unwrap();
+        let source = temp_dir.path().join("photo.jpg");
+        std::fs::write(&source, b"too large").unwrap();
+
+        assert!(manager.attach_file(uid, &source).is_err());
+        assert!(manager.attach_file(uid + 1000, &source).is_err());
+        assert!(manager.attach_file(uid, temp_dir.path()).is_err());
+        assert!(manager.list_attachments(uid).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_custom_attachments_dir() {
+        let temp_dir = setup();
+        let dir = temp_dir.path().join("files");
+        let manager = QmxManager::builder()
+            .auto_save(false)
+            .build()
+            .unwrap()
+            .with_attachments_dir(dir.to_string_lossy().into_owned())
+            .unwrap();
+        let uid = manager
+            .create_student(StudentBuilder::new("附件学生"))
+            .unwrap();
+        let source = temp_dir.path().join("note.txt");
+        std::fs::write(&source, b"medical note").unwrap();
+
+        let attachment = manager.attach_file(uid, &source).unwrap();
+        assert!(dir.join("index.json").exists());
+        assert!(
+            manager
+                .attachment_path(attachment)
+                .unwrap()
+                .unwrap()
+                .starts_with(&dir)
+        );
+    }
+}
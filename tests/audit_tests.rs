@@ -0,0 +1,215 @@
+// 测试修改操作的审计日志
+use chrono::{Duration, NaiveDate, TimeZone, Utc};
+use qmx_backend_lib::cash::PaymentFrequency;
+use qmx_backend_lib::student::Class;
+use qmx_backend_lib::{
+    AuditAction, AuditEntity, CashBuilder, CashUpdater, DeletePolicy, FixedClock,
+    InstallmentPlanBuilder, MemoryBackend, QmxManager, StudentBuilder, StudentUpdater,
+};
+use serde_json::json;
+use std::sync::Arc;
+use tempfile::TempDir;
+
+fn setup() -> TempDir {
+    let temp_dir = TempDir::new().unwrap();
+    std::env::set_current_dir(temp_dir.path()).unwrap();
+    let _ = std::fs::create_dir_all("data");
+    temp_dir
+}
+
+mod audit_log_tests {
+    use super::*;
+
+    #[test]
+    fn test_student_lifecycle_is_audited() {
+        let _temp_dir = setup();
+        let manager = QmxManager::builder().auto_save(false).build().unwrap();
+
+        let uid = manager
+            .create_student(StudentBuilder::new("审计学生").age(12))
+            .unwrap();
+        manager
+            .update_student(uid, StudentUpdater::new().age(Some(13)))
+            .unwrap();
+        // 没有实际变化的更新不产生审计记录
+        manager
+            .update_student(uid, StudentUpdater::new().age(Some(13)))
+            .unwrap();
+        assert!(manager.delete_student(uid, DeletePolicy::Restrict).unwrap());
+
+        let log = manager.get_audit_log(uid).unwrap();
+        let actions: Vec<AuditAction> = log.iter().map(|e| e.action).collect();
+        assert_eq!(
+            actions,
+            vec![
+                AuditAction::Create,
+                AuditAction::Update,
+                AuditAction::Delete
+            ]
+        );
+        assert!(log.iter().all(|e| e.entity == AuditEntity::Student));
+        assert!(log.iter().all(|e| e.actor == "系统"));
+
+        let created = log[0].changes.iter().find(|c| c.field == "name").unwrap();
+        assert_eq!(created.old, serde_json::Value::Null);
+        assert_eq!(created.new, json!("审计学生"));
+
+        assert_eq!(log[1].changes.len(), 1);
+        assert_eq!(log[1].changes[0].field, "age");
+        assert_eq!(log[1].changes[0].old, json!(12));
+        assert_eq!(log[1].changes[0].new, json!(13));
+
+        let deleted = log[2].changes.iter().find(|c| c.field == "age").unwrap();
+        assert_eq!(deleted.old, json!(13));
+        assert_eq!(deleted.new, serde_json::Value::Null);
+    }
+
+    #[test]
+    fn test_cash_changes_record_actor_and_persist() {
+        let _temp_dir = setup();
+        let manager = QmxManager::builder()
+            .auto_save(true)
+            .build()
+            .unwrap()
+            .with_actor("前台");
+
+        let uid = manager.record_cash(CashBuilder::new(1000)).unwrap();
+        manager
+            .update_cash(uid, CashUpdater::new().note(Some("学费".to_string())))
+            .unwrap();
+        let refund = manager.refund_cash(uid, 300, None).unwrap();
+
+        let log = manager.get_audit_log(uid).unwrap();
+        assert_eq!(log.len(), 2);
+        assert!(log.iter().all(|e| e.entity == AuditEntity::Cash));
+        assert!(log.iter().all(|e| e.actor == "前台"));
+        assert_eq!(log[1].changes[0].field, "note");
+        assert_eq!(manager.get_audit_log(refund).unwrap().len(), 1);
+
+        // 重新加载后审计记录仍然存在
+        let reloaded = QmxManager::builder().auto_save(false).build().unwrap();
+        assert_eq!(reloaded.get_audit_log(uid).unwrap(), log);
+    }
+
+    #[test]
+    fn test_overdue_marking_is_audited() {
+        let _temp_dir = setup();
+        let start = Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap();
+        let clock = Arc::new(FixedClock::new(start));
+        let manager = QmxManager::builder()
+            .auto_save(false)
+            .build()
+            .unwrap()
+            .with_clock(clock.clone());
+
+        let plan = manager
+            .create_installment_plan(InstallmentPlanBuilder::new(
+                3000,
+                3,
+                PaymentFrequency::Monthly,
+                start + Duration::days(10),
+            ))
+            .unwrap();
+        clock.advance(Duration::days(20));
+        manager.mark_overdue_installments().unwrap();
+
+        let first = manager.get_audit_log(plan.cash_uids[0]).unwrap();
+        assert_eq!(first.len(), 2);
+        assert_eq!(first[0].action, AuditAction::Create);
+        assert_eq!(first[1].action, AuditAction::Update);
+        assert_eq!(first[1].timestamp, start + Duration::days(20));
+        assert_eq!(first[1].changes[0].field, "installment");
+
+        let second = manager.get_audit_log(plan.cash_uids[1]).unwrap();
+        assert_eq!(second.len(), 1);
+    }
+
+    #[test]
+    fn test_backend_keeps_audit_in_memory() {
+        let temp_dir = setup();
+        let manager = QmxManager::builder()
+            .auto_save(true)
+            .build()
+            .unwrap()
+            .with_backend(Arc::new(MemoryBackend::new()))
+            .unwrap();
+
+        let uid = manager
+            .create_student(StudentBuilder::new("内存学生"))
+            .unwrap();
+        assert_eq!(manager.get_audit_log(uid).unwrap().len(), 1);
+        assert!(!std::path::Path::new("data/audit_log.json").exists());
+
+        // 显式设置路径后审计日志写入该文件
+        let path = temp_dir.path().join("audit.json");
+        let manager = manager
+            .with_audit_path(path.to_string_lossy().into_owned())
+            .unwrap();
+        manager
+            .update_student(uid, StudentUpdater::new().age(Some(9)))
+            .unwrap();
+        assert!(path.exists());
+    }
+
+    #[test]
+    fn test_conversion_funnel_from_class_changes() {
+        let _temp_dir = setup();
+        let jan = Utc.with_ymd_and_hms(2024, 1, 10, 9, 0, 0).unwrap();
+        let clock = Arc::new(FixedClock::new(jan));
+        let manager = QmxManager::builder()
+            .auto_save(false)
+            .build()
+            .unwrap()
+            .with_backend(Arc::new(MemoryBackend::new()))
+            .unwrap()
+            .with_clock(clock.clone());
+
+        let converted = manager
+            .create_student(StudentBuilder::new("转化").class(Class::TenTry))
+            .unwrap();
+        let lost = manager
+            .create_student(StudentBuilder::new("流失").class(Class::TenTry))
+            .unwrap();
+        manager
+            .create_student(StudentBuilder::new("直接办卡").class(Class::Year))
+            .unwrap();
+
+        clock.advance(Duration::days(30));
+        manager
+            .update_student(converted, StudentUpdater::new().class(Class::Month))
+            .unwrap();
+        manager
+            .update_student(lost, StudentUpdater::new().age(Some(10)))
+            .unwrap();
+        let later = manager
+            .create_student(StudentBuilder::new("二月试课"))
+            .unwrap();
+        manager
+            .update_student(later, StudentUpdater::new().class(Class::TenTry))
+            .unwrap();
+        clock.advance(Duration::days(2));
+        manager
+            .update_student(later, StudentUpdater::new().class(Class::Year))
+            .unwrap();
+        // 已转化后再改班级不重复计入
+        manager
+            .update_student(later, StudentUpdater::new().class(Class::Month))
+            .unwrap();
+
+        let funnel = manager.get_conversion_funnel().unwrap();
+        assert_eq!(funnel.trials, 3);
+        assert_eq!(funnel.converted, 2);
+        assert!((funnel.conversion_rate - 2.0 / 3.0).abs() < 1e-9);
+        assert_eq!(funnel.average_days_to_convert, Some(16.0));
+        assert_eq!(funnel.periods.len(), 2);
+        assert_eq!(
+            funnel.periods[0].period_start,
+            NaiveDate::from_ymd_opt(2024, 1, 1).unwrap()
+        );
+        assert_eq!(funnel.periods[0].trials, 2);
+        assert_eq!(funnel.periods[0].converted, 1);
+        assert_eq!(funnel.periods[0].conversion_rate, 0.5);
+        assert_eq!(funnel.periods[1].trials, 1);
+        assert_eq!(funnel.periods[1].conversion_rate, 1.0);
+    }
+}
@@ -0,0 +1,160 @@
+// 测试防抖的后台自动保存
+use qmx_backend_lib::cash::CashDatabase;
+use qmx_backend_lib::student::StudentDatabase;
+use qmx_backend_lib::{AutoSave, CashBuilder, QmxManager, StudentBuilder};
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tempfile::TempDir;
+
+fn setup() -> TempDir {
+    let temp_dir = TempDir::new().unwrap();
+    std::env::set_current_dir(temp_dir.path()).unwrap();
+    let _ = std::fs::create_dir_all("data");
+    temp_dir
+}
+
+fn debounced(quiet_period: Duration, max_pending: usize) -> AutoSave {
+    AutoSave::Debounced {
+        quiet_period,
+        max_pending,
+    }
+}
+
+fn saved_students(dir: &Path) -> usize {
+    StudentDatabase::read_from(&dir.join("student_database.json").to_string_lossy())
+        .unwrap()
+        .len()
+}
+
+/// 等待条件成立，超时返回 false
+fn wait_until(timeout: Duration, condition: impl Fn() -> bool) -> bool {
+    let deadline = Instant::now() + timeout;
+    while Instant::now() < deadline {
+        if condition() {
+            return true;
+        }
+        std::thread::sleep(Duration::from_millis(10));
+    }
+    condition()
+}
+
+mod autosave_tests {
+    use super::*;
+
+    #[test]
+    fn test_changes_are_coalesced_until_quiet_period() {
+        let temp_dir = setup();
+        // 后台线程延迟写入，使用绝对路径以免受其他测试切换工作目录影响
+        let dir = temp_dir.path().join("quiet");
+        let manager = QmxManager::builder()
+            .data_dir(&dir)
+            .auto_save(debounced(Duration::from_millis(300), usize::MAX))
+            .build()
+            .unwrap();
+
+        for i in 0..20 {
+            manager
+                .create_student(StudentBuilder::new(format!("学生{}", i)))
+                .unwrap();
+        }
+        // 修改后不会立即写文件
+        assert_eq!(saved_students(&dir), 0);
+        assert!(manager.has_unsaved_changes());
+
+        let all_saved = || saved_students(&dir) == 20;
+        assert!(wait_until(Duration::from_secs(5), all_saved));
+        assert!(!manager.has_unsaved_changes());
+        // 审计日志在数据文件之后写入
+        assert!(wait_until(Duration::from_secs(5), || dir
+            .join("audit_log.json")
+            .exists()));
+    }
+
+    #[test]
+    fn test_max_pending_triggers_save() {
+        let temp_dir = setup();
+        // 后台线程延迟写入，使用绝对路径以免受其他测试切换工作目录影响
+        let dir = temp_dir.path().join("batch");
+        let manager = QmxManager::builder()
+            .data_dir(&dir)
+            .auto_save(debounced(Duration::from_secs(3600), 3))
+            .build()
+            .unwrap();
+
+        for amount in [100, 200, 300] {
+            manager.record_cash(CashBuilder::new(amount)).unwrap();
+        }
+        assert!(wait_until(Duration::from_secs(5), || {
+            CashDatabase::read_from(&dir.join("cash_database.json").to_string_lossy())
+                .unwrap()
+                .len()
+                == 3
+        }));
+    }
+
+    #[test]
+    fn test_flush_and_drop_save_pending_changes() {
+        let temp_dir = setup();
+        // 后台线程延迟写入，使用绝对路径以免受其他测试切换工作目录影响
+        let dir = temp_dir.path().join("flush");
+        let manager = QmxManager::builder()
+            .data_dir(&dir)
+            .auto_save(debounced(Duration::from_secs(3600), usize::MAX))
+            .build()
+            .unwrap();
+
+        manager.create_student(StudentBuilder::new("张三")).unwrap();
+        assert_eq!(saved_students(&dir), 0);
+        manager.flush().unwrap();
+        assert_eq!(saved_students(&dir), 1);
+        // 没有修改时不写文件
+        manager.flush().unwrap();
+
+        manager.create_student(StudentBuilder::new("李四")).unwrap();
+        assert_eq!(saved_students(&dir), 1);
+        drop(manager);
+        assert_eq!(saved_students(&dir), 2);
+    }
+
+    #[test]
+    fn test_save_error_handler_receives_background_errors() {
+        let temp_dir = setup();
+        let dir = temp_dir.path().join("broken");
+        let errors = Arc::new(Mutex::new(Vec::new()));
+        let seen = Arc::clone(&errors);
+        let manager = QmxManager::builder()
+            .data_dir(&dir)
+            .auto_save(debounced(Duration::from_millis(50), usize::MAX))
+            .on_save_error(move |e| seen.lock().unwrap().push(e.to_string()))
+            .build()
+            .unwrap();
+
+        // 把数据库文件换成目录，使保存失败
+        let student_file = dir.join("student_database.json");
+        std::fs::remove_file(&student_file).unwrap();
+        std::fs::create_dir(&student_file).unwrap();
+        manager.create_student(StudentBuilder::new("王五")).unwrap();
+
+        let reported = || !errors.lock().unwrap().is_empty();
+        assert!(wait_until(Duration::from_secs(5), reported));
+        assert!(manager.has_unsaved_changes());
+        assert!(manager.flush().is_err());
+    }
+
+    #[test]
+    fn test_flush_without_debounce() {
+        let temp_dir = setup();
+        // 后台线程延迟写入，使用绝对路径以免受其他测试切换工作目录影响
+        let dir = temp_dir.path().join("off");
+        let manager = QmxManager::builder()
+            .data_dir(&dir)
+            .auto_save(AutoSave::Off)
+            .build()
+            .unwrap();
+        manager.create_student(StudentBuilder::new("赵六")).unwrap();
+        assert_eq!(saved_students(&dir), 0);
+        manager.flush().unwrap();
+        assert_eq!(saved_students(&dir), 1);
+    }
+}
@@ -0,0 +1,117 @@
+// 测试数据备份与恢复
+use chrono::{Duration, TimeZone, Utc};
+use qmx_backend_lib::{CashBuilder, DeletePolicy, FixedClock, QmxManager, RetentionPolicy, StudentBuilder};
+use std::sync::Arc;
+use tempfile::TempDir;
+
+fn setup() -> TempDir {
+    let temp_dir = TempDir::new().unwrap();
+    std::env::set_current_dir(temp_dir.path()).unwrap();
+    let _ = std::fs::create_dir_all("data");
+    temp_dir
+}
+
+mod backup_tests {
+    use super::*;
+
+    #[test]
+    fn test_backup_and_restore() {
+        let _temp_dir = setup();
+        let manager = QmxManager::builder().auto_save(true).build().unwrap();
+        let student = manager
+            .create_student(StudentBuilder::new("备份学生"))
+            .unwrap();
+        let cash = manager
+            .record_cash(CashBuilder::new(1000).student_id(student))
+            .unwrap();
+
+        let backup = manager.backup_now().unwrap();
+        assert!(backup.join("student_database.json").exists());
+        assert!(backup.join("audit_log.json").exists());
+
+        // 误删后从备份恢复
+        manager.delete_cash(cash).unwrap();
+        manager.delete_student(student, DeletePolicy::Restrict).unwrap();
+        manager.restore_from_backup(&backup).unwrap();
+        assert!(manager.get_student(student).unwrap().is_some());
+        assert_eq!(manager.get_cash(cash).unwrap().unwrap().cash.amount_minor, 1000);
+
+        // 恢复结果已保存，恢复前的数据也留有备份
+        let reloaded = QmxManager::builder().auto_save(false).build().unwrap();
+        assert!(reloaded.get_student(student).unwrap().is_some());
+        assert_eq!(manager.list_backups().unwrap().len(), 2);
+        assert_eq!(manager.undo_last(1).unwrap(), 0);
+    }
+
+    #[test]
+    fn test_restore_rejects_incomplete_backup() {
+        let temp_dir = setup();
+        let manager = QmxManager::builder().auto_save(false).build().unwrap();
+        let empty = temp_dir.path().join("not-a-backup");
+        std::fs::create_dir_all(&empty).unwrap();
+        assert!(manager.restore_from_backup(&empty).is_err());
+        assert!(manager.list_backups().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_keep_last_retention() {
+        let temp_dir = setup();
+        let start = Utc.with_ymd_and_hms(2024, 1, 1, 8, 0, 0).unwrap();
+        let clock = Arc::new(FixedClock::new(start));
+        let manager = QmxManager::builder()
+            .auto_save(false)
+            .build()
+            .unwrap()
+            .with_clock(clock.clone())
+            .with_backup_dir(temp_dir.path().join("backups").to_string_lossy())
+            .with_retention_policy(RetentionPolicy::keep_last(2));
+
+        let mut created = Vec::new();
+        for _ in 0..4 {
+            created.push(manager.backup_now().unwrap());
+            clock.advance(Duration::hours(1));
+        }
+        // 同一秒内的备份不会互相覆盖
+        clock.set(start + Duration::hours(3));
+        created.push(manager.backup_now().unwrap());
+        assert_ne!(created[3], created[4]);
+
+        let backups = manager.list_backups().unwrap();
+        let paths: Vec<_> = backups.iter().map(|b| b.path.clone()).collect();
+        assert_eq!(paths, vec![created[4].clone(), created[3].clone()]);
+        assert_eq!(backups[0].created_at, start + Duration::hours(3));
+    }
+
+    #[test]
+    fn test_daily_and_weekly_retention() {
+        let temp_dir = setup();
+        // 2024-01-01 是周一
+        let start = Utc.with_ymd_and_hms(2024, 1, 1, 8, 0, 0).unwrap();
+        let clock = Arc::new(FixedClock::new(start));
+        let manager = QmxManager::builder()
+            .auto_save(false)
+            .build()
+            .unwrap()
+            .with_clock(clock.clone())
+            .with_backup_dir(temp_dir.path().join("backups").to_string_lossy())
+            .with_retention_policy(RetentionPolicy::keep_last(1).daily(3).weekly(2));
+
+        // 每天早晚各备份一次，持续两周
+        for _ in 0..14 {
+            manager.backup_now().unwrap();
+            clock.advance(Duration::hours(12));
+            manager.backup_now().unwrap();
+            clock.advance(Duration::hours(12));
+        }
+
+        let kept: Vec<String> = manager
+            .list_backups()
+            .unwrap()
+            .iter()
+            .map(|b| b.created_at.format("%m-%d %H").to_string())
+            .collect();
+        // 最近三天每天最新的一份，其中 01-14 20 时同时是第二周最新的一份，
+        // 以及第一周最新的一份
+        assert_eq!(kept, vec!["01-14 20", "01-13 20", "01-12 20", "01-07 20"]);
+    }
+}
@@ -0,0 +1,145 @@
+// 测试多校区支持
+use qmx_backend_lib::cash::{Cash, PaymentFrequency};
+use qmx_backend_lib::student::Student;
+use qmx_backend_lib::{
+    AutoSave, CashBuilder, CashQuery, CashUpdater, InstallmentPlanBuilder, QmxManager,
+    StudentBuilder, StudentQuery, StudentUpdater,
+};
+use tempfile::TempDir;
+
+fn setup() -> TempDir {
+    let temp_dir = TempDir::new().unwrap();
+    std::env::set_current_dir(temp_dir.path()).unwrap();
+    let _ = std::fs::create_dir_all("data");
+    temp_dir
+}
+
+fn manager(temp_dir: &TempDir) -> QmxManager {
+    QmxManager::builder()
+        .data_dir(temp_dir.path())
+        .auto_save(AutoSave::Off)
+        .build()
+        .unwrap()
+}
+
+fn cash_branch(manager: &QmxManager, uid: u64) -> Option<String> {
+    manager.get_cash(uid).unwrap().unwrap().branch_id
+}
+
+mod branch_tests {
+    use super::*;
+
+    #[test]
+    fn test_branch_filters_and_inheritance() {
+        let temp_dir = setup();
+        let manager = manager(&temp_dir);
+        let north = manager
+            .create_student(StudentBuilder::new("张三").branch("north"))
+            .unwrap();
+        let south = manager
+            .create_student(StudentBuilder::new("李四").branch("south"))
+            .unwrap();
+        manager.create_student(StudentBuilder::new("王五")).unwrap();
+
+        // 未指定校区的现金记录使用学生的校区，退款沿用原记录的校区
+        let paid = manager
+            .record_cash(CashBuilder::new(1000).student_id(north))
+            .unwrap();
+        assert_eq!(cash_branch(&manager, paid).as_deref(), Some("north"));
+        let refund = manager.refund_cash(paid, 200, None).unwrap();
+        assert_eq!(cash_branch(&manager, refund).as_deref(), Some("north"));
+        let plan = manager
+            .create_installment_plan(
+                InstallmentPlanBuilder::new(300, 3, PaymentFrequency::Monthly, manager.now())
+                    .student_id(south),
+            )
+            .unwrap();
+        for uid in &plan.cash_uids {
+            assert_eq!(cash_branch(&manager, *uid).as_deref(), Some("south"));
+        }
+        manager
+            .record_cash(CashBuilder::new(50).student_id(south).branch("north"))
+            .unwrap();
+
+        let students = manager
+            .search_students(StudentQuery::new().branch("north"))
+            .unwrap();
+        assert_eq!(students.len(), 1);
+        assert_eq!(students[0].uid(), north);
+        assert_eq!(
+            manager
+                .search_cash(CashQuery::new().branch("north"))
+                .unwrap()
+                .len(),
+            3
+        );
+        assert_eq!(manager.list_branches().unwrap(), ["north", "south"]);
+    }
+
+    #[test]
+    fn test_branch_dashboard_and_move() {
+        let temp_dir = setup();
+        let manager = manager(&temp_dir);
+        let uid = manager
+            .create_student(StudentBuilder::new("张三").branch("north"))
+            .unwrap();
+        manager
+            .record_cash(CashBuilder::new(1000).student_id(uid))
+            .unwrap();
+        manager
+            .create_student(StudentBuilder::new("李四").branch("south"))
+            .unwrap();
+
+        let north = manager.get_branch_dashboard_stats("north").unwrap();
+        assert_eq!(north.total_students, 1);
+        assert_eq!(north.total_revenue, 1000);
+        let south = manager.get_branch_dashboard_stats("south").unwrap();
+        assert_eq!(south.total_students, 1);
+        assert_eq!(south.total_revenue, 0);
+
+        let changes = manager.move_student_to_branch(uid, "south").unwrap();
+        assert_eq!(changes[0].field, "branch_id");
+        assert_eq!(
+            manager.get_student(uid).unwrap().unwrap().branch_id(),
+            Some("south")
+        );
+        // 历史收款仍计入原校区
+        let north = manager.get_branch_dashboard_stats("north").unwrap();
+        assert_eq!(north.total_students, 0);
+        assert_eq!(north.total_revenue, 1000);
+        assert_eq!(
+            manager
+                .get_branch_dashboard_stats("south")
+                .unwrap()
+                .total_students,
+            2
+        );
+
+        assert!(manager.move_student_to_branch(uid, " ").is_err());
+        manager
+            .update_student(uid, StudentUpdater::new().branch(None))
+            .unwrap();
+        assert_eq!(manager.get_student(uid).unwrap().unwrap().branch_id(), None);
+        let cash_uid = manager.search_cash(CashQuery::new()).unwrap()[0].uid;
+        manager
+            .update_cash(
+                cash_uid,
+                CashUpdater::new().branch(Some("east".to_string())),
+            )
+            .unwrap();
+        assert_eq!(manager.list_branches().unwrap(), ["east", "south"]);
+    }
+
+    #[test]
+    fn test_legacy_records_have_no_branch() {
+        let mut student = serde_json::to_value(Student::new()).unwrap();
+        student.as_object_mut().unwrap().remove("branch_id");
+        let student: Student = serde_json::from_value(student).unwrap();
+        assert_eq!(student.branch_id(), None);
+
+        let mut cash = serde_json::to_value(Cash::new(None)).unwrap();
+        cash.as_object_mut().unwrap().remove("branch_id");
+        let cash: Cash = serde_json::from_value(cash).unwrap();
+        assert_eq!(cash.branch_id, None);
+    }
+}
@@ -0,0 +1,118 @@
+// 测试完整数据包的导出与导入
+use qmx_backend_lib::manager::CoachBuilder;
+use qmx_backend_lib::{
+    AutoSave, CashBuilder, DeletePolicy, MergeStrategy, QmxManager, StudentBuilder, StudentUpdater,
+};
+use std::path::Path;
+use tempfile::TempDir;
+
+fn manager(dir: &Path) -> QmxManager {
+    QmxManager::builder()
+        .data_dir(dir)
+        .auto_save(AutoSave::Off)
+        .build()
+        .unwrap()
+}
+
+mod bundle_tests {
+    use super::*;
+
+    #[test]
+    fn test_move_installation() {
+        let source_dir = TempDir::new().unwrap();
+        let source = manager(source_dir.path());
+        let student = source.create_student(StudentBuilder::new("张三")).unwrap();
+        let cash = source
+            .record_cash(CashBuilder::new(1000).student_id(student))
+            .unwrap();
+        let coach = source.create_coach(CoachBuilder::new("王教练")).unwrap();
+        let photo = source_dir.path().join("photo.jpg");
+        std::fs::write(&photo, b"jpeg data").unwrap();
+        let attachment = source.attach_file(student, &photo).unwrap();
+
+        let bundle = source_dir.path().join("export.qmxbundle");
+        let manifest = source.export_bundle(&bundle).unwrap();
+        assert_eq!(manifest.students, 1);
+        assert_eq!(manifest.attachments, 1);
+
+        let target_dir = TempDir::new().unwrap();
+        let target = manager(target_dir.path());
+        let report = target.import_bundle(&bundle, MergeStrategy::Replace).unwrap();
+        assert_eq!(report.manifest, manifest);
+        assert_eq!(report.students.added, 1);
+        assert_eq!(report.cash.added, 1);
+        assert!(report.safety_backup.is_dir());
+        assert!(!target.has_unsaved_changes());
+
+        assert_eq!(target.get_student(student).unwrap().unwrap().name(), Some("张三"));
+        assert_eq!(target.get_cash(cash).unwrap().unwrap().cash.amount_minor, 1000);
+        assert!(target.get_coach(coach).unwrap().is_some());
+        let path = target.attachment_path(attachment).unwrap().unwrap();
+        assert!(path.starts_with(target_dir.path()));
+        assert_eq!(std::fs::read(path).unwrap(), b"jpeg data");
+
+        // 导入后分配的 UID 不与导入的记录重复
+        let next = target.create_student(StudentBuilder::new("李四")).unwrap();
+        assert!(next > student);
+        drop(target);
+        let reopened = manager(target_dir.path());
+        assert!(reopened.get_student(student).unwrap().is_some());
+    }
+
+    #[test]
+    fn test_merge_strategies() {
+        let temp_dir = TempDir::new().unwrap();
+        let manager = manager(temp_dir.path());
+        let student = manager.create_student(StudentBuilder::new("张三")).unwrap();
+        let bundle = temp_dir.path().join("export.qmxbundle");
+        manager.export_bundle(&bundle).unwrap();
+
+        manager
+            .update_student(student, StudentUpdater::new().name("张三丰".to_string()))
+            .unwrap();
+        let added = manager.create_student(StudentBuilder::new("李四")).unwrap();
+
+        let report = manager
+            .import_bundle(&bundle, MergeStrategy::KeepExisting)
+            .unwrap();
+        assert_eq!(report.students.skipped, 1);
+        assert_eq!(report.students.added, 0);
+        assert_eq!(manager.get_student(student).unwrap().unwrap().name(), Some("张三丰"));
+
+        let report = manager
+            .import_bundle(&bundle, MergeStrategy::Overwrite)
+            .unwrap();
+        assert_eq!(report.students.overwritten, 1);
+        assert_eq!(manager.get_student(student).unwrap().unwrap().name(), Some("张三"));
+        assert!(manager.get_student(added).unwrap().is_some());
+
+        manager.import_bundle(&bundle, MergeStrategy::Replace).unwrap();
+        assert!(manager.get_student(added).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_corrupted_bundle_is_rejected() {
+        let temp_dir = TempDir::new().unwrap();
+        let manager = manager(temp_dir.path());
+        let student = manager.create_student(StudentBuilder::new("张三")).unwrap();
+        let bundle = temp_dir.path().join("export.qmxbundle");
+        manager.export_bundle(&bundle).unwrap();
+
+        let mut bytes = std::fs::read(&bundle).unwrap();
+        let pos = bytes
+            .windows("张三".len())
+            .position(|w| w == "张三".as_bytes())
+            .unwrap();
+        bytes[pos] ^= 1;
+        std::fs::write(&bundle, bytes).unwrap();
+
+        manager.delete_student(student, DeletePolicy::Restrict).unwrap();
+        assert!(
+            manager
+                .import_bundle(&bundle, MergeStrategy::Replace)
+                .is_err()
+        );
+        assert!(manager.get_student(student).unwrap().is_none());
+        assert!(manager.list_backups().unwrap().is_empty());
+    }
+}
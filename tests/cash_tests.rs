@@ -1,5 +1,7 @@
 use chrono::{Duration, Utc};
 use qmx_backend_lib::cash::*;
+use qmx_backend_lib::error::Error;
+use qmx_backend_lib::{Currency, Money};
 use std::fs;
 use std::sync::atomic::Ordering;
 
@@ -36,7 +38,7 @@ mod cash_comprehensive_tests {
     fn cash_default_values() {
         let cash = Cash::new(Some(10));
         assert_eq!(cash.student_id, Some(10));
-        assert_eq!(cash.cash, 0);
+        assert_eq!(cash.cash.amount_minor, 0);
         assert_eq!(cash.note, None);
         assert!(cash.installment.is_none());
     }
@@ -52,10 +54,14 @@ mod cash_comprehensive_tests {
         assert_eq!(cash.student_id, None);
 
         cash.set_cash(500);
-        assert_eq!(cash.cash, 500);
+        assert_eq!(cash.cash.amount_minor, 500);
 
-        cash.add(-200);
-        assert_eq!(cash.cash, 300);
+        cash.add(-200).unwrap();
+        assert_eq!(cash.cash.amount_minor, 300);
+
+        // 溢出时返回错误，金额不变
+        assert!(cash.add(i64::MAX).is_err());
+        assert_eq!(cash.cash.amount_minor, 300);
 
         cash.set_note(Some("Test note".to_string()));
         assert_eq!(cash.note(), Some("Test note"));
@@ -77,17 +83,24 @@ mod cash_comprehensive_tests {
             Some(101),
         );
 
-        assert_eq!(cash.cash, 100);
+        assert_eq!(cash.cash.amount_minor, 100);
         assert!(cash.is_installment());
 
         let installment = cash.installment.as_ref().unwrap();
         assert_eq!(installment.plan_id, 101);
-        assert_eq!(installment.total_amount, 1200);
-        assert_eq!(installment.total_installments, 12);
         assert_eq!(installment.current_installment, 1);
-        assert_eq!(installment.frequency, PaymentFrequency::Monthly);
         assert_eq!(installment.due_date, now);
         assert_eq!(installment.status, InstallmentStatus::Pending);
+
+        // 计划条款插入数据库时转存到计划数据库
+        let mut db = CashDatabase::new();
+        db.insert(cash);
+        let plan = db.plans.get(&101).unwrap();
+        assert_eq!(plan.student_id, Some(1));
+        assert_eq!(plan.total_amount, Money::cny(1200));
+        assert_eq!(plan.total_installments, 12);
+        assert_eq!(plan.frequency, PaymentFrequency::Monthly);
+        assert_eq!(plan.first_due, now);
     }
 
     #[test]
@@ -121,9 +134,162 @@ mod cash_comprehensive_tests {
             Some(1),
         );
 
-        assert_eq!(c1.cash, 333);
-        assert_eq!(c2.cash, 333);
-        assert_eq!(c3.cash, 334); // Last installment gets the remainder
+        assert_eq!(c1.cash.amount_minor, 333);
+        assert_eq!(c2.cash.amount_minor, 333);
+        assert_eq!(c3.cash.amount_minor, 334); // Last installment gets the remainder
+    }
+
+    #[test]
+    fn payment_frequency_next_due() {
+        use chrono::TimeZone;
+        let jan31 = Utc.with_ymd_and_hms(2024, 1, 31, 9, 0, 0).unwrap();
+
+        assert_eq!(
+            PaymentFrequency::Weekly.next_due(jan31),
+            Utc.with_ymd_and_hms(2024, 2, 7, 9, 0, 0).unwrap()
+        );
+        // 闰年二月只有 29 天
+        assert_eq!(
+            PaymentFrequency::Monthly.next_due(jan31),
+            Utc.with_ymd_and_hms(2024, 2, 29, 9, 0, 0).unwrap()
+        );
+        // 从锚点推算，不会因二月而漂移
+        assert_eq!(
+            PaymentFrequency::Monthly.nth_due(jan31, 2),
+            Utc.with_ymd_and_hms(2024, 3, 31, 9, 0, 0).unwrap()
+        );
+        assert_eq!(
+            PaymentFrequency::Quarterly.next_due(jan31),
+            Utc.with_ymd_and_hms(2024, 4, 30, 9, 0, 0).unwrap()
+        );
+        assert_eq!(
+            PaymentFrequency::Custom(10).nth_due(jan31, 3),
+            Utc.with_ymd_and_hms(2024, 3, 1, 9, 0, 0).unwrap()
+        );
+        assert_eq!(PaymentFrequency::Monthly.nth_due(jan31, 0), jan31);
+    }
+
+    #[test]
+    fn cash_generate_next_installment_by_frequency() {
+        use chrono::TimeZone;
+        let anchor = Utc.with_ymd_and_hms(2024, 1, 31, 0, 0, 0).unwrap();
+        let mut db = CashDatabase::new();
+        db.insert(Cash::new_installment(
+            None,
+            900,
+            3,
+            PaymentFrequency::Monthly,
+            anchor,
+            1,
+            Some(77),
+        ));
+
+        let second = db.generate_next_installment_by_frequency(77).unwrap();
+        let third = db.generate_next_installment_by_frequency(77).unwrap();
+        let due = |uid: u64| db.get(&uid).unwrap().installment.as_ref().unwrap().due_date;
+        assert_eq!(due(second), Utc.with_ymd_and_hms(2024, 2, 29, 0, 0, 0).unwrap());
+        assert_eq!(due(third), Utc.with_ymd_and_hms(2024, 3, 31, 0, 0, 0).unwrap());
+
+        assert!(db.generate_next_installment_by_frequency(77).is_err());
+        assert!(db.generate_next_installment_by_frequency(999).is_err());
+
+        // 按季度跨年，二月之后恢复原来的日期
+        let nov30 = Utc.with_ymd_and_hms(2024, 11, 30, 0, 0, 0).unwrap();
+        db.insert(Cash::new_installment(
+            None,
+            1200,
+            4,
+            PaymentFrequency::Quarterly,
+            nov30,
+            1,
+            Some(78),
+        ));
+        let uids: Vec<u64> = (0..3)
+            .map(|_| db.generate_next_installment_by_frequency(78).unwrap())
+            .collect();
+        let dues: Vec<_> = uids
+            .iter()
+            .map(|uid| db.get(uid).unwrap().installment.as_ref().unwrap().due_date)
+            .collect();
+        assert_eq!(
+            dues,
+            [
+                Utc.with_ymd_and_hms(2025, 2, 28, 0, 0, 0).unwrap(),
+                Utc.with_ymd_and_hms(2025, 5, 30, 0, 0, 0).unwrap(),
+                Utc.with_ymd_and_hms(2025, 8, 30, 0, 0, 0).unwrap(),
+            ]
+        );
+    }
+
+    #[test]
+    fn cash_installment_remainder_strategies() {
+        let amounts = |strategy: RemainderStrategy| -> Vec<i64> {
+            (1..=4).map(|k| strategy.amount_for(1003, 4, k)).collect()
+        };
+        assert_eq!(amounts(RemainderStrategy::LastPays), vec![250, 250, 250, 253]);
+        assert_eq!(amounts(RemainderStrategy::FirstPays), vec![253, 250, 250, 250]);
+        assert_eq!(amounts(RemainderStrategy::Spread), vec![251, 251, 251, 250]);
+
+        // 负数金额（退款分期）同样保证总额不变
+        let spread: i64 = (1..=4)
+            .map(|k| RemainderStrategy::Spread.amount_for(-1003, 4, k))
+            .sum();
+        assert_eq!(spread, -1003);
+
+        let first = Cash::new_installment(
+            None,
+            1000,
+            3,
+            PaymentFrequency::Monthly,
+            Utc::now(),
+            1,
+            Some(42),
+        )
+        .with_remainder_strategy(RemainderStrategy::FirstPays);
+        assert_eq!(first.cash.amount_minor, 334);
+
+        // 后续期数沿用计划的分配方式
+        let mut db = CashDatabase::new();
+        db.insert(first);
+        assert_eq!(
+            db.plans.get(&42).unwrap().remainder_strategy,
+            RemainderStrategy::FirstPays
+        );
+        let next_uid = db.generate_next_installment(42, Utc::now()).unwrap();
+        assert_eq!(db.get(&next_uid).unwrap().cash.amount_minor, 333);
+    }
+
+    #[test]
+    fn cash_legacy_installment_moves_plan_terms() {
+        // 旧版本数据中每期记录都带有计划条款
+        let due = Utc::now();
+        let mut value = serde_json::to_value(Cash::new_installment(
+            Some(7),
+            900,
+            3,
+            PaymentFrequency::Weekly,
+            due,
+            2,
+            Some(303),
+        ))
+        .unwrap();
+        let installment = value["installment"].as_object_mut().unwrap();
+        installment.remove("remainder_strategy");
+        assert_eq!(installment["total_amount"], 900);
+        let cash: Cash = serde_json::from_value(value).unwrap();
+
+        let mut db = CashDatabase::new();
+        db.insert(cash);
+        let plan = db.plans.get(&303).unwrap();
+        assert_eq!(plan.student_id, Some(7));
+        assert_eq!(plan.total_installments, 3);
+        assert_eq!(plan.first_due, due - Duration::weeks(1));
+
+        // 重新保存后分期记录仍带有条款副本，旧版本程序可以读取
+        let saved = serde_json::to_value(db.get_installments_by_plan(303)[0]).unwrap();
+        assert_eq!(saved["installment"]["total_amount"], 900);
+        assert_eq!(saved["installment"]["total_installments"], 3);
+        assert_eq!(saved["installment"]["frequency"], "Weekly");
     }
 
     #[test]
@@ -148,6 +314,41 @@ mod cash_comprehensive_tests {
         non_installment.set_installment_status(InstallmentStatus::Paid);
         assert!(!non_installment.is_installment());
     }
+
+    #[test]
+    fn test_cash_totals_rule() {
+        let mut totals = CashTotals::default();
+        for amount in [500, 0, -200, -100] {
+            totals.record(amount.into()).unwrap();
+        }
+        assert_eq!(totals.income, 500);
+        assert_eq!(totals.expense, 300);
+        assert_eq!(totals.net(), 200);
+        assert_eq!(totals.count, 4);
+        assert_eq!(totals.currency, Some(Currency::Cny));
+
+        // 极端金额不会溢出
+        let mut extreme = CashTotals::default();
+        for amount in [i64::MIN, i64::MIN] {
+            extreme.record(amount.into()).unwrap();
+        }
+        assert_eq!(extreme.expense, i64::MAX);
+        extreme.record(i64::MAX.into()).unwrap();
+        assert_eq!(extreme.net(), 0);
+    }
+
+    #[test]
+    fn test_cash_totals_reject_mixed_currencies() {
+        let mut totals = CashTotals::default();
+        // 金额为 0 的记录不区分币种
+        totals.record(Money::zero(Currency::Cny)).unwrap();
+        totals.record(Money::new(1000, Currency::Usd)).unwrap();
+        let err = totals.record(Money::cny(500)).unwrap_err();
+        assert_eq!(err.code(), "currency_mismatch");
+        assert_eq!(totals.income, 1000);
+        assert_eq!(totals.count, 2);
+        assert_eq!(totals.currency, Some(Currency::Usd));
+    }
 }
 
 #[cfg(test)]
@@ -156,7 +357,7 @@ mod cash_database_comprehensive_tests {
 
     pub(super) fn setup_db_with_installments() -> (CashDatabase, u64) {
         let mut db = CashDatabase::new();
-        let plan_id = CASH_UID_COUNTER.fetch_add(1, Ordering::SeqCst);
+        let plan_id = allocate_plan_id();
 
         let c1 = Cash::new_installment(
             Some(1),
@@ -212,7 +413,7 @@ mod cash_database_comprehensive_tests {
             true
         });
         assert_eq!(update_count, 2);
-        assert_eq!(db.get(&uids[0]).unwrap().cash, 100);
+        assert_eq!(db.get(&uids[0]).unwrap().cash.amount_minor, 100);
 
         let remove_count = db.remove_batch(&[uids[1], uids[3]]);
         assert_eq!(remove_count, 2);
@@ -284,7 +485,9 @@ mod cash_database_comprehensive_tests {
 
         // Plan already completed
         let _ = db.generate_next_installment(plan_id, Utc::now() + Duration::days(30));
-        assert!(db.generate_next_installment(plan_id, Utc::now()).is_err());
+        let err = db.generate_next_installment(plan_id, Utc::now()).unwrap_err();
+        assert!(matches!(err, Error::InstallmentComplete(id) if id == plan_id));
+        assert_eq!(err.code(), "installment_complete");
     }
 
     #[test]
@@ -306,12 +509,93 @@ mod cash_database_comprehensive_tests {
         // Cancel again, should be 0.
         assert_eq!(db.cancel_installment_plan(plan_id), 0);
     }
+
+    #[test]
+    fn cash_database_indexes() {
+        let (mut db, plan_id) = setup_db_with_installments();
+        let other = Cash::new(Some(2));
+        let other_uid = other.uid;
+        db.insert(other);
+        db.insert(Cash::new(None));
+
+        assert_eq!(db.get_by_student(1).len(), 2);
+        assert_eq!(db.get_installments_by_plan(plan_id).len(), 2);
+        assert_eq!(db.get_student_installments(1).len(), 2);
+
+        // 修改学生后索引随之更新
+        db.update_batch(&[other_uid], |c| {
+            c.set_id(1);
+            true
+        });
+        assert_eq!(db.get_by_student(1).len(), 3);
+        assert!(db.get_by_student(2).is_empty());
+
+        db.remove(&other_uid);
+        assert_eq!(db.get_by_student(1).len(), 2);
+
+        // 反序列化后重建索引
+        let loaded = CashDatabase::from_json(&db.json()).unwrap();
+        assert_eq!(loaded.get_by_student(1).len(), 2);
+        assert_eq!(loaded.get_installments_by_plan(plan_id).len(), 2);
+
+        // 直接修改记录表后需手动重建
+        let uids: Vec<u64> = db.get_by_student(1).iter().map(|c| c.uid).collect();
+        for uid in &uids {
+            db.cash_data.get_mut(uid).unwrap().student_id = Some(3);
+        }
+        db.rebuild_indexes();
+        assert!(db.get_by_student(1).is_empty());
+        assert_eq!(
+            db.get_by_student(3).iter().map(|c| c.uid).collect::<Vec<_>>(),
+            uids
+        );
+    }
 }
 
 #[cfg(test)]
 mod cash_file_operations_tests {
     use super::*;
 
+    #[test]
+    fn cash_database_salvage_keeps_plans_and_next_uid() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = dir.path().join("cash_database.json");
+        let path = path.to_str().unwrap();
+
+        let mut db = CashDatabase::new();
+        let installment = Cash::new_installment(
+            Some(1),
+            1200,
+            12,
+            PaymentFrequency::Monthly,
+            Utc::now(),
+            1,
+            None,
+        );
+        db.insert(installment.clone());
+        db.insert(Cash::new(Some(2)));
+        assert_eq!(db.plans.len(), 1);
+
+        // 记录表不在第一个字段，且最后一条记录被截断
+        let value = serde_json::to_value(&db).unwrap();
+        let json = format!(
+            r#"{{"plans":{},"next_uid":9500000,"cash_data":{}}}"#,
+            value["plans"], value["cash_data"]
+        );
+        fs::write(path, &json[..json.len() - 20]).unwrap();
+
+        assert!(CashDatabase::read_from(path).is_err());
+        let (salvaged, report) = CashDatabase::salvage_from(path).unwrap();
+        assert_eq!(salvaged.len(), 1);
+        assert!(salvaged.get(&installment.uid).is_some());
+        assert_eq!(salvaged.plans.len(), 1);
+        assert_eq!(report.recovered, 1);
+        assert_eq!(report.lost, 1);
+
+        let saved = serde_json::to_value(&salvaged).unwrap();
+        assert!(saved["next_uid"].as_u64().unwrap() >= 9500000);
+    }
+
     #[test]
     fn cash_uid_persistence() {
         setup();
@@ -331,6 +615,47 @@ mod cash_file_operations_tests {
         let _ = fs::remove_file("./data/cash_uid_counter");
     }
 
+    #[test]
+    fn plan_id_persistence() {
+        setup();
+        let path = "./data/plan_id_counter";
+        let _ = fs::remove_file(path);
+
+        assert_eq!(load_saved_plan_id().unwrap(), 1);
+
+        PLAN_ID_COUNTER.fetch_max(300, Ordering::SeqCst);
+        save_plan_id().unwrap();
+        assert!(load_saved_plan_id().unwrap() >= 300);
+
+        let _ = fs::remove_file(path);
+    }
+
+    #[test]
+    fn plan_id_counter_migrates_existing_plans() {
+        // 旧数据中的计划 ID 来自现金 UID 计数器，可能远大于计划计数器
+        let mut db = CashDatabase::new();
+        db.insert(Cash::new_installment(
+            None,
+            900,
+            3,
+            PaymentFrequency::Monthly,
+            Utc::now(),
+            1,
+            Some(50_000),
+        ));
+        assert_eq!(db.max_plan_id(), Some(50_000));
+
+        sync_plan_id_counter(&db);
+        assert!(allocate_plan_id() > 50_000);
+
+        let uid_before = CASH_UID_COUNTER.load(Ordering::SeqCst);
+        let record =
+            Cash::new_installment(None, 300, 3, PaymentFrequency::Weekly, Utc::now(), 1, None);
+        assert!(record.installment_plan_id().unwrap() > 50_000);
+        assert_ne!(record.installment_plan_id(), Some(record.uid));
+        assert!(CASH_UID_COUNTER.load(Ordering::SeqCst) > uid_before);
+    }
+
     #[test]
     fn cash_database_save_and_load() {
         setup();
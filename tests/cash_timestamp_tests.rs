@@ -124,7 +124,7 @@ fn test_cash_serialization_with_timestamp() {
 
     // 验证时间戳保持不变
     assert_eq!(deserialized.created_at, original_time);
-    assert_eq!(deserialized.cash, 5000);
+    assert_eq!(deserialized.cash.amount_minor, 5000);
     assert_eq!(deserialized.note, Some("测试备注".to_string()));
     assert_eq!(deserialized.student_id, Some(789));
 }
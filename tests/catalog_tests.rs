@@ -0,0 +1,269 @@
+// 测试价目表管理、售卖及退款撤回
+use chrono::{Months, TimeZone, Utc};
+use qmx_backend_lib::error::Error;
+use qmx_backend_lib::student::Class;
+use qmx_backend_lib::{
+    AutoSave, CashBuilder, CatalogItemBuilder, Event, EventKind, FixedClock, ItemEffect, Money,
+    QmxManager, StudentBuilder,
+};
+use std::sync::{Arc, Mutex};
+use tempfile::TempDir;
+
+fn manager(temp_dir: &TempDir) -> QmxManager {
+    QmxManager::builder()
+        .data_dir(temp_dir.path())
+        .auto_save(AutoSave::Off)
+        .build()
+        .unwrap()
+}
+
+mod catalog_tests {
+    use super::*;
+
+    #[test]
+    fn test_default_catalog_and_persistence() {
+        let temp_dir = TempDir::new().unwrap();
+        let manager = manager(&temp_dir);
+        let names: Vec<String> = manager
+            .list_catalog()
+            .unwrap()
+            .into_iter()
+            .map(|item| item.name)
+            .collect();
+        assert_eq!(names, vec!["十次卡", "月卡", "年卡", "器材租赁"]);
+
+        let uid = manager
+            .add_catalog_item(CatalogItemBuilder::new(
+                "二十次卡",
+                180_000,
+                ItemEffect::Lessons {
+                    class: Class::TenTry,
+                    count: 20,
+                },
+            ))
+            .unwrap();
+        assert_eq!(uid, 5);
+        manager
+            .update_catalog_item(
+                4,
+                CatalogItemBuilder::new("器材租赁", 8_000, ItemEffect::None),
+            )
+            .unwrap();
+        assert!(manager.delete_catalog_item(2).unwrap());
+        assert!(!manager.delete_catalog_item(2).unwrap());
+        assert!(matches!(
+            manager.update_catalog_item(2, CatalogItemBuilder::new("月卡", 1, ItemEffect::None)),
+            Err(Error::NotFound(_))
+        ));
+
+        // 价目表立即写入磁盘，重新打开后保留修改
+        let reopened = self::manager(&temp_dir);
+        let items = reopened.list_catalog().unwrap();
+        assert_eq!(items.len(), 4);
+        assert!(reopened.get_catalog_item(2).unwrap().is_none());
+        assert_eq!(
+            reopened.get_catalog_item(4).unwrap().unwrap().price,
+            Money::cny(8_000)
+        );
+        assert_eq!(
+            reopened.get_catalog_item(5).unwrap().unwrap().name,
+            "二十次卡"
+        );
+    }
+
+    #[test]
+    fn test_sell_lessons_records_cash_and_grants() {
+        let temp_dir = TempDir::new().unwrap();
+        let manager = manager(&temp_dir);
+        let student = manager
+            .create_student(StudentBuilder::new("张三").class(Class::Month))
+            .unwrap();
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let seen = events.clone();
+        manager.subscribe(EventKind::StudentUpdated, move |event| {
+            seen.lock().unwrap().push(event.clone());
+        });
+
+        let cash_uid = manager.sell(student, 1).unwrap();
+        let cash = manager.get_cash(cash_uid).unwrap().unwrap();
+        assert_eq!(cash.student_id, Some(student));
+        assert_eq!(cash.cash, Money::cny(100_000));
+        assert_eq!(cash.note.as_deref(), Some("十次卡"));
+        let updated = manager.get_student(student).unwrap().unwrap();
+        assert_eq!(updated.class(), &Class::TenTry);
+        assert_eq!(updated.lesson_left(), Some(10));
+        assert!(matches!(
+            events.lock().unwrap().as_slice(),
+            [Event::StudentUpdated { uid, .. }] if *uid == student
+        ));
+
+        // 再买一次在原有课时上累加
+        manager.sell(student, 1).unwrap();
+        let updated = manager.get_student(student).unwrap().unwrap();
+        assert_eq!(updated.lesson_left(), Some(20));
+
+        // 一次撤销同时恢复收款和课时
+        assert_eq!(manager.undo_last(1).unwrap(), 1);
+        assert_eq!(manager.get_student_cash(student).unwrap().len(), 1);
+        let updated = manager.get_student(student).unwrap().unwrap();
+        assert_eq!(updated.lesson_left(), Some(10));
+    }
+
+    #[test]
+    fn test_sell_membership_extends_from_end_date() {
+        let temp_dir = TempDir::new().unwrap();
+        let now = Utc.with_ymd_and_hms(2025, 1, 31, 8, 0, 0).unwrap();
+        let manager = manager(&temp_dir).with_clock(Arc::new(FixedClock::new(now)));
+        let student = manager.create_student(StudentBuilder::new("李四")).unwrap();
+
+        manager.sell(student, 2).unwrap();
+        let updated = manager.get_student(student).unwrap().unwrap();
+        assert_eq!(updated.class(), &Class::Month);
+        assert_eq!(updated.membership_start_date(), Some(now));
+        let month_end = Utc.with_ymd_and_hms(2025, 2, 28, 8, 0, 0).unwrap();
+        assert_eq!(updated.membership_end_date(), Some(month_end));
+
+        // 会员未到期时从原结束时间顺延，开始时间不变
+        manager.sell(student, 3).unwrap();
+        let updated = manager.get_student(student).unwrap().unwrap();
+        assert_eq!(updated.class(), &Class::Year);
+        assert_eq!(updated.membership_start_date(), Some(now));
+        assert_eq!(
+            updated.membership_end_date(),
+            month_end.checked_add_months(Months::new(12))
+        );
+    }
+
+    #[test]
+    fn test_sell_rental_and_failures() {
+        let temp_dir = TempDir::new().unwrap();
+        let manager = manager(&temp_dir);
+        let student = manager.create_student(StudentBuilder::new("王五")).unwrap();
+        let before = manager.get_student(student).unwrap().unwrap();
+
+        let cash_uid = manager.sell(student, 4).unwrap();
+        assert_eq!(
+            manager.get_cash(cash_uid).unwrap().unwrap().cash,
+            Money::cny(5_000)
+        );
+        let after = manager.get_student(student).unwrap().unwrap();
+        assert_eq!(after.updated_at(), before.updated_at());
+        assert_eq!(after.lesson_left(), before.lesson_left());
+
+        assert!(matches!(manager.sell(student, 99), Err(Error::NotFound(_))));
+        assert!(matches!(
+            manager.sell(9_999_999, 1),
+            Err(Error::NotFound(_))
+        ));
+        assert_eq!(manager.get_student_cash(student).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_marked_sale_credits_and_full_refund_reverses() {
+        let temp_dir = TempDir::new().unwrap();
+        let manager = manager(&temp_dir);
+        let student = manager
+            .create_student(StudentBuilder::new("赵六").class(Class::TenTry))
+            .unwrap();
+        assert_eq!(
+            manager.get_student(student).unwrap().unwrap().lesson_left(),
+            Some(10)
+        );
+
+        // 折扣价售出，课时按商品增加
+        let sale = manager
+            .record_cash(CashBuilder::new(90_000).student_id(student).sale(1))
+            .unwrap();
+        let cash = manager.get_cash(sale).unwrap().unwrap();
+        let credit = cash.sale.unwrap();
+        assert_eq!((credit.item_id, credit.lessons), (1, 10));
+        assert!(!credit.reversed);
+        assert_eq!(
+            manager.get_student(student).unwrap().unwrap().lesson_left(),
+            Some(20)
+        );
+
+        // 部分退款不影响课时
+        manager.refund_cash(sale, 30_000, None).unwrap();
+        assert_eq!(
+            manager.get_student(student).unwrap().unwrap().lesson_left(),
+            Some(20)
+        );
+
+        // 全额退清后扣回课时，并标记已撤回
+        manager.refund_cash(sale, 60_000, None).unwrap();
+        assert_eq!(
+            manager.get_student(student).unwrap().unwrap().lesson_left(),
+            Some(10)
+        );
+        assert!(
+            manager
+                .get_cash(sale)
+                .unwrap()
+                .unwrap()
+                .sale
+                .unwrap()
+                .reversed
+        );
+
+        // 撤销退款时课时和撤回标记一起恢复
+        assert_eq!(manager.undo_last(1).unwrap(), 1);
+        assert_eq!(
+            manager.get_student(student).unwrap().unwrap().lesson_left(),
+            Some(20)
+        );
+        assert!(
+            !manager
+                .get_cash(sale)
+                .unwrap()
+                .unwrap()
+                .sale
+                .unwrap()
+                .reversed
+        );
+
+        // 售卖收款必须关联学生
+        assert!(matches!(
+            manager.record_cash(CashBuilder::new(100).sale(1)),
+            Err(Error::InvalidInput(_))
+        ));
+    }
+
+    #[test]
+    fn test_refund_restores_previous_membership() {
+        let temp_dir = TempDir::new().unwrap();
+        let now = Utc.with_ymd_and_hms(2025, 3, 1, 0, 0, 0).unwrap();
+        let manager = manager(&temp_dir).with_clock(Arc::new(FixedClock::new(now)));
+        let old_start = Utc.with_ymd_and_hms(2025, 2, 1, 0, 0, 0).unwrap();
+        let old_end = Utc.with_ymd_and_hms(2025, 3, 10, 0, 0, 0).unwrap();
+        let student = manager
+            .create_student(StudentBuilder::new("孙七").membership(old_start, old_end))
+            .unwrap();
+
+        let uids = manager
+            .record_cash_batch(vec![
+                CashBuilder::new(80_000).student_id(student).sale(2),
+                CashBuilder::new(80_000).student_id(student).sale(2),
+            ])
+            .unwrap();
+        let updated = manager.get_student(student).unwrap().unwrap();
+        assert_eq!(
+            updated.membership_end_date(),
+            Some(Utc.with_ymd_and_hms(2025, 5, 10, 0, 0, 0).unwrap())
+        );
+
+        manager.refund_cash(uids[1], 80_000, None).unwrap();
+        let updated = manager.get_student(student).unwrap().unwrap();
+        assert_eq!(updated.membership_start_date(), Some(old_start));
+        assert_eq!(
+            updated.membership_end_date(),
+            Some(Utc.with_ymd_and_hms(2025, 4, 10, 0, 0, 0).unwrap())
+        );
+
+        // 整批在撤销时算作一次操作，学生恢复到售卖前
+        assert_eq!(manager.undo_last(2).unwrap(), 2);
+        let updated = manager.get_student(student).unwrap().unwrap();
+        assert_eq!(updated.membership_end_date(), Some(old_end));
+        assert!(manager.get_student_cash(student).unwrap().is_empty());
+    }
+}
@@ -0,0 +1,77 @@
+// 测试数据文件的完整性校验
+use qmx_backend_lib::checksum::{checksum, checksum_path, read_checksums};
+use qmx_backend_lib::error::Error;
+use qmx_backend_lib::student::{Student, StudentDatabase};
+use tempfile::TempDir;
+
+fn saved_db(dir: &TempDir) -> (String, u64) {
+    let path = dir.path().join("student_database.json");
+    let path = path.to_str().unwrap().to_string();
+    let mut db = StudentDatabase::new();
+    let mut student = Student::new();
+    student.set_name("张三".to_string());
+    let uid = student.uid();
+    db.insert(student);
+    db.save_to(&path).unwrap();
+    (path, uid)
+}
+
+mod checksum_tests {
+    use super::*;
+
+    #[test]
+    fn test_checksum_written_and_verified() {
+        let temp_dir = TempDir::new().unwrap();
+        let (path, uid) = saved_db(&temp_dir);
+
+        let bytes = std::fs::read(&path).unwrap();
+        assert_eq!(read_checksums(&path).unwrap().unwrap(), vec![checksum(&bytes)]);
+        let db = StudentDatabase::read_from(&path).unwrap();
+        assert_eq!(db.get(&uid).unwrap().name(), Some("张三"));
+
+        // 再次保存时保留上一版本的校验值
+        let mut db = db;
+        db.insert(Student::new());
+        db.save_to(&path).unwrap();
+        let sums = read_checksums(&path).unwrap().unwrap();
+        assert_eq!(sums, vec![checksum(&std::fs::read(&path).unwrap()), checksum(&bytes)]);
+    }
+
+    #[test]
+    fn test_corrupted_file() {
+        let temp_dir = TempDir::new().unwrap();
+        let (path, _) = saved_db(&temp_dir);
+
+        // 内容仍是合法 JSON，但与校验值不符
+        let text = std::fs::read_to_string(&path).unwrap().replace("张三", "李四");
+        std::fs::write(&path, text).unwrap();
+        match StudentDatabase::read_from(&path) {
+            Err(Error::Corrupted {
+                path: corrupted,
+                expected,
+                actual,
+            }) => {
+                assert_eq!(corrupted, path);
+                assert_ne!(expected, actual);
+            }
+            other => panic!("应返回 Corrupted，实际为 {:?}", other.map(|db| db.len())),
+        }
+
+        // 截断导致无法解析时也优先报告校验失败
+        let bytes = std::fs::read(&path).unwrap();
+        std::fs::write(&path, &bytes[..bytes.len() / 2]).unwrap();
+        let err = StudentDatabase::read_from(&path).unwrap_err();
+        assert_eq!(err.code(), "corrupted");
+    }
+
+    #[test]
+    fn test_missing_checksum_is_skipped() {
+        let temp_dir = TempDir::new().unwrap();
+        let (path, uid) = saved_db(&temp_dir);
+
+        // 旧版本数据没有校验文件
+        std::fs::remove_file(checksum_path(&path)).unwrap();
+        let db = StudentDatabase::read_from(&path).unwrap();
+        assert!(db.get(&uid).is_some());
+    }
+}
@@ -0,0 +1,276 @@
+// 测试可注入时钟在管理器中的行为
+use chrono::{Duration, TimeZone, Utc};
+use qmx_backend_lib::cash::{Cash, InstallmentStatus, PaymentFrequency};
+use qmx_backend_lib::{
+    CashBuilder, CashUpdater, FixedClock, InstallmentPlanBuilder, MembershipStatus, QmxManager,
+    StudentBuilder, TimePeriod,
+};
+use std::sync::Arc;
+use tempfile::TempDir;
+
+fn setup() -> TempDir {
+    let temp_dir = TempDir::new().unwrap();
+    std::env::set_current_dir(temp_dir.path()).unwrap();
+    let _ = std::fs::create_dir_all("data");
+    temp_dir
+}
+
+mod fixed_clock_tests {
+    use super::*;
+
+    #[test]
+    fn test_record_cash_uses_clock() {
+        let _temp_dir = setup();
+        let frozen = Utc.with_ymd_and_hms(2024, 5, 20, 10, 0, 0).unwrap();
+        let clock = Arc::new(FixedClock::new(frozen));
+        let manager = QmxManager::builder()
+            .auto_save(false)
+            .build()
+            .unwrap()
+            .with_clock(clock.clone());
+
+        let uid = manager.record_cash(CashBuilder::new(800)).unwrap();
+        assert_eq!(manager.get_cash(uid).unwrap().unwrap().created_at, frozen);
+
+        let stats = manager.get_financial_stats(TimePeriod::Today).unwrap();
+        assert_eq!(stats.total_income, 800);
+
+        // 时间推进到第二天后，今天的统计为空
+        clock.advance(Duration::days(1));
+        let stats = manager.get_financial_stats(TimePeriod::Today).unwrap();
+        assert_eq!(stats.transaction_count, 0);
+        let stats = manager.get_financial_stats(TimePeriod::ThisMonth).unwrap();
+        assert_eq!(stats.total_income, 800);
+    }
+
+    #[test]
+    fn test_membership_status_follows_clock() {
+        let _temp_dir = setup();
+        let start = Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap();
+        let end = start + Duration::days(30);
+        let clock = Arc::new(FixedClock::new(start + Duration::days(10)));
+        let manager = QmxManager::builder()
+            .auto_save(false)
+            .build()
+            .unwrap()
+            .with_clock(clock.clone());
+
+        let uid = manager
+            .create_student(StudentBuilder::new("时钟会员").membership(start, end))
+            .unwrap();
+
+        let stats = manager.get_student_stats(uid).unwrap();
+        assert!(matches!(
+            stats.membership_status,
+            MembershipStatus::Active { .. }
+        ));
+        let student = manager.get_student(uid).unwrap().unwrap();
+        assert!(student.is_membership_active_at(manager.now()));
+        assert_eq!(
+            student.membership_days_remaining_at(manager.now()),
+            Some(20)
+        );
+
+        clock.advance(Duration::days(31));
+        let stats = manager.get_student_stats(uid).unwrap();
+        assert!(matches!(
+            stats.membership_status,
+            MembershipStatus::Expired { .. }
+        ));
+        assert!(!student.is_membership_active_at(manager.now()));
+    }
+
+    #[test]
+    fn test_expiring_memberships_follow_clock() {
+        let _temp_dir = setup();
+        let now = Utc.with_ymd_and_hms(2024, 3, 1, 0, 0, 0).unwrap();
+        let clock = Arc::new(FixedClock::new(now));
+        let manager = QmxManager::builder()
+            .auto_save(false)
+            .build()
+            .unwrap()
+            .with_clock(clock.clone());
+
+        let start = now - Duration::days(30);
+        let later = manager
+            .create_student(
+                StudentBuilder::new("十天后到期").membership(start, now + Duration::days(10)),
+            )
+            .unwrap();
+        let sooner = manager
+            .create_student(
+                StudentBuilder::new("三天后到期").membership(start, now + Duration::days(3)),
+            )
+            .unwrap();
+        manager
+            .create_student(
+                StudentBuilder::new("已过期").membership(start, now - Duration::days(1)),
+            )
+            .unwrap();
+        manager
+            .create_student(
+                StudentBuilder::new("远期会员").membership(start, now + Duration::days(90)),
+            )
+            .unwrap();
+        manager
+            .create_student(StudentBuilder::new("非会员"))
+            .unwrap();
+
+        let uids: Vec<u64> = manager
+            .get_expiring_memberships(14)
+            .unwrap()
+            .iter()
+            .map(|s| s.uid())
+            .collect();
+        assert_eq!(uids, vec![sooner, later]);
+        assert!(manager.get_expiring_memberships(1).unwrap().is_empty());
+
+        // 时间推进后，三天后到期的会员已过期
+        clock.advance(Duration::days(5));
+        let uids: Vec<u64> = manager
+            .get_expiring_memberships(14)
+            .unwrap()
+            .iter()
+            .map(|s| s.uid())
+            .collect();
+        assert_eq!(uids, vec![later]);
+    }
+
+    #[test]
+    fn test_overdue_installments_follow_clock() {
+        let _temp_dir = setup();
+        let now = Utc.with_ymd_and_hms(2024, 3, 1, 0, 0, 0).unwrap();
+        let clock = Arc::new(FixedClock::new(now));
+        let manager = QmxManager::builder()
+            .auto_save(false)
+            .build()
+            .unwrap()
+            .with_clock(clock.clone());
+
+        let due = now + Duration::days(7);
+        let installment =
+            Cash::new_installment(None, 300, 3, PaymentFrequency::Weekly, due, 1, None)
+                .installment
+                .unwrap();
+        let uid = manager
+            .record_cash(CashBuilder::new(100).installment(installment))
+            .unwrap();
+        assert!(manager.get_overdue_installments().unwrap().is_empty());
+
+        clock.advance(Duration::days(8));
+        let overdue = manager.get_overdue_installments().unwrap();
+        assert_eq!(overdue.len(), 1);
+        assert_eq!(overdue[0].uid, uid);
+
+        manager
+            .update_cash(uid, CashUpdater::new().installment(None))
+            .unwrap();
+        assert!(manager.get_overdue_installments().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_mark_overdue_installments() {
+        let _temp_dir = setup();
+        let now = Utc.with_ymd_and_hms(2024, 6, 1, 0, 0, 0).unwrap();
+        let clock = Arc::new(FixedClock::new(now));
+        let manager = QmxManager::builder()
+            .auto_save(false)
+            .build()
+            .unwrap()
+            .with_clock(clock.clone());
+
+        let plan = manager
+            .create_installment_plan(InstallmentPlanBuilder::new(
+                900,
+                3,
+                PaymentFrequency::Weekly,
+                now + Duration::days(1),
+            ))
+            .unwrap();
+        assert!(manager.mark_overdue_installments().unwrap().is_empty());
+
+        // 前两期到期
+        clock.advance(Duration::days(9));
+        let marked = manager.mark_overdue_installments().unwrap();
+        let uids: Vec<_> = marked.iter().map(|c| c.uid).collect();
+        assert_eq!(uids, plan.cash_uids[..2].to_vec());
+        assert!(
+            marked
+                .iter()
+                .all(|c| { c.installment.as_ref().unwrap().status == InstallmentStatus::Overdue })
+        );
+
+        // 已标记的记录不会重复返回，也不再出现在待付逾期列表中
+        assert!(manager.mark_overdue_installments().unwrap().is_empty());
+        assert!(manager.get_overdue_installments().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_grace_period_delays_overdue() {
+        let _temp_dir = setup();
+        let now = Utc.with_ymd_and_hms(2024, 6, 1, 0, 0, 0).unwrap();
+        let clock = Arc::new(FixedClock::new(now));
+        let manager = QmxManager::builder()
+            .auto_save(false)
+            .grace_days(3)
+            .build()
+            .unwrap()
+            .with_clock(clock.clone());
+
+        let due = now + Duration::days(1);
+        let global = manager
+            .create_installment_plan(InstallmentPlanBuilder::new(
+                100,
+                1,
+                PaymentFrequency::Monthly,
+                due,
+            ))
+            .unwrap();
+        let strict = manager
+            .create_installment_plan(
+                InstallmentPlanBuilder::new(100, 1, PaymentFrequency::Monthly, due).grace_days(0),
+            )
+            .unwrap();
+        let lenient = manager
+            .create_installment_plan(
+                InstallmentPlanBuilder::new(100, 1, PaymentFrequency::Monthly, due).grace_days(10),
+            )
+            .unwrap();
+        let overdue = || -> Vec<u64> {
+            manager
+                .get_overdue_installments()
+                .unwrap()
+                .iter()
+                .map(|c| c.uid)
+                .collect()
+        };
+
+        // 到期后第二天只有不设宽限期的计划逾期
+        clock.advance(Duration::days(2));
+        assert_eq!(overdue(), strict.cash_uids);
+
+        // 全局宽限 3 天已过，计划单独设置的 10 天未过
+        clock.advance(Duration::days(3));
+        let mut expected = [global.cash_uids.clone(), strict.cash_uids.clone()].concat();
+        expected.sort();
+        assert_eq!(overdue(), expected);
+        let marked: Vec<u64> = manager
+            .mark_overdue_installments()
+            .unwrap()
+            .iter()
+            .map(|c| c.uid)
+            .collect();
+        assert_eq!(marked, expected);
+
+        clock.advance(Duration::days(8));
+        assert_eq!(overdue(), lenient.cash_uids);
+        assert_eq!(
+            manager
+                .get_installment_plan(lenient.plan_id)
+                .unwrap()
+                .unwrap()
+                .grace_days,
+            Some(10)
+        );
+    }
+}
@@ -0,0 +1,122 @@
+// 测试教练管理与学生分配
+use qmx_backend_lib::error::Error;
+use qmx_backend_lib::student::{Student, Subject};
+use qmx_backend_lib::{CoachBuilder, QmxManager, StudentBuilder, StudentQuery};
+use tempfile::TempDir;
+
+fn setup() -> TempDir {
+    let temp_dir = TempDir::new().unwrap();
+    std::env::set_current_dir(temp_dir.path()).unwrap();
+    let _ = std::fs::create_dir_all("data");
+    temp_dir
+}
+
+mod coach_tests {
+    use super::*;
+
+    #[test]
+    fn test_coach_crud_persists() {
+        let _temp_dir = setup();
+        let manager = QmxManager::builder().auto_save(false).build().unwrap();
+        let uid = manager
+            .create_coach(
+                CoachBuilder::new("王教练")
+                    .phone("13900000000")
+                    .subject(Subject::Shooting)
+                    .subject(Subject::Shooting),
+            )
+            .unwrap();
+
+        let coach = manager.get_coach(uid).unwrap().unwrap();
+        assert_eq!(coach.name, "王教练");
+        assert_eq!(coach.phone.as_deref(), Some("13900000000"));
+        assert_eq!(coach.subjects, vec![Subject::Shooting]);
+        assert!(coach.teaches(&Subject::Shooting));
+
+        manager
+            .update_coach(
+                uid,
+                CoachBuilder::new("王教练")
+                    .subject(Subject::Shooting)
+                    .subject(Subject::Archery),
+            )
+            .unwrap();
+
+        // 教练数据立即写入磁盘，不依赖手动保存
+        let reloaded = QmxManager::builder().auto_save(false).build().unwrap();
+        let coaches = reloaded.list_coaches().unwrap();
+        assert_eq!(coaches.len(), 1);
+        assert_eq!(coaches[0].phone, None);
+        assert!(coaches[0].teaches(&Subject::Archery));
+
+        assert!(matches!(
+            manager.update_coach(99, CoachBuilder::new("不存在")),
+            Err(Error::NotFound(_))
+        ));
+        assert!(manager.delete_coach(uid).unwrap());
+        assert!(!manager.delete_coach(uid).unwrap());
+        assert!(manager.list_coaches().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_assign_coach_and_roster() {
+        let _temp_dir = setup();
+        let manager = QmxManager::builder().auto_save(false).build().unwrap();
+        let coach = manager.create_coach(CoachBuilder::new("李教练")).unwrap();
+        let other = manager.create_coach(CoachBuilder::new("赵教练")).unwrap();
+        let first = manager
+            .create_student(StudentBuilder::new("学生甲"))
+            .unwrap();
+        let second = manager
+            .create_student(StudentBuilder::new("学生乙"))
+            .unwrap();
+        manager
+            .create_student(StudentBuilder::new("学生丙"))
+            .unwrap();
+
+        let changes = manager.assign_coach(first, Some(coach)).unwrap();
+        assert_eq!(changes[0].field, "assigned_coach");
+        manager.assign_coach(second, Some(coach)).unwrap();
+        assert!(matches!(
+            manager.assign_coach(first, Some(99)),
+            Err(Error::NotFound(_))
+        ));
+
+        let roster: Vec<u64> = manager
+            .search_students(StudentQuery::new().coach(coach))
+            .unwrap()
+            .iter()
+            .map(|s| s.uid())
+            .collect();
+        assert_eq!(roster, vec![first, second]);
+        assert!(
+            manager
+                .search_students(StudentQuery::new().coach(other))
+                .unwrap()
+                .is_empty()
+        );
+
+        // 仍有学生时不能删除教练
+        assert!(matches!(manager.delete_coach(coach), Err(Error::State(_))));
+        manager.assign_coach(first, Some(other)).unwrap();
+        manager.assign_coach(second, None).unwrap();
+        assert!(manager.delete_coach(coach).unwrap());
+        assert_eq!(
+            manager
+                .get_student(first)
+                .unwrap()
+                .unwrap()
+                .assigned_coach(),
+            Some(other)
+        );
+    }
+
+    #[test]
+    fn test_assigned_coach_backward_compatible() {
+        let student = Student::new();
+        let mut value = serde_json::to_value(&student).unwrap();
+        value.as_object_mut().unwrap().remove("assigned_coach");
+        let legacy: Student = serde_json::from_value(value).unwrap();
+        assert_eq!(legacy.assigned_coach(), None);
+    }
+}
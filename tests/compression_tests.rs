@@ -0,0 +1,183 @@
+// 测试数据文件压缩
+use qmx_backend_lib::cash::CashDatabase;
+use qmx_backend_lib::compression::{compression, is_compressed};
+use qmx_backend_lib::student::StudentDatabase;
+use qmx_backend_lib::{
+    Compression, EncryptionKey, QmxManager, StudentBuilder, StudentUpdater, set_compression,
+    set_encryption_key,
+};
+use std::sync::Mutex;
+use tempfile::TempDir;
+
+/// 压缩方式是全局配置，同一时间只能有一个测试修改
+static COMPRESSION_LOCK: Mutex<()> = Mutex::new(());
+
+/// 由标准 gzip 实现（最高压缩级别，动态哈夫曼编码）生成的现金数据库文件，包含 40 条记录
+const REFERENCE_FILE: &str = concat!(
+    "1f8b0800000000000203a5d74d6a1b4110c5f1ab845ecbd0ef55cf97cee155364244821814056279657c9decb2cb2607",
+    "0ae41891039a52f7542d2a03c22031f05fbd9fa75ed3a7fdf3e7dd617fd9a7ed6bc2fb9f97a743da62939e2f2f87e3f9",
+    "b27bffcacdbf07afbfe7bc49e7af9763daa6df3fbefff9f9eb03d2263d9d9f2ffbd3e9cbf5f9b43dbf9c4ed7e7bf1df7",
+    "97e361b7bffe9298591e321e321ff3b8cdf9faf998de3689739075506e412e838c04a50eca1c943a586e41590625122c",
+    "75b0ccc15207bb5bb02c832512ecea603707bb3ad8df82dd32d845827d1dece7605f07875bb05f06fb4870a883c31c1c",
+    "ea206ec161191c22c1b10e8e73707466312e83632438d5c1690e4ece2ca665700a0491eb20b22e3f3bc3b84e7fb9fd1c",
+    "89a289de7103671cb0c0898883461c2839a0331018e820a20e1a75a0ec409c91c080071179d0c803a507c5190a0c7c10",
+    "d1078d3e507ed079ff430c801011088d405082d03b83818110220aa15108ca10066f3206448848844622284518bdc918",
+    "1821a2111a8da01c61f22663808488486c44a28ac4ec4c8686488c88c44624aa488433191a22312212db77a0bb97203a",
+    "93a1f51a1411898d48549128deab9721122322b111892a128b33191a223122121b91a822b17326434324464462231255",
+    "24f6ce646888c488486c44a28ac4c19b8c21122322b111892a12476f3286480cbd1fb53b55913879933144e2b4e25611",
+    "1549b23319314492bce65e519104dec56288245871b3888a247426238648c215778bdc9d66e24c46ace34c56dc2ea222",
+    "4971262386485256dc2fa22249e74c460c91a45b71c3888a24bd33193144927ec51d232a920cde640c91645871cb888a",
+    "24a3371943241957dc32a222c9e44dc61049a615b74c51914a7626530c914afeef5be6eded2f988fb88b33120000",
+);
+
+fn setup() -> TempDir {
+    let temp_dir = TempDir::new().unwrap();
+    std::env::set_current_dir(temp_dir.path()).unwrap();
+    let _ = std::fs::create_dir_all("data");
+    temp_dir
+}
+
+fn from_hex(hex: &str) -> Vec<u8> {
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&hex[i..i + 2], 16).unwrap())
+        .collect()
+}
+
+mod compression_tests {
+    use super::*;
+
+    #[test]
+    fn test_reads_reference_file() {
+        let temp_dir = setup();
+        let path = temp_dir.path().join("cash.json.gz");
+        std::fs::write(&path, from_hex(REFERENCE_FILE)).unwrap();
+
+        let db = CashDatabase::read_from(&path.to_string_lossy()).unwrap();
+        assert_eq!(db.len(), 40);
+        let cash = db.get(&40).unwrap();
+        assert_eq!(cash.cash.amount_minor, 4000);
+        assert_eq!(cash.note.as_deref(), Some("学费 40"));
+    }
+
+    #[test]
+    fn test_corrupt_file_is_rejected() {
+        let temp_dir = setup();
+        let path = temp_dir.path().join("cash.json.gz");
+        let mut bytes = from_hex(REFERENCE_FILE);
+        let middle = bytes.len() / 2;
+        bytes[middle] ^= 0xff;
+        std::fs::write(&path, &bytes).unwrap();
+        assert!(CashDatabase::read_from(&path.to_string_lossy()).is_err());
+
+        bytes.truncate(middle);
+        std::fs::write(&path, &bytes).unwrap();
+        assert!(CashDatabase::read_from(&path.to_string_lossy()).is_err());
+    }
+
+    #[test]
+    fn test_manager_roundtrip_with_compression() {
+        let _guard = COMPRESSION_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        let temp_dir = setup();
+        let student_path = temp_dir.path().join("students.json");
+        let cash_path = temp_dir.path().join("cash.json");
+        let student_path = student_path.to_string_lossy();
+        let cash_path = cash_path.to_string_lossy();
+
+        // 未压缩的旧文件
+        StudentDatabase::new().save_to(&student_path).unwrap();
+        CashDatabase::new().save_to(&cash_path).unwrap();
+        let manager = QmxManager::builder()
+            .database_files(&*student_path, &*cash_path)
+            .auto_save(false)
+            .build()
+            .unwrap();
+        let uid = manager.create_student(StudentBuilder::new("张三")).unwrap();
+        let mut rings = StudentUpdater::new();
+        for i in 0..2000 {
+            rings = rings.add_ring(f64::from(i % 11));
+        }
+        manager.update_student(uid, rings).unwrap();
+        manager.save().unwrap();
+        let plain_size = std::fs::metadata(&*student_path).unwrap().len();
+        assert!(!is_compressed(&std::fs::read(&*student_path).unwrap()));
+
+        // 启用压缩后旧文件照常加载，重新保存为压缩格式
+        let manager = QmxManager::builder()
+            .database_files(&*student_path, &*cash_path)
+            .auto_save(false)
+            .build()
+            .unwrap()
+            .with_compression(Compression::Gzip);
+        // 压缩方式只属于这个管理器，进程默认值不变
+        assert_eq!(compression(), Compression::None);
+        manager.save().unwrap();
+        let bytes = std::fs::read(&*student_path).unwrap();
+        assert!(is_compressed(&bytes));
+        assert!((bytes.len() as u64) * 4 < plain_size);
+
+        // 未启用压缩的管理器仍可读取压缩文件
+        let manager = QmxManager::builder()
+            .database_files(&*student_path, &*cash_path)
+            .auto_save(false)
+            .build()
+            .unwrap();
+        let student = manager.get_student(uid).unwrap().unwrap();
+        assert_eq!(student.rings().len(), 2000);
+        assert_eq!(student.rings()[1999], 8.0);
+    }
+
+    #[test]
+    fn test_compression_with_encryption() {
+        let _guard = COMPRESSION_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        let temp_dir = setup();
+        let path = temp_dir.path().join("cash.json");
+        let path = path.to_string_lossy();
+        let reference = from_hex(REFERENCE_FILE);
+        let reference_path = temp_dir.path().join("reference.json.gz");
+        std::fs::write(&reference_path, &reference).unwrap();
+        let db = CashDatabase::read_from(&reference_path.to_string_lossy()).unwrap();
+
+        set_compression(Compression::Gzip);
+        set_encryption_key(Some(EncryptionKey::new([3; 32])));
+        db.save_to(&path).unwrap();
+        set_compression(Compression::None);
+
+        let loaded = CashDatabase::read_from(&path);
+        set_encryption_key(None);
+        assert_eq!(loaded.unwrap().len(), 40);
+        assert!(CashDatabase::read_from(&path).is_err());
+    }
+
+    #[cfg(feature = "zstd")]
+    #[test]
+    fn test_zstd_roundtrip() {
+        let temp_dir = setup();
+        let student_path = temp_dir.path().join("students.json");
+        let cash_path = temp_dir.path().join("cash.json");
+        let student_path = student_path.to_string_lossy();
+        let cash_path = cash_path.to_string_lossy();
+        let db = CashDatabase::read_from(&{
+            let reference = temp_dir.path().join("reference.json.gz");
+            std::fs::write(&reference, from_hex(REFERENCE_FILE)).unwrap();
+            reference.to_string_lossy().into_owned()
+        })
+        .unwrap();
+        db.save_to(&cash_path).unwrap();
+        StudentDatabase::new().save_to(&student_path).unwrap();
+
+        let manager = QmxManager::builder()
+            .database_files(&*student_path, &*cash_path)
+            .compression(Compression::Zstd)
+            .auto_save(true)
+            .build()
+            .unwrap();
+        manager.create_student(StudentBuilder::new("张三")).unwrap();
+        manager.save().unwrap();
+        let bytes = std::fs::read(&*cash_path).unwrap();
+        assert_eq!(&bytes[..4], &[0x28, 0xb5, 0x2f, 0xfd]);
+        assert!(is_compressed(&bytes));
+        assert_eq!(CashDatabase::read_from(&cash_path).unwrap().len(), 40);
+        assert_eq!(StudentDatabase::read_from(&student_path).unwrap().len(), 1);
+    }
+}
@@ -0,0 +1,89 @@
+// 测试删除学生时关联现金记录的处理
+use qmx_backend_lib::{
+    AuditAction, AuditEntity, AutoSave, CashBuilder, DeletePolicy, Error, QmxManager,
+    StudentBuilder,
+};
+use tempfile::TempDir;
+
+fn manager(temp_dir: &TempDir) -> QmxManager {
+    QmxManager::builder()
+        .data_dir(temp_dir.path())
+        .auto_save(AutoSave::Off)
+        .build()
+        .unwrap()
+}
+
+/// 创建一名有两条现金记录的学生
+fn student_with_cash(manager: &QmxManager) -> (u64, Vec<u64>) {
+    let uid = manager.create_student(StudentBuilder::new("张三")).unwrap();
+    let cash = [1000, 500]
+        .into_iter()
+        .map(|amount| {
+            manager
+                .record_cash(CashBuilder::new(amount).student_id(uid))
+                .unwrap()
+        })
+        .collect();
+    (uid, cash)
+}
+
+mod delete_policy_tests {
+    use super::*;
+
+    #[test]
+    fn test_restrict_rejects_student_with_cash() {
+        let temp_dir = TempDir::new().unwrap();
+        let manager = manager(&temp_dir);
+        let (uid, cash) = student_with_cash(&manager);
+
+        let err = manager.delete_student(uid, DeletePolicy::Restrict).unwrap_err();
+        assert!(matches!(err, Error::State(_)));
+        assert!(manager.get_student(uid).unwrap().is_some());
+        assert_eq!(manager.get_student_cash(uid).unwrap().len(), cash.len());
+
+        let empty = manager.create_student(StudentBuilder::new("李四")).unwrap();
+        assert!(manager.delete_student(empty, DeletePolicy::default()).unwrap());
+        assert!(!manager.delete_student(empty, DeletePolicy::Restrict).unwrap());
+    }
+
+    #[test]
+    fn test_cascade_deletes_cash_in_one_operation() {
+        let temp_dir = TempDir::new().unwrap();
+        let manager = manager(&temp_dir);
+        let (uid, cash) = student_with_cash(&manager);
+
+        assert!(manager.delete_student(uid, DeletePolicy::Cascade).unwrap());
+        assert!(manager.get_student(uid).unwrap().is_none());
+        for cash_uid in &cash {
+            assert!(manager.get_cash(*cash_uid).unwrap().is_none());
+        }
+        assert!(manager.verify_integrity().unwrap().is_ok());
+
+        // 一次撤销同时恢复学生和现金记录
+        assert_eq!(manager.undo_last(1).unwrap(), 1);
+        assert!(manager.get_student(uid).unwrap().is_some());
+        assert_eq!(manager.get_student_cash(uid).unwrap().len(), cash.len());
+    }
+
+    #[test]
+    fn test_detach_keeps_cash_without_student() {
+        let temp_dir = TempDir::new().unwrap();
+        let manager = manager(&temp_dir);
+        let (uid, cash) = student_with_cash(&manager);
+
+        assert!(manager.delete_student(uid, DeletePolicy::Detach).unwrap());
+        for cash_uid in &cash {
+            let record = manager.get_cash(*cash_uid).unwrap().unwrap();
+            assert_eq!(record.student_id, None);
+        }
+        assert!(manager.verify_integrity().unwrap().is_ok());
+
+        let log = manager.get_audit_log(cash[0]).unwrap();
+        let detached = log
+            .iter()
+            .rfind(|entry| entry.entity == AuditEntity::Cash)
+            .unwrap();
+        assert_eq!(detached.action, AuditAction::Update);
+        assert_eq!(detached.changes[0].field, "student_id");
+    }
+}
@@ -0,0 +1,171 @@
+// 测试数据文件的静态加密
+use qmx_backend_lib::cash::CashDatabase;
+use qmx_backend_lib::encryption::{encryption_enabled, is_encrypted};
+use qmx_backend_lib::student::StudentDatabase;
+use qmx_backend_lib::{
+    Database, EncryptionKey, FileCodec, QmxManager, StudentBuilder, set_encryption_key,
+};
+use std::sync::Mutex;
+use tempfile::TempDir;
+
+/// 密钥是全局配置，同一时间只能有一个测试修改
+static KEY_LOCK: Mutex<()> = Mutex::new(());
+
+/// 由标准 AES-256-GCM 实现生成的加密文件：
+/// 密钥为 0x00..0x1f，随机数为 0x00..0x0b，明文为 `{"cash_data":{}}`
+const REFERENCE_FILE: &str = "514d58454e433031000102030405060708090a0b3c20b57ab68d9d7fec35f6a98b920510c0143fb1768d57f3861d027c2ea3d8a3";
+
+fn setup() -> TempDir {
+    let temp_dir = TempDir::new().unwrap();
+    std::env::set_current_dir(temp_dir.path()).unwrap();
+    let _ = std::fs::create_dir_all("data");
+    temp_dir
+}
+
+fn test_key() -> EncryptionKey {
+    let bytes: Vec<u8> = (0..32).collect();
+    EncryptionKey::from_slice(&bytes).unwrap()
+}
+
+fn from_hex(hex: &str) -> Vec<u8> {
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&hex[i..i + 2], 16).unwrap())
+        .collect()
+}
+
+mod encryption_tests {
+    use super::*;
+
+    #[test]
+    fn test_reads_reference_file() {
+        let _guard = KEY_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        let temp_dir = setup();
+        let path = temp_dir.path().join("cash.json");
+        std::fs::write(&path, from_hex(REFERENCE_FILE)).unwrap();
+        let path = path.to_string_lossy();
+
+        set_encryption_key(None);
+        let err = CashDatabase::read_from(&path).unwrap_err();
+        assert_eq!(err.code(), "encryption");
+
+        set_encryption_key(Some(EncryptionKey::new([7; 32])));
+        let err = CashDatabase::read_from(&path).unwrap_err();
+        assert_eq!(err.code(), "encryption");
+
+        set_encryption_key(Some(test_key()));
+        assert!(CashDatabase::read_from(&path).unwrap().is_empty());
+        set_encryption_key(None);
+    }
+
+    #[test]
+    fn test_manager_files_are_encrypted() {
+        let _guard = KEY_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        let _temp_dir = setup();
+
+        // 未加密的旧文件在设置密钥后仍可读取
+        set_encryption_key(None);
+        let manager = QmxManager::builder().auto_save(true).build().unwrap();
+        let old = manager
+            .create_student(StudentBuilder::new("旧学生"))
+            .unwrap();
+
+        set_encryption_key(Some(test_key()));
+        assert!(encryption_enabled());
+        let manager = QmxManager::builder().auto_save(true).build().unwrap();
+        assert!(manager.get_student(old).unwrap().is_some());
+        let uid = manager
+            .create_student(StudentBuilder::new("加密学生").phone("13812345678"))
+            .unwrap();
+
+        let raw = std::fs::read("data/student_database.json").unwrap();
+        assert!(is_encrypted(&raw));
+        assert!(!String::from_utf8_lossy(&raw).contains("13812345678"));
+        assert!(is_encrypted(&std::fs::read("data/audit_log.json").unwrap()));
+
+        let reloaded = QmxManager::builder().auto_save(false).build().unwrap();
+        let student = reloaded.get_student(uid).unwrap().unwrap();
+        assert_eq!(student.phone(), Some("13812345678"));
+        set_encryption_key(None);
+    }
+
+    #[test]
+    fn test_tampered_file_is_rejected() {
+        let _guard = KEY_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        let temp_dir = setup();
+        set_encryption_key(Some(test_key()));
+
+        let path = temp_dir.path().join("cash.json");
+        let path = path.to_string_lossy();
+        CashDatabase::new().save_to(&path).unwrap();
+        let mut raw = std::fs::read(&*path).unwrap();
+        let last = raw.len() - 20;
+        raw[last] ^= 1;
+        std::fs::write(&*path, raw).unwrap();
+
+        let err = CashDatabase::read_from(&path).unwrap_err();
+        assert_eq!(err.code(), "encryption");
+        assert!(EncryptionKey::from_slice(&[0; 16]).is_err());
+        set_encryption_key(None);
+    }
+
+    #[test]
+    fn test_keys_are_per_manager() {
+        let _guard = KEY_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        let temp_dir = setup();
+        set_encryption_key(None);
+        let dir_a = temp_dir.path().join("a");
+        let dir_b = temp_dir.path().join("b");
+
+        let manager_a = QmxManager::builder()
+            .data_dir(&dir_a)
+            .encryption_key(test_key())
+            .auto_save(true)
+            .build()
+            .unwrap();
+        let manager_b = QmxManager::builder()
+            .data_dir(&dir_b)
+            .encryption_key(EncryptionKey::new([9; 32]))
+            .auto_save(true)
+            .build()
+            .unwrap();
+        let plain = QmxManager::builder()
+            .data_dir(temp_dir.path().join("plain"))
+            .auto_save(true)
+            .build()
+            .unwrap();
+        manager_a.create_student(StudentBuilder::new("甲")).unwrap();
+        manager_b.create_student(StudentBuilder::new("乙")).unwrap();
+        plain.create_student(StudentBuilder::new("丙")).unwrap();
+
+        // 指定密钥不会修改进程默认密钥
+        assert!(!encryption_enabled());
+        let student_file = |dir: &std::path::Path| {
+            dir.join("student_database.json")
+                .to_string_lossy()
+                .into_owned()
+        };
+        let plain_file = student_file(&temp_dir.path().join("plain"));
+        assert!(!is_encrypted(&std::fs::read(&plain_file).unwrap()));
+        assert_eq!(StudentDatabase::read_from(&plain_file).unwrap().len(), 1);
+
+        let path_a = student_file(&dir_a);
+        assert!(is_encrypted(&std::fs::read(&path_a).unwrap()));
+        let codec_a = FileCodec::new().with_key(Some(test_key()));
+        let codec_b = FileCodec::new().with_key(Some(EncryptionKey::new([9; 32])));
+        let loaded = StudentDatabase::read_from_with(&path_a, &codec_a).unwrap();
+        assert_eq!(loaded.len(), 1);
+        let err = StudentDatabase::read_from_with(&path_a, &codec_b).unwrap_err();
+        assert_eq!(err.code(), "encryption");
+
+        // 预写日志同样使用管理器自己的密钥
+        let wal = std::fs::read_to_string(dir_b.join("wal.jsonl")).unwrap_or_default();
+        assert!(!wal.contains("乙"));
+        let reloaded = QmxManager::builder()
+            .data_dir(&dir_b)
+            .encryption_key(EncryptionKey::new([9; 32]))
+            .build()
+            .unwrap();
+        assert_eq!(reloaded.list_students().unwrap().len(), 1);
+    }
+}
@@ -0,0 +1,185 @@
+// 测试数据变更事件订阅
+use chrono::{Duration, TimeZone, Utc};
+use qmx_backend_lib::cash::PaymentFrequency;
+use qmx_backend_lib::{
+    AuditAction, AuditEntity, CashBuilder, ChangeEvent, DeletePolicy, Event, EventKind, FixedClock,
+    InstallmentPlanBuilder, QmxManager, StudentBuilder, StudentUpdater,
+};
+use std::sync::{Arc, Mutex};
+use tempfile::TempDir;
+
+fn setup() -> TempDir {
+    let temp_dir = TempDir::new().unwrap();
+    std::env::set_current_dir(temp_dir.path()).unwrap();
+    let _ = std::fs::create_dir_all("data");
+    temp_dir
+}
+
+/// 订阅事件并把收到的事件收集到列表中
+fn collect(manager: &QmxManager, kind: EventKind) -> Arc<Mutex<Vec<Event>>> {
+    let received = Arc::new(Mutex::new(Vec::new()));
+    let sink = received.clone();
+    manager.subscribe(kind, move |event| sink.lock().unwrap().push(event.clone()));
+    received
+}
+
+mod event_tests {
+    use super::*;
+
+    #[test]
+    fn test_student_events() {
+        let _temp_dir = setup();
+        let manager = QmxManager::builder().auto_save(false).build().unwrap();
+        let created = collect(&manager, EventKind::StudentCreated);
+        let updated = collect(&manager, EventKind::StudentUpdated);
+        let deleted = collect(&manager, EventKind::StudentDeleted);
+
+        let uid = manager
+            .create_student(StudentBuilder::new("事件学生"))
+            .unwrap();
+        manager
+            .update_student(uid, StudentUpdater::new().age(Some(12)))
+            .unwrap();
+        // 没有实际变化的更新不触发事件
+        manager
+            .update_student(uid, StudentUpdater::new().age(Some(12)))
+            .unwrap();
+        manager.delete_student(uid, DeletePolicy::Restrict).unwrap();
+
+        assert_eq!(
+            *created.lock().unwrap(),
+            vec![Event::StudentCreated { uid }]
+        );
+        let updated = updated.lock().unwrap();
+        assert_eq!(updated.len(), 1);
+        match &updated[0] {
+            Event::StudentUpdated {
+                uid: event_uid,
+                changes,
+            } => {
+                assert_eq!(*event_uid, uid);
+                assert_eq!(changes[0].field, "age");
+            }
+            other => panic!("意外的事件: {:?}", other),
+        }
+        assert_eq!(
+            *deleted.lock().unwrap(),
+            vec![Event::StudentDeleted { uid }]
+        );
+
+        // 撤销删除触发创建事件
+        manager.undo_last(1).unwrap();
+        assert_eq!(created.lock().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_cash_and_overdue_events() {
+        let _temp_dir = setup();
+        let start = Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap();
+        let clock = Arc::new(FixedClock::new(start));
+        let manager = QmxManager::builder()
+            .auto_save(false)
+            .build()
+            .unwrap()
+            .with_clock(clock.clone());
+        let recorded = collect(&manager, EventKind::CashRecorded);
+        let overdue = collect(&manager, EventKind::InstallmentOverdue);
+
+        let uid = manager.record_cash(CashBuilder::new(1000)).unwrap();
+        let refund = manager.refund_cash(uid, 200, None).unwrap();
+        let plan = manager
+            .create_installment_plan(InstallmentPlanBuilder::new(
+                2000,
+                2,
+                PaymentFrequency::Monthly,
+                start + Duration::days(5),
+            ))
+            .unwrap();
+
+        let recorded_uids: Vec<u64> = recorded.lock().unwrap().iter().map(|e| e.uid()).collect();
+        let mut expected = vec![uid, refund];
+        expected.extend(&plan.cash_uids);
+        assert_eq!(recorded_uids, expected);
+
+        clock.advance(Duration::days(10));
+        manager.mark_overdue_installments().unwrap();
+        assert_eq!(
+            *overdue.lock().unwrap(),
+            vec![Event::InstallmentOverdue {
+                uid: plan.cash_uids[0]
+            }]
+        );
+    }
+
+    #[test]
+    fn test_unsubscribe_and_reentrant_callback() {
+        let _temp_dir = setup();
+        let manager = Arc::new(QmxManager::builder().auto_save(false).build().unwrap());
+        let names = Arc::new(Mutex::new(Vec::new()));
+
+        // 回调中可以再次调用管理器
+        let inner = manager.clone();
+        let sink = names.clone();
+        let id = manager.subscribe(EventKind::StudentCreated, move |event| {
+            let student = inner.get_student(event.uid()).unwrap().unwrap();
+            sink.lock()
+                .unwrap()
+                .push(student.name().unwrap().to_string());
+        });
+
+        manager
+            .create_student(StudentBuilder::new("第一个"))
+            .unwrap();
+        assert!(manager.unsubscribe(id));
+        assert!(!manager.unsubscribe(id));
+        manager
+            .create_student(StudentBuilder::new("第二个"))
+            .unwrap();
+
+        assert_eq!(*names.lock().unwrap(), vec!["第一个".to_string()]);
+    }
+
+    #[test]
+    fn test_watch_channel() {
+        let _temp_dir = setup();
+        let manager = QmxManager::builder().auto_save(false).build().unwrap();
+        let changes = manager.watch();
+        let dropped = manager.watch();
+        drop(dropped);
+
+        let student = manager
+            .create_student(StudentBuilder::new("通知学生"))
+            .unwrap();
+        manager
+            .update_student(student, StudentUpdater::new().age(Some(15)))
+            .unwrap();
+        let cash = manager
+            .record_cash(CashBuilder::new(300).student_id(student))
+            .unwrap();
+        manager.delete_cash(cash).unwrap();
+        manager.undo_last(1).unwrap();
+
+        let change = |entity, uid, action| ChangeEvent {
+            entity,
+            uid,
+            action,
+        };
+        let received: Vec<ChangeEvent> = changes.try_iter().collect();
+        assert_eq!(
+            received,
+            vec![
+                change(AuditEntity::Student, student, AuditAction::Create),
+                change(AuditEntity::Student, student, AuditAction::Update),
+                change(AuditEntity::Cash, cash, AuditAction::Create),
+                change(AuditEntity::Cash, cash, AuditAction::Delete),
+                change(AuditEntity::Cash, cash, AuditAction::Create),
+            ]
+        );
+
+        // 接收端丢弃后不再发送
+        drop(changes);
+        manager
+            .delete_student(student, DeletePolicy::Cascade)
+            .unwrap();
+    }
+}
@@ -0,0 +1,165 @@
+// 测试数据导出
+use chrono::{Duration, TimeZone, Utc};
+use qmx_backend_lib::cash::PaymentFrequency;
+use qmx_backend_lib::student::Subject;
+use qmx_backend_lib::{
+    CashBuilder, CashQuery, CoachBuilder, FixedClock, InstallmentPlanBuilder, QmxManager,
+    SessionBuilder, StudentBuilder,
+};
+use std::sync::Arc;
+use tempfile::TempDir;
+
+fn setup() -> TempDir {
+    let temp_dir = TempDir::new().unwrap();
+    std::env::set_current_dir(temp_dir.path()).unwrap();
+    let _ = std::fs::create_dir_all("data");
+    temp_dir
+}
+
+mod cash_csv_tests {
+    use super::*;
+
+    #[test]
+    fn test_export_cash_csv() {
+        let temp_dir = setup();
+        let start = Utc.with_ymd_and_hms(2024, 3, 5, 8, 0, 0).unwrap();
+        let manager = QmxManager::builder()
+            .auto_save(false)
+            .build()
+            .unwrap()
+            .with_clock(Arc::new(FixedClock::new(start)));
+        let student = manager.create_student(StudentBuilder::new("张三")).unwrap();
+        manager
+            .record_cash(
+                CashBuilder::new(12050)
+                    .student_id(student)
+                    .note("学费, 三月 \"春季班\""),
+            )
+            .unwrap();
+        manager.record_cash(CashBuilder::new(-800)).unwrap();
+        manager
+            .create_installment_plan(
+                InstallmentPlanBuilder::new(3000, 3, PaymentFrequency::Monthly, start)
+                    .student_id(student),
+            )
+            .unwrap();
+
+        let path = temp_dir.path().join("cash.csv");
+        assert_eq!(manager.export_cash_csv(CashQuery::new(), &path).unwrap(), 5);
+
+        let content = std::fs::read_to_string(&path).unwrap();
+        let content = content.strip_prefix('\u{feff}').unwrap();
+        let lines: Vec<&str> = content.split("\r\n").collect();
+        assert_eq!(lines[0], "日期,学生,金额（元）,备注,付款计划");
+        assert_eq!(
+            lines[1],
+            "2024-03-05,张三,120.50,\"学费, 三月 \"\"春季班\"\"\","
+        );
+        assert_eq!(lines[2], "2024-03-05,,-8.00,,");
+        assert!(lines[3].starts_with("2024-03-05,张三,10.00,,计划 #"));
+        assert!(lines[3].ends_with("第 1/3 期（待付）"));
+        assert_eq!(lines[6], "");
+    }
+
+    #[test]
+    fn test_export_cash_csv_applies_query() {
+        let temp_dir = setup();
+        let manager = QmxManager::builder().auto_save(false).build().unwrap();
+        // 关联的学生不存在时（如旧版本数据中已删除的学生）以 UID 代替姓名
+        let student = 9_999_999;
+        manager
+            .record_cash(CashBuilder::new(500).student_id(student).allow_dangling())
+            .unwrap();
+        manager.record_cash(CashBuilder::new(700)).unwrap();
+
+        let path = temp_dir.path().join("filtered.csv");
+        let exported = manager
+            .export_cash_csv(CashQuery::new().student_id(student), &path)
+            .unwrap();
+        assert_eq!(exported, 1);
+
+        let content = std::fs::read_to_string(&path).unwrap();
+        let lines: Vec<&str> = content.lines().collect();
+        assert_eq!(lines.len(), 2);
+        assert!(lines[1].ends_with(&format!(",#{},5.00,,", student)));
+    }
+}
+
+mod ical_tests {
+    use super::*;
+
+    #[test]
+    fn test_export_ical() {
+        let temp_dir = setup();
+        let now = Utc.with_ymd_and_hms(2024, 3, 5, 8, 0, 0).unwrap();
+        let manager = QmxManager::builder()
+            .auto_save(false)
+            .build()
+            .unwrap()
+            .with_clock(Arc::new(FixedClock::new(now)));
+        let student = manager
+            .create_student(
+                StudentBuilder::new("张三")
+                    .membership(now - Duration::days(30), now + Duration::days(60)),
+            )
+            .unwrap();
+        manager
+            .create_student(
+                StudentBuilder::new("过期会员")
+                    .membership(now - Duration::days(60), now - Duration::days(1)),
+            )
+            .unwrap();
+        // 第一期已过到期日，不写入日历
+        manager
+            .create_installment_plan(
+                InstallmentPlanBuilder::new(
+                    3000,
+                    3,
+                    PaymentFrequency::Monthly,
+                    now - Duration::days(10),
+                )
+                .student_id(student),
+            )
+            .unwrap();
+
+        // 较长的教练姓名用于检查折行
+        let coach = manager
+            .create_coach(CoachBuilder::new("王".repeat(30)))
+            .unwrap();
+        let tomorrow = Utc.with_ymd_and_hms(2024, 3, 6, 9, 0, 0).unwrap();
+        let session = manager
+            .create_session(
+                SessionBuilder::new(tomorrow, tomorrow + Duration::hours(1), Subject::Shooting)
+                    .coach(coach),
+            )
+            .unwrap();
+        manager.enroll(session, student).unwrap();
+        manager
+            .create_session(SessionBuilder::new(
+                now - Duration::days(1),
+                now - Duration::days(1) + Duration::hours(1),
+                Subject::Archery,
+            ))
+            .unwrap();
+
+        let path = temp_dir.path().join("schedule.ics");
+        assert_eq!(manager.export_ical(&path).unwrap(), 4);
+
+        let content = std::fs::read_to_string(&path).unwrap();
+        assert!(content.split("\r\n").all(|line| line.len() <= 75));
+        let unfolded = content.replace("\r\n ", "");
+        let lines: Vec<&str> = unfolded.split("\r\n").collect();
+        assert_eq!(lines[0], "BEGIN:VCALENDAR");
+        assert_eq!(lines[lines.len() - 2], "END:VCALENDAR");
+        assert_eq!(lines.iter().filter(|l| **l == "BEGIN:VEVENT").count(), 4);
+        assert!(lines.contains(&"DTSTART:20240306T090000Z"));
+        assert!(lines.contains(&"SUMMARY:射击课"));
+        let description = format!("DESCRIPTION:教练：{}\\n已报名 1/10", "王".repeat(30));
+        assert!(lines.contains(&description.as_str()));
+        assert!(lines.contains(&"DTSTART;VALUE=DATE:20240324"));
+        assert!(lines.contains(&"DTSTART;VALUE=DATE:20240424"));
+        assert!(!lines.contains(&"DTSTART;VALUE=DATE:20240224"));
+        assert!(lines.contains(&"SUMMARY:会员到期：张三"));
+        assert!(!lines.contains(&"SUMMARY:会员到期：过期会员"));
+    }
+}
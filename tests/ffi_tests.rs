@@ -0,0 +1,224 @@
+// 测试 C 语言绑定，需要启用 ffi feature
+#![cfg(feature = "ffi")]
+
+use qmx_backend_lib::ffi::*;
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::ffi::{CStr, CString, c_char};
+use tempfile::TempDir;
+
+fn setup() -> TempDir {
+    let temp_dir = TempDir::new().unwrap();
+    std::env::set_current_dir(temp_dir.path()).unwrap();
+    let _ = std::fs::create_dir_all("data");
+    temp_dir
+}
+
+fn read_source(path: &str) -> String {
+    std::fs::read_to_string(format!("{}/{}", env!("CARGO_MANIFEST_DIR"), path)).unwrap()
+}
+
+/// 与 Rust 类型对应的 C 类型
+fn c_type(rust: &str) -> &'static str {
+    match rust {
+        "bool" => "bool",
+        "i32" => "int32_t",
+        "u64" => "uint64_t",
+        "*mut u64" => "uint64_t *",
+        "*const c_char" => "const char *",
+        "*mut c_char" => "char *",
+        "*const QmxManager" => "const QmxManager *",
+        "*mut QmxManager" => "QmxManager *",
+        other => panic!("未知的 FFI 类型 {}", other),
+    }
+}
+
+/// `name(参数) -> 返回值` 形式的 Rust 签名在头文件中的声明
+fn c_prototype(signature: &str) -> String {
+    let signature = signature.split_whitespace().collect::<Vec<_>>().join(" ");
+    let (name, rest) = signature.split_once('(').unwrap();
+    let (params, ret) = rest.split_once(')').unwrap();
+    let params: Vec<String> = params
+        .split(',')
+        .map(str::trim)
+        .filter(|param| !param.is_empty())
+        .map(|param| {
+            let (param, ty) = param.split_once(": ").unwrap();
+            join_c(c_type(ty), param)
+        })
+        .collect();
+    let ret = ret.trim().strip_prefix("-> ").map_or("void", c_type);
+    let params = if params.is_empty() {
+        "void".to_string()
+    } else {
+        params.join(", ")
+    };
+    format!("{}({});", join_c(ret, name), params)
+}
+
+/// 拼接 C 类型和名称，指针类型的 `*` 紧贴名称
+fn join_c(ty: &str, name: &str) -> String {
+    if ty.ends_with('*') {
+        format!("{}{}", ty, name)
+    } else {
+        format!("{} {}", ty, name)
+    }
+}
+
+/// 源码中方法 `function` 的 `Self::变体 => 值` 分支，键为变体名
+fn match_arms(source: &str, function: &str) -> BTreeMap<String, String> {
+    let body = source.split(&format!("fn {}(", function)).nth(1).unwrap();
+    let body = &body[..body.find("\n    }\n").unwrap()];
+    body.lines()
+        .filter_map(|line| line.trim().strip_prefix("Self::"))
+        .map(|arm| {
+            let (pattern, value) = arm.split_once(" => ").unwrap();
+            let variant = pattern
+                .split(|c: char| !c.is_alphanumeric())
+                .next()
+                .unwrap();
+            let value = value.trim_end_matches(',').trim_matches('"');
+            (variant.to_string(), value.to_string())
+        })
+        .collect()
+}
+
+/// 读取并释放本库返回的 JSON 字符串
+fn take_json(text: *mut c_char) -> Value {
+    assert!(!text.is_null());
+    let value = serde_json::from_str(unsafe { CStr::from_ptr(text) }.to_str().unwrap()).unwrap();
+    unsafe { qmx_string_free(text) };
+    value
+}
+
+fn last_error() -> String {
+    let message = qmx_last_error();
+    assert!(!message.is_null());
+    unsafe { CStr::from_ptr(message) }
+        .to_string_lossy()
+        .into_owned()
+}
+
+mod ffi_tests {
+    use super::*;
+
+    #[test]
+    fn test_student_and_cash_calls() {
+        let _temp_dir = setup();
+        let manager = qmx_manager_new(false);
+        assert!(!manager.is_null());
+
+        let json = CString::new(r#"{"name": "张三", "age": 16, "class": "TenTry"}"#).unwrap();
+        let mut uid = 0;
+        assert_eq!(
+            unsafe { qmx_student_create(manager, json.as_ptr(), &mut uid) },
+            0
+        );
+        let student = take_json(unsafe { qmx_student_get(manager, uid) });
+        assert_eq!(student["name"], "张三");
+        assert_eq!(
+            take_json(unsafe { qmx_student_get(manager, 999) }),
+            Value::Null
+        );
+
+        let query = CString::new(r#"{"name": "张"}"#).unwrap();
+        let found = take_json(unsafe { qmx_students_search(manager, query.as_ptr()) });
+        assert_eq!(found.as_array().unwrap().len(), 1);
+        let all = take_json(unsafe { qmx_students_search(manager, std::ptr::null()) });
+        assert_eq!(all.as_array().unwrap().len(), 1);
+
+        let cash = CString::new(format!(r#"{{"amount": 1500, "student_id": {}}}"#, uid)).unwrap();
+        let mut cash_uid = 0;
+        assert_eq!(
+            unsafe { qmx_cash_record(manager, cash.as_ptr(), &mut cash_uid) },
+            0
+        );
+        assert!(cash_uid > 0);
+        let stats = take_json(unsafe { qmx_dashboard_stats(manager) });
+        assert_eq!(stats["total_revenue"], 1500);
+
+        assert_eq!(unsafe { qmx_manager_save(manager) }, 0);
+        // 学生还有现金记录，默认策略拒绝删除
+        assert_eq!(unsafe { qmx_student_delete(manager, uid, 0) }, 2003);
+        assert_eq!(unsafe { qmx_student_delete(manager, uid, 1) }, 0);
+        unsafe { qmx_manager_free(manager) };
+    }
+
+    #[test]
+    fn test_errors_are_reported() {
+        let _temp_dir = setup();
+        let manager = qmx_manager_new(false);
+
+        let invalid = CString::new(r#"{"age": 3}"#).unwrap();
+        assert_eq!(
+            unsafe { qmx_student_create(manager, invalid.as_ptr(), std::ptr::null_mut()) },
+            1002
+        );
+        let zero = CString::new(r#"{"amount": 0}"#).unwrap();
+        assert_eq!(
+            unsafe { qmx_cash_record(manager, zero.as_ptr(), std::ptr::null_mut()) },
+            2002
+        );
+        assert!(last_error().contains("amount"));
+        assert_eq!(unsafe { qmx_student_delete(manager, 999, 0) }, 2001);
+        assert_eq!(unsafe { qmx_student_delete(manager, 999, 7) }, 2002);
+        assert_eq!(unsafe { qmx_manager_save(std::ptr::null()) }, 2002);
+        assert!(unsafe { qmx_dashboard_stats(std::ptr::null()) }.is_null());
+
+        unsafe { qmx_manager_free(manager) };
+        unsafe { qmx_manager_free(std::ptr::null_mut()) };
+        unsafe { qmx_string_free(std::ptr::null_mut()) };
+    }
+
+    #[test]
+    fn test_header_matches_exports() {
+        let source = read_source("src/ffi.rs");
+        let header = read_source("include/qmx_backend.h");
+
+        let expected: Vec<String> = source
+            .split("extern \"C\" fn ")
+            .skip(1)
+            .map(|rest| c_prototype(&rest[..rest.find(" {").unwrap()]))
+            .collect();
+        assert!(expected.len() >= 10);
+        for prototype in &expected {
+            assert!(
+                header.lines().any(|line| line == prototype),
+                "头文件缺少 {}",
+                prototype
+            );
+        }
+        let declared = header
+            .lines()
+            .filter(|line| line.contains("qmx_") && line.ends_with(");"))
+            .count();
+        assert_eq!(declared, expected.len(), "头文件声明了不存在的函数");
+    }
+
+    #[test]
+    fn test_header_error_codes() {
+        let source = read_source("src/error.rs");
+        let header = read_source("include/qmx_backend.h");
+
+        let names = match_arms(&source, "code");
+        let numbers = match_arms(&source, "numeric_code");
+        assert_eq!(names.len(), numbers.len());
+        for (variant, number) in &numbers {
+            let define = format!(
+                "#define QMX_ERR_{} {}",
+                names[variant].to_uppercase(),
+                number
+            );
+            assert!(
+                header.lines().any(|line| line == define),
+                "头文件缺少 {}",
+                define
+            );
+        }
+        let defined = header
+            .lines()
+            .filter(|line| line.starts_with("#define QMX_ERR_"))
+            .count();
+        assert_eq!(defined, numbers.len());
+    }
+}
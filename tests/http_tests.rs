@@ -0,0 +1,237 @@
+// 测试内嵌 HTTP API 服务，需要启用 http-server feature
+#![cfg(feature = "http-server")]
+
+use qmx_backend_lib::QmxManager;
+use qmx_backend_lib::http::{HttpServer, Request, handle};
+use serde_json::{Value, json};
+use std::io::{Read, Write};
+use std::net::{SocketAddr, TcpStream};
+use std::sync::Arc;
+use std::time::Duration;
+use tempfile::TempDir;
+
+const TOKEN: &str = "test-token";
+
+fn setup() -> TempDir {
+    let temp_dir = TempDir::new().unwrap();
+    std::env::set_current_dir(temp_dir.path()).unwrap();
+    let _ = std::fs::create_dir_all("data");
+    temp_dir
+}
+
+fn call(manager: &QmxManager, method: &str, target: &str, body: Value) -> (u16, Value) {
+    let body = if body.is_null() {
+        Vec::new()
+    } else {
+        serde_json::to_vec(&body).unwrap()
+    };
+    let response = handle(manager, &Request::new(method, target, body));
+    (response.status, response.body)
+}
+
+fn start_server(manager: Arc<QmxManager>, timeout: Duration) -> SocketAddr {
+    let server = HttpServer::bind(manager, "127.0.0.1:0", TOKEN)
+        .unwrap()
+        .workers(2)
+        .timeout(timeout);
+    let addr = server.local_addr().unwrap();
+    std::thread::spawn(move || server.serve());
+    addr
+}
+
+fn send(addr: SocketAddr, request: &str) -> String {
+    let mut stream = TcpStream::connect(addr).unwrap();
+    stream.write_all(request.as_bytes()).unwrap();
+    let mut response = String::new();
+    stream.read_to_string(&mut response).unwrap();
+    response
+}
+
+mod http_tests {
+    use super::*;
+
+    #[test]
+    fn test_student_crud_routes() {
+        let _temp_dir = setup();
+        let manager = QmxManager::builder().auto_save(false).build().unwrap();
+
+        let (status, body) = call(
+            &manager,
+            "POST",
+            "/students",
+            json!({ "name": "张三", "age": 16, "class": "TenTry" }),
+        );
+        assert_eq!(status, 201);
+        let uid = body["uid"].as_u64().unwrap();
+        call(&manager, "POST", "/students", json!({ "name": "李四" }));
+
+        let (status, body) = call(&manager, "GET", &format!("/students/{}", uid), Value::Null);
+        assert_eq!(status, 200);
+        assert_eq!(body["name"], "张三");
+        assert_eq!(body["lesson_left"], 10);
+
+        // 查询参数需要 URL 解码
+        let (status, body) = call(
+            &manager,
+            "GET",
+            "/students?name=%E5%BC%A0&limit=10",
+            Value::Null,
+        );
+        assert_eq!(status, 200);
+        assert_eq!(body["total"], 1);
+        assert_eq!(body["items"][0]["uid"], uid);
+        let (_, body) = call(&manager, "GET", "/students?class=Month", Value::Null);
+        assert_eq!(body["total"], 0);
+
+        let (status, body) = call(
+            &manager,
+            "PATCH",
+            &format!("/students/{}", uid),
+            json!({ "age": 17, "rings": [9.5] }),
+        );
+        assert_eq!(status, 200);
+        assert_eq!(body.as_array().unwrap().len(), 2);
+
+        let (status, _) = call(
+            &manager,
+            "DELETE",
+            &format!("/students/{}", uid),
+            Value::Null,
+        );
+        assert_eq!(status, 200);
+        let (status, body) = call(&manager, "GET", &format!("/students/{}", uid), Value::Null);
+        assert_eq!(status, 404);
+        assert_eq!(body["error"], "not_found");
+    }
+
+    #[test]
+    fn test_cash_stats_and_errors() {
+        let _temp_dir = setup();
+        let manager = QmxManager::builder().auto_save(false).build().unwrap();
+
+        let (status, body) = call(&manager, "POST", "/cash", json!({ "amount": 1500 }));
+        assert_eq!(status, 201);
+        let uid = body["uid"].as_u64().unwrap();
+        let (_, body) = call(&manager, "GET", &format!("/cash/{}", uid), Value::Null);
+        assert_eq!(body["cash"], 1500);
+        let (status, body) = call(&manager, "GET", "/stats/dashboard", Value::Null);
+        assert_eq!(status, 200);
+        assert_eq!(body["total_revenue"], 1500);
+
+        let (status, body) = call(&manager, "POST", "/cash", json!({ "amount": 0 }));
+        assert_eq!(status, 400);
+        assert_eq!(body["error"], "invalid_input");
+        let (status, _) = call(&manager, "POST", "/students", json!({ "age": 3 }));
+        assert_eq!(status, 400);
+        let (status, _) = call(&manager, "GET", "/students/abc", Value::Null);
+        assert_eq!(status, 400);
+        let (status, _) = call(&manager, "DELETE", "/cash", Value::Null);
+        assert_eq!(status, 405);
+        let (status, _) = call(&manager, "GET", "/unknown", Value::Null);
+        assert_eq!(status, 404);
+    }
+
+    #[test]
+    fn test_delete_student_policy() {
+        let _temp_dir = setup();
+        let manager = QmxManager::builder().auto_save(false).build().unwrap();
+        let (_, body) = call(&manager, "POST", "/students", json!({ "name": "张三" }));
+        let uid = body["uid"].as_u64().unwrap();
+        call(&manager, "POST", "/cash", json!({ "amount": 800, "student_id": uid }));
+
+        let target = format!("/students/{}", uid);
+        let (status, body) = call(&manager, "DELETE", &target, Value::Null);
+        assert_eq!(status, 409);
+        assert_eq!(body["error"], "state");
+        let (status, _) = call(&manager, "DELETE", &format!("{}?policy=Purge", target), Value::Null);
+        assert_eq!(status, 400);
+        let (status, _) = call(&manager, "DELETE", &format!("{}?policy=Detach", target), Value::Null);
+        assert_eq!(status, 200);
+        assert!(manager.get_student_cash(uid).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_server_over_tcp() {
+        let _temp_dir = setup();
+        let manager = Arc::new(QmxManager::builder().auto_save(false).build().unwrap());
+        let addr = start_server(manager.clone(), Duration::from_secs(10));
+
+        let body = r#"{"name":"网络学生"}"#;
+        let response = send(
+            addr,
+            &format!(
+                "POST /students HTTP/1.1\r\nHost: localhost\r\nAuthorization: Bearer {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\r\n{}",
+                TOKEN,
+                body.len(),
+                body
+            ),
+        );
+
+        assert!(response.starts_with("HTTP/1.1 201 Created\r\n"));
+        let (_, json) = response.split_once("\r\n\r\n").unwrap();
+        let uid = serde_json::from_str::<Value>(json).unwrap()["uid"]
+            .as_u64()
+            .unwrap();
+        assert_eq!(
+            manager.get_student(uid).unwrap().unwrap().name(),
+            Some("网络学生")
+        );
+    }
+
+    #[test]
+    fn test_server_requires_token() {
+        let _temp_dir = setup();
+        let manager = Arc::new(QmxManager::builder().auto_save(false).build().unwrap());
+        assert!(HttpServer::bind(manager.clone(), "127.0.0.1:0", "").is_err());
+        let addr = start_server(manager, Duration::from_secs(10));
+
+        let response = send(addr, "GET /students HTTP/1.1\r\nHost: localhost\r\n\r\n");
+        assert!(response.starts_with("HTTP/1.1 401 Unauthorized\r\n"));
+        let response = send(
+            addr,
+            "GET /students HTTP/1.1\r\nAuthorization: Bearer wrong-token\r\n\r\n",
+        );
+        assert!(response.starts_with("HTTP/1.1 401 Unauthorized\r\n"));
+        let response = send(
+            addr,
+            &format!(
+                "GET /students HTTP/1.1\r\nAuthorization: Bearer {}\r\n\r\n",
+                TOKEN
+            ),
+        );
+        assert!(response.starts_with("HTTP/1.1 200 OK\r\n"));
+    }
+
+    #[test]
+    fn test_server_limits_headers_and_slow_clients() {
+        let _temp_dir = setup();
+        let manager = Arc::new(QmxManager::builder().auto_save(false).build().unwrap());
+        let addr = start_server(manager, Duration::from_millis(200));
+
+        // 超长的请求头被拒绝，而不是无限读取
+        let response = send(
+            addr,
+            &format!(
+                "GET /students HTTP/1.1\r\nX-Padding: {}\r\n\r\n",
+                "a".repeat(10_000)
+            ),
+        );
+        assert!(response.starts_with("HTTP/1.1 400 Bad Request\r\n"));
+
+        // 不发送请求的连接在超时后被关闭，不会一直占用工作线程
+        let idle: Vec<TcpStream> = (0..2).map(|_| TcpStream::connect(addr).unwrap()).collect();
+        for mut stream in idle {
+            let mut response = String::new();
+            stream.read_to_string(&mut response).unwrap();
+            assert!(response.starts_with("HTTP/1.1 400 Bad Request\r\n"));
+        }
+        let response = send(
+            addr,
+            &format!(
+                "GET /students HTTP/1.1\r\nAuthorization: Bearer {}\r\n\r\n",
+                TOKEN
+            ),
+        );
+        assert!(response.starts_with("HTTP/1.1 200 OK\r\n"));
+    }
+}
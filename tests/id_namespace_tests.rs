@@ -0,0 +1,162 @@
+// 测试确定性、分命名空间的 ID 生成
+use chrono::{Duration, TimeZone, Utc};
+use qmx_backend_lib::id::{
+    IdGenerator, IdNamespace, TimeOrderedIds, compose_id, namespace_of, node_of, sequence_of,
+    timestamp_of,
+};
+use qmx_backend_lib::{CashBuilder, FixedClock, IdStrategy, QmxManager, StudentBuilder};
+use std::sync::Arc;
+use tempfile::TempDir;
+
+fn setup() -> TempDir {
+    let temp_dir = TempDir::new().unwrap();
+    std::env::set_current_dir(temp_dir.path()).unwrap();
+    let _ = std::fs::create_dir_all("data");
+    temp_dir
+}
+
+mod id_namespace_tests {
+    use super::*;
+
+    #[test]
+    fn test_compose_and_split_ids() {
+        let uid = compose_id(7, 42);
+        assert_eq!(namespace_of(uid), 7);
+        assert_eq!(sequence_of(uid), 42);
+        assert_ne!(compose_id(1, 1), compose_id(2, 1));
+    }
+
+    #[test]
+    fn test_namespace_sequences_are_independent() {
+        let ids = IdNamespace::new(3);
+        assert_eq!(ids.next_student_uid(), compose_id(3, 1));
+        assert_eq!(ids.next_student_uid(), compose_id(3, 2));
+        assert_eq!(ids.next_cash_uid(), compose_id(3, 1));
+
+        let resumed = IdNamespace::new(3);
+        resumed.observe_existing([compose_id(3, 9), compose_id(4, 100)], []);
+        assert_eq!(resumed.next_student_uid(), compose_id(3, 10));
+        assert_eq!(resumed.next_cash_uid(), compose_id(3, 1));
+    }
+
+    #[test]
+    fn test_manager_deterministic_ids() {
+        let _temp_dir = setup();
+
+        let manager = QmxManager::builder()
+            .auto_save(false)
+            .build()
+            .unwrap()
+            .with_id_namespace(5)
+            .unwrap();
+        let first = manager.create_student(StudentBuilder::new("甲")).unwrap();
+        let second = manager.create_student(StudentBuilder::new("乙")).unwrap();
+        let cash = manager
+            .record_cash(CashBuilder::new(100).student_id(first))
+            .unwrap();
+        assert_eq!(first, compose_id(5, 1));
+        assert_eq!(second, compose_id(5, 2));
+        assert_eq!(cash, compose_id(5, 1));
+        manager.save().unwrap();
+
+        // 重新加载后继续已有序列，不会重复
+        let reloaded = QmxManager::builder()
+            .auto_save(false)
+            .build()
+            .unwrap()
+            .with_id_namespace(5)
+            .unwrap();
+        let third = reloaded.create_student(StudentBuilder::new("丙")).unwrap();
+        assert_eq!(third, compose_id(5, 3));
+
+        // 其他命名空间从自己的序列开始
+        let other = QmxManager::builder()
+            .auto_save(false)
+            .build()
+            .unwrap()
+            .with_id_namespace(6)
+            .unwrap();
+        assert_eq!(
+            other.create_student(StudentBuilder::new("丁")).unwrap(),
+            compose_id(6, 1)
+        );
+    }
+
+    #[test]
+    fn test_time_ordered_ids() {
+        let start = Utc.with_ymd_and_hms(2025, 3, 1, 8, 0, 0).unwrap();
+        let clock = Arc::new(FixedClock::new(start));
+        let a = TimeOrderedIds::new(1, clock.clone()).unwrap();
+        let b = TimeOrderedIds::new(2, clock.clone()).unwrap();
+        assert!(TimeOrderedIds::new(1024, clock.clone()).is_none());
+
+        // 同一毫秒内按序号递增，不同节点互不冲突
+        let first = a.next_student_uid();
+        let second = a.next_cash_uid();
+        let other = b.next_student_uid();
+        assert!(second > first);
+        assert_ne!(first, other);
+        assert_eq!(node_of(first), 1);
+        assert_eq!(node_of(other), 2);
+        assert_eq!(timestamp_of(first), start);
+
+        clock.advance(Duration::seconds(1));
+        let later = a.next_student_uid();
+        assert!(later > second);
+        assert_eq!(timestamp_of(later), start + Duration::seconds(1));
+
+        // 时钟回拨时继续递增
+        clock.set(start);
+        assert!(a.next_student_uid() > later);
+
+        // 重新创建的序列跳过已有 UID
+        let resumed = TimeOrderedIds::new(1, clock).unwrap();
+        resumed.observe_existing(&mut [later].into_iter(), &mut std::iter::empty());
+        assert!(resumed.next_student_uid() > later);
+    }
+
+    #[test]
+    fn test_manager_id_strategy() {
+        let _temp_dir = setup();
+        let clock = Arc::new(FixedClock::new(Utc::now()));
+
+        let manager = QmxManager::builder()
+            .auto_save(false)
+            .build()
+            .unwrap()
+            .with_clock(clock)
+            .with_id_strategy(IdStrategy::TimeOrdered { node: 7 })
+            .unwrap();
+        let student = manager.create_student(StudentBuilder::new("甲")).unwrap();
+        let cash = manager
+            .record_cash(CashBuilder::new(100).student_id(student))
+            .unwrap();
+        assert_eq!(node_of(student), 7);
+        assert_eq!(node_of(cash), 7);
+        assert!(cash > student);
+        assert_eq!(
+            manager.get_student(student).unwrap().unwrap().uid(),
+            student
+        );
+
+        assert!(
+            QmxManager::builder()
+                .auto_save(false)
+                .build()
+                .unwrap()
+                .with_id_strategy(IdStrategy::TimeOrdered { node: 5000 })
+                .is_err()
+        );
+
+        let counter = QmxManager::builder()
+            .auto_save(false)
+            .build()
+            .unwrap()
+            .with_id_namespace(9)
+            .unwrap()
+            .with_id_strategy(IdStrategy::Counter)
+            .unwrap();
+        let uid = counter.create_student(StudentBuilder::new("乙")).unwrap();
+        assert_ne!(namespace_of(uid), 9);
+    }
+}
@@ -0,0 +1,141 @@
+// 测试标记分期已付款及自动生成下一期
+use chrono::{Duration, TimeZone, Utc};
+use qmx_backend_lib::cash::{Cash, InstallmentStatus, PaymentFrequency, PaymentMethod};
+use qmx_backend_lib::{
+    AutoSave, CashBuilder, CashQuery, Error, Event, EventKind, InstallmentPlanBuilder, QmxManager,
+    StudentBuilder,
+};
+use std::sync::{Arc, Mutex};
+use tempfile::TempDir;
+
+fn manager(temp_dir: &TempDir) -> QmxManager {
+    QmxManager::builder()
+        .data_dir(temp_dir.path())
+        .auto_save(AutoSave::Off)
+        .build()
+        .unwrap()
+}
+
+mod installment_payment_tests {
+    use super::*;
+
+    #[test]
+    fn test_mark_paid_stamps_date_and_method() {
+        let temp_dir = TempDir::new().unwrap();
+        let manager = manager(&temp_dir);
+        let plan = manager
+            .create_installment_plan(InstallmentPlanBuilder::new(
+                900,
+                3,
+                PaymentFrequency::Monthly,
+                Utc::now() + Duration::days(1),
+            ))
+            .unwrap();
+        let paid = Arc::new(Mutex::new(Vec::new()));
+        let seen = paid.clone();
+        manager.subscribe(EventKind::InstallmentPaid, move |event| {
+            seen.lock().unwrap().push(event.clone());
+        });
+
+        let paid_at = Utc.with_ymd_and_hms(2025, 3, 1, 10, 0, 0).unwrap();
+        let uid = plan.cash_uids[0];
+        let next = manager
+            .mark_installment_paid(uid, paid_at, PaymentMethod::Alipay)
+            .unwrap();
+        assert_eq!(next, None);
+
+        let cash = manager.get_cash(uid).unwrap().unwrap();
+        let installment = cash.installment.unwrap();
+        assert_eq!(installment.status, InstallmentStatus::Paid);
+        assert_eq!(installment.paid_at, Some(paid_at));
+        assert_eq!(cash.payment_method, Some(PaymentMethod::Alipay));
+        assert_eq!(*paid.lock().unwrap(), vec![Event::InstallmentPaid { uid }]);
+
+        // 已付款的分期和普通现金记录不能再标记
+        assert!(matches!(
+            manager.mark_installment_paid(uid, paid_at, PaymentMethod::Cash),
+            Err(Error::State(_))
+        ));
+        let plain = manager.record_cash(CashBuilder::new(100)).unwrap();
+        assert!(matches!(
+            manager.mark_installment_paid(plain, paid_at, PaymentMethod::Cash),
+            Err(Error::State(_))
+        ));
+        assert!(matches!(
+            manager.mark_installment_paid(9_999_999, paid_at, PaymentMethod::Cash),
+            Err(Error::NotFound(_))
+        ));
+
+        // 撤销普通记录和付款标记后恢复为待付款
+        assert_eq!(manager.undo_last(2).unwrap(), 2);
+        let installment = manager.get_cash(uid).unwrap().unwrap().installment.unwrap();
+        assert_eq!(installment.status, InstallmentStatus::Pending);
+        assert_eq!(installment.paid_at, None);
+    }
+
+    #[test]
+    fn test_auto_generates_next_period() {
+        let temp_dir = TempDir::new().unwrap();
+        let manager = manager(&temp_dir).with_auto_next_installment(true);
+        let student = manager.create_student(StudentBuilder::new("张三")).unwrap();
+        let due = Utc.with_ymd_and_hms(2025, 1, 31, 0, 0, 0).unwrap();
+        // 只录入第一期，计划条款随记录一起保存
+        let first = Cash::new_installment(
+            Some(student),
+            600,
+            2,
+            PaymentFrequency::Monthly,
+            due,
+            1,
+            Some(4242),
+        );
+        let first = manager
+            .record_cash(
+                CashBuilder::new(300)
+                    .student_id(student)
+                    .installment(first.installment.unwrap()),
+            )
+            .unwrap();
+        assert_eq!(
+            manager.get_installment_plan(4242).unwrap().unwrap().total_installments,
+            2
+        );
+
+        let second = manager
+            .mark_installment_paid(first, due, PaymentMethod::WeChat)
+            .unwrap()
+            .unwrap();
+        let cash = manager.get_cash(second).unwrap().unwrap();
+        assert_eq!(cash.student_id, Some(student));
+        assert_eq!(cash.cash.amount_minor, 300);
+        let installment = cash.installment.unwrap();
+        assert_eq!(installment.plan_id, 4242);
+        assert_eq!(installment.current_installment, 2);
+        assert_eq!(installment.status, InstallmentStatus::Pending);
+        assert_eq!(
+            installment.due_date,
+            Utc.with_ymd_and_hms(2025, 2, 28, 0, 0, 0).unwrap()
+        );
+
+        // 最后一期付清后不再生成
+        assert_eq!(
+            manager
+                .mark_installment_paid(second, due, PaymentMethod::WeChat)
+                .unwrap(),
+            None
+        );
+        assert_eq!(manager.get_student_cash(student).unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_plan_requires_existing_student() {
+        let temp_dir = TempDir::new().unwrap();
+        let manager = manager(&temp_dir);
+        let due = Utc.with_ymd_and_hms(2024, 3, 1, 0, 0, 0).unwrap();
+        let result = manager.create_installment_plan(
+            InstallmentPlanBuilder::new(3000, 3, PaymentFrequency::Monthly, due).student_id(999),
+        );
+        assert!(matches!(result, Err(Error::NotFound(_))));
+        assert_eq!(manager.count_cash(CashQuery::new()).unwrap(), 0);
+    }
+}
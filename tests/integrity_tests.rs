@@ -0,0 +1,94 @@
+// 测试数据一致性检查
+use chrono::Utc;
+use qmx_backend_lib::cash::{Cash, CashDatabase, PaymentFrequency};
+use qmx_backend_lib::database::Database;
+use qmx_backend_lib::integrity::{Counter, OrphanedCash, PlanIssue, check};
+use qmx_backend_lib::student::{Student, StudentDatabase};
+use qmx_backend_lib::{AutoSave, CashBuilder, QmxManager, StudentBuilder};
+use tempfile::TempDir;
+
+fn installment(student_id: u64, plan_id: u64, period: u32) -> Cash {
+    Cash::new_installment(
+        Some(student_id),
+        3000,
+        3,
+        PaymentFrequency::Monthly,
+        Utc::now(),
+        period,
+        Some(plan_id),
+    )
+}
+
+mod integrity_tests {
+    use super::*;
+
+    #[test]
+    fn test_clean_database() {
+        let temp_dir = TempDir::new().unwrap();
+        let manager = QmxManager::builder()
+            .data_dir(temp_dir.path())
+            .auto_save(AutoSave::Off)
+            .build()
+            .unwrap();
+        let uid = manager.create_student(StudentBuilder::new("张三")).unwrap();
+        manager
+            .record_cash(CashBuilder::new(1000).student_id(uid))
+            .unwrap();
+
+        let report = manager.verify_integrity().unwrap();
+        assert!(report.is_ok(), "{:?}", report);
+    }
+
+    #[test]
+    fn test_reports_inconsistencies() {
+        let student = Student::new();
+        let student_uid = student.uid();
+        let mut students = StudentDatabase::new();
+        students.insert(student);
+
+        let mut cash = CashDatabase::new();
+        let orphan = Cash::new(Some(u64::MAX));
+        let orphan_uid = orphan.uid;
+        cash.insert(orphan);
+        // 第 2 期缺失，第 3 期重复；计划 ID 超出计数器
+        let plan_id = u64::MAX - 1;
+        cash.insert(installment(student_uid, plan_id, 1));
+        cash.insert(installment(student_uid, plan_id, 3));
+        cash.insert(installment(student_uid, plan_id, 3));
+
+        let report = check(&Database::new(students, cash), true);
+        assert_eq!(
+            report.orphaned_cash,
+            vec![OrphanedCash {
+                cash_uid: orphan_uid,
+                student_id: u64::MAX,
+            }]
+        );
+        assert_eq!(
+            report.installment_plans,
+            vec![PlanIssue {
+                plan_id,
+                missing: vec![2],
+                duplicated: vec![3],
+            }]
+        );
+        assert_eq!(report.counters.len(), 1);
+        assert_eq!(report.counters[0].counter, Counter::InstallmentPlan);
+        assert_eq!(report.counters[0].max_uid, plan_id);
+        assert_eq!(report.issue_count(), 3);
+    }
+
+    #[test]
+    fn test_uid_counter_behind_data() {
+        let mut students = StudentDatabase::new();
+        students.insert(Student::new_with_uid(u64::MAX - 1));
+
+        let report = check(&Database::new(students.clone(), CashDatabase::new()), true);
+        assert_eq!(report.counters.len(), 1);
+        assert_eq!(report.counters[0].counter, Counter::Student);
+        assert_eq!(report.counters[0].max_uid, u64::MAX - 1);
+
+        // 自定义 ID 策略下不检查学生和现金计数器
+        assert!(check(&Database::new(students, CashDatabase::new()), false).is_ok());
+    }
+}
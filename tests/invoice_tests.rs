@@ -0,0 +1,86 @@
+// 测试收据生成与编号持久化
+use qmx_backend_lib::{CashBuilder, InstitutionHeader, QmxManager, Receipt, StudentBuilder};
+use tempfile::TempDir;
+
+fn setup() -> TempDir {
+    let temp_dir = TempDir::new().unwrap();
+    std::env::set_current_dir(temp_dir.path()).unwrap();
+    let _ = std::fs::create_dir_all("data");
+    temp_dir
+}
+
+mod invoice_tests {
+    use super::*;
+
+    #[test]
+    fn test_receipt_generation_and_rendering() {
+        let _temp_dir = setup();
+        let manager = QmxManager::builder().auto_save(false).build().unwrap();
+        let header = InstitutionHeader::new("启明星射击俱乐部")
+            .address("幸福路 1 号")
+            .phone("010-12345678");
+
+        let student = manager
+            .create_student(StudentBuilder::new("<张三>"))
+            .unwrap();
+        let cash = manager
+            .record_cash(CashBuilder::new(123456).student_id(student).note("学费"))
+            .unwrap();
+
+        let first = manager.generate_receipt(cash, &header).unwrap();
+        let second = manager.generate_receipt(cash, &header).unwrap();
+        assert_eq!(second.number, first.number + 1);
+        assert_eq!(manager.next_receipt_number(), second.number + 1);
+
+        assert_eq!(first.student_name.as_deref(), Some("<张三>"));
+        assert_eq!(first.formatted_amount(), "1234.56");
+
+        let text = first.render_text();
+        assert!(text.starts_with("启明星射击俱乐部\n"));
+        assert!(text.contains("金额: 1234.56 元"));
+        assert!(text.contains(&first.formatted_number()));
+
+        let html = first.render_html();
+        assert!(html.contains("&lt;张三&gt;"));
+        assert!(!html.contains("<张三>"));
+
+        let json = serde_json::to_string(&first).unwrap();
+        let parsed: Receipt = serde_json::from_str(&json).unwrap();
+        assert_eq!(parsed, first);
+
+        assert_eq!(
+            manager
+                .generate_receipt(u64::MAX, &header)
+                .unwrap_err()
+                .code(),
+            "not_found"
+        );
+    }
+
+    #[test]
+    fn test_receipt_numbers_belong_to_data_dir() {
+        let temp_dir = TempDir::new().unwrap();
+        let open = |name: &str| {
+            QmxManager::builder()
+                .data_dir(temp_dir.path().join(name))
+                .auto_save(false)
+                .build()
+                .unwrap()
+        };
+        let header = InstitutionHeader::new("启明星射击俱乐部");
+        let main = open("main");
+        let cash = main.record_cash(CashBuilder::new(100)).unwrap();
+        assert_eq!(main.generate_receipt(cash, &header).unwrap().number, 1);
+        assert_eq!(main.generate_receipt(cash, &header).unwrap().number, 2);
+
+        // 其他数据目录的管理器从 1 开始编号
+        let branch = open("branch");
+        let cash = branch.record_cash(CashBuilder::new(100)).unwrap();
+        assert_eq!(branch.generate_receipt(cash, &header).unwrap().number, 1);
+
+        let counter = temp_dir.path().join("main/receipt_counter");
+        assert_eq!(std::fs::read_to_string(&counter).unwrap(), "3");
+        drop(main);
+        assert_eq!(open("main").next_receipt_number(), 3);
+    }
+}
@@ -0,0 +1,119 @@
+// 测试大型数据文件的流式加载与延迟加载
+use qmx_backend_lib::cash::{Cash, CashDatabase};
+use qmx_backend_lib::student::{Student, StudentDatabase};
+use tempfile::TempDir;
+
+fn student_file(dir: &TempDir) -> (String, Vec<u64>) {
+    let path = dir.path().join("student_database.json");
+    let path = path.to_str().unwrap().to_string();
+    let mut db = StudentDatabase::new();
+    let mut uids = Vec::new();
+    for i in 0..3 {
+        let mut student = Student::new();
+        student
+            .set_name(format!("学生{}", i))
+            .set_phone(format!("1380000000{}", i))
+            .set_rings(vec![9.0, 10.0, i as f64])
+            .unwrap()
+            .set_note(format!("备注{}", i));
+        uids.push(student.uid());
+        db.insert(student);
+    }
+    db.save_to(&path).unwrap();
+    (path, uids)
+}
+
+mod lazy_load_tests {
+    use super::*;
+
+    #[test]
+    fn test_streaming_read_matches_saved_data() {
+        let temp_dir = TempDir::new().unwrap();
+        let (path, uids) = student_file(&temp_dir);
+
+        let db = StudentDatabase::read_from(&path).unwrap();
+        assert!(!db.has_pending_details());
+        let student = db.get(&uids[2]).unwrap();
+        assert_eq!(student.rings(), [9.0, 10.0, 2.0]);
+        assert_eq!(student.note(), "备注2");
+
+        // 末尾多余的内容仍视为文件损坏
+        let mut bytes = std::fs::read(&path).unwrap();
+        bytes.extend_from_slice(b"{}");
+        std::fs::write(&path, bytes).unwrap();
+        assert!(StudentDatabase::read_from(&path).is_err());
+    }
+
+    #[test]
+    fn test_lazy_student_details() {
+        let temp_dir = TempDir::new().unwrap();
+        let (path, uids) = student_file(&temp_dir);
+
+        let mut db = StudentDatabase::read_lazy(&path).unwrap();
+        assert!(db.has_pending_details());
+        assert_eq!(db.len(), 3);
+        let student = db.get(&uids[0]).unwrap();
+        assert_eq!(student.name(), Some("学生0"));
+        assert!(student.rings().is_empty());
+        assert_eq!(student.note(), "");
+        // 索引字段照常可用
+        assert_eq!(db.find_by_phone("13800000001")[0].uid(), uids[1]);
+
+        assert_eq!(db.load_details(&[uids[1], u64::MAX]).unwrap(), 1);
+        assert_eq!(db.get(&uids[1]).unwrap().rings(), [9.0, 10.0, 1.0]);
+        assert_eq!(db.get(&uids[1]).unwrap().note(), "备注1");
+        assert!(db.get(&uids[0]).unwrap().rings().is_empty());
+        // 已加载的不重复读取
+        assert_eq!(db.load_details(&[uids[1]]).unwrap(), 0);
+
+        // 详细字段未加载完时不能保存，避免丢失成绩
+        let saved = temp_dir.path().join("saved.json");
+        assert!(db.save_to(saved.to_str().unwrap()).is_err());
+
+        db.remove(&uids[2]);
+        assert_eq!(db.load_all_details().unwrap(), 1);
+        assert!(!db.has_pending_details());
+        assert_eq!(db.get(&uids[0]).unwrap().note(), "备注0");
+        db.save_to(saved.to_str().unwrap()).unwrap();
+        let reloaded = StudentDatabase::read_from(saved.to_str().unwrap()).unwrap();
+        assert_eq!(reloaded.len(), 2);
+        assert_eq!(reloaded.get(&uids[0]).unwrap().rings(), [9.0, 10.0, 0.0]);
+    }
+
+    #[test]
+    fn test_lazy_cash_notes() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("cash_database.json");
+        let path = path.to_str().unwrap();
+        let mut db = CashDatabase::new();
+        let mut noted = Cash::new(Some(1));
+        noted.set_cash(1000);
+        noted.set_note(Some("学费".to_string()));
+        let noted_uid = noted.uid;
+        db.insert(noted);
+        db.insert(Cash::new(Some(2)));
+        db.save_to(path).unwrap();
+
+        let mut db = CashDatabase::read_lazy(path).unwrap();
+        let cash = db.get(&noted_uid).unwrap();
+        assert_eq!(cash.cash.amount_minor, 1000);
+        assert_eq!(cash.note, None);
+        assert_eq!(db.get_by_student(1).len(), 1);
+
+        assert_eq!(db.load_all_details().unwrap(), 2);
+        assert_eq!(db.get(&noted_uid).unwrap().note.as_deref(), Some("学费"));
+        db.save_to(path).unwrap();
+    }
+
+    #[test]
+    fn test_lazy_load_empty_database() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("student_database.json");
+        let path = path.to_str().unwrap();
+        StudentDatabase::new().save_to(path).unwrap();
+
+        let db = StudentDatabase::read_lazy(path).unwrap();
+        assert!(db.is_empty());
+        assert!(!db.has_pending_details());
+    }
+}
@@ -0,0 +1,81 @@
+// 测试数据库锁中毒后的恢复
+use chrono::{DateTime, Utc};
+use qmx_backend_lib::{CashBuilder, Clock, QmxManager, StudentBuilder};
+use std::panic::{AssertUnwindSafe, catch_unwind};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use tempfile::TempDir;
+
+fn setup() -> TempDir {
+    let temp_dir = TempDir::new().unwrap();
+    std::env::set_current_dir(temp_dir.path()).unwrap();
+    let _ = std::fs::create_dir_all("data");
+    temp_dir
+}
+
+/// 可以在持锁期间触发 panic 的时钟
+#[derive(Default)]
+struct PanickingClock {
+    armed: AtomicBool,
+}
+
+impl Clock for PanickingClock {
+    fn now(&self) -> DateTime<Utc> {
+        if self.armed.swap(false, Ordering::SeqCst) {
+            panic!("模拟持锁期间的 panic");
+        }
+        Utc::now()
+    }
+}
+
+mod lock_recovery_tests {
+    use super::*;
+
+    #[test]
+    fn test_recover_after_poisoned_lock() {
+        let _temp_dir = setup();
+        let clock = Arc::new(PanickingClock::default());
+        let manager = QmxManager::builder()
+            .auto_save(false)
+            .build()
+            .unwrap()
+            .with_clock(clock.clone());
+
+        let saved = manager
+            .create_student(StudentBuilder::new("已保存"))
+            .unwrap();
+        manager.save().unwrap();
+        let unsaved = manager
+            .create_student(StudentBuilder::new("未保存"))
+            .unwrap();
+
+        clock.armed.store(true, Ordering::SeqCst);
+        let result = catch_unwind(AssertUnwindSafe(|| {
+            let _ = manager.record_cash(CashBuilder::new(100));
+        }));
+        assert!(result.is_err());
+        assert!(manager.is_poisoned());
+
+        let err = manager.get_student(saved).unwrap_err();
+        assert_eq!(err.code(), "poison");
+
+        manager.recover().unwrap();
+        assert!(!manager.is_poisoned());
+        assert!(manager.get_student(saved).unwrap().is_some());
+        assert!(manager.get_student(unsaved).unwrap().is_none());
+
+        // 恢复后可以继续正常写入
+        manager.record_cash(CashBuilder::new(200)).unwrap();
+    }
+
+    #[test]
+    fn test_recover_without_poison_reloads() {
+        let _temp_dir = setup();
+        let manager = QmxManager::builder().auto_save(false).build().unwrap();
+        let uid = manager.create_student(StudentBuilder::new("临时")).unwrap();
+
+        assert!(!manager.is_poisoned());
+        manager.recover().unwrap();
+        assert!(manager.get_student(uid).unwrap().is_none());
+    }
+}
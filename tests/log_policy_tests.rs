@@ -0,0 +1,59 @@
+// 测试日志中个人数据的脱敏策略
+use qmx_backend_lib::{LogPolicy, log_policy, set_log_policy};
+use serde_json::json;
+
+mod log_policy_tests {
+    use super::*;
+
+    #[test]
+    fn test_full_policy_keeps_values() {
+        let policy = LogPolicy::Full;
+        assert_eq!(policy.name("张三"), "张三");
+        assert_eq!(policy.phone("13800138000"), "13800138000");
+        assert_eq!(policy.text("备注"), "备注");
+        assert!(policy.logs_values());
+    }
+
+    #[test]
+    fn test_redacted_policy_masks_personal_data() {
+        let policy = LogPolicy::Redacted;
+        assert_eq!(policy.name("张三丰"), "张**");
+        assert_eq!(policy.name("李"), "*");
+        assert_eq!(policy.phone("13800138000"), "*******8000");
+        assert_eq!(policy.phone("123"), "123");
+        assert_eq!(policy.text("家长要求周末上课"), "<8 字符>");
+
+        assert_eq!(policy.field_value("name", &json!("王五")), "王*");
+        assert_eq!(policy.field_value("phone", &json!("5551234")), "***1234");
+        assert_eq!(policy.field_value("age", &json!(18)), "18");
+        assert_eq!(policy.field_value("class", &json!("Month")), "\"Month\"");
+        assert_eq!(
+            policy.field_value(
+                "guardians",
+                &json!([{"name": "王父", "phone": "13900000000", "relation": "父亲"}])
+            ),
+            "<1 位监护人>"
+        );
+        assert_eq!(
+            policy.field_value("custom_fields", &json!({"校区": {"Text": "东区"}})),
+            "<1 个自定义字段>"
+        );
+    }
+
+    #[test]
+    fn test_minimal_policy_hides_everything() {
+        let policy = LogPolicy::Minimal;
+        assert_eq!(policy.name("张三"), "[已隐藏]");
+        assert_eq!(policy.phone("13800138000"), "[已隐藏]");
+        assert_eq!(policy.text("备注"), "[已隐藏]");
+        assert!(!policy.logs_values());
+    }
+
+    #[test]
+    fn test_global_policy_roundtrip() {
+        assert_eq!(log_policy(), LogPolicy::Full);
+        set_log_policy(LogPolicy::Redacted);
+        assert_eq!(log_policy(), LogPolicy::Redacted);
+        set_log_policy(LogPolicy::Full);
+    }
+}
@@ -0,0 +1,151 @@
+// 测试 QmxManagerBuilder 与配置结构
+use qmx_backend_lib::cash::CashDatabase;
+use qmx_backend_lib::student::{Class, ClassPolicy, ClassPolicyTable, StudentDatabase};
+use qmx_backend_lib::validation::{Rule, Validator};
+use qmx_backend_lib::{
+    AutoSave, CashBuilder, Limits, ManagerConfig, QmxManager, QmxManagerBuilder, StudentBuilder,
+};
+use tempfile::TempDir;
+
+fn setup() -> TempDir {
+    let temp_dir = TempDir::new().unwrap();
+    std::env::set_current_dir(temp_dir.path()).unwrap();
+    let _ = std::fs::create_dir_all("data");
+    temp_dir
+}
+
+mod manager_builder_tests {
+    use super::*;
+
+    #[test]
+    fn test_default_config() {
+        let config = ManagerConfig::default();
+        assert_eq!(config.auto_save, AutoSave::Immediate);
+        assert!(config.data_dir.is_none());
+        assert_eq!(config.limits, Limits::default());
+        assert!(config.validator.is_empty());
+        assert!(config.duplicate_guard.is_none());
+        assert_eq!(AutoSave::from(false), AutoSave::Off);
+    }
+
+    #[test]
+    fn test_data_dir_creates_and_persists() {
+        let _temp_dir = setup();
+        let dir = std::path::Path::new("branch/data");
+
+        let manager = QmxManager::builder()
+            .data_dir(dir)
+            .auto_save(AutoSave::Immediate)
+            .build()
+            .unwrap();
+        assert!(dir.join("student_database.json").exists());
+        assert!(dir.join("cash_database.json").exists());
+        let uid = manager.create_student(StudentBuilder::new("张三")).unwrap();
+
+        // 立即保存到数据目录，其他文件也放在该目录下
+        let saved =
+            StudentDatabase::read_from(&dir.join("student_database.json").to_string_lossy())
+                .unwrap();
+        assert!(saved.get(&uid).is_some());
+        assert!(dir.join("audit_log.json").exists());
+        assert!(!std::path::Path::new("data/student_database.json").exists());
+
+        let backup = manager.backup_now().unwrap();
+        assert!(backup.starts_with(dir.join("backups")));
+    }
+
+    #[test]
+    fn test_auto_save_off_and_config_options() {
+        let _temp_dir = setup();
+        let config = ManagerConfig {
+            data_dir: Some("office".into()),
+            auto_save: AutoSave::Off,
+            limits: Limits {
+                max_name_len: 4,
+                ..Limits::default()
+            },
+            validator: Validator::new().cash_rule(Rule::cash_note_max_len(2)),
+            ..ManagerConfig::default()
+        };
+        let manager = QmxManagerBuilder::from_config(config).build().unwrap();
+
+        assert!(
+            manager
+                .create_student(StudentBuilder::new("名字太长了啊"))
+                .is_err()
+        );
+        assert!(
+            manager
+                .record_cash(CashBuilder::new(100).note("备注太长"))
+                .is_err()
+        );
+        manager.record_cash(CashBuilder::new(100)).unwrap();
+        assert!(
+            CashDatabase::read_from("office/cash_database.json")
+                .unwrap()
+                .is_empty()
+        );
+        manager.save().unwrap();
+        assert_eq!(
+            CashDatabase::read_from("office/cash_database.json")
+                .unwrap()
+                .len(),
+            1
+        );
+    }
+
+    #[test]
+    fn test_database_files_must_exist() {
+        let _temp_dir = setup();
+        assert!(
+            QmxManager::builder()
+                .database_files("missing/student.json", "missing/cash.json")
+                .build()
+                .is_err()
+        );
+    }
+
+    #[test]
+    #[allow(deprecated)]
+    fn test_deprecated_constructors_still_work() {
+        let _temp_dir = setup();
+        let manager = QmxManager::new(false).unwrap();
+        let uid = manager
+            .create_student(StudentBuilder::new("旧接口"))
+            .unwrap();
+        manager.save().unwrap();
+
+        let reopened = QmxManager::from_path(
+            "./data/student_database.json",
+            "./data/cash_database.json",
+            false,
+        )
+        .unwrap();
+        assert!(reopened.get_student(uid).unwrap().is_some());
+    }
+
+    #[test]
+    fn test_class_policies_are_per_manager() {
+        let custom_dir = TempDir::new().unwrap();
+        let mut policies = ClassPolicyTable::new();
+        policies.set(Class::Month, ClassPolicy::lessons(8));
+        let custom = QmxManager::builder()
+            .data_dir(custom_dir.path())
+            .class_policies(policies)
+            .build()
+            .unwrap();
+        let default_dir = TempDir::new().unwrap();
+        let default = QmxManager::builder()
+            .data_dir(default_dir.path())
+            .build()
+            .unwrap();
+
+        let builder = || StudentBuilder::new("月卡学员").class(Class::Month);
+        let custom_uid = custom.create_student(builder()).unwrap();
+        let default_uid = default.create_student(builder()).unwrap();
+        let custom_student = custom.get_student(custom_uid).unwrap().unwrap();
+        let default_student = default.get_student(default_uid).unwrap().unwrap();
+        assert_eq!(custom_student.lesson_left(), Some(8));
+        assert_eq!(default_student.lesson_left(), None);
+    }
+}
@@ -0,0 +1,148 @@
+// 测试两个数据库的合并
+use chrono::Utc;
+use qmx_backend_lib::cash::{Cash, CashDatabase, PaymentFrequency};
+use qmx_backend_lib::database::Database;
+use qmx_backend_lib::manager::InstallmentPlanBuilder;
+use qmx_backend_lib::student::{Student, StudentDatabase};
+use qmx_backend_lib::{
+    AutoSave, CashBuilder, ConflictPolicy, QmxManager, StudentBuilder, StudentUpdater,
+};
+use tempfile::TempDir;
+
+fn manager(temp_dir: &TempDir) -> QmxManager {
+    QmxManager::builder()
+        .data_dir(temp_dir.path())
+        .auto_save(AutoSave::Off)
+        .build()
+        .unwrap()
+}
+
+fn student(uid: u64, name: &str, phone: Option<&str>) -> Student {
+    let mut student = Student::new_with_uid(uid);
+    student.set_name(name.to_string());
+    if let Some(phone) = phone {
+        student.set_phone(phone.to_string());
+    }
+    student
+}
+
+fn cash(uid: u64, student_id: u64, amount: i64) -> Cash {
+    let mut cash = Cash::new_with_uid(uid, Some(student_id));
+    cash.set_cash(amount);
+    cash
+}
+
+mod merge_tests {
+    use super::*;
+
+    #[test]
+    fn test_remaps_colliding_uids() {
+        let temp_dir = TempDir::new().unwrap();
+        let manager = manager(&temp_dir);
+        let zhang = manager
+            .create_student(StudentBuilder::new("张三").phone("13800000001"))
+            .unwrap();
+        let paid = manager
+            .record_cash(CashBuilder::new(1000).student_id(zhang))
+            .unwrap();
+
+        // 另一个校区的数据：李四与张三的 UID 相同，张三的 UID 不同但姓名电话相同
+        let mut students = StudentDatabase::new();
+        students.insert(student(zhang, "李四", None));
+        students.insert(student(zhang + 1000, "张三", Some("138-0000-0001")));
+        let mut other_cash = CashDatabase::new();
+        other_cash.insert(cash(paid, zhang, 500));
+        let mut refund = cash(paid + 1000, zhang, -500);
+        refund.refund_of = Some(paid);
+        other_cash.insert(refund);
+        other_cash.insert(cash(paid + 1001, zhang + 1000, 800));
+
+        let report = manager
+            .merge_from(
+                Database::new(students, other_cash),
+                ConflictPolicy::KeepExisting,
+            )
+            .unwrap();
+        assert_eq!(report.students.added, 1);
+        assert_eq!(report.students.remapped, 1);
+        assert_eq!(report.students.matched, 1);
+        assert_eq!(report.cash.added, 3);
+        assert_eq!(report.cash.remapped, 1);
+
+        let li = report.student_uids[&zhang];
+        assert_eq!(report.student_uids[&(zhang + 1000)], zhang);
+        assert_eq!(manager.get_student(li).unwrap().unwrap().name(), Some("李四"));
+        assert_eq!(manager.get_student(zhang).unwrap().unwrap().name(), Some("张三"));
+
+        // 外键随 UID 一起改写
+        let moved = report.cash_uids[&paid];
+        assert_eq!(manager.get_cash(moved).unwrap().unwrap().student_id, Some(li));
+        let refund = manager.get_cash(paid + 1000).unwrap().unwrap();
+        assert_eq!(refund.student_id, Some(li));
+        assert_eq!(refund.refund_of, Some(moved));
+        let matched = manager.get_cash(paid + 1001).unwrap().unwrap();
+        assert_eq!(matched.student_id, Some(zhang));
+        assert_eq!(manager.get_cash(paid).unwrap().unwrap().cash.amount_minor, 1000);
+
+        // 合并算作一次操作
+        assert_eq!(manager.undo_last(1).unwrap(), 1);
+        assert!(manager.get_student(li).unwrap().is_none());
+        assert!(manager.get_cash(moved).unwrap().is_none());
+        assert!(manager.get_cash(paid).unwrap().is_some());
+    }
+
+    #[test]
+    fn test_conflict_policy_and_plans() {
+        let temp_dir = TempDir::new().unwrap();
+        let manager = manager(&temp_dir);
+        let uid = manager.create_student(StudentBuilder::new("王五")).unwrap();
+        let plan = manager
+            .create_installment_plan(
+                InstallmentPlanBuilder::new(300, 3, PaymentFrequency::Monthly, manager.now())
+                    .student_id(uid),
+            )
+            .unwrap();
+
+        let mut incoming = manager.get_student(uid).unwrap().unwrap();
+        incoming.set_age(Some(12));
+        let mut students = StudentDatabase::new();
+        students.insert(incoming);
+        let mut other_cash = CashDatabase::new();
+        let installment = Cash::new_installment(
+            Some(uid),
+            600,
+            2,
+            PaymentFrequency::Monthly,
+            Utc::now(),
+            1,
+            Some(plan.plan_id),
+        );
+        let installment_uid = installment.uid;
+        other_cash.insert(installment);
+        let other = Database::new(students, other_cash);
+
+        let report = manager
+            .merge_from(other.clone(), ConflictPolicy::KeepExisting)
+            .unwrap();
+        assert_eq!(report.conflicts.len(), 1);
+        assert!(!report.conflicts[0].took_incoming);
+        assert_eq!(manager.get_student(uid).unwrap().unwrap().age(), None);
+        // 计划 ID 已被占用，重新分配
+        let new_plan = report.plan_ids[&plan.plan_id];
+        assert_ne!(new_plan, plan.plan_id);
+        let merged = manager.get_cash(installment_uid).unwrap().unwrap();
+        assert_eq!(merged.installment_plan_id(), Some(new_plan));
+
+        manager
+            .update_student(uid, StudentUpdater::new().age(Some(10)))
+            .unwrap();
+        let report = manager
+            .merge_from(other, ConflictPolicy::PreferIncoming)
+            .unwrap();
+        assert_eq!(report.students.updated, 1);
+        assert_eq!(manager.get_student(uid).unwrap().unwrap().age(), Some(12));
+        // 已合并过的分期记录识别为同一条，沿用之前分配的计划
+        assert_eq!(report.cash.matched, 1);
+        assert_eq!(report.plan_ids[&plan.plan_id], new_plan);
+    }
+}
@@ -0,0 +1,127 @@
+// 测试金额类型的运算、格式化与序列化兼容
+use chrono::Utc;
+use qmx_backend_lib::cash::{Cash, PaymentFrequency};
+use qmx_backend_lib::{
+    AutoSave, CashBuilder, CashQuery, CashSortKey, Currency, InstallmentPlanBuilder, Money,
+    QmxManager, SortOrder, TimePeriod,
+};
+use serde_json::json;
+use tempfile::TempDir;
+
+mod money_tests {
+    use super::*;
+
+    #[test]
+    fn test_display_and_plain_format() {
+        assert_eq!(Money::cny(123450).to_string(), "¥1,234.50");
+        assert_eq!(Money::cny(-5).to_string(), "-¥0.05");
+        assert_eq!(Money::cny(100_000_000).to_string(), "¥1,000,000.00");
+        assert_eq!(Money::new(300, Currency::Usd).to_string(), "$3.00");
+        assert_eq!(Money::cny(-1205).to_plain_string(), "-12.05");
+        assert_eq!(Money::cny(i64::MIN).to_plain_string(), "-92233720368547758.08");
+    }
+
+    #[test]
+    fn test_arithmetic_checks_currency() {
+        let a = Money::cny(1000);
+        assert_eq!(a.checked_add(Money::cny(250)), Some(Money::cny(1250)));
+        assert_eq!(a.checked_sub(Money::cny(1500)), Some(Money::cny(-500)));
+        assert_eq!(a.checked_add(Money::new(1, Currency::Hkd)), None);
+        assert_eq!(Money::cny(i64::MAX).checked_add(Money::cny(1)), None);
+        assert_eq!(-a, Money::cny(-1000));
+        assert_eq!(Money::cny(i64::MIN).saturating_abs(), Money::cny(i64::MAX));
+        assert!(Money::zero(Currency::Eur).is_zero());
+        assert_eq!(Money::from(42), Money::cny(42));
+    }
+
+    #[test]
+    fn test_serde_is_backward_compatible() {
+        // 人民币仍保存为整数（分）
+        assert_eq!(serde_json::to_value(Money::cny(1500)).unwrap(), json!(1500));
+        let usd = Money::new(1999, Currency::Usd);
+        let value = serde_json::to_value(usd).unwrap();
+        assert_eq!(value, json!({ "amount_minor": 1999, "currency": "USD" }));
+        assert_eq!(serde_json::from_value::<Money>(value).unwrap(), usd);
+        assert_eq!(
+            serde_json::from_value::<Money>(json!({ "amount_minor": 7 })).unwrap(),
+            Money::cny(7)
+        );
+
+        // 旧版本数据文件中的整数金额按分读取
+        let mut cash = Cash::new(None);
+        cash.set_cash(usd);
+        let mut value = serde_json::to_value(&cash).unwrap();
+        value["cash"] = json!(-800);
+        let legacy: Cash = serde_json::from_value(value).unwrap();
+        assert_eq!(legacy.cash, Money::cny(-800));
+    }
+
+    #[test]
+    fn test_common_currency_ignores_zero() {
+        assert_eq!(Currency::common([]).unwrap(), Currency::Cny);
+        let amounts = [Money::zero(Currency::Cny), Money::new(5, Currency::Eur)];
+        assert_eq!(Currency::common(amounts).unwrap(), Currency::Eur);
+        let err = Currency::common([Money::cny(1), Money::new(1, Currency::Usd)]).unwrap_err();
+        assert_eq!(err.code(), "currency_mismatch");
+    }
+
+    #[test]
+    fn test_manager_does_not_mix_currencies() {
+        let temp_dir = TempDir::new().unwrap();
+        let manager = QmxManager::builder()
+            .data_dir(temp_dir.path())
+            .auto_save(AutoSave::Off)
+            .build()
+            .unwrap();
+        let cny = manager.record_cash(CashBuilder::new(1500)).unwrap();
+        let usd = manager
+            .record_cash(CashBuilder::new(Money::new(1000, Currency::Usd)))
+            .unwrap();
+
+        // 统计遇到多种币种时报错，而不是把金额直接相加
+        let err = manager.get_financial_stats(TimePeriod::ThisYear).unwrap_err();
+        assert_eq!(err.code(), "currency_mismatch");
+        assert!(manager.get_dashboard_stats().is_err());
+
+        // 金额范围只匹配同一币种
+        let matched = manager
+            .search_cash(CashQuery::new().amount_range(
+                Money::new(0, Currency::Usd),
+                Money::new(i64::MAX, Currency::Usd),
+            ))
+            .unwrap();
+        assert_eq!(matched.iter().map(|c| c.uid).collect::<Vec<_>>(), vec![usd]);
+        let matched = manager
+            .search_cash(CashQuery::new().amount_range(0, i64::MAX))
+            .unwrap();
+        assert_eq!(matched.iter().map(|c| c.uid).collect::<Vec<_>>(), vec![cny]);
+
+        // 按金额排序时先按币种分开
+        let sorted = manager
+            .search_cash(CashQuery::new().order_by(CashSortKey::Amount, SortOrder::Ascending))
+            .unwrap();
+        assert_eq!(sorted.iter().map(|c| c.uid).collect::<Vec<_>>(), vec![cny, usd]);
+    }
+
+    #[test]
+    fn test_plan_keeps_currency() {
+        let temp_dir = TempDir::new().unwrap();
+        let manager = QmxManager::builder()
+            .data_dir(temp_dir.path())
+            .auto_save(AutoSave::Off)
+            .build()
+            .unwrap();
+        let plan = manager
+            .create_installment_plan(InstallmentPlanBuilder::new(
+                Money::new(1000, Currency::Usd),
+                3,
+                PaymentFrequency::Monthly,
+                Utc::now(),
+            ))
+            .unwrap();
+        let terms = manager.get_installment_plan(plan.plan_id).unwrap().unwrap();
+        assert_eq!(terms.total_amount, Money::new(1000, Currency::Usd));
+        let first = manager.get_cash(plan.cash_uids[0]).unwrap().unwrap();
+        assert_eq!(first.cash.currency, Currency::Usd);
+    }
+}
@@ -0,0 +1,108 @@
+// 测试基于角色的权限控制
+use qmx_backend_lib::error::Error;
+use qmx_backend_lib::{
+    AutoSave, Capability, CashBuilder, CoachBuilder, DeletePolicy, Operator, QmxManager, Role,
+    StudentBuilder, StudentUpdater,
+};
+use tempfile::TempDir;
+
+fn setup() -> TempDir {
+    let temp_dir = TempDir::new().unwrap();
+    std::env::set_current_dir(temp_dir.path()).unwrap();
+    let _ = std::fs::create_dir_all("data");
+    temp_dir
+}
+
+fn manager_as(dir: &std::path::Path, role: Role) -> QmxManager {
+    QmxManager::builder()
+        .data_dir(dir)
+        .auto_save(AutoSave::Off)
+        .operator(Operator::new(format!("{:?}", role), role))
+        .build()
+        .unwrap()
+}
+
+fn assert_denied<T: std::fmt::Debug>(result: qmx_backend_lib::error::Result<T>) {
+    match result.unwrap_err() {
+        Error::PermissionDenied(_) => {}
+        other => panic!("期望权限错误，实际为 {:?}", other),
+    }
+}
+
+mod permissions_tests {
+    use super::*;
+
+    #[test]
+    fn test_role_matrix() {
+        assert!(Role::Admin.can(Capability::DeleteCash));
+        assert!(Role::FrontDesk.can(Capability::RecordCash));
+        assert!(!Role::FrontDesk.can(Capability::DeleteCash));
+        assert!(Role::Coach.can(Capability::ManageSessions));
+        assert!(!Role::Coach.can(Capability::RecordCash));
+        assert!(Role::Viewer.capabilities().is_empty());
+
+        let err = Operator::new("小王", Role::FrontDesk)
+            .authorize(Capability::DeleteCash)
+            .unwrap_err();
+        assert_eq!(err.code(), "permission_denied");
+        assert_eq!(err.numeric_code(), 2010);
+        assert_eq!(err.to_string(), "没有权限: 小王（前台）不能删除现金记录");
+    }
+
+    #[test]
+    fn test_front_desk_cannot_delete_cash() {
+        let temp_dir = setup();
+        let manager = manager_as(temp_dir.path(), Role::FrontDesk);
+
+        let uid = manager.create_student(StudentBuilder::new("张三")).unwrap();
+        let cash_uid = manager
+            .record_cash(CashBuilder::new(100).student_id(uid))
+            .unwrap();
+        assert_denied(manager.delete_cash(cash_uid));
+        assert_denied(manager.refund_cash(cash_uid, 50, None));
+        assert_denied(manager.delete_student(uid, DeletePolicy::Restrict));
+        assert_denied(manager.create_coach(CoachBuilder::new("李教练")));
+        assert_denied(manager.undo_last(1));
+        assert!(manager.get_cash(cash_uid).unwrap().is_some());
+
+        // 审计记录中的操作者为操作者标识
+        let entries = manager.get_audit_log(uid).unwrap();
+        assert_eq!(entries[0].actor, "FrontDesk");
+    }
+
+    #[test]
+    fn test_coach_and_viewer() {
+        let temp_dir = setup();
+        let admin = manager_as(temp_dir.path(), Role::Admin);
+        let uid = admin.create_student(StudentBuilder::new("张三")).unwrap();
+        admin.save().unwrap();
+        drop(admin);
+
+        let coach = manager_as(temp_dir.path(), Role::Coach);
+        coach
+            .update_student(uid, StudentUpdater::new().add_ring(9.5))
+            .unwrap();
+        assert_denied(coach.record_cash(CashBuilder::new(100)));
+        drop(coach);
+
+        let viewer = manager_as(temp_dir.path(), Role::Viewer);
+        assert_eq!(viewer.operator().unwrap().role, Role::Viewer);
+        assert!(viewer.get_student(uid).unwrap().is_some());
+        assert_denied(viewer.create_student(StudentBuilder::new("李四")));
+        assert_denied(viewer.update_student(uid, StudentUpdater::new().name("王五")));
+        assert_denied(viewer.backup_now());
+    }
+
+    #[test]
+    fn test_no_operator_skips_checks() {
+        let temp_dir = setup();
+        let manager = QmxManager::builder()
+            .data_dir(temp_dir.path())
+            .auto_save(AutoSave::Off)
+            .build()
+            .unwrap();
+        assert!(manager.operator().is_none());
+        let cash_uid = manager.record_cash(CashBuilder::new(100)).unwrap();
+        assert!(manager.delete_cash(cash_uid).unwrap());
+    }
+}
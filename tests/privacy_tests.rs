@@ -0,0 +1,143 @@
+// 测试个人数据导出与清除
+use chrono::Duration;
+use qmx_backend_lib::audit::{AuditEntity, REDACTED};
+use qmx_backend_lib::student::{Guardian, Subject};
+use qmx_backend_lib::{
+    AutoSave, CashBuilder, Error, QmxManager, SessionBuilder, StudentBuilder, StudentUpdater,
+};
+use tempfile::TempDir;
+
+fn setup() -> TempDir {
+    let temp_dir = TempDir::new().unwrap();
+    std::env::set_current_dir(temp_dir.path()).unwrap();
+    let _ = std::fs::create_dir_all("data");
+    temp_dir
+}
+
+fn manager(temp_dir: &TempDir) -> QmxManager {
+    QmxManager::builder()
+        .data_dir(temp_dir.path())
+        .auto_save(AutoSave::Off)
+        .build()
+        .unwrap()
+}
+
+mod privacy_tests {
+    use super::*;
+
+    #[test]
+    fn test_export_student_data() {
+        let temp_dir = setup();
+        let manager = manager(&temp_dir);
+        let uid = manager
+            .create_student(StudentBuilder::new("张三").phone("13800000000"))
+            .unwrap();
+        let other = manager.create_student(StudentBuilder::new("李四")).unwrap();
+        let paid = manager
+            .record_cash(CashBuilder::new(1000).student_id(uid).note("张三学费"))
+            .unwrap();
+        let deleted = manager
+            .record_cash(CashBuilder::new(200).student_id(uid))
+            .unwrap();
+        manager.delete_cash(deleted).unwrap();
+        manager
+            .record_cash(CashBuilder::new(500).student_id(other))
+            .unwrap();
+        let start = manager.now() + Duration::days(1);
+        let session = manager
+            .create_session(SessionBuilder::new(
+                start,
+                start + Duration::hours(1),
+                Subject::Archery,
+            ))
+            .unwrap();
+        manager.enroll(session, uid).unwrap();
+        let source = temp_dir.path().join("照片.jpg");
+        std::fs::write(&source, b"photo").unwrap();
+        manager.attach_file(uid, &source).unwrap();
+
+        let export = manager.export_student_data(uid).unwrap();
+        assert_eq!(export.student.name(), Some("张三"));
+        assert_eq!(export.cash.len(), 1);
+        assert_eq!(export.cash[0].uid, paid);
+        assert_eq!(export.sessions.len(), 1);
+        assert_eq!(export.attachments.len(), 1);
+        // 学生创建、两条现金记录的创建以及已删除记录的删除
+        assert_eq!(export.audit.len(), 4);
+        assert!(
+            export
+                .audit
+                .iter()
+                .any(|e| e.entity == AuditEntity::Cash && e.entity_uid == deleted)
+        );
+
+        let json: serde_json::Value = serde_json::from_str(&export.to_json().unwrap()).unwrap();
+        assert_eq!(json["student"]["phone"], "13800000000");
+        assert!(matches!(
+            manager.export_student_data(u64::MAX),
+            Err(Error::NotFound(_))
+        ));
+    }
+
+    #[test]
+    fn test_erase_student_data() {
+        let temp_dir = setup();
+        let manager = manager(&temp_dir);
+        let uid = manager
+            .create_student(
+                StudentBuilder::new("张三")
+                    .age(12)
+                    .phone("13800000000")
+                    .note("过敏")
+                    .guardian(Guardian::new("张父", "13900000000", "父亲")),
+            )
+            .unwrap();
+        manager
+            .update_student(
+                uid,
+                StudentUpdater::new().phone("13811111111").add_ring(9.0),
+            )
+            .unwrap();
+        let paid = manager
+            .record_cash(CashBuilder::new(1000).student_id(uid).note("张三学费"))
+            .unwrap();
+        manager.record_cash(CashBuilder::new(-300)).unwrap();
+        let source = temp_dir.path().join("病历.pdf");
+        std::fs::write(&source, b"record").unwrap();
+        let attachment = manager.attach_file(uid, &source).unwrap();
+        let stored = manager.attachment_path(attachment).unwrap().unwrap();
+        let before = manager.get_dashboard_stats().unwrap();
+
+        assert!(manager.erase_student_data(uid).unwrap());
+        assert!(!manager.erase_student_data(u64::MAX).unwrap());
+
+        let student = manager.get_student(uid).unwrap().unwrap();
+        assert_eq!(student.name(), None);
+        assert_eq!(student.phone(), None);
+        assert_eq!(student.age(), None);
+        assert_eq!(student.note(), "");
+        assert!(student.guardians().is_empty());
+        assert_eq!(student.rings(), [9.0]);
+        let cash = manager.get_cash(paid).unwrap().unwrap();
+        assert_eq!(cash.note, None);
+        assert_eq!(cash.cash.amount_minor, 1000);
+        assert_eq!(cash.student_id, Some(uid));
+
+        let after = manager.get_dashboard_stats().unwrap();
+        assert_eq!(after.total_revenue, before.total_revenue);
+        assert_eq!(after.total_expense, before.total_expense);
+        assert_eq!(after.total_students, before.total_students);
+
+        assert!(manager.list_attachments(uid).unwrap().is_empty());
+        assert!(!stored.exists());
+        // 撤销历史已清空，无法恢复个人信息
+        assert_eq!(manager.undo_last(10).unwrap(), 0);
+
+        let audit =
+            serde_json::to_string(&manager.export_student_data(uid).unwrap().audit).unwrap();
+        for secret in ["张三", "13800000000", "13811111111", "过敏", "张父"] {
+            assert!(!audit.contains(secret), "审计记录中仍有 {}", secret);
+        }
+        assert!(audit.contains(REDACTED));
+    }
+}
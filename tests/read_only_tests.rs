@@ -0,0 +1,108 @@
+// 测试只读模式
+use qmx_backend_lib::error::Error;
+use qmx_backend_lib::{
+    CashBuilder, CoachBuilder, DeletePolicy, QmxManager, StudentBuilder, StudentQuery,
+    StudentUpdater,
+};
+use tempfile::TempDir;
+
+fn setup() -> TempDir {
+    let temp_dir = TempDir::new().unwrap();
+    std::env::set_current_dir(temp_dir.path()).unwrap();
+    let _ = std::fs::create_dir_all("data");
+    temp_dir
+}
+
+fn assert_read_only<T: std::fmt::Debug>(result: qmx_backend_lib::error::Result<T>) {
+    match result.unwrap_err() {
+        Error::ReadOnly(_) => {}
+        other => panic!("期望只读错误，实际为 {:?}", other),
+    }
+}
+
+mod read_only_tests {
+    use super::*;
+
+    #[test]
+    fn test_read_only_rejects_mutations() {
+        let temp_dir = setup();
+        let dir = temp_dir.path().join("shop");
+        let writer = QmxManager::builder().data_dir(&dir).build().unwrap();
+        let uid = writer.create_student(StudentBuilder::new("张三")).unwrap();
+        let cash_uid = writer
+            .record_cash(CashBuilder::new(100).student_id(uid))
+            .unwrap();
+        drop(writer);
+        let student_path = dir.join("student_database.json");
+        let before = std::fs::read(&student_path).unwrap();
+
+        let manager =
+            QmxManager::open_read_only(&student_path, dir.join("cash_database.json")).unwrap();
+        assert!(manager.is_read_only());
+
+        // 查询和统计照常可用
+        assert_eq!(
+            manager.get_student(uid).unwrap().unwrap().name(),
+            Some("张三")
+        );
+        assert_eq!(
+            manager.search_students(StudentQuery::new()).unwrap().len(),
+            1
+        );
+        assert_eq!(manager.get_dashboard_stats().unwrap().total_revenue, 100);
+
+        assert_read_only(manager.create_student(StudentBuilder::new("李四")));
+        assert_read_only(manager.update_student(uid, StudentUpdater::new().name("王五")));
+        assert_read_only(manager.delete_student(uid, DeletePolicy::Restrict));
+        assert_read_only(manager.record_cash(CashBuilder::new(50)));
+        assert_read_only(manager.delete_cash(cash_uid));
+        assert_read_only(manager.create_coach(CoachBuilder::new("教练")));
+        assert_read_only(manager.undo_last(1));
+        assert_read_only(manager.backup_now());
+        assert_read_only(manager.save());
+
+        let err = manager
+            .delete_student(uid, DeletePolicy::Restrict)
+            .unwrap_err();
+        assert_eq!(err.code(), "read_only");
+        assert_eq!(err.numeric_code(), 2009);
+
+        // 数据和文件都没有变化
+        assert_eq!(
+            manager.search_students(StudentQuery::new()).unwrap().len(),
+            1
+        );
+        assert_eq!(std::fs::read(&student_path).unwrap(), before);
+        assert!(!dir.join("backups").exists());
+    }
+
+    #[test]
+    fn test_read_only_does_not_create_files() {
+        let temp_dir = setup();
+        let dir = temp_dir.path().join("missing");
+        assert!(
+            QmxManager::builder()
+                .data_dir(&dir)
+                .read_only(true)
+                .build()
+                .is_err()
+        );
+        assert!(!dir.exists());
+    }
+
+    #[test]
+    fn test_builder_read_only_with_data_dir() {
+        let temp_dir = setup();
+        let dir = temp_dir.path().join("report");
+        QmxManager::builder().data_dir(&dir).build().unwrap();
+
+        let manager = QmxManager::builder()
+            .data_dir(&dir)
+            .read_only(true)
+            .build()
+            .unwrap();
+        assert!(manager.is_read_only());
+        assert!(manager.list_students().unwrap().is_empty());
+        assert_read_only(manager.create_student(StudentBuilder::new("赵六")));
+    }
+}
@@ -0,0 +1,100 @@
+// 测试损坏数据文件的自动恢复
+use qmx_backend_lib::recovery::{RecoverySource, bak_path};
+use qmx_backend_lib::{AutoSave, QmxManager, StudentBuilder};
+use std::path::Path;
+use std::sync::Mutex;
+use tempfile::TempDir;
+
+// database::init 通过环境变量读取数据目录，测试之间不能并行
+static ENV_LOCK: Mutex<()> = Mutex::new(());
+
+fn manager(dir: &Path) -> QmxManager {
+    QmxManager::builder()
+        .data_dir(dir)
+        .auto_save(AutoSave::Off)
+        .build()
+        .unwrap()
+}
+
+fn init_in(dir: &Path) -> qmx_backend_lib::error::Result<(
+    qmx_backend_lib::database::Database,
+    Vec<qmx_backend_lib::recovery::RecoveryReport>,
+)> {
+    // SAFETY: 测试持有 ENV_LOCK，不会并发修改环境变量
+    unsafe { std::env::set_var("QMX_DATA_DIR", dir) };
+    let result = qmx_backend_lib::database::init_with_report();
+    unsafe { std::env::remove_var("QMX_DATA_DIR") };
+    result
+}
+
+mod recovery_tests {
+    use super::*;
+
+    #[test]
+    fn test_recover_from_bak() {
+        let _guard = ENV_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        let temp_dir = TempDir::new().unwrap();
+        let uid = {
+            let manager = manager(temp_dir.path());
+            let uid = manager.create_student(StudentBuilder::new("张三")).unwrap();
+            manager.save().unwrap();
+            uid
+        };
+        let path = temp_dir.path().join("student_database.json");
+        let path = path.to_str().unwrap();
+        assert!(Path::new(&bak_path(path)).is_file());
+        std::fs::write(path, b"{\"student_data\": {").unwrap();
+
+        let (db, reports) = init_in(temp_dir.path()).unwrap();
+        assert_eq!(db.student.get(&uid).unwrap().name(), Some("张三"));
+        assert_eq!(reports.len(), 1);
+        let report = &reports[0];
+        assert_eq!(report.path, path);
+        assert_eq!(report.recovered, 1);
+        assert_eq!(report.source, RecoverySource::Bak(bak_path(path).into()));
+        // 损坏的文件被隔离，原位置写入恢复的数据
+        assert_eq!(
+            std::fs::read(&report.quarantine_path).unwrap(),
+            b"{\"student_data\": {"
+        );
+        let (_, reports) = init_in(temp_dir.path()).unwrap();
+        assert!(reports.is_empty());
+    }
+
+    #[test]
+    fn test_recover_from_backup() {
+        let _guard = ENV_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        let temp_dir = TempDir::new().unwrap();
+        let uid = {
+            let manager = manager(temp_dir.path());
+            let uid = manager.create_student(StudentBuilder::new("李四")).unwrap();
+            manager.save().unwrap();
+            manager.backup_now().unwrap();
+            uid
+        };
+        let path = temp_dir.path().join("cash_database.json");
+        let path = path.to_str().unwrap();
+        std::fs::write(path, b"garbage").unwrap();
+        std::fs::remove_file(bak_path(path)).unwrap();
+
+        let (db, reports) = init_in(temp_dir.path()).unwrap();
+        assert!(db.student.get(&uid).is_some());
+        assert_eq!(reports.len(), 1);
+        assert!(matches!(&reports[0].source, RecoverySource::Backup(p) if p.ends_with("cash_database.json")));
+        assert_eq!(reports[0].recovered, 0);
+    }
+
+    #[test]
+    fn test_unrecoverable_file_is_left_in_place() {
+        let _guard = ENV_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        let temp_dir = TempDir::new().unwrap();
+        manager(temp_dir.path()).save().unwrap();
+        let path = temp_dir.path().join("student_database.json");
+        let path = path.to_str().unwrap();
+        std::fs::write(path, b"garbage").unwrap();
+        std::fs::write(bak_path(path), b"also garbage").unwrap();
+
+        assert!(init_in(temp_dir.path()).is_err());
+        assert_eq!(std::fs::read(path).unwrap(), b"garbage");
+    }
+}
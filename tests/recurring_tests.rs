@@ -0,0 +1,140 @@
+// 测试定期支出模板与按月生成支出记录
+use chrono::{NaiveDate, TimeZone, Utc};
+use qmx_backend_lib::error::Error;
+use qmx_backend_lib::permissions::{Operator, Role};
+use qmx_backend_lib::recurring::RecurringOccurrence;
+use qmx_backend_lib::{AutoSave, FixedClock, Money, QmxManager, RecurringExpenseBuilder};
+use std::sync::Arc;
+use tempfile::TempDir;
+
+fn manager(temp_dir: &TempDir) -> QmxManager {
+    QmxManager::builder()
+        .data_dir(temp_dir.path())
+        .auto_save(AutoSave::Off)
+        .build()
+        .unwrap()
+}
+
+mod recurring_tests {
+    use super::*;
+
+    #[test]
+    fn test_process_generates_each_period_once() {
+        let temp_dir = TempDir::new().unwrap();
+        let start = Utc.with_ymd_and_hms(2025, 1, 10, 8, 0, 0).unwrap();
+        let manager = manager(&temp_dir).with_clock(Arc::new(FixedClock::new(start)));
+        let rent = manager
+            .add_recurring_expense(RecurringExpenseBuilder::new("场地租金", 500_000, "房租", 5))
+            .unwrap();
+        let salary = manager
+            .add_recurring_expense(RecurringExpenseBuilder::new(
+                "教练工资",
+                800_000,
+                "工资",
+                31,
+            ))
+            .unwrap();
+
+        // 1 月 5 日早于生效时间，房租从 2 月开始；工资在 2 月取月末
+        let now = Utc.with_ymd_and_hms(2025, 3, 1, 0, 0, 0).unwrap();
+        let uids = manager.process_recurring_expenses(now).unwrap();
+        let records: Vec<_> = uids
+            .iter()
+            .map(|&uid| manager.get_cash(uid).unwrap().unwrap())
+            .collect();
+        let summary: Vec<(Option<&str>, NaiveDate, NaiveDate)> = records
+            .iter()
+            .map(|cash| {
+                let occurrence = cash.recurring.unwrap();
+                (
+                    cash.category.as_deref(),
+                    occurrence.period,
+                    cash.created_at.date_naive(),
+                )
+            })
+            .collect();
+        let day = |m: u32, d: u32| NaiveDate::from_ymd_opt(2025, m, d).unwrap();
+        assert_eq!(
+            summary,
+            vec![
+                (Some("房租"), day(2, 1), day(2, 5)),
+                (Some("工资"), day(1, 1), day(1, 31)),
+                (Some("工资"), day(2, 1), day(2, 28)),
+            ]
+        );
+        assert_eq!(records[0].cash, Money::cny(-500_000));
+        assert_eq!(records[0].note.as_deref(), Some("场地租金"));
+        assert_eq!(
+            records[1].recurring,
+            Some(RecurringOccurrence {
+                template_id: salary,
+                period: day(1, 1),
+            })
+        );
+        assert_eq!(records[0].recurring.unwrap().template_id, rent);
+
+        // 再次处理同一时间不会重复生成
+        assert!(manager.process_recurring_expenses(now).unwrap().is_empty());
+        let later = Utc.with_ymd_and_hms(2025, 3, 5, 0, 0, 0).unwrap();
+        assert_eq!(manager.process_recurring_expenses(later).unwrap().len(), 1);
+        assert_eq!(
+            manager.get_dashboard_stats().unwrap().total_expense,
+            2_600_000
+        );
+
+        // 撤销后整批恢复，再次处理时重新生成
+        assert_eq!(manager.undo_last(1).unwrap(), 1);
+        assert_eq!(manager.process_recurring_expenses(later).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_manage_templates() {
+        let temp_dir = TempDir::new().unwrap();
+        let manager = manager(&temp_dir);
+        let start = Utc.with_ymd_and_hms(2025, 1, 1, 0, 0, 0).unwrap();
+        let uid = manager
+            .add_recurring_expense(
+                RecurringExpenseBuilder::new("场地租金", 500_000, "房租", 1).start(start),
+            )
+            .unwrap();
+        manager
+            .update_recurring_expense(
+                uid,
+                RecurringExpenseBuilder::new("场地租金", 520_000, "房租", 3),
+            )
+            .unwrap();
+
+        // 模板立即写入磁盘，修改时未指定生效时间则保留原值
+        let reopened = self::manager(&temp_dir);
+        let templates = reopened.list_recurring_expenses().unwrap();
+        assert_eq!(templates.len(), 1);
+        assert_eq!(templates[0].amount, Money::cny(520_000));
+        assert_eq!(templates[0].day_of_month, 3);
+        assert_eq!(templates[0].start, start);
+
+        assert!(matches!(
+            manager.add_recurring_expense(RecurringExpenseBuilder::new("房租", -1, "房租", 1)),
+            Err(Error::InvalidInput(_))
+        ));
+        assert!(matches!(
+            manager.add_recurring_expense(RecurringExpenseBuilder::new("房租", 100, "房租", 32)),
+            Err(Error::InvalidInput(_))
+        ));
+        assert!(matches!(
+            manager
+                .update_recurring_expense(99, RecurringExpenseBuilder::new("房租", 1, "房租", 1)),
+            Err(Error::NotFound(_))
+        ));
+
+        let front_desk =
+            self::manager(&temp_dir).with_operator(Operator::new("frontdesk-01", Role::FrontDesk));
+        assert!(matches!(
+            front_desk.delete_recurring_expense(uid),
+            Err(Error::PermissionDenied(_))
+        ));
+        assert!(manager.delete_recurring_expense(uid).unwrap());
+        assert!(!manager.delete_recurring_expense(uid).unwrap());
+        let now = Utc.with_ymd_and_hms(2025, 6, 1, 0, 0, 0).unwrap();
+        assert!(manager.process_recurring_expenses(now).unwrap().is_empty());
+    }
+}
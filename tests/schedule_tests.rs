@@ -0,0 +1,202 @@
+// 测试课程排期与报名
+use chrono::{Duration, TimeZone, Utc};
+use qmx_backend_lib::error::Error;
+use qmx_backend_lib::student::Subject;
+use qmx_backend_lib::{
+    CashBuilder, CoachBuilder, FixedClock, QmxManager, SessionBuilder, StudentBuilder,
+};
+use std::sync::Arc;
+use tempfile::TempDir;
+
+fn setup() -> TempDir {
+    let temp_dir = TempDir::new().unwrap();
+    std::env::set_current_dir(temp_dir.path()).unwrap();
+    let _ = std::fs::create_dir_all("data");
+    temp_dir
+}
+
+mod schedule_tests {
+    use super::*;
+
+    #[test]
+    fn test_create_session_validation() {
+        let _temp_dir = setup();
+        let manager = QmxManager::builder().auto_save(false).build().unwrap();
+        let start = Utc.with_ymd_and_hms(2024, 3, 1, 9, 0, 0).unwrap();
+        let end = start + Duration::hours(1);
+
+        assert!(matches!(
+            manager.create_session(SessionBuilder::new(end, start, Subject::Shooting)),
+            Err(Error::InvalidInput(_))
+        ));
+        assert!(matches!(
+            manager.create_session(SessionBuilder::new(start, end, Subject::Shooting).capacity(0)),
+            Err(Error::ValidationFailed { .. })
+        ));
+        assert!(matches!(
+            manager.create_session(SessionBuilder::new(start, end, Subject::Shooting).coach(7)),
+            Err(Error::NotFound(_))
+        ));
+
+        let coach = manager.create_coach(CoachBuilder::new("教练")).unwrap();
+        let uid = manager
+            .create_session(SessionBuilder::new(start, end, Subject::Archery).coach(coach))
+            .unwrap();
+        let session = manager.get_session(uid).unwrap().unwrap();
+        assert_eq!(session.coach_id, Some(coach));
+        assert_eq!(session.capacity, SessionBuilder::DEFAULT_CAPACITY);
+        assert!(session.enrolled.is_empty());
+
+        // 课程排期立即写入磁盘
+        let reloaded = QmxManager::builder().auto_save(false).build().unwrap();
+        assert_eq!(reloaded.get_session(uid).unwrap(), Some(session));
+        assert!(manager.delete_session(uid).unwrap());
+        assert!(!manager.delete_session(uid).unwrap());
+    }
+
+    #[test]
+    fn test_enroll_with_capacity() {
+        let _temp_dir = setup();
+        let manager = QmxManager::builder().auto_save(false).build().unwrap();
+        let start = Utc.with_ymd_and_hms(2024, 3, 1, 9, 0, 0).unwrap();
+        let session = manager
+            .create_session(
+                SessionBuilder::new(start, start + Duration::hours(1), Subject::Shooting)
+                    .capacity(2),
+            )
+            .unwrap();
+        let students: Vec<u64> = (0..3)
+            .map(|i| {
+                manager
+                    .create_student(StudentBuilder::new(format!("学生{}", i)))
+                    .unwrap()
+            })
+            .collect();
+
+        manager.enroll(session, students[0]).unwrap();
+        assert!(matches!(
+            manager.enroll(session, students[0]),
+            Err(Error::State(_))
+        ));
+        manager.enroll(session, students[1]).unwrap();
+        assert!(matches!(
+            manager.enroll(session, students[2]),
+            Err(Error::State(_))
+        ));
+        assert!(matches!(
+            manager.enroll(session, 9999),
+            Err(Error::NotFound(_))
+        ));
+        assert!(matches!(
+            manager.enroll(99, students[2]),
+            Err(Error::NotFound(_))
+        ));
+
+        // 取消报名后空出名额
+        assert!(manager.unenroll(session, students[0]).unwrap());
+        assert!(!manager.unenroll(session, students[0]).unwrap());
+        manager.enroll(session, students[2]).unwrap();
+        let session = manager.get_session(session).unwrap().unwrap();
+        assert_eq!(session.enrolled, vec![students[1], students[2]]);
+        assert!(session.is_full());
+    }
+
+    #[test]
+    fn test_todays_and_upcoming_sessions() {
+        let _temp_dir = setup();
+        let now = Utc.with_ymd_and_hms(2024, 3, 1, 12, 0, 0).unwrap();
+        let manager = QmxManager::builder()
+            .auto_save(false)
+            .build()
+            .unwrap()
+            .with_clock(Arc::new(FixedClock::new(now)));
+        let student = manager
+            .create_student(StudentBuilder::new("排课学生"))
+            .unwrap();
+        let create = |start| {
+            manager
+                .create_session(SessionBuilder::new(
+                    start,
+                    start + Duration::hours(1),
+                    Subject::Shooting,
+                ))
+                .unwrap()
+        };
+        let afternoon = create(now + Duration::hours(3));
+        let morning = create(now - Duration::hours(3));
+        let tomorrow = create(now + Duration::days(1));
+        let next_week = create(now + Duration::days(7));
+
+        let today: Vec<u64> = manager
+            .todays_sessions()
+            .unwrap()
+            .iter()
+            .map(|s| s.uid)
+            .collect();
+        assert_eq!(today, vec![morning, afternoon]);
+
+        for session in [next_week, morning, tomorrow] {
+            manager.enroll(session, student).unwrap();
+        }
+        // 已经开始的课程不算即将到来的课程
+        let upcoming: Vec<u64> = manager
+            .upcoming_sessions(student)
+            .unwrap()
+            .iter()
+            .map(|s| s.uid)
+            .collect();
+        assert_eq!(upcoming, vec![tomorrow, next_week]);
+    }
+
+    #[test]
+    fn test_inactive_students() {
+        let _temp_dir = setup();
+        let now = Utc.with_ymd_and_hms(2024, 3, 1, 12, 0, 0).unwrap();
+        let clock = Arc::new(FixedClock::new(now - Duration::days(60)));
+        let manager = QmxManager::builder()
+            .auto_save(false)
+            .build()
+            .unwrap()
+            .with_clock(clock.clone());
+        let paying = manager.create_student(StudentBuilder::new("缴费")).unwrap();
+        let attending = manager.create_student(StudentBuilder::new("上课")).unwrap();
+        let lapsed = manager.create_student(StudentBuilder::new("流失")).unwrap();
+        let idle = manager.create_student(StudentBuilder::new("从未活动")).unwrap();
+        // 60 天前的缴费不在统计窗口内
+        manager
+            .record_cash(CashBuilder::new(500).student_id(lapsed))
+            .unwrap();
+
+        clock.advance(Duration::days(50));
+        manager
+            .record_cash(CashBuilder::new(500).student_id(paying))
+            .unwrap();
+        let session = manager
+            .create_session(SessionBuilder::new(
+                now - Duration::days(5),
+                now - Duration::days(5) + Duration::hours(1),
+                Subject::Shooting,
+            ))
+            .unwrap();
+        manager.enroll(session, attending).unwrap();
+        // 尚未开始的课程不算活动
+        let future = manager
+            .create_session(SessionBuilder::new(
+                now + Duration::days(1),
+                now + Duration::days(1) + Duration::hours(1),
+                Subject::Shooting,
+            ))
+            .unwrap();
+        manager.enroll(future, idle).unwrap();
+        clock.advance(Duration::days(10));
+
+        let inactive: Vec<u64> = manager
+            .get_inactive_students(30)
+            .unwrap()
+            .iter()
+            .map(|s| s.uid())
+            .collect();
+        assert_eq!(inactive, vec![lapsed, idle]);
+        assert_eq!(manager.get_inactive_students(90).unwrap().len(), 1);
+    }
+}
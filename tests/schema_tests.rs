@@ -0,0 +1,198 @@
+// 测试 JSON Schema 导出，需要启用 schema feature
+#![cfg(feature = "schema")]
+
+use chrono::{Duration, Utc};
+use qmx_backend_lib::cash::{Cash, CashDatabase, PaymentFrequency};
+use qmx_backend_lib::schema::{JsonSchema, export_schemas, schema_for, write_schemas};
+use qmx_backend_lib::student::{Guardian, MembershipTier, Student, StudentDatabase};
+use qmx_backend_lib::{Currency, CustomValue, DashboardStats, Money, get_dashboard_stats};
+use serde_json::Value;
+use tempfile::TempDir;
+
+/// 按 Schema 校验 JSON 值，只支持导出的 Schema 用到的关键字
+fn validate(root: &Value, schema: &Value, value: &Value, path: &str) -> Result<(), String> {
+    if let Some(reference) = schema["$ref"].as_str() {
+        let name = reference.trim_start_matches("#/$defs/");
+        return validate(root, &root["$defs"][name], value, path);
+    }
+    if let Some(options) = schema["anyOf"].as_array().or(schema["oneOf"].as_array()) {
+        let matched = options
+            .iter()
+            .filter(|option| validate(root, option, value, path).is_ok())
+            .count();
+        return match matched {
+            0 => Err(format!("{}: 不匹配任何分支", path)),
+            1 => Ok(()),
+            _ if schema["anyOf"].is_array() => Ok(()),
+            _ => Err(format!("{}: 匹配多个 oneOf 分支", path)),
+        };
+    }
+    let type_ok = |name: &Value| match name.as_str() {
+        Some("string") => value.is_string(),
+        Some("integer") => value.is_i64() || value.is_u64(),
+        Some("number") => value.is_number(),
+        Some("boolean") => value.is_boolean(),
+        Some("null") => value.is_null(),
+        Some("array") => value.is_array(),
+        Some("object") => value.is_object(),
+        _ => true,
+    };
+    let type_ok = match &schema["type"] {
+        Value::Array(names) => names.iter().any(type_ok),
+        name => type_ok(name),
+    };
+    if !type_ok {
+        return Err(format!("{}: 类型应为 {}", path, schema["type"]));
+    }
+    if let Some(variants) = schema["enum"].as_array()
+        && !variants.contains(value)
+    {
+        return Err(format!("{}: {} 不在枚举中", path, value));
+    }
+    if let Some(constant) = schema.get("const")
+        && constant != value
+    {
+        return Err(format!("{}: 应为 {}", path, constant));
+    }
+    if let Some(minimum) = schema["minimum"].as_i64()
+        && value.as_i64().is_some_and(|v| v < minimum)
+    {
+        return Err(format!("{}: 小于 {}", path, minimum));
+    }
+    if let Some(maximum) = schema["maximum"].as_u64()
+        && value.as_u64().is_some_and(|v| v > maximum)
+    {
+        return Err(format!("{}: 大于 {}", path, maximum));
+    }
+    if let Some(items) = value.as_array() {
+        for (i, item) in items.iter().enumerate() {
+            validate(root, &schema["items"], item, &format!("{}[{}]", path, i))?;
+        }
+    }
+    if let Some(fields) = value.as_object() {
+        for required in schema["required"].as_array().into_iter().flatten() {
+            let name = required.as_str().unwrap();
+            if !fields.contains_key(name) {
+                return Err(format!("{}: 缺少必填字段 {}", path, name));
+            }
+        }
+        for (name, field) in fields {
+            let field_path = format!("{}.{}", path, name);
+            match (&schema["properties"][name], &schema["additionalProperties"]) {
+                (Value::Null, Value::Bool(false)) => {
+                    return Err(format!("{}: 不允许的字段", field_path));
+                }
+                (Value::Null, Value::Null) => {
+                    // 根对象的每个字段都必须在 Schema 中声明，以便发现类型与 Schema 不一致
+                    if schema.get("properties").is_some() {
+                        return Err(format!("{}: Schema 中未声明", field_path));
+                    }
+                }
+                (Value::Null, additional) => validate(root, additional, field, &field_path)?,
+                (property, _) => validate(root, property, field, &field_path)?,
+            }
+        }
+    }
+    Ok(())
+}
+
+fn assert_valid<T: JsonSchema + serde::Serialize>(value: &T) {
+    let schema = schema_for::<T>();
+    let json = serde_json::to_value(value).unwrap();
+    if let Err(e) = validate(&schema, &schema, &json, &T::schema_name()) {
+        panic!("{}\n{}", e, json);
+    }
+}
+
+mod schema_tests {
+    use super::*;
+
+    #[test]
+    fn test_export_schemas() {
+        let schemas = export_schemas();
+        let names: Vec<&str> = schemas.keys().map(String::as_str).collect();
+        assert_eq!(names, [
+                "Cash",
+                "DashboardStats",
+                "Installment",
+                "InstallmentPlan",
+                "Student"
+            ]);
+        for (name, schema) in &schemas {
+            assert_eq!(schema["title"], *name);
+            assert!(schema["$schema"].as_str().unwrap().contains("2020-12"));
+        }
+
+        let student = &schemas["Student"];
+        let required = student["required"].as_array().unwrap();
+        assert!(required.contains(&"uid".into()));
+        assert!(!required.contains(&"name".into()));
+        assert_eq!(student["$defs"]["Class"]["enum"][0], "TenTry");
+        // Cash 引用的 Installment 定义与独立导出的一致
+        assert_eq!(
+            schemas["Cash"]["$defs"]["Installment"]["properties"],
+            schemas["Installment"]["properties"]
+        );
+    }
+
+    #[test]
+    fn test_serialized_values_match_schemas() {
+        let mut minimal = Student::new();
+        minimal.set_name("张三".to_string());
+        assert_valid(&minimal);
+
+        let now = Utc::now();
+        let mut student = Student::new();
+        student
+            .set_name("李四".to_string())
+            .set_age(Some(16))
+            .set_phone("13800138000".to_string())
+            .add_ring(9.5)
+            .unwrap()
+            .set_membership_dates(Some(now), Some(now + Duration::days(30)))
+            .set_membership_tier(Some(MembershipTier::Custom("钻石".to_string())))
+            .add_guardian(Guardian::new("王五", "13900139000", "母亲"))
+            .set_custom_field("学校", CustomValue::from("一中"))
+            .set_custom_field("体检", CustomValue::Date(now));
+        assert_valid(&student);
+
+        let mut cash = Cash::new(Some(student.uid()));
+        cash.set_cash(-500);
+        assert_valid(&cash);
+        let mut foreign = Cash::new(None);
+        foreign.set_cash(Money::new(1999, Currency::Usd));
+        assert_valid(&foreign);
+        let installment = Cash::new_installment(
+            Some(student.uid()),
+            3000,
+            3,
+            PaymentFrequency::Custom(10),
+            now,
+            1,
+            None,
+        );
+        assert_valid(&installment);
+        assert_valid(installment.installment.as_ref().unwrap());
+
+        let mut students = StudentDatabase::new();
+        students.insert(student);
+        let mut cash_db = CashDatabase::new();
+        cash_db.insert(cash);
+        let plan_id = installment.installment.as_ref().unwrap().plan_id;
+        cash_db.insert(installment);
+        assert_valid(cash_db.plans.get(&plan_id).unwrap());
+        let stats: DashboardStats = get_dashboard_stats(&students, &cash_db).unwrap();
+        assert_valid(&stats);
+    }
+
+    #[test]
+    fn test_write_schemas() {
+        let temp_dir = TempDir::new().unwrap();
+        let dir = temp_dir.path().join("schemas");
+        assert_eq!(write_schemas(&dir).unwrap(), 5);
+
+        let text = std::fs::read_to_string(dir.join("Student.schema.json")).unwrap();
+        let schema: Value = serde_json::from_str(&text).unwrap();
+        assert_eq!(schema, export_schemas()["Student"]);
+    }
+}
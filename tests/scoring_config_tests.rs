@@ -0,0 +1,137 @@
+// 测试按科目区分的计分配置
+use qmx_backend_lib::student::{RingRange, ScoringConfig, ScoringConfigs, Subject};
+use qmx_backend_lib::{QmxManager, StudentBuilder, StudentUpdater};
+use tempfile::TempDir;
+
+mod scoring_config_tests {
+    use super::*;
+
+    #[test]
+    fn test_scoring_config_precision() {
+        assert!(ScoringConfig::SHOOTING.allows_precision(10.9));
+        assert!(ScoringConfig::SHOOTING.allows_precision(9.0));
+        assert!(!ScoringConfig::SHOOTING.allows_precision(9.55));
+        assert!(ScoringConfig::ARCHERY.allows_precision(10.0));
+        assert!(!ScoringConfig::ARCHERY.allows_precision(9.5));
+        assert!(ScoringConfig::PERCENTAGE.allows_precision(87.125));
+    }
+
+    #[test]
+    fn test_histogram_and_series() {
+        let shooting = ScoringConfig::SHOOTING;
+        let histogram = shooting.histogram(&[10.9, 10.0, 9.8, 9.1, 0.0, 12.0]);
+        assert_eq!(histogram.len(), 11);
+        assert_eq!(histogram[10], 2);
+        assert_eq!(histogram[9], 2);
+        assert_eq!(histogram[0], 1);
+        assert_eq!(histogram.iter().sum::<usize>(), 5);
+
+        let archery = ScoringConfig::new(10.0, Some(0), 3);
+        assert_eq!(
+            archery.series_totals(&[10.0, 9.0, 8.0, 7.0, 6.0]),
+            vec![27.0, 13.0]
+        );
+    }
+
+    #[test]
+    fn test_configs_ranges() {
+        let configs = ScoringConfigs::standard();
+        assert_eq!(configs.range(&Subject::Shooting).max, 10.9);
+        assert_eq!(configs.range(&Subject::Archery).max, 10.0);
+        assert_eq!(configs.range(&Subject::Others).max, 100.0);
+    }
+
+    #[test]
+    fn test_configs_loaded_from_data_dir() {
+        let temp_dir = TempDir::new().unwrap();
+        std::env::set_current_dir(temp_dir.path()).unwrap();
+        let _ = std::fs::create_dir_all("data");
+
+        ScoringConfigs::standard()
+            .save_to("data/scoring_config.json")
+            .unwrap();
+        let manager = QmxManager::builder().auto_save(false).build().unwrap();
+        assert_eq!(manager.scoring_configs().unwrap(), ScoringConfigs::standard());
+
+        let uid = manager
+            .create_student(StudentBuilder::new("射箭学员").subject(Subject::Archery))
+            .unwrap();
+        let err = manager
+            .update_student(uid, StudentUpdater::new().add_ring(9.5))
+            .unwrap_err();
+        assert_eq!(err.code(), "validation_failed");
+        let err = manager
+            .update_student(uid, StudentUpdater::new().add_ring(10.9))
+            .unwrap_err();
+        assert_eq!(err.code(), "validation_failed");
+
+        manager
+            .update_student(uid, StudentUpdater::new().set_rings(vec![10.0; 7]))
+            .unwrap();
+        let stats = manager.get_student_stats(uid).unwrap();
+        assert_eq!(stats.score_histogram.len(), 11);
+        assert_eq!(stats.score_histogram[10], 7);
+        assert_eq!(stats.series_totals, vec![60.0, 10.0]);
+    }
+
+    #[test]
+    fn test_default_configs_limit_ranges_only() {
+        let configs = ScoringConfigs::default();
+        assert_eq!(configs.range(&Subject::Shooting), RingRange::SHOOTING);
+        assert_eq!(configs.range(&Subject::Archery), RingRange::ARCHERY);
+        assert_eq!(configs.get(&Subject::Shooting).decimals, None);
+
+        let mut custom = ScoringConfigs::standard();
+        custom.set_range(Subject::Archery, RingRange::PERCENTAGE);
+        assert_eq!(custom.get(&Subject::Archery).range(), RingRange::PERCENTAGE);
+        assert_eq!(custom.get(&Subject::Archery).decimals, Some(0));
+    }
+
+    #[test]
+    fn test_configs_are_per_manager() {
+        let strict_dir = TempDir::new().unwrap();
+        let strict = QmxManager::builder()
+            .data_dir(strict_dir.path())
+            .scoring_configs(ScoringConfigs::standard())
+            .build()
+            .unwrap();
+        let loose_dir = TempDir::new().unwrap();
+        let loose = QmxManager::builder()
+            .data_dir(loose_dir.path())
+            .scoring_configs(ScoringConfigs::percentage())
+            .build()
+            .unwrap();
+
+        let builder = || StudentBuilder::new("射击学员").subject(Subject::Shooting);
+        let strict_uid = strict.create_student(builder()).unwrap();
+        let loose_uid = loose.create_student(builder()).unwrap();
+        assert!(
+            strict
+                .update_student(strict_uid, StudentUpdater::new().add_ring(50.0))
+                .is_err()
+        );
+        loose
+            .update_student(loose_uid, StudentUpdater::new().add_ring(50.0))
+            .unwrap();
+    }
+
+    #[test]
+    fn test_set_configs_saved_to_data_dir() {
+        let dir = TempDir::new().unwrap();
+        let manager = QmxManager::builder().data_dir(dir.path()).build().unwrap();
+        assert_eq!(manager.scoring_configs().unwrap(), ScoringConfigs::default());
+
+        manager
+            .set_scoring_configs(ScoringConfigs::standard())
+            .unwrap();
+        drop(manager);
+
+        let reopened = QmxManager::builder().data_dir(dir.path()).build().unwrap();
+        assert_eq!(reopened.scoring_configs().unwrap(), ScoringConfigs::standard());
+        let path = dir.path().join("scoring_config.json");
+        assert_eq!(
+            ScoringConfigs::read_from(path.to_str().unwrap()).unwrap(),
+            ScoringConfigs::standard()
+        );
+    }
+}
@@ -0,0 +1,76 @@
+// 测试内存快照的创建与恢复
+use qmx_backend_lib::{
+    AutoSave, CashBuilder, DeletePolicy, Error, QmxManager, StudentBuilder, StudentUpdater,
+};
+use tempfile::TempDir;
+
+fn manager(temp_dir: &TempDir) -> QmxManager {
+    QmxManager::builder()
+        .data_dir(temp_dir.path())
+        .auto_save(AutoSave::Off)
+        .build()
+        .unwrap()
+}
+
+mod snapshot_tests {
+    use super::*;
+
+    #[test]
+    fn test_restore_reverts_bulk_changes() {
+        let temp_dir = TempDir::new().unwrap();
+        let manager = manager(&temp_dir);
+        let zhang = manager.create_student(StudentBuilder::new("张三")).unwrap();
+        let li = manager.create_student(StudentBuilder::new("李四")).unwrap();
+        let paid = manager
+            .record_cash(CashBuilder::new(1000).student_id(zhang))
+            .unwrap();
+
+        let info = manager.snapshot("批量调整前").unwrap();
+        assert_eq!((info.students, info.cash), (2, 1));
+
+        manager
+            .update_student(zhang, StudentUpdater::new().name("张三丰"))
+            .unwrap();
+        manager.delete_student(li, DeletePolicy::Restrict).unwrap();
+        manager.delete_cash(paid).unwrap();
+        let added = manager.create_student(StudentBuilder::new("王五")).unwrap();
+
+        assert_eq!(manager.restore_snapshot("批量调整前").unwrap(), 4);
+        assert_eq!(manager.get_student(zhang).unwrap().unwrap().name(), Some("张三"));
+        assert!(manager.get_student(li).unwrap().is_some());
+        assert!(manager.get_cash(paid).unwrap().is_some());
+        assert!(manager.get_student(added).unwrap().is_none());
+
+        // 恢复可以撤销，快照本身保留
+        assert_eq!(manager.undo_last(1).unwrap(), 1);
+        assert_eq!(manager.get_student(zhang).unwrap().unwrap().name(), Some("张三丰"));
+        assert!(manager.get_student(added).unwrap().is_some());
+        assert_eq!(manager.list_snapshots().unwrap(), vec![info]);
+        assert_eq!(manager.restore_snapshot("批量调整前").unwrap(), 4);
+        assert_eq!(manager.restore_snapshot("批量调整前").unwrap(), 0);
+    }
+
+    #[test]
+    fn test_labels_are_unique_and_deletable() {
+        let temp_dir = TempDir::new().unwrap();
+        let manager = manager(&temp_dir);
+        manager.snapshot("a").unwrap();
+        manager.create_student(StudentBuilder::new("张三")).unwrap();
+        manager.snapshot("b").unwrap();
+
+        assert!(matches!(manager.snapshot("a"), Err(Error::InvalidInput(_))));
+        assert!(matches!(manager.snapshot(" "), Err(Error::ValidationFailed { .. })));
+        let labels: Vec<String> = manager
+            .list_snapshots()
+            .unwrap()
+            .into_iter()
+            .map(|info| info.label)
+            .collect();
+        assert_eq!(labels, ["a", "b"]);
+
+        assert!(manager.delete_snapshot("a").unwrap());
+        assert!(!manager.delete_snapshot("a").unwrap());
+        assert!(matches!(manager.restore_snapshot("a"), Err(Error::NotFound(_))));
+        assert_eq!(manager.list_snapshots().unwrap().len(), 1);
+    }
+}
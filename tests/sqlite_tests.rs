@@ -0,0 +1,83 @@
+// 测试 SQLite 存储后端，需要启用 sqlite feature
+#![cfg(feature = "sqlite")]
+
+use qmx_backend_lib::cash::PaymentFrequency;
+use qmx_backend_lib::{
+    CashBuilder, EncryptionKey, FileCodec, InstallmentPlanBuilder, QmxManager, SqliteBackend,
+    StorageBackend, StudentBuilder, StudentUpdater,
+};
+use std::sync::Arc;
+use tempfile::TempDir;
+
+fn open(backend: &Arc<SqliteBackend>) -> QmxManager {
+    QmxManager::builder()
+        .storage(backend.clone())
+        .auto_save(true)
+        .build()
+        .unwrap()
+}
+
+fn rows(path: &std::path::Path, table: &str) -> i64 {
+    rusqlite::Connection::open(path)
+        .unwrap()
+        .query_row(&format!("SELECT count(*) FROM {}", table), [], |row| {
+            row.get(0)
+        })
+        .unwrap()
+}
+
+mod sqlite_tests {
+    use super::*;
+
+    #[test]
+    fn test_records_written_separately() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("qmx.sqlite");
+        let backend = Arc::new(SqliteBackend::open(&path).unwrap());
+        let manager = open(&backend);
+        let first = manager.create_student(StudentBuilder::new("张三")).unwrap();
+        let second = manager.create_student(StudentBuilder::new("李四")).unwrap();
+        manager
+            .update_student(first, StudentUpdater::new().age(Some(12)))
+            .unwrap();
+        let cash = manager
+            .record_cash(CashBuilder::new(1000).student_id(first))
+            .unwrap();
+        manager
+            .create_installment_plan(
+                InstallmentPlanBuilder::new(3000, 3, PaymentFrequency::Monthly, manager.now())
+                    .student_id(second),
+            )
+            .unwrap();
+        manager.delete_cash(cash).unwrap();
+        assert_eq!(rows(&path, "students"), 2);
+        assert_eq!(rows(&path, "cash"), 3);
+        assert_eq!(rows(&path, "plans"), 1);
+        drop(manager);
+
+        let reopened = open(&backend);
+        assert_eq!(
+            reopened.get_student(first).unwrap().unwrap().age(),
+            Some(12)
+        );
+        assert!(reopened.get_cash(cash).unwrap().is_none());
+        // 删除最新记录后重新打开也不复用其 UID
+        let next = reopened.record_cash(CashBuilder::new(500)).unwrap();
+        assert!(next > cash);
+    }
+
+    #[test]
+    fn test_full_save_and_encrypted_rows() {
+        let key = EncryptionKey::new([7; 32]);
+        let backend = Arc::new(
+            SqliteBackend::open_in_memory()
+                .unwrap()
+                .with_codec(FileCodec::new().with_key(Some(key))),
+        );
+        let manager = open(&backend);
+        let uid = manager.create_student(StudentBuilder::new("王五")).unwrap();
+        manager.save().unwrap();
+        let db = backend.load().unwrap();
+        assert_eq!(db.student.get(&uid).unwrap().name(), Some("王五"));
+    }
+}
@@ -0,0 +1,109 @@
+// 测试学生对账单的生成与渲染
+use chrono::{TimeZone, Utc};
+use qmx_backend_lib::cash::PaymentMethod;
+use qmx_backend_lib::error::Error;
+use qmx_backend_lib::statement::StatementItem;
+use qmx_backend_lib::student::Class;
+use qmx_backend_lib::{
+    AutoSave, FixedClock, Money, QmxManager, StudentBuilder, StudentUpdater, TimePeriod,
+};
+use std::sync::Arc;
+use tempfile::TempDir;
+
+mod statement_tests {
+    use super::*;
+
+    #[test]
+    fn test_statement_lists_transactions_and_changes() {
+        let temp_dir = TempDir::new().unwrap();
+        let clock = Arc::new(FixedClock::new(
+            Utc.with_ymd_and_hms(2025, 1, 5, 9, 0, 0).unwrap(),
+        ));
+        let manager = QmxManager::builder()
+            .data_dir(temp_dir.path())
+            .auto_save(AutoSave::Off)
+            .build()
+            .unwrap()
+            .with_clock(clock.clone());
+        let student = manager
+            .create_student(StudentBuilder::new("张<三>").class(Class::TenTry))
+            .unwrap();
+        manager
+            .top_up_balance(student, 50_000, PaymentMethod::WeChat)
+            .unwrap();
+
+        clock.set(Utc.with_ymd_and_hms(2025, 1, 10, 9, 0, 0).unwrap());
+        manager
+            .update_student(student, StudentUpdater::new().consume_lesson())
+            .unwrap();
+        manager.deduct_balance(student, 12_000, "上课扣费").unwrap();
+
+        clock.set(Utc.with_ymd_and_hms(2025, 1, 12, 9, 0, 0).unwrap());
+        manager.sell(student, 2).unwrap();
+
+        clock.set(Utc.with_ymd_and_hms(2025, 2, 1, 9, 0, 0).unwrap());
+        let january = TimePeriod::Custom {
+            start: Utc.with_ymd_and_hms(2025, 1, 1, 0, 0, 0).unwrap(),
+            end: Utc.with_ymd_and_hms(2025, 1, 31, 23, 59, 59).unwrap(),
+        };
+        let statement = manager.get_student_statement(student, january).unwrap();
+        assert_eq!(statement.opening_balance, Money::cny(0));
+        assert_eq!(statement.closing_balance, Money::cny(38_000));
+        let items: Vec<&StatementItem> = statement.entries.iter().map(|e| &e.item).collect();
+        assert!(matches!(
+            items.as_slice(),
+            [
+                StatementItem::Transaction {
+                    wallet: Some(_),
+                    ..
+                },
+                StatementItem::LessonsAdded {
+                    count: 10,
+                    lesson_left: Some(10)
+                },
+                StatementItem::Transaction {
+                    wallet: Some(_),
+                    ..
+                },
+                StatementItem::LessonsConsumed {
+                    count: 1,
+                    lesson_left: Some(9)
+                },
+                StatementItem::Transaction { wallet: None, .. },
+                StatementItem::MembershipChanged { end: Some(_) },
+            ]
+        ));
+        let balances: Vec<i64> = statement
+            .entries
+            .iter()
+            .map(|e| e.balance.amount_minor)
+            .collect();
+        assert_eq!(
+            balances,
+            vec![50_000, 50_000, 38_000, 38_000, 38_000, 38_000]
+        );
+
+        let text = statement.render_text();
+        assert!(text.contains("期间: 2025-01-01 至 2025-01-31"));
+        assert!(text.contains("2025-01-10  上课扣费  储值 -¥120.00  余额 ¥380.00"));
+        assert!(text.contains("会员到期 2025-02-12"));
+        let html = statement.render_html();
+        assert!(html.contains("张&lt;三&gt;"));
+        assert!(!html.contains("张<三>"));
+
+        // 期初余额包含期间开始前的储值记录
+        let later = TimePeriod::Custom {
+            start: Utc.with_ymd_and_hms(2025, 1, 8, 0, 0, 0).unwrap(),
+            end: Utc.with_ymd_and_hms(2025, 1, 11, 0, 0, 0).unwrap(),
+        };
+        let statement = manager.get_student_statement(student, later).unwrap();
+        assert_eq!(statement.opening_balance, Money::cny(50_000));
+        assert_eq!(statement.entries.len(), 2);
+        assert_eq!(statement.closing_balance, Money::cny(38_000));
+
+        assert!(matches!(
+            manager.get_student_statement(9_999_999, TimePeriod::ThisMonth),
+            Err(Error::NotFound(_))
+        ));
+    }
+}
@@ -1,6 +1,7 @@
-use qmx_backend_lib::cash::{Cash, CashDatabase};
+use chrono::{Datelike, TimeZone, Utc};
+use qmx_backend_lib::cash::{Cash, CashDatabase, Installment, InstallmentStatus};
 use qmx_backend_lib::stats::*;
-use qmx_backend_lib::student::{Class, Student, StudentDatabase};
+use qmx_backend_lib::student::{Class, Student, StudentDatabase, Subject};
 
 #[cfg(test)]
 mod stats_comprehensive_tests {
@@ -24,7 +25,7 @@ mod stats_comprehensive_tests {
     fn stats_with_students_no_cash() {
         let mut student_db = StudentDatabase::new();
         let mut s1 = Student::new();
-        s1.set_class(Class::Month).add_ring(8.0);
+        s1.set_class(Class::Month).add_ring(8.0).unwrap();
         student_db.insert(s1);
 
         let cash_db = CashDatabase::new();
@@ -60,10 +61,14 @@ mod stats_comprehensive_tests {
     fn stats_full_scenario() {
         let mut student_db = StudentDatabase::new();
         let mut s1 = Student::new();
-        s1.set_class(Class::Year).add_ring(10.0).add_ring(9.0);
+        s1.set_class(Class::Year)
+            .add_ring(10.0)
+            .unwrap()
+            .add_ring(9.0)
+            .unwrap();
         student_db.insert(s1);
         let mut s2 = Student::new();
-        s2.set_class(Class::TenTry).add_ring(7.5);
+        s2.set_class(Class::TenTry).add_ring(7.5).unwrap();
         student_db.insert(s2);
         let mut s3 = Student::new();
         s3.set_class(Class::Others);
@@ -143,4 +148,196 @@ mod stats_comprehensive_tests {
         assert_eq!(stats.total_revenue, 300);
         assert_eq!(stats.total_expense, 50);
     }
+
+    #[test]
+    fn stats_breakdown_by_subject_and_class() {
+        let mut student_db = StudentDatabase::new();
+        let mut cash_db = CashDatabase::new();
+
+        let mut shooter = Student::new();
+        shooter
+            .set_subject(Subject::Shooting)
+            .set_class(Class::Month)
+            .add_ring(8.0)
+            .unwrap()
+            .add_ring(10.0)
+            .unwrap();
+        let mut archer1 = Student::new();
+        archer1
+            .set_subject(Subject::Archery)
+            .set_class(Class::Month)
+            .add_ring(6.0)
+            .unwrap();
+        let mut archer2 = Student::new();
+        archer2.set_subject(Subject::Archery).set_class(Class::Year);
+
+        let mut c1 = Cash::new(Some(shooter.uid()));
+        c1.set_cash(1000);
+        let mut c2 = Cash::new(Some(archer1.uid()));
+        c2.set_cash(500);
+        let mut c3 = Cash::new(Some(archer2.uid()));
+        c3.set_cash(-200);
+        let mut c4 = Cash::new(None);
+        c4.set_cash(9999); // 未关联学生，不计入分组
+        for cash in [c1, c2, c3, c4] {
+            cash_db.insert(cash);
+        }
+        for student in [shooter, archer1, archer2] {
+            student_db.insert(student);
+        }
+
+        let stats = get_breakdown_stats(&student_db, &cash_db).unwrap();
+
+        let shooting = &stats.by_subject[&Subject::Shooting];
+        assert_eq!(shooting.student_count, 1);
+        assert_eq!(shooting.revenue, 1000);
+        assert_eq!(shooting.average_score, 9.0);
+        let archery = &stats.by_subject[&Subject::Archery];
+        assert_eq!(archery.student_count, 2);
+        assert_eq!(archery.revenue, 500);
+        assert_eq!(archery.expense, 200);
+        assert_eq!(archery.average_score, 6.0);
+        assert_eq!(archery.score_count, 1);
+        assert!(!stats.by_subject.contains_key(&Subject::Others));
+
+        let month = &stats.by_class[&Class::Month];
+        assert_eq!(month.student_count, 2);
+        assert_eq!(month.revenue, 1500);
+        assert_eq!(month.average_score, 8.0);
+        let year = &stats.by_class[&Class::Year];
+        assert_eq!(year.student_count, 1);
+        assert_eq!(year.average_score, 0.0);
+    }
+
+    #[test]
+    fn stats_forecast_revenue() {
+        let mut cash_db = CashDatabase::new();
+        let at = |month: u32, day: u32| Utc.with_ymd_and_hms(2024, month, day, 8, 0, 0).unwrap();
+
+        // 1 月至 6 月的非分期收入逐月增加 100
+        for month in 1..=6 {
+            let mut cash = Cash::new(None);
+            cash.set_cash(i64::from(month) * 100);
+            cash.created_at = at(month, 10);
+            cash_db.insert(cash);
+        }
+        let mut refund = Cash::new(None);
+        refund.set_cash(-1000);
+        refund.created_at = at(6, 20);
+        cash_db.insert(refund);
+
+        let installment = |due: chrono::DateTime<Utc>, status| {
+            let mut cash = Cash::new(None);
+            cash.set_cash(300);
+            let mut installment = Installment::new(1, 1, due);
+            installment.status = status;
+            cash.installment = Some(installment);
+            cash
+        };
+        cash_db.insert(installment(at(7, 1), InstallmentStatus::Overdue));
+        cash_db.insert(installment(at(7, 20), InstallmentStatus::Pending));
+        cash_db.insert(installment(at(8, 20), InstallmentStatus::Paid));
+        cash_db.insert(installment(at(9, 1), InstallmentStatus::Pending));
+
+        let forecast = forecast_revenue(&cash_db, 3, at(7, 15)).unwrap();
+        let months: Vec<u32> = forecast.iter().map(|m| m.month.month()).collect();
+        assert_eq!(months, vec![7, 8, 9]);
+        let installments: Vec<i64> = forecast.iter().map(|m| m.installment_income).collect();
+        assert_eq!(installments, vec![300, 0, 300]);
+        let trend: Vec<i64> = forecast.iter().map(|m| m.trend_income).collect();
+        assert_eq!(trend, vec![700, 800, 900]);
+        assert_eq!(forecast[0].total, 1000);
+
+        // 没有历史收入时趋势为 0
+        let forecast = forecast_revenue(&CashDatabase::new(), 2, at(7, 15)).unwrap();
+        assert!(forecast.iter().all(|m| m.total == 0));
+    }
+
+    #[test]
+    fn stats_growth_and_membership_metrics() {
+        let now = Utc.with_ymd_and_hms(2024, 5, 20, 12, 0, 0).unwrap();
+        let day = |month: u32, day: u32| Utc.with_ymd_and_hms(2024, month, day, 0, 0, 0).unwrap();
+        let mut student_db = StudentDatabase::new();
+
+        let mut new_member = Student::new();
+        new_member
+            .set_created_at(Some(day(5, 3)))
+            .set_membership_dates(Some(day(5, 3)), Some(day(8, 3)));
+        let mut expired = Student::new();
+        expired
+            .set_created_at(Some(day(1, 10)))
+            .set_membership_dates(Some(day(1, 10)), Some(day(5, 10)));
+        let mut expired_last_month = Student::new();
+        expired_last_month
+            .set_created_at(Some(day(1, 10)))
+            .set_membership_dates(Some(day(1, 10)), Some(day(4, 30)));
+        // 旧数据没有创建时间
+        let mut legacy = Student::new();
+        legacy.set_created_at(None);
+        // 本月晚些时候才到期的会员仍然有效
+        let mut expiring = Student::new();
+        expiring
+            .set_created_at(Some(day(4, 1)))
+            .set_membership_dates(Some(day(4, 1)), Some(day(5, 31)));
+        for student in [new_member, expired, expired_last_month, legacy, expiring] {
+            student_db.insert(student);
+        }
+
+        let stats = get_dashboard_stats_at(&student_db, &CashDatabase::new(), now).unwrap();
+        assert_eq!(stats.new_students_this_month, 1);
+        assert_eq!(stats.expired_memberships_this_month, 1);
+        assert_eq!(stats.active_members, 2);
+    }
+
+    #[test]
+    fn stats_outstanding_balances_per_student() {
+        let due = Utc.with_ymd_and_hms(2024, 7, 1, 0, 0, 0).unwrap();
+        let mut cash_db = CashDatabase::new();
+        let installment = |student_id: u64, amount: i64, status: InstallmentStatus| {
+            let mut cash = Cash::new(Some(student_id));
+            cash.set_cash(amount);
+            let mut installment = Installment::new(student_id, 1, due);
+            installment.status = status;
+            cash.installment = Some(installment);
+            cash
+        };
+        cash_db.insert(installment(1, 300, InstallmentStatus::Pending));
+        cash_db.insert(installment(1, 300, InstallmentStatus::Overdue));
+        cash_db.insert(installment(1, 300, InstallmentStatus::Paid));
+        cash_db.insert(installment(2, 500, InstallmentStatus::Cancelled));
+        cash_db.insert(installment(3, 200, InstallmentStatus::Pending));
+        let wallet = |student_id: u64, delta: i64| {
+            let mut cash = Cash::new(Some(student_id));
+            cash.wallet = Some(delta.into());
+            cash
+        };
+        // 学生 3 储值欠费 900，学生 4 余额为正
+        cash_db.insert(wallet(3, -900));
+        cash_db.insert(wallet(4, 1000));
+        cash_db.insert(wallet(4, -400));
+
+        let debtors = get_outstanding_balances(&cash_db).unwrap();
+        assert_eq!(
+            debtors,
+            vec![
+                OutstandingBalance {
+                    student_id: 3,
+                    unpaid_installments: 200,
+                    installment_count: 1,
+                    wallet_debt: 900,
+                    total: 1100,
+                },
+                OutstandingBalance {
+                    student_id: 1,
+                    unpaid_installments: 600,
+                    installment_count: 2,
+                    wallet_debt: 0,
+                    total: 600,
+                },
+            ]
+        );
+
+        let stats = get_dashboard_stats_at(&StudentDatabase::new(), &cash_db, due).unwrap();
+        assert_eq!(stats.total_outstanding, 1700);
+    }
 }
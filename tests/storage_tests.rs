@@ -0,0 +1,307 @@
+// 测试可插拔存储后端
+use qmx_backend_lib::database::Database;
+use qmx_backend_lib::error::{Error, Result};
+use qmx_backend_lib::storage::STUDENT_KEY;
+use qmx_backend_lib::{
+    CashBuilder, CashLogBackend, CoachBuilder, JsonFileBackend, KeyValueBackend, KeyValueStore, MemoryBackend,
+    MemoryStore, QmxManager, StorageBackend, StudentBuilder, StudentUpdater,
+};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use tempfile::TempDir;
+
+fn setup() -> TempDir {
+    let temp_dir = TempDir::new().unwrap();
+    std::env::set_current_dir(temp_dir.path()).unwrap();
+    let _ = std::fs::create_dir_all("data");
+    temp_dir
+}
+
+/// 统计整体保存与增量保存次数的后端
+#[derive(Default)]
+struct CountingBackend {
+    inner: MemoryBackend,
+    full_saves: AtomicUsize,
+    record_saves: AtomicUsize,
+}
+
+impl StorageBackend for CountingBackend {
+    fn load(&self) -> Result<Database> {
+        self.inner.load()
+    }
+
+    fn save(&self, db: &Database) -> Result<()> {
+        self.full_saves.fetch_add(1, Ordering::SeqCst);
+        self.inner.save(db)
+    }
+
+    fn save_student(&self, db: &Database, uid: u64) -> Result<()> {
+        self.record_saves.fetch_add(1, Ordering::SeqCst);
+        self.inner.save_student(db, uid)
+    }
+
+    fn save_cash(&self, db: &Database, uid: u64) -> Result<()> {
+        self.record_saves.fetch_add(1, Ordering::SeqCst);
+        self.inner.save_cash(db, uid)
+    }
+}
+
+/// 可以模拟写入失败的键值存储
+#[derive(Default)]
+struct FlakyStore {
+    inner: MemoryStore,
+    failing: AtomicBool,
+}
+
+impl KeyValueStore for FlakyStore {
+    async fn get(&self, key: &str) -> Result<Option<Vec<u8>>> {
+        self.inner.get(key).await
+    }
+
+    async fn set(&self, key: &str, value: Vec<u8>) -> Result<()> {
+        if self.failing.load(Ordering::SeqCst) {
+            return Err(Error::Other("存储不可用".to_string()));
+        }
+        self.inner.set(key, value).await
+    }
+}
+
+mod storage_backend_tests {
+    use super::*;
+
+    #[test]
+    fn test_memory_backend_incremental_saves() {
+        let _temp_dir = setup();
+        let backend = Arc::new(CountingBackend::default());
+        let manager = QmxManager::builder()
+            .auto_save(true)
+            .build()
+            .unwrap()
+            .with_backend(backend.clone())
+            .unwrap();
+
+        let student = manager
+            .create_student(StudentBuilder::new("后端学生"))
+            .unwrap();
+        let cash = manager
+            .record_cash(CashBuilder::new(500).student_id(student))
+            .unwrap();
+        manager
+            .update_student(student, StudentUpdater::new().name("改名"))
+            .unwrap();
+        assert_eq!(backend.record_saves.load(Ordering::SeqCst), 3);
+        assert_eq!(backend.full_saves.load(Ordering::SeqCst), 0);
+
+        let stored = backend.load().unwrap();
+        assert_eq!(stored.student.get(&student).unwrap().name(), Some("改名"));
+        assert_eq!(stored.cash.get(&cash).unwrap().cash.amount_minor, 500);
+
+        manager.delete_cash(cash).unwrap();
+        assert!(backend.load().unwrap().cash.get(&cash).is_none());
+
+        manager.save().unwrap();
+        assert_eq!(backend.full_saves.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn test_batch_operations_save_once() {
+        let _temp_dir = setup();
+        let backend = Arc::new(CountingBackend::default());
+        let manager = QmxManager::builder()
+            .auto_save(true)
+            .build()
+            .unwrap()
+            .with_backend(backend.clone())
+            .unwrap();
+
+        let uids = manager
+            .create_students(vec![
+                StudentBuilder::new("批量一"),
+                StudentBuilder::new("批量二"),
+                StudentBuilder::new("批量三"),
+            ])
+            .unwrap();
+        manager
+            .record_cash_batch(
+                uids.iter()
+                    .map(|&uid| CashBuilder::new(100).student_id(uid))
+                    .collect(),
+            )
+            .unwrap();
+
+        // 未覆盖批量方法的后端每批只整体保存一次
+        assert_eq!(backend.full_saves.load(Ordering::SeqCst), 2);
+        assert_eq!(backend.record_saves.load(Ordering::SeqCst), 0);
+        assert_eq!(backend.load().unwrap().student.len(), 3);
+    }
+
+    #[test]
+    fn test_json_backend_roundtrip() {
+        let _temp_dir = setup();
+        let backend = Arc::new(JsonFileBackend::new(
+            "data/backend_students.json",
+            "data/backend_cash.json",
+        ));
+        let manager = QmxManager::builder()
+            .auto_save(true)
+            .build()
+            .unwrap()
+            .with_backend(backend.clone())
+            .unwrap();
+        let uid = manager
+            .create_student(StudentBuilder::new("文件学生"))
+            .unwrap();
+
+        let reopened = QmxManager::builder()
+            .auto_save(false)
+            .build()
+            .unwrap()
+            .with_backend(backend)
+            .unwrap();
+        let student = reopened.get_student(uid).unwrap().unwrap();
+        assert_eq!(student.name(), Some("文件学生"));
+    }
+
+    #[test]
+    fn test_cash_log_backend_appends_and_compacts() {
+        let temp_dir = setup();
+        let path = |name: &str| temp_dir.path().join(name).to_str().unwrap().to_string();
+        let backend = Arc::new(
+            CashLogBackend::new(path("students.json"), path("cash.json")).compact_after(5),
+        );
+        let manager = QmxManager::builder()
+            .auto_save(true)
+            .build()
+            .unwrap()
+            .with_backend(backend.clone())
+            .unwrap();
+
+        let student = manager.create_student(StudentBuilder::new("日志学生")).unwrap();
+        let first = manager
+            .record_cash(CashBuilder::new(100).student_id(student))
+            .unwrap();
+        let second = manager.record_cash(CashBuilder::new(200)).unwrap();
+        manager.delete_cash(second).unwrap();
+        // 现金记录只追加日志，不写快照
+        assert_eq!(backend.log_len(), 3);
+        assert!(!std::path::Path::new(&path("cash.json")).exists());
+        let log = std::fs::read_to_string(backend.log_path()).unwrap();
+        assert_eq!(log.lines().count(), 3);
+
+        let reloaded = backend.load().unwrap();
+        assert_eq!(reloaded.cash.len(), 1);
+        assert_eq!(reloaded.cash.get(&first).unwrap().cash.amount_minor, 100);
+        assert_eq!(reloaded.cash.get_by_student(student).len(), 1);
+
+        // 达到压缩阈值后重写快照并清空日志
+        manager
+            .record_cash_batch(vec![CashBuilder::new(300), CashBuilder::new(400)])
+            .unwrap();
+        assert_eq!(backend.log_len(), 0);
+        assert_eq!(std::fs::read_to_string(backend.log_path()).unwrap(), "");
+        assert_eq!(backend.load().unwrap().cash.len(), 3);
+
+        // 整体保存同样压缩日志
+        manager.record_cash(CashBuilder::new(500)).unwrap();
+        assert_eq!(backend.log_len(), 1);
+        manager.save().unwrap();
+        assert_eq!(backend.log_len(), 0);
+        assert_eq!(backend.load().unwrap().cash.len(), 4);
+    }
+
+    #[test]
+    fn test_cash_log_backend_ignores_torn_last_line() {
+        let temp_dir = setup();
+        let path = |name: &str| temp_dir.path().join(name).to_str().unwrap().to_string();
+        let backend = Arc::new(CashLogBackend::new(path("students.json"), path("cash.json")));
+        let manager = QmxManager::builder()
+            .auto_save(true)
+            .build()
+            .unwrap()
+            .with_backend(backend.clone())
+            .unwrap();
+        let uid = manager.record_cash(CashBuilder::new(100)).unwrap();
+
+        // 模拟追加时崩溃留下的半行
+        let mut log = std::fs::OpenOptions::new()
+            .append(true)
+            .open(backend.log_path())
+            .unwrap();
+        std::io::Write::write_all(&mut log, b"{\"uid\":99,\"rec").unwrap();
+        let reloaded = backend.load().unwrap();
+        assert_eq!(reloaded.cash.len(), 1);
+        assert!(reloaded.cash.get(&uid).is_some());
+
+        // 中间的行损坏不能静默跳过
+        std::io::Write::write_all(&mut log, b"\n{}\n").unwrap();
+        assert!(backend.load().is_err());
+    }
+
+    #[tokio::test]
+    async fn test_with_storage_does_not_touch_file_system() {
+        let temp_dir = TempDir::new().unwrap();
+        std::env::set_current_dir(temp_dir.path()).unwrap();
+
+        let store = MemoryStore::new();
+        let backend = Arc::new(KeyValueBackend::open(store.clone()).await.unwrap());
+        let manager = QmxManager::builder()
+            .storage(backend.clone())
+            .auto_save(true)
+            .build()
+            .unwrap();
+        let first = manager
+            .create_student(StudentBuilder::new("浏览器学生"))
+            .unwrap();
+        manager
+            .record_cash(CashBuilder::new(600).student_id(first))
+            .unwrap();
+        manager.create_coach(CoachBuilder::new("教练")).unwrap();
+        manager.save().unwrap();
+        // 保存只更新内存中的副本，flush 后才写入存储
+        assert!(backend.is_dirty());
+        assert!(store.get(STUDENT_KEY).await.unwrap().is_none());
+        backend.flush().await.unwrap();
+        assert!(!backend.is_dirty());
+        assert!(store.get(STUDENT_KEY).await.unwrap().is_some());
+
+        // 重新打开后新记录的 UID 不与已有记录冲突
+        let backend = Arc::new(KeyValueBackend::open(store.clone()).await.unwrap());
+        let reopened = QmxManager::builder()
+            .storage(backend.clone())
+            .auto_save(true)
+            .build()
+            .unwrap();
+        assert_eq!(reopened.list_students().unwrap().len(), 1);
+        let second = reopened
+            .create_student(StudentBuilder::new("第二个"))
+            .unwrap();
+        assert!(second > first);
+        assert_eq!(backend.load().unwrap().student.len(), 2);
+        assert_eq!(backend.load().unwrap().cash.len(), 1);
+
+        assert_eq!(std::fs::read_dir(temp_dir.path()).unwrap().count(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_failed_flush_keeps_changes() {
+        let backend = Arc::new(KeyValueBackend::open(FlakyStore::default()).await.unwrap());
+        let manager = QmxManager::builder()
+            .storage(backend.clone())
+            .auto_save(true)
+            .build()
+            .unwrap();
+        manager
+            .create_student(StudentBuilder::new("离线学生"))
+            .unwrap();
+
+        backend.store().failing.store(true, Ordering::SeqCst);
+        assert!(backend.flush().await.is_err());
+        assert!(backend.is_dirty());
+
+        // 存储恢复后重试即可写入
+        backend.store().failing.store(false, Ordering::SeqCst);
+        backend.flush().await.unwrap();
+        assert!(!backend.is_dirty());
+        assert!(backend.store().get(STUDENT_KEY).await.unwrap().is_some());
+    }
+}
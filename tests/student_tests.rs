@@ -35,8 +35,8 @@ mod student_comprehensive_tests {
         let student = Student::new();
 
         assert_eq!(student.age(), None);
-        assert_eq!(student.name(), "未填写");
-        assert_eq!(student.phone(), "未填写");
+        assert_eq!(student.name(), None);
+        assert_eq!(student.phone(), None);
         assert_eq!(student.lesson_left(), None);
         assert_eq!(student.class(), &Class::Others);
         assert_eq!(student.subject(), &Subject::Others);
@@ -44,6 +44,31 @@ mod student_comprehensive_tests {
         assert_eq!(student.note(), "");
     }
 
+    #[test]
+    fn student_display_defaults_and_legacy_placeholder() {
+        let student = Student::new();
+        assert_eq!(student.display_name(), display_defaults().name);
+        assert_eq!(student.display_phone(), display_defaults().phone);
+
+        // 旧版本文件中的占位字符串读取为未填写
+        let legacy = r#"{"uid":1,"age":null,"name":"未填写","phone":"未填写","lesson_left":null,
+            "class":"Others","subject":"Others","rings":[],"note":""}"#;
+        let migrated: Student = serde_json::from_str(legacy).unwrap();
+        assert_eq!(migrated.name(), None);
+        assert_eq!(migrated.phone(), None);
+
+        let json = serde_json::to_string(&migrated).unwrap();
+        assert!(!json.contains("未填写"));
+
+        let mut named = Student::new();
+        named
+            .set_name("王五".to_string())
+            .set_phone("123".to_string());
+        named.clear_phone();
+        assert_eq!(named.display_name(), "王五");
+        assert_eq!(named.phone(), None);
+    }
+
     #[test]
     fn student_age_boundaries() {
         let mut student = Student::new();
@@ -69,17 +94,17 @@ mod student_comprehensive_tests {
         let mut student = Student::new();
 
         student.set_name("".to_string());
-        assert_eq!(student.name(), "");
+        assert_eq!(student.name(), Some(""));
 
         student.set_name("A".to_string());
-        assert_eq!(student.name(), "A");
+        assert_eq!(student.name(), Some("A"));
 
         let long_name = "A".repeat(1000);
         student.set_name(long_name.clone());
-        assert_eq!(student.name(), &long_name);
+        assert_eq!(student.name(), Some(long_name.as_str()));
 
         student.set_name("张三".to_string());
-        assert_eq!(student.name(), "张三");
+        assert_eq!(student.name(), Some("张三"));
     }
 
     #[test]
@@ -87,13 +112,13 @@ mod student_comprehensive_tests {
         let mut student = Student::new();
 
         student.set_phone("13800138000".to_string());
-        assert_eq!(student.phone(), "13800138000");
+        assert_eq!(student.phone(), Some("13800138000"));
 
         student.set_phone("".to_string());
-        assert_eq!(student.phone(), "");
+        assert_eq!(student.phone(), Some(""));
 
         student.set_phone("invalid-phone".to_string());
-        assert_eq!(student.phone(), "invalid-phone");
+        assert_eq!(student.phone(), Some("invalid-phone"));
     }
 
     #[test]
@@ -136,33 +161,96 @@ mod student_comprehensive_tests {
         assert_eq!(student.lesson_left(), Some(1));
     }
 
+    #[test]
+    fn student_consume_and_grant_lessons() {
+        let mut student = Student::new();
+        assert!(student.consume_lesson().is_err());
+        assert!(student.grant_lessons(5).is_err());
+        assert_eq!(student.lesson_left(), None);
+
+        student.set_class_with_lesson_init(Class::TenTry);
+        student.consume_lesson().unwrap();
+        assert_eq!(student.lesson_left(), Some(9));
+
+        student.set_lesson_left(0);
+        assert!(student.consume_lesson().is_err());
+        assert_eq!(student.lesson_left(), Some(0));
+
+        student.grant_lessons(3).unwrap();
+        assert_eq!(student.lesson_left(), Some(3));
+
+        student.set_lesson_left(u32::MAX);
+        assert!(student.grant_lessons(1).is_err());
+        assert_eq!(student.lesson_left(), Some(u32::MAX));
+    }
+
+    #[test]
+    fn student_class_policy_table() {
+        let mut policies = ClassPolicyTable::new();
+        assert_eq!(policies.get(&Class::TenTry), ClassPolicy::lessons(10));
+        assert_eq!(policies.get(&Class::Month), ClassPolicy::untracked());
+
+        // 带次数限制的月卡
+        policies.set(Class::Month, ClassPolicy::lessons(8));
+
+        let mut student = Student::new();
+        student.set_class_with_policy(Class::Month, &policies);
+        assert_eq!(student.class(), &Class::Month);
+        assert_eq!(student.lesson_left(), Some(8));
+
+        student.set_class_with_policy(Class::Year, &policies);
+        assert_eq!(student.lesson_left(), None);
+
+        student.set_class_with_policy(Class::TenTry, &policies);
+        assert_eq!(student.lesson_left(), Some(10));
+    }
+
     #[test]
     fn student_rings_operations() {
         let mut student = Student::new();
 
         assert!(student.rings().is_empty());
 
-        student.add_ring(9.5);
+        student.add_ring(9.5).unwrap();
         assert_eq!(student.rings().len(), 1);
         assert_eq!(student.rings()[0], 9.5);
 
-        student.add_ring(8.2);
-        student.add_ring(10.0);
+        student.add_ring(8.2).unwrap();
+        student.add_ring(10.0).unwrap();
         assert_eq!(student.rings().len(), 3);
         assert_eq!(student.rings()[1], 8.2);
         assert_eq!(student.rings()[2], 10.0);
 
-        student.add_ring(0.0);
+        student.add_ring(0.0).unwrap();
         assert_eq!(student.rings().len(), 4);
         assert_eq!(student.rings()[3], 0.0);
 
-        student.add_ring(-1.0);
-        assert_eq!(student.rings().len(), 5);
-        assert_eq!(student.rings()[4], -1.0);
+        // 负数和异常大的成绩会被拒绝
+        assert!(student.add_ring(-1.0).is_err());
+        assert!(student.add_ring(f64::MAX).is_err());
+        assert!(student.add_ring(f64::NAN).is_err());
+        assert_eq!(student.rings().len(), 4);
+
+        assert!(student.set_rings(vec![9.0, -5.0]).is_err());
+        assert_eq!(student.rings().len(), 4);
+
+        assert!(student.update_ring_at(0, f64::INFINITY).is_err());
+        assert_eq!(student.rings()[0], 9.5);
+    }
 
-        let large_score = f64::MAX;
-        student.add_ring(large_score);
-        assert_eq!(student.rings()[5], large_score);
+    #[test]
+    fn student_ring_rules() {
+        let configs = ScoringConfigs::default();
+        assert!(configs.range(&Subject::Shooting).contains(10.9));
+        assert!(!configs.range(&Subject::Shooting).contains(11.0));
+        assert!(configs.range(&Subject::Archery).contains(10.0));
+        assert!(!configs.range(&Subject::Archery).contains(10.5));
+        assert_eq!(configs.range(&Subject::Others), RingRange::PERCENTAGE);
+
+        let mut custom = ScoringConfigs::percentage();
+        custom.set_range(Subject::Others, RingRange::new(1.0, 5.0));
+        assert!(!custom.range(&Subject::Others).contains(0.5));
+        assert!(custom.range(&Subject::Shooting).contains(50.0));
     }
 
     #[test]
@@ -236,11 +324,13 @@ mod student_comprehensive_tests {
             .set_subject(Subject::Shooting)
             .set_note("Chained operations".to_string())
             .add_ring(9.0)
-            .add_ring(8.5);
+            .unwrap()
+            .add_ring(8.5)
+            .unwrap();
 
         assert_eq!(student.age(), Some(25));
-        assert_eq!(student.name(), "Chain Test");
-        assert_eq!(student.phone(), "12345678901");
+        assert_eq!(student.name(), Some("Chain Test"));
+        assert_eq!(student.phone(), Some("12345678901"));
         assert_eq!(student.class(), &Class::TenTry);
         assert_eq!(student.lesson_left(), Some(8));
         assert_eq!(student.subject(), &Subject::Shooting);
@@ -406,7 +496,9 @@ mod student_database_comprehensive_tests {
             .set_name("JSON Test".to_string())
             .set_age(Some(30))
             .add_ring(9.5)
-            .add_ring(8.0);
+            .unwrap()
+            .add_ring(8.0)
+            .unwrap();
 
         db.insert(student.clone());
 
@@ -419,7 +511,7 @@ mod student_database_comprehensive_tests {
         assert_eq!(deserialized_db.len(), 1);
 
         let retrieved = deserialized_db.get(&student.uid()).unwrap();
-        assert_eq!(retrieved.name(), "JSON Test");
+        assert_eq!(retrieved.name(), Some("JSON Test"));
         assert_eq!(retrieved.age(), Some(30));
         assert_eq!(retrieved.rings().len(), 2);
     }
@@ -454,7 +546,7 @@ mod student_database_comprehensive_tests {
         let mut collected_uids = Vec::new();
         for (uid, student) in db.iter() {
             collected_uids.push(*uid);
-            assert!(student.name().starts_with("Student"));
+            assert!(student.name().unwrap().starts_with("Student"));
         }
 
         expected_uids.sort();
@@ -475,7 +567,7 @@ mod student_database_comprehensive_tests {
 
         db.insert(student1);
         assert_eq!(db.len(), 1);
-        assert_eq!(db.get(&uid).unwrap().name(), "First");
+        assert_eq!(db.get(&uid).unwrap().name(), Some("First"));
 
         let mut student2 = Student::new();
         unsafe {
@@ -485,7 +577,39 @@ mod student_database_comprehensive_tests {
 
         db.insert(student2);
         assert_eq!(db.len(), 1);
-        assert_eq!(db.get(&uid).unwrap().name(), "Second");
+        assert_eq!(db.get(&uid).unwrap().name(), Some("Second"));
+    }
+
+    #[test]
+    fn student_database_indexes() {
+        let mut db = StudentDatabase::new();
+        let mut zhang = Student::new();
+        zhang.set_name("张三".to_string()).set_phone("138-0000-1234".to_string());
+        let zhang_uid = zhang.uid();
+        let mut namesake = Student::new();
+        namesake.set_name("张三".to_string());
+        let namesake_uid = namesake.uid();
+        db.insert_batch(vec![zhang, namesake]);
+
+        let uids = |students: Vec<&Student>| students.iter().map(|s| s.uid()).collect::<Vec<_>>();
+        assert_eq!(uids(db.find_by_name("张三")), vec![zhang_uid, namesake_uid]);
+        assert!(db.find_by_name("张").is_empty());
+        assert_eq!(uids(db.find_by_phone("13800001234")), vec![zhang_uid]);
+
+        db.update_batch(&[zhang_uid], |s| {
+            s.set_name("张小三".to_string()).set_phone("139 0000 5678".to_string());
+            true
+        });
+        assert_eq!(uids(db.find_by_name("张三")), vec![namesake_uid]);
+        assert_eq!(uids(db.find_by_name("张小三")), vec![zhang_uid]);
+        assert!(db.find_by_phone("13800001234").is_empty());
+        assert_eq!(uids(db.find_by_phone("139-0000-5678")), vec![zhang_uid]);
+
+        db.remove(&namesake_uid);
+        assert!(db.find_by_name("张三").is_empty());
+
+        let loaded = StudentDatabase::from_json(&db.json()).unwrap();
+        assert_eq!(uids(loaded.find_by_phone("13900005678")), vec![zhang_uid]);
     }
 }
 
@@ -611,18 +735,18 @@ mod student_query_tests {
         // Create students with different ring scores
         let mut student1 = Student::new();
         student1.set_name("Student 1".to_string());
-        student1.add_ring(8.5);
-        student1.add_ring(9.0);
+        student1.add_ring(8.5).unwrap();
+        student1.add_ring(9.0).unwrap();
 
         let mut student2 = Student::new();
         student2.set_name("Student 2".to_string());
-        student2.add_ring(7.5);
-        student2.add_ring(8.0);
+        student2.add_ring(7.5).unwrap();
+        student2.add_ring(8.0).unwrap();
 
         let mut student3 = Student::new();
         student3.set_name("Student 3".to_string());
-        student3.add_ring(9.5);
-        student3.add_ring(10.0);
+        student3.add_ring(9.5).unwrap();
+        student3.add_ring(10.0).unwrap();
 
         // Create a database with these students
         let mut db = StudentDatabase::new();
@@ -642,16 +766,16 @@ mod student_query_tests {
         // Create students with edge case scores
         let mut student1 = Student::new();
         student1.set_name("Student 1".to_string());
-        student1.add_ring(8.0); // Exactly at the lower bound
+        student1.add_ring(8.0).unwrap(); // Exactly at the lower bound
 
         let mut student2 = Student::new();
         student2.set_name("Student 2".to_string());
-        student2.add_ring(9.0); // Exactly at the upper bound
+        student2.add_ring(9.0).unwrap(); // Exactly at the upper bound
 
         let mut student3 = Student::new();
         student3.set_name("Student 3".to_string());
-        student3.add_ring(7.9); // Just below the lower bound
-        student3.add_ring(9.1); // Just above the upper bound
+        student3.add_ring(7.9).unwrap(); // Just below the lower bound
+        student3.add_ring(9.1).unwrap(); // Just above the upper bound
 
         let mut student4 = Student::new();
         student4.set_name("Student 4".to_string());
@@ -675,6 +799,60 @@ mod student_query_tests {
 mod student_file_operations_tests {
     use super::*;
 
+    #[test]
+    fn student_database_salvage_truncated_file() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = dir.path().join("student_database.json");
+        let path = path.to_str().unwrap();
+
+        let mut db = StudentDatabase::new();
+        let mut good = Student::new();
+        good.set_name("完好".to_string());
+        let mut bad = Student::new();
+        bad.set_name("损坏".to_string());
+        let mut cut = Student::new();
+        cut.set_name("截断".to_string());
+        db.insert(good.clone());
+        db.insert(bad.clone());
+        db.insert(cut.clone());
+
+        // 破坏第二条记录的字段类型，并截断最后一条记录
+        let json = db
+            .json()
+            .replace("\"name\":\"损坏\"", "\"name\":12345");
+        let truncated = &json[..json.len() - 30];
+        std::fs::write(path, truncated).unwrap();
+
+        assert!(StudentDatabase::read_from(path).is_err());
+        let (salvaged, report) = StudentDatabase::salvage_from(path).unwrap();
+        assert_eq!(salvaged.len(), 1);
+        assert_eq!(salvaged.get(&good.uid()).unwrap().name(), Some("完好"));
+        assert_eq!(report.recovered, 1);
+        assert_eq!(report.lost, 2);
+        assert_eq!(report.errors.len(), 2);
+
+        let quarantine = report.quarantine_path.unwrap();
+        let content = std::fs::read_to_string(quarantine).unwrap();
+        assert!(content.contains("12345"));
+        assert!(content.contains("截断"));
+    }
+
+    #[test]
+    fn student_database_salvage_valid_file() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = dir.path().join("student_database.json");
+        let path = path.to_str().unwrap();
+
+        let mut db = StudentDatabase::new();
+        db.insert(Student::new());
+        db.save_to(path).unwrap();
+
+        let (salvaged, report) = StudentDatabase::salvage_from(path).unwrap();
+        assert_eq!(salvaged.len(), 1);
+        assert_eq!(report.lost, 0);
+        assert!(report.quarantine_path.is_none());
+    }
+
     #[test]
     fn student_database_save_and_load() {
         let test_path = "./data/test_student_db.json";
@@ -690,7 +868,10 @@ mod student_file_operations_tests {
 
         let loaded_db = StudentDatabase::read_from(test_path).unwrap();
         assert_eq!(loaded_db.len(), 1);
-        assert_eq!(loaded_db.get(&student.uid()).unwrap().name(), "Save Test");
+        assert_eq!(
+            loaded_db.get(&student.uid()).unwrap().name(),
+            Some("Save Test")
+        );
 
         let _ = std::fs::remove_file(test_path);
     }
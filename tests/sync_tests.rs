@@ -0,0 +1,296 @@
+// 测试多台电脑之间的数据同步，需要启用 sync feature
+#![cfg(feature = "sync")]
+
+use chrono::{Duration, TimeZone, Utc};
+use qmx_backend_lib::encryption;
+use qmx_backend_lib::sync::{
+    FileTransport, HttpTransport, MAX_RESPONSE_LEN, SyncClient, SyncTransport,
+};
+use qmx_backend_lib::{
+    AutoSave, CashBuilder, DeletePolicy, EncryptionKey, Error, FixedClock, QmxManager, StudentBuilder, StudentUpdater,
+};
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::TcpListener;
+use std::sync::Arc;
+use tempfile::TempDir;
+
+fn manager(temp_dir: &TempDir, name: &str) -> QmxManager {
+    QmxManager::builder()
+        .data_dir(temp_dir.path().join(name))
+        .auto_save(AutoSave::Off)
+        .build()
+        .unwrap()
+}
+
+fn keyed_manager(temp_dir: &TempDir, name: &str) -> QmxManager {
+    QmxManager::builder()
+        .data_dir(temp_dir.path().join(name))
+        .auto_save(AutoSave::Off)
+        .encryption_key(EncryptionKey::new([9; 32]))
+        .build()
+        .unwrap()
+}
+
+fn file_client(temp_dir: &TempDir, name: &str) -> SyncClient {
+    SyncClient::new(
+        FileTransport::new(temp_dir.path().join("shared/qmx-sync.json")),
+        temp_dir.path().join(name).join("sync_state.json"),
+    )
+}
+
+/// 支持 GET/PUT、ETag 和 Basic 认证的最小 WebDAV 服务，返回文档地址
+fn serve_webdav(authorization: &'static str) -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    std::thread::spawn(move || {
+        // 文档版本号和内容
+        let mut document: Option<(u32, Vec<u8>)> = None;
+        for stream in listener.incoming() {
+            let mut stream = stream.unwrap();
+            let mut reader = BufReader::new(stream.try_clone().unwrap());
+            let mut line = String::new();
+            reader.read_line(&mut line).unwrap();
+            let method = line.split_whitespace().next().unwrap().to_string();
+            let mut headers = Vec::new();
+            loop {
+                line.clear();
+                reader.read_line(&mut line).unwrap();
+                match line.trim_end().split_once(':') {
+                    Some((name, value)) => {
+                        headers.push((name.to_ascii_lowercase(), value.trim().to_string()))
+                    }
+                    None => break,
+                }
+            }
+            let header = |name: &str| {
+                headers
+                    .iter()
+                    .find(|(key, _)| key == name)
+                    .map(|(_, value)| value.clone())
+            };
+            let length: usize = header("content-length").unwrap_or_default().parse().unwrap_or(0);
+            let mut body = vec![0; length];
+            reader.read_exact(&mut body).unwrap();
+
+            let etag = |version: u32| format!("\"v{}\"", version);
+            let (status, etag_header, response) =
+                if header("authorization").as_deref() != Some(authorization) {
+                    (401, None, Vec::new())
+                } else if method == "GET" {
+                    match &document {
+                        Some((version, bytes)) => (200, Some(etag(*version)), bytes.clone()),
+                        None => (404, None, Vec::new()),
+                    }
+                } else {
+                    let current = document.as_ref().map(|(version, _)| etag(*version));
+                    let allowed = match (header("if-match"), header("if-none-match")) {
+                        (Some(expected), _) => current.as_ref() == Some(&expected),
+                        (None, Some(_)) => current.is_none(),
+                        (None, None) => true,
+                    };
+                    if allowed {
+                        let version = document.as_ref().map_or(1, |(version, _)| version + 1);
+                        document = Some((version, body));
+                        (201, Some(etag(version)), Vec::new())
+                    } else {
+                        (412, None, Vec::new())
+                    }
+                };
+            let mut head = format!(
+                "HTTP/1.1 {} X\r\nContent-Length: {}\r\nConnection: close\r\n",
+                status,
+                response.len()
+            );
+            if let Some(etag) = etag_header {
+                head.push_str(&format!("ETag: {}\r\n", etag));
+            }
+            head.push_str("\r\n");
+            stream.write_all(head.as_bytes()).unwrap();
+            stream.write_all(&response).unwrap();
+        }
+    });
+    format!("http://{}/dav/qmx-sync.json", addr)
+}
+
+/// 对每个请求都返回固定响应头的服务，返回文档地址
+fn serve_head(head: String) -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    std::thread::spawn(move || {
+        for stream in listener.incoming() {
+            let mut stream = stream.unwrap();
+            let mut reader = BufReader::new(stream.try_clone().unwrap());
+            let mut line = String::new();
+            while reader.read_line(&mut line).unwrap() > 2 {
+                line.clear();
+            }
+            let _ = stream.write_all(head.as_bytes());
+        }
+    });
+    format!("http://{}/qmx-sync.json", addr)
+}
+
+mod sync_tests {
+    use super::*;
+
+    #[test]
+    fn test_changes_and_deletions_propagate() {
+        let temp_dir = TempDir::new().unwrap();
+        let front_desk = manager(&temp_dir, "front_desk");
+        let laptop = manager(&temp_dir, "laptop");
+        let front_client = file_client(&temp_dir, "front_desk");
+        let laptop_client = file_client(&temp_dir, "laptop");
+
+        let uid = front_desk.create_student(StudentBuilder::new("张三")).unwrap();
+        let cash_uid = front_desk
+            .record_cash(CashBuilder::new(1000).student_id(uid))
+            .unwrap();
+        let report = front_desk.sync(&front_client).unwrap();
+        assert_eq!((report.pushed.students, report.pushed.cash), (1, 1));
+
+        let report = laptop.sync(&laptop_client).unwrap();
+        assert_eq!((report.pulled.students, report.pulled.cash), (1, 1));
+        assert_eq!(laptop.get_student(uid).unwrap().unwrap().name(), Some("张三"));
+        assert_eq!(laptop.get_cash(cash_uid).unwrap().unwrap().cash.amount_minor, 1000);
+
+        // 没有修改时再次同步不产生任何变化
+        let report = laptop.sync(&laptop_client).unwrap();
+        assert_eq!(report, Default::default());
+
+        assert!(laptop.delete_cash(cash_uid).unwrap());
+        let report = laptop.sync(&laptop_client).unwrap();
+        assert_eq!(report.pushed.cash, 1);
+        let report = front_desk.sync(&front_client).unwrap();
+        assert_eq!(report.pulled.cash, 1);
+        assert!(front_desk.get_cash(cash_uid).unwrap().is_none());
+
+        // 从远端拉取的修改可以撤销，撤销后下次同步作为本地修改写回
+        assert_eq!(front_desk.undo_last(1).unwrap(), 1);
+        assert!(front_desk.get_cash(cash_uid).unwrap().is_some());
+        assert_eq!(front_desk.sync(&front_client).unwrap().pushed.cash, 1);
+        laptop.sync(&laptop_client).unwrap();
+        assert!(laptop.get_cash(cash_uid).unwrap().is_some());
+    }
+
+    #[test]
+    fn test_newer_student_wins() {
+        let temp_dir = TempDir::new().unwrap();
+        let start = Utc.with_ymd_and_hms(2025, 3, 1, 9, 0, 0).unwrap();
+        let front_clock = Arc::new(FixedClock::new(start));
+        let laptop_clock = Arc::new(FixedClock::new(start));
+        let front_desk = manager(&temp_dir, "front_desk").with_clock(front_clock.clone());
+        let laptop = manager(&temp_dir, "laptop").with_clock(laptop_clock.clone());
+        let front_client = file_client(&temp_dir, "front_desk");
+        let laptop_client = file_client(&temp_dir, "laptop");
+
+        let uid = front_desk.create_student(StudentBuilder::new("张三")).unwrap();
+        front_desk.sync(&front_client).unwrap();
+        laptop.sync(&laptop_client).unwrap();
+
+        // 两边离线修改同一名学生，笔记本上的修改较晚
+        front_clock.advance(Duration::hours(1));
+        front_desk
+            .update_student(uid, StudentUpdater::new().name("张三（前台）".to_string()))
+            .unwrap();
+        laptop_clock.advance(Duration::hours(2));
+        laptop
+            .update_student(uid, StudentUpdater::new().name("张三（笔记本）".to_string()))
+            .unwrap();
+
+        assert_eq!(front_desk.sync(&front_client).unwrap().pushed.students, 1);
+        let report = laptop.sync(&laptop_client).unwrap();
+        assert_eq!((report.pushed.students, report.pulled.students), (1, 0));
+        assert_eq!(front_desk.sync(&front_client).unwrap().pulled.students, 1);
+        for manager in [&front_desk, &laptop] {
+            let student = manager.get_student(uid).unwrap().unwrap();
+            assert_eq!(student.name(), Some("张三（笔记本）"));
+        }
+    }
+
+    #[test]
+    fn test_rejects_concurrent_remote_change() {
+        let temp_dir = TempDir::new().unwrap();
+        let transport = FileTransport::new(temp_dir.path().join("qmx-sync.json"));
+        transport.store(b"{\"format_version\":1}", None).unwrap();
+        let fetched = transport.fetch().unwrap().unwrap();
+
+        assert!(matches!(transport.store(b"{}", None), Err(Error::State(_))));
+        transport
+            .store(b"{\"format_version\":1,\"students\":{}}", Some(&fetched))
+            .unwrap();
+        assert!(matches!(
+            transport.store(b"{}", Some(&fetched)),
+            Err(Error::State(_))
+        ));
+
+        // 其他电脑持有写入锁时不写入
+        let fetched = transport.fetch().unwrap().unwrap();
+        std::fs::write(temp_dir.path().join("qmx-sync.json.lock"), b"").unwrap();
+        assert!(matches!(
+            transport.store(b"{}", Some(&fetched)),
+            Err(Error::State(_))
+        ));
+        std::fs::remove_file(temp_dir.path().join("qmx-sync.json.lock")).unwrap();
+        transport.store(b"{}", Some(&fetched)).unwrap();
+        assert!(!temp_dir.path().join("qmx-sync.json.lock").exists());
+    }
+
+    #[test]
+    fn test_sync_over_webdav() {
+        let temp_dir = TempDir::new().unwrap();
+        // "qmx:secret" 的 Base64 编码
+        let url = serve_webdav("Basic cW14OnNlY3JldA==");
+        let client = |name: &str| {
+            SyncClient::new(
+                HttpTransport::new(&url).unwrap().basic_auth("qmx", "secret"),
+                temp_dir.path().join(name).join("sync_state.json"),
+            )
+        };
+        // 明文 HTTP 上同步需要加密密钥
+        assert!(matches!(
+            manager(&temp_dir, "plain").sync(&client("plain")),
+            Err(Error::Encryption(_))
+        ));
+        let front_desk = keyed_manager(&temp_dir, "front_desk");
+        let laptop = keyed_manager(&temp_dir, "laptop");
+
+        let uid = front_desk.create_student(StudentBuilder::new("李四")).unwrap();
+        front_desk.sync(&client("front_desk")).unwrap();
+        let uploaded = HttpTransport::new(&url)
+            .unwrap()
+            .basic_auth("qmx", "secret")
+            .fetch()
+            .unwrap()
+            .unwrap();
+        assert!(encryption::is_encrypted(&uploaded.bytes));
+        laptop.sync(&client("laptop")).unwrap();
+        assert_eq!(laptop.get_student(uid).unwrap().unwrap().name(), Some("李四"));
+
+        assert!(laptop.delete_student(uid, DeletePolicy::Restrict).unwrap());
+        laptop.sync(&client("laptop")).unwrap();
+        assert_eq!(front_desk.sync(&client("front_desk")).unwrap().pulled.students, 1);
+        assert!(front_desk.get_student(uid).unwrap().is_none());
+
+        let wrong_password = HttpTransport::new(&url).unwrap().basic_auth("qmx", "wrong");
+        assert!(matches!(wrong_password.fetch(), Err(Error::PermissionDenied(_))));
+        assert!(matches!(
+            HttpTransport::new("https://example.com/qmx-sync.json"),
+            Err(Error::InvalidInput(_))
+        ));
+    }
+
+    #[test]
+    fn test_rejects_oversized_response() {
+        let too_long = MAX_RESPONSE_LEN + 1;
+        for head in [
+            format!("HTTP/1.1 200 OK\r\nContent-Length: {}\r\n\r\n", too_long),
+            format!(
+                "HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n{:x}\r\n",
+                too_long
+            ),
+        ] {
+            let transport = HttpTransport::new(&serve_head(head)).unwrap();
+            assert!(matches!(transport.fetch(), Err(Error::InvalidInput(_))));
+        }
+    }
+}
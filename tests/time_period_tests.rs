@@ -0,0 +1,59 @@
+// 测试 TimePeriod::bounds 的时间边界计算
+use chrono::{FixedOffset, TimeZone, Utc};
+use qmx_backend_lib::TimePeriod;
+
+mod time_period_bounds_tests {
+    use super::*;
+
+    #[test]
+    fn test_today_bounds_utc() {
+        let now = Utc.with_ymd_and_hms(2024, 2, 29, 15, 0, 0).unwrap();
+        let (start, end) = TimePeriod::Today.bounds(now, &Utc).unwrap();
+        assert_eq!(start, Utc.with_ymd_and_hms(2024, 2, 29, 0, 0, 0).unwrap());
+        assert_eq!(end, Utc.with_ymd_and_hms(2024, 2, 29, 23, 59, 59).unwrap());
+    }
+
+    #[test]
+    fn test_week_month_year_bounds() {
+        // 2024-01-03 是星期三
+        let now = Utc.with_ymd_and_hms(2024, 1, 3, 8, 0, 0).unwrap();
+
+        let (start, end) = TimePeriod::ThisWeek.bounds(now, &Utc).unwrap();
+        assert_eq!(start, Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap());
+        assert_eq!(end, now);
+
+        let (start, _) = TimePeriod::ThisMonth.bounds(now, &Utc).unwrap();
+        assert_eq!(start, Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap());
+
+        let (start, _) = TimePeriod::ThisYear.bounds(now, &Utc).unwrap();
+        assert_eq!(start, Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap());
+
+        // 跨年的一周：2025-01-01 是星期三，周一在上一年
+        let now = Utc.with_ymd_and_hms(2025, 1, 1, 12, 0, 0).unwrap();
+        let (start, _) = TimePeriod::ThisWeek.bounds(now, &Utc).unwrap();
+        assert_eq!(start, Utc.with_ymd_and_hms(2024, 12, 30, 0, 0, 0).unwrap());
+    }
+
+    #[test]
+    fn test_bounds_with_timezone() {
+        // UTC 2024-03-31 20:00 在东八区已经是 4 月 1 日
+        let tz = FixedOffset::east_opt(8 * 3600).unwrap();
+        let now = Utc.with_ymd_and_hms(2024, 3, 31, 20, 0, 0).unwrap();
+
+        let (start, end) = TimePeriod::ThisMonth.bounds(now, &tz).unwrap();
+        assert_eq!(start, Utc.with_ymd_and_hms(2024, 3, 31, 16, 0, 0).unwrap());
+        assert_eq!(end, now);
+
+        let (start, end) = TimePeriod::Today.bounds(now, &tz).unwrap();
+        assert_eq!(start, Utc.with_ymd_and_hms(2024, 3, 31, 16, 0, 0).unwrap());
+        assert_eq!(end, Utc.with_ymd_and_hms(2024, 4, 1, 15, 59, 59).unwrap());
+    }
+
+    #[test]
+    fn test_custom_bounds_passthrough() {
+        let start = Utc.with_ymd_and_hms(2023, 6, 1, 0, 0, 0).unwrap();
+        let end = Utc.with_ymd_and_hms(2023, 6, 30, 0, 0, 0).unwrap();
+        let period = TimePeriod::Custom { start, end };
+        assert_eq!(period.bounds(Utc::now(), &Utc).unwrap(), (start, end));
+    }
+}
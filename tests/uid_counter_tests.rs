@@ -0,0 +1,79 @@
+// 测试数据库文件中内嵌的 UID 计数器
+use qmx_backend_lib::cash::{CASH_UID_COUNTER, Cash, CashDatabase};
+use qmx_backend_lib::student::{STUDENT_UID_COUNTER, Student, StudentDatabase};
+use qmx_backend_lib::QmxManager;
+use serde_json::Value;
+use std::fs;
+use std::sync::atomic::Ordering;
+use tempfile::TempDir;
+
+mod uid_counter_tests {
+    use super::*;
+
+    #[test]
+    fn test_serialized_databases_embed_next_uid() {
+        let mut students = StudentDatabase::new();
+        let student = Student::new();
+        let uid = student.uid();
+        students.insert(student);
+        let json: Value = serde_json::from_str(&students.json()).unwrap();
+        assert!(json["next_uid"].as_u64().unwrap() > uid);
+        assert!(json["student_data"][uid.to_string()].is_object());
+
+        let mut cash_db = CashDatabase::new();
+        let cash = Cash::new(None);
+        let cash_uid = cash.uid;
+        cash_db.insert(cash);
+        let json: Value = serde_json::to_value(&cash_db).unwrap();
+        assert!(json["next_uid"].as_u64().unwrap() > cash_uid);
+    }
+
+    #[test]
+    fn test_parsing_does_not_advance_counters() {
+        // 解析备份或导入的数据不影响全局计数器
+        let students =
+            StudentDatabase::from_json(r#"{"student_data": {}, "next_uid": 9000000}"#).unwrap();
+        assert!(students.is_empty());
+        assert!(STUDENT_UID_COUNTER.load(Ordering::SeqCst) < 9000000);
+
+        let cash_db = CashDatabase::from_json(r#"{"cash_data": {}, "next_uid": 9000000}"#).unwrap();
+        assert!(cash_db.is_empty());
+        assert!(CASH_UID_COUNTER.load(Ordering::SeqCst) < 9000000);
+
+        // 再次保存时仍保留文件中的 next_uid
+        let json: Value = serde_json::to_value(&students).unwrap();
+        assert_eq!(json["next_uid"].as_u64(), Some(9000000));
+    }
+
+    #[test]
+    fn test_manager_load_advances_counters() {
+        // 只复制了数据文件：管理器加载时计数器按文件中的 next_uid 推进
+        let dir = TempDir::new().unwrap();
+        let student_path = dir.path().join("student_database.json");
+        let cash_path = dir.path().join("cash_database.json");
+        fs::write(&student_path, r#"{"student_data": {}, "next_uid": 500000}"#).unwrap();
+        fs::write(&cash_path, r#"{"cash_data": {}, "next_uid": 700000}"#).unwrap();
+
+        QmxManager::builder()
+            .database_files(&student_path, &cash_path)
+            .read_only(true)
+            .build()
+            .unwrap();
+        assert!(STUDENT_UID_COUNTER.load(Ordering::SeqCst) >= 500000);
+        assert!(Student::new().uid() >= 500000);
+        assert!(CASH_UID_COUNTER.load(Ordering::SeqCst) >= 700000);
+        assert!(Cash::new(None).uid >= 700000);
+    }
+
+    #[test]
+    fn test_legacy_files_derive_counter_from_records() {
+        // 旧版本数据文件没有 next_uid，计数器至少越过已有的最大 UID
+        let mut record = serde_json::to_value(Student::new()).unwrap();
+        record["uid"] = 900000.into();
+        let json = serde_json::json!({ "student_data": { "900000": record } });
+        let students = StudentDatabase::from_json(&json.to_string()).unwrap();
+        assert_eq!(students.len(), 1);
+        students.sync_uid_counter();
+        assert!(Student::new().uid() > 900000);
+    }
+}
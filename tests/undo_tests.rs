@@ -0,0 +1,154 @@
+// 测试 QmxManager 的撤销功能
+use chrono::{Duration, TimeZone, Utc};
+use qmx_backend_lib::cash::{InstallmentStatus, PaymentFrequency};
+use qmx_backend_lib::{
+    AuditAction, CashBuilder, CashUpdater, DeletePolicy, FixedClock, InstallmentPlanBuilder,
+    MemoryBackend, QmxManager, StudentBuilder, StudentUpdater,
+};
+use std::sync::Arc;
+use tempfile::TempDir;
+
+fn setup() -> TempDir {
+    let temp_dir = TempDir::new().unwrap();
+    std::env::set_current_dir(temp_dir.path()).unwrap();
+    let _ = std::fs::create_dir_all("data");
+    temp_dir
+}
+
+mod undo_tests {
+    use super::*;
+
+    #[test]
+    fn test_undo_delete_restores_student() {
+        let _temp_dir = setup();
+        let manager = QmxManager::builder().auto_save(false).build().unwrap();
+
+        let uid = manager
+            .create_student(StudentBuilder::new("误删学生").age(15).phone("13800138000"))
+            .unwrap();
+        let original = manager.get_student(uid).unwrap().unwrap();
+        assert!(manager.delete_student(uid, DeletePolicy::Restrict).unwrap());
+        assert!(manager.get_student(uid).unwrap().is_none());
+
+        assert_eq!(manager.undo_last(1).unwrap(), 1);
+        let restored = manager.get_student(uid).unwrap().unwrap();
+        assert_eq!(
+            serde_json::to_value(&restored).unwrap(),
+            serde_json::to_value(&original).unwrap()
+        );
+
+        // 撤销会写入审计日志
+        let log = manager.get_audit_log(uid).unwrap();
+        assert_eq!(log.last().unwrap().action, AuditAction::Create);
+    }
+
+    #[test]
+    fn test_undo_multiple_operations_in_reverse_order() {
+        let _temp_dir = setup();
+        let manager = QmxManager::builder().auto_save(false).build().unwrap();
+
+        let uid = manager
+            .create_student(StudentBuilder::new("多次修改").age(10))
+            .unwrap();
+        manager
+            .update_student(uid, StudentUpdater::new().age(Some(11)))
+            .unwrap();
+        manager
+            .update_student(uid, StudentUpdater::new().name("改名"))
+            .unwrap();
+        // 没有实际变化的更新不计入操作日志
+        manager
+            .update_student(uid, StudentUpdater::new().age(Some(11)))
+            .unwrap();
+
+        assert_eq!(manager.undo_last(1).unwrap(), 1);
+        let student = manager.get_student(uid).unwrap().unwrap();
+        assert_eq!(student.name(), Some("多次修改"));
+        assert_eq!(student.age(), Some(11));
+
+        // 超出日志长度时只撤销已有的操作，包括最初的创建
+        assert_eq!(manager.undo_last(10).unwrap(), 2);
+        assert!(manager.get_student(uid).unwrap().is_none());
+        assert_eq!(manager.undo_last(1).unwrap(), 0);
+    }
+
+    #[test]
+    fn test_undo_bulk_cash_operations() {
+        let _temp_dir = setup();
+        let start = Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap();
+        let clock = Arc::new(FixedClock::new(start));
+        let manager = QmxManager::builder()
+            .auto_save(false)
+            .build()
+            .unwrap()
+            .with_clock(clock.clone());
+
+        let plan = manager
+            .create_installment_plan(InstallmentPlanBuilder::new(
+                3000,
+                3,
+                PaymentFrequency::Monthly,
+                start + Duration::days(1),
+            ))
+            .unwrap();
+        clock.advance(Duration::days(90));
+        assert_eq!(manager.mark_overdue_installments().unwrap().len(), 3);
+
+        // 一次标记的全部逾期记录作为一次操作撤销
+        assert_eq!(manager.undo_last(1).unwrap(), 1);
+        for uid in &plan.cash_uids {
+            let cash = manager.get_cash(*uid).unwrap().unwrap();
+            assert_eq!(cash.installment.unwrap().status, InstallmentStatus::Pending);
+        }
+
+        assert_eq!(manager.undo_last(1).unwrap(), 1);
+        for uid in &plan.cash_uids {
+            assert!(manager.get_cash(*uid).unwrap().is_none());
+        }
+        // 撤销创建时计划一并删除，不留下没有分期记录的计划
+        assert!(
+            manager
+                .get_installment_plan(plan.plan_id)
+                .unwrap()
+                .is_none()
+        );
+    }
+
+    #[test]
+    fn test_undo_cash_update_persists_with_backend() {
+        let _temp_dir = setup();
+        let backend = Arc::new(MemoryBackend::new());
+        let manager = QmxManager::builder()
+            .auto_save(true)
+            .build()
+            .unwrap()
+            .with_backend(backend.clone())
+            .unwrap();
+
+        let uid = manager.record_cash(CashBuilder::new(500)).unwrap();
+        manager
+            .update_cash(uid, CashUpdater::new().amount(800))
+            .unwrap();
+        assert_eq!(manager.undo_last(1).unwrap(), 1);
+
+        let reloaded = QmxManager::builder()
+            .auto_save(false)
+            .build()
+            .unwrap()
+            .with_backend(backend)
+            .unwrap();
+        assert_eq!(reloaded.get_cash(uid).unwrap().unwrap().cash.amount_minor, 500);
+    }
+
+    #[test]
+    fn test_recover_clears_journal() {
+        let _temp_dir = setup();
+        let manager = QmxManager::builder().auto_save(true).build().unwrap();
+
+        manager
+            .create_student(StudentBuilder::new("已保存学生"))
+            .unwrap();
+        manager.recover().unwrap();
+        assert_eq!(manager.undo_last(1).unwrap(), 0);
+    }
+}
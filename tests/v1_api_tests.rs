@@ -20,7 +20,7 @@ mod student_v1_tests {
             .set_class(Class::TenTry)
             .set_subject(Subject::Shooting);
 
-        assert_eq!(student.name(), "张三");
+        assert_eq!(student.name(), Some("张三"));
         assert_eq!(student.age(), Some(18));
         assert_eq!(student.class(), &Class::TenTry);
         assert_eq!(student.subject(), &Subject::Shooting);
@@ -38,7 +38,7 @@ mod student_v1_tests {
         assert_eq!(db.len(), 1);
 
         let retrieved = db.get(&uid).unwrap();
-        assert_eq!(retrieved.name(), "李四");
+        assert_eq!(retrieved.name(), Some("李四"));
         assert_eq!(retrieved.age(), Some(20));
 
         let removed = db.remove(&uid);
@@ -74,7 +74,7 @@ mod cash_v1_tests {
         cash.set_note(Some("学费收入".to_string()));
 
         assert_eq!(cash.student_id, Some(1));
-        assert_eq!(cash.cash, 1000);
+        assert_eq!(cash.cash.amount_minor, 1000);
         assert_eq!(cash.note(), Some("学费收入"));
     }
 
@@ -90,7 +90,7 @@ mod cash_v1_tests {
         assert_eq!(db.len(), 1);
 
         let retrieved = db.get(&uid).unwrap();
-        assert_eq!(retrieved.cash, 2000);
+        assert_eq!(retrieved.cash.amount_minor, 2000);
         assert_eq!(retrieved.student_id, Some(1));
 
         let removed = db.remove(&uid);
@@ -206,10 +206,10 @@ mod database_v1_tests {
             assert_eq!(cash_db.len(), 1);
 
             let student = student_db.iter().next().unwrap().1;
-            assert_eq!(student.name(), "测试学生");
+            assert_eq!(student.name(), Some("测试学生"));
 
             let cash = cash_db.iter().next().unwrap().1;
-            assert_eq!(cash.cash, 500);
+            assert_eq!(cash.cash.amount_minor, 500);
         }
     }
 }
@@ -227,7 +227,9 @@ mod stats_v1_tests {
         student
             .set_name("统计测试".to_string())
             .add_ring(85.5)
-            .add_ring(92.0);
+            .unwrap()
+            .add_ring(92.0)
+            .unwrap();
         let student_id = student.uid();
         student_db.insert(student);
 
@@ -266,8 +268,10 @@ fn test_v1_api_integration() {
         .set_age(Some(19))
         .set_class(Class::Month)
         .set_subject(Subject::Archery)
-        .add_ring(88.0)
-        .add_ring(91.5);
+        .add_ring(8.0)
+        .unwrap()
+        .add_ring(9.0)
+        .unwrap();
 
     let student_id = student.uid();
     db.student.insert(student);
@@ -292,6 +296,6 @@ fn test_v1_api_integration() {
     assert_eq!(reloaded_db.cash.len(), 1);
 
     let reloaded_student = reloaded_db.student.get(&student_id).unwrap();
-    assert_eq!(reloaded_student.name(), "集成测试学生");
+    assert_eq!(reloaded_student.name(), Some("集成测试学生"));
     assert_eq!(reloaded_student.rings().len(), 2);
 }
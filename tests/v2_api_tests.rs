@@ -1,12 +1,19 @@
 // V2 API 测试集合
 // 包含所有使用新 QmxManager API 的测试
 
-use chrono::{Duration, Utc};
-use qmx_backend_lib::student::{Class, Subject};
+use chrono::{Duration, NaiveDate, TimeZone, Utc};
+use qmx_backend_lib::cash::{
+    Cash, CashDatabase, InstallmentStatus, PaymentFrequency, PaymentMethod, RemainderStrategy,
+};
+use qmx_backend_lib::student::{
+    Class, Guardian, MembershipTier, Student, StudentDatabase, Subject,
+};
 use qmx_backend_lib::{
-    CashBuilder, CashQuery, CashUpdater, MembershipStatus, QmxManager, StudentBuilder,
-    StudentQuery, StudentUpdater, TimePeriod,
+    CashBuilder, CashQuery, CashSortKey, CashUpdater, CustomValue, DeletePolicy, DuplicateGuard, DuplicatePolicy,
+    FixedClock, GroupBy, GroupKey, InstallmentPlanBuilder, MembershipStatus, QmxManager, ScoreTrend, SortOrder,
+    StudentBuilder, StudentQuery, StudentSortKey, StudentUpdater, TimePeriod,
 };
+use std::sync::Arc;
 use tempfile::TempDir;
 
 mod qmx_manager_tests {
@@ -21,7 +28,7 @@ mod qmx_manager_tests {
         // 确保data目录存在
         let _ = std::fs::create_dir_all("data");
 
-        let manager = QmxManager::new(true).unwrap();
+        let manager = QmxManager::builder().auto_save(true).build().unwrap();
 
         // 测试基本功能
         let students = manager.list_students().unwrap();
@@ -41,22 +48,86 @@ mod qmx_manager_tests {
         let _ = std::fs::create_dir_all("data");
 
         // 先创建一些数据文件
-        let initial_manager = QmxManager::new(true).unwrap();
+        let initial_manager = QmxManager::builder().auto_save(true).build().unwrap();
         let _student_id = initial_manager
             .create_student(StudentBuilder::new("初始学生").age(18).class(Class::TenTry))
             .unwrap();
 
         // 从路径加载
-        let manager = QmxManager::from_path(
-            "./data/student_database.json",
-            "./data/cash_database.json",
-            false,
-        )
-        .unwrap();
+        let manager = QmxManager::builder()
+            .database_files("./data/student_database.json", "./data/cash_database.json")
+            .auto_save(false)
+            .build()
+            .unwrap();
 
         let students = manager.list_students().unwrap();
         assert_eq!(students.len(), 1);
-        assert_eq!(students[0].name(), "初始学生");
+        assert_eq!(students[0].name(), Some("初始学生"));
+    }
+
+    #[test]
+    fn test_save_skips_untouched_databases() {
+        let temp_dir = TempDir::new().unwrap();
+        let student_path = temp_dir.path().join("students.json");
+        let cash_path = temp_dir.path().join("cash.json");
+        let student_path = student_path.to_str().unwrap();
+        let cash_path = cash_path.to_str().unwrap();
+
+        StudentDatabase::new().save_to(student_path).unwrap();
+        CashDatabase::new().save_to(cash_path).unwrap();
+
+        let manager = QmxManager::builder()
+            .database_files(student_path, cash_path)
+            .auto_save(false)
+            .build()
+            .unwrap();
+        let uid = manager.create_student(StudentBuilder::new("张三")).unwrap();
+        assert!(manager.has_unsaved_changes());
+        manager.save().unwrap();
+        assert!(!manager.has_unsaved_changes());
+
+        // 用标记内容替换学生文件，只修改现金数据时学生文件不应被重写
+        std::fs::write(student_path, "marker").unwrap();
+        manager
+            .record_cash(CashBuilder::new(500).student_id(uid))
+            .unwrap();
+        manager.save().unwrap();
+        assert_eq!(std::fs::read_to_string(student_path).unwrap(), "marker");
+        assert_eq!(CashDatabase::read_from(cash_path).unwrap().len(), 1);
+
+        // 没有修改时两个文件都不重写
+        std::fs::write(cash_path, "marker").unwrap();
+        manager.save().unwrap();
+        assert_eq!(std::fs::read_to_string(cash_path).unwrap(), "marker");
+
+        // 撤销也会标记修改
+        manager.undo_last(1).unwrap();
+        assert!(manager.has_unsaved_changes());
+        manager.save().unwrap();
+        assert!(CashDatabase::read_from(cash_path).unwrap().is_empty());
+        assert_eq!(std::fs::read_to_string(student_path).unwrap(), "marker");
+    }
+
+    #[test]
+    fn test_auto_save_only_rewrites_changed_database() {
+        let temp_dir = TempDir::new().unwrap();
+        let student_path = temp_dir.path().join("students.json");
+        let cash_path = temp_dir.path().join("cash.json");
+        let student_path = student_path.to_str().unwrap();
+        let cash_path = cash_path.to_str().unwrap();
+        StudentDatabase::new().save_to(student_path).unwrap();
+        CashDatabase::new().save_to(cash_path).unwrap();
+
+        let manager = QmxManager::builder()
+            .database_files(student_path, cash_path)
+            .auto_save(true)
+            .build()
+            .unwrap();
+        std::fs::write(student_path, "marker").unwrap();
+        manager.record_cash(CashBuilder::new(800)).unwrap();
+        assert_eq!(std::fs::read_to_string(student_path).unwrap(), "marker");
+        assert_eq!(CashDatabase::read_from(cash_path).unwrap().len(), 1);
+        assert!(!manager.has_unsaved_changes());
     }
 }
 
@@ -72,7 +143,7 @@ mod student_builder_tests {
         // 确保data目录存在
         let _ = std::fs::create_dir_all("data");
 
-        let manager = QmxManager::new(true).unwrap();
+        let manager = QmxManager::builder().auto_save(true).build().unwrap();
 
         let student_id = manager
             .create_student(
@@ -85,9 +156,9 @@ mod student_builder_tests {
             .unwrap();
 
         let student = manager.get_student(student_id).unwrap().unwrap();
-        assert_eq!(student.name(), "张三");
+        assert_eq!(student.name(), Some("张三"));
         assert_eq!(student.age(), Some(16));
-        assert_eq!(student.phone(), "13800138000");
+        assert_eq!(student.phone(), Some("13800138000"));
         assert_eq!(student.class(), &Class::TenTry);
         assert_eq!(student.subject(), &Subject::Shooting);
         assert_eq!(student.note(), "优秀学生");
@@ -102,7 +173,7 @@ mod student_builder_tests {
         // 确保data目录存在
         let _ = std::fs::create_dir_all("data");
 
-        let manager = QmxManager::new(true).unwrap();
+        let manager = QmxManager::builder().auto_save(true).build().unwrap();
 
         let start = Utc::now();
         let end = start + Duration::days(365);
@@ -126,18 +197,49 @@ mod student_builder_tests {
         // 确保data目录存在
         let _ = std::fs::create_dir_all("data");
 
-        let manager = QmxManager::new(true).unwrap();
+        let manager = QmxManager::builder().auto_save(true).build().unwrap();
 
         let student_id = manager
             .create_student(StudentBuilder::new("最小学生").age(15))
             .unwrap();
 
         let student = manager.get_student(student_id).unwrap().unwrap();
-        assert_eq!(student.name(), "最小学生");
+        assert_eq!(student.name(), Some("最小学生"));
         assert_eq!(student.age(), Some(15));
-        assert_eq!(student.phone(), "未填写");
+        assert_eq!(student.phone(), None);
         assert_eq!(student.class(), &Class::Others);
         assert_eq!(student.subject(), &Subject::Others);
+
+        // 未填写的字段不会被占位文本搜索命中
+        let found = manager
+            .search_students(StudentQuery::new().name_contains("未填写"))
+            .unwrap();
+        assert!(found.is_empty());
+    }
+
+    #[test]
+    fn test_student_builder_without_age() {
+        let temp_dir = TempDir::new().unwrap();
+        let temp_path = temp_dir.path();
+        std::env::set_current_dir(temp_path).unwrap();
+
+        // 确保data目录存在
+        let _ = std::fs::create_dir_all("data");
+
+        let manager = QmxManager::builder().auto_save(true).build().unwrap();
+
+        let student_id = manager
+            .create_student(StudentBuilder::new("学生4"))
+            .unwrap();
+        let student = manager.get_student(student_id).unwrap().unwrap();
+        assert_eq!(student.name(), Some("学生4"));
+        assert_eq!(student.age(), None);
+
+        #[allow(deprecated)]
+        let legacy = StudentBuilder::new_with_age("学生5", 12);
+        let legacy_id = manager.create_student(legacy).unwrap();
+        let legacy_student = manager.get_student(legacy_id).unwrap().unwrap();
+        assert_eq!(legacy_student.age(), Some(12));
     }
 }
 
@@ -153,7 +255,7 @@ mod student_updater_tests {
         // 确保data目录存在
         let _ = std::fs::create_dir_all("data");
 
-        let manager = QmxManager::new(true).unwrap();
+        let manager = QmxManager::builder().auto_save(true).build().unwrap();
 
         let student_id = manager
             .create_student(StudentBuilder::new("更新测试").age(16))
@@ -172,7 +274,7 @@ mod student_updater_tests {
 
         let student = manager.get_student(student_id).unwrap().unwrap();
         assert_eq!(student.age(), Some(17));
-        assert_eq!(student.phone(), "新电话");
+        assert_eq!(student.phone(), Some("新电话"));
         assert_eq!(student.class(), &Class::Month);
         assert_eq!(student.note(), "更新后的备注");
     }
@@ -186,7 +288,7 @@ mod student_updater_tests {
         // 确保data目录存在
         let _ = std::fs::create_dir_all("data");
 
-        let manager = QmxManager::new(true).unwrap();
+        let manager = QmxManager::builder().auto_save(true).build().unwrap();
 
         let student_id = manager
             .create_student(StudentBuilder::new("成绩测试").age(18))
@@ -220,6 +322,111 @@ mod student_updater_tests {
             .unwrap();
         let student = manager.get_student(student_id).unwrap().unwrap();
         assert_eq!(student.rings(), &[91.0, 92.0]);
+
+        // 无效成绩应返回校验错误
+        let err = manager
+            .update_student(student_id, StudentUpdater::new().add_ring(-3.0))
+            .unwrap_err();
+        assert_eq!(err.code(), "validation_failed");
+        let student = manager.get_student(student_id).unwrap().unwrap();
+        assert_eq!(student.rings(), &[91.0, 92.0]);
+    }
+
+    #[test]
+    fn test_student_updater_lessons() {
+        let temp_dir = TempDir::new().unwrap();
+        let temp_path = temp_dir.path();
+        std::env::set_current_dir(temp_path).unwrap();
+
+        // 确保data目录存在
+        let _ = std::fs::create_dir_all("data");
+
+        let manager = QmxManager::builder().auto_save(false).build().unwrap();
+
+        let student_id = manager
+            .create_student(StudentBuilder::new("课时学生").class(Class::TenTry))
+            .unwrap();
+
+        manager
+            .update_student(
+                student_id,
+                StudentUpdater::new().consume_lesson().consume_lesson(),
+            )
+            .unwrap();
+        let student = manager.get_student(student_id).unwrap().unwrap();
+        assert_eq!(student.lesson_left(), Some(8));
+
+        manager
+            .update_student(student_id, StudentUpdater::new().grant_lessons(10))
+            .unwrap();
+        let student = manager.get_student(student_id).unwrap().unwrap();
+        assert_eq!(student.lesson_left(), Some(18));
+
+        let month_id = manager
+            .create_student(StudentBuilder::new("月卡学生").class(Class::Month))
+            .unwrap();
+        let result = manager.update_student(month_id, StudentUpdater::new().consume_lesson());
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_failed_update_leaves_student_unchanged() {
+        let temp_dir = TempDir::new().unwrap();
+        let manager = QmxManager::builder()
+            .data_dir(temp_dir.path())
+            .auto_save(false)
+            .build()
+            .unwrap();
+        let student_id = manager
+            .create_student(StudentBuilder::new("月卡学生").class(Class::Month))
+            .unwrap();
+        let audit_before = manager.get_audit_log(student_id).unwrap().len();
+
+        // 改名在前、扣课时失败在后，整个更新都不生效
+        let result = manager.update_student(
+            student_id,
+            StudentUpdater::new().name("新名字").consume_lesson(),
+        );
+        assert!(result.is_err());
+        let student = manager.get_student(student_id).unwrap().unwrap();
+        assert_eq!(student.name(), Some("月卡学生"));
+        assert_eq!(manager.get_audit_log(student_id).unwrap().len(), audit_before);
+
+        // 失败的更新没有进入操作日志，撤销的是创建学生
+        assert_eq!(manager.undo_last(1).unwrap(), 1);
+        assert!(manager.get_student(student_id).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_updater_returns_field_changes() {
+        let temp_dir = TempDir::new().unwrap();
+        let temp_path = temp_dir.path();
+        std::env::set_current_dir(temp_path).unwrap();
+
+        // 确保data目录存在
+        let _ = std::fs::create_dir_all("data");
+
+        let manager = QmxManager::builder().auto_save(false).build().unwrap();
+
+        let student_id = manager
+            .create_student(StudentBuilder::new("原名").phone("123"))
+            .unwrap();
+        let changes = manager
+            .update_student(student_id, StudentUpdater::new().name("新名").phone("123"))
+            .unwrap();
+        assert_eq!(changes.len(), 1);
+        assert_eq!(changes[0].field, "name");
+        assert_eq!(changes[0].old, serde_json::json!("原名"));
+        assert_eq!(changes[0].new, serde_json::json!("新名"));
+
+        let cash_id = manager.record_cash(CashBuilder::new(100)).unwrap();
+        let changes = manager
+            .update_cash(cash_id, CashUpdater::new().amount(250))
+            .unwrap();
+        assert_eq!(changes.len(), 1);
+        assert_eq!(changes[0].field, "cash");
+        assert_eq!(changes[0].old, serde_json::json!(100));
+        assert_eq!(changes[0].new, serde_json::json!(250));
     }
 
     #[test]
@@ -231,7 +438,7 @@ mod student_updater_tests {
         // 确保data目录存在
         let _ = std::fs::create_dir_all("data");
 
-        let manager = QmxManager::new(true).unwrap();
+        let manager = QmxManager::builder().auto_save(true).build().unwrap();
 
         let student_id = manager
             .create_student(StudentBuilder::new("会员更新").age(19))
@@ -250,6 +457,16 @@ mod student_updater_tests {
         let student = manager.get_student(student_id).unwrap().unwrap();
         assert_eq!(student.membership_start_date(), Some(start));
         assert_eq!(student.membership_end_date(), Some(end));
+
+        // 开始时间晚于结束时间应被拒绝
+        let err = manager
+            .update_student(
+                student_id,
+                StudentUpdater::new().membership(Some(end), Some(start)),
+            )
+            .unwrap_err();
+        assert_eq!(err.code(), "membership_invalid");
+        assert_eq!(err.numeric_code(), 2005);
     }
 }
 
@@ -265,7 +482,7 @@ mod cash_builder_tests {
         // 确保data目录存在
         let _ = std::fs::create_dir_all("data");
 
-        let manager = QmxManager::new(true).unwrap();
+        let manager = QmxManager::builder().auto_save(true).build().unwrap();
 
         let student_id = manager
             .create_student(StudentBuilder::new("现金测试").age(18))
@@ -280,7 +497,7 @@ mod cash_builder_tests {
             .unwrap();
 
         let cash = manager.get_cash(cash_id).unwrap().unwrap();
-        assert_eq!(cash.cash, 1500);
+        assert_eq!(cash.cash.amount_minor, 1500);
         assert_eq!(cash.student_id, Some(student_id));
         assert_eq!(cash.note(), Some("学费收入"));
     }
@@ -294,17 +511,237 @@ mod cash_builder_tests {
         // 确保data目录存在
         let _ = std::fs::create_dir_all("data");
 
-        let manager = QmxManager::new(true).unwrap();
+        let manager = QmxManager::builder().auto_save(true).build().unwrap();
 
         let cash_id = manager
             .record_cash(CashBuilder::new(-200).note("设备采购"))
             .unwrap();
 
         let cash = manager.get_cash(cash_id).unwrap().unwrap();
-        assert_eq!(cash.cash, -200);
+        assert_eq!(cash.cash.amount_minor, -200);
         assert_eq!(cash.student_id, None);
         assert_eq!(cash.note(), Some("设备采购"));
     }
+    #[test]
+    fn test_duplicate_cash_detection() {
+        let temp_dir = TempDir::new().unwrap();
+        let temp_path = temp_dir.path();
+        std::env::set_current_dir(temp_path).unwrap();
+
+        // 确保data目录存在
+        let _ = std::fs::create_dir_all("data");
+
+        let manager = QmxManager::builder()
+            .auto_save(false)
+            .build()
+            .unwrap()
+            .with_duplicate_guard(DuplicateGuard {
+                window: Duration::seconds(5),
+                policy: DuplicatePolicy::Reject,
+            });
+        let student = manager.create_student(StudentBuilder::new("张三")).unwrap();
+
+        let first = manager
+            .record_cash(CashBuilder::new(500).student_id(student))
+            .unwrap();
+
+        // 双击产生的重复记录被拒绝
+        let err = manager
+            .record_cash(CashBuilder::new(500).student_id(student))
+            .unwrap_err();
+        assert_eq!(err.code(), "validation_failed");
+
+        // 不同金额不算重复
+        manager
+            .record_cash(CashBuilder::new(600).student_id(student))
+            .unwrap();
+
+        // 强制写入后可以检测到重复
+        let second = manager
+            .record_cash(CashBuilder::new(500).student_id(student).force())
+            .unwrap();
+        let duplicates = manager
+            .detect_duplicate_cash(Duration::seconds(5))
+            .unwrap();
+        assert_eq!(duplicates, vec![(first, second)]);
+    }
+
+    #[test]
+    fn test_rejected_duplicate_does_not_consume_uid() {
+        let temp_dir = TempDir::new().unwrap();
+        let manager = QmxManager::builder()
+            .data_dir(temp_dir.path())
+            .auto_save(false)
+            .build()
+            .unwrap()
+            .with_id_namespace(7)
+            .unwrap()
+            .with_duplicate_guard(DuplicateGuard {
+                window: Duration::seconds(5),
+                policy: DuplicatePolicy::Reject,
+            });
+        let student = manager.create_student(StudentBuilder::new("张三")).unwrap();
+        let first = manager
+            .record_cash(CashBuilder::new(500).student_id(student))
+            .unwrap();
+        for _ in 0..3 {
+            assert!(
+                manager
+                    .record_cash(CashBuilder::new(500).student_id(student))
+                    .is_err()
+            );
+        }
+
+        // 被拒绝的重复记录没有占用 UID
+        let next = manager
+            .record_cash(CashBuilder::new(600).student_id(student))
+            .unwrap();
+        assert_eq!(next, first + 1);
+    }
+
+    #[test]
+    fn test_create_installment_plan() {
+        let temp_dir = TempDir::new().unwrap();
+        let temp_path = temp_dir.path();
+        std::env::set_current_dir(temp_path).unwrap();
+
+        // 确保data目录存在
+        let _ = std::fs::create_dir_all("data");
+
+        let manager = QmxManager::builder().auto_save(false).build().unwrap();
+
+        let student_id = manager.create_student(StudentBuilder::new("分期学员")).unwrap();
+        let first_due = Utc::now() + Duration::days(30);
+        let plan = manager
+            .create_installment_plan(
+                InstallmentPlanBuilder::new(1000, 3, PaymentFrequency::Monthly, first_due)
+                    .student_id(student_id)
+                    .remainder_strategy(RemainderStrategy::FirstPays),
+            )
+            .unwrap();
+        assert_eq!(plan.cash_uids.len(), 3);
+
+        let records: Vec<_> = plan
+            .cash_uids
+            .iter()
+            .map(|&uid| manager.get_cash(uid).unwrap().unwrap())
+            .collect();
+        let amounts: Vec<_> = records.iter().map(|c| c.cash.amount_minor).collect();
+        assert_eq!(amounts, vec![334, 333, 333]);
+        for (k, record) in records.iter().enumerate() {
+            let installment = record.installment.as_ref().unwrap();
+            assert_eq!(installment.plan_id, plan.plan_id);
+            assert_eq!(installment.current_installment, k as u32 + 1);
+            assert_eq!(installment.status, InstallmentStatus::Pending);
+            assert_eq!(
+                installment.due_date,
+                PaymentFrequency::Monthly.nth_due(first_due, k as u32)
+            );
+            assert_eq!(record.student_id, Some(student_id));
+        }
+
+        let err = manager
+            .create_installment_plan(InstallmentPlanBuilder::new(
+                1000,
+                0,
+                PaymentFrequency::Weekly,
+                first_due,
+            ))
+            .unwrap_err();
+        assert_eq!(err.code(), "invalid_input");
+    }
+
+    #[test]
+    fn test_refund_cash() {
+        let temp_dir = TempDir::new().unwrap();
+        let temp_path = temp_dir.path();
+        std::env::set_current_dir(temp_path).unwrap();
+
+        // 确保data目录存在
+        let _ = std::fs::create_dir_all("data");
+
+        let manager = QmxManager::builder().auto_save(false).build().unwrap();
+        let student = manager.create_student(StudentBuilder::new("张三")).unwrap();
+
+        let original = manager
+            .record_cash(CashBuilder::new(1000).student_id(student))
+            .unwrap();
+        let refund = manager
+            .refund_cash(original, 300, Some("部分退款".to_string()))
+            .unwrap();
+
+        let record = manager.get_cash(refund).unwrap().unwrap();
+        assert_eq!(record.cash.amount_minor, -300);
+        assert_eq!(record.refund_of, Some(original));
+        assert_eq!(record.student_id, Some(student));
+
+        // 累计退款不能超过原金额
+        let err = manager.refund_cash(original, 800, None).unwrap_err();
+        assert_eq!(err.code(), "validation_failed");
+        manager.refund_cash(original, 700, None).unwrap();
+        assert!(manager.refund_cash(original, 1, None).is_err());
+
+        // 退款记录本身和支出记录都不能退款
+        assert_eq!(
+            manager.refund_cash(refund, 100, None).unwrap_err().code(),
+            "state"
+        );
+        assert_eq!(
+            manager.refund_cash(original, 0, None).unwrap_err().code(),
+            "invalid_input"
+        );
+
+        let stats = manager.get_financial_stats(TimePeriod::ThisYear).unwrap();
+        assert_eq!(stats.total_income, 1000);
+        assert_eq!(stats.total_expense, 1000);
+        assert_eq!(stats.net_income, 0);
+    }
+
+    #[test]
+    fn test_cash_student_must_exist() {
+        let temp_dir = TempDir::new().unwrap();
+        std::env::set_current_dir(temp_dir.path()).unwrap();
+        let _ = std::fs::create_dir_all("data");
+
+        let manager = QmxManager::builder().auto_save(false).build().unwrap();
+        let student = manager.create_student(StudentBuilder::new("张三")).unwrap();
+        let missing = student + 1000;
+
+        let err = manager
+            .record_cash(CashBuilder::new(500).student_id(missing))
+            .unwrap_err();
+        assert_eq!(err.code(), "not_found");
+        assert!(manager
+            .record_cash_batch(vec![
+                CashBuilder::new(500).student_id(student),
+                CashBuilder::new(600).student_id(missing),
+            ])
+            .is_err());
+        assert!(manager.search_cash(CashQuery::new()).unwrap().is_empty());
+
+        // 导入历史记录时可以跳过检查
+        let imported = manager
+            .record_cash(CashBuilder::new(500).student_id(missing).allow_dangling())
+            .unwrap();
+
+        let uid = manager
+            .record_cash(CashBuilder::new(800).student_id(student))
+            .unwrap();
+        let err = manager
+            .update_cash(uid, CashUpdater::new().student_id(Some(missing)))
+            .unwrap_err();
+        assert_eq!(err.code(), "not_found");
+        assert_eq!(manager.get_cash(uid).unwrap().unwrap().student_id, Some(student));
+        manager
+            .update_cash(uid, CashUpdater::new().student_id(None))
+            .unwrap();
+        manager
+            .update_cash(
+                imported,
+                CashUpdater::new().student_id(Some(missing + 1)).allow_dangling(),
+            )
+            .unwrap();
+    }
 }
 
 mod student_query_tests {
@@ -319,7 +756,7 @@ mod student_query_tests {
         // 确保data目录存在
         let _ = std::fs::create_dir_all("data");
 
-        let manager = QmxManager::new(true).unwrap();
+        let manager = QmxManager::builder().auto_save(true).build().unwrap();
 
         // 创建不同年龄的学生
         let _id1 = manager
@@ -340,7 +777,7 @@ mod student_query_tests {
             .search_students(StudentQuery::new().age_range(16, 20))
             .unwrap();
         assert_eq!(students.len(), 1);
-        assert_eq!(students[0].name(), "学生2");
+        assert_eq!(students[0].name(), Some("学生2"));
         assert_eq!(students[0].age(), Some(18));
 
         // 查询年龄在15-22之间的学生（应该包含3个有年龄的学生）
@@ -359,7 +796,7 @@ mod student_query_tests {
         // 确保data目录存在
         let _ = std::fs::create_dir_all("data");
 
-        let manager = QmxManager::new(true).unwrap();
+        let manager = QmxManager::builder().auto_save(true).build().unwrap();
 
         manager
             .create_student(
@@ -403,7 +840,7 @@ mod student_query_tests {
             )
             .unwrap();
         assert_eq!(tentry_archery.len(), 1);
-        assert_eq!(tentry_archery[0].name(), "TenTry射箭");
+        assert_eq!(tentry_archery[0].name(), Some("TenTry射箭"));
     }
 
     #[test]
@@ -415,7 +852,7 @@ mod student_query_tests {
         // 确保data目录存在
         let _ = std::fs::create_dir_all("data");
 
-        let manager = QmxManager::new(true).unwrap();
+        let manager = QmxManager::builder().auto_save(true).build().unwrap();
 
         let start = Utc::now();
         let end = start + Duration::days(30);
@@ -434,27 +871,23 @@ mod student_query_tests {
             .search_students(StudentQuery::new().has_membership(true))
             .unwrap();
         assert_eq!(members.len(), 1);
-        assert_eq!(members[0].name(), "会员学生");
+        assert_eq!(members[0].name(), Some("会员学生"));
 
         let non_members = manager
             .search_students(StudentQuery::new().has_membership(false))
             .unwrap();
         assert_eq!(non_members.len(), 1);
-        assert_eq!(non_members[0].name(), "普通学生");
+        assert_eq!(non_members[0].name(), Some("普通学生"));
 
         let active_members = manager
             .search_students(StudentQuery::new().membership_active_at(Utc::now()))
             .unwrap();
         assert_eq!(active_members.len(), 1);
-        assert_eq!(active_members[0].name(), "会员学生");
+        assert_eq!(active_members[0].name(), Some("会员学生"));
     }
-}
-
-mod cash_query_tests {
-    use super::*;
 
     #[test]
-    fn test_cash_query_student_id() {
+    fn test_student_query_pagination() {
         let temp_dir = TempDir::new().unwrap();
         let temp_path = temp_dir.path();
         std::env::set_current_dir(temp_path).unwrap();
@@ -462,39 +895,36 @@ mod cash_query_tests {
         // 确保data目录存在
         let _ = std::fs::create_dir_all("data");
 
-        let manager = QmxManager::new(true).unwrap();
+        let manager = QmxManager::builder().auto_save(false).build().unwrap();
 
-        let student1_id = manager
-            .create_student(StudentBuilder::new("学生1").age(18))
-            .unwrap();
-        let student2_id = manager
-            .create_student(StudentBuilder::new("学生2").age(19))
-            .unwrap();
+        for i in 0..5 {
+            manager
+                .create_student(StudentBuilder::new(format!("分页学生{}", i)))
+                .unwrap();
+        }
 
-        manager
-            .record_cash(CashBuilder::new(1000).student_id(student1_id))
-            .unwrap();
-        manager
-            .record_cash(CashBuilder::new(1500).student_id(student1_id))
-            .unwrap();
-        manager
-            .record_cash(CashBuilder::new(2000).student_id(student2_id))
+        let page = manager
+            .search_students_paged(StudentQuery::new().offset(1).limit(2))
             .unwrap();
+        assert_eq!(page.total, 5);
+        assert_eq!(page.items.len(), 2);
+        assert_eq!(page.items[0].name(), Some("分页学生1"));
+        assert!(page.has_more());
 
-        let student1_cash = manager
-            .search_cash(CashQuery::new().student_id(student1_id))
+        let last = manager
+            .search_students_paged(StudentQuery::new().offset(4).limit(2))
             .unwrap();
-        assert_eq!(student1_cash.len(), 2);
+        assert_eq!(last.items.len(), 1);
+        assert!(!last.has_more());
 
-        let student2_cash = manager
-            .search_cash(CashQuery::new().student_id(student2_id))
+        let plain = manager
+            .search_students(StudentQuery::new().limit(3))
             .unwrap();
-        assert_eq!(student2_cash.len(), 1);
-        assert_eq!(student2_cash[0].cash, 2000);
+        assert_eq!(plain.len(), 3);
     }
 
     #[test]
-    fn test_cash_query_amount_range() {
+    fn test_student_query_order_by() {
         let temp_dir = TempDir::new().unwrap();
         let temp_path = temp_dir.path();
         std::env::set_current_dir(temp_path).unwrap();
@@ -502,31 +932,45 @@ mod cash_query_tests {
         // 确保data目录存在
         let _ = std::fs::create_dir_all("data");
 
-        let manager = QmxManager::new(true).unwrap();
+        let manager = QmxManager::builder().auto_save(false).build().unwrap();
 
-        manager.record_cash(CashBuilder::new(500)).unwrap();
-        manager.record_cash(CashBuilder::new(1500)).unwrap();
-        manager.record_cash(CashBuilder::new(2500)).unwrap();
-        manager.record_cash(CashBuilder::new(-200)).unwrap();
+        manager
+            .create_student(StudentBuilder::new("乙").age(20))
+            .unwrap();
+        manager.create_student(StudentBuilder::new("甲")).unwrap();
+        manager
+            .create_student(StudentBuilder::new("丙").age(12))
+            .unwrap();
 
-        let medium_amounts = manager
-            .search_cash(CashQuery::new().amount_range(1000, 2000))
+        let by_age = manager
+            .search_students(
+                StudentQuery::new().order_by(StudentSortKey::Age, SortOrder::Descending),
+            )
             .unwrap();
-        assert_eq!(medium_amounts.len(), 1);
-        assert_eq!(medium_amounts[0].cash, 1500);
+        let ages: Vec<_> = by_age.iter().map(|s| s.age()).collect();
+        // 没有年龄的学生排在最后
+        assert_eq!(ages, vec![Some(20), Some(12), None]);
 
-        let positive_amounts = manager
-            .search_cash(CashQuery::new().amount_range(0, i64::MAX))
+        let by_age = manager
+            .search_students(
+                StudentQuery::new().order_by(StudentSortKey::Age, SortOrder::Ascending),
+            )
             .unwrap();
-        assert_eq!(positive_amounts.len(), 3);
-    }
-}
+        let ages: Vec<_> = by_age.iter().map(|s| s.age()).collect();
+        assert_eq!(ages, vec![Some(12), Some(20), None]);
 
-mod statistics_tests {
-    use super::*;
+        let first_by_uid_desc = manager
+            .search_students(
+                StudentQuery::new()
+                    .order_by(StudentSortKey::Uid, SortOrder::Descending)
+                    .limit(1),
+            )
+            .unwrap();
+        assert_eq!(first_by_uid_desc[0].name(), Some("丙"));
+    }
 
     #[test]
-    fn test_dashboard_stats_v2() {
+    fn test_guardians_and_guardian_phone_query() {
         let temp_dir = TempDir::new().unwrap();
         let temp_path = temp_dir.path();
         std::env::set_current_dir(temp_path).unwrap();
@@ -534,46 +978,889 @@ mod statistics_tests {
         // 确保data目录存在
         let _ = std::fs::create_dir_all("data");
 
-        let manager = QmxManager::new(true).unwrap();
-
-        // 创建学生和现金记录
-        let student_id = manager
-            .create_student(StudentBuilder::new("统计学生").age(18))
+        let manager = QmxManager::builder().auto_save(false).build().unwrap();
+        let sibling_a = manager
+            .create_student(
+                StudentBuilder::new("哥哥")
+                    .guardian(Guardian::new("张妈妈", "139-0000-1111", "母亲"))
+                    .guardian(Guardian::new("张爸爸", "13900002222", "父亲")),
+            )
             .unwrap();
-
+        let sibling_b = manager.create_student(StudentBuilder::new("弟弟")).unwrap();
         manager
             .update_student(
-                student_id,
-                StudentUpdater::new()
-                    .add_ring(85.0)
-                    .add_ring(90.0)
-                    .add_ring(88.0),
+                sibling_b,
+                StudentUpdater::new().add_guardian(Guardian::new("张妈妈", "13900001111", "母亲")),
             )
             .unwrap();
 
-        manager
-            .record_cash(CashBuilder::new(2000).student_id(student_id))
+        let student = manager.get_student(sibling_a).unwrap().unwrap();
+        assert_eq!(student.guardians().len(), 2);
+        assert_eq!(student.guardians()[0].relation, "母亲");
+
+        // 电话号码比较忽略连字符
+        let children = manager
+            .search_students(StudentQuery::new().guardian_phone("13900001111"))
             .unwrap();
-        manager.record_cash(CashBuilder::new(-300)).unwrap();
+        assert_eq!(children.len(), 2);
 
-        let stats = manager.get_dashboard_stats().unwrap();
-        assert_eq!(stats.total_students, 1);
-        assert_eq!(stats.total_revenue, 2000);
-        assert_eq!(stats.total_expense, 300);
-        assert!((stats.average_score - 87.67).abs() < 0.1);
-        assert_eq!(stats.max_score, 90.0);
+        manager
+            .update_student(sibling_a, StudentUpdater::new().remove_guardian_at(0))
+            .unwrap();
+        let children = manager
+            .search_students(StudentQuery::new().guardian_phone("139 0000 1111"))
+            .unwrap();
+        assert_eq!(children.len(), 1);
+        assert_eq!(children[0].uid(), sibling_b);
+
+        assert!(
+            manager
+                .update_student(sibling_a, StudentUpdater::new().remove_guardian_at(5))
+                .is_err()
+        );
+        manager
+            .update_student(sibling_a, StudentUpdater::new().set_guardians(Vec::new()))
+            .unwrap();
+        let student = manager.get_student(sibling_a).unwrap().unwrap();
+        assert!(student.guardians().is_empty());
     }
 
     #[test]
-    fn test_student_stats() {
+    fn test_phone_query() {
         let temp_dir = TempDir::new().unwrap();
-        let temp_path = temp_dir.path();
-        std::env::set_current_dir(temp_path).unwrap();
-
-        // 确保data目录存在
+        std::env::set_current_dir(temp_dir.path()).unwrap();
         let _ = std::fs::create_dir_all("data");
 
-        let manager = QmxManager::new(true).unwrap();
+        let manager = QmxManager::builder().auto_save(false).build().unwrap();
+        let zhang = manager
+            .create_student(StudentBuilder::new("张三").phone("138-0000-1234"))
+            .unwrap();
+        let li = manager
+            .create_student(StudentBuilder::new("李四").phone("13900001234"))
+            .unwrap();
+        manager.create_student(StudentBuilder::new("王五")).unwrap();
+
+        let found = manager
+            .search_students(StudentQuery::new().phone_equals("138 0000 1234"))
+            .unwrap();
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].uid(), zhang);
+        assert!(
+            manager
+                .search_students(StudentQuery::new().phone_equals("1380000"))
+                .unwrap()
+                .is_empty()
+        );
+
+        let by_tail = manager
+            .search_students(StudentQuery::new().phone_contains("1234"))
+            .unwrap();
+        let uids: Vec<u64> = by_tail.iter().map(|s| s.uid()).collect();
+        assert_eq!(uids, vec![zhang, li]);
+        // 没有数字的查询不匹配任何学生
+        assert!(
+            manager
+                .search_students(StudentQuery::new().phone_contains("-"))
+                .unwrap()
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn test_custom_fields() {
+        let temp_dir = TempDir::new().unwrap();
+        let temp_path = temp_dir.path();
+        std::env::set_current_dir(temp_path).unwrap();
+
+        // 确保data目录存在
+        let _ = std::fs::create_dir_all("data");
+
+        let manager = QmxManager::builder().auto_save(false).build().unwrap();
+        let enrolled = Utc::now();
+        let uid = manager
+            .create_student(
+                StudentBuilder::new("自定义字段")
+                    .custom_field("校区", "东区")
+                    .custom_field("段位", 3)
+                    .custom_field("已签协议", true)
+                    .custom_field("入营日期", enrolled),
+            )
+            .unwrap();
+        let other = manager
+            .create_student(StudentBuilder::new("普通学生").custom_field("校区", "西区"))
+            .unwrap();
+
+        let student = manager.get_student(uid).unwrap().unwrap();
+        assert_eq!(student.custom_fields().len(), 4);
+        assert_eq!(
+            student.custom_field("段位"),
+            Some(&CustomValue::Number(3.0))
+        );
+        assert_eq!(
+            student.custom_field("入营日期"),
+            Some(&CustomValue::Date(enrolled))
+        );
+
+        let east = manager
+            .search_students(StudentQuery::new().custom_field("校区", "东区"))
+            .unwrap();
+        assert_eq!(east.len(), 1);
+        assert_eq!(east[0].uid(), uid);
+        let with_campus = manager
+            .search_students(StudentQuery::new().has_custom_field("校区"))
+            .unwrap();
+        assert_eq!(with_campus.len(), 2);
+
+        let changes = manager
+            .update_student(
+                other,
+                StudentUpdater::new()
+                    .set_custom_field("校区", "东区")
+                    .remove_custom_field("不存在"),
+            )
+            .unwrap();
+        assert_eq!(changes.len(), 1);
+        assert_eq!(changes[0].field, "custom_fields");
+        manager
+            .update_student(uid, StudentUpdater::new().remove_custom_field("已签协议"))
+            .unwrap();
+        let signed = manager
+            .search_students(StudentQuery::new().has_custom_field("已签协议"))
+            .unwrap();
+        assert!(signed.is_empty());
+
+        // 字段名不能为空
+        assert!(
+            manager
+                .update_student(uid, StudentUpdater::new().set_custom_field("", 1))
+                .is_err()
+        );
+
+        let cash_uid = manager
+            .record_cash(
+                CashBuilder::new(500)
+                    .student_id(uid)
+                    .custom_field("支付方式", "微信"),
+            )
+            .unwrap();
+        manager.record_cash(CashBuilder::new(300)).unwrap();
+        let wechat = manager
+            .search_cash(CashQuery::new().custom_field("支付方式", "微信"))
+            .unwrap();
+        assert_eq!(wechat.len(), 1);
+        assert_eq!(wechat[0].uid, cash_uid);
+
+        manager
+            .update_cash(
+                cash_uid,
+                CashUpdater::new().set_custom_field("支付方式", "现金"),
+            )
+            .unwrap();
+        let cash = manager.get_cash(cash_uid).unwrap().unwrap();
+        assert_eq!(
+            cash.custom_fields.get("支付方式"),
+            Some(&CustomValue::Text("现金".to_string()))
+        );
+        assert!(
+            manager
+                .search_cash(CashQuery::new().has_custom_field("备用"))
+                .unwrap()
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn test_custom_fields_backward_compatible() {
+        // 旧数据中没有 custom_fields 字段
+        let mut value = serde_json::to_value(Cash::new(None)).unwrap();
+        value.as_object_mut().unwrap().remove("custom_fields");
+        let cash: Cash = serde_json::from_value(value).unwrap();
+        assert!(cash.custom_fields.is_empty());
+
+        let mut cash = cash;
+        cash.custom_fields
+            .insert("发票".to_string(), CustomValue::Bool(true));
+        let json = serde_json::to_string(&cash).unwrap();
+        let restored: Cash = serde_json::from_str(&json).unwrap();
+        assert_eq!(restored.custom_fields, cash.custom_fields);
+    }
+
+    #[test]
+    fn test_student_created_at() {
+        let temp_dir = TempDir::new().unwrap();
+        std::env::set_current_dir(temp_dir.path()).unwrap();
+        let _ = std::fs::create_dir_all("data");
+
+        let created = Utc::now() - Duration::days(3);
+        let manager = QmxManager::builder()
+            .auto_save(false)
+            .build()
+            .unwrap()
+            .with_clock(Arc::new(FixedClock::new(created)));
+        let uid = manager.create_student(StudentBuilder::new("新生")).unwrap();
+        let student = manager.get_student(uid).unwrap().unwrap();
+        assert_eq!(student.created_at(), Some(created));
+
+        // 旧数据中没有 created_at 字段
+        let mut value = serde_json::to_value(&student).unwrap();
+        value.as_object_mut().unwrap().remove("created_at");
+        let legacy: Student = serde_json::from_value(value).unwrap();
+        assert_eq!(legacy.created_at(), None);
+    }
+
+    #[test]
+    fn test_student_updated_at() {
+        let temp_dir = TempDir::new().unwrap();
+        std::env::set_current_dir(temp_dir.path()).unwrap();
+        let _ = std::fs::create_dir_all("data");
+
+        let start = Utc::now() - Duration::days(3);
+        let clock = Arc::new(FixedClock::new(start));
+        let manager = QmxManager::builder()
+            .auto_save(false)
+            .build()
+            .unwrap()
+            .with_clock(clock.clone());
+        let uid = manager.create_student(StudentBuilder::new("修改")).unwrap();
+        assert_eq!(
+            manager.get_student(uid).unwrap().unwrap().updated_at(),
+            Some(start)
+        );
+
+        // 没有实际变化的更新不改动修改时间，也不计入变更字段
+        clock.advance(Duration::days(1));
+        let changes = manager
+            .update_student(uid, StudentUpdater::new().name("修改".to_string()))
+            .unwrap();
+        assert!(changes.is_empty());
+        let student = manager.get_student(uid).unwrap().unwrap();
+        assert_eq!(student.updated_at(), Some(start));
+
+        let changes = manager
+            .update_student(uid, StudentUpdater::new().age(Some(10)))
+            .unwrap();
+        assert_eq!(changes.len(), 1);
+        let student = manager.get_student(uid).unwrap().unwrap();
+        assert_eq!(student.updated_at(), Some(start + Duration::days(1)));
+        assert_eq!(student.created_at(), Some(start));
+
+        // setter 直接修改时自动更新
+        let mut student = Student::new();
+        student.set_updated_at(None);
+        student.set_note("备注".to_string());
+        assert!(student.updated_at().is_some());
+
+        let mut value = serde_json::to_value(&student).unwrap();
+        value.as_object_mut().unwrap().remove("updated_at");
+        let legacy: Student = serde_json::from_value(value).unwrap();
+        assert_eq!(legacy.updated_at(), None);
+    }
+
+    #[test]
+    fn test_query_created_between() {
+        let temp_dir = TempDir::new().unwrap();
+        std::env::set_current_dir(temp_dir.path()).unwrap();
+        let _ = std::fs::create_dir_all("data");
+
+        let start = Utc::now() - Duration::days(30);
+        let clock = Arc::new(FixedClock::new(start));
+        let manager = QmxManager::builder()
+            .auto_save(false)
+            .build()
+            .unwrap()
+            .with_clock(clock.clone());
+        let old = manager.create_student(StudentBuilder::new("老生")).unwrap();
+        clock.advance(Duration::days(20));
+        let new = manager.create_student(StudentBuilder::new("新生")).unwrap();
+
+        let result = manager
+            .search_students(
+                StudentQuery::new().created_between(start + Duration::days(10), Utc::now()),
+            )
+            .unwrap();
+        let uids: Vec<u64> = result.iter().map(|s| s.uid()).collect();
+        assert_eq!(uids, vec![new]);
+
+        let result = manager
+            .search_students(StudentQuery::new().created_between(start, start))
+            .unwrap();
+        let uids: Vec<u64> = result.iter().map(|s| s.uid()).collect();
+        assert_eq!(uids, vec![old]);
+    }
+
+    #[test]
+    fn test_student_query_or_and_not() {
+        let temp_dir = TempDir::new().unwrap();
+        std::env::set_current_dir(temp_dir.path()).unwrap();
+        let _ = std::fs::create_dir_all("data");
+
+        let now = Utc::now();
+        let manager = QmxManager::builder().auto_save(false).build().unwrap();
+        let ten_try = manager
+            .create_student(StudentBuilder::new("体验").class(Class::TenTry))
+            .unwrap();
+        let expired = manager
+            .create_student(
+                StudentBuilder::new("过期")
+                    .class(Class::Month)
+                    .membership(now - Duration::days(60), now - Duration::days(30)),
+            )
+            .unwrap();
+        let active = manager
+            .create_student(
+                StudentBuilder::new("有效")
+                    .class(Class::Month)
+                    .membership(now - Duration::days(10), now + Duration::days(20)),
+            )
+            .unwrap();
+
+        let uids = |query: StudentQuery| -> Vec<u64> {
+            manager
+                .search_students(query)
+                .unwrap()
+                .iter()
+                .map(|s| s.uid())
+                .collect()
+        };
+        let expired_query = || {
+            StudentQuery::new()
+                .has_membership(true)
+                .not(StudentQuery::new().membership_active_at(now))
+        };
+        assert_eq!(uids(expired_query()), vec![expired]);
+        assert_eq!(
+            uids(StudentQuery::new().class(Class::TenTry).or(expired_query())),
+            vec![ten_try, expired]
+        );
+        // 组合之后添加的条件与整个组合取交集
+        assert_eq!(
+            uids(
+                StudentQuery::new()
+                    .class(Class::TenTry)
+                    .or(StudentQuery::new().class(Class::Month))
+                    .name_contains("效")
+            ),
+            vec![active]
+        );
+        assert_eq!(
+            uids(StudentQuery::new().not(StudentQuery::new().class(Class::Month))),
+            vec![ten_try]
+        );
+        assert_eq!(uids(StudentQuery::new().or(StudentQuery::new().name_contains("无"))).len(), 3);
+    }
+
+    #[test]
+    fn test_for_each_student_and_cash() {
+        let temp_dir = TempDir::new().unwrap();
+        std::env::set_current_dir(temp_dir.path()).unwrap();
+        let _ = std::fs::create_dir_all("data");
+
+        let manager = QmxManager::builder().auto_save(false).build().unwrap();
+        for age in [12, 15, 18, 21] {
+            let uid = manager
+                .create_student(StudentBuilder::new(format!("学生{}", age)).age(age))
+                .unwrap();
+            manager
+                .record_cash(CashBuilder::new(i64::from(age) * 100).student_id(uid))
+                .unwrap();
+        }
+
+        let mut names = Vec::new();
+        let visited = manager
+            .for_each_student(
+                StudentQuery::new()
+                    .age_range(14, 30)
+                    .order_by(StudentSortKey::Age, SortOrder::Descending)
+                    .limit(2),
+                |student| names.push(student.name().unwrap().to_string()),
+            )
+            .unwrap();
+        assert_eq!(visited, 2);
+        assert_eq!(names, vec!["学生21", "学生18"]);
+
+        let mut total = 0;
+        let visited = manager
+            .for_each_cash(CashQuery::new().amount_range(1500, i64::MAX), |cash| {
+                total += cash.cash.amount_minor
+            })
+            .unwrap();
+        assert_eq!(visited, 3);
+        assert_eq!(total, 5400);
+    }
+
+    #[test]
+    fn test_count_and_exists() {
+        let temp_dir = TempDir::new().unwrap();
+        std::env::set_current_dir(temp_dir.path()).unwrap();
+        let _ = std::fs::create_dir_all("data");
+
+        let manager = QmxManager::builder().auto_save(false).build().unwrap();
+        for class in [Class::Month, Class::Month, Class::Year] {
+            let uid = manager
+                .create_student(StudentBuilder::new("学生").class(class))
+                .unwrap();
+            manager
+                .record_cash(CashBuilder::new(1000).student_id(uid))
+                .unwrap();
+        }
+        manager.record_cash(CashBuilder::new(-200)).unwrap();
+
+        // 计数忽略分页
+        assert_eq!(
+            manager
+                .count_students(StudentQuery::new().class(Class::Month).limit(1))
+                .unwrap(),
+            2
+        );
+        assert!(manager.student_exists(StudentQuery::new().class(Class::Year)).unwrap());
+        assert!(!manager.student_exists(StudentQuery::new().class(Class::TenTry)).unwrap());
+        assert_eq!(manager.count_cash(CashQuery::new()).unwrap(), 4);
+        assert_eq!(
+            manager
+                .count_cash(CashQuery::new().amount_range(i64::MIN, -1))
+                .unwrap(),
+            1
+        );
+        assert!(!manager.cash_exists(CashQuery::new().note_contains("退款")).unwrap());
+    }
+
+    #[test]
+    fn test_lesson_left_below_and_renewal() {
+        let temp_dir = TempDir::new().unwrap();
+        std::env::set_current_dir(temp_dir.path()).unwrap();
+        let _ = std::fs::create_dir_all("data");
+
+        let now = Utc::now();
+        let manager = QmxManager::builder()
+            .auto_save(false)
+            .build()
+            .unwrap()
+            .with_clock(Arc::new(FixedClock::new(now)));
+        let low = manager
+            .create_student(StudentBuilder::new("课时不足").class(Class::TenTry).lesson_left(2))
+            .unwrap();
+        manager
+            .create_student(StudentBuilder::new("课时充足").class(Class::TenTry))
+            .unwrap();
+        let expiring = manager
+            .create_student(StudentBuilder::new("会员将到期").membership(
+                now - Duration::days(300),
+                now + Duration::days(7),
+            ))
+            .unwrap();
+        manager
+            .create_student(StudentBuilder::new("会员已过期").membership(
+                now - Duration::days(300),
+                now - Duration::days(1),
+            ))
+            .unwrap();
+        manager.create_student(StudentBuilder::new("不记课时")).unwrap();
+
+        let result = manager
+            .search_students(StudentQuery::new().lesson_left_below(3))
+            .unwrap();
+        let uids: Vec<u64> = result.iter().map(|s| s.uid()).collect();
+        assert_eq!(uids, vec![low]);
+
+        let renewal = manager.get_students_needing_renewal().unwrap();
+        let uids: Vec<u64> = renewal.iter().map(|s| s.uid()).collect();
+        assert_eq!(uids, vec![low, expiring]);
+    }
+}
+
+mod cash_query_tests {
+    use super::*;
+
+    #[test]
+    fn test_cash_query_student_id() {
+        let temp_dir = TempDir::new().unwrap();
+        let temp_path = temp_dir.path();
+        std::env::set_current_dir(temp_path).unwrap();
+
+        // 确保data目录存在
+        let _ = std::fs::create_dir_all("data");
+
+        let manager = QmxManager::builder().auto_save(true).build().unwrap();
+
+        let student1_id = manager
+            .create_student(StudentBuilder::new("学生1").age(18))
+            .unwrap();
+        let student2_id = manager
+            .create_student(StudentBuilder::new("学生2").age(19))
+            .unwrap();
+
+        manager
+            .record_cash(CashBuilder::new(1000).student_id(student1_id))
+            .unwrap();
+        manager
+            .record_cash(CashBuilder::new(1500).student_id(student1_id))
+            .unwrap();
+        manager
+            .record_cash(CashBuilder::new(2000).student_id(student2_id))
+            .unwrap();
+
+        let student1_cash = manager
+            .search_cash(CashQuery::new().student_id(student1_id))
+            .unwrap();
+        assert_eq!(student1_cash.len(), 2);
+
+        let student2_cash = manager
+            .search_cash(CashQuery::new().student_id(student2_id))
+            .unwrap();
+        assert_eq!(student2_cash.len(), 1);
+        assert_eq!(student2_cash[0].cash.amount_minor, 2000);
+    }
+
+    #[test]
+    fn test_cash_query_note_and_student_name() {
+        let temp_dir = TempDir::new().unwrap();
+        std::env::set_current_dir(temp_dir.path()).unwrap();
+        let _ = std::fs::create_dir_all("data");
+
+        let manager = QmxManager::builder().auto_save(false).build().unwrap();
+        let zhang = manager.create_student(StudentBuilder::new("张小明")).unwrap();
+        let li = manager.create_student(StudentBuilder::new("李华")).unwrap();
+        let tuition = manager
+            .record_cash(CashBuilder::new(1000).student_id(zhang).note("三月学费 微信"))
+            .unwrap();
+        let gear = manager
+            .record_cash(CashBuilder::new(300).student_id(li).note("护具 微信"))
+            .unwrap();
+        manager.record_cash(CashBuilder::new(-200)).unwrap();
+
+        let uids = |query: CashQuery| -> Vec<u64> {
+            manager
+                .search_cash(query)
+                .unwrap()
+                .iter()
+                .map(|c| c.uid)
+                .collect()
+        };
+        assert_eq!(uids(CashQuery::new().note_contains("学费")), vec![tuition]);
+        assert_eq!(uids(CashQuery::new().note_contains("微信")), vec![tuition, gear]);
+        assert_eq!(uids(CashQuery::new().student_name_contains("小明")), vec![tuition]);
+        assert_eq!(
+            uids(CashQuery::new().student_name_contains("李").note_contains("学费")),
+            Vec::<u64>::new()
+        );
+
+        manager.delete_student(li, DeletePolicy::Detach).unwrap();
+        assert!(uids(CashQuery::new().student_name_contains("李华")).is_empty());
+    }
+
+    #[test]
+    fn test_cash_query_installment_status_and_plan() {
+        let temp_dir = TempDir::new().unwrap();
+        std::env::set_current_dir(temp_dir.path()).unwrap();
+        let _ = std::fs::create_dir_all("data");
+
+        let now = Utc::now();
+        let manager = QmxManager::builder()
+            .auto_save(false)
+            .build()
+            .unwrap()
+            .with_clock(Arc::new(FixedClock::new(now)));
+        let late = manager
+            .create_installment_plan(InstallmentPlanBuilder::new(
+                900,
+                3,
+                PaymentFrequency::Monthly,
+                now - Duration::days(45),
+            ))
+            .unwrap();
+        let future = manager
+            .create_installment_plan(InstallmentPlanBuilder::new(
+                600,
+                2,
+                PaymentFrequency::Monthly,
+                now + Duration::days(10),
+            ))
+            .unwrap();
+        manager.record_cash(CashBuilder::new(100)).unwrap();
+        manager.mark_overdue_installments().unwrap();
+
+        let uids = |query: CashQuery| -> Vec<u64> {
+            manager
+                .search_cash(query)
+                .unwrap()
+                .iter()
+                .map(|c| c.uid)
+                .collect()
+        };
+        assert_eq!(uids(CashQuery::new().plan_id(future.plan_id)), future.cash_uids);
+        let overdue = uids(CashQuery::new().installment_status(InstallmentStatus::Overdue));
+        assert_eq!(overdue, late.cash_uids[..2]);
+        assert_eq!(
+            uids(
+                CashQuery::new()
+                    .plan_id(late.plan_id)
+                    .installment_status(InstallmentStatus::Pending)
+            ),
+            late.cash_uids[2..]
+        );
+        assert!(uids(CashQuery::new().installment_status(InstallmentStatus::Paid)).is_empty());
+    }
+
+    #[test]
+    fn test_cash_query_or_and_not() {
+        let temp_dir = TempDir::new().unwrap();
+        std::env::set_current_dir(temp_dir.path()).unwrap();
+        let _ = std::fs::create_dir_all("data");
+
+        let manager = QmxManager::builder().auto_save(false).build().unwrap();
+        let small = manager.record_cash(CashBuilder::new(100)).unwrap();
+        let large = manager.record_cash(CashBuilder::new(5000)).unwrap();
+        let expense = manager
+            .record_cash(CashBuilder::new(-300).note("房租"))
+            .unwrap();
+
+        let uids = |query: CashQuery| -> Vec<u64> {
+            manager
+                .search_cash(query)
+                .unwrap()
+                .iter()
+                .map(|c| c.uid)
+                .collect()
+        };
+        assert_eq!(
+            uids(
+                CashQuery::new()
+                    .amount_range(1000, i64::MAX)
+                    .or(CashQuery::new().note_contains("房租"))
+            ),
+            vec![large, expense]
+        );
+        assert_eq!(
+            uids(CashQuery::new().not(CashQuery::new().amount_range(i64::MIN, -1))),
+            vec![small, large]
+        );
+    }
+
+    #[test]
+    fn test_aggregate_cash() {
+        let temp_dir = TempDir::new().unwrap();
+        std::env::set_current_dir(temp_dir.path()).unwrap();
+        let _ = std::fs::create_dir_all("data");
+
+        let march = Utc.with_ymd_and_hms(2024, 3, 15, 9, 0, 0).unwrap();
+        let clock = Arc::new(FixedClock::new(march));
+        let manager = QmxManager::builder()
+            .auto_save(false)
+            .build()
+            .unwrap()
+            .with_clock(clock.clone());
+        let zhang = manager.create_student(StudentBuilder::new("张三")).unwrap();
+        let li = manager.create_student(StudentBuilder::new("李四")).unwrap();
+        manager
+            .record_cash(
+                CashBuilder::new(1000)
+                    .student_id(zhang)
+                    .payment_method(PaymentMethod::WeChat),
+            )
+            .unwrap();
+        manager
+            .record_cash(CashBuilder::new(-300).payment_method(PaymentMethod::Cash))
+            .unwrap();
+        clock.advance(Duration::days(30));
+        manager
+            .record_cash(
+                CashBuilder::new(2000)
+                    .student_id(li)
+                    .payment_method(PaymentMethod::WeChat),
+            )
+            .unwrap();
+        let uid = manager
+            .record_cash(CashBuilder::new(500).student_id(zhang))
+            .unwrap();
+
+        let by_month = manager
+            .aggregate_cash(CashQuery::new(), GroupBy::Month)
+            .unwrap();
+        assert_eq!(by_month.len(), 2);
+        assert_eq!(
+            by_month[0].key,
+            GroupKey::Month(NaiveDate::from_ymd_opt(2024, 3, 1).unwrap())
+        );
+        assert_eq!(by_month[0].totals.income, 1000);
+        assert_eq!(by_month[0].totals.expense, 300);
+        assert_eq!(by_month[1].totals.income, 2500);
+        assert_eq!(by_month[1].totals.count, 2);
+
+        let by_student = manager
+            .aggregate_cash(CashQuery::new().amount_range(1, i64::MAX), GroupBy::Student)
+            .unwrap();
+        let totals: Vec<(GroupKey, i64)> = by_student
+            .iter()
+            .map(|g| (g.key, g.totals.income))
+            .collect();
+        assert_eq!(
+            totals,
+            vec![
+                (GroupKey::Student(Some(zhang)), 1500),
+                (GroupKey::Student(Some(li)), 2000)
+            ]
+        );
+
+        manager
+            .update_cash(uid, CashUpdater::new().payment_method(Some(PaymentMethod::Alipay)))
+            .unwrap();
+        let by_method = manager
+            .aggregate_cash(CashQuery::new(), GroupBy::PaymentMethod)
+            .unwrap();
+        let keys: Vec<GroupKey> = by_method.iter().map(|g| g.key).collect();
+        assert_eq!(
+            keys,
+            vec![
+                GroupKey::PaymentMethod(Some(PaymentMethod::Cash)),
+                GroupKey::PaymentMethod(Some(PaymentMethod::WeChat)),
+                GroupKey::PaymentMethod(Some(PaymentMethod::Alipay)),
+            ]
+        );
+        assert_eq!(by_method[1].totals.income, 3000);
+    }
+
+    #[test]
+    fn test_cash_query_amount_range() {
+        let temp_dir = TempDir::new().unwrap();
+        let temp_path = temp_dir.path();
+        std::env::set_current_dir(temp_path).unwrap();
+
+        // 确保data目录存在
+        let _ = std::fs::create_dir_all("data");
+
+        let manager = QmxManager::builder().auto_save(true).build().unwrap();
+
+        manager.record_cash(CashBuilder::new(500)).unwrap();
+        manager.record_cash(CashBuilder::new(1500)).unwrap();
+        manager.record_cash(CashBuilder::new(2500)).unwrap();
+        manager.record_cash(CashBuilder::new(-200)).unwrap();
+
+        let medium_amounts = manager
+            .search_cash(CashQuery::new().amount_range(1000, 2000))
+            .unwrap();
+        assert_eq!(medium_amounts.len(), 1);
+        assert_eq!(medium_amounts[0].cash.amount_minor, 1500);
+
+        let positive_amounts = manager
+            .search_cash(CashQuery::new().amount_range(0, i64::MAX))
+            .unwrap();
+        assert_eq!(positive_amounts.len(), 3);
+    }
+
+    #[test]
+    fn test_cash_query_pagination() {
+        let temp_dir = TempDir::new().unwrap();
+        let temp_path = temp_dir.path();
+        std::env::set_current_dir(temp_path).unwrap();
+
+        // 确保data目录存在
+        let _ = std::fs::create_dir_all("data");
+
+        let manager = QmxManager::builder().auto_save(false).build().unwrap();
+
+        for amount in [100, 200, 300, -50] {
+            manager.record_cash(CashBuilder::new(amount)).unwrap();
+        }
+
+        let page = manager
+            .search_cash_paged(CashQuery::new().amount_range(0, i64::MAX).limit(2))
+            .unwrap();
+        assert_eq!(page.total, 3);
+        assert_eq!(page.items.len(), 2);
+        assert!(page.has_more());
+
+        let beyond = manager
+            .search_cash_paged(CashQuery::new().offset(10))
+            .unwrap();
+        assert_eq!(beyond.total, 4);
+        assert!(beyond.items.is_empty());
+    }
+
+    #[test]
+    fn test_cash_query_order_by_amount() {
+        let temp_dir = TempDir::new().unwrap();
+        let temp_path = temp_dir.path();
+        std::env::set_current_dir(temp_path).unwrap();
+
+        // 确保data目录存在
+        let _ = std::fs::create_dir_all("data");
+
+        let manager = QmxManager::builder().auto_save(false).build().unwrap();
+
+        for amount in [300, -50, 1000, 200] {
+            manager.record_cash(CashBuilder::new(amount)).unwrap();
+        }
+
+        let sorted = manager
+            .search_cash(CashQuery::new().order_by(CashSortKey::Amount, SortOrder::Ascending))
+            .unwrap();
+        let amounts: Vec<_> = sorted.iter().map(|c| c.cash.amount_minor).collect();
+        assert_eq!(amounts, vec![-50, 200, 300, 1000]);
+
+        let top = manager
+            .search_cash(
+                CashQuery::new()
+                    .order_by(CashSortKey::Amount, SortOrder::Descending)
+                    .limit(2),
+            )
+            .unwrap();
+        let amounts: Vec<_> = top.iter().map(|c| c.cash.amount_minor).collect();
+        assert_eq!(amounts, vec![1000, 300]);
+    }
+}
+
+mod statistics_tests {
+    use super::*;
+
+    #[test]
+    fn test_dashboard_stats_v2() {
+        let temp_dir = TempDir::new().unwrap();
+        let temp_path = temp_dir.path();
+        std::env::set_current_dir(temp_path).unwrap();
+
+        // 确保data目录存在
+        let _ = std::fs::create_dir_all("data");
+
+        let manager = QmxManager::builder().auto_save(true).build().unwrap();
+
+        // 创建学生和现金记录
+        let student_id = manager
+            .create_student(StudentBuilder::new("统计学生").age(18))
+            .unwrap();
+
+        manager
+            .update_student(
+                student_id,
+                StudentUpdater::new()
+                    .add_ring(85.0)
+                    .add_ring(90.0)
+                    .add_ring(88.0),
+            )
+            .unwrap();
+
+        manager
+            .record_cash(CashBuilder::new(2000).student_id(student_id))
+            .unwrap();
+        manager.record_cash(CashBuilder::new(-300)).unwrap();
+
+        let stats = manager.get_dashboard_stats().unwrap();
+        assert_eq!(stats.total_students, 1);
+        assert_eq!(stats.total_revenue, 2000);
+        assert_eq!(stats.total_expense, 300);
+        assert!((stats.average_score - 87.67).abs() < 0.1);
+        assert_eq!(stats.max_score, 90.0);
+    }
+
+    #[test]
+    fn test_student_stats() {
+        let temp_dir = TempDir::new().unwrap();
+        let temp_path = temp_dir.path();
+        std::env::set_current_dir(temp_path).unwrap();
+
+        // 确保data目录存在
+        let _ = std::fs::create_dir_all("data");
+
+        let manager = QmxManager::builder().auto_save(true).build().unwrap();
 
         let start = Utc::now();
         let end = start + Duration::days(30);
@@ -610,6 +1897,98 @@ mod statistics_tests {
         }
     }
 
+    #[test]
+    fn test_score_trend() {
+        let temp_dir = TempDir::new().unwrap();
+        std::env::set_current_dir(temp_dir.path()).unwrap();
+        let _ = std::fs::create_dir_all("data");
+
+        let manager = QmxManager::builder().auto_save(false).build().unwrap();
+        let student_id = manager
+            .create_student(StudentBuilder::new("趋势学生"))
+            .unwrap();
+        assert_eq!(manager.get_score_trend(student_id, 5, 2).unwrap(), None);
+        assert!(manager.get_score_trend(student_id, 0, 2).is_err());
+        assert!(manager.get_score_trend(999_999, 5, 2).is_err());
+
+        let mut updater = StudentUpdater::new();
+        for ring in [3.0, 6.0, 7.0, 8.0, 9.0, 10.0] {
+            updater = updater.add_ring(ring);
+        }
+        manager.update_student(student_id, updater).unwrap();
+
+        // 只统计最近 4 次：7, 8, 9, 10
+        let trend = manager.get_score_trend(student_id, 4, 2).unwrap().unwrap();
+        assert_eq!(trend.count, 4);
+        assert_eq!(trend.moving_average, vec![7.5, 8.5, 9.5]);
+        assert_eq!(trend.best, 10.0);
+        assert_eq!(trend.worst, 7.0);
+        assert_eq!(trend.mean, 8.5);
+        assert!((trend.std_dev - 1.25f64.sqrt()).abs() < 1e-9);
+        assert!((trend.slope - 1.0).abs() < 1e-9);
+
+        // 窗口大于成绩数量时只有一项移动平均
+        let trend = manager.get_score_trend(student_id, 3, 10).unwrap().unwrap();
+        assert_eq!(trend.moving_average, vec![9.0]);
+        let trend = ScoreTrend::calculate(&[9.0, 7.0], 10, 1).unwrap().unwrap();
+        assert!((trend.slope + 2.0).abs() < 1e-9);
+        let trend = ScoreTrend::calculate(&[9.0], 10, 1).unwrap().unwrap();
+        assert_eq!(trend.slope, 0.0);
+        assert_eq!(trend.std_dev, 0.0);
+    }
+
+    #[test]
+    fn test_student_ranking() {
+        let temp_dir = TempDir::new().unwrap();
+        std::env::set_current_dir(temp_dir.path()).unwrap();
+        let _ = std::fs::create_dir_all("data");
+
+        let manager = QmxManager::builder().auto_save(false).build().unwrap();
+        let mut uids = Vec::new();
+        for (name, rings) in [
+            ("甲", vec![8.0, 10.0]),
+            ("乙", vec![9.5]),
+            ("丙", vec![9.0]),
+            ("丁", vec![7.0]),
+            ("无成绩", vec![]),
+        ] {
+            let mut updater = StudentUpdater::new();
+            for ring in rings {
+                updater = updater.add_ring(ring);
+            }
+            let uid = manager
+                .create_student(StudentBuilder::new(name).subject(Subject::Shooting))
+                .unwrap();
+            manager.update_student(uid, updater).unwrap();
+            uids.push(uid);
+        }
+        let archer = manager
+            .create_student(StudentBuilder::new("射箭").subject(Subject::Archery))
+            .unwrap();
+        manager
+            .update_student(archer, StudentUpdater::new().add_ring(10.0))
+            .unwrap();
+
+        let ranking = manager.get_student_ranking(Subject::Shooting).unwrap();
+        let names: Vec<&str> = ranking.iter().map(|r| r.name.as_str()).collect();
+        assert_eq!(names, vec!["乙", "甲", "丙", "丁"]);
+        let ranks: Vec<usize> = ranking.iter().map(|r| r.rank).collect();
+        assert_eq!(ranks, vec![1, 2, 2, 4]);
+        let percentiles: Vec<f64> = ranking.iter().map(|r| r.percentile).collect();
+        assert_eq!(percentiles, vec![100.0, 100.0 / 3.0, 100.0 / 3.0, 0.0]);
+        assert_eq!(ranking[1].uid, uids[0]);
+
+        let archery = manager.get_student_ranking(Subject::Archery).unwrap();
+        assert_eq!(archery.len(), 1);
+        assert_eq!(archery[0].percentile, 100.0);
+        assert!(
+            manager
+                .get_student_ranking(Subject::Others)
+                .unwrap()
+                .is_empty()
+        );
+    }
+
     #[test]
     fn test_financial_stats() {
         let temp_dir = TempDir::new().unwrap();
@@ -619,7 +1998,7 @@ mod statistics_tests {
         // 确保data目录存在
         let _ = std::fs::create_dir_all("data");
 
-        let manager = QmxManager::new(true).unwrap();
+        let manager = QmxManager::builder().auto_save(true).build().unwrap();
 
         manager.record_cash(CashBuilder::new(2000)).unwrap();
         manager.record_cash(CashBuilder::new(1500)).unwrap();
@@ -633,6 +2012,90 @@ mod statistics_tests {
         assert_eq!(stats.transaction_count, 4);
         assert_eq!(stats.installment_count, 0);
     }
+
+    #[test]
+    fn test_dashboard_and_financial_revenue_agree() {
+        let temp_dir = TempDir::new().unwrap();
+        let temp_path = temp_dir.path();
+        std::env::set_current_dir(temp_path).unwrap();
+
+        // 确保data目录存在
+        let _ = std::fs::create_dir_all("data");
+
+        // 零金额记录只能来自旧接口或历史数据，直接写入数据库文件
+        let mut legacy = CashDatabase::new();
+        let mut zero = Cash::new(None);
+        zero.set_cash(0);
+        legacy.insert(zero);
+        legacy.save().unwrap();
+
+        let manager = QmxManager::builder().auto_save(false).build().unwrap();
+        manager.record_cash(CashBuilder::new(1000)).unwrap();
+        manager
+            .record_cash(CashBuilder::new(-400).note("退款"))
+            .unwrap();
+
+        let dashboard = manager.get_dashboard_stats().unwrap();
+        let financial = manager.get_financial_stats(TimePeriod::ThisYear).unwrap();
+        assert_eq!(dashboard.total_revenue, 1000);
+        assert_eq!(dashboard.total_expense, 400);
+        assert_eq!(financial.total_income, dashboard.total_revenue);
+        assert_eq!(financial.total_expense, dashboard.total_expense);
+        assert_eq!(financial.net_income, 600);
+        assert_eq!(financial.transaction_count, 3);
+    }
+
+    #[test]
+    fn test_membership_tiers() {
+        let temp_dir = TempDir::new().unwrap();
+        let temp_path = temp_dir.path();
+        std::env::set_current_dir(temp_path).unwrap();
+
+        // 确保data目录存在
+        let _ = std::fs::create_dir_all("data");
+
+        let manager = QmxManager::builder().auto_save(false).build().unwrap();
+        let gold = manager
+            .create_student(StudentBuilder::new("金卡学生").membership_tier(MembershipTier::Gold))
+            .unwrap();
+        manager
+            .create_student(StudentBuilder::new("银卡学生").membership_tier(MembershipTier::Silver))
+            .unwrap();
+        let plain = manager
+            .create_student(StudentBuilder::new("普通学生"))
+            .unwrap();
+
+        let student = manager.get_student(gold).unwrap().unwrap();
+        assert_eq!(student.membership_tier(), Some(&MembershipTier::Gold));
+
+        let changes = manager
+            .update_student(
+                plain,
+                StudentUpdater::new()
+                    .membership_tier(Some(MembershipTier::Custom("年卡".to_string()))),
+            )
+            .unwrap();
+        assert_eq!(changes.len(), 1);
+        assert_eq!(changes[0].field, "membership_tier");
+
+        let golds = manager
+            .search_students(StudentQuery::new().membership_tier(MembershipTier::Gold))
+            .unwrap();
+        assert_eq!(golds.len(), 1);
+        assert_eq!(golds[0].uid(), gold);
+
+        let stats = manager.get_dashboard_stats().unwrap();
+        assert_eq!(stats.membership_tiers.get("Gold"), Some(&1));
+        assert_eq!(stats.membership_tiers.get("Silver"), Some(&1));
+        assert_eq!(stats.membership_tiers.get("年卡"), Some(&1));
+        assert_eq!(stats.membership_tiers.get("Basic"), None);
+
+        manager
+            .update_student(gold, StudentUpdater::new().membership_tier(None))
+            .unwrap();
+        let stats = manager.get_dashboard_stats().unwrap();
+        assert_eq!(stats.membership_tiers.get("Gold"), None);
+    }
 }
 
 mod crud_operations_tests {
@@ -647,7 +2110,7 @@ mod crud_operations_tests {
         // 确保data目录存在
         let _ = std::fs::create_dir_all("data");
 
-        let manager = QmxManager::new(true).unwrap();
+        let manager = QmxManager::builder().auto_save(true).build().unwrap();
 
         // Create
         let student_id = manager
@@ -656,7 +2119,7 @@ mod crud_operations_tests {
 
         // Read
         let student = manager.get_student(student_id).unwrap().unwrap();
-        assert_eq!(student.name(), "CRUD测试");
+        assert_eq!(student.name(), Some("CRUD测试"));
 
         // Update
         manager
@@ -667,18 +2130,18 @@ mod crud_operations_tests {
             .unwrap();
 
         let updated_student = manager.get_student(student_id).unwrap().unwrap();
-        assert_eq!(updated_student.name(), "更新后的名字");
+        assert_eq!(updated_student.name(), Some("更新后的名字"));
         assert_eq!(updated_student.age(), Some(19));
 
         // Delete
-        let deleted = manager.delete_student(student_id).unwrap();
+        let deleted = manager.delete_student(student_id, DeletePolicy::Restrict).unwrap();
         assert!(deleted);
 
         let not_found = manager.get_student(student_id).unwrap();
         assert!(not_found.is_none());
 
         // Delete non-existent
-        let not_deleted = manager.delete_student(student_id).unwrap();
+        let not_deleted = manager.delete_student(student_id, DeletePolicy::Restrict).unwrap();
         assert!(!not_deleted);
     }
 
@@ -691,7 +2154,7 @@ mod crud_operations_tests {
         // 确保data目录存在
         let _ = std::fs::create_dir_all("data");
 
-        let manager = QmxManager::new(true).unwrap();
+        let manager = QmxManager::builder().auto_save(true).build().unwrap();
 
         // Create
         let cash_id = manager
@@ -700,7 +2163,7 @@ mod crud_operations_tests {
 
         // Read
         let cash = manager.get_cash(cash_id).unwrap().unwrap();
-        assert_eq!(cash.cash, 1000);
+        assert_eq!(cash.cash.amount_minor, 1000);
         assert_eq!(cash.note(), Some("CRUD测试"));
 
         // Update
@@ -714,7 +2177,7 @@ mod crud_operations_tests {
             .unwrap();
 
         let updated_cash = manager.get_cash(cash_id).unwrap().unwrap();
-        assert_eq!(updated_cash.cash, 1500);
+        assert_eq!(updated_cash.cash.amount_minor, 1500);
         assert_eq!(updated_cash.note(), Some("更新后的备注"));
 
         // Delete
@@ -724,6 +2187,120 @@ mod crud_operations_tests {
         let not_found = manager.get_cash(cash_id).unwrap();
         assert!(not_found.is_none());
     }
+
+    #[test]
+    fn test_batch_operations() {
+        let temp_dir = TempDir::new().unwrap();
+        let temp_path = temp_dir.path();
+        std::env::set_current_dir(temp_path).unwrap();
+
+        // 确保data目录存在
+        let _ = std::fs::create_dir_all("data");
+
+        let manager = QmxManager::builder().auto_save(true).build().unwrap();
+
+        let uids = manager
+            .create_students(vec![
+                StudentBuilder::new("批量学生1").age(10),
+                StudentBuilder::new("批量学生2").age(11),
+            ])
+            .unwrap();
+        assert_eq!(uids.len(), 2);
+        assert_eq!(
+            manager.get_student(uids[1]).unwrap().unwrap().name(),
+            Some("批量学生2")
+        );
+
+        // 任一构建器无效时整批不写入
+        let too_long = "长".repeat(200);
+        assert!(
+            manager
+                .create_students(vec![
+                    StudentBuilder::new("批量学生3"),
+                    StudentBuilder::new(too_long.clone()),
+                ])
+                .is_err()
+        );
+        assert_eq!(manager.list_students().unwrap().len(), 2);
+
+        let changes = manager
+            .update_students(vec![
+                (uids[0], StudentUpdater::new().age(Some(12))),
+                (uids[1], StudentUpdater::new().age(Some(11))),
+            ])
+            .unwrap();
+        assert_eq!(changes[0].len(), 1);
+        assert!(changes[1].is_empty());
+
+        // 任一更新失败时已执行的更新全部回滚
+        assert!(
+            manager
+                .update_students(vec![
+                    (uids[0], StudentUpdater::new().age(Some(20))),
+                    (uids[1], StudentUpdater::new().name(too_long)),
+                ])
+                .is_err()
+        );
+        assert_eq!(
+            manager.get_student(uids[0]).unwrap().unwrap().age(),
+            Some(12)
+        );
+
+        let cash_uids = manager
+            .record_cash_batch(vec![
+                CashBuilder::new(300).student_id(uids[0]),
+                CashBuilder::new(400).student_id(uids[1]),
+            ])
+            .unwrap();
+        assert_eq!(manager.get_cash(cash_uids[1]).unwrap().unwrap().cash.amount_minor, 400);
+
+        // 批量数据已一次性持久化
+        let reloaded = QmxManager::builder().auto_save(false).build().unwrap();
+        assert_eq!(reloaded.list_students().unwrap().len(), 2);
+        assert_eq!(
+            reloaded
+                .search_cash(CashQuery::new().amount_range(300, 400))
+                .unwrap()
+                .len(),
+            2
+        );
+    }
+
+    #[test]
+    fn test_record_cash_batch_duplicate_guard() {
+        let temp_dir = TempDir::new().unwrap();
+        let temp_path = temp_dir.path();
+        std::env::set_current_dir(temp_path).unwrap();
+
+        // 确保data目录存在
+        let _ = std::fs::create_dir_all("data");
+
+        let manager = QmxManager::builder()
+            .auto_save(false)
+            .build()
+            .unwrap()
+            .with_duplicate_guard(DuplicateGuard {
+                window: Duration::minutes(5),
+                policy: DuplicatePolicy::Reject,
+            });
+        let student = manager.create_student(StudentBuilder::new("张三")).unwrap();
+
+        // 批次内的重复记录同样被拒绝，整批不写入
+        let result = manager.record_cash_batch(vec![
+            CashBuilder::new(500).student_id(student),
+            CashBuilder::new(500).student_id(student),
+        ]);
+        assert!(result.is_err());
+        assert!(manager.search_cash(CashQuery::new()).unwrap().is_empty());
+
+        let uids = manager
+            .record_cash_batch(vec![
+                CashBuilder::new(500).student_id(student),
+                CashBuilder::new(500).student_id(student).force(),
+            ])
+            .unwrap();
+        assert_eq!(uids.len(), 2);
+    }
 }
 
 #[test]
@@ -733,7 +2310,7 @@ fn test_v2_api_integration() {
     std::env::set_current_dir(temp_path).unwrap();
 
     // 完整的 v2 API 工作流程
-    let manager = QmxManager::new(true).unwrap();
+    let manager = QmxManager::builder().auto_save(true).build().unwrap();
 
     // 1. 创建学生（使用构建器）
     let student_id = manager
@@ -752,9 +2329,9 @@ fn test_v2_api_integration() {
         .update_student(
             student_id,
             StudentUpdater::new()
-                .add_ring(88.0)
-                .add_ring(91.5)
-                .add_ring(89.0),
+                .add_ring(8.0)
+                .add_ring(9.0)
+                .add_ring(10.0),
         )
         .unwrap();
 
@@ -772,13 +2349,13 @@ fn test_v2_api_integration() {
         .search_students(StudentQuery::new().class(Class::Month).has_membership(true))
         .unwrap();
     assert_eq!(month_students.len(), 1);
-    assert_eq!(month_students[0].name(), "集成测试学生");
+    assert_eq!(month_students[0].name(), Some("集成测试学生"));
 
     let student_cash = manager
         .search_cash(CashQuery::new().student_id(student_id))
         .unwrap();
     assert_eq!(student_cash.len(), 1);
-    assert_eq!(student_cash[0].cash, 2500);
+    assert_eq!(student_cash[0].cash.amount_minor, 2500);
 
     // 5. 统计分析
     let dashboard_stats = manager.get_dashboard_stats().unwrap();
@@ -788,11 +2365,11 @@ fn test_v2_api_integration() {
     let student_stats = manager.get_student_stats(student_id).unwrap();
     assert_eq!(student_stats.total_payments, 2500);
     assert_eq!(student_stats.score_count, 3);
-    assert!((student_stats.average_score.unwrap() - 89.5).abs() < 0.1);
+    assert!((student_stats.average_score.unwrap() - 9.0).abs() < 0.1);
 
     // 6. 验证数据持久化（自动保存已启用）
-    let new_manager = QmxManager::new(false).unwrap();
+    let new_manager = QmxManager::builder().auto_save(false).build().unwrap();
     let reloaded_students = new_manager.list_students().unwrap();
     assert_eq!(reloaded_students.len(), 1);
-    assert_eq!(reloaded_students[0].name(), "集成测试学生");
+    assert_eq!(reloaded_students[0].name(), Some("集成测试学生"));
 }
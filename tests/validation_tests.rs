@@ -0,0 +1,169 @@
+use qmx_backend_lib::cash::Cash;
+use qmx_backend_lib::error::Error;
+use qmx_backend_lib::id::compose_id;
+use qmx_backend_lib::validation::{Rule, Validator, Violation};
+use qmx_backend_lib::{CashBuilder, QmxManager, StudentBuilder, StudentQuery};
+use tempfile::TempDir;
+
+fn setup() -> TempDir {
+    let temp_dir = TempDir::new().unwrap();
+    std::env::set_current_dir(temp_dir.path()).unwrap();
+    let _ = std::fs::create_dir_all("data");
+    temp_dir
+}
+
+fn validator() -> Validator {
+    Validator::new()
+        .student_rule(Rule::phone_pattern(&["1##########", "0###-#######"]))
+        .student_rule(Rule::age_between(6, 80))
+        .student_rule(Rule::student_note_max_len(10))
+        .cash_rule(Rule::non_zero_amount())
+        .cash_rule(Rule::cash_note_max_len(5))
+}
+
+fn violations(err: Error) -> Vec<Violation> {
+    match err {
+        Error::Validation(violations) => violations,
+        other => panic!("期望校验错误，实际为 {:?}", other),
+    }
+}
+
+mod validation_tests {
+    use super::*;
+
+    #[test]
+    fn test_create_student_reports_all_violations() {
+        let _temp_dir = setup();
+        let manager = QmxManager::builder()
+            .auto_save(false)
+            .build()
+            .unwrap()
+            .with_validator(validator());
+
+        let err = manager
+            .create_student(
+                StudentBuilder::new("张三")
+                    .age(3)
+                    .phone("12345")
+                    .note("这是一段超过十个字符的备注内容"),
+            )
+            .unwrap_err();
+        assert_eq!(err.code(), "validation");
+        assert_eq!(err.numeric_code(), 2008);
+        let fields: Vec<String> = violations(err).into_iter().map(|v| v.field).collect();
+        assert_eq!(fields, ["phone", "age", "note"]);
+        assert!(
+            manager
+                .search_students(StudentQuery::new())
+                .unwrap()
+                .is_empty()
+        );
+
+        // 未填写的可选字段不检查
+        manager.create_student(StudentBuilder::new("李四")).unwrap();
+        manager
+            .create_student(StudentBuilder::new("王五").age(18).phone("0755-1234567"))
+            .unwrap();
+        let err = manager
+            .create_students(vec![
+                StudentBuilder::new("赵六").phone("13800138000"),
+                StudentBuilder::new("钱七").age(90),
+            ])
+            .unwrap_err();
+        assert_eq!(
+            violations(err),
+            [Violation::new("age", "年龄 90 不在 6-80 之间")]
+        );
+        assert_eq!(
+            manager.search_students(StudentQuery::new()).unwrap().len(),
+            2
+        );
+    }
+
+    #[test]
+    fn test_record_cash_and_custom_rules() {
+        let _temp_dir = setup();
+        let manager = QmxManager::builder()
+            .auto_save(false)
+            .build()
+            .unwrap()
+            .with_validator(
+                validator().cash_rule(Rule::new("student_id", |cash: &Cash| {
+                    cash.student_id
+                        .map(|_| ())
+                        .ok_or("必须关联学生".to_string())
+                })),
+            );
+
+        let err = manager
+            .record_cash(CashBuilder::new(100).note("备注太长了啊"))
+            .unwrap_err();
+        let fields: Vec<String> = violations(err).into_iter().map(|v| v.field).collect();
+        assert_eq!(fields, ["note", "student_id"]);
+        let student = manager
+            .create_student(StudentBuilder::new("张三").age(12))
+            .unwrap();
+        assert!(
+            manager
+                .record_cash(CashBuilder::new(100).student_id(student).note("学费"))
+                .is_ok()
+        );
+    }
+
+    #[test]
+    fn test_rejected_records_do_not_consume_uids() {
+        let _temp_dir = setup();
+        let manager = QmxManager::builder()
+            .auto_save(false)
+            .build()
+            .unwrap()
+            .with_id_namespace(2)
+            .unwrap()
+            .with_validator(validator());
+
+        assert!(
+            manager
+                .create_student(StudentBuilder::new("甲").age(3))
+                .is_err()
+        );
+        assert!(
+            manager
+                .create_students(vec![
+                    StudentBuilder::new("乙").age(12),
+                    StudentBuilder::new("丙").age(3),
+                ])
+                .is_err()
+        );
+        let student = manager
+            .create_student(StudentBuilder::new("丁").age(12))
+            .unwrap();
+        assert_eq!(student, compose_id(2, 1));
+
+        assert!(
+            manager
+                .record_cash(
+                    CashBuilder::new(100)
+                        .student_id(student)
+                        .note("备注太长了啊")
+                )
+                .is_err()
+        );
+        let cash = manager
+            .record_cash(CashBuilder::new(100).student_id(student))
+            .unwrap();
+        assert_eq!(cash, compose_id(2, 1));
+    }
+
+    #[test]
+    fn test_validator_standalone() {
+        let validator = validator();
+        assert!(!validator.is_empty());
+        assert!(Validator::new().is_empty());
+
+        let zero = Cash::new(None);
+        let violations = validator.check_cash(&zero);
+        assert_eq!(violations, [Violation::new("cash", "金额不能为 0")]);
+        let err = validator.validate_cash(&zero).unwrap_err();
+        assert!(err.to_string().contains("cash: 金额不能为 0"));
+    }
+}
@@ -0,0 +1,178 @@
+// 测试预写日志与崩溃恢复
+use qmx_backend_lib::student::Guardian;
+use qmx_backend_lib::wal::WAL_FILE;
+use qmx_backend_lib::{
+    AutoSave, CashBuilder, CashQuery, CashUpdater, CoachBuilder, QmxManager, StudentBuilder,
+    StudentUpdater,
+};
+use std::io::Write;
+use tempfile::TempDir;
+
+fn manager(temp_dir: &TempDir) -> QmxManager {
+    QmxManager::builder()
+        .data_dir(temp_dir.path())
+        .auto_save(AutoSave::Off)
+        .build()
+        .unwrap()
+}
+
+fn wal_lines(temp_dir: &TempDir) -> usize {
+    std::fs::read_to_string(temp_dir.path().join(WAL_FILE))
+        .unwrap_or_default()
+        .lines()
+        .count()
+}
+
+mod wal_tests {
+    use super::*;
+
+    #[test]
+    fn test_unsaved_changes_survive_crash() {
+        let temp_dir = TempDir::new().unwrap();
+        let (student, kept, deleted) = {
+            let manager = manager(&temp_dir);
+            let student = manager.create_student(StudentBuilder::new("张三")).unwrap();
+            manager
+                .update_student(student, StudentUpdater::new().age(Some(12)))
+                .unwrap();
+            let kept = manager
+                .record_cash(CashBuilder::new(1000).student_id(student))
+                .unwrap();
+            manager
+                .update_cash(kept, CashUpdater::new().amount(1200))
+                .unwrap();
+            let deleted = manager.record_cash(CashBuilder::new(-300)).unwrap();
+            manager.delete_cash(deleted).unwrap();
+            // 每次修改一条数据记录和一条审计记录
+            assert_eq!(wal_lines(&temp_dir), 12);
+            // 不保存直接丢弃，模拟进程崩溃
+            (student, kept, deleted)
+        };
+
+        let manager = manager(&temp_dir);
+        let recovered = manager.get_student(student).unwrap().unwrap();
+        assert_eq!(recovered.name(), Some("张三"));
+        assert_eq!(recovered.age(), Some(12));
+        assert_eq!(
+            manager.get_cash(kept).unwrap().unwrap().cash.amount_minor,
+            1200
+        );
+        assert!(manager.get_cash(deleted).unwrap().is_none());
+        assert!(manager.has_unsaved_changes());
+        assert_eq!(manager.get_audit_log(student).unwrap().len(), 2);
+
+        // 保存后日志清空，之后的修改重新开始记录
+        manager.save().unwrap();
+        assert_eq!(wal_lines(&temp_dir), 0);
+        let next = manager.create_student(StudentBuilder::new("李四")).unwrap();
+        assert_eq!(wal_lines(&temp_dir), 2);
+        assert!(next > student);
+    }
+
+    #[test]
+    fn test_failed_append_rolls_back() {
+        let temp_dir = TempDir::new().unwrap();
+        let manager = manager(&temp_dir);
+        let uid = manager.create_student(StudentBuilder::new("张三")).unwrap();
+        manager.save().unwrap();
+        // 日志无法写入时修改不生效
+        std::fs::remove_file(temp_dir.path().join(WAL_FILE)).unwrap();
+        std::fs::create_dir(temp_dir.path().join(WAL_FILE)).unwrap();
+        assert!(
+            manager
+                .update_student(uid, StudentUpdater::new().age(Some(12)))
+                .is_err()
+        );
+        assert_eq!(manager.get_student(uid).unwrap().unwrap().age(), None);
+        assert!(manager.record_cash(CashBuilder::new(1000)).is_err());
+        assert!(manager.search_cash(CashQuery::new()).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_side_stores_replayed() {
+        let temp_dir = TempDir::new().unwrap();
+        let coach = {
+            let manager = manager(&temp_dir);
+            manager.create_coach(CoachBuilder::new("王教练")).unwrap()
+        };
+        // 模拟写入教练文件时崩溃：文件仍为旧内容，日志中有新记录
+        std::fs::remove_file(temp_dir.path().join("coach_database.json")).unwrap();
+
+        let manager = manager(&temp_dir);
+        assert_eq!(manager.get_coach(coach).unwrap().unwrap().name, "王教练");
+        assert!(temp_dir.path().join("coach_database.json").exists());
+    }
+
+    #[test]
+    fn test_torn_and_corrupt_wal() {
+        let temp_dir = TempDir::new().unwrap();
+        let student = {
+            let manager = manager(&temp_dir);
+            manager.create_student(StudentBuilder::new("张三")).unwrap()
+        };
+        let mut wal = std::fs::OpenOptions::new()
+            .append(true)
+            .open(temp_dir.path().join(WAL_FILE))
+            .unwrap();
+        // 追加时崩溃留下的半行
+        wal.write_all(b"{\"Student\":{\"uid\":").unwrap();
+        let recovered = manager(&temp_dir);
+        assert!(recovered.get_student(student).unwrap().is_some());
+        drop(recovered);
+
+        // 中间的行损坏时拒绝加载，而不是静默丢弃之后的修改
+        wal.write_all(b"\n{}\n").unwrap();
+        assert!(
+            QmxManager::builder()
+                .data_dir(temp_dir.path())
+                .auto_save(AutoSave::Off)
+                .build()
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_erase_scrubs_wal() {
+        let temp_dir = TempDir::new().unwrap();
+        let manager = manager(&temp_dir);
+        let uid = manager
+            .create_student(
+                StudentBuilder::new("张三")
+                    .phone("13800000000")
+                    .guardian(Guardian::new("张父", "13900000000", "父亲")),
+            )
+            .unwrap();
+        manager
+            .record_cash(CashBuilder::new(1000).student_id(uid).note("张三学费"))
+            .unwrap();
+        manager.erase_student_data(uid).unwrap();
+
+        let wal = std::fs::read_to_string(temp_dir.path().join(WAL_FILE)).unwrap();
+        for secret in ["张三", "13800000000", "张父"] {
+            assert!(!wal.contains(secret), "预写日志中仍有 {}", secret);
+        }
+        drop(manager);
+        let recovered = self::manager(&temp_dir);
+        assert_eq!(recovered.get_student(uid).unwrap().unwrap().name(), None);
+        assert_eq!(recovered.get_student_cash(uid).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_database_init_replays_wal() {
+        let temp_dir = TempDir::new().unwrap();
+        std::env::set_current_dir(temp_dir.path()).unwrap();
+        let uid = {
+            let manager = QmxManager::builder().auto_save(false).build().unwrap();
+            manager.create_student(StudentBuilder::new("王五")).unwrap()
+        };
+
+        let db = qmx_backend_lib::database::init().unwrap();
+        assert_eq!(db.student.get(&uid).unwrap().name(), Some("王五"));
+        // 重放后已写入数据文件并清空日志
+        assert_eq!(std::fs::read_to_string("data/wal.jsonl").unwrap(), "");
+        let saved =
+            qmx_backend_lib::student::StudentDatabase::read_from("data/student_database.json")
+                .unwrap();
+        assert!(saved.get(&uid).is_some());
+    }
+}
@@ -0,0 +1,144 @@
+// 测试学生储值余额的充值、扣费与欠费查询
+use qmx_backend_lib::cash::PaymentMethod;
+use qmx_backend_lib::error::Error;
+use qmx_backend_lib::student::Class;
+use qmx_backend_lib::{
+    AutoSave, CashBuilder, Currency, Money, QmxManager, StudentBalance, StudentBuilder,
+};
+use tempfile::TempDir;
+
+fn manager(temp_dir: &TempDir) -> QmxManager {
+    QmxManager::builder()
+        .data_dir(temp_dir.path())
+        .auto_save(AutoSave::Off)
+        .build()
+        .unwrap()
+}
+
+mod wallet_tests {
+    use super::*;
+
+    #[test]
+    fn test_top_up_and_deduct() {
+        let temp_dir = TempDir::new().unwrap();
+        let manager = manager(&temp_dir);
+        let student = manager.create_student(StudentBuilder::new("张三")).unwrap();
+        assert_eq!(manager.get_student_balance(student).unwrap(), Money::cny(0));
+
+        let top_up = manager
+            .top_up_balance(student, 50_000, PaymentMethod::WeChat)
+            .unwrap();
+        let cash = manager.get_cash(top_up).unwrap().unwrap();
+        assert_eq!(cash.cash, Money::cny(50_000));
+        assert_eq!(cash.wallet, Some(Money::cny(50_000)));
+        assert_eq!(cash.payment_method, Some(PaymentMethod::WeChat));
+
+        // 扣费不计入收支，只减少余额
+        let charge = manager
+            .deduct_balance(student, 12_000, "上课 1 次")
+            .unwrap();
+        let cash = manager.get_cash(charge).unwrap().unwrap();
+        assert!(cash.cash.is_zero());
+        assert_eq!(cash.note.as_deref(), Some("上课 1 次"));
+        assert_eq!(
+            manager.get_student_balance(student).unwrap(),
+            Money::cny(38_000)
+        );
+        let stats = manager.get_dashboard_stats().unwrap();
+        assert_eq!(stats.total_revenue, 50_000);
+        assert_eq!(stats.total_expense, 0);
+
+        // 充值退款同时扣减余额
+        manager.refund_cash(top_up, 8_000, None).unwrap();
+        assert_eq!(
+            manager.get_student_balance(student).unwrap(),
+            Money::cny(30_000)
+        );
+
+        // 撤销退款后余额恢复
+        assert_eq!(manager.undo_last(1).unwrap(), 1);
+        assert_eq!(
+            manager.get_student_balance(student).unwrap(),
+            Money::cny(38_000)
+        );
+
+        assert!(matches!(
+            manager.deduct_balance(student, 0, "无效"),
+            Err(Error::InvalidInput(_))
+        ));
+        assert!(matches!(
+            manager.deduct_balance(student, Money::new(100, Currency::Usd), "币种不同"),
+            Err(Error::InvalidInput(_))
+        ));
+        assert!(matches!(
+            manager.record_cash(CashBuilder::new(-100).student_id(student).top_up()),
+            Err(Error::InvalidInput(_))
+        ));
+        assert!(matches!(
+            manager.get_student_balance(9_999_999),
+            Err(Error::NotFound(_))
+        ));
+    }
+
+    #[test]
+    fn test_sell_from_balance_applies_item() {
+        let temp_dir = TempDir::new().unwrap();
+        let manager = manager(&temp_dir);
+        let student = manager.create_student(StudentBuilder::new("李四")).unwrap();
+        manager
+            .top_up_balance(student, 150_000, PaymentMethod::Cash)
+            .unwrap();
+
+        let uid = manager.sell_from_balance(student, 1).unwrap();
+        let cash = manager.get_cash(uid).unwrap().unwrap();
+        assert_eq!(cash.wallet, Some(Money::cny(-100_000)));
+        assert_eq!(cash.sale.unwrap().lessons, 10);
+        let updated = manager.get_student(student).unwrap().unwrap();
+        assert_eq!(updated.class(), &Class::TenTry);
+        assert_eq!(updated.lesson_left(), Some(10));
+        assert_eq!(
+            manager.get_student_balance(student).unwrap(),
+            Money::cny(50_000)
+        );
+
+        // 撤销时余额和课时一起恢复
+        assert_eq!(manager.undo_last(1).unwrap(), 1);
+        assert_eq!(
+            manager.get_student_balance(student).unwrap(),
+            Money::cny(150_000)
+        );
+        let updated = manager.get_student(student).unwrap().unwrap();
+        assert_ne!(updated.lesson_left(), Some(10));
+    }
+
+    #[test]
+    fn test_negative_balances_sorted_by_debt() {
+        let temp_dir = TempDir::new().unwrap();
+        let manager = manager(&temp_dir);
+        let a = manager.create_student(StudentBuilder::new("甲")).unwrap();
+        let b = manager.create_student(StudentBuilder::new("乙")).unwrap();
+        let c = manager.create_student(StudentBuilder::new("丙")).unwrap();
+        manager.deduct_balance(a, 3_000, "上课").unwrap();
+        manager.deduct_balance(b, 9_000, "上课").unwrap();
+        manager
+            .top_up_balance(c, 10_000, PaymentMethod::Cash)
+            .unwrap();
+        manager.deduct_balance(c, 5_000, "上课").unwrap();
+
+        assert_eq!(
+            manager.get_negative_balances().unwrap(),
+            vec![
+                StudentBalance {
+                    uid: b,
+                    name: "乙".to_string(),
+                    balance: Money::cny(-9_000),
+                },
+                StudentBalance {
+                    uid: a,
+                    name: "甲".to_string(),
+                    balance: Money::cny(-3_000),
+                },
+            ]
+        );
+    }
+}
@@ -0,0 +1,81 @@
+// 测试 xlsx 工作簿导出，需要启用 xlsx feature
+#![cfg(feature = "xlsx")]
+
+use qmx_backend_lib::{CashBuilder, QmxManager, StudentBuilder};
+use std::collections::BTreeMap;
+use tempfile::TempDir;
+
+fn setup() -> TempDir {
+    let temp_dir = TempDir::new().unwrap();
+    std::env::set_current_dir(temp_dir.path()).unwrap();
+    let _ = std::fs::create_dir_all("data");
+    temp_dir
+}
+
+fn read_u16(bytes: &[u8], at: usize) -> usize {
+    u16::from_le_bytes([bytes[at], bytes[at + 1]]) as usize
+}
+
+fn read_u32(bytes: &[u8], at: usize) -> usize {
+    u32::from_le_bytes(bytes[at..at + 4].try_into().unwrap()) as usize
+}
+
+/// 按中央目录读取不压缩的 ZIP 中的全部文件
+fn read_stored_zip(bytes: &[u8]) -> BTreeMap<String, String> {
+    let end = bytes.len() - 22;
+    assert_eq!(read_u32(bytes, end), 0x0605_4b50);
+    let count = read_u16(bytes, end + 10);
+    let mut at = read_u32(bytes, end + 16);
+    let mut files = BTreeMap::new();
+    for _ in 0..count {
+        assert_eq!(read_u32(bytes, at), 0x0201_4b50);
+        let size = read_u32(bytes, at + 24);
+        let name_len = read_u16(bytes, at + 28);
+        let offset = read_u32(bytes, at + 42);
+        let name = String::from_utf8(bytes[at + 46..at + 46 + name_len].to_vec()).unwrap();
+
+        assert_eq!(read_u32(bytes, offset), 0x0403_4b50);
+        let data_start = offset + 30 + read_u16(bytes, offset + 26);
+        let data = String::from_utf8(bytes[data_start..data_start + size].to_vec()).unwrap();
+        files.insert(name, data);
+        at += 46 + name_len;
+    }
+    files
+}
+
+mod xlsx_export_tests {
+    use super::*;
+
+    #[test]
+    fn test_export_xlsx_workbook() {
+        let temp_dir = setup();
+        let manager = QmxManager::builder().auto_save(false).build().unwrap();
+        let student = manager
+            .create_student(StudentBuilder::new("张三 & <李四>").age(12))
+            .unwrap();
+        manager
+            .record_cash(CashBuilder::new(12050).student_id(student).note("学费"))
+            .unwrap();
+
+        let path = temp_dir.path().join("report.xlsx");
+        manager.export_xlsx(&path).unwrap();
+        let files = read_stored_zip(&std::fs::read(&path).unwrap());
+
+        assert!(files.contains_key("[Content_Types].xml"));
+        assert!(files.contains_key("_rels/.rels"));
+        assert!(files.contains_key("xl/_rels/workbook.xml.rels"));
+        let workbook = &files["xl/workbook.xml"];
+        assert!(workbook.contains(r#"<sheet name="学生" sheetId="1" r:id="rId1"/>"#));
+        assert!(workbook.contains(r#"<sheet name="现金""#));
+        assert!(workbook.contains(r#"<sheet name="概览""#));
+
+        let students = &files["xl/worksheets/sheet1.xml"];
+        assert!(students.contains("张三 &amp; &lt;李四&gt;"));
+        assert!(students.contains("<v>12</v>"));
+        let cash = &files["xl/worksheets/sheet2.xml"];
+        assert!(cash.contains("<v>120.5</v>"));
+        assert!(cash.contains("学费"));
+        let summary = &files["xl/worksheets/sheet3.xml"];
+        assert!(summary.contains("学生总数"));
+    }
+}